@@ -0,0 +1,29 @@
+import { listen } from '$lib/backend/ipc';
+import { readable } from 'svelte/store';
+
+/** How actively the worktree of a project is changing, as sent periodically by the backend. */
+export type ActivityPulse = {
+	projectId: string;
+	/** The amount of file changes within the last minute. */
+	editsPerMinute: number;
+	/** The amount of distinct files that changed within the last minute. */
+	activeFiles: number;
+};
+
+export class ActivityPulses {
+	// The latest pulse of each project, with idle projects removed.
+	private pulses = new Map<string, ActivityPulse>();
+
+	readonly byProject = readable<Map<string, ActivityPulse>>(new Map(), (set) => {
+		const unsubscribe = listen<ActivityPulse>('activity://pulse', (event) => {
+			const pulse = event.payload;
+			if (pulse.editsPerMinute === 0) {
+				this.pulses.delete(pulse.projectId);
+			} else {
+				this.pulses.set(pulse.projectId, pulse);
+			}
+			set(new Map(this.pulses));
+		});
+		return async () => await unsubscribe();
+	});
+}
@@ -0,0 +1,134 @@
+import { invoke } from '$lib/backend/ipc';
+
+/** A note or emoji marker for a point in time. Times are in seconds since the Unix epoch. */
+export type Bookmark = {
+	id: number;
+	/** The time the bookmark is for, or the start of the session it marks. */
+	at: number;
+	note: string;
+	emoji?: string;
+	createdAt: number;
+};
+
+/**
+ * A period of uninterrupted activity, identified by its `start`. Times are in seconds since the Unix epoch.
+ */
+export type ActivitySession = {
+	start: number;
+	end: number;
+	/** The short name of the branch the session was recorded on, or `null` if it isn't known. */
+	branch: string | null;
+	snapshots: number;
+	/** Editor heartbeats, which keep the session going while files are only read. */
+	heartbeats: number;
+	linesAdded: number;
+	linesRemoved: number;
+	filesTouched: number;
+	bookmarks: Bookmark[];
+};
+
+export type HourlyActivity = {
+	/** The start of the hour in seconds since the Unix epoch. */
+	hour: number;
+	snapshots: number;
+	linesAdded: number;
+	linesRemoved: number;
+};
+
+/** What changed in the worktree within a range of time, as recorded by snapshots. */
+export type ActivitySummary = {
+	snapshots: number;
+	filesTouched: string[];
+	linesAdded: number;
+	linesRemoved: number;
+	activeMinutes: number;
+	sessions: ActivitySession[];
+	hourly: HourlyActivity[];
+	/** All bookmarks within the range, including those outside of sessions. */
+	bookmarks: Bookmark[];
+};
+
+/**
+ * Tell that the file at the worktree-relative `filePath` is focused in an editor, for the time spent reading it
+ * to count as activity.
+ */
+export async function editorHeartbeat(projectId: string, filePath: string) {
+	return await invoke<boolean>('editor_heartbeat', { projectId, filePath });
+}
+
+/** Summarize the activity in the project between `since` and `until`. */
+export async function getActivitySummary(projectId: string, since: Date, until: Date) {
+	return await invoke<ActivitySummary>('activity_summary', {
+		projectId,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000)
+	});
+}
+
+/** List the sessions between `since` and `until`, oldest first, optionally only those recorded on `branch`. */
+export async function listSessions(projectId: string, since: Date, until: Date, branch?: string) {
+	return await invoke<ActivitySession[]>('list_sessions', {
+		projectId,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000),
+		branch
+	});
+}
+
+/** Attach `note` and `emoji` to `at`, which may be the start of a session, and return the bookmark. */
+export async function addBookmark(projectId: string, at: Date, note: string, emoji?: string) {
+	return await invoke<Bookmark>('add_bookmark', {
+		projectId,
+		timestamp: Math.floor(at.getTime() / 1000),
+		note,
+		emoji
+	});
+}
+
+/** Change the `note` and `emoji` of the bookmark `id`. */
+export async function updateBookmark(projectId: string, id: number, note: string, emoji?: string) {
+	return await invoke<Bookmark>('update_bookmark', { projectId, id, note, emoji });
+}
+
+export async function removeBookmark(projectId: string, id: number) {
+	return await invoke<Bookmark>('remove_bookmark', { projectId, id });
+}
+
+/** List the bookmarks for times between `since` and `until`, oldest first. */
+export async function listBookmarks(projectId: string, since: Date, until: Date) {
+	return await invoke<Bookmark[]>('list_bookmarks', {
+		projectId,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000)
+	});
+}
+
+/**
+ * Commit the changes to all files that changed during `session`, leaving other changes alone, and return the
+ * id of the commit.
+ */
+export async function commitSession(projectId: string, session: ActivitySession, message: string) {
+	return await invoke<string>('commit_session', { projectId, sessionId: session.start, message });
+}
+
+/** A period in which changes were written, as told by the deltas recorded during it. */
+export type DeltaSession = {
+	start: number;
+	end: number;
+	branch: string | null;
+	deltas: number;
+};
+
+/** Uncommitted hunks that were written during the same session. */
+export type ChangeGroup = {
+	/** The session the hunks were written in, or `undefined` if no delta recorded them yet. */
+	session?: DeltaSession;
+	stackIds: string[];
+	/** The hunks in the form accepted by `commit_virtual_branch` and `update_virtual_branch`. */
+	ownership: string;
+};
+
+/** Suggest how to split the uncommitted changes by the session they were written in, oldest first. */
+export async function suggestChangeGroups(projectId: string) {
+	return await invoke<ChangeGroup[]>('suggest_change_groups', { projectId });
+}
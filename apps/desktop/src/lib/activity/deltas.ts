@@ -0,0 +1,218 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
+import { invoke, listen } from '$lib/backend/ipc';
+import { invokeStreamed } from '$lib/backend/stream';
+import type { ContentType } from '$lib/files/file';
+import type { FileMode } from '$lib/history/types';
+
+export type ChangeOrigin = 'human' | 'machine';
+
+export type ClassificationReason =
+	| 'gitButler'
+	| 'gitOperation'
+	| 'projectRule'
+	| 'filePattern'
+	| 'burst';
+
+/** Whether a change was made by a human or a machine, and why, unless it's a human edit like any other. */
+export type Classification = {
+	origin: ChangeOrigin;
+	reason?: ClassificationReason;
+};
+
+/** A rule of a project that changes to files matching `pattern`, like `src/generated/**`, were made by `origin`. */
+export type ClassificationRule = {
+	pattern: string;
+	origin: ChangeOrigin;
+};
+
+/**
+ * When more than `files` files change within `windowSeconds`, the changes are recorded as a single
+ * bulk change.
+ */
+export type BulkChangeThreshold = {
+	files: number;
+	windowSeconds: number;
+};
+
+/** Files of the worktree that changed at once, noticed `at` seconds since the Unix epoch. */
+export type Delta = Classification & {
+	at: number;
+	paths: string[];
+	/** The short name of the branch the change was made on, unless `HEAD` was detached. */
+	branch?: string;
+	/** The commit with the content of the worktree after the change, if a checkpoint was written with it. */
+	checkpoint?: string;
+	/** The content of the changed files after the change, if it was made by a human. */
+	contents?: DeltaContent[];
+	/** If set, this stands for a burst of changes to many files, with the last content of each. */
+	bulk?: boolean;
+	/** The files that were moved by the change, oldest first. */
+	renames?: DeltaRename[];
+};
+
+/** A file moved from `from` to `to`, both of which are among the paths of its delta. */
+export type DeltaRename = {
+	from: string;
+	to: string;
+};
+
+/** The blob with the content of the file at `path` after a delta, or `null` if it was deleted. */
+export type DeltaContent = {
+	path: string;
+	blobId: string | null;
+	/** The line endings of the file if its content was recorded with LF line endings. */
+	eol?: LineEnding;
+	/** The type of the content when it was recorded, unknown for deletions and older deltas. */
+	contentType?: ContentType;
+	/** The mode of the file, unknown for deletions and older deltas. */
+	mode?: FileMode;
+	/** The path a symbolic link pointed to. */
+	linkTarget?: string;
+};
+
+/** The line endings a file had in the worktree when its content was normalized as the repository would. */
+export type LineEnding = 'lf' | 'crlf';
+
+/**
+ * List the changes to the worktree between `since` and `until`, oldest first, optionally only those by `origin` or
+ * on `branch`.
+ */
+export async function listDeltas(
+	projectId: string,
+	since: Date,
+	until: Date,
+	origin?: ChangeOrigin,
+	branch?: string
+) {
+	// The deltas of a long range can be too many to be sent at once.
+	return await invokeStreamed<Delta[]>({
+		kind: 'listDeltas',
+		projectId,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000),
+		origin,
+		branch
+	});
+}
+
+/** How long the steps of reconstructing a file took, in microseconds. */
+export type ReconstructionProfile = {
+	deltas: number;
+	fileBytes: number;
+	jsonBytes: number;
+	ioMicros: number;
+	parseMicros: number;
+	cachedReadMicros: number;
+	snapshotsMicros: number;
+	replayMicros: number;
+};
+
+/** Reconstruct the latest content of the file at `filePath` from its history, and tell how long it took. */
+export async function profileReconstruction(projectId: string, filePath: string) {
+	return await invoke<ReconstructionProfile>('profile_reconstruction', { projectId, filePath });
+}
+
+/** The blob with the last content of the file at `path`, deleted `deletedAt` seconds since the Unix epoch. */
+export type Tombstone = {
+	path: string;
+	deletedAt: number;
+	blobId: string;
+	eol?: LineEnding;
+};
+
+/** List the files deleted between `since` and `until` with their last content, oldest first. */
+export async function listDeletedFiles(projectId: string, since: Date, until: Date) {
+	return await invoke<Tombstone[]>('list_deleted_files', {
+		projectId,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000)
+	});
+}
+
+/** Bring back the file at `filePath` as it was when it was deleted last, unless it exists. */
+export async function recoverDeletedFile(projectId: string, filePath: string) {
+	return await invoke<Tombstone>('recover_deleted_file', { projectId, filePath });
+}
+
+/** How many of the files of a directory were reconstructed so far while restoring it. */
+export type RestoreProgress = {
+	reconstructed: number;
+	total: number;
+};
+
+/** What restoring a directory did. Paths are sorted. */
+export type RestoreOutcome = {
+	/** The snapshot to undo the restore with, unless nothing had to be written. */
+	snapshotId?: string;
+	restored: string[];
+	deleted: string[];
+	/** Files that were left as they are as nothing was recorded about them up to then. */
+	unknown: string[];
+	/** Files that were left as they are as another program changed them while restoring. */
+	changed: string[];
+	unchanged: number;
+};
+
+/**
+ * Restore all files within `directory`, or the whole worktree if it's empty, to how they were at `at`. Nothing is
+ * written if the operation with `operationId` is cancelled before all files were reconstructed.
+ */
+export async function restoreDirectoryAt(
+	projectId: string,
+	directory: string,
+	at: Date,
+	onProgress?: (progress: RestoreProgress) => void,
+	operationId?: string
+) {
+	const unlisten = onProgress
+		? listen<RestoreProgress>(`project://${projectId}/restore/progress`, (event) =>
+				onProgress(event.payload)
+			)
+		: undefined;
+	try {
+		return await invoke<RestoreOutcome>('restore_directory_at', {
+			projectId,
+			directory,
+			at: Math.floor(at.getTime() / 1000),
+			operationId,
+			confirmationToken: await requestConfirmation('discard')
+		});
+	} finally {
+		unlisten?.();
+	}
+}
+
+/** What the content of a file has to satisfy to be good, as used by `historyBisect`. */
+export type HistoryPredicate = {
+	pattern: string;
+	isRegex?: boolean;
+	/** If `true`, the content is good if `pattern` is present, otherwise if it is absent. */
+	present: boolean;
+};
+
+/** When a file stopped satisfying a predicate. Times are in seconds since the Unix epoch. */
+export type HistoryBisection = {
+	lastGoodAt: number;
+	firstBadAt: number;
+	firstBadBlobId?: string;
+	states: number;
+	steps: number;
+};
+
+/**
+ * Find when the file at `filePath` first stopped satisfying `predicate`, across all sessions, or `undefined` if it
+ * still does. It can be cancelled as the operation with `operationId`.
+ */
+export async function historyBisect(
+	projectId: string,
+	filePath: string,
+	predicate: HistoryPredicate,
+	operationId?: string
+) {
+	return await invoke<HistoryBisection | null>('history_bisect', {
+		projectId,
+		filePath,
+		predicate,
+		operationId
+	});
+}
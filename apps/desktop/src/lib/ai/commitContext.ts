@@ -0,0 +1,26 @@
+import { invoke } from '$lib/backend/ipc';
+
+export type FileContext = {
+	path: string;
+	previousPath: string | null;
+	status: 'addition' | 'deletion' | 'modification' | 'rename';
+	linesAdded: number;
+	linesRemoved: number;
+	/** Like `@@ -1,6 +1,8 @@`, always present even if the patch was left out. */
+	hunkHeaders: string[];
+	/** The patch cut off at a line boundary, or `null` if it didn't fit or the file is binary. */
+	patch: string | null;
+	patchTruncated: boolean;
+};
+
+/** A compact summary of uncommitted changes, assembled by the backend to fit a token budget. */
+export type CommitContext = {
+	files: FileContext[];
+	truncated: boolean;
+	estimatedTokens: number;
+};
+
+/** Summarize the uncommitted changes to `paths`, or to all files if empty, for drafting a commit message. */
+export async function getCommitContext(projectId: string, paths: string[], tokenBudget?: number) {
+	return await invoke<CommitContext>('commit_context', { projectId, paths, tokenBudget });
+}
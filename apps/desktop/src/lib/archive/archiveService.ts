@@ -0,0 +1,13 @@
+import { invoke } from '$lib/backend/ipc';
+
+export type ArchiveFormat = 'zip' | 'tarGz';
+
+export class ArchiveService {
+	/**
+	 * Write the files of `treeish`, or of the worktree with its uncommitted changes if undefined, as an archive
+	 * to `path`, leaving out files with the `export-ignore` attribute. Returns how many files it contains.
+	 */
+	async exportArchive(projectId: string, format: ArchiveFormat, path: string, treeish?: string) {
+		return await invoke<number>('export_archive', { projectId, treeish, format, path });
+	}
+}
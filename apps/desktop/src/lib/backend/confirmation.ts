@@ -0,0 +1,19 @@
+import { invoke } from './ipc';
+
+/** The destructive actions that the backend refuses to perform without a confirmation token. */
+export type DestructiveAction =
+	| 'forcePush'
+	| 'discard'
+	| 'purgeHistory'
+	| 'deleteBranch'
+	| 'deleteTag'
+	| 'removeGlobalConfig';
+
+/**
+ * Return a token to pass as `confirmationToken` to a command performing `action`, once the user
+ * confirmed it. Tokens can be used only once, for the action they were requested for, and expire
+ * after a minute.
+ */
+export async function requestConfirmation(action: DestructiveAction): Promise<string> {
+	return await invoke<string>('request_confirmation', { action });
+}
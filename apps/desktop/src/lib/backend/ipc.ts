@@ -1,5 +1,5 @@
 import { invoke as invokeTauri } from '@tauri-apps/api/core';
-import { listen as listenTauri } from '@tauri-apps/api/event';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 import type { EventCallback, EventName } from '@tauri-apps/api/event';
 
 export enum Code {
@@ -8,7 +8,13 @@ export enum Code {
 	ProjectsGitAuth = 'errors.projects.git.auth',
 	DefaultTargetNotFound = 'errors.projects.default_target.not_found',
 	CommitSigningFailed = 'errors.commit.signing_failed',
-	ProjectMissing = 'errors.projects.missing'
+	ProjectMissing = 'errors.projects.missing',
+	ProtectedBranch = 'errors.projects.protected_branch',
+	PushRefused = 'errors.push.refused',
+	ProjectReadOnly = 'errors.projects.read_only',
+	ProjectDirectoryEmpty = 'errors.projects.directory_empty',
+	Cancelled = 'errors.cancelled',
+	HistoryLocked = 'errors.projects.history_locked'
 }
 
 export function isUserErrorCode(something: unknown): something is Code {
@@ -73,7 +79,8 @@ export async function invoke<T>(command: string, params: Record<string, unknown>
 }
 
 export function listen<T>(event: EventName, handle: EventCallback<T>) {
-	const unlisten = listenTauri(event, handle);
+	// Listen on the current window only, as project events are only sent to the windows displaying the project.
+	const unlisten = getCurrentWebviewWindow().listen(event, handle);
 	return async () => await unlisten.then((unlistenFn) => unlistenFn());
 }
 
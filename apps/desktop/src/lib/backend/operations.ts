@@ -0,0 +1,18 @@
+import { invoke } from './ipc';
+
+/**
+ * Return an id to pass as `operationId` to a command that may take long, like cloning, fetching
+ * or exporting the history, so it can be cancelled with `cancelOperation` while it runs. Each id
+ * is for a single operation.
+ */
+export async function beginOperation(): Promise<string> {
+	return await invoke<string>('begin_operation');
+}
+
+/**
+ * Ask the operation with `operationId` to stop, which makes it fail with `Code.Cancelled`, and
+ * return `false` if it isn't known or finished already.
+ */
+export async function cancelOperation(operationId: string): Promise<boolean> {
+	return await invoke<boolean>('cancel_operation', { operationId });
+}
@@ -0,0 +1,72 @@
+import { invoke } from './ipc';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+import type { ChangeOrigin } from '$lib/activity/deltas';
+import type { HistoryExportFormat } from '$lib/history/export';
+
+/** The commands whose responses can be streamed, with the arguments of the command of the same name. */
+export type StreamRequest =
+	| {
+			kind: 'listDeltas';
+			projectId: string;
+			since: number;
+			until: number;
+			origin?: ChangeOrigin;
+			branch?: string;
+	  }
+	| {
+			kind: 'exportHistory';
+			projectId: string;
+			format: HistoryExportFormat;
+			since: number;
+			until: number;
+	  }
+	| {
+			kind: 'readWorkdirFile';
+			projectId: string;
+			relativePath: string;
+			maxSize?: number;
+	  };
+
+type StreamStart = {
+	streamId: string;
+	chunks: number;
+	size: number;
+};
+
+type StreamChunk = {
+	seq: number;
+	data: string;
+};
+
+/**
+ * Run `request` and receive its response in chunks, for responses too large to be sent at once
+ * like the whole history or big files.
+ */
+export async function invokeStreamed<T>(request: StreamRequest): Promise<T> {
+	const streamId = crypto.randomUUID();
+	const chunks: string[] = [];
+	let received = 0;
+	let expected: number | undefined;
+	let complete: () => void = () => {};
+	const completed = new Promise<void>((resolve) => (complete = resolve));
+
+	// Chunks may arrive before the response to `begin_stream`, so listen before the stream begins.
+	const unlisten = await getCurrentWebviewWindow().listen<StreamChunk>(
+		`stream://${streamId}`,
+		(event) => {
+			if (chunks[event.payload.seq] === undefined) received++;
+			chunks[event.payload.seq] = event.payload.data;
+			if (received === expected) complete();
+		}
+	);
+	try {
+		const start = await invoke<StreamStart>('begin_stream', { streamId, request });
+		expected = start.chunks;
+		if (received === expected) complete();
+		await completed;
+		return JSON.parse(chunks.join('')) as T;
+	} finally {
+		unlisten();
+		await invoke('end_stream', { streamId });
+	}
+}
@@ -1,10 +1,10 @@
 import { invoke as invokeIpc, listen as listenIpc } from './ipc';
+import { checkForUpdates } from '$lib/updater/backendUpdate';
 import { getVersion } from '@tauri-apps/api/app';
-import { check } from '@tauri-apps/plugin-updater';
 
 export class Tauri {
 	invoke = invokeIpc;
 	listen = listenIpc;
-	checkUpdate = check;
+	checkUpdate = checkForUpdates;
 	currentVersion = getVersion;
 }
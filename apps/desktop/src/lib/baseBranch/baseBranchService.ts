@@ -1,4 +1,5 @@
 import { BaseBranch, NoDefaultTarget } from './baseBranch';
+import { requestConfirmation } from '$lib/backend/confirmation';
 import { Code, invoke } from '$lib/backend/ipc';
 import { showError } from '$lib/notifications/toasts';
 import { shallowDeduplicate } from '$lib/stores/shallowDeduplicate';
@@ -8,6 +9,8 @@ import { derived, writable } from 'svelte/store';
 
 export interface RemoteBranchInfo {
 	name: string;
+	/** Whether the `HEAD` of the remote points to this branch, which makes it the default base branch. */
+	isDefault?: boolean;
 }
 
 export class BaseBranchService {
@@ -49,19 +52,22 @@ export class BaseBranchService {
 		}
 	}
 
-	async fetchFromRemotes(action: string | undefined = undefined) {
+	async fetchFromRemotes(action: string | undefined = undefined, operationId?: string) {
 		this.loading.set(true);
 		try {
 			// Note that we expect the back end to emit new fetches event, and therefore
 			// trigger a base branch reload. It feels a bit awkward and should be improved.
 			await invoke<void>('fetch_from_remotes', {
 				projectId: this.projectId,
-				action: action || 'auto'
+				action: action || 'auto',
+				operationId
 			});
 		} catch (err: any) {
 			if (err.code === Code.DefaultTargetNotFound) {
 				// Swallow this error since user should be taken to project setup page
 				return;
+			} else if (err.code === Code.Cancelled) {
+				return;
 			} else if (err.code === Code.ProjectsGitAuth) {
 				showError('Failed to authenticate', err);
 			} else if (action !== undefined) {
@@ -88,7 +94,8 @@ export class BaseBranchService {
 		try {
 			await invoke<void>('push_base_branch', {
 				projectId: this.projectId,
-				withForce
+				withForce,
+				confirmationToken: withForce ? await requestConfirmation('forcePush') : undefined
 			});
 		} catch (err: any) {
 			if (err.code === Code.DefaultTargetNotFound) {
@@ -109,10 +116,11 @@ export async function getRemoteBranches(
 	projectId: string | undefined
 ): Promise<RemoteBranchInfo[]> {
 	if (!projectId) return [];
-	return await invoke<Array<string>>('git_remote_branches', { projectId }).then((branches) =>
-		branches
-			.map((name) => name.substring(13))
-			.sort((a, b) => a.localeCompare(b))
-			.map((name) => ({ name }))
-	);
+	const [branches, defaultBranch] = await Promise.all([
+		invoke<Array<string>>('git_remote_branches', { projectId }),
+		invoke<string | null>('get_default_branch', { projectId }).catch(() => null)
+	]);
+	return branches
+		.map((name) => ({ name: name.substring(13), isDefault: name === defaultBranch }))
+		.sort((a, b) => a.name.localeCompare(b.name));
 }
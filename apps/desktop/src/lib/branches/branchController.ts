@@ -1,3 +1,4 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
 import { invoke } from '$lib/backend/ipc';
 import { showError, showToast } from '$lib/notifications/toasts';
 import * as toasts from '@gitbutler/ui/toasts';
@@ -6,12 +7,22 @@ import type { BaseBranchService } from '$lib/baseBranch/baseBranchService';
 import type { BranchListingService } from '$lib/branches/branchListing';
 import type { LocalFile } from '$lib/files/file';
 import type { Hunk } from '$lib/hunks/hunk';
+import type { CoAuthor } from '$lib/project/project';
 import type { StackOrder } from './branch';
 import type { VirtualBranchService } from './virtualBranchService';
 
 export type CommitIdOrChangeId = { CommitId: string } | { ChangeId: string };
 export type SeriesIntegrationStrategy = 'merge' | 'rebase' | 'hardreset';
 
+/** A local branch that is merged into the target branch or whose remote branch is gone. */
+export type StaleBranch = {
+	name: string;
+	reason: 'merged' | 'upstreamGone';
+	upstream?: string;
+	/** When the last commit of the branch was made, in seconds since the Unix epoch. */
+	lastCommitAt: number;
+};
+
 export class BranchController {
 	constructor(
 		private readonly projectId: string,
@@ -62,13 +73,24 @@ export class BranchController {
 		});
 	}
 
-	async commit(branchId: string, message: string, ownership: string | undefined = undefined) {
+	/**
+	 * Commit the changes of the branch, signed off by the author if `signOff` is set, and crediting `coAuthors`
+	 * with `Co-authored-by` trailers.
+	 */
+	async commit(
+		branchId: string,
+		message: string,
+		ownership: string | undefined = undefined,
+		trailers: { signOff?: boolean; coAuthors?: CoAuthor[] } = {}
+	) {
 		try {
 			await invoke<void>('commit_virtual_branch', {
 				projectId: this.projectId,
 				branch: branchId,
 				message,
-				ownership
+				ownership,
+				signOff: trailers.signOff,
+				coAuthors: trailers.coAuthors
 			});
 			this.posthog.capture('Commit Successful');
 		} catch (err: any) {
@@ -268,7 +290,20 @@ export class BranchController {
 		}
 	}
 
-	async unapplyLines(hunk: Hunk, linesToUnapply: { old?: number; new?: number }[]) {
+	/**
+	 * Move hunks to a stack even if they are locked to another one, and lock them to it instead.
+	 * @param stackId The stack to move the hunks to.
+	 * @param ownership The hunks to move, like `file.txt:1-5`.
+	 */
+	async transferOwnership(stackId: string, ownership: string) {
+		try {
+			await invoke<void>('transfer_ownership', { projectId: this.projectId, stackId, ownership });
+		} catch (err) {
+			showError('Failed to transfer ownership', err);
+		}
+	}
+
+	async unapplyLines(hunk: Hunk,linesToUnapply: { old?: number; new?: number }[]) {
 		const ownership = `${hunk.filePath}:${hunk.id}-${hunk.hash}`;
 		const lines = {
 			[hunk.id]: linesToUnapply
@@ -293,6 +328,7 @@ export class BranchController {
 		try {
 			await invoke<void>('reset_files', {
 				projectId: this.projectId,
+				confirmationToken: await requestConfirmation('discard'),
 				branchId,
 				files: files?.flatMap((f) => f.path) ?? []
 			});
@@ -301,6 +337,22 @@ export class BranchController {
 		}
 	}
 
+	/**
+	 * Discard the uncommitted changes to `paths` in all branches, and return the snapshot that brings
+	 * them back when restored.
+	 */
+	async discardChanges(paths: string[]): Promise<string | undefined> {
+		try {
+			return await invoke<string>('discard_changes', {
+				projectId: this.projectId,
+				paths,
+				confirmationToken: await requestConfirmation('discard')
+			});
+		} catch (err) {
+			showError('Failed to discard changes', err);
+		}
+	}
+
 	async saveAndUnapply(branchId: string) {
 		try {
 			await invoke<void>('save_and_unapply_virtual_branch', {
@@ -324,13 +376,33 @@ export class BranchController {
 		}
 	}
 
-	async pushBranch(branchId: string, withForce: boolean): Promise<BranchPushResult | undefined> {
+	/** Explain why pushing the branch would be refused, for each of its series it would be refused for. */
+	async checkPush(branchId: string, withForce: boolean) {
 		try {
-			const pushResult = await invoke<BranchPushResult | undefined>('push_stack', {
+			return await invoke<PushRefusal[]>('check_push_stack', {
 				projectId: this.projectId,
 				branchId,
 				withForce
 			});
+		} catch (err: any) {
+			showError('Failed to check push', err);
+		}
+	}
+
+	/** Push the branch, along with the tags pointing to its commits if `withTags` is set. */
+	async pushBranch(
+		branchId: string,
+		withForce: boolean,
+		withTags = false
+	): Promise<BranchPushResult | undefined> {
+		try {
+			const pushResult = await invoke<BranchPushResult | undefined>('push_stack', {
+				projectId: this.projectId,
+				branchId,
+				withForce,
+				withTags,
+				confirmationToken: withForce ? await requestConfirmation('forcePush') : undefined
+			});
 			this.posthog.capture('Push Successful');
 			await this.vbranchService.refresh();
 			return pushResult;
@@ -373,7 +445,8 @@ export class BranchController {
 			// TODO: make this optimistic again.
 			await invoke<void>('unapply_without_saving_virtual_branch', {
 				projectId: this.projectId,
-				branchId
+				branchId,
+				confirmationToken: await requestConfirmation('discard')
 			});
 			toasts.success('Branch unapplied successfully');
 		} catch (err) {
@@ -418,7 +491,8 @@ export class BranchController {
 			await invoke<void>('delete_local_branch', {
 				projectId: this.projectId,
 				refname,
-				givenName
+				givenName,
+				confirmationToken: await requestConfirmation('deleteBranch')
 			});
 		} catch (err) {
 			showError('Failed to delete local branch', err);
@@ -428,6 +502,30 @@ export class BranchController {
 		}
 	}
 
+	async staleBranches(): Promise<StaleBranch[]> {
+		return await invoke<StaleBranch[]>('stale_branches', { projectId: this.projectId });
+	}
+
+	/**
+	 * Deletes local branches in bulk, and the unapplied virtual branches created from them.
+	 * @param names The short names of the branches, like `feature`.
+	 * @param force Whether to also delete branches that aren't merged into the target branch.
+	 */
+	async deleteBranches(names: string[], force = false) {
+		try {
+			await invoke<void>('delete_branches', {
+				projectId: this.projectId,
+				names,
+				force,
+				confirmationToken: await requestConfirmation('deleteBranch')
+			});
+		} catch (err) {
+			showError('Failed to delete branches', err);
+		} finally {
+			this.branchListingService.refresh();
+		}
+	}
+
 	async markResolved(path: string) {
 		try {
 			await invoke<void>('mark_resolved', { projectId: this.projectId, path });
@@ -462,6 +560,35 @@ export class BranchController {
 		}
 	}
 
+	/** Amend the head commit of a branch with the uncommitted changes to `paths`, and optionally a new message. */
+	async amendCommit(branchId: string, paths: string[], message?: string, force = false) {
+		try {
+			return await invoke<string>('amend_commit', {
+				projectId: this.projectId,
+				branchId,
+				paths,
+				message,
+				force
+			});
+		} catch (err: any) {
+			showError('Failed to amend commit', err);
+		}
+	}
+
+	/** Change the message of a commit, rebasing the commits above it. */
+	async rewordCommit(commitOid: string, message: string, force = false) {
+		try {
+			await invoke<void>('reword_commit', {
+				projectId: this.projectId,
+				commitOid,
+				message,
+				force
+			});
+		} catch (err: any) {
+			showError('Failed to change commit message', err);
+		}
+	}
+
 	async moveCommitFile(
 		branchId: string,
 		fromCommitOid: string,
@@ -493,6 +620,63 @@ export class BranchController {
 		}
 	}
 
+	/** Apply the changes of a commit to the worktree, returning the files left with conflict markers. */
+	async cherryPick(commitOid: string) {
+		try {
+			return await invoke<CherryPickOutcome>('cherry_pick', {
+				projectId: this.projectId,
+				commitOid
+			});
+		} catch (err: any) {
+			showError('Failed to cherry-pick commit', err);
+		}
+	}
+
+	/** Undo the changes of a commit in the worktree, returning the files left with conflict markers. */
+	async revertCommit(commitOid: string) {
+		try {
+			return await invoke<CherryPickOutcome>('revert_commit', {
+				projectId: this.projectId,
+				commitOid
+			});
+		} catch (err: any) {
+			showError('Failed to revert commit', err);
+		}
+	}
+
+	/**
+	 * Rewrite a stack according to `instructions`, ordered from oldest to newest commit.
+	 * If the result would be conflicted nothing changes until the rebase is resumed or aborted.
+	 */
+	async startRebase(onto: string, instructions: RebaseInstruction[]) {
+		try {
+			return await invoke<RebaseStatus>('start_rebase', {
+				projectId: this.projectId,
+				onto,
+				instructions
+			});
+		} catch (err: any) {
+			showError('Failed to rebase', err);
+		}
+	}
+
+	/** Apply the pending rebase along with its conflicted commits. */
+	async resumeRebase() {
+		try {
+			await invoke<void>('resume_rebase', { projectId: this.projectId });
+		} catch (err: any) {
+			showError('Failed to resume rebase', err);
+		}
+	}
+
+	async abortRebase() {
+		try {
+			await invoke<void>('abort_rebase', { projectId: this.projectId });
+		} catch (err: any) {
+			showError('Failed to abort rebase', err);
+		}
+	}
+
 	async updateCommitMessage(branchId: string, commitOid: string, message: string) {
 		try {
 			await invoke<void>('update_commit_message', {
@@ -536,3 +720,32 @@ export interface BranchPushResult {
 	refname: string;
 	remote: string;
 }
+
+export type CherryPickOutcome = {
+	/** Files that couldn't be merged cleanly and now contain conflict markers. */
+	conflictedFiles: string[];
+};
+
+export type RebaseAction =
+	| { type: 'pick' | 'drop' }
+	| { type: 'squash'; subject: { message?: string } }
+	| { type: 'reword'; subject: { message: string } };
+
+export type RebaseInstruction = {
+	commitId: string;
+	action: RebaseAction;
+};
+
+export type RebaseStatus =
+	| { type: 'completed' }
+	/** The original ids of the commits that would be conflicted. */
+	| { type: 'conflicted'; subject: string[] };
+
+/** Why a push would be refused, as judged by the remote-tracking branches as of the last fetch. */
+export type PushRefusal =
+	| { type: 'nonFastForward'; subject: { branch: string; remoteHead: string } }
+	| { type: 'protectedBranch'; subject: { branch: string } }
+	| {
+			type: 'remoteChanged';
+			subject: { branch: string; expected: string | null; actual: string | null };
+	  };
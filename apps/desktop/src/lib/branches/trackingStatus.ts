@@ -0,0 +1,44 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import { readable } from 'svelte/store';
+
+/** How a local branch relates to its upstream branch. */
+export interface BranchTracking {
+	name: string;
+	/** Like `origin/main`, unset if the branch has no upstream. */
+	upstream?: string;
+	/** Unset if the upstream is configured but doesn't exist. */
+	ahead?: number;
+	behind?: number;
+}
+
+export interface TrackingStatus {
+	branches: BranchTracking[];
+	lastFetchedMs?: number;
+	/** Whether the remotes weren't fetched recently, so the counts might be outdated. */
+	fetchStale: boolean;
+}
+
+/** Get the ahead/behind counts of all local branches, for "needs push/pull" badges. */
+export async function getTrackingStatus(projectId: string) {
+	return await invoke<TrackingStatus>('branch_tracking_status', { projectId });
+}
+
+/** Sent after each automatic fetch of a project. */
+export type FetchCompleted = {
+	projectId: string;
+	/** Set if fetching any of the remotes failed. */
+	error?: string;
+	tracking: TrackingStatus;
+};
+
+/** The tracking status of `projectId` as of its latest automatic fetch. */
+export function autoFetchedTrackingStatus(projectId: string) {
+	return readable<TrackingStatus | undefined>(undefined, (set) => {
+		const unsubscribe = listen<FetchCompleted>('fetch://completed', (event) => {
+			if (event.payload.projectId === projectId) {
+				set(event.payload.tracking);
+			}
+		});
+		return async () => await unsubscribe();
+	});
+}
@@ -0,0 +1,22 @@
+import { invoke } from '$lib/backend/ipc';
+
+export type ViolationKind = 'empty' | 'subjectTooLong' | 'missingBlankLine' | 'notConventional';
+
+/** A problem with a commit message. Those of severity `error` keep the message from being committed. */
+export type Violation = {
+	kind: ViolationKind;
+	severity: 'warning' | 'error';
+	/** The 1-based number of the line with the problem. */
+	line: number;
+	message: string;
+};
+
+/** Return the content of the `commit.template` configured in git, to start commit messages with. */
+export async function commitTemplate(projectId: string) {
+	return await invoke<string | null>('commit_template', { projectId });
+}
+
+/** Return the problems with `message`, like not following Conventional Commits if the project requires it. */
+export async function validateCommitMessage(projectId: string, message: string) {
+	return await invoke<Violation[]>('validate_commit_message', { projectId, message });
+}
@@ -1,3 +1,4 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
 import { listen, invoke } from '$lib/backend/ipc';
 import { writable } from 'svelte/store';
 import type { Tauri } from '$lib/backend/tauri';
@@ -39,13 +40,44 @@ export class SettingsService {
 		await invoke('update_feature_flags', { update });
 	}
 
+	async updateConcurrency(update: Partial<Concurrency>) {
+		await invoke('update_concurrency', { update });
+	}
+
+	async updateLocalApi(update: Partial<LocalApi>) {
+		await invoke('update_local_api', { update });
+	}
+
+	async updateNotifications(update: Partial<NotificationSettings>) {
+		await invoke('update_notifications', { update });
+	}
+
+	async updateProjectDeletionGracePeriod(seconds: number) {
+		await invoke('update_project_deletion_grace_period', { seconds });
+	}
+
+	async updateWatcherIdleTimeout(seconds: number) {
+		await invoke('update_watcher_idle_timeout', { seconds });
+	}
+
+	async updateBackgroundMode(enabled: boolean) {
+		await invoke('update_background_mode', { enabled });
+	}
+
+	/** Pass `null` to update from the channel the app was built for. */
+	async setUpdateChannel(channel: UpdateChannel | null) {
+		await invoke('set_update_channel', { channel });
+	}
+
 	/**
 	 * For all projects this call deletes the following:
 	 * - project meta data directory
 	 * - project data directory
 	 */
 	async deleteAllData() {
-		await this.tauri.invoke<void>('delete_all_data');
+		await this.tauri.invoke<void>('delete_all_data', {
+			confirmationToken: await requestConfirmation('purgeHistory')
+		});
 	}
 }
 
@@ -56,8 +88,24 @@ export type AppSettings = {
 	telemetry: TelemetrySettings;
 	/** Feature flags that both the UI and the backend can see */
 	featureFlags: FeatureFlags;
+	/** Limits to how much work may be done at the same time */
+	concurrency: Concurrency;
+	/** How long to keep the data of deleted projects around so their deletion can be undone, in seconds. */
+	projectDeletionGracePeriodSeconds: number;
+	/** How long a project may be inactive before its watcher is stopped, in seconds. `0` keeps watchers running. */
+	watcherIdleTimeoutSeconds: number;
+	/** Whether to keep recording changes to the projects of closed windows in the background, with a tray icon. */
+	backgroundMode: boolean;
+	/** The channel to update from, or `null` to stay on the one the app was built for. */
+	updateChannel: UpdateChannel | null;
+	/** The API on a localhost port for editor integrations. */
+	localApi: LocalApi;
+	/** Which alerts about problems, like a failing watcher or a rejected push, to show and how. */
+	notifications: NotificationSettings;
 };
 
+export type UpdateChannel = 'stable' | 'nightly';
+
 export type TelemetrySettings = {
 	/** Whether the anonymous metrics are enabled. */
 	appMetricsEnabled: boolean;
@@ -65,9 +113,40 @@ export type TelemetrySettings = {
 	appErrorReportingEnabled: boolean;
 	/** Whether non-anonymous metrics are enabled. */
 	appNonAnonMetricsEnabled: boolean;
+	/** Whether anonymous usage counters of the backend are collected, off unless the user opts in. */
+	appUsageCountersEnabled: boolean;
 };
 
 export type FeatureFlags = {
 	/** Enables the v3 design, as well as the purgatory mode (no uncommitted diff ownership assignments). */
 	v3: boolean;
 };
+
+export type LocalApi = {
+	/** Whether to serve the API for editor integrations on a localhost port. Takes effect after a restart. */
+	enabled: boolean;
+	/** The port to listen on. `0` picks a free port, which is written to `local-api.json` in the app data directory. */
+	port: number;
+};
+
+export type NotificationCategory =
+	| 'watcherStopped'
+	| 'pushRejected'
+	| 'historyRepaired'
+	| 'diskSpaceLow';
+
+export type NotificationSettings = {
+	/** Whether to show notifications of the operating system as well, even when no window is focused. */
+	osNotifications: boolean;
+	/** The categories of notifications that are never shown. */
+	muted: NotificationCategory[];
+};
+
+export type Concurrency = {
+	/** The maximum amount of filesystem events to process at the same time. `0` picks a value based on the number of CPUs. */
+	watcherWorkers: number;
+	/** The maximum amount of heavy read commands to run at the same time. `0` picks a value based on the number of CPUs. */
+	commandThreads: number;
+	/** The maximum amount of fetches and pushes to run at the same time. `0` picks a value based on the number of CPUs. */
+	networkOperations: number;
+};
@@ -1,3 +1,4 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
 import type { Tauri } from '$lib/backend/tauri';
 
 export type GitCredentialCheck = {
@@ -18,7 +19,10 @@ export class GitConfigService {
 	}
 
 	async remove(key: string): Promise<undefined> {
-		return await this.tauri.invoke('git_remove_global_config', { key });
+		return await this.tauri.invoke('git_remove_global_config', {
+			key,
+			confirmationToken: await requestConfirmation('removeGlobalConfig')
+		});
 	}
 
 	async getWithDefault<T extends string>(key: string, defaultValue: T): Promise<T> {
@@ -38,6 +42,14 @@ export class GitConfigService {
 		return await this.tauri.invoke('set_gb_config', { projectId, config });
 	}
 
+	async getGlobalGitConfig(): Promise<GlobalGitConfig> {
+		return await this.tauri.invoke<GlobalGitConfig>('get_git_config');
+	}
+
+	async setGlobalGitConfig(config: GlobalGitConfig) {
+		return await this.tauri.invoke('set_git_config', { config });
+	}
+
 	async checkGitFetch(projectId: string, remoteName: string | null | undefined) {
 		if (!remoteName) return;
 		const resp = await this.tauri.invoke<string>('git_test_fetch', {
@@ -77,3 +89,12 @@ export class GbConfig {
 	gpgProgram?: string | undefined;
 	gpgSshProgram?: string | undefined;
 }
+
+// Values of the user's global git configuration that affect how GitButler behaves.
+// Unset values are left untouched when written, while empty values are removed.
+export class GlobalGitConfig {
+	userName?: string | undefined;
+	userEmail?: string | undefined;
+	excludesFile?: string | undefined;
+	defaultBranch?: string | undefined;
+}
@@ -0,0 +1,81 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import type { Classification } from '$lib/activity/deltas';
+
+/** The version of the schema of {@link EventEnvelope} this code understands. */
+export const EVENT_SCHEMA_VERSION = 1;
+
+export type GitOperation =
+	| { type: 'fetch' }
+	| { type: 'head'; subject: { head: string } }
+	| { type: 'activity' };
+
+/** Something a watcher observed. */
+export type BusEvent =
+	| { type: 'sessionStarted'; subject: { projectId: string } }
+	| {
+			/** The watcher stopped after handling everything it noticed. */
+			type: 'sessionEnded';
+			subject: { projectId: string; reason: 'appClosed' | 'stopped' };
+	  }
+	| {
+			type: 'deltaRecorded';
+			subject: {
+				projectId: string;
+				paths: string[];
+				machineGenerated: boolean;
+				/** The changes follow a git operation outside of GitButler, like a checkout. */
+				gitOperation: boolean;
+				classification: Classification;
+			};
+	  }
+	| { type: 'gitOperation'; subject: { projectId: string; operation: GitOperation } }
+	| { type: 'watcherError'; subject: { projectId: string; message: string } }
+	| {
+			/** The system limit of file watches was reached, and the worktree is polled instead. */
+			type: 'watchLimitExceeded';
+			subject: { projectId: string; limit: number | null; suggestion: string };
+	  };
+
+export type EventKind = BusEvent['type'];
+
+export type EventEnvelope = {
+	version: number;
+	/** Increases with every published event, so gaps can be detected. */
+	seq: number;
+	timestampMs: number;
+	event: BusEvent;
+};
+
+/** Which events to receive. Fields that are left out match everything. */
+export type EventFilter = {
+	projectId?: string;
+	kinds?: EventKind[];
+};
+
+/**
+ * Call `handle` with every event of the backend's event bus that matches `filter`, and return a function that
+ * ends the subscription.
+ */
+export async function subscribeEvents(
+	filter: EventFilter,
+	handle: (envelope: EventEnvelope) => void
+) {
+	const pending: { subscriptionId: number; envelope: EventEnvelope }[] = [];
+	let subscriptionId: number | undefined;
+	const unlisten = listen<{ subscriptionId: number; envelope: EventEnvelope }>('events', (event) => {
+		if (subscriptionId === undefined) {
+			// Events can arrive before we learn which subscription is ours.
+			pending.push(event.payload);
+		} else if (event.payload.subscriptionId === subscriptionId) {
+			handle(event.payload.envelope);
+		}
+	});
+	subscriptionId = await invoke<number>('subscribe_events', { filter });
+	for (const payload of pending) {
+		if (payload.subscriptionId === subscriptionId) handle(payload.envelope);
+	}
+	return async () => {
+		await unlisten();
+		await invoke<boolean>('unsubscribe_events', { subscriptionId });
+	};
+}
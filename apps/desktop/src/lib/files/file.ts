@@ -4,11 +4,27 @@ import { isDefined } from '@gitbutler/ui/utils/typeguards';
 import { Transform, Type } from 'class-transformer';
 import 'reflect-metadata';
 
+export type ContentType = 'text' | 'image' | 'archive' | 'binary';
+
 export type FileInfo = {
 	content: string;
 	name?: string;
 	mimeType?: string;
 	size?: number;
+	contentType?: ContentType;
+	/** How `content` was obtained from the bytes of the file. */
+	encoding?: ContentEncoding;
+	/** If true, some bytes couldn't be decoded, so `content` doesn't match the file exactly. */
+	lossy?: boolean;
+};
+
+export type ContentEncoding = 'utf8' | 'utf16Le' | 'utf16Be' | 'latin1' | 'base64';
+
+/** A file as it is in the worktree, the index and `HEAD`, for showing them side by side. */
+export type FileVersions = {
+	worktree: FileInfo;
+	index: FileInfo;
+	head: FileInfo;
 };
 export class RemoteFile {
 	path!: string;
@@ -1,7 +1,8 @@
 import { RemoteFile } from './file';
+import { invokeStreamed } from '$lib/backend/stream';
 import { plainToInstance } from 'class-transformer';
 import type { Tauri } from '$lib/backend/tauri';
-import type { FileInfo } from './file';
+import type { FileInfo, FileVersions } from './file';
 
 export class FileService {
 	constructor(private tauri: Tauri) {}
@@ -17,6 +18,42 @@ export class FileService {
 		};
 	}
 
+	async readFromWorktree(filePath: string, projectId: string, maxSize?: number) {
+		return await this.tauri.invoke<FileInfo>('get_worktree_file', {
+			relativePath: filePath,
+			projectId,
+			maxSize
+		});
+	}
+
+	/** Like `readFromWorktree`, but receive the file in chunks as it may be too large to be sent at once. */
+	async readLargeFromWorktree(filePath: string, projectId: string, maxSize?: number) {
+		return await invokeStreamed<FileInfo>({
+			kind: 'readWorkdirFile',
+			relativePath: filePath,
+			projectId,
+			maxSize
+		});
+	}
+
+	async readVersions(filePath: string, projectId: string, maxSize?: number) {
+		return await this.tauri.invoke<FileVersions>('get_file_versions', {
+			relativePath: filePath,
+			projectId,
+			maxSize
+		});
+	}
+
+	/** Read the exact bytes of a file as base64, from the worktree unless `commitId` is given. */
+	async readBase64(filePath: string, projectId: string, commitId?: string, maxSize?: number) {
+		return await this.tauri.invoke<FileInfo>('get_file_base64', {
+			relativePath: filePath,
+			projectId,
+			commitId,
+			maxSize
+		});
+	}
+
 	async readFromCommit(filePath: string, projectId: string, commitId: string | undefined) {
 		const data: FileInfo = await this.tauri.invoke('get_commit_file', {
 			relativePath: filePath,
@@ -0,0 +1,26 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import { readable, type Readable } from 'svelte/store';
+
+/**
+ * Emits a new value whenever the file at the worktree-relative `path` changes on disk.
+ *
+ * The backend only sends these events while there is at least one subscriber.
+ */
+export function fileChanges(projectId: string, path: string): Readable<number> {
+	// Stores only emit unique values so we use a counter to ensure
+	// derived stores are updated.
+	let counter = 0;
+	return readable<number>(undefined, (set) => {
+		invoke<void>('subscribe_file', { projectId, filePath: path });
+		const unlisten = listen<{ paths: string[] }>(
+			`project://${projectId}/files/changed`,
+			(event) => {
+				if (event.payload.paths.includes(path)) set(counter++);
+			}
+		);
+		return async () => {
+			await unlisten();
+			await invoke<void>('unsubscribe_file', { filePath: path });
+		};
+	});
+}
@@ -0,0 +1,40 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import { readable } from 'svelte/store';
+
+export type PullRequestState = 'open' | 'draft' | 'closed' | 'merged';
+export type ReviewState = 'approved' | 'changesRequested' | 'pending';
+export type ChecksState = 'passed' | 'failed' | 'pending';
+
+export type PullRequestStatus = {
+	number: number;
+	url: string;
+	state: PullRequestState;
+	/** Unset if nobody was asked to review. */
+	review?: ReviewState;
+	/** Unset if no checks are configured. */
+	checks?: ChecksState;
+};
+
+export type BranchForgeStatus = {
+	stackId: string;
+	branchName: string;
+	pullRequest: PullRequestStatus;
+};
+
+/** The pull request status of branches in the workspace, as refreshed by the backend after each fetch. */
+export class BranchForgeStatusService {
+	readonly statuses = readable<BranchForgeStatus[]>([], (set) => {
+		this.refresh().then((statuses) => set(statuses));
+		const unsubscribe = listen<BranchForgeStatus[]>(
+			`project://${this.projectId}/forge-status`,
+			(event) => set(event.payload)
+		);
+		return async () => await unsubscribe();
+	});
+
+	constructor(private projectId: string) {}
+
+	async refresh() {
+		return await invoke<BranchForgeStatus[]>('branch_forge_status', { projectId: this.projectId });
+	}
+}
@@ -0,0 +1,31 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import type { ChecksState } from './branchForgeStatus';
+
+/** A single check or commit status reported by the forge. */
+export type CheckRun = {
+	name: string;
+	state: ChecksState;
+	/** The page with the details of the check, if the forge provides one. */
+	url?: string;
+};
+
+export type CiStatus = {
+	/** The combined state of all checks, unset if no checks ran. */
+	state?: ChecksState;
+	checks: CheckRun[];
+};
+
+/** The checks that ran on the commit `reference` points to, which can be a branch name or commit id. */
+export async function getCiStatus(projectId: string, reference: string) {
+	return await invoke<CiStatus>('ci_status', { projectId, reference });
+}
+
+/** Get notified about the checks of commits shortly after they were pushed. */
+export function listenCiStatus(
+	projectId: string,
+	callback: (sha: string, status: CiStatus) => void
+) {
+	return listen<{ sha: string; status: CiStatus }>(`project://${projectId}/ci-status`, (event) =>
+		callback(event.payload.sha, event.payload.status)
+	);
+}
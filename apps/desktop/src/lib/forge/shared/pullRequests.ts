@@ -0,0 +1,31 @@
+import { invoke } from '$lib/backend/ipc';
+import type { PullRequestState } from './branchForgeStatus';
+
+/** A pull request, or merge request on GitLab, of the repository that `origin` points to. */
+export type PullRequest = {
+	number: number;
+	url: string;
+	title: string;
+	/** The branch with the changes. */
+	head: string;
+	/** The branch the changes are meant to be merged into. */
+	base: string;
+	state: PullRequestState;
+	author?: string;
+};
+
+/** List the open pull requests, newest first. */
+export async function listPullRequests(projectId: string) {
+	return await invoke<PullRequest[]>('list_pull_requests', { projectId });
+}
+
+/**
+ * Open a pull request to merge `head` into `base`, which requires being logged in to GitHub, or a GitLab
+ * access token in the keychain.
+ */
+export async function createPullRequest(
+	projectId: string,
+	params: { head: string; base: string; title: string; body: string }
+) {
+	return await invoke<PullRequest>('create_pull_request', { projectId, ...params });
+}
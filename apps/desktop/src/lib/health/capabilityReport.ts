@@ -0,0 +1,25 @@
+import { invoke } from '$lib/backend/ipc';
+
+export type Capability = 'git' | 'sshAgent' | 'keychain' | 'inotifyLimits' | 'diskSpace';
+
+/** `warning` means working, but some features may not, or not for long. */
+export type CheckStatus = 'ok' | 'warning' | 'error' | 'notApplicable';
+
+export interface CapabilityCheck {
+	capability: Capability;
+	status: CheckStatus;
+	/** What was found, to show to the user. */
+	message: string;
+}
+
+export interface CapabilityReport {
+	checks: CapabilityCheck[];
+}
+
+/**
+ * Check what the app relies on in its environment, to warn about problems during onboarding.
+ * Checking the keychain may make the system ask for permission.
+ */
+export async function capabilityReport() {
+	return await invoke<CapabilityReport>('capability_report');
+}
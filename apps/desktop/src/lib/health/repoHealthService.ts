@@ -0,0 +1,47 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import type { GitProgress } from '$lib/project/projectsService';
+
+export interface RepoHealth {
+	/** Objects stored more than once are counted more than once. */
+	objectCount: number;
+	looseObjects: number;
+	/** In bytes. */
+	looseSize: number;
+	/** Largest first. */
+	packs: { name: string; size: number }[];
+	/** Largest first, with their uncompressed size in bytes. */
+	largestBlobs: { id: string; size: number }[];
+	/** Local branches without commits for 90 days, oldest first. */
+	staleBranches: { name: string; lastCommitAt: number }[];
+	gcRecommended: boolean;
+}
+
+export class RepoHealthService {
+	async health(projectId: string) {
+		return await invoke<RepoHealth>('repo_health', { projectId });
+	}
+
+	/**
+	 * Optimize the repository in the background, calling `onProgress` as it goes, and resolve once it's done,
+	 * or reject if it failed.
+	 */
+	async optimize(projectId: string, onProgress: (progress: GitProgress) => void) {
+		const unlistenProgress = listen<GitProgress>(
+			`project://${projectId}/optimize/progress`,
+			(event) => onProgress(event.payload)
+		);
+		const finished = new Promise<void>((resolve, reject) => {
+			const unlisten = listen<{ error?: string }>(
+				`project://${projectId}/optimize/finished`,
+				(event) => {
+					unlisten();
+					unlistenProgress();
+					if (event.payload.error) reject(new Error(event.payload.error));
+					else resolve();
+				}
+			);
+		});
+		await invoke<void>('optimize_repository', { projectId });
+		return await finished;
+	}
+}
@@ -0,0 +1,32 @@
+import { invoke } from '$lib/backend/ipc';
+import { Project } from '$lib/project/project';
+import { plainToInstance } from 'class-transformer';
+
+/** How the history of a project is encrypted at rest, if it is. */
+export type HistoryEncryption =
+	| { mode: 'none' }
+	/** The key is kept in the keychain, which unlocks the history without asking. */
+	| { mode: 'keychain' }
+	/** The key is derived from a passphrase that has to be entered with `unlockHistory()`. */
+	| { mode: 'passphrase'; salt: string; check: string };
+
+/**
+ * Unlock the encrypted history of a project until the app quits, which fails with
+ * `Code.HistoryLocked` if the passphrase is wrong.
+ */
+export async function unlockHistory(projectId: string, passphrase?: string) {
+	return await invoke<void>('unlock_history', { projectId, passphrase });
+}
+
+/**
+ * Encrypt the history of a project with a key derived from `passphrase`, or with a random key kept in the
+ * keychain if unset.
+ */
+export async function encryptHistory(projectId: string, passphrase?: string) {
+	return plainToInstance(Project, await invoke('encrypt_history', { projectId, passphrase }));
+}
+
+/** Store the encrypted history of a project in plain files again. */
+export async function decryptHistory(projectId: string) {
+	return plainToInstance(Project, await invoke('decrypt_history', { projectId }));
+}
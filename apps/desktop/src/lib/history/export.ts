@@ -0,0 +1,69 @@
+import { invoke } from '$lib/backend/ipc';
+import { invokeStreamed } from '$lib/backend/stream';
+import type { FileInfo } from '$lib/files/file';
+
+/**
+ * `jsonLines` writes one JSON object per snapshot, while `fastExport` writes a stream for
+ * `git fast-import` with one commit per session.
+ */
+export type HistoryExportFormat = 'jsonLines' | 'fastExport';
+
+export type HistoryExport = {
+	path: string;
+	snapshots: number;
+	sessions: number;
+};
+
+/**
+ * Write the history recorded between `since` and `until` to the file at `path`. It can be
+ * cancelled as the operation with `operationId`.
+ */
+export async function exportHistory(
+	projectId: string,
+	format: HistoryExportFormat,
+	since: Date,
+	until: Date,
+	path: string,
+	operationId?: string
+) {
+	return await invoke<HistoryExport>('export_history', {
+		projectId,
+		format,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000),
+		path,
+		operationId
+	});
+}
+
+/**
+ * Render the history recorded between `since` and `until` without writing it to a file, with the
+ * exported content as base64.
+ */
+export async function renderHistory(
+	projectId: string,
+	format: HistoryExportFormat,
+	since: Date,
+	until: Date
+) {
+	return await invokeStreamed<FileInfo>({
+		kind: 'exportHistory',
+		projectId,
+		format,
+		since: Math.floor(since.getTime() / 1000),
+		until: Math.floor(until.getTime() / 1000)
+	});
+}
+
+export type HistoryImport = {
+	imported: number;
+	skipped: number;
+};
+
+/**
+ * Merge the history recorded in the repository at `path`, like the copy of the project on another
+ * machine, into the history of the project. Snapshots that are part of it already are skipped.
+ */
+export async function importHistory(projectId: string, path: string) {
+	return await invoke<HistoryImport>('import_history', { projectId, path });
+}
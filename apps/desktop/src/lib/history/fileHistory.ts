@@ -0,0 +1,25 @@
+import { invoke } from '$lib/backend/ipc';
+import type { ActivitySession } from '$lib/activity/activitySummary';
+
+/** An event in the history of a file. Times are in seconds since the Unix epoch. */
+export type FileHistoryEntry =
+	| {
+			type: 'commit';
+			subject: {
+				commitId: string;
+				/** The path of the file in this commit, which differs from the current one if it was renamed since. */
+				path: string;
+				createdAt: number;
+				summary: string;
+				author: string;
+			};
+	  }
+	| { type: 'session'; subject: { session: ActivitySession } };
+
+/**
+ * Get the commits that touched the file at the worktree-relative `filePath`, following renames, interleaved
+ * with the sessions in which it was edited, newest first.
+ */
+export async function getFileHistory(projectId: string, filePath: string) {
+	return await invoke<FileHistoryEntry[]>('file_history', { projectId, filePath });
+}
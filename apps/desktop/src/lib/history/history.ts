@@ -1,7 +1,46 @@
 import { Snapshot, SnapshotDiff } from './types';
-import { invoke } from '$lib/backend/ipc';
+import { invoke, listen } from '$lib/backend/ipc';
 import { plainToInstance } from 'class-transformer';
 import { get, writable } from 'svelte/store';
+import type { FileMode, Operation } from './types';
+import type { FileInfo } from '$lib/files/file';
+
+/** The state of a file at one point of a playback across snapshots. */
+export type PlaybackFrame = {
+	filePath: string;
+	index: number;
+	total: number;
+	snapshotId: string;
+	/** Seconds since the Unix epoch. */
+	createdAt: number;
+	file: FileInfo;
+	/** Undefined if the file didn't exist. */
+	mode?: FileMode;
+};
+
+/** A branch moved by an operation, with an undefined `old` or `new` meaning it didn't exist. */
+export type RefUpdate = {
+	name: string;
+	old?: string;
+	new?: string;
+};
+
+/** An operation recorded in the journal of the operations performed on a project. */
+export type JournalEntry = {
+	/** Seconds since the Unix epoch. */
+	at: number;
+	operation: Operation;
+	snapshot?: string;
+	/** What the operation was called with, by name. */
+	parameters?: Record<string, string>;
+	refUpdates: RefUpdate[];
+	/** Why the operation failed, if it did. */
+	error?: string;
+	/** The position of the undone entry, if this entry is an undo. */
+	undoes?: number;
+	/** The position of the entry in the journal, which `undoes` refers to. */
+	position?: number;
+};
 
 export class HistoryService {
 	cursor: string | undefined = undefined;
@@ -70,12 +109,48 @@ export class HistoryService {
 		}, {});
 	}
 
+	/**
+	 * Play back how the file at `filePath` changed across the latest `limit` snapshots, calling
+	 * `onFrame` for each of its versions at a rate of `fps`.
+	 */
+	async playFile(
+		filePath: string,
+		onFrame: (frame: PlaybackFrame) => void,
+		opts: { fps: number; limit: number } = { fps: 10, limit: 128 }
+	) {
+		const unlisten = listen<PlaybackFrame>(
+			`project://${this.projectId}/playback/frame`,
+			(event) => {
+				if (event.payload.filePath !== filePath) return;
+				onFrame(event.payload);
+				if (event.payload.index === event.payload.total - 1) unlisten();
+			}
+		);
+		const total = await invoke<number>('snapshot_playback', {
+			projectId: this.projectId,
+			filePath,
+			...opts
+		});
+		if (total === 0) unlisten();
+		return total;
+	}
+
 	async restoreSnapshot(projectId: string, sha: string) {
 		await invoke<string>('restore_snapshot', {
 			projectId: projectId,
 			sha: sha
 		});
 	}
+
+	/** List the latest `limit` operations performed on the project, newest first, as an audit trail. */
+	async listOperations(projectId: string, limit?: number) {
+		return await invoke<JournalEntry[]>('list_operations', { projectId, limit });
+	}
+
+	/** Undo the latest commit, rebase or similar operation that moved branches and return it. */
+	async undoLastOperation(projectId: string) {
+		return await invoke<JournalEntry>('undo_last_operation', { projectId });
+	}
 }
 
 export function createdOnDay(d: Date) {
@@ -0,0 +1,42 @@
+import { invoke } from '$lib/backend/ipc';
+
+/** How much of the history of each file is kept when deltas are compacted. */
+export type HistoryRetention = {
+	/** Drop the history of each file that is older than this many days, which is at least one. */
+	maxAgeDays?: number;
+	/** Keep the history of at most this many sessions that changed each file, which is at least one. */
+	maxSessions?: number;
+	/** The worktree-relative paths of files whose history is never dropped. */
+	pinnedFiles: string[];
+	/** Sessions whose history is never dropped. */
+	pinnedSessions: PinnedSession[];
+};
+
+/** A session from `start` to `end`, in seconds since the Unix epoch. */
+export type PinnedSession = {
+	start: number;
+	end: number;
+};
+
+export type FileBudget = {
+	path: string;
+	deltas: number;
+	sessions: number;
+	oldestAt: number;
+	pinned: boolean;
+	/** The amount of deltas of the file the next compaction drops. */
+	prunableDeltas: number;
+};
+
+export type HistoryBudget = {
+	retention: HistoryRetention;
+	deltasBytes: number;
+	/** Once the deltas are larger than this, those older than 30 days are dropped unless they are pinned. */
+	maxDeltasBytes: number;
+	files: FileBudget[];
+};
+
+/** Tell how much history of each file is kept, and how much the history retention of the project drops. */
+export async function historyBudget(projectId: string) {
+	return await invoke<HistoryBudget>('history_budget', { projectId });
+}
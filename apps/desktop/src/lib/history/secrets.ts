@@ -0,0 +1,17 @@
+import { invoke } from '$lib/backend/ipc';
+
+/** A secret that was recorded in a snapshot. */
+export type SecretFinding = {
+	snapshotId: string;
+	/** The creation time of the snapshot in seconds since the Unix epoch. */
+	createdAt: number;
+	path: string;
+	line: number;
+	/** The name of the built-in pattern, or the custom regular expression, that matched. */
+	pattern: string;
+};
+
+/** Find secrets in the snapshots of the project, oldest first. */
+export async function scanHistoryForSecrets(projectId: string) {
+	return await invoke<SecretFinding[]>('scan_history_for_secrets', { projectId });
+}
@@ -0,0 +1,41 @@
+import { invoke } from '$lib/backend/ipc';
+import type { Delta, DeltaContent } from '$lib/activity/deltas';
+
+export type SessionShare = {
+	path: string;
+	deltas: number;
+	files: number;
+	encrypted: boolean;
+};
+
+/** A session shared by another user, with everything needed to replay it. */
+export type SharedSession = {
+	version: number;
+	projectTitle: string;
+	start: number;
+	end: number;
+	branch?: string;
+	/** The content of the files changed during the session as of its start. */
+	base: DeltaContent[];
+	deltas: Delta[];
+	/** The base64-encoded contents that `base` and `deltas` refer to, by their blob id. */
+	blobs: Record<string, string>;
+};
+
+/**
+ * Write the session that started at `sessionId` seconds since the Unix epoch to the file at `path`,
+ * for another user to replay. If `passphrase` is set, the file can't be opened without it.
+ */
+export async function shareSession(
+	projectId: string,
+	sessionId: number,
+	path: string,
+	passphrase?: string
+) {
+	return await invoke<SessionShare>('share_session', { projectId, sessionId, path, passphrase });
+}
+
+/** Read a session shared by another user from the file at `path`. */
+export async function openSharedSession(path: string, passphrase?: string) {
+	return await invoke<SharedSession>('open_shared_session', { path, passphrase });
+}
@@ -23,6 +23,8 @@ export type Operation =
 	| 'UndoCommit'
 	| 'UnapplyBranch'
 	| 'CherryPick'
+	| 'RevertCommit'
+	| 'InteractiveRebase'
 	| 'SquashCommit'
 	| 'UpdateCommitMessage'
 	| 'MoveCommit'
@@ -31,7 +33,15 @@ export type Operation =
 	| 'InsertBlankCommit'
 	| 'MoveCommitFile'
 	| 'FileChanges'
-	| 'EnterEditMode';
+	| 'EnterEditMode'
+	| 'RepairHistory'
+	| 'OfflineChanges'
+	| 'ExternalGitOperation'
+	| 'UndoOperation'
+	| 'UpdateProjectSettings'
+	| 'SuspendWorkspace'
+	| 'ResumeWorkspace'
+	| 'RestoreFiles';
 
 export class Trailer {
 	key!: string;
@@ -47,8 +57,14 @@ export class SnapshotDiff {
 	oldPath!: string;
 	oldSizeBytes!: number;
 	skipped!: boolean;
+	/** Undefined if the file was added. */
+	oldMode?: FileMode;
+	/** Undefined if the file was deleted. */
+	newMode?: FileMode;
 }
 
+export type FileMode = 'regular' | 'executable' | 'symlink' | 'submodule';
+
 export class SnapshotDetails {
 	title!: string;
 	operation!: Operation;
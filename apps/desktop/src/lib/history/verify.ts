@@ -0,0 +1,35 @@
+import { invoke } from '$lib/backend/ipc';
+
+/** A way in which restoring a snapshot wouldn't produce what was recorded. */
+export type Divergence =
+	| { type: 'missingObjects'; subject: { snapshotId: string; objectIds: string[] } }
+	| { type: 'commitMismatch'; subject: { snapshotId: string; commitId: string } }
+	| { type: 'unrestorableWorktree'; subject: { snapshotId: string; reason: string } }
+	| { type: 'worktreeChanged'; subject: { snapshotId: string; paths: string[] } };
+
+export type HistoryVerification = {
+	snapshots: number;
+	/** Newest snapshot first. */
+	divergences: Divergence[];
+};
+
+/** Check that all snapshots can be restored, and that the latest one matches what is on disk. */
+export async function verifyHistory(projectId: string) {
+	return await invoke<HistoryVerification>('verify_history', { projectId });
+}
+
+/**
+ * Create a correction snapshot if the latest snapshot can't be restored or doesn't match what is on disk,
+ * returning its id.
+ */
+export async function repairHistory(projectId: string) {
+	return await invoke<string | null>('repair_history', { projectId });
+}
+
+/** What was done to the reference that keeps snapshots from being garbage-collected. */
+export type MetaRefRepair = 'noOplog' | 'intact' | 'recreated';
+
+/** Point the reference that keeps snapshots from being garbage-collected to the latest snapshot again. */
+export async function repairMetaRef(projectId: string) {
+	return await invoke<MetaRefRepair>('repair_meta_ref', { projectId });
+}
@@ -1,3 +1,4 @@
+import { listen } from '$lib/backend/ipc';
 import type { Tauri } from '$lib/backend/tauri';
 
 export type HookStatus =
@@ -28,6 +29,13 @@ export type MessageHookStatus =
 			error: string;
 	  };
 
+/** A line of output of a running hook. */
+export type HookOutput = {
+	hook: string;
+	stream: 'stdout' | 'stderr';
+	line: string;
+};
+
 export class HooksService {
 	constructor(private tauri: Tauri) {}
 
@@ -50,4 +58,11 @@ export class HooksService {
 			message
 		});
 	}
+
+	/** Call `callback` with each line of hook output as soon as a hook of the project prints it. */
+	onOutput(projectId: string, callback: (output: HookOutput) => void) {
+		return listen<HookOutput>(`project://${projectId}/hooks/output`, (event) =>
+			callback(event.payload)
+		);
+	}
 }
@@ -0,0 +1,28 @@
+import { invoke } from '$lib/backend/ipc';
+
+export type DiffGranularity = 'line' | 'word' | 'character';
+
+export type DiffAlgorithm = 'myers' | 'patience';
+
+/** A part of two compared texts, with the old text made up of all segments that weren't added and vice versa. */
+export type DiffSegment = {
+	kind: 'equal' | 'removed' | 'added';
+	text: string;
+};
+
+/**
+ * Compare `oldText` with `newText` in units of `granularity`, like words for highlighting changes within lines.
+ */
+export async function computeDiff(
+	oldText: string,
+	newText: string,
+	granularity: DiffGranularity,
+	algorithm?: DiffAlgorithm
+) {
+	return await invoke<DiffSegment[]>('compute_diff', {
+		old: oldText,
+		new: newText,
+		granularity,
+		algorithm
+	});
+}
@@ -0,0 +1,21 @@
+import { invoke } from '$lib/backend/ipc';
+
+export interface Identity {
+	name: string;
+	email: string;
+}
+
+export class IdentityService {
+	/** The identity pinned for the commits GitButler creates in the project, if there is one. */
+	async getIdentity(projectId: string) {
+		return await invoke<Identity | null>('get_project_identity', { projectId });
+	}
+
+	/**
+	 * Pin `identity` as author of the commits GitButler creates in the project, regardless of the global
+	 * Git configuration, or unpin it with `null`. Returns warnings if it doesn't seem to fit the remotes.
+	 */
+	async setIdentity(projectId: string, identity: Identity | null) {
+		return await invoke<string[]>('set_project_identity', { projectId, identity });
+	}
+}
@@ -0,0 +1,49 @@
+import { listen } from '$lib/backend/ipc';
+import { showToast } from '$lib/notifications/toasts';
+import type { NotificationCategory } from '$lib/config/appSettingsV2';
+
+/** An alert about a problem, sent by the backend once its category isn't muted and rate limits allow it. */
+export type Notification = {
+	/** Unique among the notifications of a run of the app. */
+	id: number;
+	category: NotificationCategory;
+	/** Unset if the notification isn't about a single project. */
+	projectId?: string;
+	title: string;
+	body: string;
+	timestampMs: number;
+	/** Whether to show a notification of the operating system as well. */
+	os: boolean;
+	/** How many notifications like this one weren't shown since the previous one. */
+	suppressed: number;
+};
+
+/** Show the notifications sent by the backend as toasts, and as notifications of the operating system if asked. */
+export function listenForNotifications() {
+	return listen<Notification>('notification://show', (event) => {
+		const notification = event.payload;
+		const message =
+			notification.suppressed > 0
+				? `${notification.body}\n\n${notification.suppressed} similar notifications were hidden.`
+				: notification.body;
+		showToast({
+			id: `notification-${notification.category}-${notification.projectId ?? ''}`,
+			title: notification.title,
+			message,
+			style: notification.category === 'pushRejected' ? 'error' : 'warning'
+		});
+		if (notification.os && !document.hasFocus()) {
+			showOsNotification(notification, message);
+		}
+	});
+}
+
+async function showOsNotification(notification: Notification, body: string) {
+	if (!('Notification' in window)) return;
+	if (Notification.permission === 'default') {
+		await Notification.requestPermission();
+	}
+	if (Notification.permission !== 'granted') return;
+	// Every window receives the notification, and the tag makes them replace each other instead of piling up.
+	new Notification(notification.title, { body, tag: `gitbutler-${notification.id}` });
+}
@@ -0,0 +1,23 @@
+import { invoke } from '$lib/backend/ipc';
+
+export interface PatchApplication {
+	/** The number of patches in the text. */
+	patches: number;
+	/** The paths the patches change, relative to the worktree. */
+	paths: string[];
+}
+
+export class PatchesService {
+	/**
+	 * Write one `git format-patch` file per commit in `range`, like `main..feature`, to `outDir` and return
+	 * their paths.
+	 */
+	async formatPatch(projectId: string, range: string, outDir: string) {
+		return await invoke<string[]>('format_patch', { projectId, range, outDir });
+	}
+
+	/** Apply all patches in `patchText` as uncommitted changes, or only check they apply if `checkOnly` is set. */
+	async applyPatch(projectId: string, patchText: string, checkOnly = false) {
+		return await invoke<PatchApplication>('apply_patch', { projectId, patchText, checkOnly });
+	}
+}
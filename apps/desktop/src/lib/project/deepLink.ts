@@ -0,0 +1,36 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import { unsubscribe } from '$lib/utils/unsubscribe';
+import { ask } from '@tauri-apps/plugin-dialog';
+import type { ProjectsService } from '$lib/project/projectsService';
+
+type LinkTarget = { type: 'project'; projectId: string } | { type: 'addProject'; path: string };
+
+/**
+ * Call `open` with the projects that `gitbutler://` links ask to show, including the one the app was started with.
+ *
+ * Links can come from anywhere, so worktrees that aren't projects yet are only added once the user agreed to.
+ */
+export function listenForDeepLinks(
+	projectsService: ProjectsService,
+	open: (projectId: string) => void
+) {
+	async function addProject(path: string) {
+		const confirmed = await ask(`Add the repository at ${path} as a project?`, {
+			title: 'Open in GitButler',
+			kind: 'info',
+			okLabel: 'Add project'
+		});
+		if (!confirmed) return;
+		const project = await projectsService.add(path);
+		open(project.id);
+	}
+
+	invoke<LinkTarget | null>('take_deep_link_project').then(async (target) => {
+		if (target?.type === 'project') open(target.projectId);
+		if (target?.type === 'addProject') await addProject(target.path);
+	});
+	return unsubscribe(
+		listen<string>('deep-link://open-project', (event) => open(event.payload)),
+		listen<string>('deep-link://add-project', async (event) => await addProject(event.payload))
+	);
+}
@@ -1,3 +1,7 @@
+import type { BulkChangeThreshold, ClassificationRule } from '$lib/activity/deltas';
+import type { HistoryEncryption } from '$lib/history/encryption';
+import type { HistoryRetention } from '$lib/history/retention';
+
 export type KeyType = 'gitCredentialsHelper' | 'local' | 'systemExecutable';
 export type LocalKey = {
 	local: { private_key_path: string };
@@ -5,17 +9,68 @@ export type LocalKey = {
 
 export type Key = Exclude<KeyType, 'local'> | LocalKey;
 
+/** How changes to files are noticed, `polling` is for filesystems that don't notify reliably. */
+export type WatcherMode = 'native' | 'polling';
+
+/** What a new project is started with, which decides its `.gitignore`. */
+export type ProjectTemplate = 'empty' | 'rust' | 'node' | 'python' | 'go';
+
+/** Where the files of a project are stored, as returned when adding it. */
+export type StorageLocation =
+	| 'local'
+	| { networkMount: { filesystem: string } }
+	| { cloudSync: { provider: string } };
+
+/** Someone to credit as co-author of commits with a `Co-authored-by` trailer. */
+export type CoAuthor = {
+	name: string;
+	email: string;
+};
+
 export class Project {
 	id!: string;
 	title!: string;
 	description?: string;
 	path!: string;
+	/** The directory within the worktree if this is one of several projects in the same repository. */
+	subdirectory?: string;
 	api?: CloudProject & { sync: boolean; sync_code: boolean | undefined };
 	preferred_key!: Key;
 	ok_with_force_push!: boolean;
 	omit_certificate_check: boolean | undefined;
 	use_diff_context: boolean | undefined;
 	snapshot_lines_threshold!: number | undefined;
+	protected_branches!: string[];
+	protected_branches_override!: boolean;
+	secret_patterns!: string[];
+	secret_redaction!: 'mask' | 'skipFile';
+	recording_paused!: boolean;
+	/** If set, commands that would change the project are refused, while its history is still recorded. */
+	read_only!: boolean;
+	/** If set, the remotes are fetched in the background every this many seconds. */
+	auto_fetch_interval_seconds?: number;
+	/** The name of the group the project is listed under. */
+	group?: string;
+	/** The position set with `reorderProjects()`, unset for projects listed after the ordered ones. */
+	sort_order?: number;
+	favorite!: boolean;
+	watcher_mode!: WatcherMode;
+	/** If not empty, only changes in these worktree-relative directories are watched and recorded. */
+	watch_include_paths!: string[];
+	/** Rules to classify changes as made by a human or a machine, the first matching one applies. */
+	change_classification_rules!: ClassificationRule[];
+	/** If set, commit messages that don't follow Conventional Commits are refused. */
+	conventional_commits!: boolean;
+	/** The people the user often writes code with, to choose from when crediting co-authors of a commit. */
+	co_authors!: CoAuthor[];
+	/** If set, the worktree is committed to `refs/gitbutler/wip/<branch>` at most every this many seconds. */
+	wip_snapshot_interval_seconds?: number;
+	/** If set, bursts of changes to many files are recorded as a single bulk change. */
+	bulk_change_threshold?: BulkChangeThreshold;
+	/** How much of the history of each file is kept, and what is never dropped. */
+	history_retention!: HistoryRetention;
+	/** How the history is encrypted at rest, if it is. */
+	history_encryption!: HistoryEncryption;
 	// Produced just for the frontend to determine if the project is open in any window.
 	is_open!: boolean;
 
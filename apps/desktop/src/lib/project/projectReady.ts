@@ -0,0 +1,13 @@
+import { listen } from '$lib/backend/ipc';
+import { readable } from 'svelte/store';
+
+/**
+ * Whether the backend caught up with what changed in the project while it wasn't watched, which it does shortly
+ * after the project was opened. Until then, work that isn't needed to show the project can wait.
+ */
+export function projectReady(projectId: string) {
+	return readable<boolean>(false, (set) => {
+		const unsubscribe = listen(`project://${projectId}/ready`, () => set(true));
+		return async () => await unsubscribe();
+	});
+}
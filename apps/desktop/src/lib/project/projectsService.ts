@@ -1,6 +1,11 @@
-import { Project, type CloudProject } from './project';
+import {
+	Project,
+	type CloudProject,
+	type ProjectTemplate,
+	type StorageLocation
+} from './project';
 import { invoke } from '$lib/backend/ipc';
-import { showError } from '$lib/notifications/toasts';
+import { showError, showToast } from '$lib/notifications/toasts';
 import { sleep } from '$lib/utils/sleep';
 import { persisted } from '@gitbutler/shared/persisted';
 import * as toasts from '@gitbutler/ui/toasts';
@@ -10,6 +15,13 @@ import { derived, get, writable, type Readable } from 'svelte/store';
 import type { HttpClient } from '@gitbutler/shared/network/httpClient';
 import { goto } from '$app/navigation';
 
+/** How far along a clone, fetch or repack is, as sent with `clone://progress` events. */
+export type GitProgress = {
+	/** What Git is doing, like `Receiving objects`. */
+	phase: string;
+	percent: number;
+};
+
 export class ProjectsService {
 	private persistedId = persisted<string | undefined>(undefined, 'lastProject');
 	readonly projects = writable<Project[] | undefined>(undefined, (set) => {
@@ -62,16 +74,111 @@ export class ProjectsService {
 	}
 
 	async add(path: string) {
-		const project = plainToInstance(Project, await invoke('add_project', { path }));
+		const added = await invoke<{ storage_location: StorageLocation }>('add_project', { path });
+		const project = plainToInstance(Project, added);
+		await this.reload();
+		warnAboutStorageLocation(added.storage_location);
+		return project;
+	}
+
+	/**
+	 * Clone the repository at `url` into `path` and add it, with only the last `depth` commits if set and without
+	 * the objects excluded by `filter`, like `blob:none`. Progress is sent as `clone://progress` events.
+	 */
+	async clone(url: string, path: string, opts?: { depth?: number; filter?: string }) {
+		const added = await invoke<{ storage_location: StorageLocation }>('clone_project', {
+			url,
+			path,
+			...opts
+		});
+		const project = plainToInstance(Project, added);
+		await this.reload();
+		warnAboutStorageLocation(added.storage_location);
+		return project;
+	}
+
+	/**
+	 * Start a new project in the empty directory at `path`, with a `.gitignore` from `template` in its initial commit,
+	 * as offered when adding an empty directory fails with `Code.ProjectDirectoryEmpty`.
+	 */
+	async init(path: string, template: ProjectTemplate = 'empty') {
+		const added = await invoke<{ storage_location: StorageLocation }>('init_project', {
+			path,
+			template
+		});
+		const project = plainToInstance(Project, added);
 		await this.reload();
+		warnAboutStorageLocation(added.storage_location);
 		return project;
 	}
 
+	/** The templates new projects can be started with. */
+	async templates() {
+		return await invoke<ProjectTemplate[]>('project_templates');
+	}
+
+	/** Fetch `depth` more commits of a shallow clone, or all of its history if unset. */
+	/** Add `subdirectory` of the repository of `parentId` as a project of its own, like an app in a monorepo. */
+	async addSubproject(parentId: string, subdirectory: string) {
+		const project = plainToInstance(
+			Project,
+			await invoke('add_subproject', { parentId, subdirectory })
+		);
+		await this.reload();
+		return project;
+	}
+
+	async listSubprojects(projectId: string) {
+		return plainToInstance(Project, await invoke<Project[]>('list_subprojects', { projectId }));
+	}
+
+	async unshallow(projectId: string, depth?: number) {
+		await invoke('unshallow', { projectId, depth });
+	}
+
+	async pauseRecording(projectId: string) {
+		await invoke('pause_recording', { projectId });
+		await this.reload();
+	}
+
+	async resumeRecording(projectId: string) {
+		await invoke('resume_recording', { projectId });
+		await this.reload();
+	}
+
+	/**
+	 * Only watch and record changes in the worktree-relative directories `includePaths`, or in all of the
+	 * worktree if empty. The watcher of the project follows without restarting.
+	 */
+	async setWatchIncludePaths(projectId: string, includePaths: string[]) {
+		await invoke('set_watch_include_paths', { projectId, includePaths });
+		await this.reload();
+	}
+
 	async deleteProject(id: string) {
 		await invoke('delete_project', { id });
 		await this.reload();
 	}
 
+	async undoDeleteProject(id: string) {
+		const project = plainToInstance(Project, await invoke('undo_delete_project', { id }));
+		await this.reload();
+		return project;
+	}
+
+	/** List the projects with `projectIds` first, in that order, followed by the others by title. */
+	async reorderProjects(projectIds: string[]) {
+		await invoke('reorder_projects', { projectIds });
+		await this.reload();
+	}
+
+	/** Put the project into `group`, or take it out of its group if `group` is unset. */
+	async setProjectGroup(id: string, group: string | undefined) {
+		const project = plainToInstance(Project, await invoke('set_project_group', { id, group }));
+		await this.reload();
+		return project;
+	}
+
 	async promptForDirectory(): Promise<string | undefined> {
 		const selectedPath = open({ directory: true, recursive: true, defaultPath: this.homeDir });
 		if (selectedPath) {
@@ -181,3 +288,12 @@ export class ProjectsService {
 		return await this.httpClient.get(`projects/${repositoryId}.json`);
 	}
 }
+
+function warnAboutStorageLocation(location: StorageLocation) {
+	if (location === 'local') return;
+	const message =
+		'networkMount' in location
+			? `The project is on a network filesystem (${location.networkMount.filesystem}), so changes are detected by periodically scanning the files, which may take a few seconds.`
+			: `The project is in a folder synced by ${location.cloudSync.provider}. Syncing can make files appear changed and make Git fail to lock files, consider moving the project to an unsynced folder.`;
+	showToast({ title: 'Project location may cause problems', message, style: 'warning' });
+}
@@ -0,0 +1,22 @@
+import { listen } from '$lib/backend/ipc';
+import { showToast } from '$lib/notifications/toasts';
+
+export type WriteConflict = {
+	projectId: string;
+	/** The worktree-relative path of the file that wasn't written. */
+	path: string;
+};
+
+/**
+ * Tell the user whenever the backend refused to write a file of the project, as another program
+ * like an editor changed it in the meantime. Returns a function to stop listening.
+ */
+export function listenForWriteConflicts(projectId: string) {
+	return listen<WriteConflict>(`project://${projectId}/write-conflict`, (event) => {
+		showToast({
+			title: 'File changed while writing it',
+			message: `\`${event.payload.path}\` was changed by another program, so it was left as is. Review it and try again.`,
+			style: 'warning'
+		});
+	});
+}
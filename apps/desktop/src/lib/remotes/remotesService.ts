@@ -3,6 +3,8 @@ import { invoke } from '$lib/backend/ipc';
 export interface GitRemote {
 	name?: string;
 	url?: string;
+	pushUrl?: string;
+	defaultUpstream?: string;
 }
 
 export class RemotesService {
@@ -27,4 +29,12 @@ export class RemotesService {
 
 		return await invoke<string>('add_remote', { projectId, name, url });
 	}
+
+	async removeRemote(projectId: string, name: string) {
+		return await invoke<void>('remove_remote', { projectId, name });
+	}
+
+	async setRemoteUrl(projectId: string, name: string, url: string) {
+		return await invoke<void>('set_remote_url', { projectId, name, url });
+	}
 }
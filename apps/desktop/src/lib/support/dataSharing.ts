@@ -7,3 +7,82 @@ export async function logs() {
 export async function projectData(params: { projectId: string }) {
 	return await invoke<string>('get_project_archive_path', params);
 }
+
+export type CrashReport = {
+	id: string;
+	createdAtMs: number;
+	appVersion: string;
+	message: string;
+	location?: string;
+	thread?: string;
+	backtrace: string;
+	logLines: string[];
+	submitted: boolean;
+};
+
+export async function crashReports() {
+	return await invoke<CrashReport[]>('list_crash_reports');
+}
+
+export async function crashReport(params: { id: string }) {
+	return await invoke<string>('submit_crash_report', params);
+}
+
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export async function recentLogs(params: { lines: number; levelFilter?: LogLevel }) {
+	return await invoke<string[]>('get_logs', params);
+}
+
+export async function setLogLevel(level: LogLevel) {
+	return await invoke<void>('set_log_level', { level });
+}
+
+/** The outcome of a single diagnostic check, or why it couldn't be completed. */
+export type Check<T> = { ok: T } | { failed: string };
+
+/**
+ * The state of a project that helps to diagnose problems, with paths of the user, the identity configured in Git
+ * and secrets redacted.
+ */
+export type Diagnostics = {
+	appVersion: string;
+	os: string;
+	arch: string;
+	collectedAt: number;
+	project: {
+		id: string;
+		path: string;
+		subdirectory?: string;
+		watcherMode: string;
+		watchIncludePaths: string[];
+		readOnly: boolean;
+		recordingPaused: boolean;
+	};
+	watcher: {
+		open: boolean;
+		metrics?: {
+			eventsReceived: number;
+			eventsHandled: number;
+			eventsQueued: number;
+			eventsPerMinute: number;
+			averageHandlingMs: number;
+		};
+	};
+	lastSessionEnd: Check<number | null>;
+	repository: Check<{ head?: string; state: string; shallow: boolean }>;
+	storage: Check<{
+		snapshots: number;
+		snapshotBytes: number;
+		metadataBytes: number;
+		divergenceCount: number;
+		divergences: unknown[];
+	}>;
+	metaRef: Check<'noOplog' | 'intact' | 'missing' | 'stale'>;
+	recentErrors: Check<string[]>;
+};
+
+/** Collect the state of the project with `projectId` for a bug report. */
+export async function diagnostics(projectId: string) {
+	return await invoke<Diagnostics>('diagnostics', { projectId });
+}
@@ -0,0 +1,42 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
+import { invoke } from '$lib/backend/ipc';
+
+export interface TagAnnotation {
+	tagId: string;
+	message: string;
+	taggerName?: string;
+	/** Seconds since the Unix epoch. */
+	createdAt?: number;
+	/** Whether the tag carries a signature, which isn't verified. */
+	signed: boolean;
+}
+
+export interface Tag {
+	name: string;
+	/** The commit the tag points to. */
+	targetId: string;
+	/** Set for annotated tags. */
+	annotation?: TagAnnotation;
+}
+
+export class TagsService {
+	async tags(projectId: string) {
+		return await invoke<Tag[]>('list_tags', { projectId });
+	}
+
+	/**
+	 * Create a tag pointing to the commit `oid`, annotated if it has a `message` and signed if `sign` is set,
+	 * which requires a message.
+	 */
+	async createTag(projectId: string, name: string, oid: string, message?: string, sign = false) {
+		return await invoke<string>('create_tag', { projectId, name, oid, message, sign });
+	}
+
+	async deleteTag(projectId: string, name: string) {
+		return await invoke<void>('delete_tag', {
+			projectId,
+			name,
+			confirmationToken: await requestConfirmation('deleteTag')
+		});
+	}
+}
@@ -0,0 +1,41 @@
+import { invoke, listen } from '$lib/backend/ipc';
+import type { DownloadEvent, Update } from '@tauri-apps/plugin-updater';
+
+/** The parts of an update of the updater plugin that `UpdaterService` relies on. */
+export type AvailableUpdate = Pick<
+	Update,
+	'available' | 'version' | 'body' | 'currentVersion' | 'download' | 'install'
+>;
+
+type UpdateInfo = {
+	version: string;
+	currentVersion: string;
+	body?: string;
+	date?: string;
+};
+
+/**
+ * Look up an update in the channel chosen in the settings, which the backend verifies the signature of
+ * while downloading. Returns `null` if the app is up to date.
+ */
+export async function checkForUpdates(): Promise<AvailableUpdate | null> {
+	const info = await invoke<UpdateInfo | null>('check_for_updates');
+	if (!info) return null;
+	return {
+		available: true,
+		version: info.version,
+		currentVersion: info.currentVersion,
+		body: info.body,
+		download: async (onEvent?: (progress: DownloadEvent) => void) => {
+			const unlisten = listen<DownloadEvent>('updater://progress', (event) =>
+				onEvent?.(event.payload)
+			);
+			try {
+				await invoke('download_update');
+			} finally {
+				await unlisten();
+			}
+		},
+		install: async () => await invoke('install_update')
+	};
+}
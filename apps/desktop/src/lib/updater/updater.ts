@@ -1,9 +1,10 @@
 import { showToast } from '$lib/notifications/toasts';
 import { relaunch } from '@tauri-apps/plugin-process';
-import { type DownloadEvent, Update } from '@tauri-apps/plugin-updater';
+import { type DownloadEvent } from '@tauri-apps/plugin-updater';
 import { writable } from 'svelte/store';
 import type { PostHogWrapper } from '$lib/analytics/posthog';
 import type { Tauri } from '$lib/backend/tauri';
+import type { AvailableUpdate } from '$lib/updater/backendUpdate';
 
 type UpdateStatus = {
 	version?: string;
@@ -47,8 +48,8 @@ export class UpdaterService {
 
 	private intervalId: any;
 	private seenVersion: string | undefined;
-	private tauriDownload: Update['download'] | undefined;
-	private tauriInstall: Update['install'] | undefined;
+	private tauriDownload: AvailableUpdate['download'] | undefined;
+	private tauriInstall: AvailableUpdate['install'] | undefined;
 
 	unlistenStatus?: () => void;
 	unlistenMenu?: () => void;
@@ -86,7 +87,7 @@ export class UpdaterService {
 		}
 	}
 
-	private handleUpdate(update: Update | null, manual: boolean) {
+	private handleUpdate(update: AvailableUpdate | null, manual: boolean) {
 		if (update === null) {
 			this.update.set({});
 			return;
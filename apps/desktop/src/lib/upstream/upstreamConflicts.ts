@@ -0,0 +1,28 @@
+import { listen } from '$lib/backend/ipc';
+import { readable } from 'svelte/store';
+
+/** A file that changed in the workspace and upstream in ways that can't be merged cleanly. */
+export type RiskyFile = {
+	path: string;
+	/** The stacks whose changes to the file conflict with upstream. */
+	stackIds: string[];
+};
+
+/** Sent after a fetch brought in upstream commits that aren't integrated into the workspace yet. */
+export type UpstreamConflicts = {
+	upstreamCommits: number;
+	riskyFiles: RiskyFile[];
+};
+
+export class UpstreamConflictsSignal {
+	// The prediction made after the most recent fetch, `undefined` until upstream has new commits.
+	readonly conflicts = readable<UpstreamConflicts | undefined>(undefined, (set) => {
+		const unsubscribe = listen<UpstreamConflicts>(
+			`project://${this.projectId}/upstream-conflicts`,
+			(event) => set(event.payload)
+		);
+		return async () => await unsubscribe();
+	});
+
+	constructor(private projectId: string) {}
+}
@@ -58,6 +58,12 @@ export type Resolution = {
 	approach: ResolutionApproach;
 };
 
+export type StackUpdate = {
+	stackId: string;
+	approach: ResolutionApproach;
+	conflicted: boolean;
+};
+
 export type BaseBranchResolutionApproach = 'rebase' | 'merge' | 'hardReset';
 
 export type BaseBranchResolution = {
@@ -157,6 +163,13 @@ export class UpstreamIntegrationService {
 		});
 	}
 
+	async updateFromBase(type: 'rebase' | 'merge') {
+		return await invoke<StackUpdate[]>('update_from_base', {
+			projectId: this.project.id,
+			strategy: { type }
+		});
+	}
+
 	async resolveUpstreamIntegration(type: BaseBranchResolutionApproach) {
 		return await invoke<string>('resolve_upstream_integration', {
 			projectId: this.project.id,
@@ -0,0 +1,11 @@
+import { listen } from '$lib/backend/ipc';
+
+/**
+ * Call `callback` whenever the index of the project changed outside of the app, like when files
+ * were staged or unstaged in a terminal. Returns a function to stop listening.
+ */
+export function listenForIndexChanges(projectId: string, callback: () => void) {
+	return listen<{ projectId: string }>('git://index-changed', (event) => {
+		if (event.payload.projectId === projectId) callback();
+	});
+}
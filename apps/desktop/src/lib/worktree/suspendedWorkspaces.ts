@@ -0,0 +1,36 @@
+import { requestConfirmation } from '$lib/backend/confirmation';
+import { invoke } from '$lib/backend/ipc';
+
+/** Uncommitted changes that were put aside under a name, along with the lanes they were assigned to. */
+export type SuspendedWorkspace = {
+	name: string;
+	/** In seconds since the Unix epoch. */
+	suspendedAt: number;
+	/** The paths of the files with changes that were suspended. */
+	paths: string[];
+};
+
+export type ResumedWorkspace = {
+	/** The paths of the files with changes that were restored. */
+	paths: string[];
+	/** Unset if something was committed since the workspace was suspended, so nothing was staged again. */
+	indexRestored: boolean;
+};
+
+/** Put all uncommitted changes aside as `name`, leaving a clean worktree to work on something else. */
+export async function suspendWorkspace(projectId: string, name: string) {
+	return await invoke<SuspendedWorkspace>('suspend_workspace', {
+		projectId,
+		name,
+		confirmationToken: await requestConfirmation('discard')
+	});
+}
+
+/** Bring back the changes of the suspended workspace `name`, which requires a clean worktree. */
+export async function resumeWorkspace(projectId: string, name: string) {
+	return await invoke<ResumedWorkspace>('resume_workspace', { projectId, name });
+}
+
+export async function listSuspendedWorkspaces(projectId: string) {
+	return await invoke<SuspendedWorkspace[]>('list_suspended_workspaces', { projectId });
+}
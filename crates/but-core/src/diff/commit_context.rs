@@ -0,0 +1,148 @@
+use crate::{unified_diff::DiffHunk, TreeChange, TreeStatusKind, UnifiedDiff};
+use bstr::{BString, ByteSlice};
+use serde::Serialize;
+
+/// A rough estimate of how many bytes of a patch make up one token of a language model.
+pub const BYTES_PER_TOKEN: usize = 4;
+
+/// A compact summary of changes that is suitable for sending to a language model, for instance to draft a commit message.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitContext {
+    /// All changed files, in the order they were given.
+    pub files: Vec<FileContext>,
+    /// If `true`, the patch of at least one file was shortened or left out to stay within the token budget.
+    pub truncated: bool,
+    /// The estimated amount of tokens needed for the context.
+    pub estimated_tokens: usize,
+}
+
+/// A single changed file as part of a [`CommitContext`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileContext {
+    /// The worktree-relative path of the file.
+    #[serde(serialize_with = "gitbutler_serde::bstring_lossy::serialize")]
+    pub path: BString,
+    /// The path the file was previously located at, if it was renamed.
+    #[serde(serialize_with = "gitbutler_serde::bstring_opt_lossy::serialize")]
+    pub previous_path: Option<BString>,
+    pub status: TreeStatusKind,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// The headers of all hunks, like `@@ -1,6 +1,8 @@`, even if the patch was left out.
+    pub hunk_headers: Vec<String>,
+    /// The unified diff of all hunks, cut off at a line boundary if it didn't fit into the token budget,
+    /// or `None` if it didn't fit at all or if the file is binary or too large to diff.
+    pub patch: Option<String>,
+    /// If `true`, `patch` is incomplete or missing even though the file could be diffed.
+    pub patch_truncated: bool,
+}
+
+/// Assemble the [`CommitContext`] for `changes`, obtaining their diffs with `context_lines` from `repo`.
+///
+/// Paths, statistics and hunk headers of all files are always included. The patches then share what remains
+/// of `token_budget` fairly, so a single large change can't crowd out all others.
+pub fn commit_context(
+    repo: &gix::Repository,
+    changes: &[TreeChange],
+    context_lines: u32,
+    token_budget: usize,
+) -> anyhow::Result<CommitContext> {
+    let mut files = Vec::with_capacity(changes.len());
+    let mut patches = Vec::with_capacity(changes.len());
+    for change in changes {
+        let (hunks, patch) = match change.unified_diff(repo, context_lines)? {
+            UnifiedDiff::Patch { hunks } => {
+                let patch = hunks
+                    .iter()
+                    .map(|hunk| hunk.diff.to_str_lossy())
+                    .collect::<String>();
+                (hunks, Some(patch))
+            }
+            UnifiedDiff::Binary | UnifiedDiff::TooLarge { .. } => (Vec::new(), None),
+        };
+        let (lines_added, lines_removed) = count_lines(&hunks);
+        files.push(FileContext {
+            path: change.path.clone(),
+            previous_path: change.previous_path().map(ToOwned::to_owned),
+            status: change.status.kind(),
+            lines_added,
+            lines_removed,
+            hunk_headers: hunks
+                .iter()
+                .filter_map(|hunk| hunk.diff.lines().next())
+                .map(|header| header.to_str_lossy().into_owned())
+                .collect(),
+            patch: None,
+            patch_truncated: false,
+        });
+        patches.push(patch);
+    }
+
+    let mut used_bytes: usize = files.iter().map(summary_len).sum();
+    let mut remaining_bytes = (token_budget * BYTES_PER_TOKEN).saturating_sub(used_bytes);
+    let mut smallest_first: Vec<_> = patches
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, patch)| patch.as_ref().map(|patch| (idx, patch.len())))
+        .collect();
+    smallest_first.sort_by_key(|(_, len)| *len);
+
+    let mut truncated = false;
+    let num_patches = smallest_first.len();
+    for (nth, (idx, _)) in smallest_first.into_iter().enumerate() {
+        let share = remaining_bytes / (num_patches - nth);
+        let patch = patches[idx]
+            .take()
+            .expect("only files with patches are listed");
+        let fitting = truncate_at_line(&patch, share);
+        let file = &mut files[idx];
+        file.patch_truncated = fitting.len() < patch.len();
+        truncated |= file.patch_truncated;
+        remaining_bytes -= fitting.len();
+        used_bytes += fitting.len();
+        file.patch = (!fitting.is_empty()).then(|| fitting.to_owned());
+    }
+
+    Ok(CommitContext {
+        files,
+        truncated,
+        estimated_tokens: used_bytes.div_ceil(BYTES_PER_TOKEN),
+    })
+}
+
+/// Count the added and removed lines of all `hunks`.
+fn count_lines(hunks: &[DiffHunk]) -> (usize, usize) {
+    hunks
+        .iter()
+        .flat_map(|hunk| hunk.diff.lines().skip(1))
+        .fold((0, 0), |(added, removed), line| match line.first() {
+            Some(b'+') => (added + 1, removed),
+            Some(b'-') => (added, removed + 1),
+            _ => (added, removed),
+        })
+}
+
+/// The amount of bytes needed to describe `file` without its patch.
+fn summary_len(file: &FileContext) -> usize {
+    file.path.len()
+        + file.previous_path.as_ref().map_or(0, |path| path.len())
+        + file
+            .hunk_headers
+            .iter()
+            .map(|header| header.len())
+            .sum::<usize>()
+}
+
+/// Return the longest prefix of `patch` that ends at a line boundary and is at most `max_len` bytes long.
+fn truncate_at_line(patch: &str, max_len: usize) -> &str {
+    if patch.len() <= max_len {
+        return patch;
+    }
+    // Cutting after a newline always yields a valid UTF-8 boundary.
+    let end = patch.as_bytes()[..max_len]
+        .rfind_byte(b'\n')
+        .map_or(0, |newline_idx| newline_idx + 1);
+    &patch[..end]
+}
@@ -1,6 +1,12 @@
 pub(crate) mod commit;
 pub use commit::commit_changes;
 
+mod commit_context;
+pub use commit_context::{commit_context, CommitContext, FileContext, BYTES_PER_TOKEN};
+
+mod text;
+pub use text::{compute_diff, DiffAlgorithm, DiffSegment, Granularity, SegmentKind};
+
 mod worktree;
 pub use worktree::worktree_changes;
 
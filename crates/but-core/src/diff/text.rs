@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use gix::diff::blob::intern::{InternedInput, Token, TokenSource};
+use serde::{Deserialize, Serialize};
+
+/// The units in which texts are compared by [`compute_diff()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Granularity {
+    /// Whole lines, including their line ending.
+    Line,
+    /// Runs of alphanumeric characters or of whitespace, with each other character on its own.
+    Word,
+    /// Single characters.
+    Character,
+}
+
+/// How [`compute_diff()`] finds the differences.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffAlgorithm {
+    /// The smallest amount of changes, which may match unrelated tokens like blank lines or braces.
+    #[default]
+    Myers,
+    /// Anchor the diff at tokens that occur exactly once in both texts, which tends to keep moved or rewritten
+    /// blocks together, and compare the texts between them with [Myers](Self::Myers).
+    Patience,
+}
+
+/// Whether a [`DiffSegment`] is in both texts or only in one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SegmentKind {
+    Equal,
+    Removed,
+    Added,
+}
+
+/// A part of the texts compared with [`compute_diff()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSegment {
+    pub kind: SegmentKind,
+    pub text: String,
+}
+
+/// Compare `old` with `new` in units of `granularity` using `algorithm`, and return the segments that are
+/// equal, removed or added, in order.
+///
+/// The old text is the concatenation of all segments that aren't [added](SegmentKind::Added), and the new text
+/// that of all segments that aren't [removed](SegmentKind::Removed). Removals come before additions at the same
+/// position, and adjacent segments of the same kind are merged.
+pub fn compute_diff(
+    old: &str,
+    new: &str,
+    granularity: Granularity,
+    algorithm: DiffAlgorithm,
+) -> Vec<DiffSegment> {
+    let old_tokens = tokenize(old, granularity);
+    let new_tokens = tokenize(new, granularity);
+    let input = InternedInput::new(Tokens(&old_tokens), Tokens(&new_tokens));
+    let mut changes = Vec::new();
+    match algorithm {
+        DiffAlgorithm::Myers => gix::diff::blob::diff(
+            gix::diff::blob::Algorithm::Myers,
+            &input,
+            |before: Range<u32>, after: Range<u32>| changes.push((before, after)),
+        ),
+        DiffAlgorithm::Patience => patience(
+            &input.before,
+            &input.after,
+            input.interner.num_tokens(),
+            (0, 0),
+            &mut changes,
+        ),
+    }
+
+    let mut segments = Vec::new();
+    let mut old_pos = 0;
+    for (before, after) in changes {
+        push_segment(
+            &mut segments,
+            SegmentKind::Equal,
+            &old_tokens[old_pos..before.start as usize],
+        );
+        push_segment(
+            &mut segments,
+            SegmentKind::Removed,
+            &old_tokens[before.start as usize..before.end as usize],
+        );
+        push_segment(
+            &mut segments,
+            SegmentKind::Added,
+            &new_tokens[after.start as usize..after.end as usize],
+        );
+        old_pos = before.end as usize;
+    }
+    push_segment(&mut segments, SegmentKind::Equal, &old_tokens[old_pos..]);
+    segments
+}
+
+fn tokenize(text: &str, granularity: Granularity) -> Vec<&str> {
+    match granularity {
+        Granularity::Line => text.split_inclusive('\n').collect(),
+        Granularity::Character => text
+            .char_indices()
+            .map(|(start, c)| &text[start..start + c.len_utf8()])
+            .collect(),
+        Granularity::Word => {
+            #[derive(PartialEq)]
+            enum Class {
+                Word,
+                Space,
+                Other,
+            }
+            fn class(c: char) -> Class {
+                if c.is_alphanumeric() || c == '_' {
+                    Class::Word
+                } else if c.is_whitespace() {
+                    Class::Space
+                } else {
+                    Class::Other
+                }
+            }
+            let mut tokens = Vec::new();
+            let mut start = 0;
+            let mut previous = None;
+            for (pos, c) in text.char_indices() {
+                let current = class(c);
+                if pos > start && (current == Class::Other || previous.as_ref() != Some(&current)) {
+                    tokens.push(&text[start..pos]);
+                    start = pos;
+                }
+                previous = Some(current);
+            }
+            if start < text.len() {
+                tokens.push(&text[start..]);
+            }
+            tokens
+        }
+    }
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, kind: SegmentKind, tokens: &[&str]) {
+    if tokens.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(last) if last.kind == kind => last.text.extend(tokens.iter().copied()),
+        _ => segments.push(DiffSegment {
+            kind,
+            text: tokens.concat(),
+        }),
+    }
+}
+
+/// Tokens that were already split, for interning.
+struct Tokens<'a, 'b>(&'b [&'a str]);
+
+impl<'a, 'b> TokenSource for Tokens<'a, 'b> {
+    type Token = &'a str;
+    type Tokenizer = std::iter::Copied<std::slice::Iter<'b, &'a str>>;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        self.0.iter().copied()
+    }
+
+    fn estimate_tokens(&self) -> u32 {
+        self.0.len() as u32
+    }
+}
+
+/// Push the changes between `before` and `after` onto `changes` as ranges of removed and added tokens, with
+/// `offset` being the position of both slices in the complete input.
+fn patience(
+    before: &[Token],
+    after: &[Token],
+    num_tokens: u32,
+    offset: (u32, u32),
+    changes: &mut Vec<(Range<u32>, Range<u32>)>,
+) {
+    let prefix = before
+        .iter()
+        .zip(after)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let (before, after) = (&before[prefix..], &after[prefix..]);
+    let suffix = before
+        .iter()
+        .rev()
+        .zip(after.iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let (before, after) = (
+        &before[..before.len() - suffix],
+        &after[..after.len() - suffix],
+    );
+    let offset = (offset.0 + prefix as u32, offset.1 + prefix as u32);
+    if before.is_empty() && after.is_empty() {
+        return;
+    }
+    if before.is_empty() || after.is_empty() {
+        changes.push((
+            offset.0..offset.0 + before.len() as u32,
+            offset.1..offset.1 + after.len() as u32,
+        ));
+        return;
+    }
+
+    let anchors = unique_common_tokens(before, after);
+    if anchors.is_empty() {
+        gix::diff::blob::diff_with_tokens(
+            gix::diff::blob::Algorithm::Myers,
+            before,
+            after,
+            num_tokens,
+            |old: Range<u32>, new: Range<u32>| {
+                changes.push((
+                    old.start + offset.0..old.end + offset.0,
+                    new.start + offset.1..new.end + offset.1,
+                ));
+            },
+        );
+        return;
+    }
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_pos, new_pos) in anchors
+        .into_iter()
+        .chain(std::iter::once((before.len(), after.len())))
+    {
+        patience(
+            &before[old_start..old_pos],
+            &after[new_start..new_pos],
+            num_tokens,
+            (offset.0 + old_start as u32, offset.1 + new_start as u32),
+            changes,
+        );
+        (old_start, new_start) = (old_pos + 1, new_pos + 1);
+    }
+}
+
+/// Return the positions of the longest sequence of tokens that occur exactly once in both `before` and `after`,
+/// in the same order in both.
+fn unique_common_tokens(before: &[Token], after: &[Token]) -> Vec<(usize, usize)> {
+    #[derive(Default)]
+    struct Occurrences {
+        before: usize,
+        after: usize,
+        before_pos: usize,
+        after_pos: usize,
+    }
+    let mut occurrences: HashMap<Token, Occurrences> = HashMap::new();
+    for (pos, token) in before.iter().enumerate() {
+        let entry = occurrences.entry(*token).or_default();
+        entry.before += 1;
+        entry.before_pos = pos;
+    }
+    for (pos, token) in after.iter().enumerate() {
+        if let Some(entry) = occurrences.get_mut(token) {
+            entry.after += 1;
+            entry.after_pos = pos;
+        }
+    }
+    let mut pairs: Vec<_> = occurrences
+        .into_values()
+        .filter(|entry| entry.before == 1 && entry.after == 1)
+        .map(|entry| (entry.before_pos, entry.after_pos))
+        .collect();
+    pairs.sort_unstable();
+
+    // Patience sorting: `piles[n]` is the index of the pair ending the best sequence of length `n + 1`
+    // found so far, the one with the smallest position in `after`.
+    let mut piles: Vec<usize> = Vec::new();
+    let mut predecessors = vec![None; pairs.len()];
+    for (index, (_, after_pos)) in pairs.iter().enumerate() {
+        let pile = piles.partition_point(|&top| pairs[top].1 < *after_pos);
+        predecessors[index] = pile.checked_sub(1).map(|previous| piles[previous]);
+        if pile == piles.len() {
+            piles.push(index);
+        } else {
+            piles[pile] = index;
+        }
+    }
+    let mut sequence = Vec::with_capacity(piles.len());
+    let mut next = piles.last().copied();
+    while let Some(index) = next {
+        sequence.push(pairs[index]);
+        next = predecessors[index];
+    }
+    sequence.reverse();
+    sequence
+}
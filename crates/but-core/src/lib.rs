@@ -176,7 +176,8 @@ pub enum TreeStatus {
 }
 
 /// Like [`TreeStatus`], but distilled down to its variant.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum TreeStatusKind {
     /// Something was added or scheduled to be added.
     Addition,
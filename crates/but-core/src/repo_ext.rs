@@ -70,19 +70,39 @@ impl RepositoryExt for gix::Repository {
 
     fn commit_signatures(&self) -> anyhow::Result<(gix::actor::Signature, gix::actor::Signature)> {
         let repo = gix::open(self.path())?;
+        // The identity pinned for the repository takes precedence over the configured one.
+        let config = self.config_snapshot();
+        let identity = config
+            .string("gitbutler.identity.name")
+            .zip(config.string("gitbutler.identity.email"))
+            .filter(|(name, email)| !name.is_empty() && !email.is_empty());
+        let pinned_signature = |variable_name| {
+            identity.as_ref().map(|(name, email)| {
+                gix::actor::Signature::from(gix::actor::SignatureRef {
+                    name: name.as_ref(),
+                    email: email.as_ref(),
+                    time: commit_time(variable_name),
+                })
+            })
+        };
 
-        let author = repo
-            .author()
-            .transpose()?
-            .context("No author is configured in Git")
-            .context(Code::AuthorMissing)?;
+        let author = match pinned_signature("GIT_AUTHOR_DATE") {
+            Some(author) => author,
+            None => repo
+                .author()
+                .transpose()?
+                .context("No author is configured in Git")
+                .context(Code::AuthorMissing)?
+                .into(),
+        };
 
-        let commit_as_gitbutler = !self
-            .config_snapshot()
+        let commit_as_gitbutler = !config
             .boolean("gitbutler.gitbutlerCommitter")
             .unwrap_or_default();
         let committer = if commit_as_gitbutler {
             committer_signature()
+        } else if let Some(committer) = pinned_signature("GIT_COMMITTER_DATE") {
+            committer
         } else {
             repo.committer()
                 .transpose()?
@@ -90,7 +110,7 @@ impl RepositoryExt for gix::Repository {
                 .unwrap_or_else(committer_signature)
         };
 
-        Ok((author.into(), committer))
+        Ok((author, committer))
     }
 
     fn git_settings(&self) -> anyhow::Result<GitConfigSettings> {
@@ -0,0 +1,71 @@
+use but_core::diff;
+
+use crate::diff::worktree_changes::repo;
+
+#[test]
+fn patches_within_budget() -> anyhow::Result<()> {
+    let repo = repo("added-modified-in-worktree")?;
+    let changes = diff::worktree_changes(&repo)?.changes;
+    let context = diff::commit_context(&repo, &changes, 3, 10_000)?;
+
+    assert_eq!(context.files.len(), changes.len());
+    assert!(!context.truncated, "everything fits");
+    let modified = context
+        .files
+        .iter()
+        .find(|file| file.path == "modified")
+        .expect("modified in worktree");
+    assert_eq!((modified.lines_added, modified.lines_removed), (1, 1));
+    assert_eq!(modified.hunk_headers, ["@@ -1,1 +1,1 @@"]);
+    assert_eq!(
+        modified.patch.as_deref(),
+        Some("@@ -1,1 +1,1 @@\n-something\n+change\n")
+    );
+    assert!(!modified.patch_truncated);
+    Ok(())
+}
+
+#[test]
+fn patches_are_left_out_without_budget() -> anyhow::Result<()> {
+    let repo = repo("added-modified-in-worktree")?;
+    let changes = diff::worktree_changes(&repo)?.changes;
+    let context = diff::commit_context(&repo, &changes, 3, 0)?;
+
+    assert!(context.truncated);
+    let modified = context
+        .files
+        .iter()
+        .find(|file| file.path == "modified")
+        .expect("modified in worktree");
+    assert_eq!(
+        modified.hunk_headers,
+        ["@@ -1,1 +1,1 @@"],
+        "headers and statistics are always present"
+    );
+    assert_eq!((modified.lines_added, modified.lines_removed), (1, 1));
+    assert_eq!(modified.patch, None);
+    assert!(modified.patch_truncated);
+    Ok(())
+}
+
+#[test]
+fn patches_are_cut_at_line_boundaries() -> anyhow::Result<()> {
+    let repo = repo("added-modified-in-worktree")?;
+    let changes: Vec<_> = diff::worktree_changes(&repo)?
+        .changes
+        .into_iter()
+        .filter(|change| change.path == "modified")
+        .collect();
+    let summary_tokens = "modified@@ -1,1 +1,1 @@"
+        .len()
+        .div_ceil(diff::BYTES_PER_TOKEN);
+    let context = diff::commit_context(&repo, &changes, 3, summary_tokens + 7)?;
+
+    assert!(context.truncated);
+    assert_eq!(
+        context.files[0].patch.as_deref(),
+        Some("@@ -1,1 +1,1 @@\n-something\n"),
+        "only full lines are included"
+    );
+    Ok(())
+}
@@ -1,3 +1,5 @@
 mod commit_changes;
+mod commit_context;
+mod text;
 mod ui;
 pub(crate) mod worktree_changes;
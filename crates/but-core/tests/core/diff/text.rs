@@ -0,0 +1,111 @@
+use but_core::diff::{compute_diff, DiffAlgorithm, DiffSegment, Granularity, SegmentKind};
+
+fn render(segments: &[DiffSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment.kind {
+            SegmentKind::Equal => segment.text.clone(),
+            SegmentKind::Removed => format!("[-{}-]", segment.text),
+            SegmentKind::Added => format!("{{+{}+}}", segment.text),
+        })
+        .collect()
+}
+
+fn side(segments: &[DiffSegment], skip: SegmentKind) -> String {
+    segments
+        .iter()
+        .filter(|segment| segment.kind != skip)
+        .map(|segment| segment.text.as_str())
+        .collect()
+}
+
+#[test]
+fn words_changed_within_a_line() {
+    let segments = compute_diff(
+        "let value = compute(old);\n",
+        "let result = compute(new);\n",
+        Granularity::Word,
+        DiffAlgorithm::Myers,
+    );
+    assert_eq!(
+        render(&segments),
+        "let [-value-]{+result+} = compute([-old-]{+new+});\n"
+    );
+}
+
+#[test]
+fn characters_changed_within_a_word() {
+    let segments = compute_diff(
+        "colour",
+        "color",
+        Granularity::Character,
+        DiffAlgorithm::Myers,
+    );
+    assert_eq!(render(&segments), "colo[-u-]r");
+    let segments = compute_diff(
+        "naïve",
+        "naive",
+        Granularity::Character,
+        DiffAlgorithm::Myers,
+    );
+    assert_eq!(
+        render(&segments),
+        "na[-ï-]{+i+}ve",
+        "characters aren't split into bytes"
+    );
+}
+
+#[test]
+fn lines_keep_their_line_endings() {
+    let segments = compute_diff(
+        "a\nb\nc\n",
+        "a\nc\nd",
+        Granularity::Line,
+        DiffAlgorithm::Myers,
+    );
+    assert_eq!(render(&segments), "a\n[-b\n-]c\n{+d+}");
+}
+
+#[test]
+fn both_algorithms_reproduce_the_texts() {
+    let old = "fn a() {\n    one();\n}\n\nfn b() {\n    two();\n}\n";
+    let new = "fn b() {\n    two();\n}\n\nfn a() {\n    one();\n}\n";
+    for algorithm in [DiffAlgorithm::Myers, DiffAlgorithm::Patience] {
+        let segments = compute_diff(old, new, Granularity::Line, algorithm);
+        assert_eq!(side(&segments, SegmentKind::Added), old, "{algorithm:?}");
+        assert_eq!(side(&segments, SegmentKind::Removed), new, "{algorithm:?}");
+    }
+
+    let segments = compute_diff(
+        "fn a() {\n    one();\n}\n",
+        "fn z() {\n}\n\nfn a() {\n    one();\n}\n",
+        Granularity::Line,
+        DiffAlgorithm::Patience,
+    );
+    assert_eq!(
+        render(&segments),
+        "{+fn z() {\n}\n\n+}fn a() {\n    one();\n}\n",
+        "the function that was there stays intact"
+    );
+}
+
+#[test]
+fn identical_and_empty_texts() {
+    assert_eq!(
+        compute_diff("same", "same", Granularity::Word, DiffAlgorithm::Patience),
+        [DiffSegment {
+            kind: SegmentKind::Equal,
+            text: "same".into()
+        }]
+    );
+    assert!(compute_diff("", "", Granularity::Line, DiffAlgorithm::Myers).is_empty());
+    assert_eq!(
+        render(&compute_diff(
+            "",
+            "new\n",
+            Granularity::Line,
+            DiffAlgorithm::Patience
+        )),
+        "{+new\n+}"
+    );
+}
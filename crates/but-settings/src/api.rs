@@ -1,4 +1,7 @@
-use crate::AppSettingsWithDiskSync;
+use crate::{
+    app_settings::{NotificationCategory, UpdateChannel},
+    AppSettingsWithDiskSync, OnboardingStep,
+};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +12,7 @@ pub struct TelemetryUpdate {
     pub app_metrics_enabled: Option<bool>,
     pub app_error_reporting_enabled: Option<bool>,
     pub app_non_anon_metrics_enabled: Option<bool>,
+    pub app_usage_counters_enabled: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -18,6 +22,31 @@ pub struct FeatureFlagsUpdate {
     pub v3: Option<bool>,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+/// Update request for [`crate::app_settings::Concurrency`].
+pub struct ConcurrencyUpdate {
+    pub watcher_workers: Option<usize>,
+    pub command_threads: Option<usize>,
+    pub network_operations: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+/// Update request for [`crate::app_settings::LocalApi`].
+pub struct LocalApiUpdate {
+    pub enabled: Option<bool>,
+    pub port: Option<u16>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+/// Update request for [`crate::app_settings::NotificationSettings`].
+pub struct NotificationsUpdate {
+    pub os_notifications: Option<bool>,
+    pub muted: Option<Vec<NotificationCategory>>,
+}
+
 /// Mutation, immediately followed by writing everything to disk.
 impl AppSettingsWithDiskSync {
     pub fn update_onboarding_complete(&self, update: bool) -> Result<()> {
@@ -26,6 +55,17 @@ impl AppSettingsWithDiskSync {
         settings.save()
     }
 
+    /// Mark `step` of the onboarding flow as completed, and complete the onboarding entirely
+    /// once all steps are done.
+    pub fn complete_onboarding_step(&self, step: OnboardingStep) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        step.complete(&mut settings.onboarding_steps);
+        if settings.onboarding_state().next_step.is_none() {
+            settings.onboarding_complete = true;
+        }
+        settings.save()
+    }
+
     pub fn update_telemetry(&self, update: TelemetryUpdate) -> Result<()> {
         let mut settings = self.get_mut_enforce_save()?;
         if let Some(app_metrics_enabled) = update.app_metrics_enabled {
@@ -37,6 +77,9 @@ impl AppSettingsWithDiskSync {
         if let Some(app_non_anon_metrics_enabled) = update.app_non_anon_metrics_enabled {
             settings.telemetry.app_non_anon_metrics_enabled = app_non_anon_metrics_enabled;
         }
+        if let Some(app_usage_counters_enabled) = update.app_usage_counters_enabled {
+            settings.telemetry.app_usage_counters_enabled = app_usage_counters_enabled;
+        }
         settings.save()
     }
 
@@ -47,4 +90,66 @@ impl AppSettingsWithDiskSync {
         }
         settings.save()
     }
+
+    pub fn update_concurrency(&self, update: ConcurrencyUpdate) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        if let Some(watcher_workers) = update.watcher_workers {
+            settings.concurrency.watcher_workers = watcher_workers;
+        }
+        if let Some(command_threads) = update.command_threads {
+            settings.concurrency.command_threads = command_threads;
+        }
+        if let Some(network_operations) = update.network_operations {
+            settings.concurrency.network_operations = network_operations;
+        }
+        settings.save()
+    }
+
+    pub fn update_project_deletion_grace_period(&self, seconds: u64) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        settings.project_deletion_grace_period_seconds = seconds;
+        settings.save()
+    }
+
+    pub fn update_local_api(&self, update: LocalApiUpdate) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        if let Some(enabled) = update.enabled {
+            settings.local_api.enabled = enabled;
+        }
+        if let Some(port) = update.port {
+            settings.local_api.port = port;
+        }
+        settings.save()
+    }
+
+    pub fn update_watcher_idle_timeout(&self, seconds: u64) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        settings.watcher_idle_timeout_seconds = seconds;
+        settings.save()
+    }
+
+    pub fn update_background_mode(&self, enabled: bool) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        settings.background_mode = enabled;
+        settings.save()
+    }
+
+    pub fn set_update_channel(&self, channel: Option<UpdateChannel>) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        settings.update_channel = channel;
+        settings.save()
+    }
+
+    pub fn update_notifications(&self, update: NotificationsUpdate) -> Result<()> {
+        let mut settings = self.get_mut_enforce_save()?;
+        if let Some(os_notifications) = update.os_notifications {
+            settings.notifications.os_notifications = os_notifications;
+        }
+        if let Some(mut muted) = update.muted {
+            muted.sort();
+            muted.dedup();
+            settings.notifications.muted = muted;
+        }
+        settings.save()
+    }
 }
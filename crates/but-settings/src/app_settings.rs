@@ -9,6 +9,9 @@ pub struct TelemetrySettings {
     pub app_error_reporting_enabled: bool,
     /// Whether non-anonymous metrics are enabled.
     pub app_non_anon_metrics_enabled: bool,
+    /// Whether anonymous usage counters of the backend, like how often commands ran and how long they took,
+    /// are collected. This is off unless the user opts in.
+    pub app_usage_counters_enabled: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -24,3 +27,75 @@ pub struct FeatureFlags {
     /// Enables the v3 design, as well as the purgatory mode (no uncommitted diff ownership assignments).
     pub v3: bool,
 }
+
+/// Which builds the app updates to.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateChannel {
+    /// Released builds.
+    Stable,
+    /// Builds of the latest changes, made every night.
+    Nightly,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingSteps {
+    /// Whether the user has configured their Git identity, i.e. name and email.
+    pub identity_configured: bool,
+    /// Whether the first project was added.
+    pub first_project_added: bool,
+    /// Whether the keys used for pushing and fetching were verified to work.
+    pub keys_verified: bool,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApi {
+    /// Whether to serve the API for editor integrations on a localhost port. Takes effect after a restart.
+    pub enabled: bool,
+    /// The port to listen on. `0` picks a free port, which is written to `local-api.json` in the app data directory.
+    pub port: u16,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Concurrency {
+    /// The maximum amount of filesystem events to process at the same time. `0` picks a value based on the number of CPUs.
+    pub watcher_workers: usize,
+    /// The maximum amount of heavy read commands, like computing diffs or listing branches, to run at the same time.
+    /// `0` picks a value based on the number of CPUs.
+    pub command_threads: usize,
+    /// The maximum amount of fetches and pushes to run at the same time. `0` picks a value based on the number of CPUs.
+    pub network_operations: usize,
+}
+
+/// What a notification is about, to mute by.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationCategory {
+    /// The watcher of a project failed, and changes may not be recorded anymore.
+    WatcherStopped,
+    /// A push was rejected by the remote.
+    PushRejected,
+    /// Damaged history or storage of a project was repaired.
+    HistoryRepaired,
+    /// The disk holding the app data is nearly full.
+    DiskSpaceLow,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettings {
+    /// Whether to show notifications of the operating system as well, even when no window is focused.
+    pub os_notifications: bool,
+    /// The categories of notifications that are never shown.
+    pub muted: Vec<NotificationCategory>,
+}
+
+impl NotificationSettings {
+    /// Return `true` if notifications of `category` are shown.
+    pub fn is_enabled(&self, category: NotificationCategory) -> bool {
+        !self.muted.contains(&category)
+    }
+}
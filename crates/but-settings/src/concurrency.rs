@@ -0,0 +1,95 @@
+use std::sync::Mutex;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::app_settings::Concurrency;
+
+impl Concurrency {
+    /// The maximum amount of filesystem events to process at the same time.
+    pub fn effective_watcher_workers(&self) -> usize {
+        resolve(self.watcher_workers, |cpus| (cpus / 2).max(1))
+    }
+
+    /// The maximum amount of heavy read commands, like computing diffs or listing branches, to run at the same time.
+    pub fn effective_command_threads(&self) -> usize {
+        resolve(self.command_threads, |cpus| cpus)
+    }
+
+    /// The maximum amount of fetches and pushes to run at the same time.
+    pub fn effective_network_operations(&self) -> usize {
+        resolve(self.network_operations, |cpus| cpus.clamp(2, 4))
+    }
+}
+
+/// Use `configured` unless it's `0`, in which case the value is derived from the number of CPUs.
+fn resolve(configured: usize, from_cpus: impl FnOnce(usize) -> usize) -> usize {
+    if configured != 0 {
+        return configured;
+    }
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    from_cpus(cpus)
+}
+
+/// Limit the amount of operations running at the same time, with a limit that may change between calls
+/// as it's typically read from the [settings](crate::AppSettings).
+///
+/// Waiting for [`acquire()`](Self::acquire()) doesn't occupy a thread, so work meant for blocking threads should
+/// only be spawned once it got its permit.
+#[derive(Debug)]
+pub struct Limiter {
+    semaphore: Semaphore,
+    /// The amount of permits the semaphore holds, which is more than the current limit until the permits that
+    /// were in use when it was lowered are returned.
+    permits: Mutex<usize>,
+}
+
+/// Counts as running operation of its [`Limiter`] until dropped.
+pub type LimiterGuard<'a> = SemaphorePermit<'a>;
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Limiter::new()
+    }
+}
+
+impl Limiter {
+    pub const fn new() -> Self {
+        Limiter {
+            semaphore: Semaphore::const_new(0),
+            permits: Mutex::new(0),
+        }
+    }
+
+    /// Wait until less than `limit` operations are running, and return a guard that counts as running
+    /// operation until it's dropped. A `limit` of `0` is treated as `1`.
+    pub async fn acquire(&self, limit: usize) -> LimiterGuard<'_> {
+        let limit = limit.max(1);
+        {
+            let mut permits = self.permits.lock().unwrap_or_else(|err| err.into_inner());
+            if limit > *permits {
+                self.semaphore.add_permits(limit - *permits);
+                *permits = limit;
+            }
+        }
+        loop {
+            let permit = self
+                .semaphore
+                .acquire()
+                .await
+                .expect("the semaphore is never closed");
+            let mut permits = self.permits.lock().unwrap_or_else(|err| err.into_inner());
+            if *permits <= limit {
+                return permit;
+            }
+            // The limit was lowered since this permit was added, so it's one too many.
+            permit.forget();
+            *permits -= 1;
+        }
+    }
+
+    /// Like [`acquire()`](Self::acquire()), but block the current thread while waiting, for synchronous code
+    /// that already runs on a thread of its own.
+    pub fn acquire_blocking(&self, limit: usize) -> LimiterGuard<'_> {
+        futures::executor::block_on(self.acquire(limit))
+    }
+}
@@ -11,12 +11,28 @@ pub struct AppSettings {
     pub context_lines: u32,
     /// Whether the user has passed the onboarding flow.
     pub onboarding_complete: bool,
+    /// The steps of the onboarding flow the user has completed so far.
+    pub onboarding_steps: app_settings::OnboardingSteps,
     /// Telemetry settings
     pub telemetry: app_settings::TelemetrySettings,
     /// Client ID for the GitHub OAuth application.
     pub github_oauth_app: app_settings::GitHubOAuthAppSettings,
     /// Application feature flags.
     pub feature_flags: app_settings::FeatureFlags,
+    /// Limits to how much work may be done at the same time.
+    pub concurrency: app_settings::Concurrency,
+    /// How long to keep the data of deleted projects around so their deletion can be undone, in seconds.
+    pub project_deletion_grace_period_seconds: u64,
+    /// How long a project may be inactive before its watcher is stopped, in seconds. `0` keeps watchers running.
+    pub watcher_idle_timeout_seconds: u64,
+    /// Whether to keep recording changes to the projects of closed windows in the background, with a tray icon.
+    pub background_mode: bool,
+    /// The channel to update from, or `None` to stay on the one the app was built for.
+    pub update_channel: Option<app_settings::UpdateChannel>,
+    /// The API on a localhost port for editor integrations.
+    pub local_api: app_settings::LocalApi,
+    /// Which alerts about problems, like a failing watcher or a rejected push, to show and how.
+    pub notifications: app_settings::NotificationSettings,
 }
 
 impl Default for AppSettings {
@@ -36,3 +52,9 @@ mod watch;
 pub use watch::AppSettingsWithDiskSync;
 
 pub mod api;
+
+mod concurrency;
+pub use concurrency::{Limiter, LimiterGuard};
+
+mod onboarding;
+pub use onboarding::{OnboardingState, OnboardingStep};
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{app_settings::OnboardingSteps, AppSettings};
+
+/// A step of the onboarding flow, in the order in which they are presented to the user.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OnboardingStep {
+    IdentityConfigured,
+    FirstProjectAdded,
+    KeysVerified,
+}
+
+impl OnboardingStep {
+    /// All steps in the order they should be completed.
+    pub const ALL: [OnboardingStep; 3] = [
+        OnboardingStep::IdentityConfigured,
+        OnboardingStep::FirstProjectAdded,
+        OnboardingStep::KeysVerified,
+    ];
+
+    /// Return `true` if this step is marked as done in `steps`.
+    pub fn is_completed(&self, steps: &OnboardingSteps) -> bool {
+        match self {
+            OnboardingStep::IdentityConfigured => steps.identity_configured,
+            OnboardingStep::FirstProjectAdded => steps.first_project_added,
+            OnboardingStep::KeysVerified => steps.keys_verified,
+        }
+    }
+
+    pub(crate) fn complete(&self, steps: &mut OnboardingSteps) {
+        match self {
+            OnboardingStep::IdentityConfigured => steps.identity_configured = true,
+            OnboardingStep::FirstProjectAdded => steps.first_project_added = true,
+            OnboardingStep::KeysVerified => steps.keys_verified = true,
+        }
+    }
+}
+
+/// The state of the onboarding flow, for the frontend to know where to resume it.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingState {
+    /// Whether the user has passed the onboarding flow, either by completing all steps or by skipping it.
+    pub complete: bool,
+    /// All steps that were completed so far.
+    pub completed_steps: Vec<OnboardingStep>,
+    /// The first step that wasn't completed yet, or `None` if all steps are done.
+    pub next_step: Option<OnboardingStep>,
+}
+
+impl AppSettings {
+    /// Derive the current state of the onboarding flow from the persisted steps.
+    pub fn onboarding_state(&self) -> OnboardingState {
+        let steps = &self.onboarding_steps;
+        OnboardingState {
+            complete: self.onboarding_complete,
+            completed_steps: OnboardingStep::ALL
+                .into_iter()
+                .filter(|step| step.is_completed(steps))
+                .collect(),
+            next_step: OnboardingStep::ALL
+                .into_iter()
+                .find(|step| !step.is_completed(steps)),
+        }
+    }
+}
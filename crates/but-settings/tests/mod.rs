@@ -8,9 +8,98 @@ fn test_load_settings() {
     assert_eq!(settings.telemetry.app_metrics_enabled, false); // modified
     assert_eq!(settings.telemetry.app_error_reporting_enabled, true); // default
     assert_eq!(settings.telemetry.app_non_anon_metrics_enabled, false); // default
+    assert_eq!(settings.telemetry.app_usage_counters_enabled, false); // default
     assert_eq!(settings.onboarding_complete, false); // default
     assert_eq!(
         settings.github_oauth_app.oauth_client_id,
         "cd51880daa675d9e6452"
     ); // default
+    assert!(settings.notifications.os_notifications); // default
+    assert!(settings.notifications.muted.is_empty()); // default
+}
+
+#[test]
+fn onboarding_state_resumes_at_first_incomplete_step() {
+    use but_settings::OnboardingStep;
+
+    let mut settings = AppSettings::default();
+    let state = settings.onboarding_state();
+    assert!(!state.complete);
+    assert!(state.completed_steps.is_empty());
+    assert_eq!(state.next_step, Some(OnboardingStep::IdentityConfigured));
+
+    settings.onboarding_steps.identity_configured = true;
+    settings.onboarding_steps.keys_verified = true;
+    let state = settings.onboarding_state();
+    assert_eq!(
+        state.completed_steps,
+        [
+            OnboardingStep::IdentityConfigured,
+            OnboardingStep::KeysVerified
+        ]
+    );
+    assert_eq!(
+        state.next_step,
+        Some(OnboardingStep::FirstProjectAdded),
+        "skipped steps are picked up first"
+    );
+}
+
+#[test]
+fn concurrency_defaults_scale_with_cpus() {
+    let mut settings = AppSettings::default();
+    assert_eq!(
+        settings.concurrency.watcher_workers, 0,
+        "automatic by default"
+    );
+    assert!(settings.concurrency.effective_watcher_workers() >= 1);
+    assert!(settings.concurrency.effective_command_threads() >= 1);
+    assert!((2..=4).contains(&settings.concurrency.effective_network_operations()));
+
+    settings.concurrency.network_operations = 1;
+    assert_eq!(settings.concurrency.effective_network_operations(), 1);
+}
+
+#[test]
+fn limiter_allows_up_to_limit() {
+    let limiter = but_settings::Limiter::new();
+    let first = limiter.acquire_blocking(2);
+    let second = limiter.acquire_blocking(2);
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let _third = limiter.acquire_blocking(2);
+            tx.send(()).unwrap();
+        });
+        assert!(
+            rx.recv_timeout(std::time::Duration::from_millis(50))
+                .is_err(),
+            "blocked while at the limit"
+        );
+        drop(first);
+        rx.recv().unwrap();
+    });
+    drop(second);
+}
+
+#[test]
+fn limiter_follows_a_lowered_limit() {
+    let limiter = but_settings::Limiter::new();
+    let first = limiter.acquire_blocking(2);
+    let second = limiter.acquire_blocking(2);
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let _third = limiter.acquire_blocking(1);
+            tx.send(()).unwrap();
+        });
+        drop(first);
+        assert!(
+            rx.recv_timeout(std::time::Duration::from_millis(50))
+                .is_err(),
+            "one operation still runs, which is the new limit"
+        );
+        drop(second);
+        rx.recv().unwrap();
+    });
 }
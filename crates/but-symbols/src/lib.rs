@@ -0,0 +1,232 @@
+#![deny(missing_docs, rust_2018_idioms)]
+//! Map changed lines of source files to the code symbols they belong to, like functions and types,
+//! to describe changes as "edited `parse_config()` and `Watcher::run()`" rather than by line numbers.
+//!
+//! Parsing is done with tree-sitter for a handful of popular languages, see [`Language`].
+//! Files in other languages simply yield no symbols.
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// A language we can find symbols in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl Language {
+    /// Guess the language of the file at `path` by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Some(match path.extension()?.to_str()? {
+            "rs" => Language::Rust,
+            "py" => Language::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Language::JavaScript,
+            "ts" | "mts" | "cts" => Language::TypeScript,
+            "tsx" => Language::Tsx,
+            "go" => Language::Go,
+            _ => return None,
+        })
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Language::Python => tree_sitter_python::LANGUAGE.into(),
+            Language::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Language::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Language::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Language::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// The separator between the names of nested symbols.
+    fn separator(&self) -> &'static str {
+        match self {
+            Language::Rust => "::",
+            _ => ".",
+        }
+    }
+}
+
+/// What kind of code construct a [`Symbol`] is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A function or method.
+    Function,
+    /// A type, class, trait or module.
+    Container,
+}
+
+/// A named code construct that contains changed lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name of the symbol, qualified by the names of the symbols it is nested in, like `Watcher::run`.
+    pub name: String,
+    /// What kind of symbol this is.
+    pub kind: SymbolKind,
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            SymbolKind::Function => write!(f, "{}()", self.name),
+            SymbolKind::Container => f.write_str(&self.name),
+        }
+    }
+}
+
+/// Return the innermost symbols of `source` of the file at `path` that contain any of the zero-based `lines`.
+///
+/// Symbols are returned in order of appearance and without duplicates. If the language of `path`
+/// isn't supported, no symbols are returned.
+pub fn symbols_at_lines(
+    path: &Path,
+    source: &[u8],
+    lines: impl IntoIterator<Item = Range<usize>>,
+) -> Result<Vec<Symbol>> {
+    let Some(language) = Language::from_path(path) else {
+        return Ok(Vec::new());
+    };
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&language.grammar())?;
+    let tree = parser
+        .parse(source, None)
+        .with_context(|| format!("Failed to parse '{}'", path.display()))?;
+    let root = tree.root_node();
+
+    let mut symbols: Vec<(usize, Symbol)> = Vec::new();
+    for range in lines {
+        let last_line = range.end.saturating_sub(1).max(range.start);
+        for row in [range.start, last_line] {
+            let point = tree_sitter::Point { row, column: 0 };
+            let Some(node) = root.descendant_for_point_range(point, point) else {
+                continue;
+            };
+            if let Some((start_byte, symbol)) = enclosing_symbol(language, node, source) {
+                if !symbols.iter().any(|(_, existing)| *existing == symbol) {
+                    symbols.push((start_byte, symbol));
+                }
+            }
+        }
+    }
+    symbols.sort_by_key(|(start_byte, _)| *start_byte);
+    Ok(symbols.into_iter().map(|(_, symbol)| symbol).collect())
+}
+
+/// Describe `symbols` for humans, like "parse_config() and Watcher::run()", listing at most `max` of them.
+pub fn describe(symbols: &[Symbol], max: usize) -> Option<String> {
+    let shown: Vec<_> = symbols.iter().take(max).map(ToString::to_string).collect();
+    let remaining = symbols.len() - shown.len();
+    Some(match (shown.as_slice(), remaining) {
+        ([], _) => return None,
+        ([only], 0) => only.clone(),
+        ([init @ .., last], 0) => format!("{} and {last}", init.join(", ")),
+        (shown, remaining) => format!("{} and {remaining} more", shown.join(", ")),
+    })
+}
+
+/// Find the innermost definition that contains `node`, and qualify its name with the names of all definitions
+/// it's nested in. Returns the start byte of the innermost definition as well.
+fn enclosing_symbol(
+    language: Language,
+    node: tree_sitter::Node<'_>,
+    source: &[u8],
+) -> Option<(usize, Symbol)> {
+    let mut names = Vec::new();
+    let mut innermost = None;
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if let Some((name, kind)) = definition(language, node, source) {
+            innermost.get_or_insert((node.start_byte(), kind));
+            names.push(name);
+        }
+        current = node.parent();
+    }
+    let (start_byte, kind) = innermost?;
+    names.reverse();
+    Some((
+        start_byte,
+        Symbol {
+            name: names.join(language.separator()),
+            kind,
+        },
+    ))
+}
+
+/// If `node` defines a symbol in `language`, return its name and kind.
+fn definition(
+    language: Language,
+    node: tree_sitter::Node<'_>,
+    source: &[u8],
+) -> Option<(String, SymbolKind)> {
+    use SymbolKind::*;
+    let (name_field, kind) = match (language, node.kind()) {
+        (Language::Rust, "function_item" | "function_signature_item") => ("name", Function),
+        (Language::Rust, "impl_item") => ("type", Container),
+        (
+            Language::Rust,
+            "struct_item" | "enum_item" | "trait_item" | "mod_item" | "union_item",
+        ) => ("name", Container),
+        (Language::Python, "function_definition") => ("name", Function),
+        (Language::Python, "class_definition") => ("name", Container),
+        (
+            Language::JavaScript | Language::TypeScript | Language::Tsx,
+            "function_declaration" | "generator_function_declaration" | "method_definition",
+        ) => ("name", Function),
+        (
+            Language::JavaScript | Language::TypeScript | Language::Tsx,
+            "class_declaration" | "abstract_class_declaration" | "interface_declaration",
+        ) => ("name", Container),
+        (Language::JavaScript | Language::TypeScript | Language::Tsx, "variable_declarator") => {
+            // Only functions assigned to variables, like `const f = () => {}`, are symbols of interest.
+            let value = node.child_by_field_name("value")?;
+            if !matches!(
+                value.kind(),
+                "arrow_function" | "function_expression" | "function"
+            ) {
+                return None;
+            }
+            ("name", Function)
+        }
+        (Language::Go, "function_declaration") => ("name", Function),
+        (Language::Go, "method_declaration") => {
+            let name = node_text(node.child_by_field_name("name")?, source)?;
+            let receiver_type = node
+                .child_by_field_name("receiver")
+                .and_then(|receiver| find_type_identifier(receiver, source));
+            let name = match receiver_type {
+                Some(receiver_type) => format!("{receiver_type}.{name}"),
+                None => name,
+            };
+            return Some((name, Function));
+        }
+        (Language::Go, "type_spec") => ("name", Container),
+        _ => return None,
+    };
+    let name = node_text(node.child_by_field_name(name_field)?, source)?;
+    Some((name, kind))
+}
+
+/// Return the first type identifier within `node`, like `Watcher` in the Go receiver `(w *Watcher)`.
+fn find_type_identifier(node: tree_sitter::Node<'_>, source: &[u8]) -> Option<String> {
+    if node.kind() == "type_identifier" {
+        return node_text(node, source);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<_> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_type_identifier(child, source))
+}
+
+fn node_text(node: tree_sitter::Node<'_>, source: &[u8]) -> Option<String> {
+    node.utf8_text(source).ok().map(ToOwned::to_owned)
+}
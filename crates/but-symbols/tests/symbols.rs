@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use but_symbols::{describe, symbols_at_lines, Symbol, SymbolKind};
+
+fn names(symbols: &[Symbol]) -> Vec<String> {
+    symbols.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn rust_functions_and_methods() -> anyhow::Result<()> {
+    let source = b"fn parse_config() {
+    let a = 1;
+}
+
+struct Watcher;
+
+impl Watcher {
+    fn run(&self) {
+        todo!()
+    }
+}
+";
+    let symbols = symbols_at_lines(Path::new("src/lib.rs"), source, [1..2, 8..9])?;
+    assert_eq!(names(&symbols), ["parse_config()", "Watcher::run()"]);
+    assert_eq!(symbols[1].kind, SymbolKind::Function);
+
+    let symbols = symbols_at_lines(Path::new("src/lib.rs"), source, [4..5])?;
+    assert_eq!(names(&symbols), ["Watcher"], "types are containers");
+    Ok(())
+}
+
+#[test]
+fn python_and_typescript() -> anyhow::Result<()> {
+    let source = b"class Parser:
+    def parse(self):
+        return 1
+";
+    let symbols = symbols_at_lines(Path::new("parser.py"), source, [2..3])?;
+    assert_eq!(names(&symbols), ["Parser.parse()"]);
+
+    let source = b"export const load = () => {
+  return 1;
+};
+";
+    let symbols = symbols_at_lines(Path::new("load.ts"), source, [1..2])?;
+    assert_eq!(names(&symbols), ["load()"]);
+    Ok(())
+}
+
+#[test]
+fn changes_outside_of_symbols_and_unknown_languages() -> anyhow::Result<()> {
+    let source = b"use std::fmt;\n\nfn f() {}\n";
+    assert!(symbols_at_lines(Path::new("lib.rs"), source, [0..1])?.is_empty());
+    assert!(symbols_at_lines(Path::new("notes.txt"), source, [2..3])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn describe_lists_symbols() {
+    let symbol = |name: &str| Symbol {
+        name: name.into(),
+        kind: SymbolKind::Function,
+    };
+    assert_eq!(describe(&[], 3), None);
+    assert_eq!(describe(&[symbol("a")], 3).as_deref(), Some("a()"));
+    assert_eq!(
+        describe(&[symbol("a"), symbol("b"), symbol("c")], 3).as_deref(),
+        Some("a(), b() and c()")
+    );
+    assert_eq!(
+        describe(&[symbol("a"), symbol("b"), symbol("c")], 2).as_deref(),
+        Some("a(), b() and 1 more")
+    );
+}
@@ -921,6 +921,7 @@ mod utils {
             created_timestamp_ms: 0,
             updated_timestamp_ms: 0,
             ownership: Default::default(),
+            locked_ownership: Default::default(),
             order: 0,
             selected_for_changes: None,
             allow_rebasing: false,
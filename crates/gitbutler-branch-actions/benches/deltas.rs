@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use gitbutler_oplog::deltas::{self, Delta};
+use gitbutler_project::machine_changes;
+use gitbutler_testsupport::{paths, TestProject};
+
+pub fn benchmark_reconstruction(c: &mut Criterion) {
+    const NUM_DELTAS: i64 = 2_000;
+    let data_dir = paths::data_dir();
+    let projects = gitbutler_project::Controller::from_path(data_dir.path());
+    let test_project = TestProject::default();
+    let project = projects.add(test_project.path()).unwrap();
+
+    let file = Path::new("file.txt");
+    for at in 0..NUM_DELTAS {
+        std::fs::write(test_project.path().join(file), format!("version {at}\n")).unwrap();
+        let paths = vec![file.to_owned()];
+        let classification =
+            machine_changes::classify(project.id, &project.change_classification_rules, &paths);
+        deltas::record_delta(
+            &project,
+            Delta {
+                at,
+                paths,
+                classification,
+                branch: None,
+                checkpoint: None,
+                contents: Vec::new(),
+                bulk: false,
+                renames: Vec::new(),
+            },
+        )
+        .unwrap();
+    }
+
+    // Typing into a file is sealed into a few compressed segments, whose size is what the deltas take to store.
+    let profile = deltas::profile_reconstruction(&project, file).unwrap();
+    println!(
+        "{NUM_DELTAS} deltas take {} bytes to store, and {} bytes as JSON",
+        profile.file_bytes, profile.json_bytes
+    );
+
+    let mut group = c.benchmark_group("reconstruction");
+    group.throughput(Throughput::Elements(NUM_DELTAS as u64));
+    group
+        .bench_function("list deltas", |b| {
+            b.iter(|| deltas::list_deltas(black_box(&project), 0..NUM_DELTAS, None, None).unwrap())
+        })
+        .bench_function("blob at the middle", |b| {
+            b.iter(|| deltas::blob_at(black_box(&project), file, NUM_DELTAS / 2).unwrap())
+        })
+        .bench_function("profile without cache", |b| {
+            b.iter(|| deltas::profile_reconstruction(black_box(&project), file).unwrap())
+        });
+}
+
+criterion_group!(benches, benchmark_reconstruction);
+criterion_main!(benches);
@@ -1,12 +1,16 @@
 use super::r#virtual as vbranch;
+use crate::branch_cleanup::{self, StaleBranch};
 use crate::branch_upstream_integration;
 use crate::branch_upstream_integration::IntegrationStrategy;
+use crate::cherry_pick::{self, CherryPickOutcome};
+use crate::interactive_rebase::{self, PendingRebase, RebaseInstruction, RebaseStatus};
 use crate::move_commits;
 use crate::r#virtual::StackListResult;
 use crate::reorder::{self, StackOrder};
+use crate::rewrite;
 use crate::upstream_integration::{
-    self, BaseBranchResolution, BaseBranchResolutionApproach, Resolution, StackStatuses,
-    UpstreamIntegrationContext,
+    self, BaseBranchResolution, BaseBranchResolutionApproach, Resolution, ResolutionApproach,
+    StackStatuses, StackUpdate, UpstreamIntegrationContext,
 };
 use crate::VirtualBranchHunkRangeMap;
 use crate::{
@@ -18,23 +22,25 @@ use crate::{
     remote::{RemoteBranchData, RemoteCommit},
     VirtualBranchesExt,
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use but_workspace::StackEntry;
 use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
 use gitbutler_command_context::CommandContext;
 use gitbutler_diff::DiffByPathMap;
 use gitbutler_operating_modes::assure_open_workspace_mode;
 use gitbutler_oplog::{
-    entry::{OperationKind, SnapshotDetails},
-    OplogExt, SnapshotExt,
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    journal, OplogExt, SnapshotExt,
 };
 use gitbutler_oxidize::OidExt;
 use gitbutler_project::FetchResult;
 use gitbutler_reference::{ReferenceName, Refname, RemoteRefname};
-use gitbutler_repo::RepositoryExt;
+use gitbutler_repo::{commit_message, RepositoryExt};
 use gitbutler_repo_actions::RepoActionsExt;
 use gitbutler_stack::{BranchOwnershipClaims, StackId};
+use itertools::Itertools;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::instrument;
 
 pub fn create_commit(
@@ -45,20 +51,60 @@ pub fn create_commit(
 ) -> Result<git2::Oid> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Creating a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-    let result = vbranch::commit(ctx, stack_id, message, ownership);
+    ctx.ensure_unprotected_stack(
+        &ctx.project()
+            .virtual_branches()
+            .get_stack_in_workspace(stack_id)?,
+        "commit to",
+    )?;
+    commit_message::ensure_valid(message, ctx.project().conventional_commits)?;
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("message", message.to_owned()),
+    ];
+    journaled(ctx, OperationKind::CreateCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let result = vbranch::commit(ctx, stack_id, message, ownership);
+
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_commit_creation(
+                snapshot_tree,
+                result.as_ref().err(),
+                message.to_owned(),
+                None,
+                guard.write_permission(),
+            )
+        });
+
+        result
+    })
+}
 
-    let _ = snapshot_tree.and_then(|snapshot_tree| {
-        ctx.project().snapshot_commit_creation(
-            snapshot_tree,
+/// Run `operation`, called with `parameters`, and record it in the [journal](journal) along with the branches it
+/// moved, so it shows in the audit trail and can be undone.
+pub(crate) fn journaled<T>(
+    ctx: &CommandContext,
+    kind: OperationKind,
+    parameters: impl IntoIterator<Item = (&'static str, String)>,
+    operation: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let before = journal::RefState::capture(ctx.project());
+    let result = operation();
+    let recorded = before.and_then(|before| {
+        let after = journal::RefState::capture(ctx.project())?;
+        journal::record(
+            ctx.project(),
+            kind,
+            parameters,
+            &before,
+            &after,
             result.as_ref().err(),
-            message.to_owned(),
-            None,
-            guard.write_permission(),
         )
     });
-
+    if let Err(err) = recorded {
+        tracing::warn!(?err, "failed to record operation in journal");
+    }
     result
 }
 
@@ -102,13 +148,16 @@ pub fn create_virtual_branch(
 ) -> Result<StackEntry> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Creating a branch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let branch_manager = ctx.branch_manager();
-    let stack = branch_manager.create_virtual_branch(create, guard.write_permission())?;
-    Ok(StackEntry {
-        id: stack.id,
-        branch_names: stack.heads().into_iter().map(Into::into).collect(),
-        tip: stack.head().to_gix(),
+    let parameters = create.name.iter().map(|name| ("name", name.clone()));
+    journaled(ctx, OperationKind::CreateBranch, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let branch_manager = ctx.branch_manager();
+        let stack = branch_manager.create_virtual_branch(create, guard.write_permission())?;
+        Ok(StackEntry {
+            id: stack.id,
+            branch_names: stack.heads().into_iter().map(Into::into).collect(),
+            tip: stack.head().to_gix(),
+        })
     })
 }
 
@@ -121,31 +170,34 @@ pub fn delete_local_branch(
     given_name: String,
 ) -> Result<()> {
     ctx.verify()?;
-    let repo = ctx.repo();
-    let handle = ctx.project().virtual_branches();
-    let stack = handle.list_all_stacks()?.into_iter().find(|stack| {
-        stack
-            .source_refname
-            .as_ref()
-            .is_some_and(|source_refname| source_refname == refname)
-    });
-
-    if let Some(stack) = stack {
-        // Disallow deletion of branches that are applied in workspace
-        if stack.in_workspace {
-            return Err(anyhow::anyhow!(
-                "Cannot delete a branch that is applied in workspace"
-            ));
+    let parameters = [("branch", given_name.clone())];
+    journaled(ctx, OperationKind::DeleteBranch, parameters, || {
+        let repo = ctx.repo();
+        let handle = ctx.project().virtual_branches();
+        let stack = handle.list_all_stacks()?.into_iter().find(|stack| {
+            stack
+                .source_refname
+                .as_ref()
+                .is_some_and(|source_refname| source_refname == refname)
+        });
+
+        if let Some(stack) = stack {
+            // Disallow deletion of branches that are applied in workspace
+            if stack.in_workspace {
+                return Err(anyhow::anyhow!(
+                    "Cannot delete a branch that is applied in workspace"
+                ));
+            }
+            // Deletes the virtual branch entry from the application state
+            handle.delete_branch_entry(&stack.id)?;
         }
-        // Deletes the virtual branch entry from the application state
-        handle.delete_branch_entry(&stack.id)?;
-    }
 
-    // If a branch reference for this can be found, delete it
-    if let Ok(mut branch) = repo.find_branch(&given_name, git2::BranchType::Local) {
-        branch.delete()?;
-    };
-    Ok(())
+        // If a branch reference for this can be found, delete it
+        if let Ok(mut branch) = repo.find_branch(&given_name, git2::BranchType::Local) {
+            branch.delete()?;
+        };
+        Ok(())
+    })
 }
 
 pub fn list_commit_files(
@@ -156,12 +208,15 @@ pub fn list_commit_files(
 }
 
 pub fn set_base_branch(ctx: &CommandContext, target_branch: &RemoteRefname) -> Result<BaseBranch> {
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::SetBaseBranch),
-        guard.write_permission(),
-    );
-    base::set_base_branch(ctx, target_branch)
+    let parameters = [("targetBranch", target_branch.to_string())];
+    journaled(ctx, OperationKind::SetBaseBranch, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SetBaseBranch),
+            guard.write_permission(),
+        );
+        base::set_base_branch(ctx, target_branch)
+    })
 }
 
 pub fn set_target_push_remote(ctx: &CommandContext, push_remote: &str) -> Result<()> {
@@ -181,18 +236,24 @@ pub fn integrate_upstream_commits(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Integrating upstream commits requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::MergeUpstream),
-        guard.write_permission(),
-    );
-    branch_upstream_integration::integrate_upstream_commits_for_series(
-        ctx,
-        stack_id,
-        guard.write_permission(),
-        series_name,
-        integration_strategy,
-    )
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("series", series_name.clone()),
+    ];
+    journaled(ctx, OperationKind::MergeUpstream, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MergeUpstream),
+            guard.write_permission(),
+        );
+        branch_upstream_integration::integrate_upstream_commits_for_series(
+            ctx,
+            stack_id,
+            guard.write_permission(),
+            series_name,
+            integration_strategy,
+        )
+    })
 }
 
 pub fn update_virtual_branch(
@@ -201,24 +262,27 @@ pub fn update_virtual_branch(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Updating a branch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-    let old_branch = ctx
-        .project()
-        .virtual_branches()
-        .get_stack_in_workspace(branch_update.id)?;
-    let result = vbranch::update_branch(ctx, &branch_update);
-    let _ = snapshot_tree.and_then(|snapshot_tree| {
-        ctx.project().snapshot_branch_update(
-            snapshot_tree,
-            &old_branch,
-            &branch_update,
-            result.as_ref().err(),
-            guard.write_permission(),
-        )
-    });
-    result?;
-    Ok(())
+    let parameters = [("stackId", branch_update.id.to_string())];
+    journaled(ctx, OperationKind::GenericBranchUpdate, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let old_branch = ctx
+            .project()
+            .virtual_branches()
+            .get_stack_in_workspace(branch_update.id)?;
+        let result = vbranch::update_branch(ctx, &branch_update);
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_branch_update(
+                snapshot_tree,
+                &old_branch,
+                &branch_update,
+                result.as_ref().err(),
+                guard.write_permission(),
+            )
+        });
+        result?;
+        Ok(())
+    })
 }
 
 pub fn update_branch_order(
@@ -248,14 +312,17 @@ pub fn unapply_without_saving_virtual_branch(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Deleting a branch order requires open workspace mode")?;
-    let branch_manager = ctx.branch_manager();
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let state = ctx.project().virtual_branches();
-    let default_target = state.get_default_target()?;
-    let target_commit = ctx.repo().find_commit(default_target.sha)?;
-    // NB: unapply_without_saving is also called from save_and_unapply
-    branch_manager.unapply(stack_id, guard.write_permission(), &target_commit, true)?;
-    state.delete_branch_entry(&stack_id)
+    let parameters = [("stackId", stack_id.to_string())];
+    journaled(ctx, OperationKind::DeleteBranch, parameters, || {
+        let branch_manager = ctx.branch_manager();
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let state = ctx.project().virtual_branches();
+        let default_target = state.get_default_target()?;
+        let target_commit = ctx.repo().find_commit(default_target.sha)?;
+        // NB: unapply_without_saving is also called from save_and_unapply
+        branch_manager.unapply(stack_id, guard.write_permission(), &target_commit, true)?;
+        state.delete_branch_entry(&stack_id)
+    })
 }
 
 pub fn unapply_lines(
@@ -265,35 +332,100 @@ pub fn unapply_lines(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Unapply a patch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::DiscardLines),
-        guard.write_permission(),
-    );
+    let parameters = [("ownership", ownership.to_string())];
+    journaled(ctx, OperationKind::DiscardLines, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DiscardLines),
+            guard.write_permission(),
+        );
 
-    vbranch::unapply_ownership(ctx, ownership, Some(lines), guard.write_permission())
+        vbranch::unapply_ownership(ctx, ownership, Some(lines), guard.write_permission())
+    })
 }
 
 pub fn unapply_ownership(ctx: &CommandContext, ownership: &BranchOwnershipClaims) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Unapply a patch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::DiscardHunk),
-        guard.write_permission(),
-    );
-    vbranch::unapply_ownership(ctx, ownership, None, guard.write_permission())
+    let parameters = [("ownership", ownership.to_string())];
+    journaled(ctx, OperationKind::DiscardHunk, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DiscardHunk),
+            guard.write_permission(),
+        );
+        vbranch::unapply_ownership(ctx, ownership, None, guard.write_permission())
+    })
+}
+
+/// Move the hunks of `ownership` to the stack `stack_id` even if they are locked to other stacks, and lock them
+/// to it instead.
+pub fn transfer_ownership(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    ownership: &BranchOwnershipClaims,
+) -> Result<()> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx)
+        .context("Transferring ownership requires open workspace mode")?;
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("ownership", ownership.to_string()),
+    ];
+    journaled(ctx, OperationKind::MoveHunk, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MoveHunk),
+            guard.write_permission(),
+        );
+        vbranch::transfer_ownership(ctx, stack_id, ownership)
+    })
 }
 
 pub fn reset_files(ctx: &CommandContext, stack_id: StackId, files: &[PathBuf]) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Resetting a file requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::DiscardFile),
-        guard.write_permission(),
-    );
-    vbranch::reset_files(ctx, stack_id, files, guard.write_permission())
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("files", files.iter().map(|file| file.display()).join(", ")),
+    ];
+    journaled(ctx, OperationKind::DiscardFile, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DiscardFile),
+            guard.write_permission(),
+        );
+        vbranch::reset_files(ctx, stack_id, files, guard.write_permission())
+    })
+}
+
+/// Discard the uncommitted changes to `paths`, no matter which stacks own them, and return the id of the snapshot
+/// taken right before, which brings them back when [restored](OplogExt::restore_snapshot).
+///
+/// Nothing is discarded if the snapshot can't be taken, and it's an error to pass no `paths` at all
+/// as that would check out the whole worktree.
+pub fn discard_changes(ctx: &CommandContext, paths: &[PathBuf]) -> Result<git2::Oid> {
+    if paths.is_empty() {
+        bail!("No paths were given to discard changes from");
+    }
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Discarding changes requires open workspace mode")?;
+    let files = paths.iter().map(|path| path.display()).join(", ");
+    let parameters = [("files", files.clone())];
+    journaled(ctx, OperationKind::DiscardFile, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let details =
+            SnapshotDetails::new(OperationKind::DiscardFile).with_trailers(vec![Trailer {
+                key: "files".to_string(),
+                value: files,
+            }]);
+        let snapshot = ctx
+            .project()
+            .create_snapshot(details, guard.write_permission())
+            .context("Refusing to discard changes that couldn't be recorded in a snapshot")?;
+        vbranch::discard_changes(ctx, paths, guard.write_permission())?;
+        Ok(snapshot)
+    })
 }
 
 pub fn amend(
@@ -304,18 +436,81 @@ pub fn amend(
 ) -> Result<git2::Oid> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Amending a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::AmendCommit),
-        guard.write_permission(),
-    );
-    vbranch::amend(
-        ctx,
-        stack_id,
-        commit_oid,
-        ownership,
-        guard.write_permission(),
-    )
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitId", commit_oid.to_string()),
+    ];
+    journaled(ctx, OperationKind::AmendCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AmendCommit),
+            guard.write_permission(),
+        );
+        vbranch::amend(
+            ctx,
+            stack_id,
+            commit_oid,
+            ownership,
+            guard.write_permission(),
+        )
+    })
+}
+
+/// Amend the head commit of the stack with `stack_id` with the uncommitted changes to `paths` in it, and give
+/// it `message` if set, returning the id of the amended commit.
+///
+/// Fails if the commit was already pushed to a protected branch, unless `force` is set.
+pub fn amend_commit(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    paths: &[PathBuf],
+    message: Option<&str>,
+    force: bool,
+) -> Result<git2::Oid> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Amending a commit requires open workspace mode")?;
+    let parameters = [("stackId", stack_id.to_string())];
+    journaled(ctx, OperationKind::AmendCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AmendCommit),
+            guard.write_permission(),
+        );
+        rewrite::amend_head(
+            ctx,
+            stack_id,
+            paths,
+            message,
+            force,
+            guard.write_permission(),
+        )
+    })
+}
+
+/// Change the message of the commit with `commit_id` to `message`, rebasing the commits above it in its stack.
+///
+/// Fails if the commit was already pushed to a protected branch, unless `force` is set.
+pub fn reword_commit(
+    ctx: &CommandContext,
+    commit_id: git2::Oid,
+    message: &str,
+    force: bool,
+) -> Result<()> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx)
+        .context("Updating a commit message requires open workspace mode")?;
+    let parameters = [
+        ("commitId", commit_id.to_string()),
+        ("message", message.to_owned()),
+    ];
+    journaled(ctx, OperationKind::UpdateCommitMessage, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UpdateCommitMessage),
+            guard.write_permission(),
+        );
+        rewrite::reword(ctx, commit_id, message, force, guard.write_permission())
+    })
 }
 
 pub fn move_commit_file(
@@ -327,29 +522,43 @@ pub fn move_commit_file(
 ) -> Result<git2::Oid> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Amending a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::MoveCommitFile),
-        guard.write_permission(),
-    );
-    vbranch::move_commit_file(ctx, stack_id, from_commit_oid, to_commit_oid, ownership)
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("fromCommitId", from_commit_oid.to_string()),
+        ("toCommitId", to_commit_oid.to_string()),
+    ];
+    journaled(ctx, OperationKind::MoveCommitFile, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MoveCommitFile),
+            guard.write_permission(),
+        );
+        vbranch::move_commit_file(ctx, stack_id, from_commit_oid, to_commit_oid, ownership)
+    })
 }
 
 pub fn undo_commit(ctx: &CommandContext, stack_id: StackId, commit_oid: git2::Oid) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Undoing a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-    let result: Result<()> = crate::undo_commit::undo_commit(ctx, stack_id, commit_oid).map(|_| ());
-    let _ = snapshot_tree.and_then(|snapshot_tree| {
-        ctx.project().snapshot_commit_undo(
-            snapshot_tree,
-            result.as_ref(),
-            commit_oid,
-            guard.write_permission(),
-        )
-    });
-    result
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitId", commit_oid.to_string()),
+    ];
+    journaled(ctx, OperationKind::UndoCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let result: Result<()> =
+            crate::undo_commit::undo_commit(ctx, stack_id, commit_oid).map(|_| ());
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_commit_undo(
+                snapshot_tree,
+                result.as_ref(),
+                commit_oid,
+                guard.write_permission(),
+            )
+        });
+        result
+    })
 }
 
 pub fn insert_blank_commit(
@@ -361,12 +570,18 @@ pub fn insert_blank_commit(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Inserting a blank commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::InsertBlankCommit),
-        guard.write_permission(),
-    );
-    vbranch::insert_blank_commit(ctx, stack_id, commit_oid, offset)
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitId", commit_oid.to_string()),
+    ];
+    journaled(ctx, OperationKind::InsertBlankCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::InsertBlankCommit),
+            guard.write_permission(),
+        );
+        vbranch::insert_blank_commit(ctx, stack_id, commit_oid, offset)
+    })
 }
 
 pub fn reorder_stack(
@@ -376,13 +591,16 @@ pub fn reorder_stack(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Reordering a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::ReorderCommit),
-        guard.write_permission(),
-    );
-    reorder::reorder_stack(ctx, stack_id, stack_order, guard.write_permission())?;
-    Ok(())
+    let parameters = [("stackId", stack_id.to_string())];
+    journaled(ctx, OperationKind::ReorderCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ReorderCommit),
+            guard.write_permission(),
+        );
+        reorder::reorder_stack(ctx, stack_id, stack_order, guard.write_permission())?;
+        Ok(())
+    })
 }
 
 pub fn reset_virtual_branch(
@@ -392,12 +610,58 @@ pub fn reset_virtual_branch(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Resetting a branch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::UndoCommit),
-        guard.write_permission(),
-    );
-    vbranch::reset_branch(ctx, stack_id, target_commit_oid)
+    ctx.ensure_unprotected_stack(
+        &ctx.project()
+            .virtual_branches()
+            .get_stack_in_workspace(stack_id)?,
+        "reset",
+    )?;
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitId", target_commit_oid.to_string()),
+    ];
+    journaled(ctx, OperationKind::UndoCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UndoCommit),
+            guard.write_permission(),
+        );
+        vbranch::reset_branch(ctx, stack_id, target_commit_oid)
+    })
+}
+
+/// Apply the changes of the commit with `commit_id` to the worktree as uncommitted changes.
+///
+/// Files that can't be merged cleanly are left with conflict markers and listed in the outcome.
+pub fn cherry_pick(ctx: &CommandContext, commit_id: git2::Oid) -> Result<CherryPickOutcome> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Cherry-picking requires open workspace mode")?;
+    let parameters = [("commitId", commit_id.to_string())];
+    journaled(ctx, OperationKind::CherryPick, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::CherryPick),
+            guard.write_permission(),
+        );
+        cherry_pick::apply_to_worktree(ctx, commit_id, cherry_pick::Direction::Pick)
+    })
+}
+
+/// Undo the changes of the commit with `commit_id` in the worktree, leaving the result as uncommitted changes.
+///
+/// Files that can't be merged cleanly are left with conflict markers and listed in the outcome.
+pub fn revert_commit(ctx: &CommandContext, commit_id: git2::Oid) -> Result<CherryPickOutcome> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Reverting a commit requires open workspace mode")?;
+    let parameters = [("commitId", commit_id.to_string())];
+    journaled(ctx, OperationKind::RevertCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::RevertCommit),
+            guard.write_permission(),
+        );
+        cherry_pick::apply_to_worktree(ctx, commit_id, cherry_pick::Direction::Revert)
+    })
 }
 
 pub fn save_and_unapply_virutal_branch(
@@ -407,20 +671,23 @@ pub fn save_and_unapply_virutal_branch(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Converting branch to a real branch requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-    let branch_manager = ctx.branch_manager();
-    let result = branch_manager.save_and_unapply(stack_id, guard.write_permission());
-
-    let _ = snapshot_tree.and_then(|snapshot_tree| {
-        ctx.project().snapshot_branch_unapplied(
-            snapshot_tree,
-            result.as_ref(),
-            guard.write_permission(),
-        )
-    });
-
-    result
+    let parameters = [("stackId", stack_id.to_string())];
+    journaled(ctx, OperationKind::UnapplyBranch, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let branch_manager = ctx.branch_manager();
+        let result = branch_manager.save_and_unapply(stack_id, guard.write_permission());
+
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_branch_unapplied(
+                snapshot_tree,
+                result.as_ref(),
+                guard.write_permission(),
+            )
+        });
+
+        result
+    })
 }
 
 #[deprecated(note = "use gitbutler_branch_actions::stack::push_stack instead")]
@@ -439,6 +706,53 @@ pub fn find_git_branches(ctx: &CommandContext, branch_name: &str) -> Result<Vec<
     remote::find_git_branches(ctx, branch_name)
 }
 
+/// Rewrite the stack containing the commits in `instructions` and place its commits onto `onto`.
+///
+/// `instructions` are ordered from the oldest to the newest commit and must mention each commit of the stack.
+/// If the result would contain conflicted commits nothing is changed, and the rebase remains pending until
+/// it's resumed with [`resume_rebase()`] or aborted with [`abort_rebase()`].
+pub fn start_rebase(
+    ctx: &CommandContext,
+    onto: git2::Oid,
+    instructions: Vec<RebaseInstruction>,
+) -> Result<RebaseStatus> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Rebasing requires open workspace mode")?;
+    let parameters = [("onto", onto.to_string())];
+    journaled(ctx, OperationKind::InteractiveRebase, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::InteractiveRebase),
+            guard.write_permission(),
+        );
+        interactive_rebase::start(ctx, onto, instructions, guard.write_permission())
+    })
+}
+
+/// Apply the pending rebase along with its conflicted commits, which can then be resolved one by one.
+pub fn resume_rebase(ctx: &CommandContext) -> Result<()> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Rebasing requires open workspace mode")?;
+    journaled(ctx, OperationKind::InteractiveRebase, [], || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::InteractiveRebase),
+            guard.write_permission(),
+        );
+        interactive_rebase::resume(ctx, guard.write_permission())
+    })
+}
+
+/// Forget the pending rebase, if there is one.
+pub fn abort_rebase(ctx: &CommandContext) -> Result<()> {
+    interactive_rebase::abort(ctx)
+}
+
+/// Return the rebase that is waiting to be resumed or aborted, if any.
+pub fn pending_rebase(ctx: &CommandContext) -> Result<Option<PendingRebase>> {
+    interactive_rebase::pending(ctx)
+}
+
 pub fn squash_commits(
     ctx: &CommandContext,
     stack_id: StackId,
@@ -447,18 +761,25 @@ pub fn squash_commits(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Squashing a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::SquashCommit),
-        guard.write_permission(),
-    );
-    crate::squash::squash_commits(
-        ctx,
-        stack_id,
-        source_ids,
-        destination_id,
-        guard.write_permission(),
-    )
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitIds", source_ids.iter().join(", ")),
+        ("destinationId", destination_id.to_string()),
+    ];
+    journaled(ctx, OperationKind::SquashCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SquashCommit),
+            guard.write_permission(),
+        );
+        crate::squash::squash_commits(
+            ctx,
+            stack_id,
+            source_ids,
+            destination_id,
+            guard.write_permission(),
+        )
+    })
 }
 
 pub fn update_commit_message(
@@ -470,28 +791,42 @@ pub fn update_commit_message(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Updating a commit message requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::UpdateCommitMessage),
-        guard.write_permission(),
-    );
-    vbranch::update_commit_message(ctx, stack_id, commit_oid, message)
+    let parameters = [
+        ("stackId", stack_id.to_string()),
+        ("commitId", commit_oid.to_string()),
+        ("message", message.to_owned()),
+    ];
+    journaled(ctx, OperationKind::UpdateCommitMessage, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UpdateCommitMessage),
+            guard.write_permission(),
+        );
+        vbranch::update_commit_message(ctx, stack_id, commit_oid, message)
+    })
 }
 
 pub fn find_commit(ctx: &CommandContext, commit_oid: git2::Oid) -> Result<Option<RemoteCommit>> {
     remote::get_commit_data(ctx, commit_oid)
 }
 
-pub fn fetch_from_remotes(ctx: &CommandContext, askpass: Option<String>) -> Result<FetchResult> {
+/// Fetch all remotes of the project of `ctx`, and fail once `should_interrupt` is set instead of fetching the
+/// remaining ones.
+pub fn fetch_from_remotes(
+    ctx: &CommandContext,
+    askpass: Option<String>,
+    should_interrupt: &AtomicBool,
+) -> Result<FetchResult> {
     let remotes = ctx.repo().remotes_as_string()?;
-    let fetch_errors: Vec<_> = remotes
-        .iter()
-        .filter_map(|remote| {
-            ctx.fetch(remote, askpass.clone())
-                .err()
-                .map(|err| err.to_string())
-        })
-        .collect();
+    let mut fetch_errors = Vec::new();
+    for remote in &remotes {
+        if let Err(err) = ctx.fetch_interruptibly(remote, askpass.clone(), should_interrupt) {
+            if should_interrupt.load(Ordering::Relaxed) {
+                return Err(err);
+            }
+            fetch_errors.push(err.to_string());
+        }
+    }
 
     let timestamp = std::time::SystemTime::now();
     let project_data_last_fetched = if fetch_errors.is_empty() {
@@ -517,21 +852,47 @@ pub fn move_commit(
 ) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Moving a commit requires open workspace mode")?;
-    let mut guard = ctx.project().exclusive_worktree_access();
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::MoveCommit),
-        guard.write_permission(),
-    );
-    move_commits::move_commit(
-        ctx,
-        target_stack_id,
-        commit_oid,
-        guard.write_permission(),
-        source_stack_id,
-    )
+    let parameters = [
+        ("commitId", commit_oid.to_string()),
+        ("sourceStackId", source_stack_id.to_string()),
+        ("targetStackId", target_stack_id.to_string()),
+    ];
+    journaled(ctx, OperationKind::MoveCommit, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MoveCommit),
+            guard.write_permission(),
+        );
+        move_commits::move_commit(
+            ctx,
+            target_stack_id,
+            commit_oid,
+            guard.write_permission(),
+            source_stack_id,
+        )
+    })
 }
 
 #[instrument(level = tracing::Level::DEBUG, skip(ctx), err(Debug))]
+/// Return the local branches that are merged into the target branch or whose remote branch is gone.
+pub fn stale_branches(ctx: &CommandContext) -> Result<Vec<StaleBranch>> {
+    branch_cleanup::stale_branches(ctx)
+}
+
+/// Delete the local branches `names`, which have to be merged into the target branch unless `force` is set.
+pub fn delete_branches(ctx: &CommandContext, names: &[String], force: bool) -> Result<()> {
+    ctx.verify()?;
+    let parameters = [("branches", names.join(", "))];
+    journaled(ctx, OperationKind::DeleteBranch, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DeleteBranch),
+            guard.write_permission(),
+        );
+        branch_cleanup::delete_branches(ctx, names, force)
+    })
+}
+
 pub fn create_virtual_branch_from_branch(
     ctx: &CommandContext,
     branch: &Refname,
@@ -541,14 +902,17 @@ pub fn create_virtual_branch_from_branch(
     ctx.verify()?;
     assure_open_workspace_mode(ctx)
         .context("Creating a virtual branch from a branch open workspace mode")?;
-    let branch_manager = ctx.branch_manager();
-    let mut guard = ctx.project().exclusive_worktree_access();
-    branch_manager.create_virtual_branch_from_branch(
-        branch,
-        remote,
-        pr_number,
-        guard.write_permission(),
-    )
+    let parameters = [("branch", branch.to_string())];
+    journaled(ctx, OperationKind::ApplyBranch, parameters, || {
+        let branch_manager = ctx.branch_manager();
+        let mut guard = ctx.project().exclusive_worktree_access();
+        branch_manager.create_virtual_branch_from_branch(
+            branch,
+            remote,
+            pr_number,
+            guard.write_permission(),
+        )
+    })
 }
 
 pub fn get_uncommited_files(ctx: &CommandContext) -> Result<Vec<RemoteBranchFile>> {
@@ -580,19 +944,43 @@ pub fn integrate_upstream(
     resolutions: &[Resolution],
     base_branch_resolution: Option<BaseBranchResolution>,
 ) -> Result<()> {
-    let mut guard = ctx.project().exclusive_worktree_access();
+    journaled(ctx, OperationKind::UpdateWorkspaceBase, [], || {
+        let mut guard = ctx.project().exclusive_worktree_access();
 
-    let _ = ctx.project().create_snapshot(
-        SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
-        guard.write_permission(),
-    );
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
+            guard.write_permission(),
+        );
 
-    upstream_integration::integrate_upstream(
-        ctx,
-        resolutions,
-        base_branch_resolution,
-        guard.write_permission(),
-    )
+        upstream_integration::integrate_upstream(
+            ctx,
+            resolutions,
+            base_branch_resolution,
+            guard.write_permission(),
+        )
+    })
+}
+
+/// Update all applied stacks to the latest commit of the target branch, as fetched last, see
+/// [`upstream_integration::update_from_base()`].
+pub fn update_from_base(
+    ctx: &CommandContext,
+    strategy: ResolutionApproach,
+) -> Result<Vec<StackUpdate>> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx)
+        .context("Updating from the base branch requires open workspace mode")?;
+    let parameters = [("strategy", format!("{strategy:?}"))];
+    journaled(ctx, OperationKind::UpdateWorkspaceBase, parameters, || {
+        let mut guard = ctx.project().exclusive_worktree_access();
+
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
+            guard.write_permission(),
+        );
+
+        upstream_integration::update_from_base(ctx, strategy, guard.write_permission())
+    })
 }
 
 pub fn resolve_upstream_integration(
@@ -614,6 +1002,7 @@ pub(crate) trait Verify {
 
 impl Verify for CommandContext {
     fn verify(&self) -> Result<()> {
+        gitbutler_operating_modes::assure_workspace_owner(self)?;
         let mut guard = self.project().exclusive_worktree_access();
         crate::integration::verify_branch(self, guard.write_permission())
     }
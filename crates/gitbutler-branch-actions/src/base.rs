@@ -54,6 +54,30 @@ pub fn get_base_branch_data(ctx: &CommandContext) -> Result<BaseBranch> {
     Ok(base)
 }
 
+/// Return the default branch of the repository, like `refs/remotes/origin/main`, as the `HEAD` of a remote points to
+/// it, preferring `origin`. This is set when cloning, or with `git remote set-head`, and makes a good base branch.
+pub fn default_branch(repo: &git2::Repository) -> Result<Option<RemoteRefname>> {
+    let mut remotes: Vec<String> = repo
+        .remotes()?
+        .iter()
+        .flatten()
+        .map(ToOwned::to_owned)
+        .collect();
+    remotes.sort_by_key(|remote| remote != "origin");
+    for remote in remotes {
+        let Ok(head) = repo.find_reference(&format!("refs/remotes/{remote}/HEAD")) else {
+            continue;
+        };
+        let Some(target) = head.symbolic_target() else {
+            continue;
+        };
+        if repo.find_reference(target).is_ok() {
+            return Ok(Some(target.parse()?));
+        }
+    }
+    Ok(None)
+}
+
 fn go_back_to_integration(ctx: &CommandContext, default_target: &Target) -> Result<BaseBranch> {
     let repo = ctx.repo();
     let statuses = repo
@@ -0,0 +1,163 @@
+//! Find the local branches that aren't needed anymore, as their commits are in the target branch or their remote
+//! branch was deleted, and delete them in bulk.
+use std::collections::HashSet;
+
+use anyhow::{bail, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::Refname;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// A local branch that can likely be deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleBranch {
+    /// The short name of the branch, like `feature`.
+    pub name: String,
+    /// Why the branch is considered stale.
+    pub reason: StaleReason,
+    /// The name of the remote branch it tracked, like `origin/feature`, if it has one.
+    pub upstream: Option<String>,
+    /// When the last commit of the branch was made, in seconds since the Unix epoch.
+    pub last_commit_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StaleReason {
+    /// All commits of the branch are in the target branch.
+    Merged,
+    /// The branch tracked a remote branch that doesn't exist anymore.
+    UpstreamGone,
+}
+
+/// Return the local branches of the project that are merged into the target branch or whose remote branch is gone,
+/// oldest first.
+///
+/// The local counterpart of the target branch, the branch that is checked out and the branches of applied stacks
+/// are never included.
+pub(crate) fn stale_branches(ctx: &CommandContext) -> Result<Vec<StaleBranch>> {
+    let repo = ctx.repo();
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let target_head = target.remote_head(repo).unwrap_or(target.sha);
+    let protected = protected_branches(ctx, target.branch.branch())?;
+
+    let mut stale = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let (Some(name), Some(refname)) = (branch.name()?, branch.get().name()) else {
+            continue;
+        };
+        if is_protected(&protected, name) {
+            continue;
+        }
+        let Ok(commit) = branch.get().peel_to_commit() else {
+            continue;
+        };
+        let upstream = repo
+            .branch_upstream_name(refname)
+            .ok()
+            .and_then(|upstream| upstream.as_str().map(ToOwned::to_owned));
+        let upstream_gone = upstream
+            .as_ref()
+            .is_some_and(|upstream| repo.find_reference(upstream).is_err());
+        let reason = if is_merged(repo, commit.id(), target_head)? {
+            StaleReason::Merged
+        } else if upstream_gone {
+            StaleReason::UpstreamGone
+        } else {
+            continue;
+        };
+        stale.push(StaleBranch {
+            name: name.to_owned(),
+            reason,
+            upstream: upstream.map(|upstream| {
+                upstream
+                    .strip_prefix("refs/remotes/")
+                    .unwrap_or(&upstream)
+                    .to_owned()
+            }),
+            last_commit_at: commit.time().seconds(),
+        });
+    }
+    stale.sort_by(|a, b| {
+        a.last_commit_at
+            .cmp(&b.last_commit_at)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(stale)
+}
+
+/// Delete the local branches `names`, along with the entries of unapplied stacks created from them.
+///
+/// Unless `force` is set, only branches merged into the target branch can be deleted, like with `git branch -d`.
+/// Nothing is deleted if any of the branches can't be.
+pub(crate) fn delete_branches(ctx: &CommandContext, names: &[String], force: bool) -> Result<()> {
+    let repo = ctx.repo();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let target_head = target.remote_head(repo).unwrap_or(target.sha);
+    let protected = protected_branches(ctx, target.branch.branch())?;
+
+    let mut branches = Vec::new();
+    for name in names {
+        if is_protected(&protected, name) {
+            bail!("The branch '{name}' is the target branch, checked out, applied or managed by GitButler, and can't be deleted");
+        }
+        let branch = repo
+            .find_branch(name, git2::BranchType::Local)
+            .with_context(|| format!("There is no branch named '{name}'"))?;
+        let head = branch.get().peel_to_commit()?.id();
+        if !force && !is_merged(repo, head, target_head)? {
+            bail!("The branch '{name}' isn't merged into the target branch, and can only be deleted by force");
+        }
+        branches.push(branch);
+    }
+
+    let stacks = vb_state.list_all_stacks()?;
+    for mut branch in branches {
+        let refname: Refname = branch
+            .get()
+            .name()
+            .context("branch names are valid UTF-8")?
+            .parse()?;
+        for stack in stacks
+            .iter()
+            .filter(|stack| !stack.in_workspace && stack.source_refname.as_ref() == Some(&refname))
+        {
+            vb_state.delete_branch_entry(&stack.id)?;
+        }
+        branch.delete()?;
+    }
+    Ok(())
+}
+
+/// Return the names of the local branches that mustn't be deleted: `target_branch`, the one that is checked out and
+/// those of the applied stacks.
+fn protected_branches(ctx: &CommandContext, target_branch: &str) -> Result<HashSet<String>> {
+    let mut protected = HashSet::from([target_branch.to_owned()]);
+    if let Ok(head) = ctx.repo().head() {
+        if let Some(name) = head.shorthand().filter(|_| head.is_branch()) {
+            protected.insert(name.to_owned());
+        }
+    }
+    for stack in ctx
+        .project()
+        .virtual_branches()
+        .list_stacks_in_workspace()?
+    {
+        protected.extend(stack.heads.into_iter().map(|head| head.name));
+    }
+    Ok(protected)
+}
+
+/// Return `true` if `name` is in `protected` or one of the branches GitButler manages itself, like
+/// `gitbutler/workspace`.
+fn is_protected(protected: &HashSet<String>, name: &str) -> bool {
+    name.starts_with("gitbutler/") || protected.contains(name)
+}
+
+fn is_merged(repo: &git2::Repository, head: git2::Oid, target_head: git2::Oid) -> Result<bool> {
+    Ok(head == target_head || repo.graph_descendant_of(target_head, head)?)
+}
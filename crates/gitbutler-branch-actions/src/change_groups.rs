@@ -0,0 +1,177 @@
+//! Suggest how to split uncommitted changes into groups by the session they were written in, as told by the
+//! [deltas](gitbutler_oplog::deltas) that recorded them, which helps to untangle work on multiple tasks that got
+//! interleaved.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use bstr::{BString, ByteSlice};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use gitbutler_oplog::{
+    activity::SESSION_GAP_SECONDS,
+    deltas::{self, Delta},
+    eol,
+};
+use gitbutler_oxidize::git2_to_gix_object_id;
+use gitbutler_stack::{BranchOwnershipClaims, OwnershipClaim, StackId};
+use serde::Serialize;
+
+use crate::hunk::VirtualBranchHunk;
+
+/// A period in which changes were written, with no more than [`SESSION_GAP_SECONDS`] between its deltas.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaSession {
+    /// When the first delta of the session was noticed, in seconds since the Unix epoch.
+    pub start: i64,
+    /// When the last delta of the session was noticed, in seconds since the Unix epoch.
+    pub end: i64,
+    /// The short name of the branch the first delta of the session was recorded on, like `main`, or `None` if
+    /// `HEAD` was detached.
+    pub branch: Option<String>,
+    pub deltas: usize,
+}
+
+/// Uncommitted hunks that were written during the same session, as returned by [`suggest()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeGroup {
+    /// The session in which the hunks were written, or `None` if no delta recorded them yet.
+    pub session: Option<DeltaSession>,
+    /// The stacks the hunks are currently assigned to.
+    pub stack_ids: Vec<StackId>,
+    /// The hunks, in the form that is accepted when committing or moving changes.
+    pub ownership: BranchOwnershipClaims,
+}
+
+/// Group all uncommitted hunks in the workspace by the session in which they were written, oldest session
+/// first, followed by the hunks that no delta recorded yet.
+///
+/// A hunk is considered written when the oldest of the consecutive deltas up to the latest one of its file first
+/// recorded the file with its added lines, or without its removed lines if it only removes some. Deltas that didn't
+/// record the content of the file, like those of machine-made changes, end the search as what they changed is
+/// unknown.
+pub fn suggest(ctx: &CommandContext) -> Result<Vec<ChangeGroup>> {
+    let project = ctx.project();
+    let repo = ctx.gix_repository()?;
+    let branches = crate::list_virtual_branches(ctx)?.branches;
+    let deltas = deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?;
+    let sessions = sessions(&deltas);
+
+    let mut written_at = Vec::new();
+    for branch in &branches {
+        for file in &branch.files {
+            let mut paths = deltas::former_paths(&deltas, &file.path);
+            paths.push(file.path.clone());
+            let mut versions = Vec::new();
+            for delta in deltas
+                .iter()
+                .rev()
+                .filter(|delta| delta.paths.iter().any(|path| paths.contains(path)))
+            {
+                let Some(content) = delta
+                    .contents
+                    .iter()
+                    .find(|content| paths.contains(&content.path))
+                else {
+                    break;
+                };
+                let blob = content
+                    .blob_id
+                    .map(|blob_id| -> Result<BString> {
+                        let blob = repo.find_blob(git2_to_gix_object_id(blob_id))?;
+                        Ok(eol::restore(&blob.data, content.eol).into_owned().into())
+                    })
+                    .transpose()?;
+                versions.push((delta.at, blob));
+            }
+            for hunk in &file.hunks {
+                let at = versions
+                    .iter()
+                    .take_while(|(_, content)| reflects(content.as_ref(), hunk))
+                    .last()
+                    .map(|(at, _)| *at);
+                written_at.push((branch.id, &file.path, hunk, at));
+            }
+        }
+    }
+
+    // Sessions by their start, with `None` last for hunks that weren't recorded yet.
+    let mut groups: BTreeMap<(bool, i64), ChangeGroup> = BTreeMap::new();
+    for (stack_id, path, hunk, at) in written_at {
+        let session = at.and_then(|at| {
+            sessions
+                .iter()
+                .find(|session| (session.start..=session.end).contains(&at))
+        });
+        let key = session.map_or((true, 0), |session| (false, session.start));
+        let group = groups.entry(key).or_insert_with(|| ChangeGroup {
+            session: session.cloned(),
+            stack_ids: Vec::new(),
+            ownership: BranchOwnershipClaims::default(),
+        });
+        if !group.stack_ids.contains(&stack_id) {
+            group.stack_ids.push(stack_id);
+        }
+        add_claim(&mut group.ownership, path, hunk);
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// Return the sessions that `deltas`, oldest first, were noticed in, oldest first.
+fn sessions(deltas: &[Delta]) -> Vec<DeltaSession> {
+    let mut sessions: Vec<DeltaSession> = Vec::new();
+    for delta in deltas {
+        match sessions.last_mut() {
+            Some(session) if delta.at - session.end <= SESSION_GAP_SECONDS => {
+                session.end = delta.at;
+                session.deltas += 1;
+            }
+            _ => sessions.push(DeltaSession {
+                start: delta.at,
+                end: delta.at,
+                branch: delta.branch.clone(),
+                deltas: 1,
+            }),
+        }
+    }
+    sessions
+}
+
+/// Return `true` if the file `content` recorded by a delta, or `None` if it didn't exist, contains `hunk`.
+fn reflects(content: Option<&BString>, hunk: &VirtualBranchHunk) -> bool {
+    let (mut added, mut removed) = (BString::default(), BString::default());
+    for line in hunk.diff.lines_with_terminator() {
+        if let Some(line) = line.strip_prefix(b"+") {
+            added.extend_from_slice(line);
+        } else if let Some(line) = line.strip_prefix(b"-") {
+            removed.extend_from_slice(line);
+        }
+    }
+    let content = content.map_or(b"".as_bstr(), |content| content.as_bstr());
+    if added.is_empty() {
+        !removed.is_empty() && content.find(&removed).is_none()
+    } else {
+        content.find(&added).is_some()
+    }
+}
+
+fn add_claim(ownership: &mut BranchOwnershipClaims, path: &Path, hunk: &VirtualBranchHunk) {
+    let hunk = Hunk {
+        hash: None,
+        start: hunk.start,
+        end: hunk.end,
+    };
+    match ownership
+        .claims
+        .iter_mut()
+        .find(|claim| claim.file_path == path)
+    {
+        Some(claim) => claim.hunks.push(hunk),
+        None => ownership.claims.push(OwnershipClaim {
+            file_path: path.to_owned(),
+            hunks: vec![hunk],
+        }),
+    }
+}
@@ -0,0 +1,94 @@
+//! Apply the changes of a commit, or their inverse, to the worktree as uncommitted changes.
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::AUTO_TRACK_LIMIT_BYTES;
+use gitbutler_repo::RepositoryExt;
+use serde::Serialize;
+
+use crate::conflicts;
+
+/// The result of [`cherry_pick()`](crate::cherry_pick()) or [`revert_commit()`](crate::revert_commit()).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CherryPickOutcome {
+    /// Worktree-relative paths of files that couldn't be merged cleanly and now contain conflict markers.
+    /// They are marked as conflicted until they are resolved.
+    pub conflicted_files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Direction {
+    /// Apply the changes the commit introduced.
+    Pick,
+    /// Undo the changes the commit introduced.
+    Revert,
+}
+
+/// Merge the changes of `commit_id`, or their inverse, into the worktree, and leave conflict markers
+/// in files that changed on both sides.
+pub(crate) fn apply_to_worktree(
+    ctx: &CommandContext,
+    commit_id: git2::Oid,
+    direction: Direction,
+) -> Result<CherryPickOutcome> {
+    let repo = ctx.repo();
+    let commit = repo.find_commit(commit_id)?;
+    if commit.parent_count() > 1 {
+        bail!("Cannot cherry-pick or revert merge commit {commit_id}");
+    }
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => parent.tree()?,
+        Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+    let commit_tree = commit.tree()?;
+    let (base, theirs) = match direction {
+        Direction::Pick => (&parent_tree, &commit_tree),
+        Direction::Revert => (&commit_tree, &parent_tree),
+    };
+
+    let worktree = repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?;
+    let mut merge_index = repo.merge_trees(base, &worktree, theirs, None)?;
+
+    let mut conflicted_files = Vec::new();
+    for conflict in merge_index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            conflicted_files.push(gix::path::from_bstr(entry.path.as_bstr()).into_owned());
+        }
+    }
+
+    let mut changed_paths = Vec::new();
+    repo.diff_tree_to_tree(Some(base), Some(theirs), None)?
+        .foreach(
+            &mut |delta, _| {
+                changed_paths.extend(
+                    [delta.old_file().path(), delta.new_file().path()]
+                        .into_iter()
+                        .flatten()
+                        .map(ToOwned::to_owned),
+                );
+                true
+            },
+            None,
+            None,
+            None,
+        )?;
+    gitbutler_project::machine_changes::record(ctx.project().id, changed_paths);
+
+    repo.checkout_index(
+        Some(&mut merge_index),
+        Some(
+            git2::build::CheckoutBuilder::new()
+                .force()
+                .allow_conflicts(true)
+                .conflict_style_merge(true)
+                .update_index(false),
+        ),
+    )?;
+    conflicts::mark(ctx, &conflicted_files, None)?;
+
+    Ok(CherryPickOutcome { conflicted_files })
+}
@@ -1,6 +1,6 @@
 use gitbutler_command_context::CommandContext;
 use gitbutler_repo::{
-    hooks::{self, HookResult},
+    hooks::{self, HookOutput, HookResult},
     staging,
 };
 use gitbutler_stack::BranchOwnershipClaims;
@@ -8,6 +8,7 @@ use gitbutler_stack::BranchOwnershipClaims;
 pub fn pre_commit(
     ctx: &CommandContext,
     ownership: &BranchOwnershipClaims,
+    on_output: impl FnMut(HookOutput),
 ) -> Result<HookResult, anyhow::Error> {
     let repo = ctx.repo();
     let diffs = gitbutler_diff::workdir(ctx.repo(), repo.head()?.peel_to_commit()?.id())?;
@@ -19,5 +20,5 @@ pub fn pre_commit(
             .map(|claim| (&claim.file_path, &claim.hunks))
             .collect(),
     )?;
-    hooks::pre_commit(ctx, &selected_files)
+    hooks::pre_commit(ctx, &selected_files, on_output)
 }
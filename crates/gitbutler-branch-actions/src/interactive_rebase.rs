@@ -0,0 +1,295 @@
+//! Rewrite the commits of a stack in one go by picking, squashing, rewording and dropping them,
+//! optionally placing them onto a new base.
+//!
+//! The rebase is performed in memory first. If none of the rewritten commits is conflicted it's applied right away.
+//! Otherwise it's kept as [pending rebase](PendingRebase) which can be [resumed](crate::resume_rebase()) to apply it
+//! along with its conflicted commits, to be resolved one by one afterwards, or [aborted](crate::abort_rebase()).
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use bstr::BString;
+use but_rebase::{RebaseOutput, RebaseStep};
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_ext::CommitExt as _;
+use gitbutler_oxidize::{ObjectIdExt as _, OidExt as _};
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::logging::{LogUntil, RepositoryExt as _};
+use gitbutler_stack::{Stack, StackId};
+use gitbutler_workspace::{
+    checkout_branch_trees, compute_updated_branch_head_for_commits, BranchHeadAndTree,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::VirtualBranchesExt as _;
+
+/// What to do with a commit during an interactive rebase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum RebaseAction {
+    /// Keep the commit as is.
+    Pick,
+    /// Meld the commit into the commit that precedes it, using `message` as the message of the
+    /// combined commit, or both messages if `None`.
+    Squash { message: Option<String> },
+    /// Keep the commit, but change its message.
+    Reword { message: String },
+    /// Remove the commit and its changes.
+    Drop,
+}
+
+/// A single line of the todo-list of an interactive rebase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebaseInstruction {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_id: git2::Oid,
+    pub action: RebaseAction,
+}
+
+/// The outcome of [`start_rebase()`](crate::start_rebase()).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum RebaseStatus {
+    /// The stack was rewritten.
+    Completed,
+    /// The rebase would produce conflicted commits, listed by their original ids. Nothing was changed yet,
+    /// and the rebase can be resumed or aborted.
+    Conflicted(#[serde(with = "gitbutler_serde::oid_vec")] Vec<git2::Oid>),
+}
+
+/// A rebase that produced conflicts and is waiting to be resumed or aborted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingRebase {
+    /// The stack whose commits are rebased.
+    pub stack_id: StackId,
+    /// The head of the stack when the rebase was started, to detect changes to it in the meantime.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The commit to place the rewritten commits onto.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub onto: git2::Oid,
+    /// The instructions, ordered from the oldest to the newest commit.
+    pub instructions: Vec<RebaseInstruction>,
+}
+
+pub(crate) fn start(
+    ctx: &CommandContext,
+    onto: git2::Oid,
+    instructions: Vec<RebaseInstruction>,
+    perm: &mut WorktreeWritePermission,
+) -> Result<RebaseStatus> {
+    if pending(ctx)?.is_some() {
+        bail!("A rebase is already in progress, resume or abort it first");
+    }
+    let stack = stack_to_rebase(ctx, &instructions)?;
+    let output = execute(ctx, &stack, onto, &instructions)?;
+    let conflicted = conflicted_commits(ctx.repo(), &output)?;
+    if !conflicted.is_empty() {
+        let pending = PendingRebase {
+            stack_id: stack.id,
+            head: stack.head(),
+            onto,
+            instructions,
+        };
+        gitbutler_fs::write(pending_rebase_path(ctx), toml::to_string(&pending)?)?;
+        return Ok(RebaseStatus::Conflicted(conflicted));
+    }
+    apply(ctx, stack, output, perm)?;
+    Ok(RebaseStatus::Completed)
+}
+
+pub(crate) fn resume(ctx: &CommandContext, perm: &mut WorktreeWritePermission) -> Result<()> {
+    let pending = pending(ctx)?.context("There is no rebase in progress")?;
+    let stack = ctx
+        .project()
+        .virtual_branches()
+        .get_stack_in_workspace(pending.stack_id)?;
+    if stack.head() != pending.head {
+        abort(ctx)?;
+        bail!("The stack changed since the rebase was started, please start it again");
+    }
+    let output = execute(ctx, &stack, pending.onto, &pending.instructions)?;
+    apply(ctx, stack, output, perm)?;
+    abort(ctx)
+}
+
+pub(crate) fn abort(ctx: &CommandContext) -> Result<()> {
+    match std::fs::remove_file(pending_rebase_path(ctx)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn pending(ctx: &CommandContext) -> Result<Option<PendingRebase>> {
+    let path = pending_rebase_path(ctx);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path)?;
+    Ok(Some(toml::from_str(&contents).with_context(|| {
+        format!("Failed to read pending rebase from '{}'", path.display())
+    })?))
+}
+
+fn pending_rebase_path(ctx: &CommandContext) -> PathBuf {
+    ctx.project().gb_dir().join("rebase.toml")
+}
+
+/// Find the stack in the workspace that the commits in `instructions` belong to, and assure they mention
+/// each of its commits exactly once.
+fn stack_to_rebase(ctx: &CommandContext, instructions: &[RebaseInstruction]) -> Result<Stack> {
+    let Some(first) = instructions.first() else {
+        bail!("A rebase needs at least one instruction");
+    };
+    let repo = ctx.repo();
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    for stack in vb_state.list_stacks_in_workspace()? {
+        let merge_base = repo.merge_base(stack.head(), default_target.sha)?;
+        let mut stack_commits = repo.l(stack.head(), LogUntil::Commit(merge_base), false)?;
+        if !stack_commits.contains(&first.commit_id) {
+            continue;
+        }
+        for instruction in instructions {
+            let Some(pos) = stack_commits
+                .iter()
+                .position(|id| *id == instruction.commit_id)
+            else {
+                bail!(
+                    "Commit {} is not part of the stack or mentioned more than once",
+                    instruction.commit_id
+                );
+            };
+            stack_commits.remove(pos);
+        }
+        if let Some(missing) = stack_commits.first() {
+            bail!("Commit {missing} of the stack is missing from the instructions, use 'drop' to remove it");
+        }
+        return Ok(stack);
+    }
+    bail!(
+        "Commit {} is not part of any stack in the workspace",
+        first.commit_id
+    )
+}
+
+/// Rewrite the commits of `stack` in memory according to `instructions`, and place them onto `onto`.
+fn execute(
+    ctx: &CommandContext,
+    stack: &Stack,
+    onto: git2::Oid,
+    instructions: &[RebaseInstruction],
+) -> Result<RebaseOutput> {
+    let repo = ctx.repo();
+    let default_target = ctx.project().virtual_branches().get_default_target()?;
+    let merge_base = repo.merge_base(stack.head(), default_target.sha)?;
+
+    // Branch heads are placed after the last commit that survives the rebase, which also
+    // keeps them from being placed between a commit and the commits squashed into it.
+    let mut pending_heads = stack.heads_by_commit(repo.find_commit(merge_base)?);
+    let mut squash_target_message: Option<BString> = None;
+    let mut steps = Vec::new();
+    for RebaseInstruction { commit_id, action } in instructions {
+        let commit = repo.find_commit(*commit_id)?;
+        match action {
+            RebaseAction::Pick | RebaseAction::Reword { .. } => {
+                steps.extend(
+                    pending_heads
+                        .drain(..)
+                        .map(|head| RebaseStep::Reference(but_core::Reference::Virtual(head))),
+                );
+                let new_message = match action {
+                    RebaseAction::Reword { message } => Some(BString::from(message.as_str())),
+                    _ => None,
+                };
+                squash_target_message = Some(
+                    new_message
+                        .clone()
+                        .unwrap_or_else(|| commit.message_raw_bytes().into()),
+                );
+                steps.push(RebaseStep::Pick {
+                    commit_id: commit_id.to_gix(),
+                    new_message,
+                });
+            }
+            RebaseAction::Squash { message } => {
+                let Some(target_message) = squash_target_message.as_mut() else {
+                    bail!("Commit {commit_id} can't be squashed as there is no preceding commit to squash it into");
+                };
+                let new_message = match message {
+                    Some(message) => BString::from(message.as_str()),
+                    None => {
+                        let mut combined = target_message.clone();
+                        combined.push(b'\n');
+                        combined.extend_from_slice(commit.message_raw_bytes());
+                        combined
+                    }
+                };
+                *target_message = new_message.clone();
+                steps.push(RebaseStep::SquashIntoPreceding {
+                    commit_id: commit_id.to_gix(),
+                    new_message: Some(new_message),
+                });
+            }
+            RebaseAction::Drop => {}
+        }
+        pending_heads.extend(stack.heads_by_commit(commit));
+    }
+    steps.extend(
+        pending_heads
+            .drain(..)
+            .map(|head| RebaseStep::Reference(but_core::Reference::Virtual(head))),
+    );
+
+    let gix_repo = ctx.gix_repository()?;
+    let mut builder = but_rebase::Rebase::new(&gix_repo, onto.to_gix(), None)?;
+    let builder = builder.steps(steps)?;
+    builder.rebase_noops(false);
+    builder.rebase()
+}
+
+/// Return the original ids of all commits that are conflicted after the rebase.
+fn conflicted_commits(repo: &git2::Repository, output: &RebaseOutput) -> Result<Vec<git2::Oid>> {
+    let mut conflicted = Vec::new();
+    for (_base, old, new) in &output.commit_mapping {
+        let old = old.to_git2();
+        if !conflicted.contains(&old) && repo.find_commit(new.to_git2())?.is_conflicted() {
+            conflicted.push(old);
+        }
+    }
+    Ok(conflicted)
+}
+
+fn apply(
+    ctx: &CommandContext,
+    mut stack: Stack,
+    output: RebaseOutput,
+    perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    let repo = ctx.repo();
+    let BranchHeadAndTree {
+        head: new_head_oid,
+        tree: new_tree_oid,
+    } = compute_updated_branch_head_for_commits(
+        repo,
+        stack.head(),
+        stack.tree,
+        output.top_commit.to_git2(),
+    )?;
+    stack.set_stack_head(ctx, new_head_oid, Some(new_tree_oid))?;
+
+    let mut new_heads = HashMap::new();
+    for reference in output.references {
+        if let but_core::Reference::Virtual(name) = reference.reference {
+            new_heads.insert(name, repo.find_commit(reference.commit_id.to_git2())?);
+        }
+    }
+    stack.set_all_heads(ctx, new_heads)?;
+
+    checkout_branch_trees(ctx, perm)?;
+    crate::integration::update_workspace_commit(&ctx.project().virtual_branches(), ctx)
+        .context("failed to update gitbutler workspace")?;
+    Ok(())
+}
@@ -3,19 +3,29 @@ mod actions;
 // This is our API
 #[allow(deprecated)]
 pub use actions::{
-    amend, can_apply_remote_branch, create_commit, create_virtual_branch,
-    create_virtual_branch_from_branch, delete_local_branch, fetch_from_remotes, find_commit,
-    find_git_branches, get_uncommited_files, get_uncommited_files_reusable, insert_blank_commit,
-    integrate_upstream, integrate_upstream_commits, list_commit_files, list_virtual_branches,
-    list_virtual_branches_cached, move_commit, move_commit_file, push_base_branch,
+    abort_rebase, amend, amend_commit, can_apply_remote_branch, cherry_pick, create_commit,
+    create_virtual_branch, create_virtual_branch_from_branch, delete_branches, delete_local_branch,
+    discard_changes, fetch_from_remotes, find_commit, find_git_branches, get_uncommited_files,
+    get_uncommited_files_reusable, insert_blank_commit, integrate_upstream,
+    integrate_upstream_commits, list_commit_files, list_virtual_branches,
+    list_virtual_branches_cached, move_commit, move_commit_file, pending_rebase, push_base_branch,
     push_virtual_branch, reorder_stack, reset_files, reset_virtual_branch,
-    resolve_upstream_integration, save_and_unapply_virutal_branch, set_base_branch,
-    set_target_push_remote, squash_commits, unapply_lines, unapply_ownership,
+    resolve_upstream_integration, resume_rebase, revert_commit, reword_commit,
+    save_and_unapply_virutal_branch, set_base_branch, set_target_push_remote, squash_commits,
+    stale_branches, start_rebase, transfer_ownership, unapply_lines, unapply_ownership,
     unapply_without_saving_virtual_branch, undo_commit, update_branch_order, update_commit_message,
-    update_virtual_branch, upstream_integration_statuses,
+    update_from_base, update_virtual_branch, upstream_integration_statuses,
 };
 mod squash;
 
+mod cherry_pick;
+pub use cherry_pick::CherryPickOutcome;
+
+mod interactive_rebase;
+pub use interactive_rebase::{PendingRebase, RebaseAction, RebaseInstruction, RebaseStatus};
+
+mod rewrite;
+
 mod r#virtual;
 pub use r#virtual::{BranchStatus, VirtualBranch, VirtualBranchHunksByPathMap, VirtualBranches};
 /// Avoid using these!
@@ -37,6 +47,14 @@ pub use dependencies::compute_workspace_dependencies;
 
 pub mod upstream_integration;
 
+pub mod upstream_conflicts;
+
+pub mod session_commit;
+
+pub mod suspend;
+
+pub mod change_groups;
+
 mod integration;
 pub use integration::{update_workspace_commit, verify_branch};
 
@@ -55,6 +73,8 @@ pub use reorder::{SeriesOrder, StackOrder};
 mod undo_commit;
 
 mod author;
+mod branch_cleanup;
+pub use branch_cleanup::{StaleBranch, StaleReason};
 mod gravatar;
 mod status;
 use gitbutler_stack::VirtualBranchesHandle;
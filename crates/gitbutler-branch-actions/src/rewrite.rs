@@ -0,0 +1,163 @@
+//! Amend the head commit of a stack or reword any of its commits, with the commits above it rebased onto the
+//! rewritten one.
+//!
+//! Commits that were already pushed to a protected branch are only rewritten if forced, as the branch would have
+//! to be force-pushed afterwards.
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_headers::HasCommitHeaders;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::{
+    logging::{LogUntil, RepositoryExt as _},
+    RepositoryExt as _,
+};
+use gitbutler_repo_actions::RepoActionsExt as _;
+use gitbutler_stack::{Stack, StackId};
+
+use crate::conflicts::RepoConflictsExt as _;
+use crate::interactive_rebase::{self, RebaseAction, RebaseInstruction, RebaseStatus};
+use crate::VirtualBranchesExt as _;
+
+pub(crate) fn amend_head(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    paths: &[PathBuf],
+    message: Option<&str>,
+    force: bool,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<git2::Oid> {
+    if paths.is_empty() && message.is_none() {
+        bail!("there is nothing to amend, pass files or a message");
+    }
+    if message.is_some_and(str::is_empty) {
+        bail!("commit message can not be empty");
+    }
+    ctx.assure_resolved()?;
+    let repo = ctx.repo();
+    let vb_state = ctx.project().virtual_branches();
+    let mut stack = vb_state.get_stack_in_workspace(stack_id)?;
+    if stack_commits(ctx, &stack)?.is_empty() {
+        bail!("branch has no commits - there is nothing to amend");
+    }
+    let head = repo.find_commit(stack.head())?;
+    ensure_rewritable(ctx, &stack, head.id(), force)?;
+
+    let tree_id = if paths.is_empty() {
+        head.tree_id()
+    } else {
+        let status = crate::get_applied_status(ctx, None)?
+            .branches
+            .into_iter()
+            .find_map(|(stack, files)| (stack.id == stack_id).then_some(files))
+            .unwrap_or_default();
+        let mut diffs_to_amend = HashMap::new();
+        for path in paths {
+            let Some(file) = status.iter().find(|file| file.path == *path) else {
+                bail!(
+                    "'{}' has no uncommitted changes in this branch",
+                    path.display()
+                );
+            };
+            diffs_to_amend.insert(path.clone(), file.hunks.clone());
+        }
+        gitbutler_diff::write::hunks_onto_commit(ctx, head.id(), &diffs_to_amend)?
+    };
+
+    let parents: Vec<_> = head.parents().collect();
+    let new_head = repo
+        .commit_with_signature(
+            None,
+            &head.author(),
+            &head.committer(),
+            &message.map_or_else(|| head.message_bstr().to_str_lossy(), Into::into),
+            &repo.find_tree(tree_id)?,
+            &parents.iter().collect::<Vec<_>>(),
+            head.gitbutler_headers(),
+        )
+        .context("failed to create commit")?;
+    stack.set_stack_head(ctx, new_head, None)?;
+    crate::integration::update_workspace_commit(&vb_state, ctx)?;
+    Ok(new_head)
+}
+
+pub(crate) fn reword(
+    ctx: &CommandContext,
+    commit_id: git2::Oid,
+    message: &str,
+    force: bool,
+    perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    if message.is_empty() {
+        bail!("commit message can not be empty");
+    }
+    ctx.assure_unconflicted()?;
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let mut stack_and_commits = None;
+    for stack in vb_state.list_stacks_in_workspace()? {
+        let commits = stack_commits(ctx, &stack)?;
+        if commits.contains(&commit_id) {
+            stack_and_commits = Some((stack, commits));
+            break;
+        }
+    }
+    let Some((stack, commits)) = stack_and_commits else {
+        bail!("Commit {commit_id} is not part of any stack in the workspace");
+    };
+    ensure_rewritable(ctx, &stack, commit_id, force)?;
+
+    let instructions = commits
+        .into_iter()
+        .rev()
+        .map(|id| RebaseInstruction {
+            commit_id: id,
+            action: if id == commit_id {
+                RebaseAction::Reword {
+                    message: message.to_owned(),
+                }
+            } else {
+                RebaseAction::Pick
+            },
+        })
+        .collect();
+    let onto = ctx.repo().merge_base(stack.head(), default_target.sha)?;
+    match interactive_rebase::start(ctx, onto, instructions, perm)? {
+        RebaseStatus::Completed => Ok(()),
+        RebaseStatus::Conflicted(_) => {
+            interactive_rebase::abort(ctx)?;
+            bail!("rewording commit {commit_id} would conflict the commits above it")
+        }
+    }
+}
+
+/// Return the commits of `stack` that aren't part of the target branch, newest first.
+fn stack_commits(ctx: &CommandContext, stack: &Stack) -> Result<Vec<git2::Oid>> {
+    let repo = ctx.repo();
+    let default_target = ctx.project().virtual_branches().get_default_target()?;
+    let merge_base = repo.merge_base(stack.head(), default_target.sha)?;
+    repo.l(stack.head(), LogUntil::Commit(merge_base), false)
+}
+
+/// Fail with [`Code::ProtectedBranch`](gitbutler_error::error::Code::ProtectedBranch) if `commit_id` of `stack`
+/// was already pushed to a protected branch, unless `force` is set.
+fn ensure_rewritable(
+    ctx: &CommandContext,
+    stack: &Stack,
+    commit_id: git2::Oid,
+    force: bool,
+) -> Result<()> {
+    let (Some(upstream), Some(upstream_head)) = (&stack.upstream, stack.upstream_head) else {
+        return Ok(());
+    };
+    let repo = ctx.repo();
+    let pushed =
+        upstream_head == commit_id || repo.graph_descendant_of(upstream_head, commit_id)?;
+    if force || !pushed {
+        return Ok(());
+    }
+    ctx.ensure_unprotected_remote_branch(upstream, "rewrite commits pushed to")
+}
@@ -0,0 +1,60 @@
+//! Turn what changed during a period of activity into a commit, to bridge the passive history recorded by
+//! snapshots and the history of the branches.
+use anyhow::{bail, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use gitbutler_oplog::OplogExt;
+use gitbutler_stack::{BranchOwnershipClaims, OwnershipClaim};
+
+/// Commit the uncommitted changes to all files that changed during the session that started at
+/// `session_start` seconds since the Unix epoch, leaving changes to all other files alone.
+///
+/// All of these changes have to belong to the same branch. Note that files are committed with all of their
+/// changes, including the ones that were made after the session.
+pub fn commit_session(
+    ctx: &CommandContext,
+    session_start: i64,
+    message: &str,
+) -> Result<git2::Oid> {
+    let paths = ctx
+        .project()
+        .session_changed_paths(session_start)?
+        .with_context(|| format!("No session started at {session_start}"))?;
+    if paths.is_empty() {
+        bail!("Nothing changed in the worktree during the session");
+    }
+
+    let branches = crate::list_virtual_branches(ctx)?.branches;
+    let mut owners = branches.iter().filter_map(|branch| {
+        let claims: Vec<_> = branch
+            .files
+            .iter()
+            .filter(|file| paths.contains(&file.path))
+            .map(|file| OwnershipClaim {
+                file_path: file.path.clone(),
+                hunks: file
+                    .hunks
+                    .iter()
+                    .map(|hunk| Hunk {
+                        hash: None,
+                        start: hunk.start,
+                        end: hunk.end,
+                    })
+                    .collect(),
+            })
+            .collect();
+        (!claims.is_empty()).then_some((branch.id, claims))
+    });
+    let Some((stack_id, claims)) = owners.next() else {
+        bail!("The changes of the session were already committed or discarded");
+    };
+    if owners.next().is_some() {
+        bail!("The changes of the session belong to multiple branches, move them to a single branch first");
+    }
+    crate::create_commit(
+        ctx,
+        stack_id,
+        message,
+        Some(&BranchOwnershipClaims { claims }),
+    )
+}
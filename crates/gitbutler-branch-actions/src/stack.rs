@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result};
 use gitbutler_command_context::CommandContext;
@@ -6,6 +6,7 @@ use gitbutler_commit::commit_ext::CommitExt;
 use gitbutler_oplog::entry::{OperationKind, SnapshotDetails};
 use gitbutler_oplog::{OplogExt, SnapshotExt};
 use gitbutler_reference::normalize_branch_name;
+use gitbutler_repo_actions::push_safety::{self, PushRefusal};
 use gitbutler_repo_actions::RepoActionsExt;
 use gitbutler_stack::stack_context::{CommandContextExt, StackContext};
 use gitbutler_stack::{CommitOrChangeId, PatchReferenceUpdate, StackBranch};
@@ -172,9 +173,15 @@ pub fn update_series_pr_number(
     stack.set_pr_number(ctx, &head_name, pr_number)
 }
 
-/// Pushes all series in the stack to the remote.
+/// Pushes all series in the stack to the remote, along with the tags pointing to commits of the stack if
+/// `with_tags` is set.
 /// This operation will error out if the target has no push remote configured.
-pub fn push_stack(ctx: &CommandContext, stack_id: StackId, with_force: bool) -> Result<()> {
+pub fn push_stack(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    with_force: bool,
+    with_tags: bool,
+) -> Result<()> {
     ctx.verify()?;
     assure_open_workspace_mode(ctx).context("Requires an open workspace mode")?;
     let state = ctx.project().virtual_branches();
@@ -182,8 +189,8 @@ pub fn push_stack(ctx: &CommandContext, stack_id: StackId, with_force: bool) ->
 
     let repo = ctx.repo();
     let default_target = state.get_default_target()?;
-    let merge_base = repo.find_commit(repo.merge_base(stack.head(), default_target.sha)?)?;
-    let merge_base: CommitOrChangeId = merge_base.into();
+    let merge_base_id = repo.merge_base(stack.head(), default_target.sha)?;
+    let merge_base: CommitOrChangeId = repo.find_commit(merge_base_id)?.into();
 
     // First fetch, because we dont want to push integrated series
     ctx.fetch(
@@ -217,9 +224,57 @@ pub fn push_stack(ctx: &CommandContext, stack_id: StackId, with_force: bool) ->
             Some(Some(stack.id)),
         )?
     }
+
+    if with_tags {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(stack.head())?;
+        revwalk.hide(merge_base_id)?;
+        let stack_commits = revwalk.collect::<Result<HashSet<_>, _>>()?;
+        for tag in gitbutler_repo::tags::list_tags(repo)? {
+            if stack_commits.contains(&tag.target_id) {
+                ctx.push_tag(
+                    &default_target.push_remote_name(),
+                    &tag.name,
+                    Some(Some(stack.id)),
+                )?;
+            }
+        }
+    }
     Ok(())
 }
 
+/// Return why pushing the series of the stack with `stack_id`, forced if `with_force` is set, would be refused,
+/// for each series it would be refused for, judging by the remote-tracking branches as of the last fetch.
+pub fn check_push_stack(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    with_force: bool,
+) -> Result<Vec<PushRefusal>> {
+    ctx.verify()?;
+    let state = ctx.project().virtual_branches();
+    let stack = state.get_stack(stack_id)?;
+    let repo = ctx.repo();
+    let default_target = state.get_default_target()?;
+    let merge_base: CommitOrChangeId = repo
+        .find_commit(repo.merge_base(stack.head(), default_target.sha)?)?
+        .into();
+
+    let mut refusals = Vec::new();
+    for branch in stack.branches() {
+        if branch.archived || branch.head == merge_base {
+            continue;
+        }
+        let push_details = stack.push_details(ctx, branch.name)?;
+        refusals.extend(push_safety::check(
+            ctx,
+            push_details.head,
+            &push_details.remote_refname,
+            with_force,
+        )?);
+    }
+    Ok(refusals)
+}
+
 pub(crate) fn branch_integrated(
     check_commit: &mut IsCommitIntegrated,
     branch: &StackBranch,
@@ -12,8 +12,8 @@ use crate::{
 use anyhow::{bail, Context, Result};
 use gitbutler_branch::BranchCreateRequest;
 use gitbutler_command_context::CommandContext;
-use gitbutler_diff::{diff_files_into_hunks, Hunk};
-use gitbutler_hunk_dependency::locks::HunkDependencyResult;
+use gitbutler_diff::{diff_files_into_hunks, Hunk, HunkHash};
+use gitbutler_hunk_dependency::locks::{HunkDependencyResult, HunkLock};
 use gitbutler_operating_modes::assure_open_workspace_mode;
 use gitbutler_project::access::WorktreeWritePermission;
 use gitbutler_stack::{BranchOwnershipClaims, OwnershipClaim, Stack, StackId};
@@ -92,6 +92,7 @@ pub fn get_applied_status_cached(
         compute_workspace_dependencies(ctx, &default_target.sha, &base_diffs, &virtual_branches)?;
 
     let diff_dependencies = &workspace_dependencies.diffs;
+    let lock_owners = assign_locked_hunks(&mut virtual_branches, &base_diffs, diff_dependencies);
 
     for branch in &mut virtual_branches {
         // This should never be invoked. But if it is, dont try to  make the branch name unique
@@ -118,6 +119,13 @@ pub fn get_applied_status_cached(
                                 if diff_dependencies.contains_key(&hash) {
                                     return None; // Defer allocation to unclaimed hunks processing
                                 }
+                                if lock_owners
+                                    .get(&claim.file_path)
+                                    .and_then(|owners| owners.get(&hash))
+                                    .is_some_and(|owner| *owner != branch.id)
+                                {
+                                    continue; // Locked to another branch
+                                }
                                 diffs_by_branch
                                     .entry(branch.id)
                                     .or_default()
@@ -168,6 +176,9 @@ pub fn get_applied_status_cached(
         for hunk in hunks {
             let hash = Hunk::hash_diff(&hunk.diff_lines);
             let locked_to = diff_dependencies.get(&hash);
+            let owned_by = lock_owners
+                .get(&filepath)
+                .and_then(|owners| owners.get(&hash));
 
             let vbranch_pos = if let Some(locks) = locked_to {
                 let p = virtual_branches
@@ -177,6 +188,11 @@ pub fn get_applied_status_cached(
                     Some(p) => p,
                     _ => default_vbranch_pos,
                 }
+            } else if let Some(owner) = owned_by {
+                virtual_branches
+                    .iter()
+                    .position(|vb| vb.id == *owner)
+                    .unwrap_or(default_vbranch_pos)
             } else {
                 default_vbranch_pos
             };
@@ -241,3 +257,49 @@ pub fn get_applied_status_cached(
         workspace_dependencies,
     })
 }
+
+/// Return the branch each hunk in `base_diffs` that overlaps with the
+/// [locked ownership](Stack::locked_ownership) of a branch is locked to, by file and hash, and update the locks
+/// of all branches to these hunks as they are now.
+///
+/// Hunks that depend on commits are left to their dependencies, and a hunk overlapping with the locks of several
+/// branches is locked to the first of them.
+fn assign_locked_hunks(
+    branches: &mut [Stack],
+    base_diffs: &HashMap<PathBuf, Vec<gitbutler_diff::GitHunk>>,
+    diff_dependencies: &HashMap<HunkHash, Vec<HunkLock>>,
+) -> HashMap<PathBuf, HashMap<HunkHash, StackId>> {
+    let mut owners: HashMap<PathBuf, HashMap<HunkHash, StackId>> = HashMap::new();
+    for branch in branches {
+        let mut locks = BranchOwnershipClaims::default();
+        for claim in &branch.locked_ownership.claims {
+            let Some(git_diff_hunks) = base_diffs.get(&claim.file_path) else {
+                continue;
+            };
+            for git_diff_hunk in git_diff_hunks.iter().filter(|git_diff_hunk| {
+                claim
+                    .hunks
+                    .iter()
+                    .any(|hunk| hunk.intersects(git_diff_hunk))
+            }) {
+                let hash = Hunk::hash_diff(&git_diff_hunk.diff_lines);
+                if diff_dependencies.contains_key(&hash) {
+                    continue;
+                }
+                let owner = owners
+                    .entry(claim.file_path.clone())
+                    .or_default()
+                    .entry(hash)
+                    .or_insert(branch.id);
+                if *owner == branch.id {
+                    locks.put(OwnershipClaim {
+                        file_path: claim.file_path.clone(),
+                        hunks: vec![Hunk::from(git_diff_hunk)],
+                    });
+                }
+            }
+        }
+        branch.locked_ownership = locks;
+    }
+    owners
+}
@@ -0,0 +1,285 @@
+//! Put all uncommitted changes of the workspace aside under a name and bring them back later, to switch between
+//! tasks more completely than `git stash` does.
+//!
+//! A suspended workspace is a commit under the [prefix of its project](suspended_refs_prefix()) whose parent is the
+//! workspace commit it was suspended on. Its tree holds the worktree, the index, and the state of the virtual
+//! branches with the lanes each change was assigned to. Untracked files larger than [`AUTO_TRACK_LIMIT_BYTES`]
+//! aren't suspended, and stay where they are.
+use std::{collections::BTreeSet, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_operating_modes::assure_open_workspace_mode;
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    OplogExt,
+};
+use gitbutler_project::{Project, AUTO_TRACK_LIMIT_BYTES};
+use gitbutler_repo::{RepositoryExt, SignaturePurpose};
+use gitbutler_stack::VirtualBranchesState;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::actions::journaled;
+
+/// The prefix of the refs holding suspended workspaces, like `refs/gitbutler/suspended/feature`.
+pub const SUSPENDED_REFS_PREFIX: &str = "refs/gitbutler/suspended/";
+
+/// The prefix of the refs holding the suspended workspaces of `project`, which is [`SUSPENDED_REFS_PREFIX`] unless
+/// it's a [sub-project](Project::namespaced_ref).
+pub fn suspended_refs_prefix(project: &Project) -> String {
+    project.namespaced_ref(SUSPENDED_REFS_PREFIX)
+}
+
+const WORKTREE_ENTRY: &str = "worktree";
+const INDEX_ENTRY: &str = "index";
+const VIRTUAL_BRANCHES_ENTRY: &str = "virtual_branches.toml";
+
+/// A workspace whose uncommitted changes were put aside with [`suspend_workspace()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuspendedWorkspace {
+    pub name: String,
+    /// When the workspace was suspended, in seconds since the Unix epoch.
+    pub suspended_at: i64,
+    /// The worktree-relative paths of the files with changes that were suspended, sorted.
+    pub paths: Vec<PathBuf>,
+}
+
+/// What [`resume_workspace()`] brought back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumedWorkspace {
+    /// The worktree-relative paths of the files with changes that were restored, sorted.
+    pub paths: Vec<PathBuf>,
+    /// If `false`, something was committed since the workspace was suspended, so the changes were merged into the
+    /// worktree but nothing was staged.
+    pub index_restored: bool,
+}
+
+/// Put the uncommitted changes of the worktree and the index aside as the suspended workspace `name`, and discard
+/// them along with the lanes they were assigned to.
+pub fn suspend_workspace(ctx: &CommandContext, name: &str) -> Result<SuspendedWorkspace> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx)
+        .context("Suspending the workspace requires open workspace mode")?;
+    let reference = suspended_ref(ctx.project(), name)?;
+    journaled(
+        ctx,
+        OperationKind::SuspendWorkspace,
+        [("name", name.to_owned())],
+        || {
+            let repo = ctx.repo();
+            if repo.find_reference(&reference).is_ok() {
+                bail!("A workspace named '{name}' is already suspended");
+            }
+            let mut guard = ctx.project().exclusive_worktree_access();
+            let head = repo.head()?.peel_to_commit()?;
+            let head_tree = head.tree()?;
+            let worktree = repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?;
+            let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            let paths = changed_paths(repo, &head_tree, &worktree)?;
+            if paths.is_empty() && index_tree.id() == head_tree.id() {
+                bail!("There are no uncommitted changes to suspend");
+            }
+            let _ = ctx.project().create_snapshot(
+                SnapshotDetails::new(OperationKind::SuspendWorkspace).with_trailers(vec![
+                    Trailer {
+                        key: "name".to_string(),
+                        value: name.to_owned(),
+                    },
+                ]),
+                guard.write_permission(),
+            );
+
+            let virtual_branches =
+                std::fs::read(ctx.project().gb_dir().join(VIRTUAL_BRANCHES_ENTRY))?;
+            let mut tree = repo.treebuilder(None)?;
+            tree.insert(WORKTREE_ENTRY, worktree.id(), git2::FileMode::Tree.into())?;
+            tree.insert(INDEX_ENTRY, index_tree.id(), git2::FileMode::Tree.into())?;
+            tree.insert(
+                VIRTUAL_BRANCHES_ENTRY,
+                repo.blob(&virtual_branches)?,
+                git2::FileMode::Blob.into(),
+            )?;
+            let tree = repo.find_tree(tree.write()?)?;
+            let author = gitbutler_repo::signature(SignaturePurpose::Author)?;
+            let committer = gitbutler_repo::signature(SignaturePurpose::Committer)?;
+            let commit_id = repo.commit(
+                Some(&reference),
+                &author,
+                &committer,
+                &format!("Suspended workspace {name}"),
+                &tree,
+                &[&head],
+            )?;
+
+            // Without paths, everything would be checked out, including untracked files that weren't suspended.
+            if !paths.is_empty() {
+                crate::r#virtual::discard_changes(ctx, &paths, guard.write_permission())?;
+            }
+            let mut index = repo.index()?;
+            index.read_tree(&head_tree)?;
+            index.write()?;
+            Ok(SuspendedWorkspace {
+                name: name.to_owned(),
+                suspended_at: repo.find_commit(commit_id)?.time().seconds(),
+                paths,
+            })
+        },
+    )
+}
+
+/// Restore the uncommitted changes of the suspended workspace `name` along with the lanes they were assigned to,
+/// and forget it.
+///
+/// The worktree must not have uncommitted changes. If something was committed since the workspace was suspended,
+/// its changes are merged with what was committed, which fails if they conflict.
+pub fn resume_workspace(ctx: &CommandContext, name: &str) -> Result<ResumedWorkspace> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Resuming a workspace requires open workspace mode")?;
+    let reference = suspended_ref(ctx.project(), name)?;
+    journaled(
+        ctx,
+        OperationKind::ResumeWorkspace,
+        [("name", name.to_owned())],
+        || {
+            let repo = ctx.repo();
+            let suspended = repo
+                .find_reference(&reference)
+                .and_then(|reference| reference.peel_to_commit())
+                .ok()
+                .with_context(|| format!("No workspace named '{name}' is suspended"))?;
+            let mut guard = ctx.project().exclusive_worktree_access();
+            let head = repo.head()?.peel_to_commit()?;
+            let head_tree = head.tree()?;
+            let uncommitted = changed_paths(
+                repo,
+                &head_tree,
+                &repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?,
+            )?;
+            if !uncommitted.is_empty() {
+                bail!("Suspend or commit the uncommitted changes before resuming '{name}'");
+            }
+
+            let tree = suspended.tree()?;
+            let entry = |entry| {
+                tree.get_name(entry)
+                    .with_context(|| format!("The suspended workspace has no '{entry}'"))
+            };
+            let mut worktree = repo.find_tree(entry(WORKTREE_ENTRY)?.id())?;
+            let base = suspended.parent(0)?;
+            let index_restored = base.id() == head.id();
+            if !index_restored {
+                let mut merged = repo.merge_trees(&base.tree()?, &head_tree, &worktree, None)?;
+                if merged.has_conflicts() {
+                    let conflicts = merged
+                        .conflicts()?
+                        .filter_map(Result::ok)
+                        .filter_map(|conflict| conflict.our.or(conflict.their))
+                        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                        .join(", ");
+                    bail!("The suspended changes conflict with what was committed since in {conflicts}");
+                }
+                worktree = repo.find_tree(merged.write_tree_to(repo)?)?;
+            }
+            let paths = changed_paths(repo, &head_tree, &worktree)?;
+            let _ = ctx.project().create_snapshot(
+                SnapshotDetails::new(OperationKind::ResumeWorkspace).with_trailers(vec![Trailer {
+                    key: "name".to_string(),
+                    value: name.to_owned(),
+                }]),
+                guard.write_permission(),
+            );
+
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout.force();
+            repo.checkout_tree(worktree.as_object(), Some(&mut checkout))
+                .context("failed to check out the suspended changes")?;
+            // Checking out staged everything, so the index is reset to what it was, or to nothing staged.
+            let index_tree = if index_restored {
+                repo.find_tree(entry(INDEX_ENTRY)?.id())?
+            } else {
+                head_tree
+            };
+            let mut index = repo.index()?;
+            index.read_tree(&index_tree)?;
+            index.write()?;
+
+            let virtual_branches = repo.find_blob(entry(VIRTUAL_BRANCHES_ENTRY)?.id())?;
+            let suspended_state: VirtualBranchesState =
+                toml::from_str(std::str::from_utf8(virtual_branches.content())?)
+                    .context("failed to read the lanes of the suspended workspace")?;
+            let vb_state = ctx.project().virtual_branches();
+            for mut stack in vb_state.list_stacks_in_workspace()? {
+                // Changes of lanes that were removed meanwhile end up in the default lane.
+                if let Some(suspended) = suspended_state.branches.get(&stack.id) {
+                    stack.ownership = suspended.ownership.clone();
+                    stack.locked_ownership = suspended.locked_ownership.clone();
+                    vb_state.set_stack(stack)?;
+                }
+            }
+
+            repo.find_reference(&reference)?.delete()?;
+            Ok(ResumedWorkspace {
+                paths,
+                index_restored,
+            })
+        },
+    )
+}
+
+/// Return the suspended workspaces of the project of `ctx`, sorted by name.
+pub fn list_suspended_workspaces(ctx: &CommandContext) -> Result<Vec<SuspendedWorkspace>> {
+    let repo = ctx.repo();
+    let prefix = suspended_refs_prefix(ctx.project());
+    let mut workspaces = Vec::new();
+    for reference in repo.references_glob(&format!("{prefix}*"))? {
+        let reference = reference?;
+        let Some(name) = reference
+            .name()
+            .and_then(|name| name.strip_prefix(prefix.as_str()))
+        else {
+            continue;
+        };
+        let commit = reference.peel_to_commit()?;
+        let worktree = commit
+            .tree()?
+            .get_name(WORKTREE_ENTRY)
+            .with_context(|| format!("The suspended workspace '{name}' has no worktree"))?
+            .id();
+        workspaces.push(SuspendedWorkspace {
+            name: name.to_owned(),
+            suspended_at: commit.time().seconds(),
+            paths: changed_paths(repo, &commit.parent(0)?.tree()?, &repo.find_tree(worktree)?)?,
+        });
+    }
+    workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(workspaces)
+}
+
+/// Return the ref of the suspended workspace `name` of `project`, or fail if it isn't a valid name.
+fn suspended_ref(project: &Project, name: &str) -> Result<String> {
+    let reference = format!("{}{name}", suspended_refs_prefix(project));
+    if name.trim().is_empty() || !git2::Reference::is_valid_name(&reference) {
+        bail!("'{name}' isn't a valid name for a suspended workspace");
+    }
+    Ok(reference)
+}
+
+/// Return the paths of all files that differ between `old` and `new`, sorted.
+fn changed_paths(
+    repo: &git2::Repository,
+    old: &git2::Tree,
+    new: &git2::Tree,
+) -> Result<Vec<PathBuf>> {
+    let diff = repo.diff_tree_to_tree(Some(old), Some(new), None)?;
+    Ok(diff
+        .deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(ToOwned::to_owned)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,86 @@
+//! Predict which changes in the workspace would conflict with new commits on the target branch,
+//! so users can integrate upstream early instead of discovering conflicts when pushing.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::CommandContext;
+use gitbutler_repo::logging::{LogUntil, RepositoryExt as _};
+use gitbutler_repo::RepositoryExt as _;
+use gitbutler_stack::StackId;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt as _;
+
+/// A file that changed in the workspace and upstream in ways that can't be merged cleanly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskyFile {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The stacks whose changes to the file conflict with upstream.
+    pub stack_ids: Vec<StackId>,
+}
+
+/// The outcome of [`predict()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamConflicts {
+    /// The number of upstream commits that aren't integrated into the workspace yet.
+    pub upstream_commits: usize,
+    /// Files that would conflict when integrating upstream, sorted by path.
+    pub risky_files: Vec<RiskyFile>,
+}
+
+/// Merge the tree of each stack in the workspace, including its uncommitted changes, with the most recent
+/// commit on the target branch, and collect the files that would conflict.
+///
+/// Nothing is written to the worktree or to the workspace. Returns `None` if the target branch
+/// has no new commits.
+pub fn predict(ctx: &CommandContext) -> Result<Option<UpstreamConflicts>> {
+    let repo = ctx.repo();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let upstream_head = repo
+        .maybe_find_branch_by_refname(&target.branch.clone().into())?
+        .ok_or(anyhow!("Branch not found"))?
+        .get()
+        .peel_to_commit()?;
+    if upstream_head.id() == target.sha {
+        return Ok(None);
+    }
+    let upstream_commits = repo
+        .l(upstream_head.id(), LogUntil::Commit(target.sha), false)?
+        .len();
+
+    let base_tree = repo.find_commit(target.sha)?.tree()?;
+    let upstream_tree = upstream_head.tree()?;
+    let mut risky_files: Vec<RiskyFile> = Vec::new();
+    for stack in vb_state.list_stacks_in_workspace()? {
+        let stack_tree = repo.find_tree(stack.tree)?;
+        let merge_index = repo.merge_trees(&base_tree, &stack_tree, &upstream_tree, None)?;
+        if !merge_index.has_conflicts() {
+            continue;
+        }
+        for conflict in merge_index.conflicts()? {
+            let conflict = conflict?;
+            let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) else {
+                continue;
+            };
+            let path = gix::path::from_bstr(entry.path.as_bstr()).into_owned();
+            match risky_files.iter_mut().find(|file| file.path == path) {
+                Some(file) => file.stack_ids.push(stack.id),
+                None => risky_files.push(RiskyFile {
+                    path,
+                    stack_ids: vec![stack.id],
+                }),
+            }
+        }
+    }
+    risky_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(Some(UpstreamConflicts {
+        upstream_commits,
+        risky_files,
+    }))
+}
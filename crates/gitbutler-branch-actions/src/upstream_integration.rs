@@ -493,6 +493,83 @@ pub(crate) fn integrate_upstream(
     Ok(())
 }
 
+/// How a stack was updated by [`update_from_base()`].
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StackUpdate {
+    pub stack_id: StackId,
+    pub approach: ResolutionApproach,
+    /// If commits or the uncommitted changes of the stack conflicted with the new base, and have to be
+    /// resolved.
+    pub conflicted: bool,
+}
+
+/// Update all applied stacks to the latest commit of the target branch, with `strategy`, which must be
+/// [`Rebase`](ResolutionApproach::Rebase) or [`Merge`](ResolutionApproach::Merge), and return how each of them was
+/// updated.
+///
+/// Stacks of several branches are always rebased, as merging the base into them isn't supported, and stacks whose
+/// branches are all integrated, without uncommitted changes, are unapplied. Uncommitted changes are carried over
+/// onto the updated stacks, or committed if they conflict.
+pub(crate) fn update_from_base(
+    command_context: &CommandContext,
+    strategy: ResolutionApproach,
+    permission: &mut WorktreeWritePermission,
+) -> Result<Vec<StackUpdate>> {
+    if !matches!(
+        strategy,
+        ResolutionApproach::Rebase | ResolutionApproach::Merge
+    ) {
+        bail!("Stacks can only be updated from the base branch by rebasing or merging");
+    }
+
+    let (resolutions, updates) = {
+        let context = UpstreamIntegrationContext::open(command_context, None, permission)?;
+        let StackStatuses::UpdatesRequired(statuses) = upstream_integration_statuses(&context)?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut resolutions = Vec::new();
+        let mut updates = Vec::new();
+        for stack in &context.stacks_in_workspace {
+            let (_, status) = statuses
+                .iter()
+                .find(|(stack_id, _)| *stack_id == stack.id)
+                .context("Failed to find the status of the stack")?;
+            let approach = [
+                strategy,
+                ResolutionApproach::Rebase,
+                ResolutionApproach::Unapply,
+            ]
+            .into_iter()
+            .find(|approach| status.resolution_acceptable(approach))
+            .context("Failed to find a way to update the stack")?;
+            let conflicted = approach != ResolutionApproach::Unapply
+                && (status.tree_status == TreeStatus::Conflicted
+                    || status
+                        .branch_statuses
+                        .iter()
+                        .any(|branch| matches!(branch.status, BranchStatus::Conflicted { .. })));
+
+            resolutions.push(Resolution {
+                branch_id: stack.id,
+                branch_tree: stack.tree,
+                approach,
+            });
+            updates.push(StackUpdate {
+                stack_id: stack.id,
+                approach,
+                conflicted,
+            });
+        }
+        (resolutions, updates)
+    };
+
+    integrate_upstream(command_context, &resolutions, None, permission)?;
+    Ok(updates)
+}
+
 pub(crate) fn resolve_upstream_integration(
     command_context: &CommandContext,
     resolution_approach: BaseBranchResolutionApproach,
@@ -34,8 +34,8 @@ use gitbutler_repo::{
 };
 use gitbutler_repo_actions::RepoActionsExt;
 use gitbutler_stack::{
-    reconcile_claims, stack_context::CommandContextExt, BranchOwnershipClaims, Stack, StackId,
-    Target, VirtualBranchesHandle,
+    reconcile_claims, stack_context::CommandContextExt, BranchOwnershipClaims, OwnershipClaim,
+    Stack, StackId, Target, VirtualBranchesHandle,
 };
 use gitbutler_time::time::now_since_unix_epoch_ms;
 use itertools::Itertools;
@@ -279,6 +279,51 @@ pub(crate) fn reset_files(
     unapply_ownership(ctx, &BranchOwnershipClaims { claims }, None, perm)?;
     Ok(())
 }
+
+/// Restore `paths` to how they are in `HEAD`, discarding their uncommitted changes no matter which stacks own them,
+/// and delete them if they are untracked. Nothing happens without `paths`.
+pub(crate) fn discard_changes(
+    ctx: &CommandContext,
+    paths: &[PathBuf],
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    // An empty pathspec would match, and thus check out, everything.
+    if paths.is_empty() {
+        return Ok(());
+    }
+    ctx.assure_resolved()?;
+
+    let repo = ctx.repo();
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout
+        .force()
+        .remove_untracked(true)
+        .disable_pathspec_match(true);
+    for path in paths {
+        checkout.path(path);
+    }
+    repo.checkout_tree(head_tree.as_object(), Some(&mut checkout))
+        .context("failed to check out the files from HEAD")?;
+
+    let vb_state = ctx.project().virtual_branches();
+    for mut stack in vb_state.list_stacks_in_workspace()? {
+        let claims = stack.ownership.claims.len() + stack.locked_ownership.claims.len();
+        stack
+            .ownership
+            .claims
+            .retain(|claim| !paths.contains(&claim.file_path));
+        stack
+            .locked_ownership
+            .claims
+            .retain(|claim| !paths.contains(&claim.file_path));
+        if claims != stack.ownership.claims.len() + stack.locked_ownership.claims.len() {
+            vb_state.set_stack(stack)?;
+        }
+    }
+    Ok(())
+}
+
 fn find_base_tree<'a>(
     repo: &'a git2::Repository,
     branch_commit: &'a git2::Commit<'a>,
@@ -633,6 +678,42 @@ pub(crate) fn set_ownership(
         .list_stacks_in_workspace()
         .context("failed to read virtual branches")?;
 
+    // The hunks that are assigned to the branch with this update, which mustn't be locked to another branch.
+    let newly_claimed: Vec<OwnershipClaim> = ownership
+        .claims
+        .iter()
+        .filter_map(|claim| {
+            let owned: Vec<&Hunk> = target_branch
+                .ownership
+                .claims
+                .iter()
+                .filter(|owned| owned.file_path == claim.file_path)
+                .flat_map(|owned| &owned.hunks)
+                .collect();
+            let hunks: Vec<Hunk> = claim
+                .hunks
+                .iter()
+                .filter(|hunk| !owned.contains(hunk))
+                .cloned()
+                .collect();
+            (!hunks.is_empty()).then(|| OwnershipClaim {
+                file_path: claim.file_path.clone(),
+                hunks,
+            })
+        })
+        .collect();
+    for stack in stacks.iter().filter(|stack| stack.id != target_branch.id) {
+        if let Some(locked) = newly_claimed
+            .iter()
+            .find_map(|claim| stack.locked_ownership.overlapping(claim))
+        {
+            bail!(
+                "'{locked}' is owned by the branch '{}', and its ownership has to be transferred first",
+                stack.name
+            );
+        }
+    }
+
     let mut claim_outcomes = reconcile_claims(stacks, target_branch, &ownership.claims)?;
     for claim_outcome in &mut claim_outcomes {
         if !claim_outcome.removed_claims.is_empty() {
@@ -645,7 +726,46 @@ pub(crate) fn set_ownership(
     // Updates the claiming branch that was passed as mutable state with the new ownership claims
     // TODO: remove mutable reference to target_branch
     target_branch.ownership = ownership.clone();
+    for claim in newly_claimed {
+        target_branch.locked_ownership.put(claim);
+    }
+
+    Ok(())
+}
 
+/// Assign the hunks of `ownership` to the stack `stack_id`, even if they are locked to other stacks, and lock
+/// them to it instead.
+pub(crate) fn transfer_ownership(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    ownership: &BranchOwnershipClaims,
+) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+    for mut stack in vb_state
+        .list_stacks_in_workspace()
+        .context("failed to read virtual branches")?
+        .into_iter()
+        .filter(|stack| stack.id != stack_id)
+    {
+        let mut released = false;
+        for claim in &ownership.claims {
+            released |= stack.locked_ownership.remove_overlapping(claim);
+        }
+        if released {
+            vb_state.set_stack(stack)?;
+        }
+    }
+
+    let mut stack = vb_state.get_stack_in_workspace(stack_id)?;
+    let mut claims = stack.ownership.clone();
+    for claim in &ownership.claims {
+        claims.put(claim.clone());
+    }
+    set_ownership(&vb_state, &mut stack, &claims).context("failed to set ownership")?;
+    for claim in &ownership.claims {
+        stack.locked_ownership.put(claim.clone());
+    }
+    vb_state.set_stack(stack)?;
     Ok(())
 }
 
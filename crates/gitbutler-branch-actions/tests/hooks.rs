@@ -8,7 +8,9 @@ mod tests {
     use git2::{Repository, StatusOptions};
     use gitbutler_branch_actions::hooks;
     use gitbutler_diff::Hunk;
-    use gitbutler_repo::hooks::{ErrorData, HookResult, MessageData, MessageHookResult};
+    use gitbutler_repo::hooks::{
+        ErrorData, HookOutput, HookOutputStream, HookResult, MessageData, MessageHookResult,
+    };
     use gitbutler_stack::{BranchOwnershipClaims, OwnershipClaim};
     use gitbutler_testsupport::{Case, Suite};
 
@@ -22,9 +24,9 @@ mod tests {
 #!/bin/sh
 # do nothing
 ";
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_PRE_COMMIT, hook);
+        create_hook(ctx.repo(), "pre-commit", hook);
         assert_eq!(
-            hooks::pre_commit(ctx, &selected_hunks)?,
+            hooks::pre_commit(ctx, &selected_hunks, |_| {})?,
             HookResult::Success
         );
         Ok(())
@@ -37,7 +39,7 @@ mod tests {
 
         let selected_hunks = BranchOwnershipClaims { claims: vec![] };
         assert_eq!(
-            hooks::pre_commit(ctx, &selected_hunks)?,
+            hooks::pre_commit(ctx, &selected_hunks, |_| {})?,
             HookResult::NotConfigured
         );
         Ok(())
@@ -58,14 +60,17 @@ if echo "$STAGED_DIFF" | grep -qE "^\+.*forbidden"; then
     exit 1
 fi
 "#;
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_PRE_COMMIT, hook.as_bytes());
+        create_hook(ctx.repo(), "pre-commit", hook.as_bytes());
         std::fs::write(Path::new(&project.path).join("test.txt"), "forbidden\n")?;
 
         // While we have changed a file to include the forbidden word, the hook should not
         // fail if we pass no ownership claims. These claims are used to select what hunks
         // get committed.
         let ownership1 = BranchOwnershipClaims { claims: vec![] };
-        assert_eq!(hooks::pre_commit(ctx, &ownership1)?, HookResult::Success);
+        assert_eq!(
+            hooks::pre_commit(ctx, &ownership1, |_| {})?,
+            HookResult::Success
+        );
 
         // But when including the change in the ownerships the change will be staged, and
         // the hook therefore fails.
@@ -82,7 +87,7 @@ fi
 
         assert!(!is_file_staged(ctx.repo(), "test.txt")?);
         assert_eq!(
-            hooks::pre_commit(ctx, &ownership2)?,
+            hooks::pre_commit(ctx, &ownership2, |_| {})?,
             HookResult::Failure(ErrorData {
                 error: "rejected\n".to_owned()
             })
@@ -101,10 +106,10 @@ fi
 echo 'rejected'
 exit 1
 ";
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_POST_COMMIT, hook);
+        create_hook(ctx.repo(), "post-commit", hook);
 
         assert_eq!(
-            gitbutler_repo::hooks::post_commit(ctx)?,
+            gitbutler_repo::hooks::post_commit(ctx, |_| {})?,
             HookResult::Failure(ErrorData {
                 error: "rejected\n".to_owned()
             })
@@ -122,11 +127,11 @@ exit 1
 echo 'rejected'
 exit 1
 ";
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_COMMIT_MSG, hook);
+        create_hook(ctx.repo(), "commit-msg", hook);
 
         let message = "commit message".to_owned();
         assert_eq!(
-            gitbutler_repo::hooks::commit_msg(ctx, message)?,
+            gitbutler_repo::hooks::commit_msg(ctx, message, |_| {})?,
             MessageHookResult::Failure(ErrorData {
                 error: "rejected\n".to_owned()
             })
@@ -143,11 +148,11 @@ exit 1
 #!/bin/sh
 echo 'rewritten message' > $1
 ";
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_COMMIT_MSG, hook);
+        create_hook(ctx.repo(), "commit-msg", hook);
 
         let message = "commit message".to_owned();
         assert_eq!(
-            gitbutler_repo::hooks::commit_msg(ctx, message)?,
+            gitbutler_repo::hooks::commit_msg(ctx, message, |_| {})?,
             MessageHookResult::Message(MessageData {
                 message: "rewritten message\n".to_owned()
             })
@@ -164,16 +169,92 @@ echo 'rewritten message' > $1
 #!/bin/sh
 echo 'commit message' > $1
 ";
-        git2_hooks::create_hook(ctx.repo(), git2_hooks::HOOK_COMMIT_MSG, hook);
+        create_hook(ctx.repo(), "commit-msg", hook);
 
         let message = "commit message\n".to_owned();
         assert_eq!(
-            gitbutler_repo::hooks::commit_msg(ctx, message)?,
+            gitbutler_repo::hooks::commit_msg(ctx, message, |_| {})?,
             MessageHookResult::Success
         );
         Ok(())
     }
 
+    #[test]
+    fn hook_output_is_streamed() -> anyhow::Result<()> {
+        let suite = Suite::default();
+        let Case { ctx, .. } = &suite.new_case();
+
+        let hook = b"
+#!/bin/sh
+echo 'checking'
+echo 'warning' >&2
+";
+        create_hook(ctx.repo(), "post-commit", hook);
+
+        let mut output = Vec::new();
+        assert_eq!(
+            gitbutler_repo::hooks::post_commit(ctx, |line| output.push(line))?,
+            HookResult::Success
+        );
+        output.sort_by_key(|line| line.stream == HookOutputStream::Stderr);
+        assert_eq!(
+            output,
+            [
+                HookOutput {
+                    hook: "post-commit",
+                    stream: HookOutputStream::Stdout,
+                    line: "checking".into()
+                },
+                HookOutput {
+                    hook: "post-commit",
+                    stream: HookOutputStream::Stderr,
+                    line: "warning".into()
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hooks_path_is_honored() -> anyhow::Result<()> {
+        let suite = Suite::default();
+        let Case { ctx, project, .. } = &suite.new_case();
+
+        let hooks_dir = project.path.join("custom-hooks");
+        std::fs::create_dir_all(&hooks_dir)?;
+        let hook_path = hooks_dir.join("post-commit");
+        std::fs::write(&hook_path, "#!/bin/sh\necho 'custom'\nexit 1\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+        }
+        ctx.repo()
+            .config()?
+            .set_str("core.hooksPath", "custom-hooks")?;
+
+        assert_eq!(
+            gitbutler_repo::hooks::post_commit(ctx, |_| {})?,
+            HookResult::Failure(ErrorData {
+                error: "custom\n".to_owned()
+            })
+        );
+        Ok(())
+    }
+
+    /// Write the executable `hook` with `content` into the hooks directory of `repo`.
+    fn create_hook(repo: &Repository, hook: &str, content: &[u8]) {
+        let hooks_dir = repo.path().join("hooks");
+        std::fs::create_dir_all(&hooks_dir).unwrap();
+        let hook_path = hooks_dir.join(hook);
+        std::fs::write(&hook_path, content).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
     fn is_file_staged(repo: &Repository, file_path: &str) -> Result<bool, git2::Error> {
         let mut opts = StatusOptions::new();
         opts.show(git2::StatusShow::Index);
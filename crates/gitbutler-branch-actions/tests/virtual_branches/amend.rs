@@ -251,3 +251,98 @@ fn non_existing_ownership() {
         );
     }
 }
+
+#[test]
+fn head_commit_with_files_and_message() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+
+    fs::write(repository.path().join("file.txt"), "content")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("file2.txt"), "content2")?;
+    fs::write(repository.path().join("file3.txt"), "content3")?;
+
+    let commit_id = gitbutler_branch_actions::amend_commit(
+        ctx,
+        stack_entry.id,
+        &["file2.txt".into()],
+        Some("commit one amended"),
+        false,
+    )?;
+
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    let patches = branch.series[0].clone()?.patches;
+    assert_eq!(patches.len(), 1);
+    assert_eq!(patches[0].id, commit_id);
+    assert_eq!(patches[0].description, "commit one amended");
+    assert_eq!(list_commit_files(ctx, commit_id)?.len(), 2);
+    assert_eq!(branch.files.len(), 1, "only the given files were amended");
+    Ok(())
+}
+
+#[test]
+fn pushed_to_protected_branch_only_if_forced() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project_id,
+        projects,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    #[allow(deprecated)]
+    gitbutler_branch_actions::push_virtual_branch(ctx, stack_entry.id, false, None)?;
+
+    projects.update(&projects::UpdateRequest {
+        id: *project_id,
+        protected_branches: Some(vec!["*".into()]),
+        ..Default::default()
+    })?;
+    let ctx = &CommandContext::open(&projects.get(*project_id)?, AppSettings::default())?;
+
+    let err = gitbutler_branch_actions::amend_commit(
+        ctx,
+        stack_entry.id,
+        &[],
+        Some("commit one amended"),
+        false,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<gitbutler_error::error::Code>(),
+        Some(&gitbutler_error::error::Code::ProtectedBranch)
+    );
+
+    gitbutler_branch_actions::amend_commit(
+        ctx,
+        stack_entry.id,
+        &[],
+        Some("commit one amended"),
+        true,
+    )?;
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    assert!(branch.requires_force);
+    assert_eq!(
+        branch.series[0].clone()?.patches[0].description,
+        "commit one amended"
+    );
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+#[test]
+fn cherry_pick_applies_changes_to_worktree() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default()).unwrap();
+
+    let repo = ctx.repo();
+    let base = repo
+        .find_reference("refs/remotes/origin/master")?
+        .peel_to_commit()?;
+    let mut tree = repo.treebuilder(Some(&base.tree()?))?;
+    tree.insert("picked.txt", repo.blob(b"picked\n")?, 0o100644)?;
+    let tree = repo.find_tree(tree.write()?)?;
+    let signature = git2::Signature::now("test", "test@example.com")?;
+    let commit_id = repo.commit(None, &signature, &signature, "picked", &tree, &[&base])?;
+
+    let outcome = gitbutler_branch_actions::cherry_pick(ctx, commit_id)?;
+    assert!(outcome.conflicted_files.is_empty());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("picked.txt"))?,
+        "picked\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn revert_commit_undoes_changes_in_worktree() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content\n")?;
+    let commit_id =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+
+    let outcome = gitbutler_branch_actions::revert_commit(ctx, commit_id)?;
+    assert!(outcome.conflicted_files.is_empty());
+    assert!(!repository.path().join("file.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn revert_commit_leaves_conflict_markers() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "one\n")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("file.txt"), "two\n")?;
+    let commit_id =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None)?;
+    fs::write(repository.path().join("file.txt"), "three\n")?;
+
+    let outcome = gitbutler_branch_actions::revert_commit(ctx, commit_id)?;
+    assert_eq!(outcome.conflicted_files, [PathBuf::from("file.txt")]);
+    let content = fs::read_to_string(repository.path().join("file.txt"))?;
+    assert!(content.contains("<<<<<<<"), "{content}");
+    Ok(())
+}
@@ -86,7 +86,7 @@ fn integration() {
 
     {
         // should mark commits as integrated
-        gitbutler_branch_actions::fetch_from_remotes(ctx, None).unwrap();
+        gitbutler_branch_actions::fetch_from_remotes(ctx, None, &Default::default()).unwrap();
 
         let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
             .unwrap()
@@ -0,0 +1,67 @@
+use gitbutler_branch_actions::{delete_branches, set_base_branch, stale_branches, StaleReason};
+
+use super::*;
+
+#[test]
+fn finds_and_deletes_merged_and_gone_branches() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+    set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())?;
+
+    let repo = git2::Repository::open(repository.path())?;
+    let base = repo
+        .find_reference("refs/remotes/origin/master")?
+        .peel_to_commit()?;
+    let signature = git2::Signature::now("test", "test@example.com")?;
+    let unmerged = repo.commit(
+        None,
+        &signature,
+        &signature,
+        "unmerged",
+        &base.tree()?,
+        &[&base],
+    )?;
+    let unmerged = repo.find_commit(unmerged)?;
+    repo.branch("merged", &base, false)?;
+    repo.branch("unmerged", &unmerged, false)?;
+    repo.branch("gone", &unmerged, false)?;
+    let mut config = repo.config()?;
+    config.set_str("branch.gone.remote", "origin")?;
+    config.set_str("branch.gone.merge", "refs/heads/gone")?;
+
+    let mut stale: Vec<_> = stale_branches(ctx)?
+        .into_iter()
+        .map(|branch| (branch.name, branch.reason, branch.upstream))
+        .collect();
+    stale.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        stale,
+        [
+            (
+                "gone".to_owned(),
+                StaleReason::UpstreamGone,
+                Some("origin/gone".to_owned())
+            ),
+            ("merged".to_owned(), StaleReason::Merged, None),
+        ]
+    );
+
+    let err = delete_branches(ctx, &["merged".into(), "unmerged".into()], false).unwrap_err();
+    assert!(
+        err.to_string().contains("can only be deleted by force"),
+        "{err:#}"
+    );
+    assert!(
+        repo.find_branch("merged", git2::BranchType::Local).is_ok(),
+        "nothing is deleted if one of the branches can't be"
+    );
+    assert!(delete_branches(ctx, &["master".into()], true).is_err());
+
+    delete_branches(ctx, &["merged".into(), "unmerged".into()], true)?;
+    for name in ["merged", "unmerged"] {
+        assert!(repo.find_branch(name, git2::BranchType::Local).is_err());
+    }
+    assert!(repo.find_branch("gone", git2::BranchType::Local).is_ok());
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use gitbutler_oplog::OplogExt;
+
+use super::*;
+
+#[test]
+fn discarded_changes_can_be_restored() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        ctx,
+        project,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "committed")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None)?;
+
+    fs::write(repository.path().join("file.txt"), "changed")?;
+    fs::write(repository.path().join("new.txt"), "new")?;
+    fs::write(repository.path().join("kept.txt"), "kept")?;
+
+    let snapshot =
+        gitbutler_branch_actions::discard_changes(ctx, &["file.txt".into(), "new.txt".into()])?;
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "committed"
+    );
+    assert!(!repository.path().join("new.txt").exists());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("kept.txt"))?,
+        "kept",
+        "other changes are left alone"
+    );
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)?.branches;
+    assert_eq!(branches[0].files.len(), 1);
+
+    let mut guard = project.exclusive_worktree_access();
+    project.restore_snapshot(snapshot, guard.write_permission())?;
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "changed"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("new.txt"))?,
+        "new"
+    );
+    Ok(())
+}
+
+#[test]
+fn discarding_no_paths_is_an_error_and_leaves_the_worktree_alone() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "changed")?;
+    fs::write(repository.path().join("new.txt"), "new")?;
+
+    assert!(gitbutler_branch_actions::discard_changes(ctx, &[]).is_err());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "changed"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("new.txt"))?,
+        "new"
+    );
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)?.branches;
+    assert_eq!(branches[0].files.len(), 2);
+    Ok(())
+}
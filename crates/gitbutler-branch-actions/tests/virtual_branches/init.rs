@@ -22,7 +22,7 @@ fn twice() {
             .unwrap()
             .branches
             .is_empty());
-        projects.delete(project.id).unwrap();
+        projects.purge(project.id).unwrap();
         gitbutler_branch_actions::list_virtual_branches(&ctx).unwrap_err();
     }
 
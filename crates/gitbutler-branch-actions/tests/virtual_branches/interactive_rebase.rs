@@ -0,0 +1,182 @@
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_branch_actions::{RebaseAction, RebaseInstruction, RebaseStatus};
+use gitbutler_stack::StackId;
+
+use super::*;
+
+fn instruction(commit_id: git2::Oid, action: RebaseAction) -> RebaseInstruction {
+    RebaseInstruction { commit_id, action }
+}
+
+fn descriptions(ctx: &CommandContext, stack_id: StackId) -> Vec<String> {
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_id)
+        .unwrap();
+    branch.series[0]
+        .clone()
+        .unwrap()
+        .patches
+        .iter()
+        .map(|c| c.description.clone())
+        .collect()
+}
+
+fn base_commit(ctx: &CommandContext) -> git2::Oid {
+    ctx.repo()
+        .find_reference("refs/remotes/origin/master")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap()
+        .id()
+}
+
+#[test]
+fn reorder_reword_and_drop() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("a.txt"), "a\n")?;
+    let a = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit a", None)?;
+    fs::write(repository.path().join("b.txt"), "b\n")?;
+    let b = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit b", None)?;
+    fs::write(repository.path().join("c.txt"), "c\n")?;
+    let c = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit c", None)?;
+
+    let status = gitbutler_branch_actions::start_rebase(
+        ctx,
+        base_commit(ctx),
+        vec![
+            instruction(c, RebaseAction::Pick),
+            instruction(
+                a,
+                RebaseAction::Reword {
+                    message: "commit a, reworded".into(),
+                },
+            ),
+            instruction(b, RebaseAction::Drop),
+        ],
+    )?;
+    assert_eq!(status, RebaseStatus::Completed);
+    assert_eq!(
+        descriptions(ctx, stack_entry.id),
+        ["commit a, reworded", "commit c"]
+    );
+    assert!(repository.path().join("a.txt").exists());
+    assert!(!repository.path().join("b.txt").exists());
+    assert!(gitbutler_branch_actions::pending_rebase(ctx)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn squash_combines_messages() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("one.txt"), "one\n")?;
+    let one = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("two.txt"), "two\n")?;
+    let two = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None)?;
+
+    gitbutler_branch_actions::start_rebase(
+        ctx,
+        base_commit(ctx),
+        vec![
+            instruction(one, RebaseAction::Pick),
+            instruction(two, RebaseAction::Squash { message: None }),
+        ],
+    )?;
+    assert_eq!(
+        descriptions(ctx, stack_entry.id),
+        ["commit one\ncommit two"]
+    );
+    Ok(())
+}
+
+#[test]
+fn conflicts_are_pending_until_aborted() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "one\n")?;
+    let one = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("file.txt"), "two\n")?;
+    let two = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None)?;
+
+    let status = gitbutler_branch_actions::start_rebase(
+        ctx,
+        base_commit(ctx),
+        vec![
+            instruction(two, RebaseAction::Pick),
+            instruction(one, RebaseAction::Pick),
+        ],
+    )?;
+    let RebaseStatus::Conflicted(conflicted) = status else {
+        panic!("reordering commits that change the same lines conflicts");
+    };
+    assert!(!conflicted.is_empty());
+    assert!(gitbutler_branch_actions::pending_rebase(ctx)?.is_some());
+    assert_eq!(
+        descriptions(ctx, stack_entry.id),
+        ["commit two", "commit one"],
+        "nothing changes until the rebase is resumed"
+    );
+
+    gitbutler_branch_actions::abort_rebase(ctx)?;
+    assert!(gitbutler_branch_actions::pending_rebase(ctx)?.is_none());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "two\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn instructions_must_cover_the_stack() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("one.txt"), "one\n")?;
+    let one = gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("two.txt"), "two\n")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None)?;
+
+    let err = gitbutler_branch_actions::start_rebase(
+        ctx,
+        base_commit(ctx),
+        vec![instruction(one, RebaseAction::Pick)],
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("missing from the instructions"));
+    Ok(())
+}
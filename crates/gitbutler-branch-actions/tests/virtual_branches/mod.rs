@@ -60,10 +60,14 @@ impl Test {
 
 mod amend;
 mod apply_virtual_branch;
+mod cherry_pick;
 mod create_commit;
 mod create_virtual_branch_from_branch;
+mod delete_branches;
+mod discard_changes;
 mod init;
 mod insert_blank_commit;
+mod interactive_rebase;
 mod list;
 mod list_details;
 mod locking;
@@ -76,10 +80,14 @@ mod save_and_unapply_virtual_branch;
 mod selected_for_changes;
 mod set_base_branch;
 mod squash;
+mod suspend_workspace;
+mod transfer_ownership;
 mod unapply_ownership;
 mod unapply_without_saving_virtual_branch;
 mod undo_commit;
 mod update_commit_message;
+mod update_from_base;
 mod upstream;
+mod upstream_conflicts;
 mod verify_branch;
 mod workspace_migration;
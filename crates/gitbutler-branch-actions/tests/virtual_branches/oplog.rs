@@ -1,9 +1,14 @@
 use std::{io::Write, path::Path, time::Duration};
 
 use gitbutler_branch::BranchCreateRequest;
-use gitbutler_branch_actions::list_commit_files;
-use gitbutler_oplog::OplogExt;
+use gitbutler_branch_actions::{change_groups::DeltaSession, list_commit_files};
+use gitbutler_oplog::{
+    activity::SESSION_GAP_SECONDS,
+    entry::{OperationKind, SnapshotDetails},
+    journal, OplogExt,
+};
 use gitbutler_stack::VirtualBranchesHandle;
+use gitbutler_testsupport::timeline::record_delta;
 use itertools::Itertools;
 
 use super::*;
@@ -370,6 +375,7 @@ fn restores_gitbutler_workspace() -> anyhow::Result<()> {
 }
 
 // test operations-log.toml head is not a commit
+
 #[test]
 fn head_corrupt_is_recreated_automatically() {
     let Test {
@@ -410,3 +416,315 @@ fn head_corrupt_is_recreated_automatically() {
         "it should have just reset the oplog head, so only 1, not 2"
     );
 }
+
+#[test]
+fn commit_changes_of_a_session() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(
+        repository.path().join("session.txt"),
+        "during the session\n",
+    )?;
+    gitbutler_branch_actions::list_virtual_branches(ctx)?;
+    let mut guard = project.exclusive_worktree_access();
+    project.create_snapshot(
+        SnapshotDetails::new(OperationKind::FileChanges),
+        guard.write_permission(),
+    )?;
+    drop(guard);
+    // Not recorded by any snapshot, so not part of the session.
+    fs::write(repository.path().join("other.txt"), "not part of it\n")?;
+
+    let session = project.activity_summary(0..i64::MAX)?.sessions[0].clone();
+    assert_eq!(
+        project.session_changed_paths(session.start)?,
+        Some(vec!["session.txt".into()])
+    );
+    assert_eq!(project.session_changed_paths(session.start - 1)?, None);
+
+    let commit_id = gitbutler_branch_actions::session_commit::commit_session(
+        ctx,
+        session.start,
+        "the session",
+    )?;
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .into_iter()
+        .find(|branch| branch.id == stack_entry.id)
+        .unwrap();
+    assert_eq!(branch.head, commit_id);
+    let files = list_commit_files(ctx, commit_id)?;
+    assert_eq!(
+        files
+            .iter()
+            .map(|file| file.path.as_path())
+            .collect::<Vec<_>>(),
+        [Path::new("session.txt")]
+    );
+    assert_eq!(
+        branch
+            .files
+            .iter()
+            .map(|file| file.path.as_path())
+            .collect::<Vec<_>>(),
+        [Path::new("other.txt")],
+        "other changes stay uncommitted"
+    );
+    Ok(())
+}
+
+#[test]
+fn group_changes_by_session() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(
+        repository.path().join("session.txt"),
+        "during the session\n",
+    )?;
+    record_delta(project, 1_000, &["session.txt"], Some("main"))?;
+    fs::write(
+        repository.path().join("session.txt"),
+        "during the session\nand a bit later\n",
+    )?;
+    record_delta(project, 1_060, &["session.txt"], Some("main"))?;
+    fs::write(repository.path().join("later.txt"), "in another session\n")?;
+    let later = 1_060 + 2 * SESSION_GAP_SECONDS;
+    record_delta(project, later, &["later.txt"], Some("main"))?;
+    fs::write(repository.path().join("other.txt"), "not recorded yet\n")?;
+
+    let groups = gitbutler_branch_actions::change_groups::suggest(ctx)?;
+    assert_eq!(groups.len(), 3);
+    assert_eq!(
+        groups[0].session,
+        Some(DeltaSession {
+            start: 1_000,
+            end: 1_060,
+            branch: Some("main".into()),
+            deltas: 2,
+        })
+    );
+    assert_eq!(groups[0].stack_ids, [stack_entry.id]);
+    assert_eq!(groups[0].ownership.to_string(), "session.txt:1-3");
+    assert_eq!(
+        groups[1].session.as_ref().map(|session| session.start),
+        Some(later)
+    );
+    assert_eq!(groups[1].ownership.to_string(), "later.txt:1-2");
+    assert_eq!(groups[2].session, None, "unrecorded changes come last");
+    assert_eq!(groups[2].ownership.to_string(), "other.txt:1-2");
+    Ok(())
+}
+
+#[test]
+fn undo_last_operation_moves_branches_back() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry = gitbutler_branch_actions::create_virtual_branch(ctx, &Default::default())?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+
+    let entries = journal::entries(project)?;
+    let last = entries.last().expect("the commit was recorded");
+    assert_eq!(last.operation, OperationKind::CreateCommit);
+    assert!(last
+        .ref_updates
+        .iter()
+        .any(|update| update.name == "refs/heads/gitbutler/workspace"));
+
+    let mut guard = project.exclusive_worktree_access();
+    let undo = journal::undo_last_operation(project, guard.write_permission())?;
+    drop(guard);
+    assert_eq!(undo.operation, OperationKind::UndoOperation);
+    assert_eq!(undo.undoes, Some(entries.len() - 1));
+
+    let ctx = &CommandContext::open(project, AppSettings::default())?;
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    assert_eq!(branch.series[0].clone().unwrap().patches.len(), 0);
+    assert_eq!(branch.files.len(), 1, "the change is uncommitted again");
+    for update in &last.ref_updates {
+        assert_eq!(
+            repository.local_repository.refname_to_id(&update.name).ok(),
+            update.old,
+            "{} was moved back",
+            update.name
+        );
+    }
+
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit again", None)?;
+    git(
+        repository.path(),
+        &["update-ref", "refs/heads/gitbutler/workspace", "HEAD~1"],
+    );
+    let mut guard = project.exclusive_worktree_access();
+    let err = journal::undo_last_operation(project, guard.write_permission()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Can't undo 'CreateCommit' as 'refs/heads/gitbutler/workspace' was moved since"
+    );
+    Ok(())
+}
+
+#[test]
+fn operations_are_journaled_with_their_parameters() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry = gitbutler_branch_actions::create_virtual_branch(
+        ctx,
+        &BranchCreateRequest {
+            name: Some("feature".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    gitbutler_branch_actions::update_commit_message(
+        ctx,
+        stack_entry.id,
+        git2::Oid::zero(),
+        "no such commit",
+    )
+    .unwrap_err();
+
+    let entries = journal::entries(project)?;
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry.operation)
+            .collect::<Vec<_>>(),
+        [
+            OperationKind::SetBaseBranch,
+            OperationKind::CreateBranch,
+            OperationKind::CreateCommit,
+            OperationKind::UpdateCommitMessage
+        ]
+    );
+    assert_eq!(entries[1].parameters["name"], "feature");
+    assert_eq!(entries[2].parameters["message"], "commit one");
+    assert_eq!(entries[2].parameters["stackId"], stack_entry.id.to_string());
+    assert!(entries[2].error.is_none());
+    assert!(
+        entries[3].error.is_some(),
+        "failed operations are part of the audit trail"
+    );
+
+    let mut guard = project.exclusive_worktree_access();
+    let undo = journal::undo_last_operation(project, guard.write_permission())?;
+    assert_eq!(
+        undo.undoes,
+        Some(2),
+        "the failed operation changed nothing, so the commit is undone"
+    );
+    Ok(())
+}
+
+#[test]
+fn the_journal_is_rotated_and_keeps_its_positions() -> anyhow::Result<()> {
+    let Test { project, ctx, .. } = &Test::default();
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    gitbutler_branch_actions::create_virtual_branch(ctx, &Default::default())?;
+
+    let record_large_entries = |count: usize| -> anyhow::Result<()> {
+        let unchanged = journal::RefState::default();
+        let large = "x".repeat((journal::ROTATE_AT_BYTES / 4) as usize);
+        for _ in 0..count {
+            journal::record(
+                project,
+                OperationKind::UpdateProjectSettings,
+                [("value", large.clone())],
+                &unchanged,
+                &unchanged,
+                None,
+            )?;
+        }
+        Ok(())
+    };
+    let archives = || -> anyhow::Result<usize> {
+        Ok(fs::read_dir(project.gb_dir())?
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.starts_with("journal.") && name != "journal.jsonl"
+            })
+            .count())
+    };
+
+    record_large_entries(8)?;
+    assert_eq!(archives()?, 1);
+    let mut guard = project.exclusive_worktree_access();
+    let undo = journal::undo_last_operation(project, guard.write_permission())?;
+    drop(guard);
+    assert_eq!(
+        undo.undoes,
+        Some(1),
+        "the journaled settings changed nothing, so the archived branch creation is undone"
+    );
+    assert_eq!(undo.position, Some(10));
+    assert_eq!(archives()?, 2, "the undo went into a new journal");
+
+    record_large_entries(journal::MAX_ARCHIVES * 4)?;
+    assert_eq!(
+        archives()?,
+        journal::MAX_ARCHIVES,
+        "older archives are deleted"
+    );
+    let last_position = 10 + journal::MAX_ARCHIVES * 4;
+    let latest = journal::latest_entries(project, Some(2))?;
+    assert_eq!(
+        latest
+            .iter()
+            .map(|entry| entry.position)
+            .collect::<Vec<_>>(),
+        [Some(last_position), Some(last_position - 1)],
+        "the latest entries come first"
+    );
+    let entries = journal::entries(project)?;
+    assert_eq!(
+        entries.last().and_then(|entry| entry.position),
+        Some(last_position),
+        "positions count from the first entry, even once it was rotated away"
+    );
+    assert!(entries[0].position > Some(0));
+    Ok(())
+}
+
+/// Run `git` with `args` in `dir` and assert that it succeeds.
+fn git(dir: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("git can be launched");
+    assert!(status.success(), "git {args:?} failed");
+}
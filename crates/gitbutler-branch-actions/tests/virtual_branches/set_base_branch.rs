@@ -8,6 +8,51 @@ fn success() {
         .unwrap();
 }
 
+#[test]
+fn default_branch_is_where_the_remote_head_points() -> anyhow::Result<()> {
+    let Test { repository, .. } = &Test::default();
+    let repo = git2::Repository::open(repository.path())?;
+
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/master",
+        true,
+        "set remote HEAD",
+    )?;
+    assert_eq!(
+        gitbutler_branch_actions::base::default_branch(&repo)?,
+        Some("refs/remotes/origin/master".parse()?)
+    );
+
+    repo.find_reference("refs/remotes/origin/HEAD")?.delete()?;
+    assert_eq!(gitbutler_branch_actions::base::default_branch(&repo)?, None);
+    Ok(())
+}
+
+#[test]
+fn base_branch_is_protected() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+
+    let vb_state = gitbutler_stack::VirtualBranchesHandle::new(ctx.project().gb_dir());
+    let mut stack = vb_state.get_stack_in_workspace(stack_entry.id)?;
+    stack.upstream = Some("refs/remotes/origin/master".parse()?);
+    vb_state.set_stack(stack)?;
+
+    fs::write(repository.path().join("file.txt"), "content")?;
+    let err =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<gitbutler_error::error::Code>(),
+        Some(&gitbutler_error::error::Code::ProtectedBranch)
+    );
+    Ok(())
+}
+
 mod error {
     use gitbutler_reference::RemoteRefname;
 
@@ -183,7 +183,7 @@ fn forcepush_allowed() {
         gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit four", None).unwrap()
     };
 
-    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false).unwrap();
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false, false).unwrap();
 
     let commit_two_parent_oid = repository
         .find_commit(commit_two_oid)
@@ -265,7 +265,7 @@ fn forcepush_forbidden() {
     };
 
     // TODO: flag the old one as deprecated
-    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false).unwrap();
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false, false).unwrap();
 
     let commit_two_parent_oid = repository
         .find_commit(commit_two_oid)
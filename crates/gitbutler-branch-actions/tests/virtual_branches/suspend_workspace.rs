@@ -0,0 +1,99 @@
+use gitbutler_branch_actions::suspend;
+
+use super::*;
+
+#[test]
+fn suspended_changes_are_resumed_into_their_lanes() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    gitbutler_branch_actions::create_virtual_branch(
+        ctx,
+        &BranchCreateRequest {
+            name: Some("second".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("file.txt"), "changed")?;
+    fs::write(repository.path().join("new.txt"), "new")?;
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)?.branches;
+    let (owner, other): (Vec<_>, Vec<_>) =
+        branches.iter().partition(|branch| !branch.files.is_empty());
+    let owner = owner[0].id;
+    // Changes that aren't assigned yet would go to the other lane.
+    gitbutler_branch_actions::update_virtual_branch(
+        ctx,
+        gitbutler_branch::BranchUpdateRequest {
+            id: other[0].id,
+            selected_for_changes: Some(true),
+            ..Default::default()
+        },
+    )?;
+
+    let suspended = suspend::suspend_workspace(ctx, "feature")?;
+    assert_eq!(
+        suspended.paths,
+        [PathBuf::from("file.txt"), PathBuf::from("new.txt")]
+    );
+    assert!(!repository.path().join("file.txt").exists());
+    assert!(!repository.path().join("new.txt").exists());
+    assert!(gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .iter()
+        .all(|branch| branch.files.is_empty()));
+    assert_eq!(suspend::list_suspended_workspaces(ctx)?, [suspended]);
+    assert!(
+        suspend::suspend_workspace(ctx, "other").is_err(),
+        "there is nothing left to suspend"
+    );
+
+    let resumed = suspend::resume_workspace(ctx, "feature")?;
+    assert!(resumed.index_restored);
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "changed"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("new.txt"))?,
+        "new"
+    );
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)?.branches;
+    let files_of = |id| {
+        branches
+            .iter()
+            .find(|branch| branch.id == id)
+            .map(|branch| branch.files.len())
+    };
+    assert_eq!(
+        files_of(owner),
+        Some(2),
+        "changes return to the lane they were in, not the selected one"
+    );
+    assert!(suspend::list_suspended_workspaces(ctx)?.is_empty());
+    assert!(suspend::resume_workspace(ctx, "feature").is_err());
+    Ok(())
+}
+
+#[test]
+fn resuming_requires_a_clean_worktree() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    fs::write(repository.path().join("file.txt"), "suspended")?;
+    suspend::suspend_workspace(ctx, "feature")?;
+    assert!(
+        suspend::suspend_workspace(ctx, "feature").is_err(),
+        "names are unique"
+    );
+
+    fs::write(repository.path().join("other.txt"), "uncommitted")?;
+    assert!(suspend::resume_workspace(ctx, "feature").is_err());
+    assert!(!repository.path().join("file.txt").exists());
+    assert_eq!(suspend::list_suspended_workspaces(ctx)?.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
+use gitbutler_branch_actions::{
+    create_virtual_branch, list_virtual_branches, set_base_branch, transfer_ownership,
+    update_virtual_branch,
+};
+use gitbutler_stack::StackId;
+
+use super::*;
+
+fn hunk_ranges(ctx: &CommandContext, stack_id: StackId) -> Vec<(u32, u32)> {
+    list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|branch| branch.id == stack_id)
+        .unwrap()
+        .files
+        .into_iter()
+        .flat_map(|file| file.hunks)
+        .map(|hunk| (hunk.start, hunk.end))
+        .collect()
+}
+
+#[test]
+fn assigned_lines_are_locked_until_transferred() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+    let mut lines = repository.gen_file("file.txt", 20);
+    repository.commit_all("initial commit");
+    repository.push();
+
+    set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())?;
+
+    let stack1 = create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    let stack2 = create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+
+    lines[0] = "modified line 0".to_string();
+    lines[15] = "modified line 15".to_string();
+    repository.write_file("file.txt", &lines);
+    list_virtual_branches(ctx)?;
+
+    update_virtual_branch(
+        ctx,
+        BranchUpdateRequest {
+            id: stack2.id,
+            ownership: Some("file.txt:1-5".parse()?),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(hunk_ranges(ctx, stack1.id), vec![(13, 20)]);
+    assert_eq!(hunk_ranges(ctx, stack2.id), vec![(1, 5)]);
+
+    // Changing the assigned lines further keeps them with the branch they were assigned to.
+    lines[1] = "modified line 1".to_string();
+    repository.write_file("file.txt", &lines);
+    assert_eq!(hunk_ranges(ctx, stack2.id), vec![(1, 6)]);
+
+    let err = update_virtual_branch(
+        ctx,
+        BranchUpdateRequest {
+            id: stack1.id,
+            ownership: Some("file.txt:1-6,13-20".parse()?),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(
+        format!("{err:#}").contains("its ownership has to be transferred first"),
+        "{err:#}"
+    );
+    assert_eq!(hunk_ranges(ctx, stack2.id), vec![(1, 6)]);
+
+    transfer_ownership(ctx, stack1.id, &"file.txt:1-6".parse()?)?;
+    assert_eq!(hunk_ranges(ctx, stack1.id).len(), 2);
+    assert!(hunk_ranges(ctx, stack2.id).is_empty());
+
+    // Now it's locked to the branch it was transferred to.
+    assert!(update_virtual_branch(
+        ctx,
+        BranchUpdateRequest {
+            id: stack2.id,
+            ownership: Some("file.txt:1-6".parse()?),
+            ..Default::default()
+        },
+    )
+    .is_err());
+    Ok(())
+}
@@ -309,3 +309,41 @@ fn empty() {
         "commit message can not be empty"
     );
 }
+
+#[test]
+fn reword_by_commit_alone() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+
+    fs::write(repository.path().join("file one.txt"), "")?;
+    let commit_one_oid =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None)?;
+    fs::write(repository.path().join("file two.txt"), "")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None)?;
+
+    gitbutler_branch_actions::reword_commit(ctx, commit_one_oid, "commit one updated", false)?;
+
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)?
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    let descriptions = branch.series[0]
+        .clone()?
+        .patches
+        .iter()
+        .map(|c| c.description.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["commit two", "commit one updated"]);
+
+    assert!(
+        gitbutler_branch_actions::reword_commit(ctx, commit_one_oid, "again", false).is_err(),
+        "the original commit isn't part of the stack anymore"
+    );
+    Ok(())
+}
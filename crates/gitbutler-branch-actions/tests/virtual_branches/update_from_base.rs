@@ -0,0 +1,77 @@
+use gitbutler_branch_actions::upstream_integration::{ResolutionApproach, StackUpdate};
+
+use super::*;
+
+#[test]
+fn rebases_commits_and_uncommitted_changes() {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    // make sure we have an undiscovered commit in the remote branch
+    {
+        let first_commit_oid = repository.commit_all("first");
+        fs::write(repository.path().join("file.txt"), "upstream").unwrap();
+        repository.commit_all("second");
+        repository.push();
+        repository.reset_hard(Some(first_commit_oid));
+    }
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    fs::write(repository.path().join("another_file.txt"), "committed").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "virtual commit", None).unwrap();
+    fs::write(repository.path().join("third_file.txt"), "uncommitted").unwrap();
+
+    let updates =
+        gitbutler_branch_actions::update_from_base(ctx, ResolutionApproach::Rebase).unwrap();
+    assert_eq!(
+        updates,
+        [StackUpdate {
+            stack_id: stack_entry.id,
+            approach: ResolutionApproach::Rebase,
+            conflicted: false,
+        }]
+    );
+
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "upstream"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("another_file.txt")).unwrap(),
+        "committed"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("third_file.txt")).unwrap(),
+        "uncommitted"
+    );
+
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches;
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].files.len(), 1);
+    assert_eq!(branches[0].series[0].clone().unwrap().patches.len(), 1);
+
+    assert!(
+        gitbutler_branch_actions::update_from_base(ctx, ResolutionApproach::Rebase)
+            .unwrap()
+            .is_empty(),
+        "nothing to do once up to date"
+    );
+}
+
+#[test]
+fn only_rebase_and_merge_are_strategies() {
+    let Test { ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    assert!(gitbutler_branch_actions::update_from_base(ctx, ResolutionApproach::Unapply).is_err());
+}
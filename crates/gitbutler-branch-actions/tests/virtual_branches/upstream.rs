@@ -1,4 +1,6 @@
 use gitbutler_branch::BranchCreateRequest;
+use gitbutler_error::error::Code;
+use gitbutler_repo_actions::push_safety::PushRefusal;
 
 use super::*;
 
@@ -28,7 +30,7 @@ fn detect_upstream_commits() {
     };
 
     // push
-    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry_1.id, false).unwrap();
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry_1.id, false, false).unwrap();
 
     let oid3 = {
         // create third commit
@@ -117,3 +119,56 @@ fn detect_integrated_commits() {
         assert!(branches[0].series[0].clone().unwrap().patches[2].is_integrated);
     }
 }
+
+#[test]
+fn pushes_that_are_not_fast_forwards_must_be_forced() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project_id,
+        projects,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None)?;
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false, false)?;
+    assert_eq!(
+        gitbutler_branch_actions::stack::check_push_stack(ctx, stack_entry.id, false)?,
+        []
+    );
+
+    gitbutler_branch_actions::amend_commit(ctx, stack_entry.id, &[], Some("amended"), false)?;
+    let refusals = gitbutler_branch_actions::stack::check_push_stack(ctx, stack_entry.id, false)?;
+    assert!(matches!(
+        refusals.as_slice(),
+        [PushRefusal::NonFastForward { .. }]
+    ));
+    let err =
+        gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false, false).unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::PushRefused));
+
+    projects.update(&projects::UpdateRequest {
+        id: *project_id,
+        protected_branches: Some(vec!["*".into()]),
+        ..Default::default()
+    })?;
+    let protected_ctx = &CommandContext::open(&projects.get(*project_id)?, AppSettings::default())?;
+    let refusals =
+        gitbutler_branch_actions::stack::check_push_stack(protected_ctx, stack_entry.id, true)?;
+    assert!(matches!(
+        refusals.as_slice(),
+        [PushRefusal::ProtectedBranch { .. }]
+    ));
+
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, true, false)?;
+    assert_eq!(
+        gitbutler_branch_actions::stack::check_push_stack(ctx, stack_entry.id, false)?,
+        [],
+        "the forced push went through as the remote branch didn't change"
+    );
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_branch_actions::upstream_conflicts;
+
+use super::*;
+
+/// Add a commit on top of `origin/master` which writes `content` to `path`, as if it had just been fetched.
+fn commit_upstream(repo: &git2::Repository, path: &str, content: &[u8]) -> anyhow::Result<()> {
+    let upstream = repo
+        .find_reference("refs/remotes/origin/master")?
+        .peel_to_commit()?;
+    let mut tree = repo.treebuilder(Some(&upstream.tree()?))?;
+    tree.insert(path, repo.blob(content)?, 0o100644)?;
+    let tree = repo.find_tree(tree.write()?)?;
+    let signature = git2::Signature::now("test", "test@example.com")?;
+    repo.commit(
+        Some("refs/remotes/origin/master"),
+        &signature,
+        &signature,
+        "upstream",
+        &tree,
+        &[&upstream],
+    )?;
+    Ok(())
+}
+
+#[test]
+fn nothing_to_predict_without_upstream_commits() -> anyhow::Result<()> {
+    let Test { ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    assert_eq!(upstream_conflicts::predict(ctx)?, None);
+    Ok(())
+}
+
+#[test]
+fn conflicting_uncommitted_changes_are_risky() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "one\n")?;
+    repository.commit_all("first");
+    repository.push();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "mine\n")?;
+    fs::write(repository.path().join("other.txt"), "unrelated\n")?;
+    gitbutler_branch_actions::list_virtual_branches(ctx)?;
+
+    commit_upstream(ctx.repo(), "file.txt", b"theirs\n")?;
+
+    let conflicts = upstream_conflicts::predict(ctx)?.expect("upstream has a new commit");
+    assert_eq!(conflicts.upstream_commits, 1);
+    assert_eq!(conflicts.risky_files.len(), 1);
+    assert_eq!(conflicts.risky_files[0].path, PathBuf::from("file.txt"));
+    assert_eq!(conflicts.risky_files[0].stack_ids, [stack_entry.id]);
+    Ok(())
+}
+
+#[test]
+fn unrelated_upstream_changes_are_not_risky() -> anyhow::Result<()> {
+    let Test {
+        repository, ctx, ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "mine\n")?;
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "mine", None)?;
+
+    commit_upstream(ctx.repo(), "upstream.txt", b"theirs\n")?;
+
+    let conflicts = upstream_conflicts::predict(ctx)?.expect("upstream has a new commit");
+    assert!(conflicts.risky_files.is_empty());
+    Ok(())
+}
@@ -49,6 +49,22 @@ pub enum Subcommands {
     /// List and restore snapshots.
     #[clap(visible_alias = "snapshots")]
     Snapshot(snapshot::Platform),
+    /// List periods of uninterrupted activity, as recorded by snapshots.
+    ListSessions {
+        /// Only list sessions of this many recent days.
+        #[clap(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Restore a file to what it contained at a point in time, as recorded by snapshots and all changes since.
+    Restore {
+        /// The worktree-relative path of the file to restore.
+        file: PathBuf,
+        /// The point in time, like `2024-05-01 14:30` in local time, `2024-05-01T14:30:00Z` or `@1714566600`.
+        #[clap(long)]
+        at: String,
+    },
+    /// Show the latest snapshot and the applied virtual branches with their changes.
+    Status,
 }
 
 pub mod vbranch {
@@ -170,6 +186,21 @@ pub mod project {
             /// The long name of the remote reference to track, like `refs/remotes/origin/main`.
             remote_ref_name: RemoteRefname,
         },
+        /// Encrypt the recorded history of the project at rest, including what was recorded before.
+        ///
+        /// The key is kept in the keychain unless a passphrase is given. Running it again finishes a migration
+        /// that was interrupted.
+        EncryptHistory {
+            /// The passphrase to derive the key from, which has to be entered to unlock the history.
+            #[clap(long, env = "GITBUTLER_HISTORY_PASSPHRASE", hide_env_values = true)]
+            passphrase: Option<String>,
+        },
+        /// Store the encrypted history of the project in plain files again.
+        DecryptHistory {
+            /// The passphrase the history was encrypted with, unless its key is in the keychain.
+            #[clap(long, env = "GITBUTLER_HISTORY_PASSPHRASE", hide_env_values = true)]
+            passphrase: Option<String>,
+        },
     }
 }
 
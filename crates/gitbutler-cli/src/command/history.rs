@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use but_settings::AppSettings;
+use gitbutler_command_context::CommandContext;
+use gitbutler_oplog::{
+    deltas,
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    eol, reconstruct, OplogExt,
+};
+use gitbutler_oxidize::git2_to_gix_object_id;
+use gitbutler_project::Project;
+
+pub fn list_sessions(project: Project, days: u32) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    let since = now - i64::from(days) * 24 * 60 * 60;
+    let summary = project.activity_summary(since..now + 1)?;
+    for session in summary.sessions {
+        let (Some(start), Some(end)) = (
+            chrono::DateTime::from_timestamp(session.start, 0),
+            chrono::DateTime::from_timestamp(session.end, 0),
+        ) else {
+            continue;
+        };
+        println!(
+            "{start} – {end} {snapshots} snapshots, {files} files, +{added} -{removed}",
+            snapshots = session.snapshots,
+            files = session.files_touched,
+            added = session.lines_added,
+            removed = session.lines_removed,
+        );
+    }
+    Ok(())
+}
+
+/// Restore `file` to how it was at `at`, with all recorded changes up to then, after taking a snapshot to undo it.
+pub fn restore_file(project: Project, file: PathBuf, at: String) -> Result<()> {
+    let at = parse_time(&at)?;
+    let Some(recorded) = deltas::blob_at(&project, &file, at.timestamp())? else {
+        bail!("Nothing was recorded about '{}' by {at}", file.display());
+    };
+    let content = match recorded.blob_id {
+        Some(blob_id) => {
+            let repo = gix::open(&project.path)?;
+            let blob = repo.find_blob(git2_to_gix_object_id(blob_id))?;
+            Some(eol::restore(&blob.data, recorded.eol).into_owned())
+        }
+        None => None,
+    };
+
+    let mut guard = project.exclusive_worktree_access();
+    let snapshot_id = project
+        .create_snapshot(
+            SnapshotDetails::new(OperationKind::RestoreFiles).with_trailers(vec![
+                Trailer {
+                    key: "directory".to_string(),
+                    value: file.display().to_string(),
+                },
+                Trailer {
+                    key: "at".to_string(),
+                    value: at.timestamp().to_string(),
+                },
+            ]),
+            guard.write_permission(),
+        )
+        .context("Refusing to restore the file as the snapshot to undo it failed")?;
+    let path = project.path.join(&file);
+    match content {
+        Some(content) => reconstruct::write_worktree_file(&path, &content, recorded.mode)
+            .with_context(|| format!("failed to write '{}'", path.display()))?,
+        None if path.symlink_metadata().is_ok() => std::fs::remove_file(&path)
+            .with_context(|| format!("failed to remove '{}'", path.display()))?,
+        None => {}
+    }
+    println!(
+        "Restored '{}' as it was recorded at {}, undo with snapshot {snapshot_id}",
+        file.display(),
+        chrono::DateTime::from_timestamp(recorded.recorded_at, 0)
+            .map_or_else(|| recorded.recorded_at.to_string(), |time| time.to_string()),
+    );
+    Ok(())
+}
+
+pub fn status(project: Project) -> Result<()> {
+    println!("{} at {}", project.title, project.path.display());
+    if project.recording_paused {
+        println!("Recording is paused");
+    }
+    match project.list_snapshots(1, None)?.into_iter().next() {
+        Some(snapshot) => {
+            let operation = snapshot.details.map_or_else(
+                || "unknown operation".into(),
+                |details| details.operation.to_string(),
+            );
+            let at = chrono::DateTime::from_timestamp(snapshot.created_at.seconds(), 0)
+                .map_or_else(Default::default, |time| time.to_string());
+            println!("Latest snapshot {} {at} {operation}", snapshot.commit_id);
+        }
+        None => println!("No snapshots yet"),
+    }
+
+    let ctx = CommandContext::open(&project, AppSettings::default())?;
+    match gitbutler_branch_actions::list_virtual_branches(&ctx) {
+        Ok(listing) => {
+            for branch in listing.branches {
+                println!(
+                    "{default} {name} {files} changed files",
+                    default = if branch.selected_for_changes {
+                        "🌟"
+                    } else {
+                        " "
+                    },
+                    name = branch.name,
+                    files = branch.files.len(),
+                );
+            }
+        }
+        Err(err) => println!("Not in the GitButler workspace: {err}"),
+    }
+    Ok(())
+}
+
+/// Parse `time` as RFC 3339, as local time like `2024-05-01 14:30`, or as seconds since the Unix epoch like `@1714566600`.
+fn parse_time(time: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Some(seconds) = time.strip_prefix('@') {
+        return chrono::DateTime::from_timestamp(seconds.parse()?, 0)
+            .with_context(|| format!("'{time}' is out of range"));
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(time) {
+        return Ok(time.to_utc());
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(time, format).ok())
+        .and_then(|time| time.and_local_timezone(chrono::Local).earliest())
+        .map(|time| time.to_utc())
+        .with_context(|| {
+            format!("'{time}' isn't a time like '2024-05-01 14:30', '2024-05-01T14:30:00Z' or '@1714566600'")
+        })
+}
@@ -1,3 +1,4 @@
+pub mod history;
 pub mod prepare;
 pub mod project;
 pub mod vbranch;
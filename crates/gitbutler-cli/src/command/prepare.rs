@@ -33,5 +33,9 @@ pub fn project_controller(
         bail!("Path '{}' must be a valid directory", path.display());
     }
     eprintln!("Using projects from '{}'", path.display());
+    // The application uses the name of its data directory as namespace for its secrets.
+    if let Some(identifier) = path.file_name().and_then(|name| name.to_str()) {
+        gitbutler_secret::secret::set_application_namespace(identifier);
+    }
     Ok(gitbutler_project::Controller::from_path(path))
 }
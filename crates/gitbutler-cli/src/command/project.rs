@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use but_settings::AppSettings;
 use gitbutler_command_context::CommandContext;
 use gitbutler_project::Project;
@@ -42,3 +42,43 @@ pub fn switch_to_workspace(project: Project, refname: RemoteRefname) -> Result<(
     let ctx = CommandContext::open(&project, AppSettings::default())?;
     debug_print(gitbutler_branch_actions::set_base_branch(&ctx, &refname)?)
 }
+
+pub fn encrypt_history(
+    ctrl: gitbutler_project::Controller,
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<()> {
+    let project = added_project(&ctrl, path)?;
+    let project =
+        gitbutler_oplog::encryption::encrypt_history(&ctrl, &project, passphrase.as_deref())?;
+    debug_print(project.history_encryption)
+}
+
+pub fn decrypt_history(
+    ctrl: gitbutler_project::Controller,
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<()> {
+    let project = added_project(&ctrl, path)?;
+    if let Some(passphrase) = passphrase {
+        gitbutler_oplog::encryption::unlock(&project, Some(&passphrase))?;
+    }
+    let project = gitbutler_oplog::encryption::decrypt_history(&ctrl, &project)?;
+    debug_print(project.history_encryption)
+}
+
+/// Return the project of `ctrl` for the repository at `path`.
+fn added_project(ctrl: &gitbutler_project::Controller, path: PathBuf) -> Result<Project> {
+    let worktree_dir = gix::discover(path)?
+        .work_dir()
+        .context("Only non-bare repositories can be projects")?
+        .canonicalize()?;
+    match ctrl
+        .list()?
+        .into_iter()
+        .find(|project| project.path == worktree_dir && project.subdirectory.is_none())
+    {
+        Some(project) => Ok(project),
+        None => bail!("'{}' wasn't added as project yet", worktree_dir.display()),
+    }
+}
@@ -74,6 +74,14 @@ fn main() -> Result<()> {
                 let project = command::prepare::project_from_path(args.current_dir)?;
                 command::project::switch_to_workspace(project, remote_ref_name)
             }
+            Some(project::SubCommands::EncryptHistory { passphrase }) => {
+                let ctrl = command::prepare::project_controller(app_suffix, app_data_dir)?;
+                command::project::encrypt_history(ctrl, args.current_dir, passphrase)
+            }
+            Some(project::SubCommands::DecryptHistory { passphrase }) => {
+                let ctrl = command::prepare::project_controller(app_suffix, app_data_dir)?;
+                command::project::decrypt_history(ctrl, args.current_dir, passphrase)
+            }
             Some(project::SubCommands::Add {
                 switch_to_workspace,
                 path,
@@ -98,6 +106,18 @@ fn main() -> Result<()> {
                 None => command::snapshot::list(project),
             }
         }
+        args::Subcommands::ListSessions { days } => {
+            let project = command::prepare::project_from_path(args.current_dir)?;
+            command::history::list_sessions(project, days)
+        }
+        args::Subcommands::Restore { file, at } => {
+            let project = command::prepare::project_from_path(args.current_dir)?;
+            command::history::restore_file(project, file, at)
+        }
+        args::Subcommands::Status => {
+            let project = command::prepare::project_from_path(args.current_dir)?;
+            command::history::status(project)
+        }
     }
 }
 
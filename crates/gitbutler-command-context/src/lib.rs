@@ -4,8 +4,8 @@ use gitbutler_project::Project;
 use std::path::Path;
 
 pub struct CommandContext {
-    /// The git repository of the `project` itself.
-    git_repository: git2::Repository,
+    /// The git repository of the `project` itself, borrowed from the [pool](repository_pool).
+    git_repository: PooledRepository,
     /// Metadata about the project, typically stored with GitButler application data.
     project: Project,
     /// A snapshot of the app settings obtained at the beginnig of each command.
@@ -14,8 +14,10 @@ pub struct CommandContext {
 
 impl CommandContext {
     /// Open the repository identified by `project` and perform some checks.
+    ///
+    /// The repository is reused from a previous context if possible, and kept open for the next one.
     pub fn open(project: &Project, app_settings: AppSettings) -> Result<Self> {
-        let repo = git2::Repository::open(&project.path)?;
+        let repo = repository_pool::open(project)?;
         Ok(Self {
             git_repository: repo,
             project: project.clone(),
@@ -93,3 +95,5 @@ pub fn gix_repository_for_merging(worktree_or_git_dir: &Path) -> Result<gix::Rep
 
 mod repository_ext;
 pub use repository_ext::RepositoryExtLite;
+pub mod repository_pool;
+pub use repository_pool::PooledRepository;
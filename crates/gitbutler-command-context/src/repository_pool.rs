@@ -0,0 +1,119 @@
+//! Keep the repositories of projects open between commands, as opening a `git2::Repository` reads its
+//! configuration, which adds up for commands that are invoked often, like listing changes.
+//!
+//! Repositories are handed out by [`CommandContext::open()`](crate::CommandContext::open()), or [`open()`] for
+//! code that only has a project, and returned when the context or [`PooledRepository`] is dropped. They are opened again once the configuration of the repository changed, and forgotten
+//! when their project is [deleted](forget()).
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use gitbutler_project::{Project, ProjectId};
+
+/// The most repositories to keep open for each project, which is how many commands may use it at once
+/// without having to open it.
+const MAX_IDLE_PER_PROJECT: usize = 4;
+
+/// The repositories that aren't in use, by the project they belong to.
+static IDLE: LazyLock<Mutex<HashMap<ProjectId, Vec<Idle>>>> = LazyLock::new(Default::default);
+
+struct Idle {
+    repo: git2::Repository,
+    /// The worktree the repository was opened for, in case the project moved.
+    worktree: PathBuf,
+    /// When the configuration file of the repository was modified as of opening it.
+    config_modified: Option<SystemTime>,
+}
+
+/// A repository that is returned to the pool when dropped.
+pub struct PooledRepository {
+    repo: Option<git2::Repository>,
+    project_id: ProjectId,
+    worktree: PathBuf,
+    config_modified: Option<SystemTime>,
+}
+
+impl Deref for PooledRepository {
+    type Target = git2::Repository;
+
+    fn deref(&self) -> &Self::Target {
+        self.repo.as_ref().expect("only taken when dropped")
+    }
+}
+
+impl Drop for PooledRepository {
+    fn drop(&mut self) {
+        let Some(repo) = self.repo.take() else {
+            return;
+        };
+        let mut idle = IDLE.lock().unwrap_or_else(|err| err.into_inner());
+        let idle = idle.entry(self.project_id).or_default();
+        if idle.len() < MAX_IDLE_PER_PROJECT {
+            idle.push(Idle {
+                repo,
+                worktree: std::mem::take(&mut self.worktree),
+                config_modified: self.config_modified,
+            });
+        }
+    }
+}
+
+/// Return an open repository for `project`, reusing one that isn't in use anymore if its configuration didn't
+/// change since.
+pub fn open(project: &Project) -> Result<PooledRepository> {
+    loop {
+        let Some(idle) = IDLE
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get_mut(&project.id)
+            .and_then(Vec::pop)
+        else {
+            break;
+        };
+        let reusable = idle.worktree == project.path
+            && config_modified(idle.repo.path()) == idle.config_modified
+            // Pick up changes to the index made elsewhere, which the repository would otherwise keep
+            // seeing as of when it loaded it.
+            && idle
+                .repo
+                .index()
+                .and_then(|mut index| index.read(false))
+                .is_ok();
+        if reusable {
+            return Ok(PooledRepository {
+                repo: Some(idle.repo),
+                project_id: project.id,
+                worktree: idle.worktree,
+                config_modified: idle.config_modified,
+            });
+        }
+    }
+
+    let repo = git2::Repository::open(&project.path)?;
+    let config_modified = config_modified(repo.path());
+    Ok(PooledRepository {
+        repo: Some(repo),
+        project_id: project.id,
+        worktree: project.path.clone(),
+        config_modified,
+    })
+}
+
+/// Close the repositories of the project with `project_id` that aren't in use, like after it was deleted.
+pub fn forget(project_id: ProjectId) {
+    IDLE.lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(&project_id);
+}
+
+/// Return when the configuration file in `git_dir` was modified, or `None` if it can't be told.
+fn config_modified(git_dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(git_dir.join("config"))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -1,9 +1,10 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use git2::ConfigLevel;
 use gix::bstr::{BStr, ByteVec};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::ffi::OsStr;
+use std::path::Path;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -14,11 +15,28 @@ pub struct GbConfig {
     pub gpg_program: Option<String>,
     pub gpg_ssh_program: Option<String>,
 }
+
+/// Values of the user's global git configuration that affect how GitButler behaves.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalGitConfig {
+    pub user_name: Option<String>,
+    pub user_email: Option<String>,
+    /// The file with additional ignore patterns, as configured, so it may start with `~/`.
+    pub excludes_file: Option<String>,
+    /// The name of the first branch of newly initialized repositories.
+    pub default_branch: Option<String>,
+}
+
 const SIGN_COMMITS: &str = "gitbutler.signCommits";
 const SIGNING_KEY: &str = "user.signingKey";
 const SIGNING_FORMAT: &str = "gpg.format";
 const GPG_PROGRAM: &str = "gpg.program";
 const GPG_SSH_PROGRAM: &str = "gpg.ssh.program";
+const USER_NAME: &str = "user.name";
+const USER_EMAIL: &str = "user.email";
+const EXCLUDES_FILE: &str = "core.excludesFile";
+const DEFAULT_BRANCH: &str = "init.defaultBranch";
 
 pub trait GitConfig {
     fn gb_config(&self) -> Result<GbConfig>;
@@ -66,6 +84,62 @@ impl GitConfig for git2::Repository {
     }
 }
 
+/// Read the [`GlobalGitConfig`] from the configuration files of the system and the user,
+/// ignoring any repository.
+pub fn global_git_config() -> Result<GlobalGitConfig> {
+    let config = git2::Config::open_default()?;
+    let get = |key: &str| match config.get_string(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err),
+    };
+    Ok(GlobalGitConfig {
+        user_name: get(USER_NAME)?,
+        user_email: get(USER_EMAIL)?,
+        excludes_file: get(EXCLUDES_FILE)?,
+        default_branch: get(DEFAULT_BRANCH)?,
+    })
+}
+
+/// Write all values of `config` that are set to the global configuration file of the user,
+/// leaving all other values untouched. Empty values are removed instead.
+pub fn set_global_git_config(config: GlobalGitConfig) -> Result<()> {
+    let mut global = global_config_file()?;
+    for (key, value) in [
+        (USER_NAME, config.user_name),
+        (USER_EMAIL, config.user_email),
+        (EXCLUDES_FILE, config.excludes_file),
+        (DEFAULT_BRANCH, config.default_branch),
+    ] {
+        match value {
+            Some(value) if value.trim().is_empty() => match global.remove(key) {
+                Ok(()) => {}
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {}
+                Err(err) => return Err(err.into()),
+            },
+            Some(value) => global.set_str(key, &value)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Open the global configuration file of the user, which is created if it doesn't exist yet.
+fn global_config_file() -> Result<git2::Config> {
+    match git2::Config::open_default()?.open_level(ConfigLevel::Global) {
+        Ok(global) => Ok(global),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            let home = std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .context(
+                    "Could not determine the home directory to write the git configuration to",
+                )?;
+            Ok(git2::Config::open(&Path::new(&home).join(".gitconfig"))?)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 fn bstring_into_string(s: Cow<'_, BStr>) -> Option<String> {
     match Vec::from(s.into_owned()).into_string() {
         Ok(s) => Some(s),
@@ -1,4 +1,9 @@
-use std::{borrow::Cow, collections::HashMap, path::PathBuf, str};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str,
+};
 
 use anyhow::{Context, Result};
 use bstr::{BStr, BString, ByteSlice, ByteVec};
@@ -9,6 +14,8 @@ use gitbutler_serde::BStringForFrontend;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+use crate::lfs::{self, LfsStatus};
+
 pub type DiffByPathMap = HashMap<PathBuf, FileDiff>;
 
 /// The type of change
@@ -42,6 +49,34 @@ impl From<git2::Delta> for ChangeType {
     }
 }
 
+/// The kind of a file as recorded in Git, which changes without its content when the executable bit
+/// is flipped, or when a file is replaced by a symlink.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileMode {
+    /// A regular file.
+    Regular,
+    /// A regular file with the executable bit set.
+    Executable,
+    /// A symbolic link, whose content is the path it points to.
+    Symlink,
+    /// A submodule, whose content is the commit it is checked out at.
+    Submodule,
+}
+
+impl FileMode {
+    /// Return the mode of a file on one side of a diff, or `None` if there is no file on that side.
+    pub fn from_git2(mode: git2::FileMode) -> Option<Self> {
+        match mode {
+            git2::FileMode::Blob | git2::FileMode::BlobGroupWritable => Some(FileMode::Regular),
+            git2::FileMode::BlobExecutable => Some(FileMode::Executable),
+            git2::FileMode::Link => Some(FileMode::Symlink),
+            git2::FileMode::Commit => Some(FileMode::Submodule),
+            _ => None,
+        }
+    }
+}
+
 /// A description of a hunk, as identified by its line number and the amount of lines it spans
 /// before and after the change.
 #[derive(Debug, PartialEq, Clone, Serialize)]
@@ -134,6 +169,20 @@ pub struct FileDiff {
     pub binary: bool,
     pub old_size_bytes: u64,
     pub new_size_bytes: u64,
+    /// The mode before the change, or `None` if the file was added.
+    pub old_mode: Option<FileMode>,
+    /// The mode after the change, or `None` if the file was deleted.
+    pub new_mode: Option<FileMode>,
+}
+
+impl FileDiff {
+    /// Return `true` if the file existed before and after the change, but with a different mode, like when the
+    /// executable bit was flipped.
+    ///
+    /// `hunks` are empty if only the mode changed.
+    pub fn mode_changed(&self) -> bool {
+        self.old_mode.is_some() && self.new_mode.is_some() && self.old_mode != self.new_mode
+    }
 }
 
 #[instrument(level = tracing::Level::DEBUG, skip(repo))]
@@ -202,6 +251,8 @@ pub fn trees(
 /// `repository` should be `None` if there is no reason to access the workdir, which it will do to
 /// keep the binary data in the object database, which otherwise would be lost to the system
 /// (it's not reconstructable from the delta, or it's not attempted).
+/// It's also used to leave out files stored with Git LFS whose content was downloaded, as it's not what would be
+/// committed.
 pub fn hunks_by_filepath(
     repo: Option<&git2::Repository>,
     diff: &git2::Diff,
@@ -212,6 +263,8 @@ pub fn hunks_by_filepath(
     }
     // find all the hunks
     let mut diff_files = HashMap::new();
+    // Files stored with Git LFS whose downloaded content differs from the pointer in Git.
+    let mut lfs_content_paths = HashSet::new();
     let mut err = None;
 
     diff.print(
@@ -225,6 +278,20 @@ pub fn hunks_by_filepath(
                     .expect("failed to get file name from diff")
             });
 
+            // The downloaded content of LFS files is never committed as is, and can be huge, so these
+            // files are left out instead of storing and diffing their content.
+            if lfs_content_paths.contains(file_path) {
+                return true;
+            }
+            if line.origin_value() == git2::DiffLineType::FileHeader
+                && repo.is_some_and(|repo| {
+                    matches!(lfs::status(repo, file_path), Ok(Some(LfsStatus::Downloaded)))
+                })
+            {
+                lfs_content_paths.insert(file_path.to_owned());
+                return true;
+            }
+
             let new_start = hunk.as_ref().map_or(0, git2::DiffHunk::new_start);
             let new_lines = hunk.as_ref().map_or(0, git2::DiffHunk::new_lines);
             let old_start = hunk.as_ref().map_or(0, git2::DiffHunk::old_start);
@@ -271,6 +338,8 @@ pub fn hunks_by_filepath(
                                 binary: delta.new_file().is_binary(),
                                 old_size_bytes: delta.old_file().size(),
                                 new_size_bytes: delta.new_file().size(),
+                                old_mode: FileMode::from_git2(delta.old_file().mode()),
+                                new_mode: FileMode::from_git2(delta.new_file().mode()),
                         });
                     if existing.is_some() {
                         err = Some(format!("Encountered an invalid internal state related to the diff: {existing:?}"));
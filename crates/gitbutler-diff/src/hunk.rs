@@ -130,6 +130,12 @@ impl Hunk {
             || another.contains(self.end)
     }
 
+    /// Return `true` if this hunk and `another` share at least one line, or span the same lines.
+    pub fn overlaps(&self, another: &Hunk) -> bool {
+        (self.start, self.end) == (another.start, another.end)
+            || (self.start < another.end && another.start < self.end)
+    }
+
     pub fn is_null(&self) -> bool {
         self.start == self.end && self.start == 0
     }
@@ -0,0 +1,57 @@
+//! Awareness of files stored with [Git LFS](https://git-lfs.com), whose content in the worktree is usually not
+//! what is stored in Git, but is replaced by a small pointer file when committed.
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// The first line of every Git LFS pointer file.
+const POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+/// Pointer files are tiny, so anything larger than this is actual content.
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// What a file that is tracked by Git LFS holds in the worktree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LfsStatus {
+    /// Only the pointer file is present as the content wasn't downloaded.
+    Pointer,
+    /// The actual content was downloaded.
+    Downloaded,
+}
+
+/// Return `true` if `content` is a Git LFS pointer file rather than actual content.
+pub fn is_pointer(content: &[u8]) -> bool {
+    content.len() as u64 <= MAX_POINTER_SIZE && content.starts_with(POINTER_PREFIX)
+}
+
+/// Return `true` if the worktree-relative `path` is configured to be stored with Git LFS in `.gitattributes`.
+pub fn is_tracked(repo: &git2::Repository, path: &Path) -> Result<bool> {
+    let filter = repo.get_attr(path, "filter", git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(filter == Some("lfs"))
+}
+
+/// Return what the file at the worktree-relative `path` holds, or `None` if it isn't tracked by Git LFS
+/// or doesn't exist.
+pub fn status(repo: &git2::Repository, path: &Path) -> Result<Option<LfsStatus>> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+    if !is_tracked(repo, path)? {
+        return Ok(None);
+    }
+    let file = match std::fs::File::open(workdir.join(path)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    // Reading one byte more than a pointer may have is enough to tell them apart.
+    let mut head = Vec::with_capacity(MAX_POINTER_SIZE as usize + 1);
+    file.take(MAX_POINTER_SIZE + 1).read_to_end(&mut head)?;
+    Ok(Some(if is_pointer(&head) {
+        LfsStatus::Pointer
+    } else {
+        LfsStatus::Downloaded
+    }))
+}
@@ -1,8 +1,9 @@
 mod diff;
 mod hunk;
+pub mod lfs;
 pub mod write;
 pub use diff::{
     diff_files_into_hunks, hunks_by_filepath, reverse_hunk, reverse_hunk_lines, trees, workdir,
-    ChangeType, DiffByPathMap, FileDiff, GitHunk,
+    ChangeType, DiffByPathMap, FileDiff, FileMode, GitHunk,
 };
 pub use hunk::{Hunk, HunkHash};
@@ -134,6 +134,15 @@ pub enum Code {
     CommitMergeConflictFailure,
     ProjectMissing,
     AuthorMissing,
+    ProtectedBranch,
+    PushRefused,
+    ProjectReadOnly,
+    /// The directory to add as project is empty, and could be initialized as a new one instead.
+    ProjectDirectoryEmpty,
+    /// The operation was cancelled on request, so there is nothing to tell the user about.
+    Cancelled,
+    /// The history of the project is encrypted, and has to be unlocked with its passphrase first.
+    HistoryLocked,
 }
 
 impl std::fmt::Display for Code {
@@ -147,6 +156,12 @@ impl std::fmt::Display for Code {
             Code::CommitMergeConflictFailure => "errors.commit.merge_conflict_failure",
             Code::AuthorMissing => "errors.git.author_missing",
             Code::ProjectMissing => "errors.projects.missing",
+            Code::ProtectedBranch => "errors.projects.protected_branch",
+            Code::PushRefused => "errors.push.refused",
+            Code::ProjectReadOnly => "errors.projects.read_only",
+            Code::ProjectDirectoryEmpty => "errors.projects.directory_empty",
+            Code::Cancelled => "errors.cancelled",
+            Code::HistoryLocked => "errors.projects.history_locked",
         };
         f.write_str(code)
     }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use gitbutler_project as projects;
@@ -27,4 +27,9 @@ impl Archival {
     pub fn logs_archive(&self) -> Result<PathBuf> {
         self.zipper().zip(&self.logs_dir)
     }
+
+    /// Archive the files in `dir`, like a crash report, and return the path to the archive.
+    pub fn archive_dir(&self, dir: &Path) -> Result<PathBuf> {
+        self.zipper().zip(dir)
+    }
 }
@@ -1,5 +1,12 @@
+use std::future::Future;
+
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::github::GitHub;
+use crate::gitlab::GitLab;
+use crate::pull_request::{CiStatus, PullRequest, PullRequestStatus};
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(tag = "name", rename_all = "lowercase")]
 /// Supported git forge types
@@ -9,3 +16,156 @@ pub enum ForgeName {
     Bitbucket,
     Azure,
 }
+
+/// The pull request to open with [`Forge::create_pull_request()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewPullRequest<'a> {
+    /// The name of the branch with the changes.
+    pub head: &'a str,
+    /// The name of the branch the changes are meant to be merged into.
+    pub base: &'a str,
+    pub title: &'a str,
+    pub body: &'a str,
+}
+
+/// A forge hosting a repository, which pull requests, or merge requests as GitLab calls them, can be queried
+/// from and opened in.
+///
+/// All calls authenticate with `token` if one is given, which is required to open pull requests.
+pub trait Forge {
+    fn name(&self) -> ForgeName;
+
+    /// List the open pull requests, newest first.
+    fn list_pull_requests(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<PullRequest>>> + Send;
+
+    fn create_pull_request(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        pull_request: &NewPullRequest<'_>,
+    ) -> impl Future<Output = Result<PullRequest>> + Send;
+
+    /// Return the state of the pull request `number` along with its reviews and checks.
+    fn pull_request_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        number: usize,
+    ) -> impl Future<Output = Result<PullRequestStatus>> + Send;
+
+    /// Return the checks that ran on the commit `sha`, like CI pipelines and their jobs.
+    fn ci_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        sha: &str,
+    ) -> impl Future<Output = Result<CiStatus>> + Send;
+}
+
+/// The forge of a repository, as detected from its remote URL.
+///
+/// Supporting another forge, like Gitea, means adding a variant here along with its [`Forge`] implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteForge {
+    GitHub(GitHub),
+    GitLab(GitLab),
+}
+
+impl RemoteForge {
+    /// Detect the forge from `remote_url`, which may use any of the URL formats Git understands, or return `None`
+    /// if it isn't hosted on a supported forge.
+    pub fn from_remote_url(remote_url: &str) -> Option<Self> {
+        GitHub::from_remote_url(remote_url)
+            .map(RemoteForge::GitHub)
+            .or_else(|| GitLab::from_remote_url(remote_url).map(RemoteForge::GitLab))
+    }
+}
+
+impl Forge for RemoteForge {
+    fn name(&self) -> ForgeName {
+        match self {
+            RemoteForge::GitHub(forge) => forge.name(),
+            RemoteForge::GitLab(forge) => forge.name(),
+        }
+    }
+
+    async fn list_pull_requests(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+    ) -> Result<Vec<PullRequest>> {
+        match self {
+            RemoteForge::GitHub(forge) => forge.list_pull_requests(client, token).await,
+            RemoteForge::GitLab(forge) => forge.list_pull_requests(client, token).await,
+        }
+    }
+
+    async fn create_pull_request(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        pull_request: &NewPullRequest<'_>,
+    ) -> Result<PullRequest> {
+        match self {
+            RemoteForge::GitHub(forge) => {
+                forge.create_pull_request(client, token, pull_request).await
+            }
+            RemoteForge::GitLab(forge) => {
+                forge.create_pull_request(client, token, pull_request).await
+            }
+        }
+    }
+
+    async fn pull_request_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        number: usize,
+    ) -> Result<PullRequestStatus> {
+        match self {
+            RemoteForge::GitHub(forge) => forge.pull_request_status(client, token, number).await,
+            RemoteForge::GitLab(forge) => forge.pull_request_status(client, token, number).await,
+        }
+    }
+
+    async fn ci_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        sha: &str,
+    ) -> Result<CiStatus> {
+        match self {
+            RemoteForge::GitHub(forge) => forge.ci_status(client, token, sha).await,
+            RemoteForge::GitLab(forge) => forge.ci_status(client, token, sha).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_from_remote_url() {
+        assert_eq!(
+            RemoteForge::from_remote_url("git@github.com:gitbutlerapp/gitbutler.git")
+                .map(|forge| forge.name()),
+            Some(ForgeName::GitHub)
+        );
+        assert_eq!(
+            RemoteForge::from_remote_url("https://gitlab.com/group/subgroup/project.git"),
+            Some(RemoteForge::GitLab(GitLab {
+                host: "gitlab.com".into(),
+                project_path: "group/subgroup/project".into(),
+            }))
+        );
+        assert_eq!(
+            RemoteForge::from_remote_url("https://example.com/owner/repo.git"),
+            None
+        );
+    }
+}
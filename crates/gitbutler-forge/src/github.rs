@@ -0,0 +1,70 @@
+use anyhow::Result;
+
+use crate::forge::{Forge, ForgeName, NewPullRequest};
+use crate::pull_request::{
+    github_ci_status, github_create_pull_request, github_list_pull_requests,
+    github_pull_request_status, CiStatus, ForgeRepository, PullRequest, PullRequestStatus,
+};
+
+/// A repository on GitHub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHub {
+    pub repo: ForgeRepository,
+}
+
+impl GitHub {
+    /// Return the GitHub repository that `remote_url` points to, or `None` if it isn't hosted on GitHub.
+    pub fn from_remote_url(remote_url: &str) -> Option<Self> {
+        ForgeRepository::github_from_remote_url(remote_url).map(|repo| GitHub { repo })
+    }
+}
+
+impl Forge for GitHub {
+    fn name(&self) -> ForgeName {
+        ForgeName::GitHub
+    }
+
+    async fn list_pull_requests(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+    ) -> Result<Vec<PullRequest>> {
+        github_list_pull_requests(client, token, &self.repo).await
+    }
+
+    async fn create_pull_request(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        pull_request: &NewPullRequest<'_>,
+    ) -> Result<PullRequest> {
+        github_create_pull_request(
+            client,
+            token,
+            &self.repo,
+            pull_request.head,
+            pull_request.base,
+            pull_request.title,
+            pull_request.body,
+        )
+        .await
+    }
+
+    async fn pull_request_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        number: usize,
+    ) -> Result<PullRequestStatus> {
+        github_pull_request_status(client, token, &self.repo, number).await
+    }
+
+    async fn ci_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        sha: &str,
+    ) -> Result<CiStatus> {
+        github_ci_status(client, token, &self.repo, sha).await
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::forge::{Forge, ForgeName, NewPullRequest};
+use crate::pull_request::{
+    CheckRun, ChecksState, CiStatus, PullRequest, PullRequestState, PullRequestStatus, ReviewState,
+};
+
+/// A project on GitLab, either on gitlab.com or on a self-hosted instance with `gitlab` in its host name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitLab {
+    pub host: String,
+    /// The path of the project including all of its groups, like `group/subgroup/project`.
+    pub project_path: String,
+}
+
+impl GitLab {
+    /// Return the GitLab project that `remote_url` points to, or `None` if it doesn't look like it's hosted on
+    /// GitLab.
+    pub fn from_remote_url(remote_url: &str) -> Option<Self> {
+        let url: gitbutler_url::Url = remote_url.parse().ok()?;
+        let host = url.host.as_ref().filter(|host| host.contains("gitlab"))?;
+        let path = std::str::from_utf8(&url.path).ok()?;
+        let path = path.trim_start_matches('/').trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        if !path.contains('/') || path.split('/').any(str::is_empty) {
+            return None;
+        }
+        Some(GitLab {
+            host: host.clone(),
+            project_path: path.to_owned(),
+        })
+    }
+
+    fn project_url(&self) -> String {
+        format!(
+            "https://{}/api/v4/projects/{}",
+            self.host,
+            self.project_path.replace('/', "%2F")
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct GitLabMergeRequest {
+    iid: usize,
+    web_url: String,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    source_branch: String,
+    target_branch: String,
+    author: Option<GitLabUser>,
+    head_pipeline: Option<GitLabPipeline>,
+}
+
+#[derive(Deserialize)]
+struct GitLabUser {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabPipeline {
+    status: String,
+}
+
+/// The status of a job, or of an external check reported for a commit.
+#[derive(Deserialize)]
+struct GitLabCommitStatus {
+    name: String,
+    status: String,
+    target_url: Option<String>,
+}
+
+fn checks_state(status: &str) -> ChecksState {
+    match status {
+        "success" | "skipped" | "manual" => ChecksState::Passed,
+        "failed" | "canceled" => ChecksState::Failed,
+        _ => ChecksState::Pending,
+    }
+}
+
+#[derive(Deserialize)]
+struct GitLabApprovals {
+    #[serde(default)]
+    approved: bool,
+    #[serde(default)]
+    approvals_left: usize,
+}
+
+impl GitLabMergeRequest {
+    fn state(&self) -> PullRequestState {
+        match self.state.as_str() {
+            "merged" => PullRequestState::Merged,
+            "closed" | "locked" => PullRequestState::Closed,
+            _ if self.draft => PullRequestState::Draft,
+            _ => PullRequestState::Open,
+        }
+    }
+}
+
+impl From<GitLabMergeRequest> for PullRequest {
+    fn from(mr: GitLabMergeRequest) -> Self {
+        PullRequest {
+            number: mr.iid,
+            state: mr.state(),
+            url: mr.web_url,
+            title: mr.title,
+            head: mr.source_branch,
+            base: mr.target_branch,
+            author: mr.author.map(|author| author.username),
+        }
+    }
+}
+
+impl Forge for GitLab {
+    fn name(&self) -> ForgeName {
+        ForgeName::GitLab
+    }
+
+    async fn list_pull_requests(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+    ) -> Result<Vec<PullRequest>> {
+        let merge_requests: Vec<GitLabMergeRequest> = gitlab_get(
+            client,
+            token,
+            &format!(
+                "{}/merge_requests?state=opened&per_page=100",
+                self.project_url()
+            ),
+        )
+        .await?;
+        Ok(merge_requests.into_iter().map(Into::into).collect())
+    }
+
+    async fn create_pull_request(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        pull_request: &NewPullRequest<'_>,
+    ) -> Result<PullRequest> {
+        #[derive(Serialize)]
+        struct NewMergeRequest<'a> {
+            source_branch: &'a str,
+            target_branch: &'a str,
+            title: &'a str,
+            description: &'a str,
+        }
+
+        let url = format!("{}/merge_requests", self.project_url());
+        let mr: GitLabMergeRequest = client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&NewMergeRequest {
+                source_branch: pull_request.head,
+                target_branch: pull_request.base,
+                title: pull_request.title,
+                description: pull_request.body,
+            })
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request to {url}"))?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse response of {url}"))?;
+        Ok(mr.into())
+    }
+
+    async fn pull_request_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        number: usize,
+    ) -> Result<PullRequestStatus> {
+        let url = format!("{}/merge_requests/{number}", self.project_url());
+        let mr: GitLabMergeRequest = gitlab_get(client, token, &url).await?;
+        let approvals: GitLabApprovals =
+            gitlab_get(client, token, &format!("{url}/approvals")).await?;
+
+        let review = if approvals.approved && approvals.approvals_left == 0 {
+            Some(ReviewState::Approved)
+        } else if approvals.approvals_left > 0 {
+            Some(ReviewState::Pending)
+        } else {
+            None
+        };
+        let checks = mr
+            .head_pipeline
+            .as_ref()
+            .map(|pipeline| checks_state(&pipeline.status));
+        Ok(PullRequestStatus {
+            number,
+            state: mr.state(),
+            url: mr.web_url,
+            review,
+            checks,
+        })
+    }
+
+    async fn ci_status(
+        &self,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        sha: &str,
+    ) -> Result<CiStatus> {
+        let statuses: Vec<GitLabCommitStatus> = gitlab_get(
+            client,
+            token,
+            &format!(
+                "{}/repository/commits/{sha}/statuses?all=true&per_page=100",
+                self.project_url()
+            ),
+        )
+        .await?;
+        Ok(CiStatus::from_checks(
+            statuses
+                .into_iter()
+                .map(|status| CheckRun {
+                    state: checks_state(&status.status),
+                    name: status.name,
+                    url: status.target_url,
+                })
+                .collect(),
+        ))
+    }
+}
+
+async fn gitlab_get<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    url: &str,
+) -> Result<T> {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {url}"))?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse response of {url}"))
+}
@@ -1,2 +1,5 @@
 pub mod forge;
+pub mod github;
+pub mod gitlab;
+pub mod pull_request;
 pub mod review;
@@ -0,0 +1,424 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A repository hosted on a forge, as identified by the owner and name in its remote URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeRepository {
+    pub owner: String,
+    pub name: String,
+}
+
+impl ForgeRepository {
+    /// Parse the owner and name of a GitHub repository from `remote_url`, which may use any
+    /// of the URL formats Git understands.
+    ///
+    /// Returns `None` if the remote isn't hosted on GitHub.
+    pub fn github_from_remote_url(remote_url: &str) -> Option<Self> {
+        let url: gitbutler_url::Url = remote_url.parse().ok()?;
+        if !url.is_github() {
+            return None;
+        }
+        let path = std::str::from_utf8(&url.path).ok()?;
+        let path = path.trim_start_matches('/').trim_end_matches('/');
+        let (owner, name) = path.split_once('/')?;
+        let name = name.strip_suffix(".git").unwrap_or(name);
+        if owner.is_empty() || name.is_empty() || name.contains('/') {
+            return None;
+        }
+        Some(ForgeRepository {
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PullRequestState {
+    Open,
+    Draft,
+    Closed,
+    Merged,
+}
+
+/// The combined verdict of all reviewers, judging by their most recent review.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReviewState {
+    Approved,
+    ChangesRequested,
+    /// Reviews were requested but not submitted yet.
+    Pending,
+}
+
+/// The combined result of all checks that ran on the head commit of a pull request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChecksState {
+    Passed,
+    Failed,
+    /// At least one check is still queued or running, and none failed so far.
+    Pending,
+}
+
+/// What the forge knows about a pull request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestStatus {
+    pub number: usize,
+    /// The link to the pull request in the web interface of the forge.
+    pub url: String,
+    pub state: PullRequestState,
+    /// `None` if nobody was asked to review.
+    pub review: Option<ReviewState>,
+    /// `None` if no checks are configured.
+    pub checks: Option<ChecksState>,
+}
+
+/// A pull request as listed by the forge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequest {
+    pub number: usize,
+    /// The link to the pull request in the web interface of the forge.
+    pub url: String,
+    pub title: String,
+    /// The name of the branch with the changes.
+    pub head: String,
+    /// The name of the branch the changes are meant to be merged into.
+    pub base: String,
+    pub state: PullRequestState,
+    /// The login of the user who opened the pull request.
+    pub author: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubPullRequest {
+    number: usize,
+    html_url: String,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    merged_at: Option<String>,
+    head: GitHubHead,
+    base: GitHubHead,
+    user: Option<GitHubUser>,
+    #[serde(default)]
+    requested_reviewers: Vec<serde::de::IgnoredAny>,
+}
+
+impl GitHubPullRequest {
+    fn state(&self) -> PullRequestState {
+        if self.merged_at.is_some() {
+            PullRequestState::Merged
+        } else if self.state == "closed" {
+            PullRequestState::Closed
+        } else if self.draft {
+            PullRequestState::Draft
+        } else {
+            PullRequestState::Open
+        }
+    }
+}
+
+impl From<GitHubPullRequest> for PullRequest {
+    fn from(pr: GitHubPullRequest) -> Self {
+        PullRequest {
+            number: pr.number,
+            state: pr.state(),
+            url: pr.html_url,
+            title: pr.title,
+            head: pr.head.ref_name,
+            base: pr.base.ref_name,
+            author: pr.user.map(|user| user.login),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubHead {
+    sha: String,
+    #[serde(rename = "ref")]
+    ref_name: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubReview {
+    user: Option<GitHubUser>,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubCheckRuns {
+    check_runs: Vec<GitHubCheckRun>,
+}
+
+#[derive(Deserialize)]
+struct GitHubCheckRun {
+    name: String,
+    status: String,
+    conclusion: Option<String>,
+    html_url: Option<String>,
+}
+
+impl GitHubCheckRun {
+    fn state(&self) -> ChecksState {
+        if matches!(
+            self.conclusion.as_deref(),
+            Some("failure" | "timed_out" | "cancelled" | "action_required")
+        ) {
+            ChecksState::Failed
+        } else if self.status != "completed" {
+            ChecksState::Pending
+        } else {
+            ChecksState::Passed
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubCombinedStatus {
+    statuses: Vec<GitHubStatus>,
+}
+
+/// A status reported through the older commit status API, which some CI services still use.
+#[derive(Deserialize)]
+struct GitHubStatus {
+    context: String,
+    state: String,
+    target_url: Option<String>,
+}
+
+/// A single check that ran on a commit, like a CI job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckRun {
+    pub name: String,
+    pub state: ChecksState,
+    /// The link to the details of the check, if the forge knows one.
+    pub url: Option<String>,
+}
+
+/// The checks that ran on a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CiStatus {
+    /// The combined state of all `checks`, or `None` if there are none.
+    pub state: Option<ChecksState>,
+    pub checks: Vec<CheckRun>,
+}
+
+impl CiStatus {
+    pub fn from_checks(checks: Vec<CheckRun>) -> Self {
+        CiStatus {
+            state: combined_state(checks.iter().map(|check| check.state)),
+            checks,
+        }
+    }
+}
+
+/// Fetch the status of pull request `number` in `repo` from the GitHub API, authenticating with `token` if given.
+pub async fn github_pull_request_status(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    repo: &ForgeRepository,
+    number: usize,
+) -> Result<PullRequestStatus> {
+    let base = format!("https://api.github.com/repos/{}/{}", repo.owner, repo.name);
+    let pr: GitHubPullRequest =
+        github_get(client, token, &format!("{base}/pulls/{number}")).await?;
+    let reviews: Vec<GitHubReview> = github_get(
+        client,
+        token,
+        &format!("{base}/pulls/{number}/reviews?per_page=100"),
+    )
+    .await?;
+    let check_runs: GitHubCheckRuns = github_get(
+        client,
+        token,
+        &format!("{base}/commits/{}/check-runs?per_page=100", pr.head.sha),
+    )
+    .await?;
+
+    Ok(PullRequestStatus {
+        number,
+        state: pr.state(),
+        url: pr.html_url,
+        review: review_state(&reviews, !pr.requested_reviewers.is_empty()),
+        checks: checks_state(&check_runs.check_runs),
+    })
+}
+
+/// Fetch the checks that ran on the commit `sha` in `repo`, both check runs and commit statuses, authenticating
+/// with `token` if given.
+pub async fn github_ci_status(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    repo: &ForgeRepository,
+    sha: &str,
+) -> Result<CiStatus> {
+    let base = format!(
+        "https://api.github.com/repos/{}/{}/commits/{sha}",
+        repo.owner, repo.name
+    );
+    let check_runs: GitHubCheckRuns =
+        github_get(client, token, &format!("{base}/check-runs?per_page=100")).await?;
+    let combined: GitHubCombinedStatus =
+        github_get(client, token, &format!("{base}/status?per_page=100")).await?;
+
+    let mut checks: Vec<_> = check_runs
+        .check_runs
+        .iter()
+        .map(|run| CheckRun {
+            name: run.name.clone(),
+            state: run.state(),
+            url: run.html_url.clone(),
+        })
+        .collect();
+    checks.extend(combined.statuses.into_iter().map(|status| CheckRun {
+        state: match status.state.as_str() {
+            "success" => ChecksState::Passed,
+            "pending" => ChecksState::Pending,
+            _ => ChecksState::Failed,
+        },
+        name: status.context,
+        url: status.target_url,
+    }));
+    Ok(CiStatus::from_checks(checks))
+}
+
+/// List the open pull requests of `repo`, newest first, authenticating with `token` if given.
+pub async fn github_list_pull_requests(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    repo: &ForgeRepository,
+) -> Result<Vec<PullRequest>> {
+    let pull_requests: Vec<GitHubPullRequest> = github_get(
+        client,
+        token,
+        &format!(
+            "https://api.github.com/repos/{}/{}/pulls?state=open&per_page=100",
+            repo.owner, repo.name
+        ),
+    )
+    .await?;
+    Ok(pull_requests.into_iter().map(Into::into).collect())
+}
+
+/// Open a pull request in `repo` to merge the branch `head` into `base`, authenticating with `token`.
+///
+/// `head` may be prefixed with the owner of a fork, like `owner:branch`.
+pub async fn github_create_pull_request(
+    client: &reqwest::Client,
+    token: &str,
+    repo: &ForgeRepository,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+) -> Result<PullRequest> {
+    #[derive(Serialize)]
+    struct NewPullRequest<'a> {
+        head: &'a str,
+        base: &'a str,
+        title: &'a str,
+        body: &'a str,
+    }
+
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/pulls",
+        repo.owner, repo.name
+    );
+    let pr: GitHubPullRequest = client
+        .post(&url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::USER_AGENT, "GitButler")
+        .bearer_auth(token)
+        .json(&NewPullRequest {
+            head,
+            base,
+            title,
+            body,
+        })
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {url}"))?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse response of {url}"))?;
+    Ok(pr.into())
+}
+
+async fn github_get<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    url: &str,
+) -> Result<T> {
+    let mut request = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::USER_AGENT, "GitButler");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {url}"))?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse response of {url}"))
+}
+
+/// Reviews are listed in chronological order, so the last review of each reviewer is their current verdict.
+fn review_state(reviews: &[GitHubReview], reviews_requested: bool) -> Option<ReviewState> {
+    let mut verdicts: Vec<(&str, &str)> = Vec::new();
+    for review in reviews {
+        let (Some(user), "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED") =
+            (&review.user, review.state.as_str())
+        else {
+            continue;
+        };
+        verdicts.retain(|(login, _)| *login != user.login);
+        verdicts.push((user.login.as_str(), review.state.as_str()));
+    }
+    if verdicts
+        .iter()
+        .any(|(_, state)| *state == "CHANGES_REQUESTED")
+    {
+        Some(ReviewState::ChangesRequested)
+    } else if verdicts.iter().any(|(_, state)| *state == "APPROVED") {
+        Some(ReviewState::Approved)
+    } else if reviews_requested {
+        Some(ReviewState::Pending)
+    } else {
+        None
+    }
+}
+
+fn checks_state(check_runs: &[GitHubCheckRun]) -> Option<ChecksState> {
+    combined_state(check_runs.iter().map(GitHubCheckRun::state))
+}
+
+/// A single failure fails everything, and otherwise anything that still runs keeps the result pending.
+fn combined_state(states: impl Iterator<Item = ChecksState> + Clone) -> Option<ChecksState> {
+    let mut states = states.peekable();
+    states.peek()?;
+    Some(
+        if states.clone().any(|state| state == ChecksState::Failed) {
+            ChecksState::Failed
+        } else if states.any(|state| state == ChecksState::Pending) {
+            ChecksState::Pending
+        } else {
+            ChecksState::Passed
+        },
+    )
+}
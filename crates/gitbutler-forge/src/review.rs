@@ -1,6 +1,6 @@
 use std::path::{self, Path};
 
-use gitbutler_fs::list_files;
+use gitbutler_fs::{list_files, paths::to_slash};
 
 use crate::forge::ForgeName;
 
@@ -17,16 +17,18 @@ pub fn available_review_templates(root_path: &path::Path, forge_name: &ForgeName
     let forge_root_path = get_root(root_path);
     let forge_root_path = forge_root_path.as_path();
 
-    let walked_paths = list_files(forge_root_path, &[forge_root_path]).unwrap_or_default();
+    let walked_paths = list_files(forge_root_path, &[forge_root_path])
+        .map(|listing| listing.files)
+        .unwrap_or_default();
 
     let mut available_paths = Vec::new();
     for entry in walked_paths {
         let path_entry = entry.as_path();
-        let path_str = path_entry.to_string_lossy();
+        let path_str = to_slash(path_entry);
 
         if is_review_template(&path_str) {
             if let Ok(template_path) = forge_root_path.join(path_entry).strip_prefix(root_path) {
-                available_paths.push(template_path.to_string_lossy().to_string());
+                available_paths.push(to_slash(template_path));
             }
         }
     }
@@ -88,7 +90,7 @@ fn is_valid_review_template_path_github(path: &path::Path, root_path: &path::Pat
     let forge_root_path = get_github_directory_path(root_path);
 
     if let Ok(template_path) = absolute_path.strip_prefix(forge_root_path) {
-        is_review_template_github(&template_path.to_string_lossy())
+        is_review_template_github(&to_slash(template_path))
     } else {
         false
     }
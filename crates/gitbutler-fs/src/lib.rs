@@ -2,9 +2,10 @@ use std::{
     fs::File,
     io::{Read, Write},
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use bstr::BString;
 use gix::{
     dir::walk::EmissionMode,
@@ -13,30 +14,115 @@ use gix::{
 use serde::de::DeserializeOwned;
 use walkdir::WalkDir;
 
-// Returns an ordered list of relative paths for files inside a directory recursively.
-pub fn list_files<P: AsRef<Path>>(dir_path: P, ignore_prefixes: &[P]) -> Result<Vec<PathBuf>> {
-    let mut files = vec![];
+pub mod paths;
+
+/// The files found by [`list_files()`], along with the entries that couldn't be listed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileListing {
+    /// The ordered relative paths of the files.
+    pub files: Vec<PathBuf>,
+    /// The entries that were skipped as they couldn't be read, in the order they were encountered.
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl FileListing {
+    /// Return the listed files, or an error if any entry was skipped, for when a partial listing isn't good enough.
+    pub fn into_complete(self) -> Result<Vec<PathBuf>> {
+        if let Some(entry) = self.skipped.first() {
+            bail!(
+                "Could not list '{}' ({}), along with {} other entries",
+                entry.path.display(),
+                entry.reason,
+                self.skipped.len() - 1
+            );
+        }
+        Ok(self.files)
+    }
+}
+
+/// An entry that [`list_files()`] couldn't read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    /// The relative path of the entry, empty for the listed directory itself.
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Why an entry was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The entry can't be read with the permissions of the current user.
+    PermissionDenied,
+    /// The entry was removed while the directory was listed.
+    Vanished,
+    /// Any other error, with its message.
+    Unreadable(String),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::PermissionDenied => f.write_str("permission denied"),
+            SkipReason::Vanished => f.write_str("removed while listing"),
+            SkipReason::Unreadable(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Return the ordered relative paths of the files inside `dir_path` recursively, except for those below
+/// `ignore_prefixes`.
+///
+/// Entries that can't be read, like directories without permission to list them, are skipped and reported
+/// instead of failing the whole listing. Symlinks aren't followed, so they are listed even if they are dangling.
+pub fn list_files<P: AsRef<Path>>(dir_path: P, ignore_prefixes: &[P]) -> Result<FileListing> {
+    let mut listing = FileListing::default();
     let dir_path = dir_path.as_ref();
     if !dir_path.exists() {
-        return Ok(files);
+        return Ok(listing);
     }
+    let is_ignored = |path: &Path| {
+        ignore_prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_ref()))
+    };
     for entry in WalkDir::new(dir_path) {
-        let entry = entry?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err
+                    .path()
+                    .and_then(|path| path.strip_prefix(dir_path).ok())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                if !is_ignored(&path) {
+                    listing.skipped.push(SkippedEntry {
+                        path,
+                        reason: skip_reason(&err),
+                    });
+                }
+                continue;
+            }
+        };
         if !entry.file_type().is_dir() {
             let path = entry.path();
             let path = path.strip_prefix(dir_path)?;
             let path = path.to_path_buf();
-            if ignore_prefixes
-                .iter()
-                .any(|prefix| path.starts_with(prefix.as_ref()))
-            {
+            if is_ignored(&path) {
                 continue;
             }
-            files.push(path);
+            listing.files.push(path);
         }
     }
-    files.sort();
-    Ok(files)
+    listing.files.sort();
+    Ok(listing)
+}
+
+fn skip_reason(err: &walkdir::Error) -> SkipReason {
+    match err.io_error().map(std::io::Error::kind) {
+        Some(std::io::ErrorKind::PermissionDenied) => SkipReason::PermissionDenied,
+        Some(std::io::ErrorKind::NotFound) => SkipReason::Vanished,
+        _ => SkipReason::Unreadable(err.to_string()),
+    }
 }
 
 // Return an iterator of worktree-relative slash-separated paths for files inside the `worktree_dir`, recursively.
@@ -69,6 +155,58 @@ pub fn write<P: AsRef<Path>>(file_path: P, contents: impl AsRef<[u8]>) -> anyhow
     Ok(persist_tempfile(temp_file, file_path)?)
 }
 
+/// The state of a file when it was read, to tell whether it changed before it's written, see
+/// [`write_unless_changed()`]. It can be handed to the frontend along with what was read, and back with what to
+/// write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FileStamp(Option<(SystemTime, u64)>);
+
+impl FileStamp {
+    /// Return the stamp of the file at `path`, which may not exist.
+    pub fn of(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) => Ok(FileStamp(Some((metadata.modified()?, metadata.len())))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(FileStamp(None)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The error of [`write_unless_changed()`] if the file was changed by someone else after it was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedSinceRead {
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for ChangedSinceRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' was changed by another program, and wasn't overwritten",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for ChangedSinceRead {}
+
+/// Like [`write()`], but fail with [`ChangedSinceRead`] if the file at `file_path` doesn't match `stamp`
+/// anymore, as taken when it was read, so changes made in the meantime, like by an editor, aren't lost.
+pub fn write_unless_changed<P: AsRef<Path>>(
+    file_path: P,
+    stamp: FileStamp,
+    contents: impl AsRef<[u8]>,
+) -> anyhow::Result<()> {
+    let file_path = file_path.as_ref();
+    if FileStamp::of(file_path)? != stamp {
+        return Err(ChangedSinceRead {
+            path: file_path.to_owned(),
+        }
+        .into());
+    }
+    write(file_path, contents)
+}
+
 /// Write a single file so that the write either fully succeeds, or fully fails,
 /// and create all leading directories.
 pub fn create_dirs_then_write<P: AsRef<Path>>(
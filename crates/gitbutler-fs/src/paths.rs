@@ -0,0 +1,94 @@
+//! Bring paths into a form that means the same on all platforms, as Windows has a few specifics:
+//!
+//! - Paths may be verbatim, like `\\?\C:\repo` as returned by [`std::fs::canonicalize()`], which lifts the length
+//!   limit of paths but doesn't compare equal to `C:\repo`. The standard library makes paths verbatim by itself
+//!   when they are too long, so they can be stored without the prefix.
+//! - Network shares are addressed like `\\server\share`, or `\\?\UNC\server\share` if verbatim.
+//! - Drive letters may be spelled in either case.
+//! - Some file names, like `CON` or `aux.txt`, are reserved for devices in every directory.
+//! - Separators are backslashes, while Git and the files GitButler stores use forward slashes.
+use std::path::{Component, Path, PathBuf};
+
+/// File names reserved for devices on Windows, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Return `path` without a verbatim prefix and with an uppercase drive letter on Windows, like `C:\repo` for
+/// `\\?\c:\repo` and `\\server\share` for `\\?\UNC\server\share`, so paths to the same location compare equal.
+///
+/// Other paths, and all paths on other platforms, are returned as they are.
+pub fn normalize(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(path) = path.to_str() {
+            return normalize_windows(path).into();
+        }
+    }
+    path.to_owned()
+}
+
+/// Like [`normalize()`], but for `path` which is a Windows path no matter the platform.
+pub fn normalize_windows(path: &str) -> String {
+    let path = if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{share}")
+    } else {
+        match path.strip_prefix(r"\\?\") {
+            Some(rest) if has_drive_letter(rest) => rest.to_owned(),
+            _ => path.to_owned(),
+        }
+    };
+    if has_drive_letter(&path) {
+        format!("{}{}", path[..1].to_ascii_uppercase(), &path[1..])
+    } else {
+        path
+    }
+}
+
+/// Return `true` if `path` is on a network share, like `\\server\share\repo`, no matter the platform.
+pub fn is_unc_windows(path: &str) -> bool {
+    let path = normalize_windows(path);
+    path.strip_prefix(r"\\")
+        .is_some_and(|share| !share.starts_with(['?', '.']) && share.contains('\\'))
+}
+
+/// Return the first component of `path` that can't be a file name on Windows, as it's reserved for a device like
+/// `NUL`, or ends with a dot or space, which Windows drops.
+pub fn reserved_component(path: &Path) -> Option<&str> {
+    path.components().find_map(|component| {
+        let Component::Normal(name) = component else {
+            return None;
+        };
+        let name = name.to_str()?;
+        is_reserved_name(name).then_some(name)
+    })
+}
+
+/// Return `true` if `name` can't be a file name on Windows, see [`reserved_component()`].
+pub fn is_reserved_name(name: &str) -> bool {
+    if name.ends_with(['.', ' ']) && name != "." && name != ".." {
+        return true;
+    }
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Return the relative `path` with forward slashes as separators, like Git uses them, no matter the platform.
+pub fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes
+            .get(2)
+            .is_none_or(|separator| matches!(separator, b'\\' | b'/'))
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use gitbutler_fs::{list_files, SkipReason};
+
+#[test]
+fn files_are_listed_in_order() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("b/c"))?;
+    std::fs::write(dir.path().join("b/c/d"), "")?;
+    std::fs::write(dir.path().join("a"), "")?;
+    std::fs::create_dir(dir.path().join("ignored"))?;
+    std::fs::write(dir.path().join("ignored/e"), "")?;
+
+    let listing = list_files(dir.path(), &[Path::new("ignored")])?;
+    assert_eq!(listing.files, [PathBuf::from("a"), PathBuf::from("b/c/d")]);
+    assert!(listing.skipped.is_empty());
+    Ok(())
+}
+
+#[test]
+fn missing_directories_have_no_files() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let listing = list_files(dir.path().join("missing"), &[])?;
+    assert!(listing.files.is_empty());
+    assert!(listing.skipped.is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn dangling_symlinks_are_listed() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::os::unix::fs::symlink("missing", dir.path().join("link"))?;
+
+    let listing = list_files(dir.path(), &[])?;
+    assert_eq!(listing.files, [PathBuf::from("link")]);
+    assert!(listing.skipped.is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_directories_are_skipped() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    let locked = dir.path().join("locked");
+    std::fs::create_dir(&locked)?;
+    std::fs::write(locked.join("secret"), "")?;
+    std::fs::write(dir.path().join("a"), "")?;
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000))?;
+    if std::fs::read_dir(&locked).is_ok() {
+        // Permissions don't apply to the superuser.
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755))?;
+        return Ok(());
+    }
+
+    let listing = list_files(dir.path(), &[]);
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755))?;
+    let listing = listing?;
+    assert_eq!(listing.files, [PathBuf::from("a")]);
+    assert_eq!(listing.skipped.len(), 1);
+    assert_eq!(listing.skipped[0].path, PathBuf::from("locked"));
+    assert_eq!(listing.skipped[0].reason, SkipReason::PermissionDenied);
+    assert!(
+        listing.clone().into_complete().is_err(),
+        "incomplete listings can be rejected"
+    );
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use gitbutler_fs::paths::{
+    is_reserved_name, is_unc_windows, normalize_windows, reserved_component, to_slash,
+};
+
+#[test]
+fn verbatim_prefixes_are_removed() {
+    assert_eq!(normalize_windows(r"\\?\C:\repo"), r"C:\repo");
+    assert_eq!(
+        normalize_windows(r"\\?\UNC\server\share\repo"),
+        r"\\server\share\repo"
+    );
+    assert_eq!(
+        normalize_windows(r"\\?\Volume{b75e2c83}\repo"),
+        r"\\?\Volume{b75e2c83}\repo",
+        "verbatim paths that can't be written otherwise are kept"
+    );
+}
+
+#[test]
+fn drive_letters_are_uppercase() {
+    assert_eq!(normalize_windows(r"c:\repo"), r"C:\repo");
+    assert_eq!(normalize_windows(r"\\?\d:\repo"), r"D:\repo");
+    assert_eq!(normalize_windows("c:"), "C:");
+    assert_eq!(
+        normalize_windows(r"cd:\repo"),
+        r"cd:\repo",
+        "not a drive letter"
+    );
+    assert_eq!(normalize_windows("relative"), "relative");
+}
+
+#[test]
+fn network_shares() {
+    assert!(is_unc_windows(r"\\server\share\repo"));
+    assert!(is_unc_windows(r"\\?\UNC\server\share"));
+    assert!(!is_unc_windows(r"\\?\C:\repo"));
+    assert!(!is_unc_windows(r"\\.\pipe\name"));
+    assert!(!is_unc_windows(r"C:\repo"));
+}
+
+#[test]
+fn reserved_names() {
+    for name in [
+        "CON",
+        "nul",
+        "Aux.txt",
+        "com1.tar.gz",
+        "LPT9",
+        "trailing.",
+        "space ",
+    ] {
+        assert!(is_reserved_name(name), "{name}");
+    }
+    for name in ["CONSOLE", "nullable.rs", "com10", "file.txt", ".", ".."] {
+        assert!(!is_reserved_name(name), "{name}");
+    }
+    assert_eq!(reserved_component(Path::new("src/aux/mod.rs")), Some("aux"));
+    assert_eq!(reserved_component(Path::new("src/auxiliary/mod.rs")), None);
+}
+
+#[test]
+fn slash_separated() {
+    assert_eq!(
+        to_slash(Path::new("a").join("b").join("c.txt").as_path()),
+        "a/b/c.txt"
+    );
+    assert_eq!(to_slash(Path::new("file.txt")), "file.txt");
+}
@@ -0,0 +1,42 @@
+use gitbutler_fs::{write_unless_changed, ChangedSinceRead, FileStamp};
+
+#[test]
+fn unchanged_files_are_written() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    std::fs::write(&path, "read")?;
+
+    let stamp = FileStamp::of(&path)?;
+    write_unless_changed(&path, stamp, "written")?;
+    assert_eq!(std::fs::read_to_string(&path)?, "written");
+    Ok(())
+}
+
+#[test]
+fn files_changed_since_reading_are_kept() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    std::fs::write(&path, "read")?;
+
+    let stamp = FileStamp::of(&path)?;
+    std::fs::write(&path, "edited elsewhere")?;
+    let err = write_unless_changed(&path, stamp, "written").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ChangedSinceRead>(),
+        Some(&ChangedSinceRead { path: path.clone() })
+    );
+    assert_eq!(std::fs::read_to_string(&path)?, "edited elsewhere");
+    Ok(())
+}
+
+#[test]
+fn files_created_since_reading_are_kept() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+
+    let stamp = FileStamp::of(&path)?;
+    std::fs::write(&path, "created elsewhere")?;
+    assert!(write_unless_changed(&path, stamp, "written").is_err());
+    assert_eq!(std::fs::read_to_string(&path)?, "created elsewhere");
+    Ok(())
+}
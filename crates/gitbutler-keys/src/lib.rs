@@ -0,0 +1,68 @@
+//! A key pair generated and owned by GitButler, to authenticate with remotes without relying on a working
+//! `ssh-agent` or keys set up by the user.
+//!
+//! Unlike the other credentials, which `gitbutler-secret` keeps in the keychain, the private key is a
+//! file without a passphrase, only readable by the current user like the keys in `~/.ssh`. That's because Git and
+//! libgit2 load SSH keys from files, and the path is stored as the key of projects that use it.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ssh_key::{rand_core::OsRng, Algorithm, LineEnding, PrivateKey};
+
+/// The path to the private key, relative to the application data directory.
+const PRIVATE_KEY_FILE: &str = "keys/ed25519";
+/// The path to the public key, relative to the application data directory.
+const PUBLIC_KEY_FILE: &str = "keys/ed25519.pub";
+
+/// The comment of generated public keys, to make them recognizable where they are registered.
+const KEY_COMMENT: &str = "GitButler";
+
+/// Generates and provides the GitButler key pair stored in the application data directory.
+#[derive(Clone, Debug)]
+pub struct Controller {
+    local_data_dir: PathBuf,
+}
+
+impl Controller {
+    pub fn from_path(path: impl Into<PathBuf>) -> Controller {
+        Controller {
+            local_data_dir: path.into(),
+        }
+    }
+
+    /// The path to the private key in OpenSSH format, which exists after [`Self::get_or_create()`] was called.
+    pub fn private_key_path(&self) -> PathBuf {
+        self.local_data_dir.join(PRIVATE_KEY_FILE)
+    }
+
+    /// Return the public key in OpenSSH format, like `ssh-ed25519 AAAA… GitButler`,
+    /// generating a new key pair if there is none yet.
+    pub fn get_or_create(&self) -> Result<String> {
+        let private_key_path = self.private_key_path();
+        let key = if private_key_path.is_file() {
+            PrivateKey::read_openssh_file(&private_key_path)
+                .with_context(|| format!("failed to read key at {}", private_key_path.display()))?
+        } else {
+            self.create(&private_key_path)?
+        };
+        Ok(key.public_key().to_openssh()?)
+    }
+
+    fn create(&self, private_key_path: &Path) -> Result<PrivateKey> {
+        let mut key =
+            PrivateKey::random(&mut OsRng, Algorithm::Ed25519).context("failed to generate key")?;
+        key.set_comment(KEY_COMMENT);
+
+        if let Some(keys_dir) = private_key_path.parent() {
+            std::fs::create_dir_all(keys_dir)?;
+        }
+        // This only allows the current user to read the key, as ssh requires.
+        key.write_openssh_file(private_key_path, LineEnding::LF)
+            .context("failed to write private key")?;
+        gitbutler_fs::write(
+            self.local_data_dir.join(PUBLIC_KEY_FILE),
+            key.public_key().to_openssh()?,
+        )?;
+        Ok(key)
+    }
+}
@@ -0,0 +1,20 @@
+use gitbutler_keys::Controller;
+
+#[test]
+fn key_is_generated_once_and_reused() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let controller = Controller::from_path(tmp.path());
+    assert!(!controller.private_key_path().exists());
+
+    let public_key = controller.get_or_create()?;
+    assert!(public_key.starts_with("ssh-ed25519 "));
+    assert!(public_key.ends_with(" GitButler"));
+    assert!(controller.private_key_path().is_file());
+
+    assert_eq!(
+        Controller::from_path(tmp.path()).get_or_create()?,
+        public_key,
+        "the stored key is reused"
+    );
+    Ok(())
+}
@@ -104,7 +104,9 @@ pub fn in_open_workspace_mode(ctx: &CommandContext) -> bool {
     operating_mode(ctx) == OperatingMode::OpenWorkspace
 }
 
+/// Fail unless the workspace is open and managed by the project of `ctx`.
 pub fn assure_open_workspace_mode(ctx: &CommandContext) -> Result<()> {
+    assure_workspace_owner(ctx)?;
     if in_open_workspace_mode(ctx) {
         Ok(())
     } else {
@@ -112,6 +114,17 @@ pub fn assure_open_workspace_mode(ctx: &CommandContext) -> Result<()> {
     }
 }
 
+/// Fail if the project of `ctx` is a [sub-project](gitbutler_project::Project::is_subproject).
+///
+/// Sub-projects share the workspace commit of their repository, so its virtual branches are only managed by the
+/// project of the whole repository.
+pub fn assure_workspace_owner(ctx: &CommandContext) -> Result<()> {
+    if ctx.project().is_subproject() {
+        bail!("Virtual branches are managed by the project of the whole repository, not by its sub-projects")
+    }
+    Ok(())
+}
+
 pub fn in_edit_mode(ctx: &CommandContext) -> bool {
     matches!(operating_mode(ctx), OperatingMode::Edit(_))
 }
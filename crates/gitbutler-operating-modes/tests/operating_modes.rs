@@ -30,6 +30,7 @@ fn create_edit_mode_metadata(ctx: &CommandContext) {
 
 mod operating_modes {
     mod open_workspace_mode {
+        use gitbutler_command_context::CommandContext;
         use gitbutler_operating_modes::{assure_open_workspace_mode, in_open_workspace_mode};
         use gitbutler_testsupport::{Case, Suite};
 
@@ -97,6 +98,27 @@ mod operating_modes {
 
             assert!(assure_open_workspace_mode(ctx).is_err());
         }
+
+        #[test]
+        fn assure_open_workspace_mode_err_for_subprojects() {
+            let suite = Suite::default();
+            let Case { ctx, project, .. } =
+                &suite.new_case_with_files([("lib/file.txt".into(), "content")].into());
+            create_and_checkout_branch(ctx, "gitbutler/workspace");
+
+            let subproject = suite
+                .projects
+                .add_subproject(project.id, std::path::Path::new("lib"))
+                .unwrap();
+            let sub_ctx = CommandContext::open(&subproject, Default::default()).unwrap();
+
+            assert!(in_open_workspace_mode(&sub_ctx), "the workspace is shared");
+            assert!(
+                assure_open_workspace_mode(&sub_ctx).is_err(),
+                "but only managed by the project of the repository"
+            );
+            assert!(assure_open_workspace_mode(ctx).is_ok());
+        }
     }
 
     mod outside_workspace_mode {
@@ -0,0 +1,185 @@
+//! Summaries of how the working directory changed over a period of time, as recorded by snapshots.
+use std::{collections::BTreeSet, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    bookmarks::Bookmark,
+    deltas::Delta,
+    entry::{OperationKind, SnapshotDetails},
+};
+
+/// Snapshots further apart than this many seconds belong to different [sessions](ActivitySession).
+pub const SESSION_GAP_SECONDS: i64 = 15 * 60;
+
+/// A period of uninterrupted activity, with no more than [`SESSION_GAP_SECONDS`] between its snapshots and
+/// editor [heartbeats](crate::heartbeat). Git operations outside of GitButler, like a checkout, end a session.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySession {
+    /// The creation time of the first snapshot of the session, in seconds since the Unix epoch.
+    pub start: i64,
+    /// The creation time of the last snapshot of the session, in seconds since the Unix epoch.
+    pub end: i64,
+    /// The short name of the branch the session was recorded on, like `main`, or `None` if `HEAD` was detached
+    /// or no change recorded the branch.
+    pub branch: Option<String>,
+    pub snapshots: usize,
+    /// The amount of editor heartbeats, which keep the session going while files are only read.
+    pub heartbeats: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// The amount of distinct files that changed during the session.
+    pub files_touched: usize,
+    /// The bookmarks for times from the start to the end of the session, oldest first.
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// The activity within a single hour.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HourlyActivity {
+    /// The start of the hour in seconds since the Unix epoch, aligned to the local time of the snapshots.
+    pub hour: i64,
+    pub snapshots: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// The activity within a range of time, as returned by [`activity_summary()`](crate::OplogExt::activity_summary()).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySummary {
+    /// The amount of snapshots that were created.
+    pub snapshots: usize,
+    /// All files that changed in the working directory, sorted by path.
+    pub files_touched: Vec<PathBuf>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// The sum of the duration of all sessions, where each session counts for at least a minute.
+    pub active_minutes: u64,
+    /// The sessions of activity, oldest first.
+    pub sessions: Vec<ActivitySession>,
+    /// The activity of each hour that had snapshots, oldest first.
+    pub hourly: Vec<HourlyActivity>,
+    /// All bookmarks within the range of time, including those outside of sessions, oldest first.
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// The changes to the working directory recorded by a single snapshot.
+pub(crate) struct SnapshotActivity {
+    pub snapshot_id: git2::Oid,
+    pub created_at: git2::Time,
+    pub details: Option<SnapshotDetails>,
+    pub changed_paths: Vec<PathBuf>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// Aggregate the activity of the given `snapshots` and the times of `heartbeats`, both in any order, and add
+/// `bookmarks`, oldest first, to the sessions they fall into.
+pub(crate) fn summarize(
+    snapshots: &[SnapshotActivity],
+    heartbeats: Vec<i64>,
+    bookmarks: Vec<Bookmark>,
+) -> ActivitySummary {
+    let mut points: Vec<_> = snapshots
+        .iter()
+        .map(|snapshot| (snapshot.created_at.seconds(), Some(snapshot)))
+        .chain(heartbeats.into_iter().map(|seconds| (seconds, None)))
+        .collect();
+    points.sort_by_key(|(seconds, _)| *seconds);
+
+    let mut summary = ActivitySummary::default();
+    let mut files_touched = BTreeSet::new();
+    let mut session_files = BTreeSet::new();
+    let mut after_git_operation = false;
+    for (seconds, snapshot) in points {
+        // The changes of a git operation aren't activity, and what follows it is work on something else.
+        if snapshot.is_some_and(|snapshot| {
+            snapshot
+                .details
+                .as_ref()
+                .is_some_and(|details| details.operation == OperationKind::ExternalGitOperation)
+        }) {
+            after_git_operation = true;
+            continue;
+        }
+        let continues_session = !std::mem::take(&mut after_git_operation)
+            && summary
+                .sessions
+                .last()
+                .is_some_and(|session| seconds - session.end <= SESSION_GAP_SECONDS);
+        if !continues_session {
+            session_files.clear();
+            summary.sessions.push(ActivitySession {
+                start: seconds,
+                ..Default::default()
+            });
+        }
+        let session = summary.sessions.last_mut().expect("present or just added");
+        session.end = seconds;
+        let Some(snapshot) = snapshot else {
+            session.heartbeats += 1;
+            continue;
+        };
+        session_files.extend(snapshot.changed_paths.iter());
+        session.snapshots += 1;
+        session.lines_added += snapshot.lines_added;
+        session.lines_removed += snapshot.lines_removed;
+        session.files_touched = session_files.len();
+        summary.snapshots += 1;
+        summary.lines_added += snapshot.lines_added;
+        summary.lines_removed += snapshot.lines_removed;
+        files_touched.extend(snapshot.changed_paths.iter().cloned());
+
+        let offset_seconds = i64::from(snapshot.created_at.offset_minutes()) * 60;
+        let hour = (seconds + offset_seconds).div_euclid(3600) * 3600 - offset_seconds;
+        if summary
+            .hourly
+            .last()
+            .is_none_or(|hourly| hourly.hour != hour)
+        {
+            summary.hourly.push(HourlyActivity {
+                hour,
+                ..Default::default()
+            });
+        }
+        let hourly = summary.hourly.last_mut().expect("present or just added");
+        hourly.snapshots += 1;
+        hourly.lines_added += snapshot.lines_added;
+        hourly.lines_removed += snapshot.lines_removed;
+    }
+
+    for bookmark in &bookmarks {
+        if let Some(session) = summary
+            .sessions
+            .iter_mut()
+            .find(|session| (session.start..=session.end).contains(&bookmark.at))
+        {
+            session.bookmarks.push(bookmark.clone());
+        }
+    }
+    summary.bookmarks = bookmarks;
+    summary.files_touched = files_touched.into_iter().collect();
+    summary.active_minutes = summary
+        .sessions
+        .iter()
+        .map(|session| ((session.end - session.start) as u64).div_ceil(60).max(1))
+        .sum();
+    summary
+}
+
+/// Set the branch of each of `sessions` to the one its first change among `deltas`, oldest first, was made on, or
+/// the last change before it if the session only has heartbeats or snapshots without deltas.
+pub(crate) fn assign_branches(sessions: &mut [ActivitySession], deltas: &[Delta]) {
+    for session in sessions {
+        let first_in_session = deltas
+            .iter()
+            .find(|delta| (session.start..=session.end).contains(&delta.at));
+        let last_before = || deltas.iter().rev().find(|delta| delta.at < session.start);
+        session.branch = first_in_session
+            .or_else(last_before)
+            .and_then(|delta| delta.branch.clone());
+    }
+}
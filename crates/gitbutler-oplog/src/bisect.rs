@@ -0,0 +1,150 @@
+//! Find when a file stopped satisfying a condition, like containing a function that later went missing, by
+//! binary-searching its content as [reconstructed](crate::deltas::blob_at()) from snapshots, checkpoints and
+//! deltas across all sessions.
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_project::Project;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{blob_store::BlobStore, deltas, OplogExt};
+
+/// What the content of a file has to satisfy to be good.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPredicate {
+    /// The text to look for.
+    pub pattern: String,
+    /// If `true`, `pattern` is a regular expression.
+    #[serde(default)]
+    pub is_regex: bool,
+    /// If `true`, the content is good if `pattern` is present, otherwise if it is absent.
+    pub present: bool,
+}
+
+impl HistoryPredicate {
+    fn matcher(&self) -> Result<impl Fn(&str) -> bool + '_> {
+        let regex = self
+            .is_regex
+            .then(|| Regex::new(&self.pattern))
+            .transpose()
+            .with_context(|| format!("'{}' isn't a valid regular expression", self.pattern))?;
+        Ok(move |content: &str| {
+            let found = match &regex {
+                Some(regex) => regex.is_match(content),
+                None => content.contains(&self.pattern),
+            };
+            found == self.present
+        })
+    }
+}
+
+/// The result of [`history_bisect()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryBisection {
+    /// The latest recorded state that satisfied the predicate, in seconds since the Unix epoch.
+    pub last_good_at: i64,
+    /// The first recorded state that didn't satisfy the predicate anymore, in seconds since the Unix epoch.
+    pub first_bad_at: i64,
+    /// The content of the file in the first bad state, or `None` if the file didn't exist.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub first_bad_blob_id: Option<git2::Oid>,
+    /// The amount of recorded states of the file that were searched.
+    pub states: usize,
+    /// The amount of states that had to be reconstructed and checked.
+    pub steps: usize,
+}
+
+/// Return when the file at the worktree-relative `file_path` of `project` first stopped satisfying `predicate`,
+/// or `None` if it still does in its latest recorded state.
+///
+/// The oldest recorded state must satisfy `predicate`, and it is assumed to keep doing so until the first bad state,
+/// like with `git bisect`. A file that didn't exist has no content, so doesn't contain any pattern. Fails before the
+/// next state is checked once `should_interrupt` is set.
+pub fn history_bisect(
+    project: &Project,
+    file_path: &Path,
+    predicate: &HistoryPredicate,
+    should_interrupt: &AtomicBool,
+) -> Result<Option<HistoryBisection>> {
+    let is_good = predicate.matcher()?;
+    let states = state_times(project, file_path)?;
+    let (Some(&oldest), Some(&latest)) = (states.first(), states.last()) else {
+        bail!("No states of '{}' were recorded", file_path.display());
+    };
+
+    let repo = git2::Repository::open(&project.path)?;
+    let store = BlobStore::open(&repo)?;
+    let mut steps = 0;
+    let mut check = |at: i64| -> Result<(bool, Option<git2::Oid>)> {
+        if should_interrupt.load(Ordering::Relaxed) {
+            bail!(
+                "Searching the history of '{}' was cancelled",
+                file_path.display()
+            );
+        }
+        steps += 1;
+        let blob_id = deltas::blob_at(project, file_path, at)?.and_then(|blob| blob.blob_id);
+        let content = match blob_id {
+            Some(blob_id) => String::from_utf8_lossy(&store.load(blob_id)?).into_owned(),
+            None => String::new(),
+        };
+        Ok((is_good(&content), blob_id))
+    };
+
+    if !check(oldest)?.0 {
+        bail!(
+            "The oldest recorded state of '{}' doesn't satisfy the condition already",
+            file_path.display()
+        );
+    }
+    let (latest_good, mut first_bad_blob_id) = check(latest)?;
+    if latest_good {
+        return Ok(None);
+    }
+
+    // The state at `states[good]` satisfies the predicate, the one at `states[bad]` doesn't.
+    let (mut good, mut bad) = (0, states.len() - 1);
+    while bad - good > 1 {
+        let middle = good + (bad - good) / 2;
+        let (middle_good, blob_id) = check(states[middle])?;
+        if middle_good {
+            good = middle;
+        } else {
+            bad = middle;
+            first_bad_blob_id = blob_id;
+        }
+    }
+    Ok(Some(HistoryBisection {
+        last_good_at: states[good],
+        first_bad_at: states[bad],
+        first_bad_blob_id,
+        states: states.len(),
+        steps,
+    }))
+}
+
+/// Return the times at which the content of `file_path` may have changed, oldest first: when snapshots recorded
+/// a new version, when deltas noticed a change, and when checkpoints were written.
+fn state_times(project: &Project, file_path: &Path) -> Result<Vec<i64>> {
+    let mut times: BTreeSet<_> = project
+        .file_versions(file_path, deltas::MAX_SNAPSHOTS)?
+        .iter()
+        .map(|version| version.created_at.seconds())
+        .collect();
+    times.extend(
+        deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?
+            .iter()
+            .filter(|delta| {
+                delta.checkpoint.is_some() || delta.paths.iter().any(|path| path == file_path)
+            })
+            .map(|delta| delta.at),
+    );
+    Ok(times.into_iter().collect())
+}
@@ -0,0 +1,33 @@
+//! Store file contents in the object database of the repository, where they are addressed by their hash like
+//! any other blob, so identical contents are only stored once no matter how often they are recorded.
+use anyhow::{Context, Result};
+
+/// Contents stored as blobs in the object database of a repository.
+pub struct BlobStore<'repo> {
+    odb: git2::Odb<'repo>,
+}
+
+impl<'repo> BlobStore<'repo> {
+    /// Open the object database of `repo`.
+    pub fn open(repo: &'repo git2::Repository) -> Result<Self> {
+        Ok(BlobStore { odb: repo.odb()? })
+    }
+
+    /// Store `content` unless a blob with the same content already exists, and return the id of the blob.
+    pub fn store(&self, content: &[u8]) -> Result<git2::Oid> {
+        let id = git2::Oid::hash_object(git2::ObjectType::Blob, content)?;
+        if !self.odb.exists(id) {
+            self.odb.write(git2::ObjectType::Blob, content)?;
+        }
+        Ok(id)
+    }
+
+    /// Return the content of the blob with `id`.
+    pub fn load(&self, id: git2::Oid) -> Result<Vec<u8>> {
+        let object = self
+            .odb
+            .read(id)
+            .with_context(|| format!("blob {id} isn't in the object database"))?;
+        Ok(object.data().to_vec())
+    }
+}
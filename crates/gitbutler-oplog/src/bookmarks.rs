@@ -0,0 +1,167 @@
+//! Notes and emoji markers that users attach to points in time, like the moment where everything still worked,
+//! returned along with the [sessions](crate::activity::ActivitySession) they fall into.
+//!
+//! If the history of the project is [encrypted](crate::encryption), so is the file with its bookmarks.
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_project::Project;
+use gitbutler_storage::{
+    encryption::{self, Cipher},
+    Storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::ensure_unlocked;
+
+/// The file in the GitButler directory of a project with all bookmarks, as JSON array.
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// Emoji markers are short, but a single emoji can consist of several characters.
+const MAX_EMOJI_CHARS: usize = 16;
+
+/// A note or marker for a point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    /// Identifies the bookmark within its project.
+    pub id: u64,
+    /// The point in time the bookmark is for, in seconds since the Unix epoch. To mark a session, this is its start.
+    pub at: i64,
+    /// What the user noted, which may be empty if there is an emoji.
+    pub note: String,
+    /// A marker like `✅`, if one was chosen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// When the bookmark was added, in seconds since the Unix epoch.
+    pub created_at: i64,
+}
+
+/// Add a bookmark with `note` and `emoji` for `at` seconds since the Unix epoch to `project`, and return it.
+pub fn add_bookmark(
+    project: &Project,
+    at: i64,
+    note: &str,
+    emoji: Option<&str>,
+) -> Result<Bookmark> {
+    let (note, emoji) = validate(note, emoji)?;
+    let storage = storage(project)?;
+    let mut bookmarks = read(&storage)?;
+    let bookmark = Bookmark {
+        id: bookmarks
+            .iter()
+            .map(|bookmark| bookmark.id + 1)
+            .max()
+            .unwrap_or(1),
+        at,
+        note,
+        emoji,
+        created_at: now()?,
+    };
+    bookmarks.push(bookmark.clone());
+    write(&storage, bookmarks)?;
+    Ok(bookmark)
+}
+
+/// Change the `note` and `emoji` of the bookmark `id` of `project`, and return it.
+pub fn update_bookmark(
+    project: &Project,
+    id: u64,
+    note: &str,
+    emoji: Option<&str>,
+) -> Result<Bookmark> {
+    let (note, emoji) = validate(note, emoji)?;
+    let storage = storage(project)?;
+    let mut bookmarks = read(&storage)?;
+    let Some(bookmark) = bookmarks.iter_mut().find(|bookmark| bookmark.id == id) else {
+        bail!("There is no bookmark with id {id}");
+    };
+    bookmark.note = note;
+    bookmark.emoji = emoji;
+    let bookmark = bookmark.clone();
+    write(&storage, bookmarks)?;
+    Ok(bookmark)
+}
+
+/// Remove the bookmark `id` of `project`, and return it.
+pub fn remove_bookmark(project: &Project, id: u64) -> Result<Bookmark> {
+    let storage = storage(project)?;
+    let mut bookmarks = read(&storage)?;
+    let Some(index) = bookmarks.iter().position(|bookmark| bookmark.id == id) else {
+        bail!("There is no bookmark with id {id}");
+    };
+    let bookmark = bookmarks.remove(index);
+    write(&storage, bookmarks)?;
+    Ok(bookmark)
+}
+
+/// Return the bookmarks of `project` for times within `range`, in seconds since the Unix epoch, oldest first.
+pub fn list_bookmarks(project: &Project, range: Range<i64>) -> Result<Vec<Bookmark>> {
+    let mut bookmarks: Vec<_> = read(&storage(project)?)?
+        .into_iter()
+        .filter(|bookmark| range.contains(&bookmark.at))
+        .collect();
+    bookmarks.sort_by_key(|bookmark| (bookmark.at, bookmark.id));
+    Ok(bookmarks)
+}
+
+/// Return `note` and `emoji` without surrounding whitespace, or an error if there is neither.
+fn validate(note: &str, emoji: Option<&str>) -> Result<(String, Option<String>)> {
+    let note = note.trim();
+    let emoji = emoji.map(str::trim).filter(|emoji| !emoji.is_empty());
+    if note.is_empty() && emoji.is_none() {
+        bail!("A bookmark needs a note or an emoji");
+    }
+    if let Some(emoji) = emoji {
+        if emoji.chars().count() > MAX_EMOJI_CHARS || emoji.contains(char::is_whitespace) {
+            bail!("'{emoji}' isn't a single emoji");
+        }
+    }
+    Ok((note.to_owned(), emoji.map(ToOwned::to_owned)))
+}
+
+fn now() -> Result<i64> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("the clock is before the Unix epoch")?
+        .as_secs();
+    i64::try_from(now).context("the clock is too far in the future")
+}
+
+/// Rewrite the bookmarks of `project` so they are encrypted with `target`, or are plain if it's `None`, reading them
+/// with `source`.
+pub(crate) fn reseal(
+    project: &Project,
+    source: Option<Arc<Cipher>>,
+    target: Option<Arc<Cipher>>,
+) -> Result<()> {
+    if !project.gb_dir().join(BOOKMARKS_FILE).exists() {
+        return Ok(());
+    }
+    let bookmarks = read(&Storage::new(project.gb_dir()).with_cipher(source))?;
+    write(
+        &Storage::new(project.gb_dir()).with_cipher(target),
+        bookmarks,
+    )
+}
+
+/// Return the storage of the bookmarks of `project`, which encrypts them if its history is encrypted.
+fn storage(project: &Project) -> Result<Storage> {
+    ensure_unlocked(project)?;
+    Ok(Storage::new(project.gb_dir()).with_cipher(encryption::cipher(&project.gb_dir())))
+}
+
+fn read(storage: &Storage) -> Result<Vec<Bookmark>> {
+    match storage
+        .read(BOOKMARKS_FILE)
+        .with_context(|| format!("failed to read '{BOOKMARKS_FILE}'"))?
+    {
+        Some(content) => serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse '{BOOKMARKS_FILE}'")),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write(storage: &Storage, bookmarks: Vec<Bookmark>) -> Result<()> {
+    Ok(storage.write(BOOKMARKS_FILE, serde_json::to_string_pretty(&bookmarks)?)?)
+}
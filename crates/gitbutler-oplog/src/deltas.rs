@@ -0,0 +1,2185 @@
+//! The changes to worktree files as noticed by the watcher, each
+//! [classified](gitbutler_project::machine_changes::classify()) as made by a human or a machine, so timelines
+//! can leave out what builds, formatters or code generators wrote.
+//!
+//! Every [`CHECKPOINT_INTERVAL_DELTAS`] deltas or [`CHECKPOINT_INTERVAL_SECONDS`], whichever comes first, the
+//! content of the worktree is written into the object database as a checkpoint. As blobs and trees are
+//! content-addressed, a checkpoint only costs the files that changed since the previous one. Checkpoints are
+//! chained as commits under [`CHECKPOINTS_REF`] to keep them from being garbage-collected, and serve as base
+//! when reconstructing files between snapshots with [`blob_at()`]. [Cleaning up](crate::usage::cleanup()) old
+//! history drops the checkpoints that no remaining delta needs, which rewrites the chain of those that remain.
+//!
+//! Changes made by a human also record the content of the changed files with the [`BlobStore`], so files can be
+//! reconstructed as of each of these changes. Contents that were recorded before, in any session, cost nothing
+//! extra. They are kept from being garbage-collected by the checkpoint that follows them.
+//!
+//! If the repository normalizes the line endings of a file, its content is recorded [normalized](crate::eol) too
+//! so converted line endings don't make whole files look rewritten, along with the line endings to restore.
+//!
+//! The last content of files that are gone is kept as a [tombstone](crate::tombstones).
+//!
+//! If the project has a [bulk change threshold](gitbutler_project::Project::bulk_change_threshold), a burst of
+//! changes to many files, like renaming a symbol across the codebase, is collapsed into a single [bulk](Delta::bulk)
+//! delta with the last content of each file, which keeps the deltas file small and replaying fast.
+//!
+//! How much history of each file is kept can be [limited](crate::retention), which is enforced when deltas are
+//! compacted from time to time.
+//!
+//! Once the deltas file is larger than [`SEAL_BYTES`], its deltas are sealed into a [segment](SEGMENTS_FILE)
+//! compressed with zstd, in which each run of deltas that only differ in when they were noticed and what content
+//! they recorded, like those of typing into a file, is written once with the delta-of-deltas of their times. Sealed
+//! deltas are read along with those of the deltas file as if they were still in it.
+//!
+//! Parsed deltas are cached for as long as the deltas file is only appended to, so reconstructing files
+//! repeatedly, like when scrubbing through a timeline, only parses what was recorded since.
+//!
+//! Each delta is written with the [version of its format](DELTA_FORMAT_VERSION). Deltas of older versions are
+//! [upgraded](UPGRADES) when read, and those of newer versions are read as far as they are understood, ignoring
+//! fields that were added since. Deltas are never rewritten from what was parsed, so these fields are kept for
+//! the versions that know them, even when old deltas are dropped.
+//!
+//! Each delta is appended in a single write and ends with a [checksum](CHECKSUM_FIELD), so deltas that were damaged
+//! or only partly written, like when the app was killed, are told apart from intact ones. Dropping deltas rewrites
+//! the file through the [journal](gitbutler_storage::journal), so it's either rewritten completely or not at all.
+//! Merging a burst only replaces the deltas at the end of the file, which are kept in memory, after writing their
+//! replacement to a [journal of its own](TAIL_JOURNAL_FILE), and so does sealing deltas with [another
+//! one](SEAL_JOURNAL_FILE). Segments end with a checksum of their deltas. When a project is opened, [`recover()`]
+//! finishes interrupted rewrites and moves damaged deltas and segments into the quarantine, which is reported
+//! rather than skipping them silently.
+//!
+//! If the history of the project is [encrypted](crate::encryption), each line of the deltas file is encrypted on
+//! its own so it can still be appended to, and each segment is compressed before its frame is encrypted.
+//!
+//! The deltas file only holds paths, ids and metadata. Contents are whole files stored as blobs of the object
+//! database, which compresses them and keeps each of them once, instead of edit operations with offsets that
+//! could split multi-byte characters. Secrets are redacted from them like from snapshots, and like these, they
+//! aren't encrypted as the same files are in the worktree and object database anyway.
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_command_context::repository_pool;
+use gitbutler_diff::FileMode;
+use gitbutler_project::{
+    machine_changes::{BulkChangeThreshold, ChangeOrigin, Classification},
+    HistoryRetention, Project, ProjectId, AUTO_TRACK_LIMIT_BYTES,
+};
+use gitbutler_repo::{ContentType, RepositoryExt, SignaturePurpose, WorktreeFile};
+use gitbutler_storage::{
+    encryption::{self, Cipher},
+    journal::{CorruptRecord, RecoveryReport, QUARANTINE_DIR},
+    Storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blob_store::BlobStore,
+    encryption::ensure_unlocked,
+    entry::FileVersion,
+    eol::{self, LineEnding},
+    retention,
+    secrets::{self, SecretScanner},
+    tombstones, OplogExt,
+};
+
+/// The file in the GitButler directory of a project that deltas are appended to, one JSON object per line.
+pub(crate) const DELTAS_FILE: &str = "deltas.jsonl";
+/// The file next to the [deltas file](DELTAS_FILE) holding the offset from which its end is replaced by a merged
+/// delta, followed by that delta, for as long as it's being replaced.
+const TAIL_JOURNAL_FILE: &str = "deltas.tail";
+/// How many bytes of the deltas file are read at once when reading it backwards from its end.
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// The file next to the [deltas file](DELTAS_FILE) that its deltas are [sealed](seal()) into, as segments that are
+/// each the length of a zstd frame as 4 little-endian bytes followed by the frame. A frame holds deltas as lines of
+/// the deltas file, except for [runs](RUN_PREFIX) of them, along with the checksum of its content.
+const SEGMENTS_FILE: &str = "deltas.segments";
+/// The file next to the [deltas file](DELTAS_FILE) holding the length of the [segments file](SEGMENTS_FILE) before
+/// a segment is appended to it, and the deltas to leave in the deltas file after, for as long as deltas are sealed.
+const SEAL_JOURNAL_FILE: &str = "deltas.seal";
+/// Once the deltas file is larger than this, its deltas are sealed into a segment.
+const SEAL_BYTES: u64 = 256 * 1024;
+/// The zstd level that segments are compressed with, which favours their size as they are written rarely.
+const SEGMENT_COMPRESSION_LEVEL: i32 = 9;
+/// How a line of a segment starts that stands for adjacent deltas which only differ in when they were noticed and
+/// what content they recorded, like those of typing into a file. It's a [`Run`] with the fields they have in common.
+const RUN_PREFIX: &str = "{\"run\":";
+
+/// The last field of each delta as written, with the CRC32 checksum of the JSON of the delta without it, as
+/// 8 hexadecimal digits. Readers that don't know it ignore it like any field that was added in a newer version.
+///
+/// Deltas written before they were checksummed don't have it, and are only checked by parsing them.
+const CHECKSUM_FIELD: &str = "crc";
+
+/// The version of the format that deltas are written in, stored as `version` field of each one.
+///
+/// Deltas written before the format was versioned have no `version` field, and are of version 0.
+pub const DELTA_FORMAT_VERSION: u64 = 7;
+
+/// The steps to upgrade a delta to the current [format](DELTA_FORMAT_VERSION), where the step at index `n` turns
+/// a delta of version `n` into one of version `n + 1`. The `at` field must keep its meaning across versions, as
+/// old deltas are dropped by it without parsing them.
+const UPGRADES: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[
+    // Version 1 only added the `version` field.
+    |_delta| {},
+    // Version 2 added the optional `branch` field, which is unknown for older deltas.
+    |_delta| {},
+    // Version 3 added the `bulk` field, and older deltas were never bulk changes.
+    |_delta| {},
+    // Version 4 added the optional `eol` field of contents, and older contents were recorded as they were.
+    |_delta| {},
+    // Version 5 added the optional `contentType` field of contents, which is unknown for older contents.
+    |_delta| {},
+    // Version 6 added the optional `mode` and `linkTarget` fields of contents, and recorded links, which older
+    // versions skipped.
+    |_delta| {},
+    // Version 7 added the `renames` field, and older deltas recorded renames as unrelated changes.
+    |_delta| {},
+];
+
+/// Once the deltas take more than this to store, along with their [segments](SEGMENTS_FILE), deltas older than
+/// [`RETENTION_SECONDS`] are dropped unless they are pinned.
+pub(crate) const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+pub(crate) const RETENTION_SECONDS: i64 = 30 * 24 * 60 * 60;
+/// Deltas are compacted according to the [history retention](Project::history_retention) of a project at most
+/// this often.
+const COMPACTION_INTERVAL_SECONDS: i64 = 60 * 60;
+
+/// The reference pointing to the latest checkpoint commit.
+pub const CHECKPOINTS_REF: &str = "refs/gitbutler/checkpoints";
+
+/// The name of the reference pointing to the latest checkpoint commit of `project`, which is [`CHECKPOINTS_REF`]
+/// unless it's a [sub-project](Project::namespaced_ref).
+pub fn checkpoints_ref(project: &Project) -> String {
+    project.namespaced_ref(CHECKPOINTS_REF)
+}
+/// The reference keeping the checkpoints reachable while [pruning](prune()) rewrites them.
+pub const PRUNED_CHECKPOINTS_REF: &str = "refs/gitbutler/checkpoints-pruned";
+/// The start of the message of the commits keeping the contents recorded with deltas reachable until the
+/// checkpoint they are a parent of.
+const CONTENTS_MESSAGE_PREFIX: &str = "contents until";
+/// A checkpoint is written with the delta that follows this many deltas without one.
+pub const CHECKPOINT_INTERVAL_DELTAS: usize = 100;
+/// A checkpoint is written with the first delta this many seconds after the previous checkpoint.
+pub const CHECKPOINT_INTERVAL_SECONDS: i64 = 10 * 60;
+/// The content of files larger than this isn't recorded with deltas.
+const MAX_CONTENT_BYTES: u64 = 1024 * 1024;
+
+/// When the latest checkpoint of each project was written, and how many deltas were recorded since.
+static CHECKPOINTS: LazyLock<Mutex<HashMap<ProjectId, CheckpointState>>> =
+    LazyLock::new(Default::default);
+
+/// When the deltas of each project were compacted last, in seconds since the Unix epoch.
+static COMPACTED: LazyLock<Mutex<HashMap<ProjectId, i64>>> = LazyLock::new(Default::default);
+
+/// The deltas parsed from each deltas file, by its path.
+static PARSED: LazyLock<Mutex<HashMap<PathBuf, ParsedDeltas>>> = LazyLock::new(Default::default);
+
+/// The deltas at the end of each deltas file that a later delta may be merged with, by its path.
+static BURSTS: LazyLock<Mutex<HashMap<PathBuf, TailBurst>>> = LazyLock::new(Default::default);
+
+/// A lock per deltas file, by its path, held while writing to it so recovering it can tell deltas that are still
+/// being written from those that were interrupted. Each project has its own, so a project that is slow to
+/// record, like one with a large worktree to checkpoint, doesn't hold up the others.
+static WRITES: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// The most snapshots to look at for versions of a file when reconstructing it.
+pub(crate) const MAX_SNAPSHOTS: usize = 10_000;
+
+struct ParsedDeltas {
+    /// The amount of bytes that were parsed, up to the end of the last complete line.
+    len: u64,
+    /// When the file was modified as of parsing it.
+    modified: Option<SystemTime>,
+    /// The length of the [segments file](SEGMENTS_FILE) as of parsing it, which only grows by sealing deltas.
+    sealed_len: u64,
+    deltas: Arc<Vec<Delta>>,
+}
+
+/// Adjacent deltas of a segment written as one line, as they only differ in when they were noticed and what content
+/// they recorded.
+#[derive(Serialize, Deserialize)]
+struct Run {
+    /// The fields the deltas have in common, which is all of them but `at` and `contents`.
+    run: serde_json::Map<String, serde_json::Value>,
+    /// When the first delta was noticed, followed by the [delta-of-deltas](delta_of_deltas()) of the others, the
+    /// change of the gap to the delta before each of them, which is 0 while typing at a steady pace.
+    at: Vec<i64>,
+    /// The contents each delta recorded, which are empty if it recorded none.
+    contents: Vec<serde_json::Value>,
+}
+
+/// The deltas at the end of a deltas file that a following delta may be [merged](collapse_burst()) with.
+struct TailBurst {
+    /// The length of the file as of these deltas, which tells if it was written to by anything else since.
+    len: u64,
+    /// The deltas along with their offset in the file, oldest first.
+    deltas: Vec<(u64, Delta)>,
+}
+
+#[derive(Clone, Copy)]
+struct CheckpointState {
+    /// When the latest checkpoint was written, or `None` if there is none yet.
+    at: Option<i64>,
+    deltas_since: usize,
+}
+
+/// Files of the worktree that changed at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delta {
+    /// When the change was noticed, in seconds since the Unix epoch.
+    pub at: i64,
+    /// The worktree-relative paths of the changed files.
+    pub paths: Vec<PathBuf>,
+    #[serde(flatten)]
+    pub classification: Classification,
+    /// The short name of the branch `HEAD` pointed to when the change was made, like `main`, or `None` if it was
+    /// detached or the delta was recorded before branches were.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The commit under [`checkpoints_ref()`] with the content of the worktree after the change, if one was
+    /// written along with this delta.
+    #[serde(
+        default,
+        with = "gitbutler_serde::oid_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub checkpoint: Option<git2::Oid>,
+    /// The content of the changed files after the change. Only recorded for changes made by a human, and not for
+    /// files larger than a megabyte or that are neither regular files nor symbolic links.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<DeltaContent>,
+    /// If `true`, this delta stands for a burst of changes to more files than the
+    /// [threshold](gitbutler_project::Project::bulk_change_threshold) of the project allows within its window,
+    /// with the paths of all of them and the last content of each file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bulk: bool,
+    /// The files that were moved by this change, oldest first, whose old and new paths are among its `paths`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renames: Vec<DeltaRename>,
+}
+
+/// A file that was moved by a [`Delta`], so its history can be followed across the move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaRename {
+    /// The worktree-relative path the file had before.
+    pub from: PathBuf,
+    /// The worktree-relative path the file has after.
+    pub to: PathBuf,
+}
+
+/// The content of a file after a [`Delta`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaContent {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The blob with the content, or `None` if the file was deleted.
+    #[serde(default, with = "gitbutler_serde::oid_opt")]
+    pub blob_id: Option<git2::Oid>,
+    /// The line endings the file had in the worktree if its content was recorded with LF line endings, as the
+    /// repository normalizes them, or `None` if it was recorded as it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eol: Option<LineEnding>,
+    /// The type of the content as [detected](ContentType::detect()) when it was recorded, for choosing how to show
+    /// it, or `None` if the file was deleted or an older version recorded it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<ContentType>,
+    /// The mode the file had, or `None` if it was deleted or an older version recorded it, which only recorded
+    /// regular files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<FileMode>,
+    /// The path a [symbolic link](FileMode::Symlink) pointed to, which is also the content of its blob as Git stores
+    /// links.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+
+/// Append `delta` to the deltas of `project`, along with a checkpoint if one is due, and return it as recorded.
+pub fn record_delta(project: &Project, mut delta: Delta) -> Result<Delta> {
+    ensure_unlocked(project)?;
+    let path = project.gb_dir().join(DELTAS_FILE);
+    let writes = writes_to(&path);
+    let _writing = writes.lock().unwrap_or_else(|err| err.into_inner());
+    std::fs::create_dir_all(project.gb_dir())?;
+    let large = stored_bytes(&path)? > MAX_FILE_BYTES;
+    let retention = &project.history_retention;
+    if large || (retention.limits_depth() && path.exists() && compaction_due(project.id, delta.at))
+    {
+        compact(
+            &path,
+            retention,
+            delta.at,
+            large.then(|| delta.at - RETENTION_SECONDS),
+        )?;
+    }
+
+    let known = CHECKPOINTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&project.id)
+        .copied();
+    let state = match known {
+        Some(state) => state,
+        None => checkpoint_state(&path)?,
+    };
+    let due = state.deltas_since + 1 >= CHECKPOINT_INTERVAL_DELTAS
+        || state
+            .at
+            .is_none_or(|at| delta.at - at >= CHECKPOINT_INTERVAL_SECONDS);
+    if delta.classification.origin == ChangeOrigin::Human && delta.contents.is_empty() {
+        match store_contents(project, &delta.paths) {
+            Ok(contents) => delta.contents = contents,
+            Err(err) => tracing::warn!(?err, "failed to store the content of changed files"),
+        }
+    }
+    if let Err(err) = tombstones::record_tombstones(project, delta.at, &delta.paths) {
+        tracing::warn!(?err, "failed to record the content of deleted files");
+    }
+    if due && delta.checkpoint.is_none() {
+        let unanchored = read(&path)
+            .unwrap_or_default()
+            .iter()
+            .rev()
+            .take(state.deltas_since)
+            .cloned()
+            .chain(Some(delta.clone()))
+            .flat_map(|delta| delta.contents)
+            .filter_map(|content| content.blob_id)
+            .collect();
+        match write_checkpoint(project, delta.at, unanchored) {
+            Ok(checkpoint) => delta.checkpoint = Some(checkpoint),
+            Err(err) => tracing::warn!(?err, "failed to write checkpoint"),
+        }
+    }
+    let state = if delta.checkpoint.is_some() {
+        CheckpointState {
+            at: Some(delta.at),
+            deltas_since: 0,
+        }
+    } else {
+        CheckpointState {
+            deltas_since: state.deltas_since + 1,
+            ..state
+        }
+    };
+    CHECKPOINTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(project.id, state);
+
+    let burst = match project.bulk_change_threshold {
+        Some(threshold) if path.exists() => {
+            Some((threshold, tail_burst(&path, &delta, threshold)?))
+        }
+        _ => None,
+    };
+    let collapsed = burst
+        .as_ref()
+        .and_then(|(threshold, tail)| collapse_burst(tail, &mut delta, *threshold));
+    let tail = burst.map(|(_, tail)| tail).unwrap_or_default();
+
+    let record = store_record(
+        cipher_for(&path).as_deref(),
+        &to_record(&serde_json::to_string(&VersionedDelta {
+            version: DELTA_FORMAT_VERSION,
+            delta: &delta,
+        })?),
+    )?;
+    match collapsed {
+        // The merged deltas are replaced along with writing this one, so they can't be lost in between.
+        Some(offset) => replace_tail(&path, offset, tail.len(), &record)?,
+        None => append(&path, &record)?,
+    }
+    if project.bulk_change_threshold.is_some() {
+        let len = std::fs::metadata(&path)?.len();
+        let mut tail = if collapsed.is_some() || delta.bulk {
+            Vec::new()
+        } else {
+            tail
+        };
+        if delta.checkpoint.is_some() {
+            tail.clear();
+        } else {
+            tail.push((len - record.len() as u64, delta.clone()));
+        }
+        BURSTS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(path.clone(), TailBurst { len, deltas: tail });
+    }
+    if std::fs::metadata(&path)?.len() > SEAL_BYTES {
+        if let Err(err) = seal(&path) {
+            tracing::warn!(?err, "failed to seal deltas");
+        }
+    }
+    Ok(delta)
+}
+
+/// Return the lock to hold while writing to the deltas file at `path`.
+fn writes_to(path: &Path) -> Arc<Mutex<()>> {
+    WRITES
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .entry(path.to_owned())
+        .or_default()
+        .clone()
+}
+
+/// Append `record` to the deltas file at `path` in a single write, after ending what an interrupted write left of
+/// the last delta, so that one doesn't run into this one.
+fn append(path: &Path, record: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    let mut last = [b'\n'];
+    if file.metadata()?.len() > 0 {
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+    }
+    let mut bytes = Vec::with_capacity(record.len() + 1);
+    if last != [b'\n'] {
+        bytes.push(b'\n');
+    }
+    bytes.extend_from_slice(record.as_bytes());
+    file.write_all(&bytes)
+        .with_context(|| format!("failed to write '{}'", path.display()))?;
+    Ok(())
+}
+
+/// Replace the deltas file at `path` with `content` through the [journal](gitbutler_storage::journal) of the
+/// directory it's in, so it's never left partly written, and a replacement that was interrupted is finished by
+/// [`recover()`].
+fn rewrite(path: &Path, content: &str) -> Result<()> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        anyhow::bail!("'{}' isn't a file in a directory", path.display());
+    };
+    Storage::new(dir)
+        .write(file_name, content)
+        .with_context(|| format!("failed to write '{}'", path.display()))?;
+    forget_parsed(path);
+    Ok(())
+}
+
+/// Replace the [segments](SEGMENTS_FILE) of the deltas file at `path` with `segments` like [`rewrite()`] does.
+fn rewrite_segments(path: &Path, segments: &[u8]) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        anyhow::bail!("'{}' isn't a file in a directory", path.display());
+    };
+    Storage::new(dir)
+        .write(SEGMENTS_FILE, segments)
+        .with_context(|| format!("failed to write '{}'", dir.join(SEGMENTS_FILE).display()))?;
+    forget_parsed(path);
+    Ok(())
+}
+
+/// Return how many bytes the deltas file at `path` takes along with its [segments](SEGMENTS_FILE).
+pub(crate) fn stored_bytes(path: &Path) -> Result<u64> {
+    Ok(file_len(path)? + file_len(&path.with_file_name(SEGMENTS_FILE))?)
+}
+
+/// Return the length of the file at `path`, or 0 if it doesn't exist.
+fn file_len(path: &Path) -> Result<u64> {
+    match std::fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err).with_context(|| format!("failed to read '{}'", path.display())),
+    }
+}
+
+/// Return the cipher that the deltas file at `path` is encrypted with, or `None` if it isn't encrypted or its
+/// history is locked.
+fn cipher_for(path: &Path) -> Option<Arc<Cipher>> {
+    encryption::cipher(path.parent()?)
+}
+
+/// Return `record`, a line of the deltas file, as it's stored, which is [encrypted](Cipher::encrypt_line()) with
+/// `cipher` if set, and ends with a newline.
+fn store_record(cipher: Option<&Cipher>, record: &str) -> Result<String> {
+    let record = record.strip_suffix('\n').unwrap_or(record);
+    Ok(match cipher {
+        Some(cipher) => format!("{}\n", cipher.encrypt_line(record)?),
+        None => format!("{record}\n"),
+    })
+}
+
+/// Return the stored `line` of the deltas file as it was written, decrypting it with `cipher` if it's
+/// [encrypted](store_record()), or why that isn't possible.
+fn open_record<'a>(cipher: Option<&Cipher>, line: &'a str) -> Result<Cow<'a, str>, String> {
+    if !encryption::is_encrypted_line(line) {
+        return Ok(Cow::Borrowed(line));
+    }
+    let cipher = cipher.ok_or("the delta is encrypted, but the history is locked")?;
+    cipher
+        .decrypt_line(line)
+        .map(Cow::Owned)
+        .map_err(|err| format!("the delta can't be decrypted: {err}"))
+}
+
+/// Return `json`, the JSON object of a delta, as line of the deltas file with its [checksum](CHECKSUM_FIELD).
+fn to_record(json: &str) -> String {
+    let checksum = crc32fast::hash(json.as_bytes());
+    let fields = json.strip_suffix('}').unwrap_or(json);
+    format!("{fields},\"{CHECKSUM_FIELD}\":\"{checksum:08x}\"}}\n")
+}
+
+/// Return the delta on `line` of the deltas file as JSON object without its [checksum](CHECKSUM_FIELD), or why it
+/// is corrupt.
+fn parse_record(line: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let checksum_prefix = format!(",\"{CHECKSUM_FIELD}\":\"");
+    if let Some((fields, checksum)) = line
+        .strip_suffix("\"}")
+        .and_then(|line| line.rsplit_once(&checksum_prefix))
+    {
+        let checksum =
+            u32::from_str_radix(checksum, 16).map_err(|_| "the checksum is damaged".to_owned())?;
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(fields.as_bytes());
+        hasher.update(b"}");
+        if hasher.finalize() != checksum {
+            return Err("the checksum doesn't match the delta".into());
+        }
+    }
+    let serde_json::Value::Object(mut delta) =
+        serde_json::from_str(line).map_err(|err| format!("the delta isn't valid JSON: {err}"))?
+    else {
+        return Err("the delta isn't a JSON object".into());
+    };
+    delta.remove(CHECKSUM_FIELD);
+    if !delta.get("at").is_some_and(serde_json::Value::is_i64) {
+        return Err("the delta has no time".into());
+    }
+    Ok(delta)
+}
+
+/// Finish a rewrite of the deltas of `project` that was interrupted, typically as the app was killed, and move the
+/// deltas and [segments](SEGMENTS_FILE) that are damaged or were only partly written into the
+/// [quarantine](QUARANTINE_DIR) of its GitButler directory so they can be inspected. The paths in the returned
+/// report are relative to that directory.
+///
+/// This should be called whenever a project is opened. While its history is [locked](crate::encryption::is_locked()),
+/// only the writes of the [journal](gitbutler_storage::journal) are finished, as the deltas can't be checked.
+pub fn recover(project: &Project) -> Result<RecoveryReport> {
+    let path = project.gb_dir().join(DELTAS_FILE);
+    let writes = writes_to(&path);
+    let _writing = writes.lock().unwrap_or_else(|err| err.into_inner());
+    let mut report = Storage::new(project.gb_dir()).recover()?;
+    if crate::encryption::is_locked(project) {
+        return Ok(report);
+    }
+    let cipher = cipher_for(&path);
+    let cipher = cipher.as_deref();
+    if path.exists() {
+        recover_tail(&path)?;
+        recover_seal(&path)?;
+    }
+    report.corrupt.extend(recover_segments(&path)?);
+    let content = match std::fs::read(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read '{}'", path.display()))
+        }
+    };
+    // Invalid UTF-8 is replaced, which fails the checksum of the delta it's in rather than all of them.
+    let content = String::from_utf8_lossy(&content);
+
+    let mut intact = String::new();
+    let mut quarantined = String::new();
+    let mut corrupt = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let result = match line.strip_suffix('\n') {
+            Some(record) if record.trim().is_empty() => Ok(()),
+            Some(record) => open_record(cipher, record)
+                .and_then(|record| parse_record(&record))
+                .map(|_| ()),
+            // Nothing is being written, so the app stopped while writing it.
+            None => Err("the delta is truncated".into()),
+        };
+        match result {
+            Ok(()) => intact.push_str(line),
+            Err(reason) => {
+                tracing::warn!(path = %path.display(), offset, reason, "quarantining corrupt delta");
+                quarantined.push_str(line.trim_end_matches('\n'));
+                quarantined.push('\n');
+                corrupt.push((offset, reason));
+            }
+        }
+        offset += line.len();
+    }
+    if corrupt.is_empty() {
+        return Ok(report);
+    }
+
+    let quarantined_to = quarantine(&path, DELTAS_FILE, quarantined)?;
+    rewrite(&path, &intact)?;
+    report
+        .corrupt
+        .extend(corrupt.into_iter().map(|(offset, reason)| CorruptRecord {
+            path: DELTAS_FILE.into(),
+            offset,
+            reason,
+            quarantined_to: quarantined_to.clone(),
+        }));
+    Ok(report)
+}
+
+/// Move the segments of the deltas file at `path` that are damaged or were cut off into the
+/// [quarantine](QUARANTINE_DIR), and return where to.
+fn recover_segments(path: &Path) -> Result<Vec<CorruptRecord>> {
+    let segments_path = path.with_file_name(SEGMENTS_FILE);
+    let segments = match std::fs::read(&segments_path) {
+        Ok(segments) => segments,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read '{}'", segments_path.display()))
+        }
+    };
+    let cipher = cipher_for(path);
+    let (frames, end) = frames(&segments);
+    let mut intact = Vec::new();
+    let mut quarantined = Vec::new();
+    let mut corrupt = Vec::new();
+    for (offset, frame) in frames {
+        let segment = &segments[offset..offset + 4 + frame.len()];
+        match decode_frame(cipher.as_deref(), frame) {
+            Ok(_) => intact.extend_from_slice(segment),
+            Err(reason) => {
+                tracing::warn!(path = %segments_path.display(), offset, reason, "quarantining damaged segment");
+                quarantined.extend_from_slice(segment);
+                corrupt.push((offset, reason));
+            }
+        }
+    }
+    if end < segments.len() {
+        tracing::warn!(path = %segments_path.display(), offset = end, "quarantining truncated segment");
+        quarantined.extend_from_slice(&segments[end..]);
+        corrupt.push((end, "the segment is truncated".into()));
+    }
+    if corrupt.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let quarantined_to = quarantine(path, SEGMENTS_FILE, quarantined)?;
+    rewrite_segments(path, &intact)?;
+    Ok(corrupt
+        .into_iter()
+        .map(|(offset, reason)| CorruptRecord {
+            path: SEGMENTS_FILE.into(),
+            offset,
+            reason,
+            quarantined_to: quarantined_to.clone(),
+        })
+        .collect())
+}
+
+/// Rewrite the deltas of `project` and their [segments](SEGMENTS_FILE) so they are encrypted with `target`, or are
+/// plain if it's `None`, reading them with `source`. Deltas and segments that are already as they should be, and
+/// those that can't be read, are kept as they are.
+///
+/// Recording deltas waits until they are rewritten, so nothing is recorded with the cipher being replaced.
+pub(crate) fn reseal(
+    project: &Project,
+    source: Option<&Cipher>,
+    target: Option<&Cipher>,
+) -> Result<()> {
+    let path = project.gb_dir().join(DELTAS_FILE);
+    let writes = writes_to(&path);
+    let _writing = writes.lock().unwrap_or_else(|err| err.into_inner());
+    if path.exists() {
+        let content = read_lossy(&path)?;
+        let mut resealed = String::with_capacity(content.len());
+        let mut changed = false;
+        for line in content.split_inclusive('\n') {
+            let record = line
+                .strip_suffix('\n')
+                .filter(|record| encryption::is_encrypted_line(record) != target.is_some())
+                .and_then(|record| open_record(source, record).ok());
+            match record {
+                Some(record) => {
+                    resealed.push_str(&store_record(target, &record)?);
+                    changed = true;
+                }
+                None => resealed.push_str(line),
+            }
+        }
+        if changed {
+            rewrite(&path, &resealed)?;
+        }
+    }
+
+    let segments_path = path.with_file_name(SEGMENTS_FILE);
+    let segments = match std::fs::read(&segments_path) {
+        Ok(segments) => segments,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read '{}'", segments_path.display()))
+        }
+    };
+    let (frames, end) = frames(&segments);
+    let mut resealed = Vec::with_capacity(segments.len());
+    let mut changed = false;
+    for (offset, frame) in frames {
+        let lines = Some(frame)
+            .filter(|frame| encryption::is_encrypted(frame) != target.is_some())
+            .and_then(|frame| decode_frame(source, frame).ok());
+        match lines {
+            Some(lines) => {
+                resealed.extend(encode_segment(&lines, target)?);
+                changed = true;
+            }
+            None => resealed.extend_from_slice(&segments[offset..offset + 4 + frame.len()]),
+        }
+    }
+    // What was cut off is quarantined by `recover()`.
+    resealed.extend_from_slice(&segments[end..]);
+    if changed {
+        rewrite_segments(&path, &resealed)?;
+    }
+    Ok(())
+}
+
+/// Forget what was parsed of the deltas of `project`, like after its history was unlocked, so deltas that couldn't be
+/// decrypted before are read again.
+pub(crate) fn forget(project: &Project) {
+    forget_parsed(&project.gb_dir().join(DELTAS_FILE));
+}
+
+/// What was dropped by [`prune()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Pruned {
+    pub deltas: usize,
+    pub checkpoints: usize,
+}
+
+/// Drop the deltas of `project` noticed before `before`, in seconds since the Unix epoch, unless they are pinned,
+/// along with compacting the others according to its history retention as of `now`. Then drop the checkpoints that
+/// none of the remaining deltas needs, which are those before the checkpoint that follows the oldest of them, and
+/// aren't referred to by any of them. The latest checkpoint is always kept.
+///
+/// Dropping checkpoints rewrites the ones that remain, and the deltas that refer to them. Until these deltas are
+/// rewritten, the previous checkpoints are kept reachable by [`PRUNED_CHECKPOINTS_REF`], so deltas never refer to
+/// checkpoints that may be garbage-collected, even if pruning is interrupted.
+pub(crate) fn prune(project: &Project, now: i64, before: i64) -> Result<Pruned> {
+    ensure_unlocked(project)?;
+    let path = project.gb_dir().join(DELTAS_FILE);
+    let writes = writes_to(&path);
+    let _writing = writes.lock().unwrap_or_else(|err| err.into_inner());
+    let mut pruned = Pruned::default();
+    let deltas = if path.exists() {
+        let recorded = read(&path)?.len();
+        compact(&path, &project.history_retention, now, Some(before))?;
+        let deltas = read(&path)?;
+        pruned.deltas = recorded.saturating_sub(deltas.len());
+        deltas
+    } else {
+        Arc::default()
+    };
+
+    let repo = repository_pool::open(project)?;
+    let checkpoints = checkpoint_commits(&repo, project)?;
+    let oldest_at = deltas.first().map_or(i64::MAX, |delta| delta.at);
+    let referred: BTreeSet<_> = deltas.iter().filter_map(|delta| delta.checkpoint).collect();
+    if referred
+        .iter()
+        .any(|id| !checkpoints.iter().any(|checkpoint| checkpoint.id() == *id))
+    {
+        // Like after an interrupted pruning, whose checkpoints are kept until their deltas are rewritten.
+        tracing::warn!("not pruning checkpoints as deltas refer to checkpoints of another chain");
+        return Ok(pruned);
+    }
+    let oldest_kept = checkpoints
+        .iter()
+        .rposition(|checkpoint| {
+            referred.contains(&checkpoint.id())
+                || checkpoint_at(checkpoint).is_none_or(|at| at >= oldest_at)
+        })
+        .unwrap_or(0);
+    let kept = &checkpoints[..checkpoints.len().min(oldest_kept + 1)];
+    if kept.len() == checkpoints.len() {
+        return Ok(pruned);
+    }
+
+    // Recreate the kept checkpoints oldest first, along with the contents each of them keeps reachable, with the
+    // oldest one becoming the root.
+    let mut rewritten = HashMap::new();
+    let mut head: Option<git2::Oid> = None;
+    for checkpoint in kept.iter().rev() {
+        let previous = head.map(|id| repo.find_commit(id)).transpose()?;
+        let contents: Vec<_> = checkpoint.parents().filter(is_contents).collect();
+        let parents: Vec<_> = previous.iter().chain(&contents).collect();
+        let id = repo.commit(
+            None,
+            &checkpoint.author(),
+            &checkpoint.committer(),
+            &String::from_utf8_lossy(checkpoint.message_bytes()),
+            &checkpoint.tree()?,
+            &parents,
+        )?;
+        rewritten.insert(checkpoint.id(), id);
+        head = Some(id);
+    }
+    let pruned_ref = project.namespaced_ref(PRUNED_CHECKPOINTS_REF);
+    repo.reference(
+        &pruned_ref,
+        checkpoints[0].id(),
+        true,
+        "keep checkpoints while pruning them",
+    )?;
+    repo.reference(
+        &checkpoints_ref(project),
+        head.expect("the latest checkpoint is kept"),
+        true,
+        "prune checkpoints",
+    )?;
+    if path.exists() {
+        remap_checkpoints(&path, &rewritten)?;
+    }
+    repo.find_reference(&pruned_ref)?.delete()?;
+    pruned.checkpoints = checkpoints.len() - kept.len();
+    Ok(pruned)
+}
+
+/// Rewrite the deltas at `path` and their [segments](SEGMENTS_FILE) that refer to a checkpoint in `rewritten` so
+/// they refer to the commit it was rewritten as instead.
+fn remap_checkpoints(path: &Path, rewritten: &HashMap<git2::Oid, git2::Oid>) -> Result<()> {
+    let cipher = cipher_for(path);
+    let cipher = cipher.as_deref();
+    let remap = |content: &str, encrypt: bool| -> Result<Option<String>> {
+        let mut remapped = String::with_capacity(content.len());
+        let mut changed = false;
+        for line in content.lines() {
+            let delta = open_record(cipher, line).and_then(|record| parse_record(&record));
+            let checkpoint = delta.as_ref().ok().and_then(|delta| {
+                let id = delta.get("checkpoint")?.as_str()?.parse().ok()?;
+                rewritten.get(&id)
+            });
+            match (delta, checkpoint) {
+                (Ok(mut delta), Some(checkpoint)) => {
+                    delta.insert("checkpoint".into(), checkpoint.to_string().into());
+                    let record = to_record(&serde_json::to_string(&delta)?);
+                    if encrypt {
+                        remapped.push_str(&store_record(cipher, &record)?);
+                    } else {
+                        remapped.push_str(&record);
+                    }
+                    changed = true;
+                }
+                _ => {
+                    remapped.push_str(line);
+                    remapped.push('\n');
+                }
+            }
+        }
+        Ok(changed.then_some(remapped))
+    };
+    // Segments are encrypted as a whole.
+    if let Some(sealed) = remap(&read_segments(path)?, false)? {
+        rewrite_segments(path, &encode_segments(&sealed, cipher)?)?;
+    }
+    if let Some(content) = remap(&read_lossy(path)?, true)? {
+        rewrite(path, &content)?;
+    }
+    Ok(())
+}
+
+/// Write `content` of the file named `file_name` next to the deltas file at `path` into the
+/// [quarantine](QUARANTINE_DIR) of their directory, and return where to relative to that directory.
+fn quarantine(path: &Path, file_name: &str, content: impl AsRef<[u8]>) -> Result<PathBuf> {
+    let Some(dir) = path.parent() else {
+        anyhow::bail!("'{}' isn't a file in a directory", path.display());
+    };
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let quarantined_to = Path::new(QUARANTINE_DIR).join(format!("{file_name}.{seconds}"));
+    gitbutler_fs::create_dirs_then_write(dir.join(&quarantined_to), content)?;
+    Ok(quarantined_to)
+}
+
+/// A delta as written to the deltas file.
+#[derive(Serialize)]
+struct VersionedDelta<'a> {
+    version: u64,
+    #[serde(flatten)]
+    delta: &'a Delta,
+}
+
+/// Return `true` if the deltas of the project with `project_id` weren't compacted within
+/// [`COMPACTION_INTERVAL_SECONDS`] before `at`, and remember that they are compacted at `at`.
+fn compaction_due(project_id: ProjectId, at: i64) -> bool {
+    let mut compacted = COMPACTED.lock().unwrap_or_else(|err| err.into_inner());
+    if compacted
+        .get(&project_id)
+        .is_some_and(|compacted| at - compacted < COMPACTION_INTERVAL_SECONDS)
+    {
+        return false;
+    }
+    compacted.insert(project_id, at);
+    true
+}
+
+/// Rewrite the deltas file at `path` with only the history that `retention` keeps as of `now`, in seconds since
+/// the Unix epoch, and only what is pinned of the deltas noticed before `oldest` if set.
+///
+/// Deltas are kept as they are unless files are dropped from them, so nothing is lost of deltas of newer versions.
+/// These are only ever dropped as a whole, when they are older than `oldest` and nothing of them is pinned.
+///
+/// Deltas that are [corrupt](parse_record()) are kept as they are, to be quarantined by [`recover()`]. Sealed
+/// deltas stay sealed, and their segments are only rewritten if any of them are dropped. As no delta moves between
+/// the files, it doesn't matter which of them is rewritten first.
+fn compact(path: &Path, retention: &HistoryRetention, now: i64, oldest: Option<i64>) -> Result<()> {
+    let cipher = cipher_for(path);
+    let cipher = cipher.as_deref();
+    let sealed_content = read_segments(path)?;
+    let sealed_lines = sealed_content.lines().count();
+    let content = read_lossy(path)?;
+    let mut lines = Vec::new();
+    let mut corrupt = String::new();
+    let mut sealed_changed = false;
+    for (index, line) in sealed_content.lines().chain(content.lines()).enumerate() {
+        let sealed = index < sealed_lines;
+        let delta = match open_record(cipher, line).and_then(|record| parse_record(&record)) {
+            Ok(delta) => delta,
+            Err(_) if line.trim().is_empty() => continue,
+            Err(reason) => {
+                tracing::warn!(path = %path.display(), reason, "keeping corrupt delta");
+                // Only the deltas file is quarantined, so it's where corrupt deltas are kept.
+                if sealed {
+                    corrupt.push_str(&store_record(cipher, line)?);
+                } else {
+                    corrupt.push_str(line);
+                    corrupt.push('\n');
+                }
+                sealed_changed |= sealed;
+                continue;
+            }
+        };
+        let Some(at) = delta.get("at").and_then(|at| at.as_i64()) else {
+            continue;
+        };
+        let paths: Vec<PathBuf> = delta
+            .get("paths")
+            .and_then(|paths| paths.as_array())
+            .map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(|path| Some(PathBuf::from(path.as_str()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        lines.push((line, delta, at, paths, sealed));
+    }
+    let recorded: Vec<_> = lines
+        .iter()
+        .map(|(_, _, at, paths, _)| (*at, paths.as_slice()))
+        .collect();
+    let kept = retention::retained(retention, now, oldest, &recorded);
+
+    let mut retained = corrupt;
+    let mut retained_sealed = String::new();
+    for ((line, mut delta, at, paths, sealed), kept) in lines.iter().cloned().zip(kept) {
+        let retained = if sealed {
+            &mut retained_sealed
+        } else {
+            &mut retained
+        };
+        let expired = oldest.is_some_and(|oldest| at < oldest);
+        let newer = delta
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .is_some_and(|version| version > DELTA_FORMAT_VERSION);
+        let keep_line = if newer {
+            !expired || paths.iter().any(|path| retention.is_pinned(path, at))
+        } else if kept.iter().all(|kept| *kept) {
+            true
+        } else if kept.iter().any(|kept| *kept) || (!expired && delta.contains_key("checkpoint")) {
+            let dropped: BTreeSet<&Path> = paths
+                .iter()
+                .zip(&kept)
+                .filter(|(_, kept)| !**kept)
+                .map(|(path, _)| path.as_path())
+                .collect();
+            let is_dropped = |path: &serde_json::Value| {
+                path.as_str()
+                    .is_some_and(|path| dropped.contains(Path::new(path)))
+            };
+            if let Some(serde_json::Value::Array(paths)) = delta.get_mut("paths") {
+                paths.retain(|path| !is_dropped(path));
+            }
+            if let Some(serde_json::Value::Array(contents)) = delta.get_mut("contents") {
+                contents.retain(|content| !content.get("path").is_some_and(is_dropped));
+                if contents.is_empty() {
+                    delta.remove("contents");
+                }
+            }
+            let record = to_record(&serde_json::to_string(&delta)?);
+            if sealed {
+                // Segments are encrypted as a whole.
+                retained.push_str(&record);
+            } else {
+                retained.push_str(&store_record(cipher, &record)?);
+            }
+            sealed_changed |= sealed;
+            continue;
+        } else {
+            false
+        };
+        if keep_line {
+            retained.push_str(line);
+            retained.push('\n');
+        } else {
+            sealed_changed |= sealed;
+        }
+    }
+    if sealed_changed {
+        rewrite_segments(path, &encode_segments(&retained_sealed, cipher)?)?;
+    }
+    rewrite(path, &retained)
+}
+
+/// Read the file at `path`, replacing invalid UTF-8 so only the deltas it's in fail to parse.
+fn read_lossy(path: &Path) -> Result<String> {
+    let content =
+        std::fs::read(path).with_context(|| format!("failed to read '{}'", path.display()))?;
+    Ok(String::from_utf8(content)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+}
+
+/// Return the deltas at the end of the deltas file at `path` that `delta` would be merged with if they changed more
+/// files than `threshold` allows, oldest first and along with their offset in the file. These are the deltas made
+/// by the same origin within the window of `threshold` before it, back to the first bulk change.
+///
+/// They are taken from memory if nothing else wrote to the file since the last delta was recorded, and are read
+/// backwards from the end of the file otherwise, so deltas that were [sealed](seal()) end a burst. Deltas with a
+/// checkpoint are never merged into a later one, as
+/// their checkpoint is the worktree as of them, and neither are those of newer versions, which would lose what
+/// isn't understood of them.
+fn tail_burst(
+    path: &Path,
+    delta: &Delta,
+    threshold: BulkChangeThreshold,
+) -> Result<Vec<(u64, Delta)>> {
+    let window = i64::try_from(threshold.window_seconds).unwrap_or(i64::MAX);
+    let len = std::fs::metadata(path)?.len();
+    let mut burst = Vec::new();
+    let known = BURSTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(path)
+        .filter(|tail| tail.len == len)
+        .map(|tail| tail.deltas.clone());
+    match known {
+        Some(tail) => {
+            for (offset, previous) in tail.into_iter().rev() {
+                if !extends_burst(&previous, delta, window) {
+                    break;
+                }
+                let bulk = previous.bulk;
+                burst.push((offset, previous));
+                if bulk {
+                    break;
+                }
+            }
+        }
+        None => read_backwards(path, len, |offset, line| {
+            let Ok(line) = open_record(cipher_for(path).as_deref(), line) else {
+                return false;
+            };
+            let newer = parse_record(&line)
+                .ok()
+                .and_then(|previous| previous.get("version")?.as_u64())
+                .is_some_and(|version| version > DELTA_FORMAT_VERSION);
+            let Some(previous) = parse_delta(&line)
+                .ok()
+                .filter(|previous| !newer && extends_burst(previous, delta, window))
+            else {
+                return false;
+            };
+            let bulk = previous.bulk;
+            burst.push((offset, previous));
+            !bulk
+        })?,
+    }
+    burst.reverse();
+    Ok(burst)
+}
+
+/// Return `true` if `delta` may be merged with `previous`, a delta before it.
+fn extends_burst(previous: &Delta, delta: &Delta, window: i64) -> bool {
+    previous.checkpoint.is_none()
+        && previous.classification.origin == delta.classification.origin
+        && delta.at - previous.at <= window
+}
+
+/// Pass the lines of the deltas file at `path`, which is `len` bytes long, to `line` along with their offset, last
+/// line first, for as long as it returns `true`, reading only as much of the file as that takes.
+///
+/// Nothing is passed if the file doesn't end with a newline, as the last delta was interrupted while being written.
+fn read_backwards(path: &Path, len: u64, mut line: impl FnMut(u64, &str) -> bool) -> Result<()> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    // The bytes of the file from `start` up to the end of the lines that weren't passed yet.
+    let mut buffer = Vec::new();
+    let mut start = len;
+    loop {
+        let newline = buffer[..buffer.len().saturating_sub(1)]
+            .iter()
+            .rposition(|byte| *byte == b'\n');
+        let line_start = match newline {
+            Some(index) => index + 1,
+            None if start == 0 => 0,
+            None => {
+                let chunk_start = start.saturating_sub(TAIL_CHUNK_BYTES);
+                let mut chunk = vec![0; (start - chunk_start) as usize];
+                file.seek(SeekFrom::Start(chunk_start))?;
+                file.read_exact(&mut chunk)
+                    .with_context(|| format!("failed to read '{}'", path.display()))?;
+                if start == len && chunk.last() != Some(&b'\n') {
+                    return Ok(());
+                }
+                chunk.append(&mut buffer);
+                buffer = chunk;
+                start = chunk_start;
+                continue;
+            }
+        };
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&buffer[line_start..]);
+        let text = text.trim_end_matches('\n');
+        if !text.trim().is_empty() && !line(start + line_start as u64, text) {
+            return Ok(());
+        }
+        buffer.truncate(line_start);
+    }
+}
+
+/// Merge `delta` with the deltas of the `tail` burst it [follows](tail_burst()) if they changed more files than
+/// `threshold` allows, and return the offset of the first of them in the deltas file, from which `delta` is to
+/// replace them, if there are any. A bulk change at the end of the file keeps absorbing deltas that follow it within
+/// the window.
+fn collapse_burst(
+    tail: &[(u64, Delta)],
+    delta: &mut Delta,
+    threshold: BulkChangeThreshold,
+) -> Option<u64> {
+    let burst = tail.iter().map(|(_, previous)| previous);
+    let paths: BTreeSet<&PathBuf> = burst
+        .clone()
+        .chain(Some(&*delta))
+        .flat_map(|delta| &delta.paths)
+        .collect();
+    if !burst.clone().any(|previous| previous.bulk) && paths.len() <= threshold.files {
+        return None;
+    }
+
+    let paths = paths.into_iter().cloned().collect();
+    let mut contents = BTreeMap::new();
+    for content in burst
+        .clone()
+        .chain(Some(&*delta))
+        .flat_map(|delta| &delta.contents)
+    {
+        contents.insert(content.path.clone(), content.clone());
+    }
+    delta.renames = burst
+        .chain(Some(&*delta))
+        .flat_map(|delta| delta.renames.iter().cloned())
+        .collect();
+    delta.paths = paths;
+    delta.contents = contents.into_values().collect();
+    delta.bulk = true;
+    tail.first().map(|(offset, _)| *offset)
+}
+
+/// Replace the deltas file at `path` from `offset` on, where the `replaced` deltas at its end start, with `record`.
+///
+/// The replacement is written to the [tail journal](TAIL_JOURNAL_FILE) first, so an interrupted replacement is
+/// finished by [`recover()`] instead of losing the deltas it replaces.
+fn replace_tail(path: &Path, offset: u64, replaced: usize, record: &str) -> Result<()> {
+    let journal_path = path.with_file_name(TAIL_JOURNAL_FILE);
+    let mut journal = std::fs::File::create(&journal_path)
+        .with_context(|| format!("failed to write '{}'", journal_path.display()))?;
+    journal.write_all(format!("{offset}\n{record}").as_bytes())?;
+    journal.sync_all()?;
+
+    let len = std::fs::metadata(path)?.len();
+    write_tail(path, offset, record)?;
+    let mut parsed = PARSED.lock().unwrap_or_else(|err| err.into_inner());
+    match parsed.get_mut(path) {
+        // The replaced deltas were parsed from complete lines, so they are the last ones of the cache.
+        Some(cached) if cached.len == len => {
+            let deltas = Arc::make_mut(&mut cached.deltas);
+            deltas.truncate(deltas.len().saturating_sub(replaced));
+            cached.len = offset;
+        }
+        Some(cached) if cached.len <= offset => {}
+        _ => {
+            parsed.remove(path);
+        }
+    }
+    drop(parsed);
+    std::fs::remove_file(&journal_path)
+        .with_context(|| format!("failed to remove '{}'", journal_path.display()))?;
+    Ok(())
+}
+
+/// Truncate the deltas file at `path` to `offset` and append `record`.
+fn write_tail(path: &Path, offset: u64, record: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    file.set_len(offset)?;
+    file.seek(SeekFrom::End(0))?;
+    file.write_all(record.as_bytes())
+        .with_context(|| format!("failed to write '{}'", path.display()))?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Finish replacing the end of the deltas file at `path` if the [tail journal](TAIL_JOURNAL_FILE) next to it is
+/// still present, and remove the journal. A journal that wasn't written completely is removed without touching the
+/// deltas file, as it's only written to once the journal was.
+fn recover_tail(path: &Path) -> Result<()> {
+    let journal_path = path.with_file_name(TAIL_JOURNAL_FILE);
+    let journal = match std::fs::read_to_string(&journal_path) {
+        Ok(journal) => journal,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read '{}'", journal_path.display()))
+        }
+    };
+    let cipher = cipher_for(path);
+    let replacement = journal
+        .split_once('\n')
+        .and_then(|(offset, record)| Some((offset.parse::<u64>().ok()?, record)))
+        .filter(|(_, record)| {
+            record.strip_suffix('\n').is_some_and(|record| {
+                open_record(cipher.as_deref(), record)
+                    .and_then(|record| parse_record(&record))
+                    .is_ok()
+            })
+        });
+    match replacement {
+        Some((offset, record)) if std::fs::metadata(path)?.len() >= offset => {
+            write_tail(path, offset, record)?;
+            forget_parsed(path);
+        }
+        Some(_) => {
+            tracing::warn!(path = %path.display(), "skipped tail journal beyond the end of the file")
+        }
+        None => tracing::warn!(path = %path.display(), "removed incomplete tail journal"),
+    }
+    std::fs::remove_file(&journal_path)
+        .with_context(|| format!("failed to remove '{}'", journal_path.display()))?;
+    Ok(())
+}
+
+/// Move the intact deltas of the deltas file at `path` into a new compressed segment at the end of its
+/// [segments file](SEGMENTS_FILE), leaving only the corrupt ones to be quarantined by [`recover()`].
+///
+/// The length of the segments file and what is left of the deltas file are written to the
+/// [seal journal](SEAL_JOURNAL_FILE) first, so `recover()` finishes or undoes an interrupted seal instead of
+/// losing or duplicating deltas. Deltas aren't read while they are sealed, so they are never seen in both files.
+fn seal(path: &Path) -> Result<()> {
+    let mut parsed = PARSED.lock().unwrap_or_else(|err| err.into_inner());
+    let cipher = cipher_for(path);
+    let len = std::fs::metadata(path)?.len();
+    let content = read_lossy(path)?;
+    let mut sealed = String::new();
+    let mut kept = String::new();
+    for line in content.split_inclusive('\n') {
+        let record = line
+            .strip_suffix('\n')
+            .filter(|record| !record.trim().is_empty())
+            .map(|record| open_record(cipher.as_deref(), record));
+        match record {
+            None if line.ends_with('\n') => {}
+            // Segments are encrypted as a whole, so the deltas are sealed as they were written.
+            Some(Ok(record)) if parse_record(&record).is_ok() => {
+                sealed.push_str(&record);
+                sealed.push('\n');
+            }
+            _ => kept.push_str(line),
+        }
+    }
+    if sealed.is_empty() {
+        return Ok(());
+    }
+    let segment = encode_segment(&sealed, cipher.as_deref())?;
+
+    let segments_path = path.with_file_name(SEGMENTS_FILE);
+    let segments_len = file_len(&segments_path)?;
+    let journal_path = path.with_file_name(SEAL_JOURNAL_FILE);
+    let mut journal = std::fs::File::create(&journal_path)
+        .with_context(|| format!("failed to write '{}'", journal_path.display()))?;
+    journal.write_all(format!("{segments_len} {}\n{kept}", kept.len()).as_bytes())?;
+    journal.sync_all()?;
+
+    let mut segments = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&segments_path)
+        .with_context(|| format!("failed to open '{}'", segments_path.display()))?;
+    segments
+        .write_all(&segment)
+        .with_context(|| format!("failed to write '{}'", segments_path.display()))?;
+    segments.sync_data()?;
+    write_tail(path, 0, &kept)?;
+    match parsed.get_mut(path) {
+        // The sealed deltas were all parsed, and the corrupt ones that are left never will be.
+        Some(cached) if cached.len == len => {
+            cached.len = kept.len() as u64;
+            cached.modified = std::fs::metadata(path)?.modified().ok();
+            cached.sealed_len = segments_len + segment.len() as u64;
+        }
+        _ => {
+            parsed.remove(path);
+        }
+    }
+    drop(parsed);
+    BURSTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(path);
+    std::fs::remove_file(&journal_path)
+        .with_context(|| format!("failed to remove '{}'", journal_path.display()))?;
+    Ok(())
+}
+
+/// Finish sealing the deltas of the deltas file at `path` if the [seal journal](SEAL_JOURNAL_FILE) next to it is
+/// still present, and remove the journal.
+///
+/// If the segment was appended completely, only what the journal holds is left of the deltas file, and otherwise
+/// what was appended of the segment is removed. A journal that wasn't written completely is removed without touching
+/// either file, as they are only written to once the journal was.
+fn recover_seal(path: &Path) -> Result<()> {
+    let journal_path = path.with_file_name(SEAL_JOURNAL_FILE);
+    let journal = match std::fs::read_to_string(&journal_path) {
+        Ok(journal) => journal,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read '{}'", journal_path.display()))
+        }
+    };
+    let pending = journal
+        .split_once('\n')
+        .and_then(|(lengths, kept)| {
+            let (segments_len, kept_len) = lengths.split_once(' ')?;
+            Some((
+                segments_len.parse::<usize>().ok()?,
+                kept_len.parse::<usize>().ok()?,
+                kept,
+            ))
+        })
+        .filter(|(_, kept_len, kept)| kept.len() == *kept_len);
+    match pending {
+        Some((segments_len, _, kept)) => {
+            let segments_path = path.with_file_name(SEGMENTS_FILE);
+            let segments = match std::fs::read(&segments_path) {
+                Ok(segments) => segments,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("failed to read '{}'", segments_path.display()))
+                }
+            };
+            let appended = segments.get(segments_len..).unwrap_or_default();
+            let (frames, end) = frames(appended);
+            let cipher = cipher_for(path);
+            match frames.as_slice() {
+                [(_, frame)]
+                    if end == appended.len() && decode_frame(cipher.as_deref(), frame).is_ok() =>
+                {
+                    write_tail(path, 0, kept)?;
+                }
+                _ if appended.is_empty() => {}
+                _ => {
+                    tracing::warn!(path = %segments_path.display(), "removed incomplete segment");
+                    let file = std::fs::OpenOptions::new()
+                        .write(true)
+                        .open(&segments_path)
+                        .with_context(|| format!("failed to open '{}'", segments_path.display()))?;
+                    file.set_len(segments_len as u64)?;
+                    file.sync_data()?;
+                }
+            }
+            forget_parsed(path);
+        }
+        None => tracing::warn!(path = %path.display(), "removed incomplete seal journal"),
+    }
+    std::fs::remove_file(&journal_path)
+        .with_context(|| format!("failed to remove '{}'", journal_path.display()))?;
+    Ok(())
+}
+
+/// Return the deltas sealed into the [segments](SEGMENTS_FILE) of the deltas file at `path` as lines of the deltas
+/// file, skipping segments that are damaged with a warning, as they are [quarantined](recover()) when the project
+/// is opened the next time.
+fn read_segments(path: &Path) -> Result<String> {
+    let segments_path = path.with_file_name(SEGMENTS_FILE);
+    let segments = match std::fs::read(&segments_path) {
+        Ok(segments) => segments,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read '{}'", segments_path.display()))
+        }
+    };
+    let cipher = cipher_for(path);
+    let (frames, end) = frames(&segments);
+    let mut content = String::new();
+    for (offset, frame) in frames {
+        match decode_frame(cipher.as_deref(), frame) {
+            Ok(lines) => content.push_str(&lines),
+            Err(reason) => {
+                tracing::warn!(path = %segments_path.display(), offset, reason, "skipped damaged segment");
+            }
+        }
+    }
+    if end < segments.len() {
+        tracing::warn!(path = %segments_path.display(), offset = end, "skipped truncated segment");
+    }
+    Ok(content)
+}
+
+/// Return the zstd frames of the complete segments in `segments` along with the offset of their segment, and the
+/// offset after the last of them, which is the end of `segments` unless the last segment was cut off.
+fn frames(segments: &[u8]) -> (Vec<(usize, &[u8])>, usize) {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while let Some((len, rest)) = segments[offset..].split_first_chunk::<4>() {
+        let Some(frame) = rest.get(..u32::from_le_bytes(*len) as usize) else {
+            break;
+        };
+        frames.push((offset, frame));
+        offset += len.len() + frame.len();
+    }
+    (frames, offset)
+}
+
+/// Return the deltas in the zstd `frame` of a segment as lines of the deltas file, decrypting it with `cipher` first
+/// if it's [encrypted](encryption::is_encrypted()), or why that isn't possible.
+fn decode_frame(cipher: Option<&Cipher>, frame: &[u8]) -> Result<String, String> {
+    let decrypted;
+    let frame = if encryption::is_encrypted(frame) {
+        let cipher = cipher.ok_or("the segment is encrypted, but the history is locked")?;
+        decrypted = cipher
+            .decrypt(frame)
+            .map_err(|err| format!("the segment can't be decrypted: {err}"))?;
+        decrypted.as_slice()
+    } else {
+        frame
+    };
+    let lines = zstd::stream::decode_all(frame)
+        .map_err(|err| format!("the segment can't be decompressed: {err}"))?;
+    let lines = String::from_utf8(lines).map_err(|_| "the segment isn't valid UTF-8".to_owned())?;
+    expand_runs(&lines)
+}
+
+/// Return the deltas on `lines` of the deltas file as a segment, whose frame is encrypted with `cipher` if set.
+fn encode_segment(lines: &str, cipher: Option<&Cipher>) -> Result<Vec<u8>> {
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), SEGMENT_COMPRESSION_LEVEL)?;
+    encoder.include_checksum(true)?;
+    encoder.write_all(encode_runs(lines)?.as_bytes())?;
+    let mut frame = encoder.finish()?;
+    if let Some(cipher) = cipher {
+        // Encrypted data doesn't compress, so it's compressed first.
+        frame = cipher.encrypt(&frame)?;
+    }
+    let mut segment = u32::try_from(frame.len())?.to_le_bytes().to_vec();
+    segment.extend_from_slice(&frame);
+    Ok(segment)
+}
+
+/// Return the deltas on `lines` of the deltas file as segments of up to [`SEAL_BYTES`] of lines each, encrypted with
+/// `cipher` if set.
+fn encode_segments(lines: &str, cipher: Option<&Cipher>) -> Result<Vec<u8>> {
+    let mut segments = Vec::new();
+    let mut chunk = String::new();
+    for line in lines.split_inclusive('\n') {
+        if !chunk.is_empty() && (chunk.len() + line.len()) as u64 > SEAL_BYTES {
+            segments.extend(encode_segment(&chunk, cipher)?);
+            chunk.clear();
+        }
+        chunk.push_str(line);
+    }
+    if !chunk.is_empty() {
+        segments.extend(encode_segment(&chunk, cipher)?);
+    }
+    Ok(segments)
+}
+
+/// Return the deltas on `lines` of the deltas file with each run of adjacent deltas that only differ in when they
+/// were noticed and what content they recorded written as a single [`Run`]. Deltas that don't form a run with
+/// another one are written as they are.
+fn encode_runs(lines: &str) -> Result<String> {
+    let mut encoded = String::new();
+    // The lines of the current run, which are written as they are unless there is more than one.
+    let mut lines_of_run = Vec::new();
+    let mut run: Option<Run> = None;
+    for line in lines.lines().filter(|line| !line.trim().is_empty()) {
+        let delta = parse_record(line).ok().map(|mut delta| {
+            let at = delta.remove("at").and_then(|at| at.as_i64()).unwrap_or(0);
+            let contents = delta
+                .remove("contents")
+                .unwrap_or(serde_json::Value::Array(Vec::new()));
+            (delta, at, contents)
+        });
+        match (delta, &mut run) {
+            (Some((fields, at, contents)), Some(run)) if run.run == fields => {
+                run.at.push(at);
+                run.contents.push(contents);
+                lines_of_run.push(line);
+            }
+            (delta, run) => {
+                write_run(&mut encoded, run.take(), &mut lines_of_run)?;
+                match delta {
+                    Some((fields, at, contents)) => {
+                        *run = Some(Run {
+                            run: fields,
+                            at: vec![at],
+                            contents: vec![contents],
+                        });
+                        lines_of_run.push(line);
+                    }
+                    None => {
+                        encoded.push_str(line);
+                        encoded.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    write_run(&mut encoded, run, &mut lines_of_run)?;
+    Ok(encoded)
+}
+
+/// Append `run` to `encoded` if it has more than one delta, or the `lines` it was made of otherwise, and clear them.
+fn write_run(encoded: &mut String, run: Option<Run>, lines: &mut Vec<&str>) -> Result<()> {
+    match run {
+        Some(mut run) if lines.len() > 1 => {
+            run.at = delta_of_deltas(&run.at);
+            encoded.push_str(&serde_json::to_string(&run)?);
+            encoded.push('\n');
+        }
+        _ => {
+            for line in lines.iter() {
+                encoded.push_str(line);
+                encoded.push('\n');
+            }
+        }
+    }
+    lines.clear();
+    Ok(())
+}
+
+/// Return the deltas on `lines` of a segment with each [`Run`] written as the deltas it stands for, or why a run
+/// isn't understood.
+fn expand_runs(lines: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(lines.len());
+    for line in lines.lines() {
+        if !line.starts_with(RUN_PREFIX) {
+            expanded.push_str(line);
+            expanded.push('\n');
+            continue;
+        }
+        let run: Run = serde_json::from_str(line)
+            .map_err(|err| format!("the run of deltas isn't understood: {err}"))?;
+        if run.at.len() != run.contents.len() {
+            return Err("the run of deltas is incomplete".into());
+        }
+        for (at, contents) in undo_delta_of_deltas(&run.at).into_iter().zip(run.contents) {
+            let mut delta = run.run.clone();
+            delta.insert("at".into(), at.into());
+            if contents
+                .as_array()
+                .is_none_or(|contents| !contents.is_empty())
+            {
+                delta.insert("contents".into(), contents);
+            }
+            let json = serde_json::to_string(&delta).map_err(|err| err.to_string())?;
+            expanded.push_str(&to_record(&json));
+        }
+    }
+    Ok(expanded)
+}
+
+/// Return `times` as the first of them followed by the change of the gap between each of the others and the one
+/// before it, which is 0 for times at a steady pace and compresses well.
+fn delta_of_deltas(times: &[i64]) -> Vec<i64> {
+    let mut previous = None;
+    let mut gap = 0i64;
+    times
+        .iter()
+        .map(|at| {
+            let encoded = match previous {
+                Some(previous) => {
+                    let next_gap = at.wrapping_sub(previous);
+                    let change = next_gap.wrapping_sub(gap);
+                    gap = next_gap;
+                    change
+                }
+                None => *at,
+            };
+            previous = Some(*at);
+            encoded
+        })
+        .collect()
+}
+
+/// Return the times that were turned into `encoded` by [`delta_of_deltas()`].
+fn undo_delta_of_deltas(encoded: &[i64]) -> Vec<i64> {
+    let mut previous = None;
+    let mut gap = 0i64;
+    encoded
+        .iter()
+        .map(|value| {
+            let at = match previous {
+                Some(previous) => {
+                    gap = gap.wrapping_add(*value);
+                    previous.wrapping_add(gap)
+                }
+                None => *value,
+            };
+            previous = Some(at);
+            at
+        })
+        .collect()
+}
+
+/// Return the deltas of `project` noticed within `range`, in seconds since the Unix epoch, oldest first.
+/// If `origin` is set, only the deltas made by it are returned, and if `branch` is set, only those made on it.
+pub fn list_deltas(
+    project: &Project,
+    range: Range<i64>,
+    origin: Option<ChangeOrigin>,
+    branch: Option<&str>,
+) -> Result<Vec<Delta>> {
+    let path = project.gb_dir().join(DELTAS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    ensure_unlocked(project)?;
+    Ok(read(&path)?
+        .iter()
+        .filter(|delta| range.contains(&delta.at))
+        .filter(|delta| origin.is_none_or(|origin| delta.classification.origin == origin))
+        .filter(|delta| branch.is_none_or(|branch| delta.branch.as_deref() == Some(branch)))
+        .cloned()
+        .collect())
+}
+
+/// Return the paths the file at the worktree-relative `path` had before it was moved by any of `deltas`, most
+/// recent first, following each rename back to the one before it.
+pub fn former_paths(deltas: &[Delta], path: &Path) -> Vec<PathBuf> {
+    let mut current = path.to_owned();
+    let mut former = Vec::new();
+    for rename in deltas
+        .iter()
+        .rev()
+        .flat_map(|delta| delta.renames.iter().rev())
+    {
+        if rename.to == current && !former.contains(&rename.from) && rename.from != path {
+            current = rename.from.clone();
+            former.push(current.clone());
+        }
+    }
+    former
+}
+
+/// The content of a file at some point in time, as returned by [`blob_at()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobAt {
+    /// When the content was recorded, in seconds since the Unix epoch.
+    pub recorded_at: i64,
+    /// The blob with the content, or `None` if the file didn't exist.
+    pub blob_id: Option<git2::Oid>,
+    /// The line endings to [restore](eol::restore()) the content with.
+    pub eol: Option<LineEnding>,
+    /// The mode to check the content out with, or `None` if it's unknown, which is a regular file.
+    pub mode: Option<FileMode>,
+}
+
+/// Return the content of the file at the worktree-relative `file_path` of `project` as of `at` seconds since
+/// the Unix epoch, or `None` if nothing was recorded about it up to then.
+///
+/// The base is the latest snapshot or checkpoint recorded at or before `at`, with the contents recorded by later
+/// deltas applied. If a delta didn't record the content of the file, the content as of before that delta is
+/// returned, which [`BlobAt::recorded_at`] tells.
+pub fn blob_at(project: &Project, file_path: &Path, at: i64) -> Result<Option<BlobAt>> {
+    let deltas = list_deltas(project, i64::MIN..at.saturating_add(1), None, None)?;
+    let versions = project.file_versions(file_path, MAX_SNAPSHOTS)?;
+    replay(project, file_path, at, &deltas, &versions)
+}
+
+/// How long the steps of reconstructing a file took, in microseconds, as returned by [`profile_reconstruction()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconstructionProfile {
+    /// The amount of deltas of the project.
+    pub deltas: usize,
+    /// The size of the deltas file along with its compressed segments.
+    pub file_bytes: u64,
+    /// The size of the deltas as lines of JSON, as they were before they were sealed into segments.
+    pub json_bytes: u64,
+    /// Reading the deltas file and decompressing its segments.
+    pub io_micros: u64,
+    /// Parsing all deltas.
+    pub parse_micros: u64,
+    /// Getting the deltas through the cache, which only parses what was recorded since the last time.
+    pub cached_read_micros: u64,
+    /// Finding the versions of the file in snapshots.
+    pub snapshots_micros: u64,
+    /// Finding the latest checkpoint and applying the contents recorded by deltas after it.
+    pub replay_micros: u64,
+}
+
+/// Reconstruct the latest content of the file at the worktree-relative `file_path` of `project` like
+/// [`blob_at()`] does, and return how long each step took.
+///
+/// Reading and parsing bypass the cache, to tell what reconstructing costs without it.
+pub fn profile_reconstruction(
+    project: &Project,
+    file_path: &Path,
+) -> Result<ReconstructionProfile> {
+    ensure_unlocked(project)?;
+    let path = project.gb_dir().join(DELTAS_FILE);
+    let start = Instant::now();
+    let content = if path.exists() {
+        read_segments(&path)? + &read_lossy(&path)?
+    } else {
+        String::new()
+    };
+    let io = start.elapsed();
+
+    let start = Instant::now();
+    let deltas = parse(&path, &content);
+    let parsing = start.elapsed();
+
+    let start = Instant::now();
+    if path.exists() {
+        read(&path)?;
+    }
+    let cached_read = start.elapsed();
+
+    let start = Instant::now();
+    let versions = project.file_versions(file_path, MAX_SNAPSHOTS)?;
+    let snapshots = start.elapsed();
+
+    let start = Instant::now();
+    replay(project, file_path, i64::MAX, &deltas, &versions)?;
+    let replaying = start.elapsed();
+
+    let micros = |duration: Duration| u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+    Ok(ReconstructionProfile {
+        deltas: deltas.len(),
+        file_bytes: stored_bytes(&path)?,
+        json_bytes: content.len() as u64,
+        io_micros: micros(io),
+        parse_micros: micros(parsing),
+        cached_read_micros: micros(cached_read),
+        snapshots_micros: micros(snapshots),
+        replay_micros: micros(replaying),
+    })
+}
+
+/// Return the content of `file_path` as of `at` like [`blob_at()`], given the `deltas` up to `at` and the
+/// `versions` of the file recorded by snapshots.
+fn replay(
+    project: &Project,
+    file_path: &Path,
+    at: i64,
+    deltas: &[Delta],
+    versions: &[FileVersion],
+) -> Result<Option<BlobAt>> {
+    let from_snapshot = versions
+        .iter()
+        .rev()
+        .find(|version| version.created_at.seconds() <= at)
+        .map(|version| BlobAt {
+            recorded_at: version.created_at.seconds(),
+            blob_id: version.blob_id,
+            eol: None,
+            mode: version.mode,
+        });
+    let base = match latest_checkpoint(deltas, from_snapshot.map(|snapshot| snapshot.recorded_at)) {
+        Some((recorded_at, checkpoint)) => {
+            let repo = repository_pool::open(project)?;
+            let tree = repo.find_commit(checkpoint)?.tree()?;
+            let (blob_id, mode) = match tree.get_path(file_path) {
+                Ok(entry) => (Some(entry.id()), entry_mode(&entry)),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => (None, None),
+                Err(err) => return Err(err.into()),
+            };
+            Some(BlobAt {
+                recorded_at,
+                blob_id,
+                eol: None,
+                mode,
+            })
+        }
+        None => from_snapshot,
+    };
+    Ok(apply_deltas(file_path, base, deltas))
+}
+
+/// Return the mode of the file of a tree `entry`, or `None` if it isn't one.
+pub(crate) fn entry_mode(entry: &git2::TreeEntry) -> Option<FileMode> {
+    [
+        git2::FileMode::Blob,
+        git2::FileMode::BlobExecutable,
+        git2::FileMode::Link,
+        git2::FileMode::Commit,
+    ]
+    .into_iter()
+    .find(|mode| i32::from(*mode) == entry.filemode())
+    .and_then(FileMode::from_git2)
+}
+
+/// Return when the latest checkpoint of `deltas` was recorded along with its commit, unless it wasn't recorded
+/// after the snapshot recorded at `snapshot_at`.
+pub(crate) fn latest_checkpoint(
+    deltas: &[Delta],
+    snapshot_at: Option<i64>,
+) -> Option<(i64, git2::Oid)> {
+    deltas
+        .iter()
+        .rev()
+        .find_map(|delta| Some((delta.at, delta.checkpoint?)))
+        .filter(|(recorded_at, _)| snapshot_at.is_none_or(|snapshot_at| snapshot_at < *recorded_at))
+}
+
+/// Return the content of `file_path` as of the last of `deltas`, starting from the content it had in `base`.
+///
+/// Only the deltas recorded after `base` are applied, and the first of them that didn't record the content of the
+/// file stops, as its content is unknown from there on.
+pub(crate) fn apply_deltas(
+    file_path: &Path,
+    mut blob: Option<BlobAt>,
+    deltas: &[Delta],
+) -> Option<BlobAt> {
+    let base_at = blob.map(|blob| blob.recorded_at);
+    for delta in deltas
+        .iter()
+        .filter(|delta| base_at.is_none_or(|base_at| delta.at > base_at))
+        .filter(|delta| delta.paths.iter().any(|path| path == file_path))
+    {
+        let Some(content) = delta
+            .contents
+            .iter()
+            .find(|content| content.path == file_path)
+        else {
+            break;
+        };
+        blob = Some(BlobAt {
+            recorded_at: delta.at,
+            blob_id: content.blob_id,
+            eol: content.eol,
+            mode: content.mode,
+        });
+    }
+    blob
+}
+
+/// Store the content of the worktree-relative `paths` of `project` that are regular files no larger than
+/// [`MAX_CONTENT_BYTES`] along with their content type and mode, as [read](gitbutler_repo::read_worktree_file())
+/// for showing them, store symbolic links with their target, or record them as deleted.
+///
+/// Files that can't be read, like those without permission to do so, are skipped so the others are still stored.
+/// Line endings are [normalized](eol::normalize()) as the repository would, and secrets are
+/// [redacted](Project::secret_redaction) as in snapshots, which skips files with secrets entirely if so configured.
+fn store_contents(project: &Project, paths: &[PathBuf]) -> Result<Vec<DeltaContent>> {
+    let repo = repository_pool::open(project)?;
+    let store = BlobStore::open(&repo)?;
+    let scanner = SecretScanner::for_project(project);
+    let mut contents = Vec::new();
+    for path in paths {
+        let worktree_path = project.path.join(path);
+        let content = match gitbutler_repo::read_worktree_file(&worktree_path, MAX_CONTENT_BYTES) {
+            Ok(WorktreeFile::Content {
+                content,
+                content_type,
+                executable,
+            }) => {
+                let (content, eol) = eol::normalize(&repo, path, &content)?;
+                let Some(content) =
+                    secrets::redact_content(&scanner, project.secret_redaction, &content)
+                else {
+                    tracing::info!(path = %path.display(), "skipped file with secrets");
+                    continue;
+                };
+                DeltaContent {
+                    path: path.clone(),
+                    blob_id: Some(store.store(&content)?),
+                    eol,
+                    content_type: Some(content_type),
+                    mode: Some(if executable {
+                        FileMode::Executable
+                    } else {
+                        FileMode::Regular
+                    }),
+                    link_target: None,
+                }
+            }
+            Ok(WorktreeFile::Link { target }) => DeltaContent {
+                path: path.clone(),
+                blob_id: Some(store.store(&gix::path::into_bstr(target.as_path()))?),
+                eol: None,
+                content_type: None,
+                mode: Some(FileMode::Symlink),
+                link_target: Some(target),
+            },
+            Ok(WorktreeFile::Missing) => DeltaContent {
+                path: path.clone(),
+                blob_id: None,
+                eol: None,
+                content_type: None,
+                mode: None,
+                link_target: None,
+            },
+            Ok(_) => continue,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), ?err, "skipped unreadable file");
+                continue;
+            }
+        };
+        contents.push(content);
+    }
+    Ok(contents)
+}
+/// Write the content of the worktree of `project` as a commit on top of its [checkpoints](checkpoints_ref()),
+/// reusing the previous checkpoint if nothing changed since, and return its id.
+///
+/// The `unanchored` blobs recorded with deltas since the previous checkpoint are kept reachable through a second
+/// parent, whose tree has an entry for each blob.
+fn write_checkpoint(
+    project: &Project,
+    at: i64,
+    unanchored: BTreeSet<git2::Oid>,
+) -> Result<git2::Oid> {
+    let repo = repository_pool::open(project)?;
+    let reference = checkpoints_ref(project);
+    let tree = repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?;
+    // Secrets in uncommitted changes must not be kept around in checkpoints any more than in snapshots.
+    let head_tree = match repo.head() {
+        Ok(head) => head.peel_to_tree()?,
+        Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+    let (tree_id, _redacted) = secrets::redact_tree(
+        &repo,
+        &SecretScanner::for_project(project),
+        project.secret_redaction,
+        &head_tree,
+        &tree,
+    )?;
+    let tree = repo.find_tree(tree_id)?;
+    let parent = repo
+        .find_reference(&reference)
+        .ok()
+        .and_then(|reference| reference.peel_to_commit().ok());
+    if let Some(parent) = parent
+        .as_ref()
+        .filter(|parent| parent.tree_id() == tree.id() && unanchored.is_empty())
+    {
+        return Ok(parent.id());
+    }
+    let committer = gitbutler_repo::signature(SignaturePurpose::Committer)?;
+    let author = gitbutler_repo::signature(SignaturePurpose::Author)?;
+    let contents = if unanchored.is_empty() {
+        None
+    } else {
+        let mut builder = repo.treebuilder(None)?;
+        for blob_id in &unanchored {
+            builder.insert(blob_id.to_string(), *blob_id, git2::FileMode::Blob.into())?;
+        }
+        let contents_tree = repo.find_tree(builder.write()?)?;
+        let contents_id = repo.commit(
+            None,
+            &author,
+            &committer,
+            &format!("{CONTENTS_MESSAGE_PREFIX} checkpoint at {at}"),
+            &contents_tree,
+            &[],
+        )?;
+        Some(repo.find_commit(contents_id)?)
+    };
+    let parents: Vec<_> = parent.iter().chain(contents.as_ref()).collect();
+    Ok(repo.commit(
+        Some(&reference),
+        &author,
+        &committer,
+        &format!("checkpoint at {at}"),
+        &tree,
+        &parents,
+    )?)
+}
+
+/// Return the checkpoints of `project`, newest first, following first parents only.
+pub(crate) fn checkpoint_commits<'repo>(
+    repo: &'repo git2::Repository,
+    project: &Project,
+) -> Result<Vec<git2::Commit<'repo>>> {
+    let mut checkpoints = Vec::new();
+    let Ok(reference) = repo.find_reference(&checkpoints_ref(project)) else {
+        return Ok(checkpoints);
+    };
+    let mut next = Some(reference.peel_to_commit()?);
+    while let Some(commit) = next.filter(|commit| !is_contents(commit)) {
+        next = commit.parent(0).ok();
+        checkpoints.push(commit);
+    }
+    Ok(checkpoints)
+}
+
+/// Return `true` if `commit` keeps the contents recorded with deltas until a checkpoint reachable, instead of
+/// being a checkpoint itself. It's the first parent of the oldest checkpoint if that one has contents.
+fn is_contents(commit: &git2::Commit) -> bool {
+    commit
+        .message_bytes()
+        .starts_with(CONTENTS_MESSAGE_PREFIX.as_bytes())
+}
+
+/// Return when the delta `checkpoint` was written with was noticed, or `None` if its message doesn't tell.
+fn checkpoint_at(checkpoint: &git2::Commit) -> Option<i64> {
+    std::str::from_utf8(checkpoint.message_bytes())
+        .ok()?
+        .strip_prefix("checkpoint at ")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Determine the state of the checkpoints from the deltas stored at `path`.
+fn checkpoint_state(path: &Path) -> Result<CheckpointState> {
+    let deltas = if path.exists() {
+        read(path)?
+    } else {
+        Arc::default()
+    };
+    Ok(
+        match deltas.iter().rposition(|delta| delta.checkpoint.is_some()) {
+            Some(index) => CheckpointState {
+                at: Some(deltas[index].at),
+                deltas_since: deltas.len() - index - 1,
+            },
+            None => CheckpointState {
+                at: None,
+                deltas_since: deltas.len(),
+            },
+        },
+    )
+}
+
+/// Read all deltas, skipping those that can't be parsed with a warning, as they are
+/// [quarantined](recover()) when the project is opened the next time.
+///
+/// The deltas are parsed once and cached. As long as the file is only appended to, only the lines that were
+/// appended since are parsed on later reads. Once deltas were [sealed](seal()) by anything but the last delta that
+/// was recorded, all of them are parsed again, along with decompressing their [segments](SEGMENTS_FILE).
+fn read(path: &Path) -> Result<Arc<Vec<Delta>>> {
+    // Holding the cache keeps deltas from being sealed while both files are read.
+    let mut parsed = PARSED.lock().unwrap_or_else(|err| err.into_inner());
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    let metadata = file.metadata()?;
+    let modified = metadata.modified().ok();
+    let sealed_len = file_len(&path.with_file_name(SEGMENTS_FILE))?;
+
+    let (offset, mut deltas) = match parsed.get(path) {
+        Some(cached)
+            if cached.sealed_len == sealed_len
+                && cached.len == metadata.len()
+                && cached.modified == modified =>
+        {
+            return Ok(Arc::clone(&cached.deltas))
+        }
+        Some(cached) if cached.sealed_len == sealed_len && cached.len < metadata.len() => {
+            (cached.len, Arc::clone(&cached.deltas))
+        }
+        _ => (0, Arc::new(parse(path, &read_segments(path)?))),
+    };
+    file.seek(SeekFrom::Start(offset))?;
+    let mut appended = Vec::new();
+    file.read_to_end(&mut appended)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    // A line without newline may still be written, and is parsed once it's complete.
+    let complete = appended
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |index| index + 1);
+    Arc::make_mut(&mut deltas).extend(parse(path, &String::from_utf8_lossy(&appended[..complete])));
+    parsed.insert(
+        path.to_owned(),
+        ParsedDeltas {
+            len: offset + complete as u64,
+            modified,
+            sealed_len,
+            deltas: Arc::clone(&deltas),
+        },
+    );
+    Ok(deltas)
+}
+
+/// Forget the parsed deltas and the [tail burst](tail_burst()) of the file at `path`, as it was rewritten.
+fn forget_parsed(path: &Path) {
+    PARSED
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(path);
+    BURSTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(path);
+}
+
+/// Parse the deltas in `content` of the deltas file at `path`, warning about those that can't be parsed.
+fn parse(path: &Path, content: &str) -> Vec<Delta> {
+    let cipher = cipher_for(path);
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            open_record(cipher.as_deref(), line)
+                .and_then(|line| parse_delta(&line))
+                .inspect_err(|reason| {
+                    tracing::warn!(path = %path.display(), reason, "skipped delta that can't be parsed");
+                })
+                .ok()
+        })
+        .collect()
+}
+
+/// Parse the delta on `line`, upgrading it from older versions of the format, or return why it's
+/// [corrupt](parse_record()) or that isn't possible.
+fn parse_delta(line: &str) -> Result<Delta, String> {
+    let mut delta = parse_record(line)?;
+    let version = delta
+        .get("version")
+        .map_or(Some(0), serde_json::Value::as_u64)
+        .and_then(|version| usize::try_from(version).ok())
+        .ok_or("the version is invalid")?;
+    for upgrade in UPGRADES.iter().skip(version) {
+        upgrade(&mut delta);
+    }
+    serde_json::from_value(serde_json::Value::Object(delta))
+        .map_err(|err| format!("the delta isn't understood: {err}"))
+}
@@ -0,0 +1,185 @@
+//! Opt-in [encryption](gitbutler_storage::encryption) of the history of a project at rest, which is its
+//! [deltas](crate::deltas), [heartbeats](crate::heartbeat) and [bookmarks](crate::bookmarks).
+//!
+//! The key is either random and kept in the keychain of the operating system, which unlocks the history without
+//! asking, or derived from a passphrase that has to be entered to [unlock](unlock()) the history once per run of the
+//! app. Until then, nothing can be read from or recorded into the history.
+//!
+//! [Encrypting](encrypt_history()) or [decrypting](decrypt_history()) the history rewrites all of it. The settings
+//! of the project are changed before encrypting and after decrypting it, so a migration that is interrupted leaves
+//! the history readable and is finished by running it again.
+//!
+//! Only what is recorded in the GitButler directory of the project is encrypted. The contents of files are blobs of
+//! the object database like those of snapshots, and the same files are in the worktree anyway.
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::{general_purpose::STANDARD, Engine as _};
+use gitbutler_error::error::Code;
+use gitbutler_project::{HistoryEncryption, Project};
+use gitbutler_secret::{secret, Sensitive};
+use gitbutler_storage::encryption::{self, Cipher};
+
+use crate::{bookmarks, deltas, heartbeat};
+
+/// The handle of the key to the history of a project in the keychain, followed by the id of the project.
+const KEYCHAIN_HANDLE_PREFIX: &str = "history-key-";
+/// What is [encrypted](Cipher::encrypt_line()) as the check of a passphrase.
+const CHECK_PLAINTEXT: &str = "gitbutler-history";
+
+/// Unlock the encrypted history of `project` with `passphrase`, or with its key in the keychain, so it can be read
+/// and recorded into until the app quits, and finish what was interrupted of recording it.
+///
+/// Fails with [`Code::HistoryLocked`] if the passphrase is missing or wrong.
+pub fn unlock(project: &Project, passphrase: Option<&str>) -> Result<()> {
+    let cipher = match &project.history_encryption {
+        HistoryEncryption::None => return Ok(()),
+        HistoryEncryption::Keychain => keychain_cipher(project)?,
+        HistoryEncryption::Passphrase { salt, check } => {
+            let Some(passphrase) = passphrase else {
+                return Err(anyhow!(
+                    "The history of project '{}' needs its passphrase to be unlocked",
+                    project.title
+                ))
+                .context(Code::HistoryLocked);
+            };
+            passphrase_cipher(passphrase, salt, check)?
+        }
+    };
+    encryption::unlock(project.gb_dir(), cipher);
+    deltas::forget(project);
+    deltas::recover(project)?;
+    Ok(())
+}
+
+/// Returns `true` if the history of `project` is encrypted and wasn't [unlocked](unlock()) yet.
+pub fn is_locked(project: &Project) -> bool {
+    project.history_encryption.is_enabled() && encryption::cipher(&project.gb_dir()).is_none()
+}
+
+/// Unlock the history of `project` with its key in the keychain if it's [locked](is_locked()), or fail with
+/// [`Code::HistoryLocked`] if it needs a passphrase.
+pub fn ensure_unlocked(project: &Project) -> Result<()> {
+    if !is_locked(project) {
+        return Ok(());
+    }
+    match project.history_encryption {
+        HistoryEncryption::Keychain => unlock(project, None),
+        _ => Err(anyhow!(
+            "The history of project '{}' is encrypted, and has to be unlocked with its passphrase",
+            project.title
+        ))
+        .context(Code::HistoryLocked),
+    }
+}
+
+/// Encrypt the history of `project` with a new random key kept in the keychain, or with a key derived from
+/// `passphrase` if set, and return the project with its new settings, as stored with `projects`.
+///
+/// If the history is encrypted already, what is left of it in plain files, like after an interrupted migration, is
+/// encrypted with its current key, after unlocking it with `passphrase` if needed.
+pub fn encrypt_history(
+    projects: &gitbutler_project::Controller,
+    project: &Project,
+    passphrase: Option<&str>,
+) -> Result<Project> {
+    let project = if project.history_encryption.is_enabled() {
+        if is_locked(project) {
+            unlock(project, passphrase)?;
+        }
+        project.clone()
+    } else {
+        let (settings, cipher) = match passphrase {
+            None => {
+                let key = Cipher::generate_key()?;
+                secret::persist(
+                    &keychain_handle(project),
+                    &Sensitive(STANDARD.encode(key)),
+                    secret::Namespace::BuildKind,
+                )?;
+                (HistoryEncryption::Keychain, Cipher::from_key(&key)?)
+            }
+            Some(passphrase) => {
+                let salt: [u8; encryption::SALT_LEN] = encryption::random()?;
+                let cipher = Cipher::from_passphrase(passphrase, &salt)?;
+                let settings = HistoryEncryption::Passphrase {
+                    salt: STANDARD.encode(salt),
+                    check: cipher.encrypt_line(CHECK_PLAINTEXT)?,
+                };
+                (settings, cipher)
+            }
+        };
+        // The settings are stored first, so the key is known for as long as anything is encrypted with it.
+        let project = projects.set_history_encryption(project.id, settings)?;
+        encryption::unlock(project.gb_dir(), cipher);
+        deltas::forget(&project);
+        project
+    };
+    let cipher = encryption::cipher(&project.gb_dir());
+    reseal(&project, cipher.clone(), cipher)?;
+    Ok(project)
+}
+
+/// Rewrite the encrypted history of `project` into plain files, forget its key, and return the project with its new
+/// settings, as stored with `projects`. The history has to be [unlocked](unlock()) unless its key is in the keychain.
+pub fn decrypt_history(
+    projects: &gitbutler_project::Controller,
+    project: &Project,
+) -> Result<Project> {
+    if !project.history_encryption.is_enabled() {
+        return Ok(project.clone());
+    }
+    ensure_unlocked(project)?;
+    let cipher = encryption::cipher(&project.gb_dir());
+    reseal(project, cipher.clone(), None)?;
+    let decrypted = projects.set_history_encryption(project.id, HistoryEncryption::None)?;
+    encryption::lock(&project.gb_dir());
+    // What was recorded while the settings were changed was still encrypted.
+    reseal(project, cipher, None)?;
+    deltas::forget(project);
+    if project.history_encryption == HistoryEncryption::Keychain {
+        secret::delete(&keychain_handle(project), secret::Namespace::BuildKind)?;
+    }
+    Ok(decrypted)
+}
+
+/// Rewrite the history of `project`, reading it with `source`, so it's encrypted with `target`, or is plain if it's
+/// `None`.
+fn reseal(
+    project: &Project,
+    source: Option<Arc<Cipher>>,
+    target: Option<Arc<Cipher>>,
+) -> Result<()> {
+    deltas::reseal(project, source.as_deref(), target.as_deref())?;
+    heartbeat::reseal(project, source.as_deref(), target.as_deref())?;
+    bookmarks::reseal(project, source, target)
+}
+
+fn keychain_cipher(project: &Project) -> Result<Cipher> {
+    let key = secret::retrieve(&keychain_handle(project), secret::Namespace::BuildKind)?
+        .with_context(|| {
+            format!(
+                "The key to the history of project '{}' is missing from the keychain",
+                project.title
+            )
+        })?;
+    let key = STANDARD
+        .decode(&key.0)
+        .context("The key to the history in the keychain is damaged")?;
+    Cipher::from_key(&key)
+}
+
+fn passphrase_cipher(passphrase: &str, salt: &str, check: &str) -> Result<Cipher> {
+    let salt = STANDARD
+        .decode(salt)
+        .context("The salt of the history encryption is damaged")?;
+    let cipher = Cipher::from_passphrase(passphrase, &salt)?;
+    if cipher.decrypt_line(check).ok().as_deref() != Some(CHECK_PLAINTEXT) {
+        return Err(anyhow!("The passphrase of the history is wrong")).context(Code::HistoryLocked);
+    }
+    Ok(cipher)
+}
+
+fn keychain_handle(project: &Project) -> String {
+    format!("{KEYCHAIN_HANDLE_PREFIX}{}", project.id)
+}
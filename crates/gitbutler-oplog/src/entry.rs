@@ -31,6 +31,21 @@ pub struct Snapshot {
     pub details: Option<SnapshotDetails>,
 }
 
+/// A distinct state of a single file in the working directory, as returned by
+/// [`file_versions()`](crate::OplogExt::file_versions()).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FileVersion {
+    /// The id of the oldest snapshot in which the file had this content.
+    pub snapshot_id: git2::Oid,
+    /// The creation time of the snapshot with `snapshot_id`.
+    pub created_at: git2::Time,
+    /// The id of the blob with the content of the file, or `None` if it didn't exist.
+    pub blob_id: Option<git2::Oid>,
+    /// The mode of the file, or `None` if it didn't exist. A version with the same content as the previous
+    /// one differs in its mode, like after the executable bit was flipped.
+    pub mode: Option<gitbutler_diff::FileMode>,
+}
+
 /// The payload of a snapshot commit
 ///
 /// This is persisted as a commit message in the title, body and trailers format (<https://git-scm.com/docs/git-interpret-trailers>)
@@ -163,6 +178,16 @@ pub enum OperationKind {
     UpdateDependentBranchName,
     UpdateDependentBranchDescription,
     UpdateDependentBranchPrNumber,
+    RevertCommit,
+    InteractiveRebase,
+    RepairHistory,
+    OfflineChanges,
+    ExternalGitOperation,
+    UndoOperation,
+    UpdateProjectSettings,
+    SuspendWorkspace,
+    ResumeWorkspace,
+    RestoreFiles,
     #[default]
     Unknown,
 }
@@ -0,0 +1,144 @@
+//! Line endings of recorded contents, so checking out files with `core.autocrlf` or the `text` and `eol`
+//! attributes doesn't make every line of them look changed.
+//!
+//! If the repository normalizes the line endings of a file, its content is recorded with LF line endings just
+//! like Git would store it, along with the [`LineEnding`] it had in the worktree. Restoring the content converts it
+//! back, so files are written as they were. Files with mixed line endings are recorded as they are, as converting
+//! them back couldn't tell which lines had which.
+use std::{borrow::Cow, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Git looks at this many bytes at most to tell if a file is binary.
+const BINARY_CHECK_BYTES: usize = 8000;
+
+/// The line endings a file had in the worktree when its content was recorded with LF line endings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// How the repository wants line endings of a file to be normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Normalization {
+    /// The file is text, like with the `text` attribute set.
+    Text,
+    /// The file is normalized if it looks like text, like with `text=auto` or `core.autocrlf`.
+    Auto,
+}
+
+/// Return `content` of the file at the worktree-relative `path` of `repo` as it should be recorded, along with the
+/// line endings to [`restore()`] it with, or `None` if it's recorded as it is.
+pub fn normalize<'a>(
+    repo: &git2::Repository,
+    path: &Path,
+    content: &'a [u8],
+) -> Result<(Cow<'a, [u8]>, Option<LineEnding>)> {
+    let normalization = match normalization(repo, path)? {
+        Some(Normalization::Auto) if is_binary(content) => None,
+        normalization => normalization,
+    };
+    if normalization.is_none() {
+        return Ok((Cow::Borrowed(content), None));
+    }
+    Ok(match line_ending(content) {
+        Some(LineEnding::Crlf) => (
+            Cow::Owned(replace(content, b"\r\n", b"\n")),
+            Some(LineEnding::Crlf),
+        ),
+        Some(LineEnding::Lf) => (Cow::Borrowed(content), Some(LineEnding::Lf)),
+        None => (Cow::Borrowed(content), None),
+    })
+}
+
+/// Return `content` as recorded by [`normalize()`] with the line endings the file had in the worktree.
+pub fn restore(content: &[u8], eol: Option<LineEnding>) -> Cow<'_, [u8]> {
+    match eol {
+        Some(LineEnding::Crlf) => Cow::Owned(replace(content, b"\n", b"\r\n")),
+        Some(LineEnding::Lf) | None => Cow::Borrowed(content),
+    }
+}
+
+/// Return how the file at the worktree-relative `path` is normalized according to the attributes and config of
+/// `repo`, or `None` if it isn't.
+fn normalization(repo: &git2::Repository, path: &Path) -> Result<Option<Normalization>> {
+    let attr = |name| repo.get_attr(path, name, git2::AttrCheckFlags::FILE_THEN_INDEX);
+    match git2::AttrValue::from_string(attr("text")?) {
+        git2::AttrValue::True => return Ok(Some(Normalization::Text)),
+        git2::AttrValue::False => return Ok(None),
+        git2::AttrValue::String("auto") => return Ok(Some(Normalization::Auto)),
+        _ => {}
+    }
+    if matches!(
+        git2::AttrValue::from_string(attr("eol")?),
+        git2::AttrValue::String(_)
+    ) {
+        return Ok(Some(Normalization::Text));
+    }
+
+    let config = repo.config()?.snapshot()?;
+    let autocrlf = match config.get_str("core.autocrlf") {
+        Ok(value) => value.eq_ignore_ascii_case("input") || config.get_bool("core.autocrlf")?,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => false,
+        Err(err) => return Err(err.into()),
+    };
+    Ok(autocrlf.then_some(Normalization::Auto))
+}
+
+/// Return the line endings of `content`, or `None` if they are mixed.
+fn line_ending(content: &[u8]) -> Option<LineEnding> {
+    let lf = content.iter().filter(|byte| **byte == b'\n').count();
+    let crlf = content.windows(2).filter(|pair| *pair == b"\r\n").count();
+    match crlf {
+        0 => Some(LineEnding::Lf),
+        crlf if crlf == lf => Some(LineEnding::Crlf),
+        _ => None,
+    }
+}
+
+/// Return `true` if `content` looks binary to Git, which is when it has a NUL byte near the start.
+fn is_binary(content: &[u8]) -> bool {
+    content[..content.len().min(BINARY_CHECK_BYTES)].contains(&0)
+}
+
+fn replace(content: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while !rest.is_empty() {
+        if rest.starts_with(from) {
+            replaced.extend_from_slice(to);
+            rest = &rest[from.len()..];
+        } else {
+            replaced.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_are_detected() {
+        assert_eq!(line_ending(b""), Some(LineEnding::Lf));
+        assert_eq!(line_ending(b"a\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(line_ending(b"a\r\nb\r\n"), Some(LineEnding::Crlf));
+        assert_eq!(line_ending(b"a\r\nb\n"), None, "mixed");
+    }
+
+    #[test]
+    fn restoring_crlf_gives_back_the_original() {
+        for original in [&b"a\r\nb\r\n"[..], b"a\r\r\nb", b"\r\n\r\n"] {
+            let normalized = replace(original, b"\r\n", b"\n");
+            assert_eq!(
+                restore(&normalized, Some(LineEnding::Crlf)).as_ref(),
+                original
+            );
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Export the history recorded by snapshots to formats other tools understand, for auditing or archiving.
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_oxidize::git2_to_gix_object_id;
+use gitbutler_project::Project;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    activity::{SnapshotActivity, SESSION_GAP_SECONDS},
+    oplog::{get_workdir_tree, snapshot_activities},
+};
+
+/// The branch the commits of a [fast-export](HistoryExportFormat::FastExport) stream are written to.
+pub const FAST_EXPORT_REF: &str = "refs/heads/gitbutler/history";
+
+/// The format to [export](export_history()) the history in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryExportFormat {
+    /// One JSON object per snapshot and line, oldest first.
+    JsonLines,
+    /// A stream for `git fast-import` with one commit per session, holding the files that changed in it.
+    FastExport,
+}
+
+/// What was written by [`export_history()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryExport {
+    pub path: PathBuf,
+    pub snapshots: usize,
+    pub sessions: usize,
+}
+
+/// A single line of a [JSON Lines](HistoryExportFormat::JsonLines) export.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotRecord<'a> {
+    #[serde(with = "gitbutler_serde::oid")]
+    id: git2::Oid,
+    /// The creation time in seconds since the Unix epoch.
+    created_at: i64,
+    /// The zero-based index of the session the snapshot belongs to.
+    session: usize,
+    operation: Option<String>,
+    title: Option<&'a str>,
+    body: Option<&'a str>,
+    lines_added: usize,
+    lines_removed: usize,
+    files_changed: &'a [PathBuf],
+}
+
+/// Write the history of `project` recorded by snapshots created within `range`, in seconds since the Unix epoch,
+/// to the file at `path` in the given `format`.
+///
+/// The file is replaced only once the export is complete, so nothing is written if it fails as `should_interrupt`
+/// was set.
+pub fn export_history(
+    project: &Project,
+    format: HistoryExportFormat,
+    range: Range<i64>,
+    path: &Path,
+    should_interrupt: &AtomicBool,
+) -> Result<HistoryExport> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Could not write to '{}'", dir.display()))?;
+    let (snapshots, sessions) = {
+        let mut out = std::io::BufWriter::new(file.as_file_mut());
+        let counts = write_history(&mut out, project, format, range, should_interrupt)?;
+        out.flush()?;
+        counts
+    };
+    file.persist(path)?;
+
+    Ok(HistoryExport {
+        path: path.to_owned(),
+        snapshots,
+        sessions,
+    })
+}
+
+/// Like [`export_history()`], but return the exported history instead of writing it to a file.
+pub fn render_history(
+    project: &Project,
+    format: HistoryExportFormat,
+    range: Range<i64>,
+    should_interrupt: &AtomicBool,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write_history(&mut out, project, format, range, should_interrupt)?;
+    Ok(out)
+}
+
+/// Write the history of snapshots in `range` to `out` in `format`, returning the amount of snapshots and sessions.
+/// Fails between sessions once `should_interrupt` is set.
+fn write_history(
+    out: &mut impl Write,
+    project: &Project,
+    format: HistoryExportFormat,
+    range: Range<i64>,
+    should_interrupt: &AtomicBool,
+) -> Result<(usize, usize)> {
+    let mut snapshots = snapshot_activities(project, range)?;
+    snapshots.sort_by_key(|snapshot| snapshot.created_at.seconds());
+    let sessions = sessions(&snapshots);
+    match format {
+        HistoryExportFormat::JsonLines => {
+            write_json_lines(out, &snapshots, &sessions, should_interrupt)?
+        }
+        HistoryExportFormat::FastExport => {
+            write_fast_export(out, project, &snapshots, &sessions, should_interrupt)?
+        }
+    }
+    Ok((snapshots.len(), sessions.len()))
+}
+
+/// Group `snapshots`, sorted oldest first, into sessions like [`ActivitySummary`](crate::activity::ActivitySummary)
+/// does, returning the index range of each session.
+fn sessions(snapshots: &[SnapshotActivity]) -> Vec<Range<usize>> {
+    let mut sessions: Vec<Range<usize>> = Vec::new();
+    for (idx, snapshot) in snapshots.iter().enumerate() {
+        match sessions.last_mut() {
+            Some(session)
+                if snapshot.created_at.seconds()
+                    - snapshots[session.end - 1].created_at.seconds()
+                    <= SESSION_GAP_SECONDS =>
+            {
+                session.end = idx + 1;
+            }
+            _ => sessions.push(idx..idx + 1),
+        }
+    }
+    sessions
+}
+
+fn write_json_lines(
+    out: &mut impl Write,
+    snapshots: &[SnapshotActivity],
+    sessions: &[Range<usize>],
+    should_interrupt: &AtomicBool,
+) -> Result<()> {
+    for (session, range) in sessions.iter().enumerate() {
+        check_interrupt(should_interrupt)?;
+        for snapshot in &snapshots[range.clone()] {
+            let record = SnapshotRecord {
+                id: snapshot.snapshot_id,
+                created_at: snapshot.created_at.seconds(),
+                session,
+                operation: snapshot
+                    .details
+                    .as_ref()
+                    .map(|details| details.operation.to_string()),
+                title: snapshot.details.as_ref().map(|d| d.title.as_str()),
+                body: snapshot.details.as_ref().and_then(|d| d.body.as_deref()),
+                lines_added: snapshot.lines_added,
+                lines_removed: snapshot.lines_removed,
+                files_changed: &snapshot.changed_paths,
+            };
+            serde_json::to_writer(&mut *out, &record)?;
+            out.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_fast_export(
+    out: &mut impl Write,
+    project: &Project,
+    snapshots: &[SnapshotActivity],
+    sessions: &[Range<usize>],
+    should_interrupt: &AtomicBool,
+) -> Result<()> {
+    let repo = gitbutler_command_context::gix_repository_for_merging(project.path.as_path())?;
+    let mut wd_trees_cache = HashMap::new();
+    for (session, range) in sessions.iter().enumerate() {
+        check_interrupt(should_interrupt)?;
+        let session_snapshots = &snapshots[range.clone()];
+        let (first, last) = (
+            &session_snapshots[0],
+            &session_snapshots[session_snapshots.len() - 1],
+        );
+        let changed_paths: BTreeSet<_> = session_snapshots
+            .iter()
+            .flat_map(|snapshot| &snapshot.changed_paths)
+            .collect();
+
+        let mut message = format!(
+            "Session from {} to {}\n\n",
+            first.created_at.seconds(),
+            last.created_at.seconds()
+        );
+        for snapshot in session_snapshots {
+            if let Some(details) = &snapshot.details {
+                message.push_str(&format!("- {}\n", details.title));
+            }
+        }
+        let offset = last.created_at.offset_minutes();
+        writeln!(out, "commit {FAST_EXPORT_REF}")?;
+        writeln!(out, "mark :{}", session + 1)?;
+        writeln!(
+            out,
+            "committer {} <{}> {} {}{:02}{:02}",
+            gitbutler_repo::GITBUTLER_COMMIT_AUTHOR_NAME,
+            gitbutler_repo::GITBUTLER_COMMIT_AUTHOR_EMAIL,
+            last.created_at.seconds(),
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 60,
+            offset.abs() % 60
+        )?;
+        write_data(out, message.as_bytes())?;
+        if session > 0 {
+            writeln!(out, "from :{session}")?;
+        }
+
+        let tree = get_workdir_tree(
+            &mut wd_trees_cache,
+            git2_to_gix_object_id(last.snapshot_id),
+            &repo,
+        )?;
+        for path in changed_paths {
+            let quoted = quote_path(path);
+            match tree.lookup_entry_by_path(path)? {
+                Some(entry) if entry.mode().is_blob() || entry.mode().is_link() => {
+                    let mode = if entry.mode().is_link() {
+                        "120000"
+                    } else if entry.mode().is_executable() {
+                        "100755"
+                    } else {
+                        "100644"
+                    };
+                    writeln!(out, "M {mode} inline {quoted}")?;
+                    write_data(out, &entry.object()?.data)?;
+                }
+                // Submodules and directories that replaced files can't be expressed as inline data.
+                Some(_) => {}
+                None => writeln!(out, "D {quoted}")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "done")?;
+    Ok(())
+}
+
+fn check_interrupt(should_interrupt: &AtomicBool) -> Result<()> {
+    if should_interrupt.load(Ordering::Relaxed) {
+        bail!("Exporting the history was cancelled");
+    }
+    Ok(())
+}
+
+/// Write `data` as an exact-length data block of a fast-import stream.
+fn write_data(out: &mut impl Write, data: &[u8]) -> Result<()> {
+    writeln!(out, "data {}", data.len())?;
+    out.write_all(data)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Quote `path` as C-style string with forward slashes, which fast-import always accepts.
+fn quote_path(path: &Path) -> String {
+    let mut quoted = String::from('"');
+    for c in gitbutler_fs::paths::to_slash(path).chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
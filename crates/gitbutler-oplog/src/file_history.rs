@@ -0,0 +1,159 @@
+//! The complete lineage of a file, combining the commits that touched it with the sessions in which it was
+//! edited before any of that got committed.
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use gitbutler_project::Project;
+use serde::Serialize;
+
+use crate::activity::{self, ActivitySession};
+use crate::deltas;
+use crate::heartbeat;
+use crate::oplog::snapshot_activities;
+
+/// The most commits to look at when following a file back through history.
+const MAX_COMMITS: usize = 10_000;
+
+/// An event in the history of a file, as returned by [`file_history()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum FileHistoryEntry {
+    /// A commit reachable from `HEAD` that changed the file.
+    #[serde(rename_all = "camelCase")]
+    Commit {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit_id: git2::Oid,
+        /// The worktree-relative path of the file in this commit, which differs from the current one if it was
+        /// renamed since.
+        path: PathBuf,
+        /// The commit time in seconds since the Unix epoch.
+        created_at: i64,
+        summary: String,
+        author: String,
+    },
+    /// A session during which snapshots recorded changes to the file.
+    #[serde(rename_all = "camelCase")]
+    Session { session: ActivitySession },
+}
+
+impl FileHistoryEntry {
+    fn time(&self) -> i64 {
+        match self {
+            FileHistoryEntry::Commit { created_at, .. } => *created_at,
+            FileHistoryEntry::Session { session } => session.end,
+        }
+    }
+}
+
+/// Return the history of the file at the worktree-relative `path`, newest first.
+///
+/// Renames are followed like `git log --follow` does, looking at the first parent of each commit, and sessions
+/// that edited the file under any of its names are interleaved with the commits. Renames that weren't committed yet
+/// are followed through the [deltas](deltas::DeltaRename) that recorded them, so commits are looked up by the name
+/// the file has in `HEAD`.
+pub fn file_history(project: &Project, path: &Path) -> Result<Vec<FileHistoryEntry>> {
+    let repo = git2::Repository::open(&project.path)?;
+    let mut history = Vec::new();
+    let former_paths = deltas::former_paths(
+        &deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?,
+        path,
+    );
+    let mut names = vec![path.to_owned()];
+    names.extend(former_paths.iter().cloned());
+    let mut path = path.to_owned();
+    if let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) {
+        if blob_at(&head, &path)?.is_none() {
+            for former_path in &former_paths {
+                if blob_at(&head, former_path)?.is_some() {
+                    path = former_path.clone();
+                    break;
+                }
+            }
+        }
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(head.id())?;
+        for commit_id in revwalk.take(MAX_COMMITS) {
+            let commit = repo.find_commit(commit_id?)?;
+            let entry_id = blob_at(&commit, &path)?;
+            let parent_entry_ids = commit
+                .parents()
+                .map(|parent| blob_at(&parent, &path))
+                .collect::<Result<Vec<_>>>()?;
+            if parent_entry_ids.contains(&entry_id) {
+                continue;
+            }
+            if parent_entry_ids.is_empty() && entry_id.is_none() {
+                continue;
+            }
+            history.push(FileHistoryEntry::Commit {
+                commit_id: commit.id(),
+                path: path.clone(),
+                created_at: commit.time().seconds(),
+                summary: commit.summary().unwrap_or_default().to_owned(),
+                author: commit.author().name().unwrap_or_default().to_owned(),
+            });
+            let added = entry_id.is_some() && parent_entry_ids.first().is_some_and(Option::is_none);
+            if added {
+                if let Some(old_path) = renamed_from(&repo, &commit, &path)? {
+                    if !names.contains(&old_path) {
+                        names.push(old_path.clone());
+                    }
+                    path = old_path;
+                }
+            }
+        }
+    }
+
+    let range = i64::MIN..i64::MAX;
+    let snapshots = snapshot_activities(project, range.clone())?;
+    let summary = activity::summarize(
+        &snapshots,
+        heartbeat::heartbeats(project, range)?,
+        Vec::new(),
+    );
+    history.extend(
+        summary
+            .sessions
+            .into_iter()
+            .filter(|session| {
+                snapshots.iter().any(|snapshot| {
+                    (session.start..=session.end).contains(&snapshot.created_at.seconds())
+                        && snapshot
+                            .changed_paths
+                            .iter()
+                            .any(|changed| names.contains(changed))
+                })
+            })
+            .map(|session| FileHistoryEntry::Session { session }),
+    );
+    history.sort_by_key(|entry| std::cmp::Reverse(entry.time()));
+    Ok(history)
+}
+
+/// Return the id of the blob at `path` in the tree of `commit`, or `None` if there is none.
+fn blob_at(commit: &git2::Commit, path: &Path) -> Result<Option<git2::Oid>> {
+    match commit.tree()?.get_path(path) {
+        Ok(entry) => Ok(Some(entry.id())),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return the path the file at `path` had in the first parent of `commit` if `commit` renamed it.
+fn renamed_from(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    path: &Path,
+) -> Result<Option<PathBuf>> {
+    let Ok(parent) = commit.parent(0) else {
+        return Ok(None);
+    };
+    let mut diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() == git2::Delta::Renamed)
+        .find(|delta| delta.new_file().path() == Some(path))
+        .and_then(|delta| delta.old_file().path().map(Path::to_owned)))
+}
@@ -0,0 +1,133 @@
+//! Heartbeats sent by editors while a file is focused, so reading code counts as activity even though it
+//! doesn't create snapshots.
+//!
+//! If the history of the project is [encrypted](crate::encryption), each line is encrypted on its own.
+use std::{
+    collections::HashMap,
+    io::Write,
+    ops::Range,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_project::{Project, ProjectId};
+use gitbutler_storage::encryption::{self, Cipher};
+
+use crate::encryption::ensure_unlocked;
+
+/// The file in the GitButler directory of a project that heartbeats are appended to, one `<seconds>\t<path>`
+/// line each.
+const HEARTBEATS_FILE: &str = "heartbeats";
+
+/// Heartbeats closer than this many seconds to the previously recorded one aren't recorded, as they can't
+/// change the [sessions](crate::activity::ActivitySession).
+pub const MIN_INTERVAL_SECONDS: i64 = 30;
+
+/// Once the heartbeats file is larger than this, heartbeats older than [`RETENTION_SECONDS`] are dropped.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const RETENTION_SECONDS: i64 = 90 * 24 * 60 * 60;
+
+/// When the latest heartbeat was recorded for each project, in seconds since the Unix epoch.
+static LATEST: LazyLock<Mutex<HashMap<ProjectId, i64>>> = LazyLock::new(Default::default);
+
+/// Record that the file at the worktree-relative `file_path` is focused in an editor at `at` seconds since
+/// the Unix epoch, and return `true` if it was recorded, or `false` if it was too close to the previous one.
+pub fn record_heartbeat(project: &Project, file_path: &Path, at: i64) -> Result<bool> {
+    {
+        let mut latest = LATEST.lock().unwrap_or_else(|err| err.into_inner());
+        if latest
+            .get(&project.id)
+            .is_some_and(|previous| (at - previous).abs() < MIN_INTERVAL_SECONDS)
+        {
+            return Ok(false);
+        }
+        latest.insert(project.id, at);
+    }
+
+    ensure_unlocked(project)?;
+    let cipher = encryption::cipher(&project.gb_dir());
+    std::fs::create_dir_all(project.gb_dir())?;
+    let path = project.gb_dir().join(HEARTBEATS_FILE);
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_FILE_BYTES) {
+        let retained = read(&path, cipher.as_deref())?
+            .into_iter()
+            .filter(|(seconds, _)| at - seconds <= RETENTION_SECONDS)
+            .map(|(seconds, file)| to_line(cipher.as_deref(), seconds, &file))
+            .collect::<Result<String>>()?;
+        gitbutler_fs::write(&path, retained)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    let file_path = gitbutler_fs::paths::to_slash(file_path).replace(['\t', '\n'], " ");
+    file.write_all(to_line(cipher.as_deref(), at, &file_path)?.as_bytes())?;
+    Ok(true)
+}
+
+/// Return the times of the heartbeats of `project` within `range`, in seconds since the Unix epoch and in the
+/// order they were recorded.
+pub(crate) fn heartbeats(project: &Project, range: Range<i64>) -> Result<Vec<i64>> {
+    let path = project.gb_dir().join(HEARTBEATS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    ensure_unlocked(project)?;
+    Ok(
+        read(&path, encryption::cipher(&project.gb_dir()).as_deref())?
+            .into_iter()
+            .map(|(seconds, _)| seconds)
+            .filter(|seconds| range.contains(seconds))
+            .collect(),
+    )
+}
+
+/// Rewrite the heartbeats of `project` so they are encrypted with `target`, or are plain if it's `None`, reading
+/// them with `source`.
+pub(crate) fn reseal(
+    project: &Project,
+    source: Option<&Cipher>,
+    target: Option<&Cipher>,
+) -> Result<()> {
+    let path = project.gb_dir().join(HEARTBEATS_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+    let resealed = read(&path, source)?
+        .into_iter()
+        .map(|(seconds, file)| to_line(target, seconds, &file))
+        .collect::<Result<String>>()?;
+    gitbutler_fs::write(&path, resealed)
+}
+
+/// Return the heartbeat at `seconds` for `file_path` as line of the heartbeats file, encrypted with `cipher` if set.
+fn to_line(cipher: Option<&Cipher>, seconds: i64, file_path: &str) -> Result<String> {
+    let line = format!("{seconds}\t{file_path}");
+    Ok(match cipher {
+        Some(cipher) => format!("{}\n", cipher.encrypt_line(&line)?),
+        None => format!("{line}\n"),
+    })
+}
+
+/// Read all heartbeats as `(seconds, file path)`, decrypting them with `cipher`, and skipping lines that can't be
+/// parsed or decrypted.
+fn read(path: &Path, cipher: Option<&Cipher>) -> Result<Vec<(i64, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let decrypted;
+            let line = if encryption::is_encrypted_line(line) {
+                decrypted = cipher?.decrypt_line(line).ok()?;
+                decrypted.as_str()
+            } else {
+                line
+            };
+            let (seconds, file) = line.split_once('\t')?;
+            Some((seconds.parse().ok()?, file.to_owned()))
+        })
+        .collect())
+}
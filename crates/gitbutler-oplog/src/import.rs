@@ -0,0 +1,170 @@
+//! Merge the snapshots recorded in another copy of a repository, for instance the one on a previous machine,
+//! into the history of a project.
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use gitbutler_project::{access::WorktreeWritePermission, Project};
+use serde::Serialize;
+
+use crate::{state::OplogHandle, OplogExt};
+
+/// What was done by [`import_history()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryImport {
+    /// The amount of snapshots that were added to the history.
+    pub imported: usize,
+    /// The amount of snapshots that were skipped as they were part of the history already.
+    pub skipped: usize,
+}
+
+/// A snapshot commit of one of the histories to merge.
+struct SnapshotCommit {
+    /// The id of the commit if it is part of the history of the project.
+    local_id: Option<git2::Oid>,
+    /// The id of the parent of `local_id`.
+    local_parent_id: Option<git2::Oid>,
+    tree_id: git2::Oid,
+    message: String,
+    author: git2::Signature<'static>,
+    committer: git2::Signature<'static>,
+}
+
+impl SnapshotCommit {
+    fn from_commit(commit: &git2::Commit<'_>, is_local: bool) -> Result<Self> {
+        Ok(SnapshotCommit {
+            local_id: is_local.then(|| commit.id()),
+            local_parent_id: is_local.then(|| commit.parent_id(0).ok()).flatten(),
+            tree_id: commit.tree_id(),
+            message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            author: commit.author().to_owned(),
+            committer: commit.committer().to_owned(),
+        })
+    }
+
+    /// Snapshots are considered the same if they were created at the same time with the same state.
+    fn key(&self) -> (i64, git2::Oid) {
+        (self.committer.when().seconds(), self.tree_id)
+    }
+}
+
+/// Merge the snapshots of the repository at `archive_path`, which may also be its `.git` directory,
+/// into the history of `project`, ordered by their creation time.
+///
+/// Snapshots that were created at the same time with the same state are only kept once, so importing
+/// the same history again has no effect. Local snapshots that are newer than imported ones are recreated
+/// on top of them, which changes their ids.
+pub fn import_history(
+    project: &Project,
+    archive_path: &Path,
+    perm: &mut WorktreeWritePermission,
+) -> Result<HistoryImport> {
+    let repo = git2::Repository::open(&project.path)?;
+    let archive = git2::Repository::open(archive_path).with_context(|| {
+        format!(
+            "'{}' is not a repository to import history from",
+            archive_path.display()
+        )
+    })?;
+    let Some(archive_head) = OplogHandle::new(&archive.path().join("gitbutler")).oplog_head()?
+    else {
+        return Ok(HistoryImport {
+            imported: 0,
+            skipped: 0,
+        });
+    };
+
+    let mut snapshots = match OplogHandle::new(&project.gb_dir()).oplog_head()? {
+        Some(head) => snapshot_commits(&repo, head, true)?,
+        None => Vec::new(),
+    };
+    let mut known: HashSet<_> = snapshots.iter().map(SnapshotCommit::key).collect();
+    let (src_odb, dst_odb) = (archive.odb()?, repo.odb()?);
+    let (mut imported, mut skipped) = (0, 0);
+    for snapshot in snapshot_commits(&archive, archive_head, false)? {
+        if !known.insert(snapshot.key()) {
+            skipped += 1;
+            continue;
+        }
+        copy_tree(&archive, &src_odb, &dst_odb, snapshot.tree_id)?;
+        snapshots.push(snapshot);
+        imported += 1;
+    }
+    if imported == 0 {
+        return Ok(HistoryImport { imported, skipped });
+    }
+
+    // Oldest first, and local snapshots first if created at the same time, to keep as many as possible.
+    snapshots.sort_by_key(|snapshot| {
+        (
+            snapshot.committer.when().seconds(),
+            snapshot.local_id.is_none(),
+        )
+    });
+    let mut head: Option<git2::Oid> = None;
+    for snapshot in snapshots {
+        head = Some(match snapshot.local_id {
+            Some(local_id) if snapshot.local_parent_id == head => local_id,
+            _ => {
+                let parent = head.map(|id| repo.find_commit(id)).transpose()?;
+                repo.commit(
+                    None,
+                    &snapshot.author,
+                    &snapshot.committer,
+                    &snapshot.message,
+                    &repo.find_tree(snapshot.tree_id)?,
+                    parent.as_slice(),
+                )?
+            }
+        });
+    }
+    project.set_oplog_head(head.expect("there is at least one imported snapshot"), perm)?;
+    Ok(HistoryImport { imported, skipped })
+}
+
+/// Return the snapshots reachable from `head` in `repo`, following first parents only.
+fn snapshot_commits(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    is_local: bool,
+) -> Result<Vec<SnapshotCommit>> {
+    let mut snapshots = Vec::new();
+    let mut next = Some(repo.find_commit(head)?);
+    while let Some(commit) = next {
+        if commit.tree()?.get_name("virtual_branches.toml").is_some() {
+            snapshots.push(SnapshotCommit::from_commit(&commit, is_local)?);
+        }
+        next = commit.parent(0).ok();
+    }
+    Ok(snapshots)
+}
+
+/// Copy the tree with `tree_id` and everything it references from `src` to `dst`, unless it exists there already.
+///
+/// Subtrees are written before the trees referring to them, so existing trees are known to be complete.
+fn copy_tree(
+    src_repo: &git2::Repository,
+    src: &git2::Odb<'_>,
+    dst: &git2::Odb<'_>,
+    tree_id: git2::Oid,
+) -> Result<()> {
+    if dst.exists(tree_id) {
+        return Ok(());
+    }
+    let tree = src_repo.find_tree(tree_id)?;
+    for entry in tree.iter() {
+        match entry.kind() {
+            Some(git2::ObjectType::Tree) => copy_tree(src_repo, src, dst, entry.id())?,
+            Some(git2::ObjectType::Blob) if !dst.exists(entry.id()) => {
+                let blob = src.read(entry.id())?;
+                dst.write(blob.kind(), blob.data())?;
+            }
+            // Submodules point to commits that aren't part of the repository.
+            _ => {}
+        }
+    }
+    let object = src.read(tree_id)?;
+    dst.write(object.kind(), object.data())?;
+    Ok(())
+}
@@ -0,0 +1,446 @@
+//! A journal of the operations GitButler performed on a project, like commits, rebases, restores or changes to its
+//! settings, with their parameters and where each of them moved the branches.
+//!
+//! It's the audit trail of everything that changed the project, and what [`undo_last_operation()`] relies on to
+//! know exactly which branches to move back and where to, instead of guessing from the messages in the reflog. It
+//! refuses to undo an operation if a branch it moved was moved again since, for instance by a git command in a
+//! terminal.
+//!
+//! The journal is only ever appended to, even undoing an operation is recorded as an entry of its own. Once it grew
+//! to [`ROTATE_AT_BYTES`] it's rotated into an archive, of which the latest [`MAX_ARCHIVES`] are kept, and the
+//! latest entries are read from its end, so neither appending nor undoing slows down as the journal grows.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_project::{access::WorktreeWritePermission, Project};
+use serde::{Deserialize, Serialize};
+
+use crate::{entry::OperationKind, state::OplogHandle, OplogExt};
+
+/// The file in the GitButler directory of a project that the journal is appended to, one JSON object per line.
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// The size in bytes at which the journal is moved into an archive, named `journal.<n>.jsonl` with `n` counting up,
+/// before appending to it.
+pub const ROTATE_AT_BYTES: u64 = 1024 * 1024;
+
+/// The amount of archived journals to keep, older ones are deleted.
+pub const MAX_ARCHIVES: usize = 4;
+
+/// Appending reads the position of the last entry first, which must stay the last meanwhile.
+static WRITES: Mutex<()> = Mutex::new(());
+
+/// The branches of the worktree that are recorded, which includes the workspace branch.
+const RECORDED_REFS: &str = "refs/heads/*";
+
+/// A reference that was moved by an operation, with `None` meaning it didn't exist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefUpdate {
+    /// The full name of the reference, like `refs/heads/gitbutler/workspace`.
+    pub name: String,
+    #[serde(default, with = "gitbutler_serde::oid_opt")]
+    pub old: Option<git2::Oid>,
+    #[serde(default, with = "gitbutler_serde::oid_opt")]
+    pub new: Option<git2::Oid>,
+}
+
+/// An operation recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// When the operation finished, in seconds since the Unix epoch.
+    pub at: i64,
+    pub operation: OperationKind,
+    /// The snapshot with the state from right before the operation, if one was taken.
+    #[serde(default, with = "gitbutler_serde::oid_opt")]
+    pub snapshot: Option<git2::Oid>,
+    /// What the operation was called with, like the message of a commit, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+    /// The references the operation moved.
+    pub ref_updates: Vec<RefUpdate>,
+    /// Why the operation failed, if it did. It may still have moved references before failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The position of the entry in the journal that this entry undid, if it's an undo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<usize>,
+    /// The position of the entry in the journal, counting from `0` even once older entries were rotated away.
+    ///
+    /// It's only stored with entries recorded since the journal is rotated, and set when reading older ones, whose
+    /// position is their index in the journal.
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+/// Where the branches of a project pointed at one point in time, to be compared with [`record()`].
+///
+/// The default state is for operations that can't move branches, like changing settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefState {
+    refs: BTreeMap<String, git2::Oid>,
+    oplog_head: Option<git2::Oid>,
+}
+
+impl RefState {
+    /// Read where the branches of `project` point to now.
+    pub fn capture(project: &Project) -> Result<Self> {
+        let repo = git2::Repository::open(&project.path)?;
+        let mut refs = BTreeMap::new();
+        for reference in repo.references_glob(RECORDED_REFS)? {
+            let reference = reference?;
+            if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+                refs.insert(name.to_owned(), target);
+            }
+        }
+        Ok(RefState {
+            refs,
+            oplog_head: OplogHandle::new(&project.gb_dir()).oplog_head()?,
+        })
+    }
+}
+
+/// Record that `operation`, called with `parameters`, moved the branches of `project` from `before` to `after`,
+/// and failed with `error` if set. Return the entry.
+///
+/// A snapshot taken in between is taken to be the state from before the operation.
+pub fn record(
+    project: &Project,
+    operation: OperationKind,
+    parameters: impl IntoIterator<Item = (&'static str, String)>,
+    before: &RefState,
+    after: &RefState,
+    error: Option<&anyhow::Error>,
+) -> Result<JournalEntry> {
+    let mut entry = JournalEntry {
+        parameters: parameters
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect(),
+        error: error.map(|err| format!("{err:#}")),
+        ..JournalEntry::new(operation, before, after)
+    };
+    append(project, &mut entry)?;
+    Ok(entry)
+}
+
+/// Return all entries of the journal of `project` that weren't rotated away, oldest first, which makes for an audit
+/// trail of the operations performed on it.
+pub fn entries(project: &Project) -> Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for path in files_newest_first(project)?.into_iter().rev() {
+        entries.extend(read(&path)?);
+    }
+    let mut next = 0;
+    for entry in &mut entries {
+        let position = *entry.position.get_or_insert(next);
+        next = position + 1;
+    }
+    Ok(entries)
+}
+
+/// Return the latest `limit` entries of the journal of `project`, or all of them if `None`, newest first.
+///
+/// Only as much of the journal as needed is read, from its end.
+pub fn latest_entries(project: &Project, limit: Option<usize>) -> Result<Vec<JournalEntry>> {
+    newest_first(project)?
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Undo the latest operation in the journal of `project` that moved branches or took a snapshot and wasn't undone
+/// yet, by restoring the snapshot from right before it and moving back the branches it moved. Return the entry recording the undo.
+///
+/// Fails if any of these branches was moved since, as undoing would then discard changes that weren't made by
+/// the operation. Undoing again undoes the operation before.
+pub fn undo_last_operation(
+    project: &Project,
+    perm: &mut WorktreeWritePermission,
+) -> Result<JournalEntry> {
+    let mut undone = BTreeSet::new();
+    let mut undoable = None;
+    for entry in newest_first(project)? {
+        let entry = entry?;
+        let position = entry.position.unwrap_or_default();
+        if entry.is_undoable() && !undone.contains(&position) {
+            undoable = Some((position, entry));
+            break;
+        }
+        undone.extend(entry.undoes);
+    }
+    let Some((index, entry)) = undoable else {
+        bail!("There is no operation to undo");
+    };
+
+    let repo = git2::Repository::open(&project.path)?;
+    for update in &entry.ref_updates {
+        let current = match repo.refname_to_id(&update.name) {
+            Ok(id) => Some(id),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if current != update.new {
+            bail!(
+                "Can't undo '{}' as '{}' was moved since",
+                entry.operation,
+                update.name
+            );
+        }
+    }
+
+    let before = RefState::capture(project)?;
+    if let Some(snapshot) = entry.snapshot {
+        project.restore_snapshot(snapshot, perm)?;
+    }
+    let message = format!("GitButler: undo {}", entry.operation);
+    for update in &entry.ref_updates {
+        match update.old {
+            Some(old) => {
+                let moved_by_restore = repo.refname_to_id(&update.name).ok() == Some(old);
+                if !moved_by_restore {
+                    repo.reference(&update.name, old, true, &message)
+                        .with_context(|| format!("failed to move '{}' back", update.name))?;
+                }
+            }
+            None => {
+                if let Ok(mut reference) = repo.find_reference(&update.name) {
+                    reference.delete()?;
+                }
+            }
+        }
+    }
+    let after = RefState::capture(project)?;
+
+    let mut entry = JournalEntry {
+        undoes: Some(index),
+        ..JournalEntry::new(OperationKind::UndoOperation, &before, &after)
+    };
+    append(project, &mut entry)?;
+    Ok(entry)
+}
+
+impl JournalEntry {
+    /// Whether there is anything to undo about this entry, which undos themselves don't have, nor failed
+    /// operations that didn't get to move any branch.
+    fn is_undoable(&self) -> bool {
+        self.undoes.is_none()
+            && (!self.ref_updates.is_empty() || (self.snapshot.is_some() && self.error.is_none()))
+    }
+
+    fn new(operation: OperationKind, before: &RefState, after: &RefState) -> Self {
+        let names: BTreeSet<_> = before.refs.keys().chain(after.refs.keys()).collect();
+        let ref_updates = names
+            .into_iter()
+            .filter_map(|name| {
+                let (old, new) = (
+                    before.refs.get(name).copied(),
+                    after.refs.get(name).copied(),
+                );
+                (old != new).then(|| RefUpdate {
+                    name: name.clone(),
+                    old,
+                    new,
+                })
+            })
+            .collect();
+        JournalEntry {
+            at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs() as i64),
+            operation,
+            snapshot: after
+                .oplog_head
+                .filter(|head| Some(*head) != before.oplog_head),
+            parameters: BTreeMap::new(),
+            ref_updates,
+            error: None,
+            undoes: None,
+            position: None,
+        }
+    }
+}
+
+/// Append `entry` to the journal of `project` as its last entry, after rotating the journal if it's due, and set its
+/// position.
+fn append(project: &Project, entry: &mut JournalEntry) -> Result<()> {
+    let _writing = WRITES.lock().unwrap_or_else(|err| err.into_inner());
+    std::fs::create_dir_all(project.gb_dir())?;
+    let path = project.gb_dir().join(JOURNAL_FILE);
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() >= ROTATE_AT_BYTES) {
+        rotate(project)?;
+    }
+    entry.position = Some(match newest_first(project)?.next().transpose()? {
+        Some(last) => last.position.unwrap_or_default() + 1,
+        None => 0,
+    });
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Move the journal of `project` into a new archive, and delete the archives beyond [`MAX_ARCHIVES`].
+fn rotate(project: &Project) -> Result<()> {
+    let archives = archives(project)?;
+    let next = archives.first().map_or(1, |(n, _)| n + 1);
+    let archive = project.gb_dir().join(format!("journal.{next}.jsonl"));
+    std::fs::rename(project.gb_dir().join(JOURNAL_FILE), &archive)
+        .with_context(|| format!("failed to rotate the journal into '{}'", archive.display()))?;
+    for (_, path) in archives.iter().skip(MAX_ARCHIVES.saturating_sub(1)) {
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Return the archived journals of `project` along with their number, newest first.
+fn archives(project: &Project) -> Result<Vec<(u64, PathBuf)>> {
+    let mut archives = Vec::new();
+    let dir_entries = match std::fs::read_dir(project.gb_dir()) {
+        Ok(dir_entries) => dir_entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(archives),
+        Err(err) => return Err(err.into()),
+    };
+    for dir_entry in dir_entries {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name();
+        let number = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("journal."))
+            .and_then(|name| name.strip_suffix(".jsonl"))
+            .and_then(|number| number.parse().ok());
+        if let Some(number) = number {
+            archives.push((number, dir_entry.path()));
+        }
+    }
+    archives.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(archives)
+}
+
+/// Return the files of the journal of `project` that exist, the journal first and then its archives, newest first.
+fn files_newest_first(project: &Project) -> Result<Vec<PathBuf>> {
+    let journal = project.gb_dir().join(JOURNAL_FILE);
+    Ok(journal
+        .exists()
+        .then_some(journal)
+        .into_iter()
+        .chain(archives(project)?.into_iter().map(|(_, path)| path))
+        .collect())
+}
+
+/// Return the entries of the journal of `project`, newest first, with their position set, read from the end of
+/// each file lazily and skipping lines that can't be parsed.
+fn newest_first(project: &Project) -> Result<impl Iterator<Item = Result<JournalEntry>>> {
+    let files = files_newest_first(project)?;
+    let mut lines = files
+        .clone()
+        .into_iter()
+        .flat_map(|path| match TailLines::open(&path) {
+            Ok(lines) => Box::new(lines.map(move |line| {
+                line.with_context(|| format!("failed to read '{}'", path.display()))
+            })) as Box<dyn Iterator<Item = Result<Vec<u8>>>>,
+            Err(err) => Box::new(std::iter::once(Err(anyhow::Error::from(err)))),
+        });
+    // Entries without a position precede the one after them. If even the last one has none, the journal wasn't
+    // appended to since positions are stored, and it has to be counted.
+    let mut next_position: Option<usize> = None;
+    Ok(std::iter::from_fn(move || loop {
+        let line = match lines.next()? {
+            Ok(line) => line,
+            Err(err) => return Some(Err(err)),
+        };
+        let Ok(mut entry) = serde_json::from_slice::<JournalEntry>(&line) else {
+            continue;
+        };
+        let position = match (entry.position, next_position) {
+            (Some(position), _) => position,
+            (None, Some(next)) => next.saturating_sub(1),
+            (None, None) => match files
+                .iter()
+                .map(|path| read(path).map(|entries| entries.len()))
+                .sum::<Result<usize>>()
+            {
+                Ok(count) => count.saturating_sub(1),
+                Err(err) => return Some(Err(err)),
+            },
+        };
+        entry.position = Some(position);
+        next_position = Some(position);
+        return Some(Ok(entry));
+    }))
+}
+
+/// Read all entries, skipping lines that can't be parsed.
+fn read(path: &Path) -> Result<Vec<JournalEntry>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// The non-empty lines of a file, last first, read in blocks from its end.
+struct TailLines {
+    file: File,
+    /// Where in the file `pending` starts.
+    offset: u64,
+    /// The bytes from `offset` that weren't returned as line yet.
+    pending: Vec<u8>,
+}
+
+impl TailLines {
+    const BLOCK_SIZE: u64 = 64 * 1024;
+
+    fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(TailLines {
+            offset: file.metadata()?.len(),
+            file,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl Iterator for TailLines {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(newline) = self.pending.iter().rposition(|byte| *byte == b'\n') {
+                let line = self.pending.split_off(newline + 1);
+                self.pending.truncate(newline);
+                if line.is_empty() {
+                    continue;
+                }
+                return Some(Ok(line));
+            }
+            if self.offset == 0 {
+                return (!self.pending.is_empty()).then(|| Ok(std::mem::take(&mut self.pending)));
+            }
+            let len = Self::BLOCK_SIZE.min(self.offset);
+            self.offset -= len;
+            let mut block = vec![0; len as usize];
+            if let Err(err) = self
+                .file
+                .seek(SeekFrom::Start(self.offset))
+                .and_then(|_| self.file.read_exact(&mut block))
+            {
+                return Some(Err(err));
+            }
+            block.append(&mut self.pending);
+            self.pending = block;
+        }
+    }
+}
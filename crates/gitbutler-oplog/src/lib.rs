@@ -1,10 +1,31 @@
+pub mod activity;
+pub mod bisect;
+pub mod blob_store;
+pub mod bookmarks;
+pub mod deltas;
+pub mod encryption;
 pub mod entry;
+pub mod eol;
+pub mod export;
+pub mod file_history;
+pub mod heartbeat;
+pub mod import;
+pub mod journal;
+pub mod meta_ref;
 mod oplog;
 pub use oplog::OplogExt;
+pub mod reconstruct;
 pub mod reflog;
+pub mod retention;
+pub mod secrets;
+pub mod share;
 mod snapshot;
 pub use snapshot::SnapshotExt;
 mod state;
+pub mod tombstones;
+pub mod usage;
+pub mod verify;
+pub mod wip;
 
 /// The name of the file holding our state, useful for watching for changes.
 pub const OPLOG_FILE_NAME: &str = "operations-log.toml";
@@ -0,0 +1,95 @@
+//! Anchor the oplog under [`OPLOG_REF`], or the [reference of a sub-project](oplog_ref()), so snapshots stay
+//! reachable when `git gc` runs.
+//!
+//! The [reflog entries](crate::reflog) alone don't protect snapshots from cleanup tooling that runs
+//! `git reflog expire --expire=now --all` before collecting garbage, which a reference does.
+use anyhow::{bail, Result};
+use gitbutler_project::{access::WorktreeWritePermission, Project};
+use serde::Serialize;
+
+use crate::state::OplogHandle;
+
+/// The reference pointing to the oplog head.
+pub const OPLOG_REF: &str = "refs/gitbutler/oplog";
+
+/// The name of the reference pointing to the oplog head of `project`, which is [`OPLOG_REF`] unless it's a
+/// [sub-project](Project::namespaced_ref).
+pub fn oplog_ref(project: &Project) -> String {
+    project.namespaced_ref(OPLOG_REF)
+}
+
+/// What was done by [`repair_meta_ref()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetaRefRepair {
+    /// There are no snapshots yet, so there is nothing to anchor.
+    NoOplog,
+    /// The reference already pointed to the oplog head.
+    Intact,
+    /// The reference was missing or pointed elsewhere, and now points to the oplog head again.
+    Recreated,
+}
+
+/// Whether the [oplog reference](oplog_ref()) anchors the oplog, as returned by [`meta_ref_status()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetaRefStatus {
+    /// There are no snapshots yet, so there is nothing to anchor.
+    NoOplog,
+    /// The reference points to the oplog head.
+    Intact,
+    /// The reference is missing.
+    Missing,
+    /// The reference points to another commit than the oplog head.
+    Stale,
+}
+
+/// Point the [oplog reference](oplog_ref()) of `project` in `repo` to `oplog_head`.
+pub(crate) fn set_meta_ref(
+    project: &Project,
+    repo: &git2::Repository,
+    oplog_head: git2::Oid,
+) -> Result<()> {
+    repo.reference(&oplog_ref(project), oplog_head, true, "oplog: update head")?;
+    Ok(())
+}
+
+/// Tell whether the [oplog reference](oplog_ref()) points to the oplog head of `project`, without changing it.
+pub fn meta_ref_status(project: &Project) -> Result<MetaRefStatus> {
+    let Some(oplog_head) = OplogHandle::new(&project.gb_dir()).oplog_head()? else {
+        return Ok(MetaRefStatus::NoOplog);
+    };
+    let repo = git2::Repository::open(&project.path)?;
+    Ok(match repo.find_reference(&oplog_ref(project)) {
+        Ok(reference) if reference.target() == Some(oplog_head) => MetaRefStatus::Intact,
+        Ok(_) => MetaRefStatus::Stale,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => MetaRefStatus::Missing,
+        Err(err) => return Err(err.into()),
+    })
+}
+
+/// Point the [oplog reference](oplog_ref()) to the oplog head of `project` as recorded in its `.git/gitbutler`
+/// directory, in case it was deleted or changed.
+///
+/// Fails if the oplog head itself is gone, as happens if it was garbage-collected while unreferenced.
+pub fn repair_meta_ref(
+    project: &Project,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<MetaRefRepair> {
+    let Some(oplog_head) = OplogHandle::new(&project.gb_dir()).oplog_head()? else {
+        return Ok(MetaRefRepair::NoOplog);
+    };
+    let repo = git2::Repository::open(&project.path)?;
+    if repo.find_commit(oplog_head).is_err() {
+        bail!("The oplog head {oplog_head} isn't in the repository anymore, so it can't be referenced again");
+    }
+    let current = repo
+        .find_reference(&oplog_ref(project))
+        .ok()
+        .and_then(|reference| reference.target());
+    if current == Some(oplog_head) {
+        return Ok(MetaRefRepair::Intact);
+    }
+    set_meta_ref(project, &repo, oplog_head)?;
+    Ok(MetaRefRepair::Recreated)
+}
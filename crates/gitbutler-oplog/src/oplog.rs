@@ -1,15 +1,22 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, BTreeSet, HashMap},
     fs,
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
     str::{from_utf8, FromStr},
     time::Duration,
 };
 
+use crate::activity::{self, ActivitySession, ActivitySummary, SnapshotActivity};
+use crate::bookmarks;
+use crate::deltas;
+use crate::heartbeat;
+use crate::meta_ref::set_meta_ref;
 use crate::reflog::ReflogCommits;
+use crate::secrets::{self, SecretScanner};
 
 use super::{
-    entry::{OperationKind, Snapshot, SnapshotDetails, Trailer},
+    entry::{FileVersion, OperationKind, Snapshot, SnapshotDetails, Trailer},
     reflog::set_reference_to_oplog,
     state::OplogHandle,
 };
@@ -86,6 +93,15 @@ pub trait OplogExt {
         perm: &mut WorktreeWritePermission,
     ) -> Result<git2::Oid>;
 
+    /// Like [`create_snapshot`](Self::create_snapshot), but as if it was created at `at` seconds since the Unix
+    /// epoch, to record a history at times other than the current one, like a scripted timeline in tests.
+    fn create_snapshot_at(
+        &self,
+        details: SnapshotDetails,
+        at: i64,
+        perm: &mut WorktreeWritePermission,
+    ) -> Result<git2::Oid>;
+
     /// Lists the snapshots that have been created for the given repository, up to the given limit,
     /// and with the most recent snapshot first, and at the end of the vec.
     ///
@@ -140,8 +156,47 @@ pub trait OplogExt {
     /// This is useful to show what has changed in this particular snapshot
     fn snapshot_diff(&self, sha: git2::Oid) -> Result<HashMap<PathBuf, FileDiff>>;
 
+    /// Returns the distinct states of the file at the worktree-relative `path` within the most recent `limit` snapshots,
+    /// oldest first.
+    ///
+    /// Consecutive snapshots in which the file didn't change are merged into one version, and versions from before the
+    /// file first appeared are skipped, which makes this suitable for playing back how a file evolved.
+    fn file_versions(&self, path: &Path, limit: usize) -> Result<Vec<FileVersion>>;
+
+    /// Summarizes the changes to the working directory recorded by all snapshots created within `range`,
+    /// given in seconds since the Unix epoch.
+    ///
+    /// Editor [heartbeats](heartbeat::record_heartbeat()) within `range` extend sessions, so reading counts
+    /// as active time. Note that snapshots are taken per operation and periodically while editing, so line
+    /// counts and active time are approximations.
+    fn activity_summary(&self, range: Range<i64>) -> Result<ActivitySummary>;
+
+    /// Returns the [sessions](activity::ActivitySession) of the [activity summary](Self::activity_summary()) of
+    /// `range`, oldest first. If `branch` is set, only the sessions recorded on the branch with this short name,
+    /// like `main`, are returned.
+    fn list_sessions(
+        &self,
+        range: Range<i64>,
+        branch: Option<&str>,
+    ) -> Result<Vec<ActivitySession>>;
+
+    /// Returns the paths of all files that changed in the working directory during the
+    /// [session](activity::ActivitySession) that started at `session_start` seconds since the Unix epoch,
+    /// or `None` if no session started then.
+    fn session_changed_paths(&self, session_start: i64) -> Result<Option<Vec<PathBuf>>>;
+
     /// Gets the sha of the last snapshot commit if present.
     fn oplog_head(&self) -> Result<Option<git2::Oid>>;
+
+    /// Make `snapshot_commit_id` the new head of the oplog, which is useful when the history was obtained
+    /// from elsewhere, like a remote it was backed up to.
+    ///
+    /// Fails if `snapshot_commit_id` doesn't look like an oplog commit.
+    fn set_oplog_head(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        perm: &mut WorktreeWritePermission,
+    ) -> Result<()>;
 }
 
 impl OplogExt for Project {
@@ -155,7 +210,7 @@ impl OplogExt for Project {
         details: SnapshotDetails,
         perm: &mut WorktreeWritePermission,
     ) -> Result<git2::Oid> {
-        commit_snapshot(self, snapshot_tree_id, details, perm)
+        commit_snapshot(self, snapshot_tree_id, details, None, perm)
     }
 
     #[instrument(skip(self, details, perm), err(Debug))]
@@ -165,7 +220,18 @@ impl OplogExt for Project {
         perm: &mut WorktreeWritePermission,
     ) -> Result<git2::Oid> {
         let tree_id = prepare_snapshot(self, perm.read_permission())?;
-        commit_snapshot(self, tree_id, details, perm)
+        commit_snapshot(self, tree_id, details, None, perm)
+    }
+
+    #[instrument(skip(self, details, perm), err(Debug))]
+    fn create_snapshot_at(
+        &self,
+        details: SnapshotDetails,
+        at: i64,
+        perm: &mut WorktreeWritePermission,
+    ) -> Result<git2::Oid> {
+        let tree_id = prepare_snapshot(self, perm.read_permission())?;
+        commit_snapshot(self, tree_id, details, Some(at), perm)
     }
 
     #[instrument(skip(self), err(Debug))]
@@ -228,43 +294,19 @@ impl OplogExt for Project {
 
             if let Some(parent_id) = first_parent {
                 // Get tree id from cache or calculate it
-
-                let mut files_changed = Vec::new();
-                let mut resource_cache = repo.diff_resource_cache_for_tree_diff()?;
-                let (mut lines_added, mut lines_removed) = (0, 0);
                 let parent_tree = get_workdir_tree(&mut wd_trees_cache, parent_id, &repo)?;
-                parent_tree
-                    .changes()?
-                    .options(|opts| {
-                        opts.track_rewrites(None).track_path();
-                    })
-                    .for_each_to_obtain_tree(&wd_tree, |change| -> Result<_> {
-                        match change {
-                            Change::Addition { location, .. } => {
-                                files_changed.push(gix::path::from_bstr(location).into_owned());
-                            }
-                            Change::Deletion { .. }
-                            | Change::Modification { .. }
-                            | Change::Rewrite { .. } => {}
-                        }
-                        if let Some(counts) = change
-                            .diff(&mut resource_cache)
-                            .ok()
-                            .and_then(|mut platform| platform.line_counts().ok().flatten())
-                        {
-                            lines_added += u64::from(counts.insertions);
-                            lines_removed += u64::from(counts.removals);
-                        }
-                        resource_cache.clear_resource_cache_keep_allocation();
-
-                        Ok(gix::object::tree::diff::Action::Continue)
-                    })?;
+                let WorkdirDiffStats {
+                    added_paths: files_changed,
+                    lines_added,
+                    lines_removed,
+                    ..
+                } = workdir_diff_stats(&repo, &parent_tree, &wd_tree)?;
 
                 snapshots.push(Snapshot {
                     commit_id,
                     details,
-                    lines_added: lines_added as usize,
-                    lines_removed: lines_removed as usize,
+                    lines_added,
+                    lines_removed,
                     files_changed,
                     created_at: commit_time,
                 });
@@ -337,15 +379,241 @@ impl OplogExt for Project {
         Ok(hunks)
     }
 
+    #[instrument(skip(self), err(Debug))]
+    fn file_versions(&self, path: &Path, limit: usize) -> Result<Vec<FileVersion>> {
+        let repo = gitbutler_command_context::gix_repository_for_merging(self.path.as_path())?;
+        let Some(oplog_head) = OplogHandle::new(&self.gb_dir()).oplog_head()? else {
+            return Ok(vec![]);
+        };
+
+        // Newest first while traversing, reversed at the end.
+        let mut versions: Vec<FileVersion> = Vec::new();
+        let mut wd_trees_cache: HashMap<gix::ObjectId, gix::ObjectId> = HashMap::new();
+        let mut num_snapshots = 0;
+        for commit_info in git2_to_gix_object_id(oplog_head)
+            .attach(&repo)
+            .ancestors()
+            .all()?
+        {
+            if num_snapshots == limit {
+                break;
+            }
+            let commit_id = commit_info?.id();
+            let commit = commit_id.object()?.into_commit();
+            if commit.parent_ids().count() > 1 {
+                break;
+            }
+            if commit
+                .tree()?
+                .lookup_entry_by_path("virtual_branches.toml")?
+                .is_none()
+            {
+                continue;
+            }
+            num_snapshots += 1;
+
+            let wd_tree = get_workdir_tree(&mut wd_trees_cache, commit_id, &repo)?;
+            let entry = wd_tree.lookup_entry_by_path(path)?;
+            let version = FileVersion {
+                snapshot_id: gix_to_git2_oid(commit_id),
+                created_at: gix_time_to_git2(commit.time()?),
+                blob_id: entry
+                    .as_ref()
+                    .map(|entry| gix_to_git2_oid(entry.id().detach())),
+                mode: entry
+                    .as_ref()
+                    .and_then(|entry| file_mode(entry.mode().kind())),
+            };
+            match versions.last_mut() {
+                Some(newer) if (newer.blob_id, newer.mode) == (version.blob_id, version.mode) => {
+                    *newer = version
+                }
+                _ => versions.push(version),
+            }
+        }
+
+        if versions
+            .last()
+            .is_some_and(|oldest| oldest.blob_id.is_none())
+        {
+            versions.pop();
+        }
+        versions.reverse();
+        Ok(versions)
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    fn activity_summary(&self, range: Range<i64>) -> Result<ActivitySummary> {
+        let mut summary = activity::summarize(
+            &snapshot_activities(self, range.clone())?,
+            heartbeat::heartbeats(self, range.clone())?,
+            bookmarks::list_bookmarks(self, range.clone())?,
+        );
+        let deltas = deltas::list_deltas(self, i64::MIN..range.end, None, None)?;
+        activity::assign_branches(&mut summary.sessions, &deltas);
+        Ok(summary)
+    }
+
+    fn list_sessions(
+        &self,
+        range: Range<i64>,
+        branch: Option<&str>,
+    ) -> Result<Vec<ActivitySession>> {
+        let mut sessions = self.activity_summary(range)?.sessions;
+        sessions.retain(|session| {
+            branch.is_none_or(|branch| session.branch.as_deref() == Some(branch))
+        });
+        Ok(sessions)
+    }
+
+    fn session_changed_paths(&self, session_start: i64) -> Result<Option<Vec<PathBuf>>> {
+        // Include what happened right before, to learn if the session actually started earlier.
+        let range = session_start - activity::SESSION_GAP_SECONDS..i64::MAX;
+        let snapshots = snapshot_activities(self, range.clone())?;
+        let summary =
+            activity::summarize(&snapshots, heartbeat::heartbeats(self, range)?, Vec::new());
+        let Some(session) = summary
+            .sessions
+            .iter()
+            .find(|session| session.start == session_start)
+        else {
+            return Ok(None);
+        };
+        let paths: BTreeSet<_> = snapshots
+            .iter()
+            .filter(|snapshot| {
+                (session.start..=session.end).contains(&snapshot.created_at.seconds())
+            })
+            .flat_map(|snapshot| snapshot.changed_paths.iter().cloned())
+            .collect();
+        Ok(Some(paths.into_iter().collect()))
+    }
+
     /// Gets the sha of the last snapshot commit if present.
     fn oplog_head(&self) -> Result<Option<git2::Oid>> {
         let oplog_state = OplogHandle::new(&self.gb_dir());
         oplog_state.oplog_head()
     }
+
+    fn set_oplog_head(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        _perm: &mut WorktreeWritePermission,
+    ) -> Result<()> {
+        let repo = git2::Repository::open(self.path.as_path())?;
+        let snapshot_commit = repo.find_commit(snapshot_commit_id)?;
+        if snapshot_commit
+            .tree()?
+            .get_name("virtual_branches.toml")
+            .is_none()
+        {
+            bail!("Commit {snapshot_commit_id} isn't an oplog commit");
+        }
+
+        let oplog_state = OplogHandle::new(&self.gb_dir());
+        oplog_state.set_oplog_head(snapshot_commit_id)?;
+        set_meta_ref(self, &repo, snapshot_commit_id)?;
+
+        // Without a default target there is nothing to anchor the reflog to yet, which is common
+        // for freshly cloned repositories. The reflog will be updated with the next snapshot.
+        match ReflogCommits::new(self) {
+            Ok(reflog_commits) => set_reference_to_oplog(&self.path, reflog_commits)?,
+            Err(err) => {
+                tracing::warn!(?err, "Could not protect oplog head from garbage collection")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collect what each snapshot created within `range` changed in the working directory, newest first.
+pub(crate) fn snapshot_activities(
+    project: &Project,
+    range: Range<i64>,
+) -> Result<Vec<SnapshotActivity>> {
+    let repo = gitbutler_command_context::gix_repository_for_merging(project.path.as_path())?;
+    let Some(oplog_head) = OplogHandle::new(&project.gb_dir()).oplog_head()? else {
+        return Ok(Vec::new());
+    };
+
+    let mut snapshots = Vec::new();
+    let mut wd_trees_cache: HashMap<gix::ObjectId, gix::ObjectId> = HashMap::new();
+    for commit_info in git2_to_gix_object_id(oplog_head)
+        .attach(&repo)
+        .ancestors()
+        .all()?
+    {
+        let commit_id = commit_info?.id();
+        let commit = commit_id.object()?.into_commit();
+        let mut parents = commit.parent_ids();
+        let (first_parent, second_parent) = (parents.next(), parents.next());
+        if second_parent.is_some() {
+            break;
+        }
+        if commit
+            .tree()?
+            .lookup_entry_by_path("virtual_branches.toml")?
+            .is_none()
+        {
+            continue;
+        }
+        let created_at = gix_time_to_git2(commit.time()?);
+        if created_at.seconds() < range.start {
+            break;
+        }
+        if !range.contains(&created_at.seconds()) {
+            continue;
+        }
+        let snapshot_id = gix_to_git2_oid(commit_id);
+        let details = commit
+            .message_raw()?
+            .to_str()
+            .ok()
+            .and_then(|msg| SnapshotDetails::from_str(msg).ok());
+
+        snapshots.push(match first_parent {
+            Some(parent_id) => {
+                let wd_tree = get_workdir_tree(&mut wd_trees_cache, commit_id, &repo)?;
+                let parent_tree = get_workdir_tree(&mut wd_trees_cache, parent_id, &repo)?;
+                let stats = workdir_diff_stats(&repo, &parent_tree, &wd_tree)?;
+                SnapshotActivity {
+                    snapshot_id,
+                    created_at,
+                    details,
+                    changed_paths: stats.changed_paths,
+                    lines_added: stats.lines_added,
+                    lines_removed: stats.lines_removed,
+                }
+            }
+            // The very first snapshot has nothing to compare to.
+            None => SnapshotActivity {
+                snapshot_id,
+                created_at,
+                details,
+                changed_paths: Vec::new(),
+                lines_added: 0,
+                lines_removed: 0,
+            },
+        });
+    }
+    Ok(snapshots)
+}
+
+/// Return the mode of a file in a snapshot tree, or `None` if the entry isn't a file.
+fn file_mode(kind: gix::object::tree::EntryKind) -> Option<gitbutler_diff::FileMode> {
+    use gitbutler_diff::FileMode;
+    use gix::object::tree::EntryKind;
+    match kind {
+        EntryKind::Blob => Some(FileMode::Regular),
+        EntryKind::BlobExecutable => Some(FileMode::Executable),
+        EntryKind::Link => Some(FileMode::Symlink),
+        EntryKind::Commit => Some(FileMode::Submodule),
+        EntryKind::Tree => None,
+    }
 }
 
 /// Get a tree of the working dir (applied branches merged)
-fn get_workdir_tree<'a>(
+pub(crate) fn get_workdir_tree<'a>(
     wd_trees_cache: &mut HashMap<gix::ObjectId, gix::ObjectId>,
     commit_id: impl Into<gix::ObjectId>,
     repo: &'a gix::Repository,
@@ -362,6 +630,63 @@ fn get_workdir_tree<'a>(
     Ok(repo.find_tree(id)?)
 }
 
+/// What changed between two trees of the working directory.
+struct WorkdirDiffStats {
+    /// The paths of files that were added.
+    added_paths: Vec<PathBuf>,
+    /// The paths of all files that were added, modified, deleted or renamed.
+    changed_paths: Vec<PathBuf>,
+    lines_added: usize,
+    lines_removed: usize,
+}
+
+fn workdir_diff_stats(
+    repo: &gix::Repository,
+    old_tree: &gix::Tree<'_>,
+    new_tree: &gix::Tree<'_>,
+) -> Result<WorkdirDiffStats> {
+    let mut added_paths = Vec::new();
+    let mut changed_paths = Vec::new();
+    let mut resource_cache = repo.diff_resource_cache_for_tree_diff()?;
+    let (mut lines_added, mut lines_removed) = (0, 0);
+    old_tree
+        .changes()?
+        .options(|opts| {
+            opts.track_rewrites(None).track_path();
+        })
+        .for_each_to_obtain_tree(new_tree, |change| -> Result<_> {
+            match change {
+                Change::Addition { location, .. } => {
+                    let path = gix::path::from_bstr(location).into_owned();
+                    added_paths.push(path.clone());
+                    changed_paths.push(path);
+                }
+                Change::Deletion { location, .. }
+                | Change::Modification { location, .. }
+                | Change::Rewrite { location, .. } => {
+                    changed_paths.push(gix::path::from_bstr(location).into_owned());
+                }
+            }
+            if let Some(counts) = change
+                .diff(&mut resource_cache)
+                .ok()
+                .and_then(|mut platform| platform.line_counts().ok().flatten())
+            {
+                lines_added += u64::from(counts.insertions);
+                lines_removed += u64::from(counts.removals);
+            }
+            resource_cache.clear_resource_cache_keep_allocation();
+
+            Ok(gix::object::tree::diff::Action::Continue)
+        })?;
+    Ok(WorkdirDiffStats {
+        added_paths,
+        changed_paths,
+        lines_added: lines_added as usize,
+        lines_removed: lines_removed as usize,
+    })
+}
+
 fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> Result<git2::Oid> {
     let worktree_dir = ctx.path.as_path();
     let repo = git2::Repository::open(worktree_dir)?;
@@ -394,14 +719,27 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
     // go through all virtual branches and create a subtree for each with the tree and any commits encoded
     let mut branches_tree_builder = repo.treebuilder(None)?;
     let mut head_tree_ids = Vec::new();
+    let scanner = SecretScanner::for_project(ctx);
+    let target_tree = default_target_commit.tree()?;
+    let mut redacted_files = BTreeSet::new();
 
     for stack in vb_state.list_stacks_in_workspace()? {
         head_tree_ids.push(stack.tree);
 
+        // secrets in uncommitted changes must not be kept around in the oplog
+        let (stack_tree_id, redacted) = secrets::redact_tree(
+            &repo,
+            &scanner,
+            ctx.secret_redaction,
+            &target_tree,
+            &repo.find_tree(stack.tree)?,
+        )?;
+        redacted_files.extend(redacted);
+
         // commits in virtual branches (tree and commit data)
         // calculate all the commits between branch.head and the target and codify them
         let mut branch_tree_builder = repo.treebuilder(None)?;
-        branch_tree_builder.insert("tree", stack.tree, FileMode::Tree.into())?;
+        branch_tree_builder.insert("tree", stack_tree_id, FileMode::Tree.into())?;
 
         // let's get all the commits between the branch head and the target
         let mut revwalk = repo.revwalk()?;
@@ -473,14 +811,29 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
     let branch_tree_id = branches_tree_builder.write()?;
     tree_builder.insert("virtual_branches", branch_tree_id, FileMode::Tree.into())?;
 
+    if !redacted_files.is_empty() {
+        let paths = redacted_files
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect::<String>();
+        let redacted_files_blob_id = repo.blob(paths.as_bytes())?;
+        tree_builder.insert(
+            secrets::REDACTED_FILES_ENTRY,
+            redacted_files_blob_id,
+            FileMode::Blob.into(),
+        )?;
+    }
+
     let tree_id = tree_builder.write()?;
     Ok(tree_id)
 }
 
+/// Commit `snapshot_tree_id` as the new head of the oplog, created `at` seconds since the Unix epoch if set, or now.
 fn commit_snapshot(
     ctx: &Project,
     snapshot_tree_id: git2::Oid,
     details: SnapshotDetails,
+    at: Option<i64>,
     _exclusive_access: &mut WorktreeWritePermission,
 ) -> Result<git2::Oid> {
     let repo = git2::Repository::open(ctx.path.as_path())?;
@@ -492,8 +845,16 @@ fn commit_snapshot(
         .and_then(|head_id| repo.find_commit(head_id).ok());
 
     // Construct a new commit
-    let committer = gitbutler_repo::signature(SignaturePurpose::Committer)?;
-    let author = gitbutler_repo::signature(SignaturePurpose::Author)?;
+    let (committer, author) = match at {
+        Some(at) => (
+            gitbutler_repo::signature_at(at)?,
+            gitbutler_repo::signature_at(at)?,
+        ),
+        None => (
+            gitbutler_repo::signature(SignaturePurpose::Committer)?,
+            gitbutler_repo::signature(SignaturePurpose::Author)?,
+        ),
+    };
     let parents = oplog_head_commit
         .as_ref()
         .map(|head| vec![head])
@@ -508,6 +869,7 @@ fn commit_snapshot(
     )?;
 
     oplog_state.set_oplog_head(snapshot_commit_id)?;
+    set_meta_ref(ctx, &repo, snapshot_commit_id)?;
 
     set_reference_to_oplog(&ctx.path, ReflogCommits::new(ctx)?)?;
 
@@ -610,6 +972,15 @@ fn restore_snapshot(
 
     repo.ignore_large_files_in_diffs(AUTO_TRACK_LIMIT_BYTES)?;
 
+    // The snapshot only has redacted versions of files with secrets, so these keep their current state.
+    let redacted_files: Vec<_> = secrets::redacted_files(&repo, &snapshot_tree)?
+        .into_iter()
+        .map(|path| {
+            let content = fs::read(worktree_dir.join(&path)).ok();
+            (path, content)
+        })
+        .collect();
+
     // Define the checkout builder
     let mut checkout_builder = git2::build::CheckoutBuilder::new();
     checkout_builder.remove_untracked(true);
@@ -617,6 +988,25 @@ fn restore_snapshot(
     // Checkout the tree
     repo.checkout_tree(workdir_tree.as_object(), Some(&mut checkout_builder))?;
 
+    for (path, content) in redacted_files {
+        let path = worktree_dir.join(path);
+        match content {
+            Some(content) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, content)?;
+            }
+            None => {
+                if let Err(err) = fs::remove_file(&path) {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+    }
+
     // Update virtual_branches.toml with the state from the snapshot
     fs::write(
         repo.path().join("gitbutler").join("virtual_branches.toml"),
@@ -666,6 +1056,7 @@ fn restore_snapshot(
         ctx,
         before_restore_snapshot_tree_id,
         details,
+        None,
         exclusive_access,
     )
 }
@@ -826,7 +1217,7 @@ fn deserialize_commit(
 
 /// Creates a tree that is the merge of all applied branches from a given snapshot and returns the tree id.
 /// Note that `repo` must have caching setup for merges.
-fn tree_from_applied_vbranches(
+pub(crate) fn tree_from_applied_vbranches(
     repo: &gix::Repository,
     snapshot_commit_id: git2::Oid,
 ) -> Result<git2::Oid> {
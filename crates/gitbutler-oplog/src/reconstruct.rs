@@ -0,0 +1,398 @@
+//! Restore all files within a directory of a project to how they were at some point in time, like before an
+//! agent rewrote half of them.
+//!
+//! Reconstructing each file with [`blob_at()`](crate::deltas::blob_at()) would look for its versions in all
+//! snapshots again, so instead the snapshot or checkpoint to start from is found once, and the recorded deltas are
+//! applied to the files of the directory on multiple threads. Nothing is written before all files were
+//! reconstructed, so a restore that was interrupted leaves the worktree as it was. Symbolic links and executable
+//! files are restored as such, with the mode and link target recorded last.
+//!
+//! Files that were changed after they were reconstructed, like when an editor saved them, are left as they are
+//! instead of being overwritten, see [`write_unless_changed()`](gitbutler_fs::write_unless_changed()).
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_command_context::repository_pool;
+use gitbutler_diff::FileMode;
+use gitbutler_fs::FileStamp;
+use gitbutler_project::{access::WorktreeWritePermission, Project, AUTO_TRACK_LIMIT_BYTES};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    deltas::{self, BlobAt, MAX_SNAPSHOTS},
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    eol::{self, LineEnding},
+    oplog::tree_from_applied_vbranches,
+    secrets, OplogExt,
+};
+
+/// How far [`restore_directory_at()`] got, as passed to its `progress` callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreProgress {
+    /// The amount of files that were reconstructed so far.
+    pub reconstructed: usize,
+    /// The amount of files with recorded history within the directory.
+    pub total: usize,
+}
+
+/// What [`restore_directory_at()`] did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreOutcome {
+    /// The snapshot taken before anything was written, or `None` if all files already were as they were back then.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub snapshot_id: Option<git2::Oid>,
+    /// The worktree-relative paths of the files that were written, sorted.
+    pub restored: Vec<PathBuf>,
+    /// The worktree-relative paths of the files that were removed as they didn't exist back then, sorted.
+    pub deleted: Vec<PathBuf>,
+    /// The worktree-relative paths of the files that were left as they are as nothing was recorded about them up to
+    /// then, sorted.
+    pub unknown: Vec<PathBuf>,
+    /// The worktree-relative paths of the files that were left as they are as they were changed by another program
+    /// while restoring, sorted.
+    pub changed: Vec<PathBuf>,
+    /// The amount of files that already were as they were back then.
+    pub unchanged: usize,
+}
+
+/// What to do with a single file.
+enum Plan {
+    Write {
+        path: PathBuf,
+        blob_id: git2::Oid,
+        eol: Option<LineEnding>,
+        mode: git2::FileMode,
+        stamp: FileStamp,
+    },
+    Delete(PathBuf, FileStamp),
+    Unknown(PathBuf),
+    Unchanged,
+}
+
+/// Restore all files within the worktree-relative `directory` of `project` to how they were at `at` seconds since
+/// the Unix epoch, with an empty `directory` meaning the whole worktree. A snapshot is taken before anything is
+/// written, so the restore can be undone.
+///
+/// The files are those of the latest snapshot or checkpoint recorded at or before `at`, changed by the deltas
+/// recorded up to then, so files that were only created later are removed. Files that were redacted in the snapshot,
+/// whose content wasn't recorded, or that changed since they were reconstructed, are left as they are. `progress` is called on the threads that reconstruct
+/// files whenever one is done, and once `should_interrupt` is set, reconstructing stops and nothing is written.
+pub fn restore_directory_at(
+    project: &Project,
+    directory: &Path,
+    at: i64,
+    should_interrupt: &AtomicBool,
+    progress: &(dyn Fn(RestoreProgress) + Sync),
+    perm: &mut WorktreeWritePermission,
+) -> Result<RestoreOutcome> {
+    let repo = repository_pool::open(project)?;
+    let all_deltas = deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?;
+    // Files that were only created later are among those to restore, but only what was recorded up to `at` applies.
+    let recorded = &all_deltas[..all_deltas.partition_point(|delta| delta.at <= at)];
+    let snapshot = snapshot_at(project, &repo, at)?;
+    let (base_at, base_tree, redacted) = match deltas::latest_checkpoint(
+        recorded,
+        snapshot.as_ref().map(|(recorded_at, ..)| *recorded_at),
+    ) {
+        Some((recorded_at, checkpoint)) => (
+            Some(recorded_at),
+            Some(repo.find_commit(checkpoint)?.tree_id()),
+            BTreeSet::new(),
+        ),
+        None => match snapshot {
+            Some((recorded_at, tree, redacted)) => (Some(recorded_at), Some(tree), redacted),
+            None => (None, None, BTreeSet::new()),
+        },
+    };
+
+    let mut paths = BTreeSet::new();
+    if let Some(base_tree) = base_tree {
+        repo.find_tree(base_tree)?
+            .walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+                if entry.kind() == Some(git2::ObjectType::Blob) {
+                    let path =
+                        Path::new(root).join(String::from_utf8_lossy(entry.name_bytes()).as_ref());
+                    if path.starts_with(directory) {
+                        paths.insert(path);
+                    }
+                }
+                git2::TreeWalkResult::Ok
+            })?;
+    }
+    paths.extend(
+        all_deltas
+            .iter()
+            .flat_map(|delta| &delta.paths)
+            .filter(|path| path.starts_with(directory))
+            .cloned(),
+    );
+    if paths.is_empty() {
+        bail!(
+            "Nothing was recorded within '{}' up to then",
+            directory.display()
+        );
+    }
+    let paths: Vec<_> = paths.into_iter().collect();
+
+    let reconstructed = AtomicUsize::new(0);
+    let total = paths.len();
+    let plans = paths
+        .par_iter()
+        .map_init(
+            || repository_pool::open(project),
+            |repo, path| -> Result<Plan> {
+                if should_interrupt.load(Ordering::Relaxed) {
+                    bail!("Restoring was cancelled, and nothing was written");
+                }
+                let repo = repo.as_ref().map_err(|err| anyhow::anyhow!("{err:#}"))?;
+                let base = match base_at.zip(base_tree) {
+                    Some(_) if redacted.contains(path) => None,
+                    Some((recorded_at, tree)) => match repo.find_tree(tree)?.get_path(path) {
+                        Ok(entry) => Some(BlobAt {
+                            recorded_at,
+                            blob_id: Some(entry.id()),
+                            eol: None,
+                            mode: deltas::entry_mode(&entry),
+                        }),
+                        // Large files never make it into the tree, so they aren't known to have been missing.
+                        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                            let large = AUTO_TRACK_LIMIT_BYTES > 0
+                                && std::fs::metadata(project.path.join(path))
+                                    .is_ok_and(|metadata| metadata.len() > AUTO_TRACK_LIMIT_BYTES);
+                            (!large).then_some(BlobAt {
+                                recorded_at,
+                                blob_id: None,
+                                eol: None,
+                                mode: None,
+                            })
+                        }
+                        Err(err) => return Err(err.into()),
+                    },
+                    None => None,
+                };
+                let plan = plan(
+                    repo,
+                    &project.path,
+                    path,
+                    deltas::apply_deltas(path, base, recorded),
+                )?;
+                progress(RestoreProgress {
+                    reconstructed: reconstructed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                });
+                Ok(plan)
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut outcome = RestoreOutcome {
+        snapshot_id: None,
+        restored: Vec::new(),
+        deleted: Vec::new(),
+        unknown: Vec::new(),
+        changed: Vec::new(),
+        unchanged: 0,
+    };
+    if plans
+        .iter()
+        .any(|plan| matches!(plan, Plan::Write { .. } | Plan::Delete(..)))
+    {
+        outcome.snapshot_id = Some(
+            project
+                .create_snapshot(
+                    SnapshotDetails::new(OperationKind::RestoreFiles).with_trailers(vec![
+                        Trailer {
+                            key: "directory".to_string(),
+                            value: directory.display().to_string(),
+                        },
+                        Trailer {
+                            key: "at".to_string(),
+                            value: at.to_string(),
+                        },
+                    ]),
+                    perm,
+                )
+                .context("Refusing to restore files as the snapshot to undo it failed")?,
+        );
+    }
+    for plan in plans {
+        match plan {
+            Plan::Write {
+                path,
+                blob_id,
+                eol,
+                mode,
+                stamp,
+            } => {
+                let worktree_path = project.path.join(&path);
+                if FileStamp::of(&worktree_path)? != stamp {
+                    outcome.changed.push(path);
+                    continue;
+                }
+                if let Some(parent) = worktree_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let blob = repo.find_blob(blob_id)?;
+                write_file(&worktree_path, &eol::restore(blob.content(), eol), mode)
+                    .with_context(|| format!("failed to write '{}'", worktree_path.display()))?;
+                outcome.restored.push(path);
+            }
+            Plan::Delete(path, stamp) => {
+                let worktree_path = project.path.join(&path);
+                if FileStamp::of(&worktree_path)? != stamp {
+                    outcome.changed.push(path);
+                    continue;
+                }
+                std::fs::remove_file(&worktree_path)
+                    .with_context(|| format!("failed to remove '{}'", worktree_path.display()))?;
+                outcome.deleted.push(path);
+            }
+            Plan::Unknown(path) => outcome.unknown.push(path),
+            Plan::Unchanged => outcome.unchanged += 1,
+        }
+    }
+    Ok(outcome)
+}
+
+/// Return what to do with the file at the worktree-relative `path` within `worktree_dir` to make it `blob`, checked
+/// out with its mode.
+fn plan(
+    repo: &git2::Repository,
+    worktree_dir: &Path,
+    path: &Path,
+    blob: Option<BlobAt>,
+) -> Result<Plan> {
+    let Some(blob) = blob else {
+        return Ok(Plan::Unknown(path.to_owned()));
+    };
+    let mode = checkout_mode(blob.mode);
+    let worktree_path = worktree_dir.join(path);
+    // Taken before reading the file, so changes made while it's compared are noticed when writing it.
+    let stamp = FileStamp::of(&worktree_path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    let metadata = match std::fs::symlink_metadata(&worktree_path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read '{}'", path.display()))
+        }
+    };
+    Ok(match (blob.blob_id, metadata) {
+        (None, None) => Plan::Unchanged,
+        (None, Some(_)) => Plan::Delete(path.to_owned(), stamp),
+        (Some(blob_id), metadata) => {
+            let unchanged = match metadata {
+                Some(metadata) if has_mode(&metadata, mode) => {
+                    let content = repo.find_blob(blob_id)?;
+                    read_as(&worktree_path, mode)
+                        .with_context(|| format!("failed to read '{}'", path.display()))?
+                        == eol::restore(content.content(), blob.eol).as_ref()
+                }
+                _ => false,
+            };
+            if unchanged {
+                Plan::Unchanged
+            } else {
+                Plan::Write {
+                    path: path.to_owned(),
+                    blob_id,
+                    eol: blob.eol,
+                    mode,
+                    stamp,
+                }
+            }
+        }
+    })
+}
+
+/// Turn a recorded `mode` into what's checked out, with everything that isn't a link or executable, or isn't known,
+/// being a regular file.
+fn checkout_mode(mode: Option<FileMode>) -> git2::FileMode {
+    match mode {
+        Some(FileMode::Symlink) => git2::FileMode::Link,
+        Some(FileMode::Executable) => git2::FileMode::BlobExecutable,
+        Some(FileMode::Regular | FileMode::Submodule) | None => git2::FileMode::Blob,
+    }
+}
+
+/// Read the file at `path` like git stores it when checked out with `mode`, which is the target of links.
+fn read_as(path: &Path, mode: git2::FileMode) -> std::io::Result<Vec<u8>> {
+    if cfg!(unix) && mode == git2::FileMode::Link {
+        Ok(gix::path::into_bstr(std::fs::read_link(path)?)
+            .into_owned()
+            .into())
+    } else {
+        std::fs::read(path)
+    }
+}
+
+/// Write `content` to `path` like git checks it out with `mode`, replacing what's there even if it's a link.
+/// Write `content` to the file at `path` with its recorded `mode`, creating the directories leading to it, like
+/// when restoring it.
+pub fn write_worktree_file(
+    path: &Path,
+    content: &[u8],
+    mode: Option<FileMode>,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_file(path, content, checkout_mode(mode))
+}
+
+fn write_file(path: &Path, content: &[u8], mode: git2::FileMode) -> std::io::Result<()> {
+    // Writing to a link would change what it points to instead.
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink())
+        || (mode == git2::FileMode::Link && path.exists())
+    {
+        std::fs::remove_file(path)?;
+    }
+    if mode == git2::FileMode::Link {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(gix::path::from_byte_slice(content), path);
+        // Like git without `core.symlinks`, links are files holding their target where they can't be created.
+        #[cfg(not(unix))]
+        return std::fs::write(path, content);
+    }
+    std::fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = std::fs::metadata(path)?.permissions().mode();
+        let permissions = if mode == git2::FileMode::BlobExecutable {
+            permissions | 0o755
+        } else {
+            permissions & !0o111
+        };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(permissions))?;
+    }
+    Ok(())
+}
+
+/// Return `true` if the file described by `metadata` is checked out with `mode`.
+#[cfg(unix)]
+fn has_mode(metadata: &std::fs::Metadata, mode: git2::FileMode) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        git2::FileMode::Link => metadata.is_symlink(),
+        _ => {
+            !metadata.is_symlink()
+                && (metadata.permissions().mode() & 0o111 != 0)
+                    == (mode == git2::FileMode::BlobExecutable)
+        }
+    }
+}
+
+/// Return `true` if the file described by `metadata` is checked out with `mode`, which can only be told for links
+/// on Unix.
+#[cfg(not(unix))]
+fn has_mode(metadata: &std::fs::Metadata, _mode: git2::FileMode) -> bool {
+    !metadata.is_symlink()
+}
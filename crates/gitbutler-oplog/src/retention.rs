@@ -0,0 +1,219 @@
+//! Limit how much of the recorded history of each file is kept, as set with
+//! [`Project::history_retention`](gitbutler_project::Project::history_retention).
+//!
+//! When deltas are compacted, files whose history is beyond a limit are dropped from each delta along with their
+//! content, and deltas that don't refer to any file anymore are dropped unless they have a checkpoint. Pinned files
+//! and sessions are never dropped, not even when the deltas file grew too large and old deltas are dropped anyway.
+//! [`history_budget()`] tells how much history each file has, and how much of it the next compaction drops.
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use gitbutler_project::{HistoryRetention, Project};
+use serde::Serialize;
+
+use crate::{activity::SESSION_GAP_SECONDS, deltas};
+
+/// How much history is kept of the files of a project, as returned by [`history_budget()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryBudget {
+    pub retention: HistoryRetention,
+    /// The size of the deltas file.
+    pub deltas_bytes: u64,
+    /// Once the deltas file is larger than this, deltas older than 30 days are dropped unless they are pinned.
+    pub max_deltas_bytes: u64,
+    /// The files with recorded history, sorted by path.
+    pub files: Vec<FileBudget>,
+}
+
+/// How much history is kept of a single file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileBudget {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The amount of deltas that changed the file.
+    pub deltas: usize,
+    /// The amount of sessions that changed the file.
+    pub sessions: usize,
+    /// When the oldest recorded change of the file was noticed, in seconds since the Unix epoch.
+    pub oldest_at: i64,
+    /// If `true`, the file is pinned, and its history is never dropped.
+    pub pinned: bool,
+    /// The amount of deltas of the file that the next compaction drops.
+    pub prunable_deltas: usize,
+}
+
+/// Return how much history of each file of `project` is kept as of `now`, in seconds since the Unix epoch.
+pub fn history_budget(project: &Project, now: i64) -> Result<HistoryBudget> {
+    let deltas_bytes = deltas::stored_bytes(&project.gb_dir().join(deltas::DELTAS_FILE))?;
+    let oldest = (deltas_bytes > deltas::MAX_FILE_BYTES).then(|| now - deltas::RETENTION_SECONDS);
+    let deltas = deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?;
+    let recorded: Vec<_> = deltas
+        .iter()
+        .map(|delta| (delta.at, delta.paths.as_slice()))
+        .collect();
+    let retention = &project.history_retention;
+    let kept = retained(retention, now, oldest, &recorded);
+
+    let mut files = BTreeMap::<&Path, FileBudget>::new();
+    let mut last_session = HashMap::<&Path, usize>::new();
+    for (((at, paths), kept), session) in recorded.iter().zip(&kept).zip(sessions(&recorded)) {
+        for (path, kept) in paths.iter().zip(kept) {
+            let file = files.entry(path.as_path()).or_insert_with(|| FileBudget {
+                path: path.clone(),
+                deltas: 0,
+                sessions: 0,
+                oldest_at: *at,
+                pinned: retention.pinned_files.contains(path),
+                prunable_deltas: 0,
+            });
+            file.deltas += 1;
+            if last_session.insert(path.as_path(), session) != Some(session) {
+                file.sessions += 1;
+            }
+            if !kept {
+                file.prunable_deltas += 1;
+            }
+        }
+    }
+    Ok(HistoryBudget {
+        retention: retention.clone(),
+        deltas_bytes,
+        max_deltas_bytes: deltas::MAX_FILE_BYTES,
+        files: files.into_values().collect(),
+    })
+}
+
+/// Return which of the paths of each of the `recorded` deltas, given by when they were noticed and the paths they
+/// changed in the order they were recorded, `retention` keeps as of `now`. If `oldest` is set, only pinned paths
+/// are kept of deltas noticed before it.
+pub(crate) fn retained(
+    retention: &HistoryRetention,
+    now: i64,
+    oldest: Option<i64>,
+    recorded: &[(i64, &[PathBuf])],
+) -> Vec<Vec<bool>> {
+    let sessions = sessions(recorded);
+    let oldest_kept_session = retention
+        .max_sessions
+        .map(|max_sessions| {
+            let mut sessions_of = HashMap::<&Path, Vec<usize>>::new();
+            for ((_, paths), session) in recorded.iter().zip(&sessions) {
+                for path in paths.iter() {
+                    let sessions = sessions_of.entry(path.as_path()).or_default();
+                    if sessions.last() != Some(session) {
+                        sessions.push(*session);
+                    }
+                }
+            }
+            sessions_of
+                .into_iter()
+                .map(|(path, sessions)| {
+                    (
+                        path,
+                        sessions[sessions.len().saturating_sub(max_sessions.get())],
+                    )
+                })
+                .collect::<HashMap<_, _>>()
+        })
+        .unwrap_or_default();
+    let max_age = retention.max_age_days.map(|days| {
+        now.saturating_sub(
+            i64::try_from(days.get())
+                .unwrap_or(i64::MAX)
+                .saturating_mul(24 * 60 * 60),
+        )
+    });
+    let oldest = oldest.max(max_age);
+
+    recorded
+        .iter()
+        .zip(&sessions)
+        .map(|((at, paths), session)| {
+            paths
+                .iter()
+                .map(|path| {
+                    retention.is_pinned(path, *at)
+                        || (oldest.is_none_or(|oldest| *at >= oldest)
+                            && oldest_kept_session
+                                .get(path.as_path())
+                                .is_none_or(|oldest_kept| session >= oldest_kept))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Return the index of the session each of the `recorded` deltas belongs to, with sessions ending when no delta
+/// was noticed for [`SESSION_GAP_SECONDS`].
+fn sessions(recorded: &[(i64, &[PathBuf])]) -> Vec<usize> {
+    let mut session = 0;
+    let mut previous = None;
+    recorded
+        .iter()
+        .map(|(at, _)| {
+            if previous.is_some_and(|previous| at - previous > SESSION_GAP_SECONDS) {
+                session += 1;
+            }
+            previous = Some(*at);
+            session
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use gitbutler_project::PinnedSession;
+
+    use super::*;
+
+    #[test]
+    fn history_beyond_the_limits_is_dropped_unless_pinned() {
+        let a = [PathBuf::from("a")];
+        let ab = [PathBuf::from("a"), PathBuf::from("b")];
+        let recorded = [
+            (0, &ab[..]),
+            (SESSION_GAP_SECONDS * 2, &a[..]),
+            (SESSION_GAP_SECONDS * 4, &ab[..]),
+        ];
+        let now = SESSION_GAP_SECONDS * 4;
+
+        let unlimited = HistoryRetention::default();
+        assert_eq!(
+            retained(&unlimited, now, None, &recorded),
+            [vec![true, true], vec![true], vec![true, true]]
+        );
+
+        let two_sessions = HistoryRetention {
+            max_sessions: NonZeroUsize::new(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            retained(&two_sessions, now, None, &recorded),
+            [vec![false, true], vec![true], vec![true, true]],
+            "'b' only changed in two sessions"
+        );
+
+        let pinned = HistoryRetention {
+            max_sessions: NonZeroUsize::new(1),
+            pinned_files: vec![PathBuf::from("b")],
+            pinned_sessions: vec![PinnedSession { start: 0, end: 0 }],
+            ..Default::default()
+        };
+        assert_eq!(
+            retained(&pinned, now, None, &recorded),
+            [vec![true, true], vec![false], vec![true, true]]
+        );
+        assert_eq!(
+            retained(&unlimited, now, Some(1), &recorded),
+            [vec![false, false], vec![true], vec![true, true]],
+            "nothing older than `oldest` is kept unless pinned"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Keep secrets like access keys out of snapshots, which would otherwise retain them for as long as the oplog exists.
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_project::{Project, SecretRedaction};
+use regex::Regex;
+use serde::Serialize;
+
+/// What secrets are replaced with when [masking](SecretRedaction::Mask) them.
+pub const MASK: &str = "[REDACTED]";
+
+/// Files larger than this aren't scanned, as secrets are typically found in small configuration files.
+const MAX_SCANNED_SIZE: usize = 1024 * 1024;
+
+/// The patterns that are always detected, along with their names.
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("AWS access key ID", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "AWS secret access key",
+        r#"(?i)aws_secret_access_key\s*[=:]\s*["']?[A-Za-z0-9/+=]{40}"#,
+    ),
+    // Without an end marker, everything up to the end of the file is considered part of the key.
+    (
+        "Private key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----(?s:.*?)(?:-----END [A-Z ]*PRIVATE KEY-----|\z)",
+    ),
+];
+
+/// Detects secrets in file content, using the built-in patterns and the ones configured for a project.
+pub struct SecretScanner {
+    patterns: Vec<(String, Regex)>,
+}
+
+/// A secret that was found in a snapshot by [`scan_history()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretFinding {
+    /// The id of the snapshot that recorded the secret first.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub snapshot_id: git2::Oid,
+    /// The creation time of the snapshot in seconds since the Unix epoch.
+    pub created_at: i64,
+    /// The worktree-relative path of the file containing the secret.
+    pub path: PathBuf,
+    /// The one-based number of the line the secret starts at.
+    pub line: u32,
+    /// The name of the built-in pattern, or the custom regular expression, that matched.
+    pub pattern: String,
+}
+
+impl SecretScanner {
+    /// Create a scanner for the built-in patterns and the `custom` regular expressions,
+    /// failing if one of them is invalid.
+    pub fn new(custom: &[String]) -> Result<Self> {
+        let mut patterns = builtin_patterns();
+        for pattern in custom {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid secret pattern '{pattern}'"))?;
+            patterns.push((pattern.clone(), regex));
+        }
+        Ok(SecretScanner { patterns })
+    }
+
+    /// Create a scanner with the secret patterns configured for `project`, skipping invalid ones
+    /// so they don't prevent snapshots from being created.
+    pub fn for_project(project: &Project) -> Self {
+        let mut patterns = builtin_patterns();
+        for pattern in &project.secret_patterns {
+            match Regex::new(pattern) {
+                Ok(regex) => patterns.push((pattern.clone(), regex)),
+                Err(err) => tracing::warn!(pattern, ?err, "ignoring invalid secret pattern"),
+            }
+        }
+        SecretScanner { patterns }
+    }
+
+    /// Return the name of the first pattern matching `content` along with the byte offset of the match,
+    /// or `None` if there are no secrets.
+    pub fn find(&self, content: &str) -> Option<(&str, usize)> {
+        self.patterns
+            .iter()
+            .filter_map(|(name, regex)| regex.find(content).map(|m| (name.as_str(), m.start())))
+            .min_by_key(|(_, start)| *start)
+    }
+
+    /// Return `content` with all secrets replaced by [`MASK`], or `None` if it has no secrets.
+    pub fn mask(&self, content: &str) -> Option<String> {
+        let mut masked = None;
+        for (_, regex) in &self.patterns {
+            let current = masked.as_deref().unwrap_or(content);
+            if regex.is_match(current) {
+                masked = Some(regex.replace_all(current, MASK).into_owned());
+            }
+        }
+        masked
+    }
+}
+
+fn builtin_patterns() -> Vec<(String, Regex)> {
+    BUILTIN_PATTERNS
+        .iter()
+        .map(|(name, pattern)| {
+            (
+                name.to_string(),
+                Regex::new(pattern).expect("built-in patterns are valid"),
+            )
+        })
+        .collect()
+}
+
+/// Return a version of `tree` in which all files with secrets that differ from `base_tree` are redacted
+/// according to `redaction`, along with the paths of the redacted files.
+///
+/// With [`SecretRedaction::SkipFile`], redacted files are recorded as they are in `base_tree`.
+pub(crate) fn redact_tree(
+    repo: &git2::Repository,
+    scanner: &SecretScanner,
+    redaction: SecretRedaction,
+    base_tree: &git2::Tree,
+    tree: &git2::Tree,
+) -> Result<(git2::Oid, Vec<PathBuf>)> {
+    let diff = repo.diff_tree_to_tree(Some(base_tree), Some(tree), None)?;
+    let mut update = git2::build::TreeUpdateBuilder::new();
+    let mut redacted = Vec::new();
+    for delta in diff.deltas() {
+        let new_file = delta.new_file();
+        if new_file.id().is_zero() || new_file.mode() == git2::FileMode::Commit {
+            continue;
+        }
+        let Some(path) = new_file.path() else {
+            continue;
+        };
+        let blob = repo.find_blob(new_file.id())?;
+        if blob.is_binary() || blob.size() > MAX_SCANNED_SIZE {
+            continue;
+        }
+        let Ok(content) = std::str::from_utf8(blob.content()) else {
+            continue;
+        };
+        let Some(masked) = scanner.mask(content) else {
+            continue;
+        };
+        match redaction {
+            SecretRedaction::Mask => {
+                let masked_id = repo.blob(masked.as_bytes())?;
+                update.upsert(path, masked_id, new_file.mode());
+            }
+            SecretRedaction::SkipFile => match base_tree.get_path(path) {
+                Ok(entry) => {
+                    update.upsert(path, entry.id(), file_mode(entry.filemode()));
+                }
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                    update.remove(path);
+                }
+                Err(err) => return Err(err.into()),
+            },
+        }
+        redacted.push(path.to_owned());
+    }
+    if redacted.is_empty() {
+        return Ok((tree.id(), redacted));
+    }
+    Ok((update.create_updated(repo, tree)?, redacted))
+}
+
+/// Return `content` redacted according to `redaction`, or `None` if it has secrets and must not be recorded at all,
+/// as with [`SecretRedaction::SkipFile`].
+///
+/// Binary and large content isn't scanned and is returned as is, just like by [`redact_tree()`].
+pub(crate) fn redact_content<'a>(
+    scanner: &SecretScanner,
+    redaction: SecretRedaction,
+    content: &'a [u8],
+) -> Option<Cow<'a, [u8]>> {
+    if content.len() > MAX_SCANNED_SIZE || content.contains(&0) {
+        return Some(Cow::Borrowed(content));
+    }
+    let Some(masked) = std::str::from_utf8(content)
+        .ok()
+        .and_then(|text| scanner.mask(text))
+    else {
+        return Some(Cow::Borrowed(content));
+    };
+    match redaction {
+        SecretRedaction::Mask => Some(Cow::Owned(masked.into_bytes())),
+        SecretRedaction::SkipFile => None,
+    }
+}
+
+fn file_mode(mode: i32) -> git2::FileMode {
+    match mode {
+        mode if mode == i32::from(git2::FileMode::BlobExecutable) => git2::FileMode::BlobExecutable,
+        mode if mode == i32::from(git2::FileMode::Link) => git2::FileMode::Link,
+        _ => git2::FileMode::Blob,
+    }
+}
+
+/// Find all secrets in the changes recorded by the snapshots of `project`, oldest first.
+///
+/// Only lines added by a snapshot are scanned, so each secret is reported for the snapshot that recorded it first.
+/// Snapshots created before redaction existed, or by a previous configuration, may still contain secrets.
+pub fn scan_history(project: &Project, scanner: &SecretScanner) -> Result<Vec<SecretFinding>> {
+    use crate::OplogExt;
+
+    let mut findings = Vec::new();
+    for snapshot in project.list_snapshots(usize::MAX, None)?.into_iter().rev() {
+        let diff = match project.snapshot_diff(snapshot.commit_id) {
+            Ok(diff) => diff,
+            // The very first snapshot has no parent to compare to.
+            Err(err) => {
+                tracing::debug!(snapshot = %snapshot.commit_id, ?err, "skipping snapshot without diff");
+                continue;
+            }
+        };
+        let mut files: Vec<_> = diff.into_values().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        for file in files.iter().filter(|file| !file.binary && !file.skipped) {
+            for hunk in &file.hunks {
+                scan_added_lines(scanner, &file.path, hunk, |line, pattern| {
+                    findings.push(SecretFinding {
+                        snapshot_id: snapshot.commit_id,
+                        created_at: snapshot.created_at.seconds(),
+                        path: file.path.clone(),
+                        line,
+                        pattern: pattern.to_owned(),
+                    })
+                });
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Call `found` with the line number and pattern name of each secret in the lines added by `hunk`.
+fn scan_added_lines(
+    scanner: &SecretScanner,
+    path: &Path,
+    hunk: &gitbutler_diff::GitHunk,
+    mut found: impl FnMut(u32, &str),
+) {
+    let Ok(diff) = std::str::from_utf8(&hunk.diff_lines) else {
+        tracing::debug!(path = %path.display(), "skipping hunk that isn't valid UTF-8");
+        return;
+    };
+    let mut line_number = hunk.new_start;
+    // The first line is the hunk header.
+    for line in diff.lines().skip(1) {
+        match line.as_bytes().first() {
+            Some(b'+') => {
+                if let Some((pattern, _)) = scanner.find(&line[1..]) {
+                    found(line_number, pattern);
+                }
+                line_number += 1;
+            }
+            Some(b'-') => {}
+            _ => line_number += 1,
+        }
+    }
+}
+
+/// The entry of a snapshot tree listing the files that were redacted, one path per line.
+pub(crate) const REDACTED_FILES_ENTRY: &str = "redacted_files";
+
+/// Return the paths of the files that were redacted when recording the snapshot with `snapshot_tree`.
+pub(crate) fn redacted_files(
+    repo: &git2::Repository,
+    snapshot_tree: &git2::Tree,
+) -> Result<Vec<PathBuf>> {
+    let Some(entry) = snapshot_tree.get_name(REDACTED_FILES_ENTRY) else {
+        return Ok(Vec::new());
+    };
+    let blob = repo.find_blob(entry.id())?;
+    Ok(std::str::from_utf8(blob.content())?
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
@@ -0,0 +1,231 @@
+//! Package a [session](crate::activity::ActivitySession) as a self-contained file, so another user can replay how its files changed
+//! without anything being pushed.
+//!
+//! The bundle holds the content of the files changed in the session as of its start, the deltas of the session,
+//! and every content these refer to. If a passphrase is given, the bundle is encrypted with AES-256-GCM under a
+//! key derived from it, and can't be opened without it.
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::{general_purpose::STANDARD, Engine as _};
+use gitbutler_project::Project;
+use ring::{
+    aead, pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blob_store::BlobStore,
+    deltas::{self, Delta, DeltaContent},
+    OplogExt,
+};
+
+/// The version of the format of [`SharedSession`], to tell bundles apart that can't be opened by this version.
+pub const SHARE_FORMAT_VERSION: u64 = 1;
+
+/// The start of encrypted bundles, which are followed by the salt, the nonce and the encrypted JSON.
+const ENCRYPTED_MAGIC: &[u8] = b"GITBUTLER-SHARED-SESSION\0";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// The iterations of PBKDF2-HMAC-SHA256 to derive the key from the passphrase.
+const KEY_ITERATIONS: u32 = 600_000;
+
+/// A session as packaged by [`share_session()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedSession {
+    pub version: u64,
+    /// The title of the project the session was recorded in.
+    pub project_title: String,
+    /// The start and end of the session, in seconds since the Unix epoch.
+    pub start: i64,
+    pub end: i64,
+    /// The short name of the branch the session was recorded on, if known.
+    pub branch: Option<String>,
+    /// The content of each file changed during the session as of its start, or `None` if it didn't exist.
+    pub base: Vec<DeltaContent>,
+    /// The deltas of the session, oldest first.
+    pub deltas: Vec<Delta>,
+    /// The base64-encoded contents referred to by `base` and `deltas`, by the hex id of their blob.
+    pub blobs: BTreeMap<String, String>,
+}
+
+impl SharedSession {
+    /// Return the content of the blob with `id`, if it's part of the bundle.
+    pub fn content(&self, id: git2::Oid) -> Result<Option<Vec<u8>>> {
+        self.blobs
+            .get(&id.to_string())
+            .map(|content| Ok(STANDARD.decode(content)?))
+            .transpose()
+    }
+}
+
+/// What was written by [`share_session()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionShare {
+    pub path: PathBuf,
+    pub deltas: usize,
+    pub files: usize,
+    pub encrypted: bool,
+}
+
+/// Write the session of `project` that started at `session_start`, in seconds since the Unix epoch, to the file
+/// at `path`, encrypted with `passphrase` if set.
+pub fn share_session(
+    project: &Project,
+    session_start: i64,
+    passphrase: Option<&str>,
+    path: &Path,
+) -> Result<SessionShare> {
+    let session = project
+        .list_sessions(session_start..i64::MAX, None)?
+        .into_iter()
+        .find(|session| session.start == session_start)
+        .with_context(|| format!("There is no session that started at {session_start}"))?;
+
+    let mut deltas = deltas::list_deltas(
+        project,
+        session.start..session.end.saturating_add(1),
+        None,
+        None,
+    )?;
+    let paths: BTreeSet<PathBuf> = deltas
+        .iter()
+        .flat_map(|delta| delta.paths.iter().cloned())
+        .collect();
+    let mut base = Vec::with_capacity(paths.len());
+    for path in paths {
+        let blob = deltas::blob_at(project, &path, session.start.saturating_sub(1))?;
+        base.push(DeltaContent {
+            path,
+            blob_id: blob.and_then(|blob| blob.blob_id),
+            eol: blob.and_then(|blob| blob.eol),
+            content_type: None,
+            mode: blob.and_then(|blob| blob.mode),
+            link_target: None,
+        });
+    }
+    for delta in &mut deltas {
+        // Checkpoints are commits of the whole worktree, which aren't part of the bundle.
+        delta.checkpoint = None;
+    }
+
+    let repo = git2::Repository::open(&project.path)?;
+    let store = BlobStore::open(&repo)?;
+    let mut blobs = BTreeMap::new();
+    for id in base
+        .iter()
+        .chain(deltas.iter().flat_map(|delta| &delta.contents))
+        .filter_map(|content| content.blob_id)
+    {
+        if let Entry::Vacant(entry) = blobs.entry(id.to_string()) {
+            entry.insert(STANDARD.encode(store.load(id)?));
+        }
+    }
+
+    let shared = SharedSession {
+        version: SHARE_FORMAT_VERSION,
+        project_title: project.title.clone(),
+        start: session.start,
+        end: session.end,
+        branch: session.branch,
+        base,
+        deltas,
+        blobs,
+    };
+    let json = serde_json::to_vec(&shared)?;
+    let bundle = match passphrase {
+        Some(passphrase) => encrypt(json, passphrase)?,
+        None => json,
+    };
+    gitbutler_fs::write(path, bundle)?;
+
+    Ok(SessionShare {
+        path: path.to_owned(),
+        deltas: shared.deltas.len(),
+        files: shared.base.len(),
+        encrypted: passphrase.is_some(),
+    })
+}
+
+/// Read the bundle written by [`share_session()`] at `path`, decrypting it with `passphrase` if it's encrypted.
+///
+/// The contents of the bundle are checked against their ids, so a damaged bundle isn't replayed.
+pub fn open_shared_session(path: &Path, passphrase: Option<&str>) -> Result<SharedSession> {
+    let bundle =
+        std::fs::read(path).with_context(|| format!("Could not read '{}'", path.display()))?;
+    let json = if bundle.starts_with(ENCRYPTED_MAGIC) {
+        let Some(passphrase) = passphrase else {
+            bail!("The shared session is encrypted, a passphrase is needed to open it");
+        };
+        decrypt(&bundle[ENCRYPTED_MAGIC.len()..], passphrase)?
+    } else {
+        bundle
+    };
+    let shared: SharedSession =
+        serde_json::from_slice(&json).context("The file isn't a shared session")?;
+    if shared.version > SHARE_FORMAT_VERSION {
+        bail!("The session was shared by a newer version of GitButler, please update to open it");
+    }
+    for (id, content) in &shared.blobs {
+        let content = STANDARD.decode(content)?;
+        if git2::Oid::hash_object(git2::ObjectType::Blob, &content)?.to_string() != *id {
+            bail!("The shared session is damaged, the content of {id} doesn't match");
+        }
+    }
+    Ok(shared)
+}
+
+fn key(passphrase: &str, salt: &[u8]) -> Result<aead::LessSafeKey> {
+    let mut key = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(KEY_ITERATIONS).expect("not zero"),
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key)
+        .map_err(|_| anyhow!("Could not create the encryption key"))?;
+    Ok(aead::LessSafeKey::new(key))
+}
+
+fn encrypt(mut json: Vec<u8>, passphrase: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|()| rng.fill(&mut nonce))
+        .map_err(|_| anyhow!("Could not generate random numbers for the encryption"))?;
+    key(passphrase, &salt)?
+        .seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(ENCRYPTED_MAGIC),
+            &mut json,
+        )
+        .map_err(|_| anyhow!("Could not encrypt the shared session"))?;
+    Ok([ENCRYPTED_MAGIC, &salt[..], &nonce[..], &json[..]].concat())
+}
+
+fn decrypt(encrypted: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if encrypted.len() < SALT_LEN + NONCE_LEN {
+        bail!("The shared session is damaged");
+    }
+    let (salt, rest) = encrypted.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let nonce = aead::Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| anyhow!("The shared session is damaged"))?;
+    let mut json = ciphertext.to_vec();
+    let len = key(passphrase, salt)?
+        .open_in_place(nonce, aead::Aad::from(ENCRYPTED_MAGIC), &mut json)
+        .map_err(|_| anyhow!("The passphrase is wrong, or the shared session is damaged"))?
+        .len();
+    json.truncate(len);
+    Ok(json)
+}
@@ -0,0 +1,196 @@
+//! The last content of files deleted from the worktree, so deleting the wrong file can be undone even if it was
+//! never committed.
+//!
+//! Whenever a [`Delta`](crate::deltas::Delta) is recorded for files that don't exist anymore, their content as of
+//! right before, as [reconstructed](crate::deltas::blob_at()) from snapshots, checkpoints and deltas or as in
+//! `HEAD`, is kept as a [`Tombstone`].
+use std::{
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_command_context::repository_pool;
+use gitbutler_project::Project;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blob_store::BlobStore,
+    deltas,
+    eol::{self, LineEnding},
+};
+
+/// The file in the GitButler directory of a project that tombstones are appended to, one JSON object per line.
+const TOMBSTONES_FILE: &str = "tombstones.jsonl";
+
+/// Once the tombstones file is larger than this, tombstones older than [`RETENTION_SECONDS`] are dropped.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const RETENTION_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Tombstones are recorded for at most this many files deleted at once, as larger deletions are typically of
+/// generated files, like removing a build directory, and reconstructing content isn't free.
+const MAX_TOMBSTONES_PER_DELTA: usize = 100;
+
+/// The last content of a deleted file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// When the deletion was noticed, in seconds since the Unix epoch.
+    pub deleted_at: i64,
+    /// The blob with the content of the file right before it was deleted.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub blob_id: git2::Oid,
+    /// The line endings the file had if its content was recorded with LF line endings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eol: Option<LineEnding>,
+}
+
+/// Record a tombstone for each of the worktree-relative `paths` of `project` that doesn't exist anymore, as of
+/// `at` seconds since the Unix epoch, if its content before is known.
+///
+/// This has to be called before the delta noticing the deletion is recorded.
+pub(crate) fn record_tombstones(project: &Project, at: i64, paths: &[PathBuf]) -> Result<()> {
+    let deleted: Vec<_> = paths
+        .iter()
+        .filter(|path| {
+            std::fs::symlink_metadata(project.path.join(path))
+                .is_err_and(|err| err.kind() == std::io::ErrorKind::NotFound)
+        })
+        .take(MAX_TOMBSTONES_PER_DELTA)
+        .collect();
+    if deleted.is_empty() {
+        return Ok(());
+    }
+
+    let repo = repository_pool::open(project)?;
+    let head_tree = repo.head().and_then(|head| head.peel_to_tree()).ok();
+    let mut tombstones = Vec::new();
+    for path in deleted {
+        let recorded = deltas::blob_at(project, path, at - 1)?
+            .and_then(|blob| Some((blob.blob_id?, blob.eol)));
+        let blob = match recorded {
+            Some(recorded) => Some(recorded),
+            None => head_tree
+                .as_ref()
+                .and_then(|tree| tree.get_path(path).ok())
+                .filter(|entry| entry.kind() == Some(git2::ObjectType::Blob))
+                .map(|entry| (entry.id(), None)),
+        };
+        if let Some((blob_id, eol)) = blob {
+            tombstones.push(Tombstone {
+                path: path.clone(),
+                deleted_at: at,
+                blob_id,
+                eol,
+            });
+        }
+    }
+    append(project, &tombstones)
+}
+
+/// Return the tombstones of the files of `project` deleted within `range`, in seconds since the Unix epoch,
+/// oldest first.
+pub fn list_deleted_files(project: &Project, range: Range<i64>) -> Result<Vec<Tombstone>> {
+    let path = project.gb_dir().join(TOMBSTONES_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(read(&path)?
+        .into_iter()
+        .filter(|tombstone| range.contains(&tombstone.deleted_at))
+        .collect())
+}
+
+/// Write the content the file at the worktree-relative `file_path` of `project` had when it was deleted last
+/// back to the worktree, and return its tombstone.
+///
+/// Files that exist are never overwritten, which fails with [`gitbutler_fs::ChangedSinceRead`] if the file was
+/// created while its content was loaded.
+pub fn recover_deleted_file(project: &Project, file_path: &Path) -> Result<Tombstone> {
+    let Some(tombstone) = list_deleted_files(project, i64::MIN..i64::MAX)?
+        .into_iter()
+        .rev()
+        .find(|tombstone| tombstone.path == file_path)
+    else {
+        bail!("No deletion of '{}' was recorded", file_path.display());
+    };
+    let worktree_path = project.path.join(file_path);
+    if std::fs::symlink_metadata(&worktree_path).is_ok() {
+        bail!(
+            "'{}' exists, and won't be overwritten by the deleted file",
+            file_path.display()
+        );
+    }
+
+    let repo = repository_pool::open(project)?;
+    let content = BlobStore::open(&repo)?.load(tombstone.blob_id)?;
+    let content = eol::restore(&content, tombstone.eol);
+    if let Some(parent) = worktree_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Don't overwrite the file if it was created while the content was loaded.
+    let mut file = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&worktree_path)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(gitbutler_fs::ChangedSinceRead {
+                path: worktree_path,
+            }
+            .into());
+        }
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to create '{}'", worktree_path.display()))
+        }
+    };
+    file.write_all(&content)
+        .with_context(|| format!("failed to write '{}'", worktree_path.display()))?;
+    Ok(tombstone)
+}
+
+/// Append `tombstones` to those of `project`, dropping old ones if the file grew too large.
+fn append(project: &Project, tombstones: &[Tombstone]) -> Result<()> {
+    if tombstones.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(project.gb_dir())?;
+    let path = project.gb_dir().join(TOMBSTONES_FILE);
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_FILE_BYTES) {
+        let newest = tombstones[0].deleted_at;
+        let mut retained = String::new();
+        for old in read(&path)?
+            .into_iter()
+            .filter(|old| newest - old.deleted_at <= RETENTION_SECONDS)
+        {
+            retained.push_str(&serde_json::to_string(&old)?);
+            retained.push('\n');
+        }
+        gitbutler_fs::write(&path, retained)?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    for tombstone in tombstones {
+        writeln!(file, "{}", serde_json::to_string(tombstone)?)?;
+    }
+    Ok(())
+}
+
+/// Read all tombstones, skipping lines that can't be parsed.
+fn read(path: &Path) -> Result<Vec<Tombstone>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
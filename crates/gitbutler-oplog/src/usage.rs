@@ -0,0 +1,230 @@
+//! Report how much space the history of a project takes, and drop old snapshots, deltas and checkpoints to reclaim
+//! some of it.
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use gitbutler_project::{access::WorktreeWritePermission, Project};
+use serde::{Deserialize, Serialize};
+
+use crate::{deltas, encryption, state::OplogHandle, OplogExt};
+
+/// The space taken by the history of a project, as returned by [`data_usage()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataUsage {
+    /// The amount of snapshots in the oplog.
+    pub snapshots: usize,
+    /// The uncompressed size of the objects that only snapshots refer to, so not the commit at `HEAD`. Git
+    /// compresses objects and stores similar ones as deltas, so the space taken on disk is typically smaller.
+    pub snapshot_bytes: u64,
+    /// The amount of [deltas](crate::deltas), or `None` if the history is encrypted and wasn't unlocked yet.
+    pub deltas: Option<usize>,
+    /// The size of the deltas file along with the segments its older deltas are sealed into.
+    pub deltas_bytes: u64,
+    /// The amount of checkpoints of the worktree written along with deltas.
+    pub checkpoints: usize,
+    /// The uncompressed size of the objects that only checkpoints and the contents recorded with deltas refer to,
+    /// so neither the commit at `HEAD` nor snapshots.
+    pub checkpoint_bytes: u64,
+    /// The size of the other files in the `.git/gitbutler` directory, like the state of the branches and heartbeats.
+    pub metadata_bytes: u64,
+}
+
+/// What to remove with [`cleanup()`]. The newest snapshot and checkpoint are always kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupOptions {
+    /// Keep at most this many snapshots, the newest ones.
+    pub keep_snapshots: Option<usize>,
+    /// Remove snapshots, deltas and checkpoints that are older than this many days. Deltas of pinned files and
+    /// sessions are kept.
+    pub max_age_days: Option<u64>,
+}
+
+/// What was done by [`cleanup()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cleanup {
+    pub removed_snapshots: usize,
+    pub removed_deltas: usize,
+    pub removed_checkpoints: usize,
+    /// The usage after cleaning up.
+    pub usage: DataUsage,
+}
+
+/// Return how much space the history of `project` takes.
+pub fn data_usage(project: &Project) -> Result<DataUsage> {
+    let repo = git2::Repository::open(&project.path)?;
+    let odb = repo.odb()?;
+    let mut seen = HashSet::new();
+    if let Ok(head) = repo.head().and_then(|head| head.peel_to_tree()) {
+        object_sizes(&repo, &odb, head.id(), &mut seen)?;
+    }
+    let deltas_bytes = deltas::stored_bytes(&project.gb_dir().join(deltas::DELTAS_FILE))?;
+    let mut usage = DataUsage {
+        snapshots: 0,
+        snapshot_bytes: 0,
+        deltas: None,
+        deltas_bytes,
+        checkpoints: 0,
+        checkpoint_bytes: 0,
+        metadata_bytes: directory_size(&project.gb_dir())?.saturating_sub(deltas_bytes),
+    };
+    for commit in snapshot_commits(&repo, project)? {
+        usage.snapshots += 1;
+        usage.snapshot_bytes += odb.read_header(commit.id())?.0 as u64;
+        usage.snapshot_bytes += object_sizes(&repo, &odb, commit.tree_id(), &mut seen)?;
+    }
+    for checkpoint in deltas::checkpoint_commits(&repo, project)? {
+        usage.checkpoints += 1;
+        // Its parents are the previous checkpoint and what keeps the contents recorded until it.
+        for commit in Some(checkpoint.clone())
+            .into_iter()
+            .chain(checkpoint.parents())
+        {
+            if seen.insert(commit.id()) {
+                usage.checkpoint_bytes += odb.read_header(commit.id())?.0 as u64;
+                usage.checkpoint_bytes += object_sizes(&repo, &odb, commit.tree_id(), &mut seen)?;
+            }
+        }
+    }
+    if !encryption::is_locked(project) {
+        let deltas = deltas::list_deltas(project, i64::MIN..i64::MAX, None, None)?;
+        // The contents recorded since the latest checkpoint aren't kept by any of them yet.
+        for blob_id in deltas
+            .iter()
+            .flat_map(|delta| &delta.contents)
+            .filter_map(|content| content.blob_id)
+        {
+            if seen.insert(blob_id) {
+                usage.checkpoint_bytes += odb.read_header(blob_id)?.0 as u64;
+            }
+        }
+        usage.deltas = Some(deltas.len());
+    }
+    Ok(usage)
+}
+
+/// Remove the snapshots of `project` that `options` don't keep, which rewrites the ones that remain, along with the
+/// deltas and checkpoints that are older than [`CleanupOptions::max_age_days`].
+///
+/// The objects of removed snapshots and checkpoints stay in the repository until Git garbage-collects it, which
+/// happens automatically from time to time, or when running `git gc`.
+pub fn cleanup(
+    project: &Project,
+    options: CleanupOptions,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Cleanup> {
+    let repo = git2::Repository::open(&project.path)?;
+    let snapshots = snapshot_commits(&repo, project)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+    let max_age_seconds = options.max_age_days.map(|days| {
+        i64::try_from(days)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(24 * 60 * 60)
+    });
+    let keep = snapshots
+        .iter()
+        .enumerate()
+        .take_while(|(index, commit)| {
+            *index == 0
+                || (options.keep_snapshots.is_none_or(|keep| *index < keep)
+                    && max_age_seconds
+                        .is_none_or(|max_age| now - commit.time().seconds() <= max_age))
+        })
+        .count();
+    let removed_snapshots = snapshots.len() - keep;
+    if removed_snapshots > 0 {
+        // Recreate the kept snapshots oldest first, with the oldest one becoming the root.
+        let mut head: Option<git2::Oid> = None;
+        for commit in snapshots[..keep].iter().rev() {
+            let parent = head.map(|id| repo.find_commit(id)).transpose()?;
+            head = Some(repo.commit(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                &String::from_utf8_lossy(commit.message_bytes()),
+                &commit.tree()?,
+                parent.as_slice(),
+            )?);
+        }
+        project.set_oplog_head(head.expect("the newest snapshot is kept"), perm)?;
+    }
+    let pruned = match max_age_seconds {
+        Some(max_age) => deltas::prune(project, now, now.saturating_sub(max_age))?,
+        None => deltas::Pruned::default(),
+    };
+    Ok(Cleanup {
+        removed_snapshots,
+        removed_deltas: pruned.deltas,
+        removed_checkpoints: pruned.checkpoints,
+        usage: data_usage(project)?,
+    })
+}
+
+/// Return the size of all files in `dir` and below, or `0` if it doesn't exist.
+pub fn directory_size(dir: &Path) -> Result<u64> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let mut size = 0;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += directory_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Return the snapshot commits of `project`, newest first, following first parents only.
+fn snapshot_commits<'repo>(
+    repo: &'repo git2::Repository,
+    project: &Project,
+) -> Result<Vec<git2::Commit<'repo>>> {
+    let mut snapshots = Vec::new();
+    let Some(head) = OplogHandle::new(&project.gb_dir()).oplog_head()? else {
+        return Ok(snapshots);
+    };
+    let mut next = Some(repo.find_commit(head)?);
+    while let Some(commit) = next {
+        next = commit.parent(0).ok();
+        if commit.tree()?.get_name("virtual_branches.toml").is_some() {
+            snapshots.push(commit);
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Return the size of the tree with `tree_id` and all objects it refers to that aren't in `seen` yet, and add
+/// them to `seen`.
+fn object_sizes(
+    repo: &git2::Repository,
+    odb: &git2::Odb<'_>,
+    tree_id: git2::Oid,
+    seen: &mut HashSet<git2::Oid>,
+) -> Result<u64> {
+    if !seen.insert(tree_id) {
+        return Ok(0);
+    }
+    let mut size = odb.read_header(tree_id)?.0 as u64;
+    for entry in repo.find_tree(tree_id)?.iter() {
+        match entry.kind() {
+            Some(git2::ObjectType::Tree) => size += object_sizes(repo, odb, entry.id(), seen)?,
+            Some(git2::ObjectType::Blob) if seen.insert(entry.id()) => {
+                size += odb.read_header(entry.id())?.0 as u64;
+            }
+            _ => {}
+        }
+    }
+    Ok(size)
+}
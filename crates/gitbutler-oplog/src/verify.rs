@@ -0,0 +1,319 @@
+//! Check that snapshots can actually be restored, and that the latest one recorded what is on disk.
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use gitbutler_oxidize::gix_to_git2_oid;
+use gitbutler_project::{
+    access::WorktreeWritePermission, machine_changes, Project, AUTO_TRACK_LIMIT_BYTES,
+};
+use gitbutler_repo::RepositoryExt;
+use serde::Serialize;
+
+use crate::{
+    deltas,
+    entry::{OperationKind, SnapshotDetails},
+    oplog::get_workdir_tree,
+    secrets, OplogExt,
+};
+
+/// What was found by [`verify_history()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryVerification {
+    /// The amount of snapshots that were checked.
+    pub snapshots: usize,
+    /// All problems that were found, newest snapshot first.
+    pub divergences: Vec<Divergence>,
+}
+
+/// A way in which restoring a snapshot wouldn't produce what was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum Divergence {
+    /// Objects that the snapshot refers to are missing from the repository, so it can't be restored.
+    #[serde(rename_all = "camelCase")]
+    MissingObjects {
+        #[serde(with = "gitbutler_serde::oid")]
+        snapshot_id: git2::Oid,
+        #[serde(with = "gitbutler_serde::oid_vec")]
+        object_ids: Vec<git2::Oid>,
+    },
+    /// A commit recorded by the snapshot can't be recreated with its original id, so restoring would fail.
+    #[serde(rename_all = "camelCase")]
+    CommitMismatch {
+        #[serde(with = "gitbutler_serde::oid")]
+        snapshot_id: git2::Oid,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit_id: git2::Oid,
+    },
+    /// The worktree can't be computed from the branches recorded by the snapshot.
+    #[serde(rename_all = "camelCase")]
+    UnrestorableWorktree {
+        #[serde(with = "gitbutler_serde::oid")]
+        snapshot_id: git2::Oid,
+        reason: String,
+    },
+    /// The files on disk differ from what the latest snapshot recorded, so restoring it wouldn't bring them back.
+    #[serde(rename_all = "camelCase")]
+    WorktreeChanged {
+        #[serde(with = "gitbutler_serde::oid")]
+        snapshot_id: git2::Oid,
+        paths: Vec<PathBuf>,
+    },
+}
+
+impl HistoryVerification {
+    /// Return `true` if the latest snapshot can't be restored, or doesn't match what is on disk.
+    fn latest_diverges(&self, latest: git2::Oid) -> bool {
+        self.divergences.iter().any(|divergence| match divergence {
+            Divergence::MissingObjects { snapshot_id, .. }
+            | Divergence::CommitMismatch { snapshot_id, .. }
+            | Divergence::UnrestorableWorktree { snapshot_id, .. }
+            | Divergence::WorktreeChanged { snapshot_id, .. } => *snapshot_id == latest,
+        })
+    }
+}
+
+/// Check every snapshot of `project` for missing objects and commits that can't be recreated, and compare the
+/// worktree recorded by the latest snapshot to what is on disk.
+///
+/// Files that were redacted from the latest snapshot aren't compared, as they are expected to differ.
+pub fn verify_history(project: &Project) -> Result<HistoryVerification> {
+    let repo = git2::Repository::open(&project.path)?;
+    let gix_repo = gitbutler_command_context::gix_repository_for_merging(project.path.as_path())?;
+    let odb = repo.odb()?;
+
+    let mut divergences = Vec::new();
+    let mut snapshots = 0;
+    let mut checked_trees = HashSet::new();
+    let mut wd_trees_cache = HashMap::new();
+    let mut next = project.oplog_head()?;
+    while let Some(snapshot_id) = next {
+        let commit = match repo.find_commit(snapshot_id) {
+            Ok(commit) => commit,
+            Err(_) => {
+                divergences.push(Divergence::MissingObjects {
+                    snapshot_id,
+                    object_ids: vec![snapshot_id],
+                });
+                break;
+            }
+        };
+        next = commit.parent_id(0).ok();
+        snapshots += 1;
+
+        let mut missing = Vec::new();
+        collect_missing(
+            &repo,
+            &odb,
+            commit.tree_id(),
+            &mut checked_trees,
+            &mut missing,
+        )?;
+        if !missing.is_empty() {
+            divergences.push(Divergence::MissingObjects {
+                snapshot_id,
+                object_ids: missing,
+            });
+            continue;
+        }
+        for commit_id in mismatched_commits(&repo, &commit.tree()?)? {
+            divergences.push(Divergence::CommitMismatch {
+                snapshot_id,
+                commit_id,
+            });
+        }
+
+        let workdir_tree = match get_workdir_tree(&mut wd_trees_cache, commit.id(), &gix_repo) {
+            Ok(tree) => tree,
+            Err(err) => {
+                divergences.push(Divergence::UnrestorableWorktree {
+                    snapshot_id,
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+        if snapshots == 1 {
+            let paths = worktree_changes(&repo, &commit.tree()?, gix_to_git2_oid(workdir_tree.id))?;
+            if !paths.is_empty() {
+                divergences.push(Divergence::WorktreeChanged { snapshot_id, paths });
+            }
+        }
+    }
+    Ok(HistoryVerification {
+        snapshots,
+        divergences,
+    })
+}
+
+/// Create a snapshot of the current state of `project` if the [verification](verify_history()) shows that the
+/// latest snapshot can't be restored or doesn't match what is on disk, and return its id.
+///
+/// Snapshots that can't be restored can't be repaired, but the correction snapshot provides a point to restore to
+/// that produces what is on disk. Note that the worktrees of the branches have to be up to date for it to
+/// record the latest changes.
+pub fn repair_history(
+    project: &Project,
+    verification: &HistoryVerification,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Option<git2::Oid>> {
+    match project.oplog_head()? {
+        Some(latest) if !verification.latest_diverges(latest) => Ok(None),
+        _ => project
+            .create_snapshot(SnapshotDetails::new(OperationKind::RepairHistory), perm)
+            .map(Some),
+    }
+}
+
+/// Create a snapshot of the current state of `project` if files on disk differ from what the latest snapshot
+/// recorded, as they were changed while nobody was watching, and return its id. A [delta](deltas::Delta) with the
+/// changed files is recorded along with it, so the changes show up in timelines and files can be reconstructed as
+/// of them.
+///
+/// Nothing is recorded if there is no snapshot yet, as there is no history with gaps then. Note that the
+/// worktrees of the branches have to be up to date for the snapshot to record the changes.
+pub fn record_offline_changes(
+    project: &Project,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Option<git2::Oid>> {
+    let Some(latest) = project.oplog_head()? else {
+        return Ok(None);
+    };
+    let repo = git2::Repository::open(&project.path)?;
+    let gix_repo = gitbutler_command_context::gix_repository_for_merging(project.path.as_path())?;
+    let workdir_tree = get_workdir_tree(&mut HashMap::new(), latest, &gix_repo)?;
+    let paths = worktree_changes(
+        &repo,
+        &repo.find_commit(latest)?.tree()?,
+        gix_to_git2_oid(workdir_tree.id),
+    )?;
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let mut details = SnapshotDetails::new(OperationKind::OfflineChanges);
+    details.body = Some(match paths.as_slice() {
+        [path] => format!("Changed {} while GitButler wasn't running", path.display()),
+        _ => format!(
+            "Changed {} files while GitButler wasn't running",
+            paths.len()
+        ),
+    });
+    let snapshot_id = project.create_snapshot(details, perm)?;
+
+    let branch = repo
+        .find_reference("HEAD")
+        .ok()
+        .and_then(|head| head.symbolic_target().map(ToOwned::to_owned))
+        .map(|name| name.strip_prefix("refs/heads/").unwrap_or(&name).to_owned());
+    let delta = deltas::Delta {
+        at: repo.find_commit(snapshot_id)?.time().seconds(),
+        classification: machine_changes::classify(
+            project.id,
+            &project.change_classification_rules,
+            &paths,
+        ),
+        paths,
+        branch,
+        checkpoint: None,
+        contents: Vec::new(),
+        bulk: false,
+        renames: Vec::new(),
+    };
+    if let Err(err) = deltas::record_delta(project, delta) {
+        tracing::warn!(?err, "failed to record offline changes as delta");
+    }
+    Ok(Some(snapshot_id))
+}
+
+/// Add the ids of all objects reachable from `tree_id` that are missing to `missing`, skipping trees
+/// in `checked_trees`, which are known to be complete.
+fn collect_missing(
+    repo: &git2::Repository,
+    odb: &git2::Odb<'_>,
+    tree_id: git2::Oid,
+    checked_trees: &mut HashSet<git2::Oid>,
+    missing: &mut Vec<git2::Oid>,
+) -> Result<()> {
+    if checked_trees.contains(&tree_id) {
+        return Ok(());
+    }
+    let Ok(tree) = repo.find_tree(tree_id) else {
+        missing.push(tree_id);
+        return Ok(());
+    };
+    let missing_before = missing.len();
+    for entry in tree.iter() {
+        match entry.kind() {
+            Some(git2::ObjectType::Tree) => {
+                collect_missing(repo, odb, entry.id(), checked_trees, missing)?
+            }
+            Some(git2::ObjectType::Blob) if !odb.exists(entry.id()) => missing.push(entry.id()),
+            // Submodules point to commits that aren't part of the repository.
+            _ => {}
+        }
+    }
+    if missing.len() == missing_before {
+        checked_trees.insert(tree_id);
+    }
+    Ok(())
+}
+
+/// Return the ids of the commits recorded in `snapshot_tree` whose data doesn't hash to their id, which is
+/// what restoring relies on to recreate commits that don't exist anymore.
+fn mismatched_commits(
+    repo: &git2::Repository,
+    snapshot_tree: &git2::Tree,
+) -> Result<Vec<git2::Oid>> {
+    let mut mismatched = Vec::new();
+    let Some(branches_entry) = snapshot_tree.get_name("virtual_branches") else {
+        return Ok(mismatched);
+    };
+    for branch_entry in repo.find_tree(branches_entry.id())?.iter() {
+        let branch_tree = repo.find_tree(branch_entry.id())?;
+        let Some(commits_entry) = branch_tree.get_name("commits") else {
+            continue;
+        };
+        for commit_entry in repo.find_tree(commits_entry.id())?.iter() {
+            let Some(commit_id) = commit_entry.name().and_then(|name| name.parse().ok()) else {
+                continue;
+            };
+            let commit_data = repo
+                .find_tree(commit_entry.id())?
+                .get_name("commit")
+                .map(|entry| repo.find_blob(entry.id()))
+                .transpose()?;
+            let matches = commit_data.is_some_and(|blob| {
+                git2::Oid::hash_object(git2::ObjectType::Commit, blob.content())
+                    .is_ok_and(|id| id == commit_id)
+            });
+            if !matches {
+                mismatched.push(commit_id);
+            }
+        }
+    }
+    Ok(mismatched)
+}
+
+/// Return the paths of the files on disk that differ from `workdir_tree_id`, the worktree recorded by the
+/// snapshot with `snapshot_tree`.
+fn worktree_changes(
+    repo: &git2::Repository,
+    snapshot_tree: &git2::Tree,
+    workdir_tree_id: git2::Oid,
+) -> Result<Vec<PathBuf>> {
+    let redacted = secrets::redacted_files(repo, snapshot_tree)?;
+    let current_tree = repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?;
+    let diff = repo.diff_tree_to_tree(
+        Some(&repo.find_tree(workdir_tree_id)?),
+        Some(&current_tree),
+        None,
+    )?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .filter(|path| !redacted.iter().any(|redacted| redacted == path))
+        .map(ToOwned::to_owned)
+        .collect())
+}
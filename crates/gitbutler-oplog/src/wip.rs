@@ -0,0 +1,87 @@
+//! Commit the whole worktree to a hidden ref of the current branch every now and then, for projects that opted in
+//! with [`Project::wip_snapshot_interval_seconds`].
+//!
+//! Unlike snapshots, WIP snapshots live in the repository itself under [`WIP_REFS_PREFIX`], so they survive when the
+//! GitButler data of the project is lost, and are pushed to the history backup remote along with the oplog.
+//! The index and `HEAD` are never touched.
+use anyhow::Result;
+use gitbutler_project::{Project, AUTO_TRACK_LIMIT_BYTES};
+use gitbutler_repo::{RepositoryExt, SignaturePurpose};
+
+/// The prefix of the refs holding the WIP snapshots of each branch, like `refs/gitbutler/wip/main`.
+pub const WIP_REFS_PREFIX: &str = "refs/gitbutler/wip/";
+/// The name WIP snapshots are recorded under while `HEAD` is detached.
+const DETACHED: &str = "detached";
+
+/// Return the ref holding the WIP snapshots of the branch with the short name `branch`.
+pub fn wip_ref(branch: &str) -> String {
+    format!("{WIP_REFS_PREFIX}{branch}")
+}
+
+/// Commit the worktree of `project` to the WIP ref of the current branch if WIP snapshots are enabled and their
+/// interval passed since the previous one, as of `at` seconds since the Unix epoch.
+///
+/// Return the id of the new commit, or `None` if none was due or the worktree didn't change since the previous one.
+/// Commits of the same branch are chained, and the first one has the commit of `HEAD` as parent.
+pub fn write_wip_snapshot(project: &Project, at: i64) -> Result<Option<git2::Oid>> {
+    let Some(interval) = project.wip_snapshot_interval_seconds else {
+        return Ok(None);
+    };
+    let repo = git2::Repository::open(&project.path)?;
+    let Ok(head) = repo.head() else {
+        return Ok(None);
+    };
+    let branch = if head.is_branch() {
+        head.shorthand().unwrap_or(DETACHED).to_owned()
+    } else {
+        DETACHED.to_owned()
+    };
+    let reference = wip_ref(&branch);
+    let previous = repo
+        .find_reference(&reference)
+        .ok()
+        .and_then(|reference| reference.peel_to_commit().ok());
+    if previous
+        .as_ref()
+        .is_some_and(|previous| at - previous.time().seconds() < interval as i64)
+    {
+        return Ok(None);
+    }
+
+    let tree = repo.create_wd_tree(AUTO_TRACK_LIMIT_BYTES)?;
+    if previous
+        .as_ref()
+        .is_some_and(|previous| previous.tree_id() == tree.id())
+    {
+        return Ok(None);
+    }
+    let head_commit = head.peel_to_commit()?;
+    let parent = previous.unwrap_or_else(|| head_commit.clone());
+    let author = gitbutler_repo::signature(SignaturePurpose::Author)?;
+    let committer = gitbutler_repo::signature(SignaturePurpose::Committer)?;
+    let id = repo.commit(
+        Some(&reference),
+        &author,
+        &committer,
+        &format!(
+            "WIP snapshot of {branch} at {at}\n\nHEAD: {}",
+            head_commit.id()
+        ),
+        &tree,
+        &[&parent],
+    )?;
+    Ok(Some(id))
+}
+
+/// Return the WIP refs of the repository at `repo` along with the commit they point to, sorted by name.
+pub fn list_wip_refs(repo: &git2::Repository) -> Result<Vec<(String, git2::Oid)>> {
+    let mut refs = Vec::new();
+    for reference in repo.references_glob(&format!("{WIP_REFS_PREFIX}*"))? {
+        let reference = reference?;
+        if let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) {
+            refs.push((name.to_owned(), commit.id()));
+        }
+    }
+    refs.sort();
+    Ok(refs)
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use gitbutler_error::error::Code;
+use gitbutler_oplog::{bookmarks, deltas, encryption, heartbeat};
+use gitbutler_project::HistoryEncryption;
+use gitbutler_storage::encryption as storage_encryption;
+use gitbutler_testsupport::timeline::record_delta;
+
+use super::{deltas::typing_into_a_file, *};
+
+#[test]
+fn history_is_encrypted_and_decrypted_with_a_passphrase() -> anyhow::Result<()> {
+    let test = Test::default();
+    let project = &test.project;
+    let projects = projects::Controller::from_path(test.data_dir.as_ref().unwrap().path());
+    fs::create_dir_all(project.gb_dir())?;
+    let deltas_file = project.gb_dir().join("deltas.jsonl");
+    let segments_file = project.gb_dir().join("deltas.segments");
+    let heartbeats_file = project.gb_dir().join("heartbeats");
+    let bookmarks_file = project.gb_dir().join("bookmarks.json");
+    // Plain history recorded before, most of which is sealed into a segment.
+    fs::write(&deltas_file, typing_into_a_file(6000))?;
+    record_delta(project, 20_000, &["a.txt"], None)?;
+    record_delta(project, 20_010, &["b.txt"], None)?;
+    heartbeat::record_heartbeat(project, Path::new("plans.txt"), 20_000)?;
+    let bookmark = bookmarks::add_bookmark(project, 20_000, "launch day", None)?;
+    let count = |project: &Project| -> anyhow::Result<usize> {
+        Ok(deltas::list_deltas(project, 0..i64::MAX, None, None)?.len())
+    };
+
+    let encrypted = &encryption::encrypt_history(&projects, project, Some("correct horse"))?;
+    assert!(matches!(
+        encrypted.history_encryption,
+        HistoryEncryption::Passphrase { .. }
+    ));
+    assert_eq!(
+        projects.get(project.id)?.history_encryption,
+        encrypted.history_encryption,
+        "the settings are stored"
+    );
+    let deltas = fs::read_to_string(&deltas_file)?;
+    assert!(!deltas.is_empty());
+    assert!(deltas.lines().all(storage_encryption::is_encrypted_line));
+    assert!(storage_encryption::is_encrypted(
+        &fs::read(&segments_file)?[4..]
+    ));
+    let heartbeats = fs::read_to_string(&heartbeats_file)?;
+    assert!(!heartbeats.contains("plans.txt"));
+    assert!(storage_encryption::is_encrypted(&fs::read(
+        &bookmarks_file
+    )?));
+    assert_eq!(count(encrypted)?, 6002, "the history is still readable");
+
+    record_delta(encrypted, 20_020, &["c.txt"], None)?;
+    let deltas = fs::read_to_string(&deltas_file)?;
+    assert!(
+        storage_encryption::is_encrypted_line(deltas.lines().last().unwrap()),
+        "new deltas are encrypted as well"
+    );
+
+    storage_encryption::lock(&project.gb_dir());
+    let err = deltas::list_deltas(encrypted, 0..i64::MAX, None, None).unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::HistoryLocked));
+    assert!(bookmarks::list_bookmarks(encrypted, 0..i64::MAX).is_err());
+    assert!(
+        deltas::recover(encrypted)?.is_empty(),
+        "locked deltas aren't mistaken for corrupt ones"
+    );
+    assert_eq!(fs::read_to_string(&deltas_file)?, deltas);
+
+    let err = encryption::unlock(encrypted, Some("wrong")).unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::HistoryLocked));
+    encryption::unlock(encrypted, Some("correct horse"))?;
+    assert_eq!(count(encrypted)?, 6003);
+    assert_eq!(
+        bookmarks::list_bookmarks(encrypted, 0..i64::MAX)?,
+        [bookmark.clone()]
+    );
+
+    let decrypted = &encryption::decrypt_history(&projects, encrypted)?;
+    assert_eq!(decrypted.history_encryption, HistoryEncryption::None);
+    assert!(!encryption::is_locked(decrypted));
+    assert!(!fs::read_to_string(&deltas_file)?
+        .lines()
+        .any(storage_encryption::is_encrypted_line));
+    assert!(!storage_encryption::is_encrypted(
+        &fs::read(&segments_file)?[4..]
+    ));
+    assert!(fs::read_to_string(&heartbeats_file)?.contains("plans.txt"));
+    assert!(fs::read_to_string(&bookmarks_file)?.contains("launch day"));
+    assert_eq!(count(decrypted)?, 6003);
+    assert_eq!(
+        bookmarks::list_bookmarks(decrypted, 0..i64::MAX)?,
+        [bookmark]
+    );
+    Ok(())
+}
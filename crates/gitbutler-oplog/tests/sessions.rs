@@ -0,0 +1,397 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_oplog::{
+    activity, bookmarks,
+    deltas::{self, Delta},
+    entry::{OperationKind, SnapshotDetails},
+    file_history::{self, FileHistoryEntry},
+    heartbeat, share, OplogExt,
+};
+use gitbutler_project::machine_changes;
+use gitbutler_testsupport::timeline::{record_delta, Timeline};
+
+use super::*;
+
+#[test]
+fn heartbeats_extend_sessions() -> anyhow::Result<()> {
+    let Test { project, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let snapshots = project.list_snapshots(100, None)?;
+    let snapshot_time = snapshots.last().unwrap().created_at.seconds();
+    let snapshots = snapshots.len();
+    let minutes = |count: i64| snapshot_time + count * 60;
+    let file = Path::new("file.txt");
+    assert!(heartbeat::record_heartbeat(project, file, minutes(10))?);
+    assert!(
+        !heartbeat::record_heartbeat(project, file, minutes(10) + 1)?,
+        "too close to the previous one"
+    );
+    assert!(heartbeat::record_heartbeat(project, file, minutes(20))?);
+    // Too far from the others to continue the session.
+    assert!(heartbeat::record_heartbeat(project, file, minutes(60))?);
+
+    let summary = project.activity_summary(0..i64::MAX)?;
+    assert_eq!(summary.sessions.len(), 2);
+    let reading = &summary.sessions[0];
+    assert_eq!((reading.start, reading.end), (snapshot_time, minutes(20)));
+    assert_eq!((reading.snapshots, reading.heartbeats), (snapshots, 2));
+    assert_eq!(
+        (
+            summary.sessions[1].snapshots,
+            summary.sessions[1].heartbeats
+        ),
+        (0, 1)
+    );
+    assert_eq!(summary.snapshots, snapshots, "heartbeats aren't snapshots");
+    assert_eq!(summary.active_minutes, 20 + 1);
+
+    assert_eq!(
+        project
+            .activity_summary(minutes(30)..i64::MAX)?
+            .sessions
+            .len(),
+        1,
+        "heartbeats outside of the range are ignored"
+    );
+    Ok(())
+}
+
+#[test]
+fn sessions_follow_a_scripted_timeline() -> anyhow::Result<()> {
+    let Test { project, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    // Far enough from the snapshots of setting the base branch to not be part of their session.
+    let start = project.list_snapshots(1, None)?[0].created_at.seconds() + 24 * 60 * 60;
+    let minutes = |count: u64| Duration::from_secs(count * 60);
+    let played = Timeline::starting_at(start)
+        .edit("file.txt", "first\n")
+        .snapshot()
+        .wait(minutes(10))
+        .edit("file.txt", "second\n")
+        .snapshot()
+        .wait(minutes(5))
+        .heartbeat("file.txt")
+        .wait(Duration::from_secs(
+            heartbeat::MIN_INTERVAL_SECONDS as u64 - 1,
+        ))
+        .heartbeat("file.txt")
+        .wait(Duration::from_secs(
+            activity::SESSION_GAP_SECONDS as u64 + 1,
+        ))
+        .edit("dir/other.txt", "later\n")
+        .snapshot()
+        .play(project)?;
+    assert_eq!(played.deltas.len(), 3);
+    assert_eq!(
+        played.heartbeats,
+        [start + 15 * 60],
+        "the second heartbeat was too close to the first"
+    );
+
+    let sessions = project.list_sessions(start..i64::MAX, None)?;
+    assert_eq!(
+        sessions.len(),
+        2,
+        "the gap after the heartbeat ends the session"
+    );
+    assert_eq!(
+        (sessions[0].start, sessions[0].end),
+        (start, start + 15 * 60)
+    );
+    assert_eq!((sessions[0].snapshots, sessions[0].heartbeats), (2, 1));
+    let (later, _) = played.snapshots[2];
+    assert_eq!((sessions[1].start, sessions[1].end), (later, later));
+    Ok(())
+}
+
+#[test]
+fn bookmarks_are_returned_with_sessions() -> anyhow::Result<()> {
+    let Test { project, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let session_start = project.activity_summary(0..i64::MAX)?.sessions[0].start;
+    let in_session = bookmarks::add_bookmark(project, session_start, "it works here", Some("✅"))?;
+    let later = bookmarks::add_bookmark(project, session_start + 3600, " broken ", None)?;
+    assert_eq!(later.note, "broken", "surrounding whitespace is removed");
+    assert_ne!(in_session.id, later.id);
+    assert!(
+        bookmarks::add_bookmark(project, session_start, "  ", None).is_err(),
+        "a bookmark needs a note or an emoji"
+    );
+
+    let summary = project.activity_summary(0..i64::MAX)?;
+    assert_eq!(summary.sessions[0].bookmarks, [in_session.clone()]);
+    assert_eq!(
+        summary.bookmarks,
+        [in_session.clone(), later.clone()],
+        "bookmarks outside of sessions are returned as well"
+    );
+
+    let updated = bookmarks::update_bookmark(project, later.id, "fixed", Some("🎉"))?;
+    assert_eq!(updated.emoji.as_deref(), Some("🎉"));
+    assert_eq!(
+        bookmarks::remove_bookmark(project, in_session.id)?,
+        in_session
+    );
+    assert_eq!(
+        bookmarks::list_bookmarks(project, 0..i64::MAX)?,
+        [updated],
+        "bookmarks are persisted"
+    );
+    assert!(bookmarks::remove_bookmark(project, in_session.id).is_err());
+    Ok(())
+}
+
+#[test]
+fn external_git_operations_end_sessions() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    let mut guard = project.exclusive_worktree_access();
+    fs::write(repository.path().join("file.txt"), "one\n")?;
+    project.create_snapshot(
+        SnapshotDetails::new(OperationKind::FileChanges),
+        guard.write_permission(),
+    )?;
+    project.create_snapshot(
+        SnapshotDetails::new(OperationKind::ExternalGitOperation),
+        guard.write_permission(),
+    )?;
+    fs::write(repository.path().join("file.txt"), "one\ntwo\n")?;
+    project.create_snapshot(
+        SnapshotDetails::new(OperationKind::FileChanges),
+        guard.write_permission(),
+    )?;
+
+    let summary = project.activity_summary(0..i64::MAX)?;
+    assert_eq!(
+        summary.sessions.len(),
+        2,
+        "the git operation ends the session even though snapshots were taken in a row"
+    );
+    assert_eq!(
+        summary.snapshots,
+        project.list_snapshots(100, None)?.len() - 1,
+        "the git operation itself isn't activity"
+    );
+    Ok(())
+}
+
+#[test]
+fn sessions_are_shared_as_encrypted_bundles() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ..
+    } = &Test::default();
+
+    let hour = 60 * 60;
+    fs::write(repository.path().join("file.txt"), "one\n")?;
+    record_delta(project, hour, &["file.txt"], Some("main"))?;
+    heartbeat::record_heartbeat(project, Path::new("file.txt"), hour)?;
+
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("session.gbsession");
+    assert!(
+        share::share_session(project, hour + 1, None, &path).is_err(),
+        "sessions are found by their start"
+    );
+    let shared = share::share_session(project, hour, Some("passphrase"), &path)?;
+    assert_eq!(shared.deltas, 1);
+    assert_eq!(shared.files, 1);
+    assert!(shared.encrypted);
+
+    assert!(share::open_shared_session(&path, None).is_err());
+    assert!(share::open_shared_session(&path, Some("wrong")).is_err());
+    let session = share::open_shared_session(&path, Some("passphrase"))?;
+    assert_eq!(session.start, hour);
+    assert_eq!(session.branch.as_deref(), Some("main"));
+    assert_eq!(session.base[0].path, Path::new("file.txt"));
+    let blob_id = session.deltas[0].contents[0]
+        .blob_id
+        .expect("the content was recorded");
+    assert_eq!(session.content(blob_id)?.as_deref(), Some(&b"one\n"[..]));
+
+    share::share_session(project, hour, None, &path)?;
+    assert_eq!(
+        share::open_shared_session(&path, None)?,
+        session,
+        "the bundle doesn't depend on the encryption"
+    );
+    Ok(())
+}
+
+#[test]
+fn sessions_and_deltas_are_listed_by_branch() -> anyhow::Result<()> {
+    let Test { project, .. } = &Test::default();
+
+    let hour = 60 * 60;
+    for (at, branch) in [(hour, "main"), (3 * hour, "feature-x")] {
+        record_delta(project, at, &["file.txt"], Some(branch))?;
+        heartbeat::record_heartbeat(project, Path::new("file.txt"), at)?;
+    }
+    // Only reading, so the branch is the one of the change before.
+    heartbeat::record_heartbeat(project, Path::new("file.txt"), 5 * hour)?;
+
+    let at = |deltas: Vec<Delta>| deltas.iter().map(|delta| delta.at).collect::<Vec<_>>();
+    assert_eq!(
+        at(deltas::list_deltas(
+            project,
+            0..i64::MAX,
+            None,
+            Some("feature-x")
+        )?),
+        [3 * hour]
+    );
+    assert!(deltas::list_deltas(project, 0..i64::MAX, None, Some("other"))?.is_empty());
+
+    let branches: Vec<_> = project
+        .list_sessions(0..i64::MAX, None)?
+        .into_iter()
+        .map(|session| (session.start, session.branch))
+        .collect();
+    assert_eq!(
+        branches,
+        [
+            (hour, Some("main".to_owned())),
+            (3 * hour, Some("feature-x".to_owned())),
+            (5 * hour, Some("feature-x".to_owned())),
+        ]
+    );
+    assert_eq!(
+        project
+            .list_sessions(0..i64::MAX, Some("main"))?
+            .iter()
+            .map(|session| session.start)
+            .collect::<Vec<_>>(),
+        [hour]
+    );
+    Ok(())
+}
+
+#[test]
+fn file_history_follows_renames() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ctx,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("a.txt"), "first\n")?;
+    repository.commit_all("add a");
+    fs::rename(
+        repository.path().join("a.txt"),
+        repository.path().join("b.txt"),
+    )?;
+    repository.commit_all("rename to b");
+    fs::write(repository.path().join("b.txt"), "first\nsecond\n")?;
+    repository.commit_all("change b");
+    repository.push();
+
+    gitbutler_branch_actions::set_base_branch(ctx, &"refs/remotes/origin/master".parse()?)?;
+    gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("b.txt"), "first\nsecond\nthird\n")?;
+    gitbutler_branch_actions::list_virtual_branches(ctx)?;
+    let mut guard = project.exclusive_worktree_access();
+    project.create_snapshot(
+        SnapshotDetails::new(OperationKind::FileChanges),
+        guard.write_permission(),
+    )?;
+    drop(guard);
+
+    let history = file_history::file_history(project, Path::new("b.txt"))?;
+    let commits: Vec<_> = history
+        .iter()
+        .filter_map(|entry| match entry {
+            FileHistoryEntry::Commit { summary, path, .. } => {
+                Some((summary.as_str(), path.to_str().unwrap()))
+            }
+            FileHistoryEntry::Session { .. } => None,
+        })
+        .collect();
+    assert_eq!(
+        commits,
+        [
+            ("change b", "b.txt"),
+            ("rename to b", "b.txt"),
+            ("add a", "a.txt")
+        ]
+    );
+    let session = project.activity_summary(0..i64::MAX)?.sessions[0].clone();
+    assert!(history.contains(&FileHistoryEntry::Session { session }));
+    Ok(())
+}
+
+#[test]
+fn file_history_follows_renames_recorded_by_deltas() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("a.txt"), "first\n")?;
+    repository.commit_all("add a");
+    let rename = |at: i64, from: &str, to: &str| -> anyhow::Result<Delta> {
+        fs::rename(repository.path().join(from), repository.path().join(to))?;
+        let paths = vec![PathBuf::from(from), PathBuf::from(to)];
+        deltas::record_delta(
+            project,
+            Delta {
+                at,
+                classification: machine_changes::classify(
+                    project.id,
+                    &project.change_classification_rules,
+                    &paths,
+                ),
+                paths,
+                branch: None,
+                checkpoint: None,
+                contents: Vec::new(),
+                bulk: false,
+                renames: vec![deltas::DeltaRename {
+                    from: from.into(),
+                    to: to.into(),
+                }],
+            },
+        )
+    };
+    let renamed = rename(100, "a.txt", "b.txt")?;
+    assert_eq!(
+        renamed
+            .contents
+            .iter()
+            .map(|content| (content.path.to_str().unwrap(), content.blob_id.is_some()))
+            .collect::<Vec<_>>(),
+        [("a.txt", false), ("b.txt", true)],
+        "the content moved along"
+    );
+    rename(200, "b.txt", "c.txt")?;
+
+    let recorded = deltas::list_deltas(project, 0..i64::MAX, None, None)?;
+    assert_eq!(
+        deltas::former_paths(&recorded, Path::new("c.txt")),
+        [PathBuf::from("b.txt"), PathBuf::from("a.txt")]
+    );
+    let history = file_history::file_history(project, Path::new("c.txt"))?;
+    assert!(
+        history.iter().any(|entry| matches!(
+            entry,
+            FileHistoryEntry::Commit { summary, path, .. } if summary == "add a" && path == Path::new("a.txt")
+        )),
+        "the commit is found by the name the file has in HEAD"
+    );
+    Ok(())
+}
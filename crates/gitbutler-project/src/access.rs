@@ -3,7 +3,7 @@ use parking_lot::RawRwLock;
 use std::path::PathBuf;
 use std::{collections::BTreeMap, sync::Arc};
 
-use crate::{Project, ProjectId};
+use crate::Project;
 
 /// Access Control
 impl Project {
@@ -40,7 +40,7 @@ impl Project {
     pub fn exclusive_worktree_access(&self) -> WriteWorkspaceGuard {
         let mut map = WORKTREE_LOCKS.lock();
         WriteWorkspaceGuard {
-            _inner: map.entry(self.id).or_default().write_arc(),
+            _inner: map.entry(self.path.clone()).or_default().write_arc(),
             perm: WorktreeWritePermission(()),
         }
     }
@@ -50,7 +50,7 @@ impl Project {
     /// thus block readers to prevent writer starvation.
     pub fn shared_worktree_access(&self) -> WorkspaceReadGuard {
         let mut map = WORKTREE_LOCKS.lock();
-        WorkspaceReadGuard(map.entry(self.id).or_default().read_arc())
+        WorkspaceReadGuard(map.entry(self.path.clone()).or_default().read_arc())
     }
 }
 
@@ -101,7 +101,8 @@ impl WorktreeWritePermission {
     }
 }
 
-static WORKTREE_LOCKS: parking_lot::Mutex<BTreeMap<ProjectId, Arc<parking_lot::RwLock<()>>>> =
+/// The locks by worktree directory, which is shared by all projects of a repository.
+static WORKTREE_LOCKS: parking_lot::Mutex<BTreeMap<PathBuf, Arc<parking_lot::RwLock<()>>>> =
     parking_lot::Mutex::new(BTreeMap::new());
 
 /// A file-based lock that can indicate exclusive access.
@@ -140,6 +141,21 @@ impl LockFile {
         })
     }
 
+    /// Wait until the resource is locked, or pretend it was locked if the underlying filesystem doesn't support it.
+    pub fn lock(&mut self) -> Result<(), fslock::Error> {
+        self.inner.lock().or_else(|err| {
+            if err.kind() == std::io::ErrorKind::Unsupported {
+                tracing::warn!(
+                    "Filesystem hosting '{}' doesn't support file locking - pretending to own lock to avoid failure",
+                    self.path.display()
+                );
+                Ok(())
+            } else {
+                Err(err)
+            }
+        })
+    }
+
     /// Drop the lock on this file, or do nothing if we don't own the lock.
     pub fn unlock(&mut self) -> Result<(), fslock::Error> {
         if !self.inner.owns_lock() {
@@ -1,10 +1,11 @@
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 use anyhow::{anyhow, bail, Context, Result};
 use gitbutler_error::error;
 
-use super::{storage, storage::UpdateRequest, Project, ProjectId};
-use crate::AuthKey;
+use super::{discover, storage, storage::UpdateRequest, Project, ProjectId};
+use crate::{AuthKey, DiscoveredProject, HistoryEncryption, StorageLocation, WatcherMode};
 
 #[derive(Clone)]
 pub struct Controller {
@@ -21,14 +22,49 @@ impl Controller {
         }
     }
 
+    /// Like [`add()`](Self::add()), but for each of `paths`, returning one result per path in the same order.
+    ///
+    /// A failure to add one path doesn't prevent the others from being added.
+    pub fn add_many<P: AsRef<Path>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Vec<Result<Project>> {
+        paths.into_iter().map(|path| self.add(path)).collect()
+    }
+
+    /// Find repositories in `root` and below, descending at most `max_depth` directories, which can then be
+    /// added with [`add_many()`](Self::add_many()).
+    pub fn discover(&self, root: &Path, max_depth: usize) -> Result<Vec<DiscoveredProject>> {
+        let added: Vec<_> = self
+            .list()?
+            .into_iter()
+            .filter(|project| !project.is_subproject())
+            .map(|project| project.path)
+            .collect();
+        let mut discovered = discover::discover(root, max_depth)?;
+        for project in &mut discovered {
+            project.already_added = gix::path::realpath(&project.path)
+                .is_ok_and(|path| added.contains(&gitbutler_fs::paths::normalize(&path)));
+        }
+        Ok(discovered)
+    }
+
     pub fn add<P: AsRef<Path>>(&self, path: P) -> Result<Project> {
-        let path = path.as_ref();
+        let path = gitbutler_fs::paths::normalize(path.as_ref());
+        let path = path.as_path();
         let all_projects = self
             .projects_storage
             .list()
             .context("failed to list projects from storage")?;
-        if all_projects.iter().any(|project| project.path == path) {
-            bail!("project already exists");
+        if let Some(existing) = all_projects
+            .iter()
+            .find(|project| project.path == path && !project.is_subproject())
+        {
+            if !existing.is_deleted() {
+                bail!("project already exists");
+            }
+            // Adding it again is the user's way of saying they don't want the old one back.
+            self.purge(existing.id)?;
         }
         if !path.exists() {
             bail!("path not found");
@@ -36,6 +72,14 @@ impl Controller {
         if !path.is_dir() {
             bail!("not a directory");
         }
+        if path.read_dir()?.next().is_none() {
+            return Err(
+                anyhow!("the directory is empty").context(error::Context::new_static(
+                    error::Code::ProjectDirectoryEmpty,
+                    "The directory is empty, but can be initialized as a new project",
+                )),
+            );
+        }
         match gix::open_opts(path, gix::open::Options::isolated()) {
             Ok(repo) if repo.is_bare() => {
                 bail!("bare repositories are unsupported");
@@ -69,11 +113,18 @@ impl Controller {
             .next_back()
             .map_or_else(|| id.clone(), |p| p.to_str().unwrap().to_string());
 
+        let path = gitbutler_fs::paths::normalize(&gix::path::realpath(path)?);
+        // Network filesystems may not notify about changes at all.
+        let watcher_mode = match StorageLocation::detect(&path) {
+            StorageLocation::NetworkMount { .. } => WatcherMode::Polling,
+            StorageLocation::Local | StorageLocation::CloudSync { .. } => WatcherMode::Native,
+        };
         let project = Project {
             id: ProjectId::generate(),
             title,
-            path: gix::path::realpath(path)?,
+            path,
             api: None,
+            watcher_mode,
             ..Default::default()
         };
 
@@ -89,6 +140,65 @@ impl Controller {
         Ok(project)
     }
 
+    /// Add the worktree-relative `subdirectory` of the repository of the project with `parent_id` as a logical
+    /// project of its own, with its own history, deltas and virtual branches. Its watcher only covers `subdirectory`.
+    pub fn add_subproject(&self, parent_id: ProjectId, subdirectory: &Path) -> Result<Project> {
+        let parent = self.get(parent_id)?;
+        if parent.is_subproject() {
+            bail!("sub-projects can only be added to the project of a whole repository");
+        }
+        if subdirectory.as_os_str().is_empty()
+            || !subdirectory
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            bail!(
+                "'{}' isn't a directory within the worktree",
+                subdirectory.display()
+            );
+        }
+        if !parent.path.join(subdirectory).is_dir() {
+            bail!("'{}' isn't a directory", subdirectory.display());
+        }
+        let already_added = self.projects_storage.list()?.into_iter().any(|project| {
+            !project.is_deleted()
+                && project.path == parent.path
+                && project.subdirectory.as_deref() == Some(subdirectory)
+        });
+        if already_added {
+            bail!("sub-project '{}' already exists", subdirectory.display());
+        }
+
+        let project = Project {
+            id: ProjectId::generate(),
+            title: format!("{}/{}", parent.title, subdirectory.display()),
+            path: parent.path.clone(),
+            subdirectory: Some(subdirectory.to_owned()),
+            watcher_mode: parent.watcher_mode,
+            watch_include_paths: vec![subdirectory.to_owned()],
+            ..Default::default()
+        };
+        self.projects_storage
+            .add(&project)
+            .context("failed to add sub-project to storage")?;
+        if let Err(error) = std::fs::create_dir_all(project.gb_dir()) {
+            tracing::error!(project_id = %project.id, ?error, "failed to create {:?} on sub-project add", project.gb_dir());
+        }
+        Ok(project)
+    }
+
+    /// Return the sub-projects in the repository of the project with `id`, by title.
+    pub fn subprojects(&self, id: ProjectId) -> Result<Vec<Project>> {
+        let project = self.get(id)?;
+        let mut subprojects: Vec<_> = self
+            .list()?
+            .into_iter()
+            .filter(|other| other.is_subproject() && other.path == project.path)
+            .collect();
+        subprojects.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(subprojects)
+    }
+
     pub fn update(&self, project: &UpdateRequest) -> Result<Project> {
         #[cfg(not(windows))]
         if let Some(AuthKey::Local {
@@ -149,6 +259,9 @@ impl Controller {
     fn get_inner(&self, id: ProjectId, validate: bool) -> Result<Project> {
         #[cfg_attr(not(windows), allow(unused_mut))]
         let mut project = self.projects_storage.get(id)?;
+        if project.is_deleted() {
+            bail!("project {id} was deleted");
+        }
         if validate {
             let worktree_dir = &project.path;
             if gix::open_opts(worktree_dir, gix::open::Options::isolated()).is_err() {
@@ -187,15 +300,110 @@ impl Controller {
         Ok(project)
     }
 
+    /// List all projects, except for the ones that were [deleted](Self::delete()).
     pub fn list(&self) -> Result<Vec<Project>> {
-        self.projects_storage.list()
+        Ok(self
+            .projects_storage
+            .list()?
+            .into_iter()
+            .filter(|project| !project.is_deleted())
+            .collect())
+    }
+
+    /// Put the project with `id` into the group called `group`, or take it out of its group with `None` or an
+    /// empty name.
+    pub fn set_group(&self, id: ProjectId, group: Option<String>) -> Result<Project> {
+        let group = group
+            .map(|group| group.trim().to_owned())
+            .filter(|group| !group.is_empty());
+        self.projects_storage.set_group(id, group)
+    }
+
+    /// Record that the history of the project with `id` is encrypted as `encryption`, which is only to be done
+    /// while its history is encrypted or decrypted accordingly.
+    pub fn set_history_encryption(
+        &self,
+        id: ProjectId,
+        encryption: HistoryEncryption,
+    ) -> Result<Project> {
+        self.projects_storage.set_history_encryption(id, encryption)
+    }
+
+    /// Make [`list()`](Self::list()) return the projects with `ids` first, in that order, followed by all other
+    /// projects by title.
+    pub fn reorder(&self, ids: &[ProjectId]) -> Result<()> {
+        self.projects_storage.reorder(ids)
     }
 
+    /// List the projects that were [deleted](Self::delete()) but not yet purged.
+    pub fn list_deleted(&self) -> Result<Vec<Project>> {
+        Ok(self
+            .projects_storage
+            .list()?
+            .into_iter()
+            .filter(Project::is_deleted)
+            .collect())
+    }
+
+    /// Mark the project with `id` as deleted, hiding it from [`list()`](Self::list()) and [`get()`](Self::get()).
+    ///
+    /// Its data is kept until it's [purged](Self::purge_expired()), so the deletion can be [undone](Self::undo_delete()).
     pub fn delete(&self, id: ProjectId) -> Result<()> {
         let Some(project) = self.projects_storage.try_get(id)? else {
             return Ok(());
         };
+        if project.is_deleted() {
+            return Ok(());
+        }
+        self.projects_storage
+            .set_deleted_at(id, Some(SystemTime::now()))?;
+        Ok(())
+    }
+
+    /// Restore the project with `id` after it was [deleted](Self::delete()), as long as it wasn't purged yet.
+    pub fn undo_delete(&self, id: ProjectId) -> Result<Project> {
+        let project = self
+            .projects_storage
+            .try_get(id)?
+            .with_context(|| format!("project {id} was already purged and can't be restored"))?;
+        if !project.is_deleted() {
+            return Ok(project);
+        }
+        self.projects_storage.set_deleted_at(id, None)
+    }
+
+    /// Purge all projects that were [deleted](Self::delete()) more than `grace_period` ago,
+    /// returning the ids of the purged projects.
+    pub fn purge_expired(&self, grace_period: Duration) -> Result<Vec<ProjectId>> {
+        let now = SystemTime::now();
+        let mut purged = Vec::new();
+        for project in self.list_deleted()? {
+            let expired = project.deleted_at.is_some_and(|deleted_at| {
+                now.duration_since(deleted_at).unwrap_or_default() >= grace_period
+            });
+            if expired {
+                self.purge(project.id)?;
+                purged.push(project.id);
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Remove the project with `id` along with all of its data, whether it was deleted before or not.
+    ///
+    /// The sub-projects of its repository are removed as well, as their data is kept within that of the project.
+    pub fn purge(&self, id: ProjectId) -> Result<()> {
+        let Some(project) = self.projects_storage.try_get(id)? else {
+            return Ok(());
+        };
 
+        if !project.is_subproject() {
+            for subproject in self.projects_storage.list()? {
+                if subproject.is_subproject() && subproject.path == project.path {
+                    self.purge(subproject.id)?;
+                }
+            }
+        }
         self.projects_storage.purge(project.id)?;
 
         if let Err(error) = std::fs::remove_dir_all(self.project_metadata_dir(project.id)) {
@@ -206,7 +414,7 @@ impl Controller {
 
         if project.gb_dir().exists() {
             if let Err(error) = std::fs::remove_dir_all(project.gb_dir()) {
-                tracing::error!(project_id = %project.id, ?error, "failed to remove {:?} on project delete", project.gb_dir());
+                tracing::error!(project_id = %project.id, ?error, "failed to remove {:?} on project purge", project.gb_dir());
             }
         }
 
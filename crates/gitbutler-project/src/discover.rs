@@ -0,0 +1,110 @@
+//! Find git repositories below a directory, so all of them can be added as projects at once.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// Directories that hold dependencies or build outputs, which never contain repositories worth adding.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "build", "dist"];
+
+/// A repository that was found by [`Controller::discover()`](crate::Controller::discover()).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredProject {
+    /// The worktree of the repository.
+    pub path: PathBuf,
+    /// The title the project would have, the name of its directory.
+    pub title: String,
+    pub remotes: Vec<DiscoveredRemote>,
+    /// The time of the commit at `HEAD` in seconds since the Unix epoch, or `None` if there are no commits yet.
+    pub last_commit_at: Option<i64>,
+    /// If `true`, the repository is a project already.
+    pub already_added: bool,
+}
+
+/// A remote of a [`DiscoveredProject`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredRemote {
+    pub name: String,
+    pub url: Option<String>,
+}
+
+/// Find all repositories with a `.git` directory in `root` and below, descending at most `max_depth` directories.
+///
+/// Repositories aren't descended into, and neither are hidden directories or those holding dependencies or
+/// build outputs, like `node_modules`. Unreadable directories are skipped.
+/// The returned projects are sorted by path and [`already_added`](DiscoveredProject::already_added) is `false`.
+pub(crate) fn discover(root: &Path, max_depth: usize) -> Result<Vec<DiscoveredProject>> {
+    if !root.is_dir() {
+        bail!("'{}' is not a directory", root.display());
+    }
+    let mut projects = Vec::new();
+    let mut dirs = vec![(root.to_owned(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        if dir.join(".git").is_dir() {
+            match inspect(&dir) {
+                Ok(project) => projects.push(project),
+                Err(err) => {
+                    tracing::debug!(path = %dir.display(), ?err, "skipping unreadable repository")
+                }
+            }
+            continue;
+        }
+        if depth == max_depth {
+            continue;
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                tracing::debug!(path = %dir.display(), ?err, "skipping unreadable directory");
+                continue;
+            }
+        };
+        for entry in entries.filter_map(Result::ok) {
+            // Symlinks aren't followed to avoid cycles and finding the same repository twice.
+            if !entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                continue;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            dirs.push((entry.path(), depth + 1));
+        }
+    }
+    projects.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(projects)
+}
+
+fn inspect(path: &Path) -> Result<DiscoveredProject> {
+    let repo = git2::Repository::open(path)?;
+    let remotes = repo
+        .remotes()?
+        .iter()
+        .flatten()
+        .map(|name| DiscoveredRemote {
+            name: name.to_owned(),
+            url: repo
+                .find_remote(name)
+                .ok()
+                .and_then(|remote| remote.url().map(ToOwned::to_owned)),
+        })
+        .collect();
+    let last_commit_at = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .ok()
+        .map(|commit| commit.time().seconds());
+    Ok(DiscoveredProject {
+        path: path.to_owned(),
+        title: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        remotes,
+        last_commit_at,
+        already_added: false,
+    })
+}
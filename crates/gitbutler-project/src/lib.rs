@@ -1,11 +1,19 @@
 pub mod access;
 mod controller;
 mod default_true;
+mod discover;
+mod location;
+pub mod machine_changes;
 mod project;
 mod storage;
 
 pub use controller::Controller;
-pub use project::{ApiProject, AuthKey, CodePushState, FetchResult, Project, ProjectId};
+pub use discover::{DiscoveredProject, DiscoveredRemote};
+pub use location::StorageLocation;
+pub use project::{
+    ApiProject, AuthKey, CoAuthor, CodePushState, FetchResult, HistoryEncryption, HistoryRetention,
+    PinnedSession, Project, ProjectId, SecretRedaction, WatcherMode, SUBPROJECT_REFS_PREFIX,
+};
 pub use storage::UpdateRequest;
 
 /// A utility to be used from applications to optimize `git2` configuration.
@@ -0,0 +1,172 @@
+//! Tell if a project is on a filesystem that the file watcher can't rely on.
+//!
+//! Network filesystems often don't notify about changes, particularly those made on other machines, and the
+//! clients of cloud-sync services like Dropbox or OneDrive rewrite files while syncing them. That is seen as
+//! changes without anyone editing a file, and Git fails with locking errors if the client holds files in `.git`
+//! open at the wrong time.
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+/// Filesystem types as reported by the system that are known to be backed by the network.
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "9p",
+    "afpfs",
+    "afs",
+    "ceph",
+    "cifs",
+    "davfs",
+    "fuse.rclone",
+    "fuse.sshfs",
+    "glusterfs",
+    "lustre",
+    "nfs",
+    "nfs4",
+    "smb3",
+    "smbfs",
+    "sshfs",
+    "webdav",
+];
+
+/// Where the files of a project are stored, as returned by [`StorageLocation::detect()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageLocation {
+    /// A local filesystem, which the file watcher can rely on.
+    Local,
+    /// A network filesystem like NFS or SMB, with `filesystem` being its type as reported by the system.
+    NetworkMount { filesystem: String },
+    /// A folder synced by the client of a cloud storage `provider`, like `Dropbox`.
+    CloudSync { provider: String },
+}
+
+impl StorageLocation {
+    /// Return where the directory at `path` is stored.
+    ///
+    /// Cloud-sync folders are recognized by the names their clients give them by default, so folders the user
+    /// chose to sync elsewhere are seen as local. Mapped network drives on Windows aren't recognized, only UNC paths.
+    pub fn detect(path: &Path) -> Self {
+        let path = gix::path::realpath(path).unwrap_or_else(|_| path.to_owned());
+        if let Some(provider) = cloud_sync_provider(&path) {
+            return StorageLocation::CloudSync {
+                provider: provider.to_owned(),
+            };
+        }
+        if let Some(filesystem) = network_filesystem(&path) {
+            return StorageLocation::NetworkMount { filesystem };
+        }
+        StorageLocation::Local
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, StorageLocation::Local)
+    }
+}
+
+/// Return the name of the cloud storage provider if a directory above `path` is known to be synced by its
+/// client.
+fn cloud_sync_provider(path: &Path) -> Option<&'static str> {
+    let mut previous: Option<&str> = None;
+    for component in path.parent()?.components() {
+        let Some(name) = component.as_os_str().to_str() else {
+            previous = None;
+            continue;
+        };
+        let provider = match name {
+            "Dropbox" => Some("Dropbox"),
+            _ if name.starts_with("Dropbox (") => Some("Dropbox"),
+            "OneDrive" => Some("OneDrive"),
+            _ if name.starts_with("OneDrive - ") => Some("OneDrive"),
+            "Google Drive" | "My Drive" => Some("Google Drive"),
+            "Box Sync" => Some("Box"),
+            "pCloud Drive" => Some("pCloud"),
+            "com~apple~CloudDocs" if previous == Some("Mobile Documents") => Some("iCloud Drive"),
+            // Where the File Provider based clients on macOS keep their folders, like `GoogleDrive-user@example.com`.
+            _ if previous == Some("CloudStorage") => {
+                if name.starts_with("GoogleDrive") {
+                    Some("Google Drive")
+                } else if name.starts_with("OneDrive") {
+                    Some("OneDrive")
+                } else if name.starts_with("Dropbox") {
+                    Some("Dropbox")
+                } else if name.starts_with("Box") {
+                    Some("Box")
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if provider.is_some() {
+            return provider;
+        }
+        previous = Some(name);
+    }
+    None
+}
+
+/// Return the type of the filesystem `path` is on if it is backed by the network.
+fn network_filesystem(path: &Path) -> Option<String> {
+    if let Some(Component::Prefix(prefix)) = path.components().next() {
+        if matches!(
+            prefix.kind(),
+            std::path::Prefix::UNC(..) | std::path::Prefix::VerbatimUNC(..)
+        ) {
+            return Some("smb".into());
+        }
+    }
+    let filesystem = mount_table()
+        .into_iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, filesystem)| filesystem)?;
+    NETWORK_FILESYSTEMS
+        .contains(&filesystem.as_str())
+        .then_some(filesystem)
+}
+
+/// Return the mount points along with the type of their filesystem, or nothing if that can't be determined.
+#[cfg(target_os = "linux")]
+fn mount_table() -> Vec<(PathBuf, String)> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let (_device, mount_point, filesystem) =
+                (fields.next()?, fields.next()?, fields.next()?);
+            // Whitespace in mount points is escaped as octal.
+            let mount_point = mount_point
+                .replace("\\040", " ")
+                .replace("\\011", "\t")
+                .replace("\\134", "\\");
+            Some((PathBuf::from(mount_point), filesystem.to_owned()))
+        })
+        .collect()
+}
+
+/// Return the mount points along with the type of their filesystem, or nothing if that can't be determined.
+#[cfg(target_os = "macos")]
+fn mount_table() -> Vec<(PathBuf, String)> {
+    let Ok(output) = std::process::Command::new("/sbin/mount").output() else {
+        return Vec::new();
+    };
+    // Lines look like `//user@host/share on /Volumes/share (smbfs, nodev, nosuid, mounted by user)`.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (_device, rest) = line.split_once(" on ")?;
+            let (mount_point, options) = rest.rsplit_once(" (")?;
+            let filesystem = options.split([',', ')']).next()?.trim();
+            Some((PathBuf::from(mount_point), filesystem.to_owned()))
+        })
+        .collect()
+}
+
+/// Return the mount points along with the type of their filesystem, or nothing if that can't be determined.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn mount_table() -> Vec<(PathBuf, String)> {
+    Vec::new()
+}
@@ -0,0 +1,233 @@
+//! Remember which worktree files were just written by GitButler itself, so the watcher can tell these
+//! changes apart from edits made by the user. Likewise, remember when git operations like a checkout changed
+//! `HEAD`, as the files they write aren't edits either.
+//!
+//! This works in-process only, which is fine as the writing operations and the watcher run in the same application.
+//!
+//! Changes made by other tools, like builds, formatters or code generators, can't be attributed that way, so
+//! [`classify()`] also considers which files changed and how many of them changed at once, along with the
+//! [rules](ClassificationRule) of the project.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ProjectId;
+
+/// How long recorded paths are considered machine-generated, which must cover the watcher's debounce delay.
+const EXPIRY: Duration = Duration::from_secs(5);
+/// How long worktree changes are attributed to a git operation after it was noticed. Checkouts of large trees
+/// write files for a while, and these may be noticed after `HEAD` changed.
+const GIT_OPERATION_EXPIRY: Duration = Duration::from_secs(10);
+
+/// Changes of at least this many files at once are too many for them to be edits, like when a formatter runs
+/// over the whole tree.
+pub const BURST_PATHS: usize = 25;
+/// Patterns of files that are typically written by tools, like build output, dependencies or lock files.
+const MACHINE_PATTERNS: &[&str] = &[
+    "**/node_modules/**",
+    "**/target/**",
+    "**/dist/**",
+    "**/build/**",
+    "**/.svelte-kit/**",
+    "**/.next/**",
+    "**/__pycache__/**",
+    "**/*.pyc",
+    "**/*.min.js",
+    "**/*.map",
+    "**/*.pb.go",
+    "**/*_pb2.py",
+    "**/*.generated.*",
+    "**/Cargo.lock",
+    "**/package-lock.json",
+    "**/pnpm-lock.yaml",
+    "**/yarn.lock",
+    "**/poetry.lock",
+    "**/Gemfile.lock",
+    "**/go.sum",
+];
+
+static RECENT: parking_lot::Mutex<BTreeMap<ProjectId, Vec<(Instant, PathBuf)>>> =
+    parking_lot::Mutex::new(BTreeMap::new());
+static GIT_OPERATIONS: parking_lot::Mutex<BTreeMap<ProjectId, Instant>> =
+    parking_lot::Mutex::new(BTreeMap::new());
+
+/// Record that GitButler is about to write the worktree-relative `paths` of the project with `project_id`.
+pub fn record(project_id: ProjectId, paths: impl IntoIterator<Item = impl Into<PathBuf>>) {
+    let now = Instant::now();
+    let mut recent = RECENT.lock();
+    let entries = recent.entry(project_id).or_default();
+    entries.retain(|(at, _)| now.duration_since(*at) < EXPIRY);
+    entries.extend(paths.into_iter().map(|path| (now, path.into())));
+}
+
+/// Return `true` if all worktree-relative `paths` of the project with `project_id` were recently
+/// [recorded](record()) as written by GitButler.
+pub fn contains_all(project_id: ProjectId, paths: &[impl AsRef<Path>]) -> bool {
+    if paths.is_empty() {
+        return false;
+    }
+    let now = Instant::now();
+    let recent = RECENT.lock();
+    let Some(entries) = recent.get(&project_id) else {
+        return false;
+    };
+    paths.iter().all(|path| {
+        entries.iter().any(|(at, recorded)| {
+            now.duration_since(*at) < EXPIRY && recorded.as_path() == path.as_ref()
+        })
+    })
+}
+
+/// Record that a git operation outside of GitButler just changed `HEAD` of the project with `project_id`.
+pub fn record_git_operation(project_id: ProjectId) {
+    GIT_OPERATIONS.lock().insert(project_id, Instant::now());
+}
+
+/// Return `true` if a git operation was [recorded](record_git_operation()) for the project with `project_id`
+/// recently enough for changes to its worktree to be caused by it.
+pub fn follows_git_operation(project_id: ProjectId) -> bool {
+    GIT_OPERATIONS
+        .lock()
+        .get(&project_id)
+        .is_some_and(|at| at.elapsed() < GIT_OPERATION_EXPIRY)
+}
+
+/// Who made a change to the worktree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeOrigin {
+    Human,
+    Machine,
+}
+
+/// Why a change was [classified](classify()) the way it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClassificationReason {
+    /// GitButler wrote all files as part of one of its operations.
+    GitButler,
+    /// The change follows a git operation outside of GitButler, like a checkout in a terminal.
+    GitOperation,
+    /// A [rule](ClassificationRule) of the project matched.
+    ProjectRule,
+    /// All files are of a kind typically written by tools, like build output or lock files.
+    FilePattern,
+    /// At least [`BURST_PATHS`] files changed at once.
+    Burst,
+}
+
+/// The result of [`classify()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Classification {
+    pub origin: ChangeOrigin,
+    /// Why the change is considered to be made by `origin`, or `None` for edits by a human as nothing
+    /// indicated otherwise.
+    pub reason: Option<ClassificationReason>,
+}
+
+impl Classification {
+    fn machine(reason: ClassificationReason) -> Self {
+        Classification {
+            origin: ChangeOrigin::Machine,
+            reason: Some(reason),
+        }
+    }
+
+    pub fn is_machine_generated(&self) -> bool {
+        self.origin == ChangeOrigin::Machine
+    }
+}
+
+/// A rule of a project that changes to files matching `pattern` were made by `origin`, taking precedence over
+/// the built-in patterns and the [burst](BURST_PATHS) heuristic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassificationRule {
+    /// A pattern matched against worktree-relative paths, with `*` matching within a directory and `**` across
+    /// directories, like `src/generated/**` or `**/*.snap`.
+    pub pattern: String,
+    pub origin: ChangeOrigin,
+}
+
+/// When more than `files` distinct files change within `window_seconds`, like when renaming a symbol across the
+/// codebase, the changes are recorded as a single bulk change instead of one delta per save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkChangeThreshold {
+    pub files: usize,
+    pub window_seconds: u64,
+}
+
+impl ClassificationRule {
+    fn matches(&self, path: &Path) -> bool {
+        matches_pattern(&self.pattern, path)
+    }
+}
+
+/// Tell whether the change to the worktree-relative `paths` of the project with `project_id`, as noticed at
+/// once by the watcher, was made by a human or a machine, using `rules` of the project.
+///
+/// Changes written by GitButler or following a git operation are made by a machine. Otherwise, a change to
+/// a file matching a rule for humans is made by a human, and a change to only files matching rules for machines
+/// or built-in patterns like `**/target/**` is made by a machine. Changes to many files at once are considered
+/// to be made by a machine as well.
+pub fn classify(
+    project_id: ProjectId,
+    rules: &[ClassificationRule],
+    paths: &[impl AsRef<Path>],
+) -> Classification {
+    if contains_all(project_id, paths) {
+        return Classification::machine(ClassificationReason::GitButler);
+    }
+    if follows_git_operation(project_id) {
+        return Classification::machine(ClassificationReason::GitOperation);
+    }
+    let mut matched_rule = false;
+    let mut all_machine = !paths.is_empty();
+    for path in paths {
+        let path = path.as_ref();
+        match rules.iter().find(|rule| rule.matches(path)) {
+            Some(rule) if rule.origin == ChangeOrigin::Human => {
+                return Classification {
+                    origin: ChangeOrigin::Human,
+                    reason: Some(ClassificationReason::ProjectRule),
+                };
+            }
+            Some(_) => matched_rule = true,
+            None => {
+                if !MACHINE_PATTERNS
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, path))
+                {
+                    all_machine = false;
+                }
+            }
+        }
+    }
+    if all_machine {
+        return Classification::machine(if matched_rule {
+            ClassificationReason::ProjectRule
+        } else {
+            ClassificationReason::FilePattern
+        });
+    }
+    if paths.len() >= BURST_PATHS {
+        return Classification::machine(ClassificationReason::Burst);
+    }
+    Classification {
+        origin: ChangeOrigin::Human,
+        reason: None,
+    }
+}
+
+fn matches_pattern(pattern: &str, path: &Path) -> bool {
+    let path = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(path));
+    gix::glob::wildmatch(
+        pattern.into(),
+        path.as_ref(),
+        gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+    )
+}
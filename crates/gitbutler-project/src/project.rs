@@ -1,6 +1,7 @@
 use anyhow::Context;
 use gitbutler_id::id::Id;
 use serde::{Deserialize, Serialize};
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::Path;
 use std::{
     path::{self, PathBuf},
@@ -8,6 +9,11 @@ use std::{
 };
 
 use crate::default_true::DefaultTrue;
+use crate::machine_changes::{BulkChangeThreshold, ClassificationRule};
+
+/// The prefix under which the references of [sub-projects](Project::subdirectory) are namespaced by their id,
+/// see [`Project::namespaced_ref()`].
+pub const SUBPROJECT_REFS_PREFIX: &str = "refs/gitbutler/subprojects/";
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +28,109 @@ pub enum AuthKey {
     SystemExecutable,
 }
 
+/// What to do with files that contain secrets when recording them in snapshots.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SecretRedaction {
+    /// Replace the secrets with a placeholder.
+    #[default]
+    Mask,
+    /// Don't record changes to the file at all.
+    SkipFile,
+}
+
+/// How changes to the files of a project are noticed while it's open.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WatcherMode {
+    /// Rely on the notifications of the operating system, like inotify or FSEvents.
+    #[default]
+    Native,
+    /// Scan the files periodically, for filesystems that don't reliably notify about changes, like network mounts.
+    Polling,
+}
+
+/// Someone who often writes code together with the user, to credit as co-author of commits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+/// How much of the recorded history of each file is kept when deltas are compacted.
+///
+/// History that isn't pinned is dropped once it's beyond any of the limits, and is kept entirely if none is set.
+/// The limits can't be zero, as that would drop all history, which is what deleting it is for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRetention {
+    /// Drop the history of each file that is older than this many days.
+    #[serde(default)]
+    pub max_age_days: Option<NonZeroU64>,
+    /// Keep the history of each file of at most this many sessions that changed it, the newest ones.
+    #[serde(default)]
+    pub max_sessions: Option<NonZeroUsize>,
+    /// The worktree-relative paths of files whose history is never dropped.
+    #[serde(default)]
+    pub pinned_files: Vec<PathBuf>,
+    /// Sessions whose history is never dropped.
+    #[serde(default)]
+    pub pinned_sessions: Vec<PinnedSession>,
+}
+
+impl HistoryRetention {
+    /// Returns `true` if any limit is set, so compacting may drop history of files.
+    pub fn limits_depth(&self) -> bool {
+        self.max_age_days.is_some() || self.max_sessions.is_some()
+    }
+
+    /// Returns `true` if what was recorded of the worktree-relative `path` at `at` seconds since the Unix epoch
+    /// is pinned, either as file or as part of a session.
+    pub fn is_pinned(&self, path: &Path, at: i64) -> bool {
+        self.pinned_files.iter().any(|pinned| pinned == path)
+            || self
+                .pinned_sessions
+                .iter()
+                .any(|session| (session.start..=session.end).contains(&at))
+    }
+}
+
+/// A session of activity, from its `start` to its `end` in seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedSession {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// How the history of a project, like its deltas, heartbeats and bookmarks, is encrypted at rest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum HistoryEncryption {
+    /// The history is kept in plain files.
+    #[default]
+    None,
+    /// The history is encrypted with a random key that is kept in the keychain of the operating system, so it's
+    /// unlocked without asking the user.
+    Keychain,
+    /// The history is encrypted with a key derived from a passphrase, which is asked for to unlock it.
+    #[serde(rename_all = "camelCase")]
+    Passphrase {
+        /// The salt to derive the key from the passphrase with, as base64.
+        salt: String,
+        /// A known value encrypted with the key, to tell if a passphrase is right before decrypting anything.
+        check: String,
+    },
+}
+
+impl HistoryEncryption {
+    /// Returns `true` if the history is encrypted.
+    pub fn is_enabled(&self) -> bool {
+        *self != HistoryEncryption::None
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ApiProject {
     pub name: String,
@@ -78,6 +187,11 @@ pub struct Project {
     // TODO(ST): rename this to `worktree_dir` and while at it, add a `git_dir` if it's retrieved from a repo.
     //           Then find `.join(".git")` and use the `git_dir` instead.
     pub path: path::PathBuf,
+    /// The directory within the worktree, relative to it, if this is one of several logical projects in the
+    /// same repository, like an app in a monorepo. Such sub-projects keep their own GitButler data, like the
+    /// history, deltas and virtual branches, and share the watcher of the repository.
+    #[serde(default)]
+    pub subdirectory: Option<PathBuf>,
     #[serde(default)]
     pub preferred_key: AuthKey,
     /// if ok_with_force_push is true, we'll not try to avoid force pushing
@@ -95,6 +209,78 @@ pub struct Project {
     pub omit_certificate_check: Option<bool>,
     // The number of changed lines that will trigger a snapshot
     pub snapshot_lines_threshold: Option<usize>,
+    /// The name of the remote to back up the oplog to, if set.
+    #[serde(default)]
+    pub history_backup_remote: Option<String>,
+    /// Names of branches that must not be committed to, force-pushed or reset directly.
+    /// A `*` matches any sequence of characters, so `release/*` protects all release branches.
+    #[serde(default)]
+    pub protected_branches: Vec<String>,
+    /// If `true`, operations on [protected branches](Self::protected_branches) are allowed anyway.
+    #[serde(default)]
+    pub protected_branches_override: bool,
+    /// Regular expressions matching secrets that must not be recorded in snapshots, in addition to
+    /// the built-in ones.
+    #[serde(default)]
+    pub secret_patterns: Vec<String>,
+    /// How files with secrets are recorded in snapshots.
+    #[serde(default)]
+    pub secret_redaction: SecretRedaction,
+    /// If `true`, changes to files aren't recorded in snapshots while the project stays open otherwise.
+    #[serde(default)]
+    pub recording_paused: bool,
+    /// If `true`, the project is only observed: its history is recorded and can be browsed, but nothing that
+    /// would change the worktree, the branches or the history, like committing or restoring, is allowed.
+    #[serde(default)]
+    pub read_only: bool,
+    /// If set, the remotes are fetched in the background every this many seconds while the project is open.
+    #[serde(default)]
+    pub auto_fetch_interval_seconds: Option<u64>,
+    /// The name of the group the project is listed under, or `None` if it's not in a group.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The position of the project in the list as set by the user. Projects without one are listed
+    /// after those with one, by title.
+    #[serde(default)]
+    pub sort_order: Option<usize>,
+    /// If `true`, the user marked the project as favorite.
+    #[serde(default)]
+    pub favorite: bool,
+    /// How changes to files are noticed. Projects on a network mount are polled when they are added.
+    #[serde(default)]
+    pub watcher_mode: WatcherMode,
+    /// If not empty, only changes to files in these worktree-relative directories are watched and recorded, which
+    /// keeps the cost of watching large monorepos low.
+    #[serde(default)]
+    pub watch_include_paths: Vec<PathBuf>,
+    /// Rules to classify changes to files as made by a human or a machine, in addition to the built-in heuristics.
+    /// The first matching rule applies.
+    #[serde(default)]
+    pub change_classification_rules: Vec<ClassificationRule>,
+    /// If `true`, commit messages have to follow [Conventional Commits](https://www.conventionalcommits.org), like
+    /// `fix(parser): handle empty input`, and others are refused.
+    #[serde(default)]
+    pub conventional_commits: bool,
+    /// The people the user often writes code with, to choose from when crediting co-authors of a commit.
+    #[serde(default)]
+    pub co_authors: Vec<CoAuthor>,
+    /// If set, the whole worktree is committed to a hidden ref of the current branch at most every this many
+    /// seconds while it changes, without touching the index or `HEAD`.
+    #[serde(default)]
+    pub wip_snapshot_interval_seconds: Option<u64>,
+    /// If set, bursts of changes to many files are recorded as a single bulk change.
+    #[serde(default)]
+    pub bulk_change_threshold: Option<BulkChangeThreshold>,
+    /// How much of the history of each file is kept, and what is never dropped.
+    #[serde(default)]
+    pub history_retention: HistoryRetention,
+    /// How the history is encrypted at rest, if it is.
+    #[serde(default)]
+    pub history_encryption: HistoryEncryption,
+    /// When the project was deleted by the user, if it was. Deleted projects are hidden, and their data
+    /// is kept until the grace period expires so the deletion can be undone.
+    #[serde(default)]
+    pub deleted_at: Option<time::SystemTime>,
 }
 
 /// Instantiation
@@ -113,6 +299,47 @@ impl Project {
 }
 
 impl Project {
+    /// Returns `true` if `branch_name` matches one of the [protected branches](Self::protected_branches).
+    pub fn is_protected_branch(&self, branch_name: &str) -> bool {
+        self.protected_branches
+            .iter()
+            .any(|pattern| glob_matches(pattern, branch_name))
+    }
+
+    /// Fail with [`Code::ProtectedBranch`](gitbutler_error::error::Code::ProtectedBranch) if `operation`
+    /// would affect the protected branch `branch_name` and no override is set.
+    pub fn ensure_unprotected_branch(
+        &self,
+        branch_name: &str,
+        operation: &str,
+    ) -> anyhow::Result<()> {
+        if self.protected_branches_override || !self.is_protected_branch(branch_name) {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "Refusing to {operation} protected branch '{branch_name}'"
+        ))
+        .context(gitbutler_error::error::Code::ProtectedBranch)
+    }
+
+    /// Fail with [`Code::ProjectReadOnly`](gitbutler_error::error::Code::ProjectReadOnly) if the project is
+    /// [read-only](Self::read_only), as it would be changed by `operation`.
+    pub fn ensure_writable(&self, operation: &str) -> anyhow::Result<()> {
+        if !self.read_only {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "Refusing to {operation} as project '{}' is read-only",
+            self.title
+        ))
+        .context(gitbutler_error::error::Code::ProjectReadOnly)
+    }
+
+    /// Returns `true` if the project was deleted but not yet purged.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
     /// Determines if the project Operations log will be synched with the GitButHub
     pub fn oplog_sync_enabled(&self) -> bool {
         let has_url = self.api.as_ref().map(|api| api.git_url.clone()).is_some();
@@ -141,9 +368,33 @@ impl Project {
 
     /// Returns the path to the directory containing the `GitButler` state for this project.
     ///
-    /// Normally this is `.git/gitbutler` in the project's repository.
+    /// Normally this is `.git/gitbutler` in the project's repository, and `.git/gitbutler/subprojects/<id>` for
+    /// [sub-projects](Self::subdirectory).
     pub fn gb_dir(&self) -> PathBuf {
-        self.path.join(".git").join("gitbutler")
+        let gb_dir = self.path.join(".git").join("gitbutler");
+        if self.is_subproject() {
+            gb_dir.join("subprojects").join(self.id.to_string())
+        } else {
+            gb_dir
+        }
+    }
+
+    /// Returns the name of the `refs/gitbutler/` reference, or reference prefix, `name` as used by this project.
+    ///
+    /// It's `name` itself, and `refs/gitbutler/subprojects/<id>/…` for [sub-projects](Self::subdirectory) so they don't
+    /// move the references of the other projects in the same repository.
+    pub fn namespaced_ref(&self, name: &str) -> String {
+        match name.strip_prefix("refs/gitbutler/") {
+            Some(rest) if self.is_subproject() => {
+                format!("{SUBPROJECT_REFS_PREFIX}{}/{rest}", self.id)
+            }
+            _ => name.to_owned(),
+        }
+    }
+
+    /// Returns `true` if this is a logical project in a [subdirectory](Self::subdirectory) of the repository.
+    pub fn is_subproject(&self) -> bool {
+        self.subdirectory.is_some()
     }
 
     pub fn snapshot_lines_threshold(&self) -> usize {
@@ -154,3 +405,23 @@ impl Project {
         self.path.clone()
     }
 }
+
+/// Match `name` against `pattern`, where `*` matches any sequence of characters.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<_> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
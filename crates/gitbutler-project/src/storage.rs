@@ -1,15 +1,28 @@
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::{ApiProject, AuthKey, CodePushState, FetchResult, Project, ProjectId};
+use crate::{
+    access::LockFile,
+    machine_changes::{BulkChangeThreshold, ClassificationRule},
+    ApiProject, AuthKey, CoAuthor, CodePushState, FetchResult, HistoryEncryption, HistoryRetention,
+    Project, ProjectId, SecretRedaction, WatcherMode,
+};
 
 const PROJECTS_FILE: &str = "projects.json";
+/// The file that is locked while [`PROJECTS_FILE`] is changed, so other instances of the application don't
+/// overwrite the change with what they read before.
+const PROJECTS_LOCK_FILE: &str = "projects.json.lock";
+
+/// Serializes all changes of projects within this process, as the file lock doesn't reliably exclude
+/// threads of the same process on all platforms.
+static WRITE_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
 
 #[derive(Debug, Clone)]
 pub(crate) struct Storage {
     inner: gitbutler_storage::Storage,
+    lock_path: PathBuf,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -29,6 +42,31 @@ pub struct UpdateRequest {
     pub omit_certificate_check: Option<bool>,
     pub use_diff_context: Option<bool>,
     pub snapshot_lines_threshold: Option<usize>,
+    pub history_backup_remote: Option<String>,
+    #[serde(default = "default_false")]
+    pub unset_history_backup_remote: bool,
+    pub protected_branches: Option<Vec<String>>,
+    pub protected_branches_override: Option<bool>,
+    pub secret_patterns: Option<Vec<String>>,
+    pub secret_redaction: Option<SecretRedaction>,
+    pub recording_paused: Option<bool>,
+    pub read_only: Option<bool>,
+    pub auto_fetch_interval_seconds: Option<u64>,
+    #[serde(default = "default_false")]
+    pub unset_auto_fetch_interval_seconds: bool,
+    pub favorite: Option<bool>,
+    pub watcher_mode: Option<WatcherMode>,
+    pub watch_include_paths: Option<Vec<PathBuf>>,
+    pub change_classification_rules: Option<Vec<ClassificationRule>>,
+    pub conventional_commits: Option<bool>,
+    pub co_authors: Option<Vec<CoAuthor>>,
+    pub wip_snapshot_interval_seconds: Option<u64>,
+    #[serde(default = "default_false")]
+    pub unset_wip_snapshot_interval_seconds: bool,
+    pub bulk_change_threshold: Option<BulkChangeThreshold>,
+    #[serde(default = "default_false")]
+    pub unset_bulk_change_threshold: bool,
+    pub history_retention: Option<HistoryRetention>,
 }
 
 fn default_false() -> bool {
@@ -37,11 +75,32 @@ fn default_false() -> bool {
 
 impl Storage {
     pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
         Storage {
+            lock_path: path.join(PROJECTS_LOCK_FILE),
             inner: gitbutler_storage::Storage::new(path),
         }
     }
 
+    /// Read all projects, let `mutate` change them, and write them back unless it fails, all while holding
+    /// the lock so no other thread or process changes them in the meantime.
+    fn mutate<T>(&self, mutate: impl FnOnce(&mut Vec<Project>) -> Result<T>) -> Result<T> {
+        let _in_process = WRITE_LOCK.lock();
+        if let Some(parent) = self.lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut lock = LockFile::open(&self.lock_path)?;
+        lock.lock()
+            .with_context(|| format!("failed to lock {}", self.lock_path.display()))?;
+
+        let mut projects = self.list()?;
+        let result = mutate(&mut projects)?;
+        self.inner
+            .write(PROJECTS_FILE, &serde_json::to_string_pretty(&projects)?)?;
+        lock.unlock()?;
+        Ok(result)
+    }
+
     pub fn list(&self) -> Result<Vec<Project>> {
         match self.inner.read(PROJECTS_FILE)? {
             Some(projects) => {
@@ -59,7 +118,13 @@ impl Storage {
                     })
                     .collect();
 
-                all_projects.sort_by(|a, b| a.title.cmp(&b.title));
+                all_projects.sort_by(|a, b| {
+                    (a.sort_order.is_none(), a.sort_order, &a.title).cmp(&(
+                        b.sort_order.is_none(),
+                        b.sort_order,
+                        &b.title,
+                    ))
+                });
                 Ok(all_projects)
             }
             None => Ok(vec![]),
@@ -77,87 +142,256 @@ impl Storage {
     }
 
     pub fn update(&self, update_request: &UpdateRequest) -> Result<Project> {
-        let mut projects = self.list()?;
-        let project = projects
-            .iter_mut()
-            .find(|p| p.id == update_request.id)
-            .with_context(|| "project {id} not found for update")?;
+        self.mutate(|projects| {
+            let project = projects
+                .iter_mut()
+                .find(|p| p.id == update_request.id)
+                .with_context(|| "project {id} not found for update")?;
 
-        if let Some(title) = &update_request.title {
-            project.title.clone_from(title);
-        }
+            if let Some(title) = &update_request.title {
+                project.title.clone_from(title);
+            }
 
-        if let Some(description) = &update_request.description {
-            project.description = Some(description.clone());
-        }
+            if let Some(description) = &update_request.description {
+                project.description = Some(description.clone());
+            }
 
-        if let Some(path) = &update_request.path {
-            project.path = path.clone();
-        }
+            if let Some(path) = &update_request.path {
+                project.path = path.clone();
+            }
 
-        if let Some(api) = &update_request.api {
-            project.api = Some(api.clone());
-        }
+            if let Some(api) = &update_request.api {
+                project.api = Some(api.clone());
+            }
 
-        if update_request.unset_api {
-            project.api = None;
-        }
+            if update_request.unset_api {
+                project.api = None;
+            }
 
-        if let Some(preferred_key) = &update_request.preferred_key {
-            project.preferred_key = preferred_key.clone();
-        }
+            if let Some(preferred_key) = &update_request.preferred_key {
+                project.preferred_key = preferred_key.clone();
+            }
 
-        if let Some(gitbutler_data_last_fetched) =
-            update_request.gitbutler_data_last_fetched.as_ref()
-        {
-            project.gitbutler_data_last_fetch = Some(gitbutler_data_last_fetched.clone());
-        }
+            if let Some(gitbutler_data_last_fetched) =
+                update_request.gitbutler_data_last_fetched.as_ref()
+            {
+                project.gitbutler_data_last_fetch = Some(gitbutler_data_last_fetched.clone());
+            }
 
-        if let Some(project_data_last_fetched) = update_request.project_data_last_fetched.as_ref() {
-            project.project_data_last_fetch = Some(project_data_last_fetched.clone());
-        }
+            if let Some(project_data_last_fetched) =
+                update_request.project_data_last_fetched.as_ref()
+            {
+                project.project_data_last_fetch = Some(project_data_last_fetched.clone());
+            }
 
-        if let Some(state) = update_request.gitbutler_code_push_state {
-            project.gitbutler_code_push_state = Some(state);
-        }
+            if let Some(state) = update_request.gitbutler_code_push_state {
+                project.gitbutler_code_push_state = Some(state);
+            }
 
-        if let Some(ok_with_force_push) = update_request.ok_with_force_push {
-            *project.ok_with_force_push = ok_with_force_push;
-        }
+            if let Some(ok_with_force_push) = update_request.ok_with_force_push {
+                *project.ok_with_force_push = ok_with_force_push;
+            }
 
-        if let Some(omit_certificate_check) = update_request.omit_certificate_check {
-            project.omit_certificate_check = Some(omit_certificate_check);
-        }
+            if let Some(omit_certificate_check) = update_request.omit_certificate_check {
+                project.omit_certificate_check = Some(omit_certificate_check);
+            }
 
-        if let Some(snapshot_lines_threshold) = update_request.snapshot_lines_threshold {
-            project.snapshot_lines_threshold = Some(snapshot_lines_threshold);
-        }
+            if let Some(snapshot_lines_threshold) = update_request.snapshot_lines_threshold {
+                project.snapshot_lines_threshold = Some(snapshot_lines_threshold);
+            }
 
-        self.inner
-            .write(PROJECTS_FILE, &serde_json::to_string_pretty(&projects)?)?;
+            if let Some(history_backup_remote) = &update_request.history_backup_remote {
+                project.history_backup_remote = Some(history_backup_remote.clone());
+            }
+
+            if update_request.unset_history_backup_remote {
+                project.history_backup_remote = None;
+            }
 
-        Ok(projects
-            .iter()
-            .find(|p| p.id == update_request.id)
-            .unwrap()
-            .clone())
+            if let Some(protected_branches) = &update_request.protected_branches {
+                project.protected_branches = protected_branches.clone();
+            }
+
+            if let Some(protected_branches_override) = update_request.protected_branches_override {
+                project.protected_branches_override = protected_branches_override;
+            }
+
+            if let Some(secret_patterns) = &update_request.secret_patterns {
+                project.secret_patterns = secret_patterns.clone();
+            }
+
+            if let Some(secret_redaction) = update_request.secret_redaction {
+                project.secret_redaction = secret_redaction;
+            }
+
+            if let Some(recording_paused) = update_request.recording_paused {
+                project.recording_paused = recording_paused;
+            }
+
+            if let Some(read_only) = update_request.read_only {
+                project.read_only = read_only;
+            }
+
+            if let Some(auto_fetch_interval_seconds) = update_request.auto_fetch_interval_seconds {
+                project.auto_fetch_interval_seconds = Some(auto_fetch_interval_seconds);
+            }
+
+            if update_request.unset_auto_fetch_interval_seconds {
+                project.auto_fetch_interval_seconds = None;
+            }
+
+            if let Some(favorite) = update_request.favorite {
+                project.favorite = favorite;
+            }
+
+            if let Some(watcher_mode) = update_request.watcher_mode {
+                project.watcher_mode = watcher_mode;
+            }
+
+            if let Some(watch_include_paths) = &update_request.watch_include_paths {
+                if let Some(invalid) = watch_include_paths.iter().find(|path| {
+                    path.as_os_str().is_empty()
+                        || !path
+                            .components()
+                            .all(|c| matches!(c, std::path::Component::Normal(_)))
+                }) {
+                    bail!(
+                        "'{}' isn't a directory within the worktree",
+                        invalid.display()
+                    );
+                }
+                project.watch_include_paths = watch_include_paths.clone();
+            }
+
+            if let Some(rules) = &update_request.change_classification_rules {
+                project.change_classification_rules = rules.clone();
+            }
+
+            if let Some(conventional_commits) = update_request.conventional_commits {
+                project.conventional_commits = conventional_commits;
+            }
+
+            if let Some(co_authors) = &update_request.co_authors {
+                if let Some(invalid) = co_authors.iter().find(|co_author| {
+                    co_author.name.trim().is_empty() || !co_author.email.contains('@')
+                }) {
+                    bail!(
+                        "'{} <{}>' needs a name and an email address",
+                        invalid.name,
+                        invalid.email
+                    );
+                }
+                project.co_authors = co_authors.clone();
+            }
+
+            if let Some(interval) = update_request.wip_snapshot_interval_seconds {
+                if interval == 0 {
+                    bail!("WIP snapshots need an interval of at least a second");
+                }
+                project.wip_snapshot_interval_seconds = Some(interval);
+            }
+
+            if update_request.unset_wip_snapshot_interval_seconds {
+                project.wip_snapshot_interval_seconds = None;
+            }
+
+            if let Some(threshold) = update_request.bulk_change_threshold {
+                if threshold.files == 0 || threshold.window_seconds == 0 {
+                    bail!("Bulk changes need at least one file and a window of at least a second");
+                }
+                project.bulk_change_threshold = Some(threshold);
+            }
+
+            if update_request.unset_bulk_change_threshold {
+                project.bulk_change_threshold = None;
+            }
+
+            if let Some(retention) = &update_request.history_retention {
+                if let Some(session) = retention
+                    .pinned_sessions
+                    .iter()
+                    .find(|session| session.start > session.end)
+                {
+                    bail!(
+                        "The pinned session starting at {} ends before it starts",
+                        session.start
+                    );
+                }
+                project.history_retention = retention.clone();
+            }
+
+            Ok(project.clone())
+        })
+    }
+
+    /// Set the time at which the project with `id` was deleted, or clear it with `None` to restore it.
+    pub fn set_deleted_at(
+        &self,
+        id: ProjectId,
+        deleted_at: Option<std::time::SystemTime>,
+    ) -> Result<Project> {
+        self.mutate(|projects| {
+            let project = projects
+                .iter_mut()
+                .find(|p| p.id == id)
+                .with_context(|| format!("project {id} not found"))?;
+            project.deleted_at = deleted_at;
+            Ok(project.clone())
+        })
+    }
+
+    /// Put the project with `id` into `group`, or take it out of its group with `None`.
+    pub fn set_group(&self, id: ProjectId, group: Option<String>) -> Result<Project> {
+        self.mutate(|projects| {
+            let project = projects
+                .iter_mut()
+                .find(|p| p.id == id)
+                .with_context(|| format!("project {id} not found"))?;
+            project.group = group;
+            Ok(project.clone())
+        })
+    }
+
+    /// Record that the history of the project with `id` is encrypted as `encryption`.
+    pub fn set_history_encryption(
+        &self,
+        id: ProjectId,
+        encryption: HistoryEncryption,
+    ) -> Result<Project> {
+        self.mutate(|projects| {
+            let project = projects
+                .iter_mut()
+                .find(|p| p.id == id)
+                .with_context(|| format!("project {id} not found"))?;
+            project.history_encryption = encryption;
+            Ok(project.clone())
+        })
+    }
+
+    /// List the projects with `ids` first, in that order, followed by all other projects by title.
+    pub fn reorder(&self, ids: &[ProjectId]) -> Result<()> {
+        self.mutate(|projects| {
+            if let Some(unknown) = ids.iter().find(|id| !projects.iter().any(|p| p.id == **id)) {
+                bail!("project {unknown} not found");
+            }
+            for project in projects {
+                project.sort_order = ids.iter().position(|id| *id == project.id);
+            }
+            Ok(())
+        })
     }
 
     pub fn purge(&self, id: ProjectId) -> Result<()> {
-        let mut projects = self.list()?;
-        if let Some(index) = projects.iter().position(|p| p.id == id) {
-            projects.remove(index);
-            self.inner
-                .write(PROJECTS_FILE, &serde_json::to_string_pretty(&projects)?)?;
-        }
-        Ok(())
+        self.mutate(|projects| {
+            projects.retain(|p| p.id != id);
+            Ok(())
+        })
     }
 
     pub fn add(&self, project: &Project) -> Result<()> {
-        let mut projects = self.list()?;
-        projects.push(project.clone());
-        let projects = serde_json::to_string_pretty(&projects)?;
-        self.inner.write(PROJECTS_FILE, &projects)?;
-        Ok(())
+        self.mutate(|projects| {
+            projects.push(project.clone());
+            Ok(())
+        })
     }
 }
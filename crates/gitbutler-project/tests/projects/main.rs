@@ -24,6 +24,26 @@ mod add {
         );
     }
 
+    #[test]
+    fn many_with_per_path_results() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let path = repository.path();
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("missing");
+        let results = controller.add_many([path, missing.as_path(), path]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().path, path);
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            "path not found"
+        );
+        assert_eq!(
+            results[2].as_ref().unwrap_err().to_string(),
+            "project already exists"
+        );
+    }
+
     mod error {
         use super::*;
         use std::path::PathBuf;
@@ -77,10 +97,19 @@ mod add {
 
         #[test]
         fn empty() {
+            use gitbutler_error::error::{AnyhowContextExt, Code};
+
             let (controller, _tmp) = new();
             let tmp = tempfile::tempdir().unwrap();
             let err = controller.add(tmp.path()).unwrap_err();
-            assert_eq!(err.to_string(), "must be a Git repository");
+            assert_eq!(
+                err.to_string(),
+                "The directory is empty, but can be initialized as a new project"
+            );
+            assert_eq!(
+                err.custom_context().map(|context| context.code),
+                Some(Code::ProjectDirectoryEmpty)
+            );
         }
 
         #[test]
@@ -150,8 +179,47 @@ mod add {
     }
 }
 
+mod discover {
+    use super::*;
+
+    #[test]
+    fn finds_repositories_up_to_max_depth() {
+        let (controller, _tmp) = new();
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let added = git2::Repository::init(root.join("added")).unwrap();
+        added
+            .remote("origin", "https://github.com/gitbutlerapp/gitbutler")
+            .unwrap();
+        git2::Repository::init(root.join("group/nested")).unwrap();
+        git2::Repository::init(root.join("group/nested/inner")).unwrap();
+        git2::Repository::init(root.join("node_modules/dependency")).unwrap();
+        git2::Repository::init(root.join(".hidden/repo")).unwrap();
+        git2::Repository::init(root.join("a/b/too-deep")).unwrap();
+        controller.add(root.join("added")).unwrap();
+
+        let discovered = controller.discover(root, 2).unwrap();
+        let titles: Vec<_> = discovered.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["added", "nested"],
+            "repositories aren't descended into, and skipped directories aren't searched"
+        );
+        assert!(discovered[0].already_added);
+        assert_eq!(discovered[0].remotes.len(), 1);
+        assert_eq!(discovered[0].remotes[0].name, "origin");
+        assert_eq!(discovered[0].last_commit_at, None, "there are no commits");
+        assert!(!discovered[1].already_added);
+
+        assert!(controller.discover(&root.join("missing"), 2).is_err());
+    }
+}
+
 mod delete {
+    use std::time::Duration;
+
     use super::*;
+
     #[test]
     fn success() {
         let (controller, _tmp) = new();
@@ -161,6 +229,524 @@ mod delete {
         assert!(controller.delete(project.id).is_ok());
         assert!(controller.delete(project.id).is_ok()); // idempotent
         assert!(controller.get(project.id).is_err());
+        assert!(controller.list().unwrap().is_empty());
+        assert!(
+            project.gb_dir().exists(),
+            "data is kept until the grace period expires"
+        );
+    }
+
+    #[test]
+    fn undo_within_grace_period() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        controller.delete(project.id).unwrap();
+        assert_eq!(controller.list_deleted().unwrap().len(), 1);
+
+        let purged = controller
+            .purge_expired(Duration::from_secs(60 * 60))
+            .unwrap();
+        assert!(purged.is_empty(), "the grace period didn't expire yet");
+
+        let restored = controller.undo_delete(project.id).unwrap();
+        assert!(!restored.is_deleted());
+        assert!(controller.get(project.id).is_ok());
+        assert!(controller.list_deleted().unwrap().is_empty());
+    }
+
+    #[test]
+    fn purge_after_grace_period() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        controller.delete(project.id).unwrap();
+
+        let purged = controller.purge_expired(Duration::ZERO).unwrap();
+        assert_eq!(purged, [project.id]);
         assert!(!project.gb_dir().exists());
+        assert!(
+            controller.undo_delete(project.id).is_err(),
+            "purged projects can't be restored"
+        );
+    }
+
+    #[test]
+    fn adding_again_replaces_deleted_project() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        controller.delete(project.id).unwrap();
+
+        let new_project = controller.add(repository.path()).unwrap();
+        assert_ne!(new_project.id, project.id);
+        assert!(controller.list_deleted().unwrap().is_empty());
+    }
+}
+
+mod subprojects {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn keep_their_own_data_in_the_same_repository() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        std::fs::create_dir_all(repository.path().join("apps/web")).unwrap();
+
+        let subproject = controller
+            .add_subproject(project.id, Path::new("apps/web"))
+            .unwrap();
+        assert_eq!(subproject.path, project.path);
+        assert_eq!(subproject.subdirectory, Some(PathBuf::from("apps/web")));
+        assert_eq!(subproject.watch_include_paths, [PathBuf::from("apps/web")]);
+        assert_ne!(subproject.gb_dir(), project.gb_dir());
+        assert!(subproject.gb_dir().starts_with(project.gb_dir()));
+        assert!(subproject.gb_dir().is_dir());
+
+        assert_eq!(
+            controller
+                .subprojects(project.id)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>(),
+            [subproject.id]
+        );
+        assert!(
+            controller
+                .add_subproject(project.id, Path::new("apps/web"))
+                .is_err(),
+            "each directory can only be added once"
+        );
+        assert!(
+            controller
+                .add_subproject(subproject.id, Path::new("apps/web"))
+                .is_err(),
+            "sub-projects can't be nested"
+        );
+    }
+
+    #[test]
+    fn namespace_their_references_by_id() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        std::fs::create_dir_all(repository.path().join("lib")).unwrap();
+        let subproject = controller
+            .add_subproject(project.id, Path::new("lib"))
+            .unwrap();
+
+        assert_eq!(
+            project.namespaced_ref("refs/gitbutler/oplog"),
+            "refs/gitbutler/oplog"
+        );
+        assert_eq!(
+            subproject.namespaced_ref("refs/gitbutler/oplog"),
+            format!("refs/gitbutler/subprojects/{}/oplog", subproject.id)
+        );
+        assert_eq!(
+            subproject.namespaced_ref("refs/gitbutler/suspended/"),
+            format!("refs/gitbutler/subprojects/{}/suspended/", subproject.id)
+        );
+        assert_eq!(
+            subproject.namespaced_ref("refs/heads/main"),
+            "refs/heads/main",
+            "only GitButler references are namespaced"
+        );
+    }
+
+    #[test]
+    fn must_be_existing_directories_within_the_worktree() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        for invalid in ["missing", "../elsewhere", "/absolute", ""] {
+            assert!(
+                controller
+                    .add_subproject(project.id, Path::new(invalid))
+                    .is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn are_purged_along_with_the_project_of_their_repository() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        std::fs::create_dir_all(repository.path().join("lib")).unwrap();
+        let subproject = controller
+            .add_subproject(project.id, Path::new("lib"))
+            .unwrap();
+
+        controller.purge(project.id).unwrap();
+        assert!(controller.get(subproject.id).is_err());
+        assert!(!subproject.gb_dir().exists());
+    }
+}
+
+mod recording {
+    use super::*;
+    use gitbutler_project::UpdateRequest;
+
+    #[test]
+    fn paused_flag_is_persisted() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        assert!(!project.recording_paused);
+
+        let update = |recording_paused| {
+            controller
+                .update(&UpdateRequest {
+                    id: project.id,
+                    recording_paused: Some(recording_paused),
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        assert!(update(true).recording_paused);
+        assert!(controller.get(project.id).unwrap().recording_paused);
+        assert!(!update(false).recording_paused);
+    }
+}
+
+mod organize {
+    use super::*;
+    use gitbutler_project::UpdateRequest;
+
+    #[test]
+    fn reorder_puts_listed_projects_first() {
+        let (controller, _tmp) = new();
+        let repositories: Vec<_> = (0..3)
+            .map(|_| gitbutler_testsupport::TestProject::default())
+            .collect();
+        let projects: Vec<_> = repositories
+            .iter()
+            .map(|repository| controller.add(repository.path()).unwrap())
+            .collect();
+
+        controller
+            .reorder(&[projects[2].id, projects[0].id])
+            .unwrap();
+        let listed: Vec<_> = controller.list().unwrap().iter().map(|p| p.id).collect();
+        assert_eq!(listed, [projects[2].id, projects[0].id, projects[1].id]);
+
+        assert!(
+            controller
+                .reorder(&[gitbutler_project::ProjectId::generate()])
+                .is_err(),
+            "unknown projects can't be ordered"
+        );
+    }
+
+    #[test]
+    fn group_and_favorite_are_persisted() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        assert_eq!(project.group, None);
+        assert!(!project.favorite);
+
+        let grouped = controller
+            .set_group(project.id, Some(" work ".into()))
+            .unwrap();
+        assert_eq!(grouped.group.as_deref(), Some("work"));
+        controller
+            .update(&UpdateRequest {
+                id: project.id,
+                favorite: Some(true),
+                ..Default::default()
+            })
+            .unwrap();
+        let project = controller.get(project.id).unwrap();
+        assert_eq!(project.group.as_deref(), Some("work"));
+        assert!(project.favorite);
+
+        let ungrouped = controller.set_group(project.id, Some("".into())).unwrap();
+        assert_eq!(ungrouped.group, None, "empty names remove the group");
+    }
+}
+
+mod concurrent_updates {
+    use super::*;
+    use gitbutler_project::UpdateRequest;
+
+    #[test]
+    fn are_not_lost() {
+        let (controller, _tmp) = new();
+        let repositories: Vec<_> = (0..8)
+            .map(|_| gitbutler_testsupport::TestProject::default())
+            .collect();
+        let projects: Vec<_> = repositories
+            .iter()
+            .map(|repository| controller.add(repository.path()).unwrap())
+            .collect();
+
+        std::thread::scope(|scope| {
+            for project in &projects {
+                let controller = controller.clone();
+                scope.spawn(move || {
+                    controller
+                        .update(&UpdateRequest {
+                            id: project.id,
+                            title: Some(format!("renamed {}", project.id)),
+                            ..Default::default()
+                        })
+                        .unwrap();
+                });
+            }
+        });
+        for project in &projects {
+            assert_eq!(
+                controller.get(project.id).unwrap().title,
+                format!("renamed {}", project.id),
+                "each read-modify-write sees the changes of the previous one"
+            );
+        }
+    }
+}
+
+mod protected_branches {
+    use gitbutler_error::error::Code;
+    use gitbutler_project::Project;
+
+    fn project(protected_branches: &[&str]) -> Project {
+        Project {
+            protected_branches: protected_branches.iter().map(|b| b.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exact_and_glob_patterns() {
+        let project = project(&["main", "release/*"]);
+        assert!(project.is_protected_branch("main"));
+        assert!(project.is_protected_branch("release/1.0"));
+        assert!(!project.is_protected_branch("mainline"));
+        assert!(!project.is_protected_branch("feature/release/1.0"));
+        assert!(!project.is_protected_branch("release"));
+    }
+
+    #[test]
+    fn refuses_unless_overridden() {
+        let mut project = project(&["main"]);
+        let err = project
+            .ensure_unprotected_branch("main", "force-push")
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Code>(), Some(&Code::ProtectedBranch));
+        assert!(project
+            .ensure_unprotected_branch("feat", "force-push")
+            .is_ok());
+
+        project.protected_branches_override = true;
+        assert!(project
+            .ensure_unprotected_branch("main", "force-push")
+            .is_ok());
+    }
+}
+
+mod read_only {
+    use gitbutler_error::error::Code;
+    use gitbutler_project::UpdateRequest;
+
+    use super::*;
+
+    #[test]
+    fn refuses_changes_once_set() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        assert!(project.ensure_writable("commit").is_ok());
+
+        let project = controller
+            .update(&UpdateRequest {
+                id: project.id,
+                read_only: Some(true),
+                ..Default::default()
+            })
+            .unwrap();
+        let err = project.ensure_writable("commit").unwrap_err();
+        assert_eq!(err.downcast_ref::<Code>(), Some(&Code::ProjectReadOnly));
+        assert!(controller.get(project.id).unwrap().read_only);
+    }
+}
+
+mod storage_location {
+    use gitbutler_project::{StorageLocation, WatcherMode};
+
+    use super::*;
+
+    #[test]
+    fn cloud_sync_folders_are_recognized_by_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (dir, provider) in [
+            ("Dropbox", "Dropbox"),
+            ("Dropbox (Team)", "Dropbox"),
+            ("OneDrive - Company", "OneDrive"),
+            (
+                "Library/Mobile Documents/com~apple~CloudDocs",
+                "iCloud Drive",
+            ),
+            (
+                "Library/CloudStorage/GoogleDrive-user@example.com",
+                "Google Drive",
+            ),
+        ] {
+            let path = tmp.path().join(dir).join("repo");
+            std::fs::create_dir_all(&path).unwrap();
+            assert_eq!(
+                StorageLocation::detect(&path),
+                StorageLocation::CloudSync {
+                    provider: provider.into()
+                },
+                "{dir}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_name_of_the_project_itself_is_not_considered() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("Dropbox");
+        std::fs::create_dir_all(&path).unwrap();
+        assert!(StorageLocation::detect(&path).is_local());
+    }
+
+    #[test]
+    fn local_projects_use_the_native_watcher() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        assert!(StorageLocation::detect(&project.path).is_local());
+        assert_eq!(project.watcher_mode, WatcherMode::Native);
+    }
+
+    #[test]
+    fn watch_include_paths_must_be_within_the_worktree() {
+        use gitbutler_project::UpdateRequest;
+        use std::path::PathBuf;
+
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path()).unwrap();
+        assert!(project.watch_include_paths.is_empty());
+
+        let update = |paths: &[&str]| {
+            controller.update(&UpdateRequest {
+                id: project.id,
+                watch_include_paths: Some(paths.iter().map(PathBuf::from).collect()),
+                ..Default::default()
+            })
+        };
+        let updated = update(&["services/api", "libs"]).unwrap();
+        assert_eq!(
+            updated.watch_include_paths,
+            [PathBuf::from("services/api"), PathBuf::from("libs")]
+        );
+        for invalid in ["../elsewhere", "/absolute", ""] {
+            assert!(update(&[invalid]).is_err(), "{invalid}");
+        }
+        assert_eq!(
+            controller.get(project.id).unwrap().watch_include_paths,
+            updated.watch_include_paths
+        );
+    }
+}
+
+mod change_classification {
+    use std::path::PathBuf;
+
+    use gitbutler_project::{
+        machine_changes::{
+            self, ChangeOrigin, Classification, ClassificationReason, ClassificationRule,
+            BURST_PATHS,
+        },
+        ProjectId,
+    };
+
+    fn classify(rules: &[ClassificationRule], paths: &[&str]) -> Classification {
+        machine_changes::classify(ProjectId::generate(), rules, paths)
+    }
+
+    fn rule(pattern: &str, origin: ChangeOrigin) -> ClassificationRule {
+        ClassificationRule {
+            pattern: pattern.into(),
+            origin,
+        }
+    }
+
+    #[test]
+    fn edits_are_made_by_humans() {
+        let classification = classify(&[], &["src/lib.rs", "README.md"]);
+        assert_eq!(classification.origin, ChangeOrigin::Human);
+        assert_eq!(classification.reason, None);
+    }
+
+    #[test]
+    fn build_output_and_lock_files_are_made_by_machines() {
+        let classification = classify(
+            &[],
+            &[
+                "target/debug/app",
+                "web/node_modules/x/index.js",
+                "Cargo.lock",
+            ],
+        );
+        assert_eq!(classification.origin, ChangeOrigin::Machine);
+        assert_eq!(
+            classification.reason,
+            Some(ClassificationReason::FilePattern)
+        );
+
+        assert_eq!(
+            classify(&[], &["Cargo.lock", "src/lib.rs"]).origin,
+            ChangeOrigin::Human,
+            "a single edit among generated files makes it a human change"
+        );
+    }
+
+    #[test]
+    fn bursts_are_made_by_machines() {
+        let paths: Vec<_> = (0..BURST_PATHS).map(|n| format!("src/{n}.rs")).collect();
+        let paths: Vec<_> = paths.iter().map(String::as_str).collect();
+        assert_eq!(
+            classify(&[], &paths).reason,
+            Some(ClassificationReason::Burst)
+        );
+        assert_eq!(classify(&[], &paths[1..]).origin, ChangeOrigin::Human);
+    }
+
+    #[test]
+    fn project_rules_take_precedence() {
+        let rules = [
+            rule("src/generated/**", ChangeOrigin::Machine),
+            rule("**/Cargo.lock", ChangeOrigin::Human),
+        ];
+        let classification = classify(&rules, &["src/generated/api.rs"]);
+        assert_eq!(classification.origin, ChangeOrigin::Machine);
+        assert_eq!(
+            classification.reason,
+            Some(ClassificationReason::ProjectRule)
+        );
+
+        let classification = classify(&rules, &["Cargo.lock"]);
+        assert_eq!(classification.origin, ChangeOrigin::Human);
+        assert_eq!(
+            classification.reason,
+            Some(ClassificationReason::ProjectRule)
+        );
+    }
+
+    #[test]
+    fn files_written_by_gitbutler_are_made_by_machines() {
+        let project_id = ProjectId::generate();
+        machine_changes::record(project_id, [PathBuf::from("src/lib.rs")]);
+        let classification = machine_changes::classify(project_id, &[], &["src/lib.rs"]);
+        assert_eq!(classification.reason, Some(ClassificationReason::GitButler));
     }
 }
@@ -1,4 +1,5 @@
 pub mod askpass;
+pub mod push_safety;
 
 mod repository;
 pub use repository::RepoActionsExt;
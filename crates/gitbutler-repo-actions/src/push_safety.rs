@@ -0,0 +1,108 @@
+//! Decide whether a push may be made before making it.
+//!
+//! Pushes that aren't fast-forwards need to be forced, and forced pushes only overwrite the remote branch if it's
+//! still where its remote-tracking branch says, which is what `git push --force-with-lease` does. Protected
+//! branches, including the base branch of the workspace, are never force-pushed, unless the project overrides that.
+use anyhow::Result;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_reference::RemoteRefname;
+use serde::Serialize;
+
+use crate::RepoActionsExt;
+
+/// Why a push is refused, as returned by [`check()`] so it can be explained before pushing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum PushRefusal {
+    /// The remote `branch` has commits that the pushed commit doesn't contain, so it would have to be
+    /// force-pushed. `remote_head` is the commit it points to as of the last fetch.
+    #[serde(rename_all = "camelCase")]
+    NonFastForward { branch: String, remote_head: String },
+    /// The `branch` matches one of the protected branches of the project, or is the base branch, and can't be
+    /// force-pushed.
+    ProtectedBranch { branch: String },
+    /// The remote `branch` moved since it was last fetched, from `expected` to `actual`, with `None` meaning
+    /// it didn't exist. Force-pushing would discard commits that weren't fetched yet.
+    RemoteChanged {
+        branch: String,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+}
+
+impl std::fmt::Display for PushRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushRefusal::NonFastForward { branch, .. } => write!(
+                f,
+                "Refusing to push to '{branch}' as it has commits that aren't part of the pushed branch, force-push to overwrite them"
+            ),
+            PushRefusal::ProtectedBranch { branch } => {
+                write!(f, "Refusing to force-push protected branch '{branch}'")
+            }
+            PushRefusal::RemoteChanged { branch, .. } => write!(
+                f,
+                "Refusing to force-push to '{branch}' as it changed since it was last fetched, fetch and try again"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PushRefusal {}
+
+impl PushRefusal {
+    /// Turn this refusal into an error with a code the frontend can tell apart.
+    pub fn into_error(self) -> anyhow::Error {
+        let code = match &self {
+            PushRefusal::ProtectedBranch { .. } => Code::ProtectedBranch,
+            PushRefusal::NonFastForward { .. } | PushRefusal::RemoteChanged { .. } => {
+                Code::PushRefused
+            }
+        };
+        anyhow::Error::new(self).context(code)
+    }
+}
+
+/// Return why pushing `head` to `branch`, forced if `with_force` is set, would be refused, or `None` if it can be
+/// pushed, judging by the remote-tracking branch as of the last fetch.
+///
+/// Whether the remote branch is still where it was when forcing a push can only be told while pushing, with a
+/// [lease](remote_tracking_head()).
+pub fn check(
+    ctx: &CommandContext,
+    head: git2::Oid,
+    branch: &RemoteRefname,
+    with_force: bool,
+) -> Result<Option<PushRefusal>> {
+    if with_force && !ctx.project().protected_branches_override && ctx.is_protected_branch(branch) {
+        return Ok(Some(PushRefusal::ProtectedBranch {
+            branch: branch.branch().to_owned(),
+        }));
+    }
+    let repo = ctx.repo();
+    let Some(remote_head) = remote_tracking_head(repo, branch)? else {
+        return Ok(None);
+    };
+    let fast_forward = remote_head == head || repo.graph_descendant_of(head, remote_head)?;
+    Ok(
+        (!fast_forward && !with_force).then(|| PushRefusal::NonFastForward {
+            branch: branch.branch().to_owned(),
+            remote_head: remote_head.to_string(),
+        }),
+    )
+}
+
+/// Return the commit the remote-tracking branch of `branch` points to, or `None` if there is none.
+///
+/// A forced push only overwrites the remote branch if it still points to this commit.
+pub fn remote_tracking_head(
+    repo: &git2::Repository,
+    branch: &RemoteRefname,
+) -> Result<Option<git2::Oid>> {
+    match repo.refname_to_id(&branch.to_string()) {
+        Ok(id) => Ok(Some(id)),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
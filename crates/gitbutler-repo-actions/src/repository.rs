@@ -1,21 +1,44 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use but_settings::Limiter;
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_headers::CommitHeadersV2;
 use gitbutler_error::error::Code;
 use gitbutler_project::AuthKey;
 use gitbutler_reference::{Refname, RemoteRefname};
-use gitbutler_stack::{Stack, StackId};
+use gitbutler_stack::{Stack, StackId, VirtualBranchesHandle};
 
 use crate::askpass;
+use crate::push_safety::{self, PushRefusal};
 use gitbutler_repo::{
     credentials,
     logging::{LogUntil, RepositoryExt as _},
     RepositoryExt,
 };
+/// Limits how many fetches and pushes run at the same time, across all projects.
+static NETWORK_OPERATIONS: Limiter = Limiter::new();
+
 pub trait RepoActionsExt {
     fn fetch(&self, remote_name: &str, askpass: Option<String>) -> Result<()>;
+    /// Like [`fetch()`](Self::fetch()), but fail as soon as `should_interrupt` is set. Fetching with the Git
+    /// executable can only be stopped before it started.
+    fn fetch_interruptibly(
+        &self,
+        remote_name: &str,
+        askpass: Option<String>,
+        should_interrupt: &AtomicBool,
+    ) -> Result<()>;
+    /// Like [`fetch()`](Self::fetch()), but fetch `refspec` instead of all branches of `remote_name`.
+    fn fetch_refspec(
+        &self,
+        remote_name: &str,
+        refspec: String,
+        askpass: Option<String>,
+    ) -> Result<()>;
     fn push(
         &self,
         head: git2::Oid,
@@ -24,6 +47,26 @@ pub trait RepoActionsExt {
         refspec: Option<String>,
         askpass_broker: Option<Option<StackId>>,
     ) -> Result<()>;
+    /// Force-push `head` to `refname` on the remote called `remote_name`, which is a reference that isn't a
+    /// branch, like the history backup, and thus isn't [protected](Self::is_protected_branch()).
+    ///
+    /// Like `git push --force-with-lease`, the remote reference is only overwritten if it still points to `lease`,
+    /// with `None` meaning it must not exist yet.
+    fn push_ref(
+        &self,
+        remote_name: &str,
+        head: git2::Oid,
+        refname: &str,
+        lease: Option<git2::Oid>,
+        askpass_broker: Option<Option<StackId>>,
+    ) -> Result<()>;
+    /// Push the tag called `tag_name` to the remote called `remote_name`.
+    fn push_tag(
+        &self,
+        remote_name: &str,
+        tag_name: &str,
+        askpass_broker: Option<Option<StackId>>,
+    ) -> Result<()>;
     fn commit(
         &self,
         message: &str,
@@ -40,9 +83,57 @@ pub trait RepoActionsExt {
         branch_name: &str,
         askpass: Option<Option<StackId>>,
     ) -> Result<()>;
+    /// Fail if `operation` would directly affect a protected branch of `stack`, that is any of its heads
+    /// or its upstream branch, as configured in [`Project::protected_branches`](gitbutler_project::Project::protected_branches).
+    fn ensure_unprotected_stack(&self, stack: &Stack, operation: &str) -> Result<()>;
+    /// Return `true` if the remote `branch` matches one of the
+    /// [protected branches](gitbutler_project::Project::protected_branches), or is the base branch of the
+    /// workspace, which is always protected.
+    fn is_protected_branch(&self, branch: &RemoteRefname) -> bool;
+    /// Fail with [`Code::ProtectedBranch`] if `operation` would affect the remote `branch` while it's
+    /// [protected](Self::is_protected_branch()), unless the project overrides that.
+    fn ensure_unprotected_remote_branch(
+        &self,
+        branch: &RemoteRefname,
+        operation: &str,
+    ) -> Result<()>;
 }
 
 impl RepoActionsExt for CommandContext {
+    fn ensure_unprotected_stack(&self, stack: &Stack, operation: &str) -> Result<()> {
+        let project = self.project();
+        for head in stack.heads() {
+            project.ensure_unprotected_branch(&head, operation)?;
+        }
+        if let Some(upstream) = &stack.upstream {
+            self.ensure_unprotected_remote_branch(upstream, operation)?;
+        }
+        Ok(())
+    }
+
+    fn is_protected_branch(&self, branch: &RemoteRefname) -> bool {
+        let project = self.project();
+        project.is_protected_branch(branch.branch())
+            || VirtualBranchesHandle::new(project.gb_dir())
+                .get_default_target()
+                .is_ok_and(|target| target.branch == *branch)
+    }
+
+    fn ensure_unprotected_remote_branch(
+        &self,
+        branch: &RemoteRefname,
+        operation: &str,
+    ) -> Result<()> {
+        if self.project().protected_branches_override || !self.is_protected_branch(branch) {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Refusing to {operation} protected branch '{}'",
+            branch.branch()
+        ))
+        .context(Code::ProtectedBranch)
+    }
+
     fn git_test_push(
         &self,
         remote_name: &str,
@@ -152,6 +243,23 @@ impl RepoActionsExt for CommandContext {
             .context("failed to commit")
     }
 
+    fn push_tag(
+        &self,
+        remote_name: &str,
+        tag_name: &str,
+        askpass_broker: Option<Option<StackId>>,
+    ) -> Result<()> {
+        let refname = format!("refs/tags/{tag_name}");
+        let target = self.repo().refname_to_id(&refname)?;
+        self.push(
+            target,
+            &RemoteRefname::new(remote_name, tag_name),
+            false,
+            Some(format!("{refname}:{refname}")),
+            askpass_broker,
+        )
+    }
+
     fn push(
         &self,
         head: git2::Oid,
@@ -160,6 +268,12 @@ impl RepoActionsExt for CommandContext {
         refspec: Option<String>,
         askpass_broker: Option<Option<StackId>>,
     ) -> Result<()> {
+        if let Some(refusal) = push_safety::check(self, head, branch, with_force)? {
+            return Err(refusal.into_error());
+        }
+        let lease = with_force
+            .then(|| push_safety::remote_tracking_head(self.repo(), branch))
+            .transpose()?;
         let refspec = refspec.unwrap_or_else(|| {
             if with_force {
                 format!("+{}:refs/heads/{}", head, branch.branch())
@@ -167,154 +281,264 @@ impl RepoActionsExt for CommandContext {
                 format!("{}:refs/heads/{}", head, branch.branch())
             }
         });
+        push_refspec(
+            self,
+            branch.remote(),
+            branch.branch(),
+            head,
+            refspec,
+            lease,
+            askpass_broker,
+        )
+    }
 
-        // NOTE(qix-): This is a nasty hack, however the codebase isn't structured
-        // NOTE(qix-): in a way that allows us to really incorporate new backends
-        // NOTE(qix-): without a lot of work. This is a temporary measure to
-        // NOTE(qix-): work around a time-sensitive change that was necessary
-        // NOTE(qix-): without having to refactor a large portion of the codebase.
-        if self.project().preferred_key == AuthKey::SystemExecutable {
-            let path = self.project().worktree_path();
-            let remote = branch.remote().to_string();
-            return std::thread::spawn(move || {
-                tokio::runtime::Runtime::new()
-                    .unwrap()
-                    .block_on(gitbutler_git::push(
-                        path,
-                        gitbutler_git::tokio::TokioExecutor,
-                        &remote,
-                        gitbutler_git::RefSpec::parse(refspec).unwrap(),
-                        with_force,
-                        handle_git_prompt_push,
-                        askpass_broker,
-                    ))
-            })
-            .join()
-            .unwrap()
-            .map_err(Into::into);
-        }
+    fn push_ref(
+        &self,
+        remote_name: &str,
+        head: git2::Oid,
+        refname: &str,
+        lease: Option<git2::Oid>,
+        askpass_broker: Option<Option<StackId>>,
+    ) -> Result<()> {
+        push_refspec(
+            self,
+            remote_name,
+            refname,
+            head,
+            format!("+{head}:{refname}"),
+            Some(lease),
+            askpass_broker,
+        )
+    }
 
-        let auth_flows = credentials::help(self, branch.remote())?;
-        for (mut remote, callbacks) in auth_flows {
-            let mut update_refs_error: Option<git2::Error> = None;
-            for callback in callbacks {
-                let mut cbs: git2::RemoteCallbacks = callback.into();
-                if self.project().omit_certificate_check.unwrap_or(false) {
-                    cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
-                }
-                cbs.push_update_reference(|_reference: &str, status: Option<&str>| {
-                    if let Some(status) = status {
-                        update_refs_error = Some(git2::Error::from_str(status));
-                        return Err(git2::Error::from_str(status));
-                    };
+    fn fetch(&self, remote_name: &str, askpass: Option<String>) -> Result<()> {
+        self.fetch_interruptibly(remote_name, askpass, &AtomicBool::new(false))
+    }
+
+    fn fetch_interruptibly(
+        &self,
+        remote_name: &str,
+        askpass: Option<String>,
+        should_interrupt: &AtomicBool,
+    ) -> Result<()> {
+        let refspec = format!("+refs/heads/*:refs/remotes/{}/*", remote_name);
+        fetch_refspec_interruptibly(self, remote_name, refspec, askpass, should_interrupt)
+    }
+
+    fn fetch_refspec(
+        &self,
+        remote_name: &str,
+        refspec: String,
+        askpass: Option<String>,
+    ) -> Result<()> {
+        fetch_refspec_interruptibly(self, remote_name, refspec, askpass, &AtomicBool::new(false))
+    }
+}
+
+/// Push `refspec` to the remote called `remote_name`, with `target` being the branch or reference it pushes to.
+///
+/// If `lease` is set the push is forced, but like `git push --force-with-lease` it only overwrites the remote
+/// reference if it still points to the commit in `lease`, or doesn't exist if that's `None`.
+fn push_refspec(
+    ctx: &CommandContext,
+    remote_name: &str,
+    target: &str,
+    head: git2::Oid,
+    refspec: String,
+    lease: Option<Option<git2::Oid>>,
+    askpass_broker: Option<Option<StackId>>,
+) -> Result<()> {
+    let with_force = lease.is_some();
+    let _permit = NETWORK_OPERATIONS.acquire_blocking(
+        ctx.app_settings()
+            .concurrency
+            .effective_network_operations(),
+    );
+
+    // NOTE(qix-): This is a nasty hack, however the codebase isn't structured
+    // NOTE(qix-): in a way that allows us to really incorporate new backends
+    // NOTE(qix-): without a lot of work. This is a temporary measure to
+    // NOTE(qix-): work around a time-sensitive change that was necessary
+    // NOTE(qix-): without having to refactor a large portion of the codebase.
+    if ctx.project().preferred_key == AuthKey::SystemExecutable {
+        let path = ctx.project().worktree_path();
+        let remote = remote_name.to_string();
+        return std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(gitbutler_git::push(
+                    path,
+                    gitbutler_git::tokio::TokioExecutor,
+                    &remote,
+                    gitbutler_git::RefSpec::parse(refspec).unwrap(),
+                    with_force,
+                    handle_git_prompt_push,
+                    askpass_broker,
+                ))
+        })
+        .join()
+        .unwrap()
+        .map_err(Into::into);
+    }
+
+    let auth_flows = credentials::help(ctx, remote_name)?;
+    for (mut remote, callbacks) in auth_flows {
+        let mut update_refs_error: Option<git2::Error> = None;
+        let mut lease_broken: Option<PushRefusal> = None;
+        for callback in callbacks {
+            let mut cbs: git2::RemoteCallbacks = callback.into();
+            if ctx.project().omit_certificate_check.unwrap_or(false) {
+                cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
+            }
+            cbs.push_update_reference(|_reference: &str, status: Option<&str>| {
+                if let Some(status) = status {
+                    update_refs_error = Some(git2::Error::from_str(status));
+                    return Err(git2::Error::from_str(status));
+                };
+                Ok(())
+            });
+            if let Some(lease) = lease {
+                // Like `--force-with-lease`, only overwrite the remote branch if it's where it was last fetched.
+                cbs.push_negotiation(|updates| {
+                    for update in updates {
+                        let actual = (!update.src().is_zero()).then(|| update.src());
+                        if actual != lease {
+                            lease_broken = Some(PushRefusal::RemoteChanged {
+                                branch: target.to_owned(),
+                                expected: lease.map(|id| id.to_string()),
+                                actual: actual.map(|id| id.to_string()),
+                            });
+                            return Err(git2::Error::from_str("remote branch changed"));
+                        }
+                    }
                     Ok(())
                 });
+            }
 
-                let push_result = remote.push(
-                    &[refspec.as_str()],
-                    Some(&mut git2::PushOptions::new().remote_callbacks(cbs)),
-                );
-                match push_result {
-                    Ok(()) => {
-                        tracing::info!(
-                            project_id = %self.project().id,
-                            remote = %branch.remote(),
-                            %head,
-                            branch = branch.branch(),
-                            "pushed git branch"
-                        );
-                        return Ok(());
+            let push_result = remote.push(
+                &[refspec.as_str()],
+                Some(&mut git2::PushOptions::new().remote_callbacks(cbs)),
+            );
+            match push_result {
+                Ok(()) => {
+                    tracing::info!(
+                        project_id = %ctx.project().id,
+                        remote = remote_name,
+                        %head,
+                        branch = target,
+                        "pushed git branch"
+                    );
+                    return Ok(());
+                }
+                Err(err) => match err.class() {
+                    git2::ErrorClass::Net | git2::ErrorClass::Http => {
+                        tracing::warn!(project_id = %ctx.project().id, ?err, "push failed due to network");
+                        continue;
                     }
-                    Err(err) => match err.class() {
-                        git2::ErrorClass::Net | git2::ErrorClass::Http => {
-                            tracing::warn!(project_id = %self.project().id, ?err, "push failed due to network");
+                    _ => match err.code() {
+                        git2::ErrorCode::Auth => {
+                            tracing::warn!(project_id = %ctx.project().id, ?err, "push failed due to auth");
                             continue;
                         }
-                        _ => match err.code() {
-                            git2::ErrorCode::Auth => {
-                                tracing::warn!(project_id = %self.project().id, ?err, "push failed due to auth");
-                                continue;
+                        _ => {
+                            if let Some(refusal) = lease_broken {
+                                return Err(refusal.into_error());
                             }
-                            _ => {
-                                if let Some(update_refs_err) = update_refs_error {
-                                    return Err(update_refs_err).context(err);
-                                }
-                                return Err(err.into());
+                            if let Some(update_refs_err) = update_refs_error {
+                                return Err(update_refs_err).context(err);
                             }
-                        },
+                            return Err(err.into());
+                        }
                     },
-                }
+                },
             }
         }
-
-        Err(anyhow!("authentication failed").context(Code::ProjectGitAuth))
     }
 
-    fn fetch(&self, remote_name: &str, askpass: Option<String>) -> Result<()> {
-        let refspec = format!("+refs/heads/*:refs/remotes/{}/*", remote_name);
+    Err(anyhow!("authentication failed").context(Code::ProjectGitAuth))
+}
 
-        // NOTE(qix-): This is a nasty hack, however the codebase isn't structured
-        // NOTE(qix-): in a way that allows us to really incorporate new backends
-        // NOTE(qix-): without a lot of work. This is a temporary measure to
-        // NOTE(qix-): work around a time-sensitive change that was necessary
-        // NOTE(qix-): without having to refactor a large portion of the codebase.
-        if self.project().preferred_key == AuthKey::SystemExecutable {
-            let path = self.project().worktree_path();
-            let remote = remote_name.to_string();
-            return std::thread::spawn(move || {
-                tokio::runtime::Runtime::new()
-                    .unwrap()
-                    .block_on(gitbutler_git::fetch(
-                        path,
-                        gitbutler_git::tokio::TokioExecutor,
-                        &remote,
-                        gitbutler_git::RefSpec::parse(refspec).unwrap(),
-                        handle_git_prompt_fetch,
-                        askpass,
-                    ))
-            })
-            .join()
-            .unwrap()
-            .map_err(Into::into);
-        }
+fn fetch_refspec_interruptibly(
+    ctx: &CommandContext,
+    remote_name: &str,
+    refspec: String,
+    askpass: Option<String>,
+    should_interrupt: &AtomicBool,
+) -> Result<()> {
+    let _permit = NETWORK_OPERATIONS.acquire_blocking(
+        ctx.app_settings()
+            .concurrency
+            .effective_network_operations(),
+    );
+    // Waiting for the other fetches and pushes can take a while.
+    if should_interrupt.load(Ordering::Relaxed) {
+        bail!("Fetching '{remote_name}' was cancelled");
+    }
+    // NOTE(qix-): This is a nasty hack, however the codebase isn't structured
+    // NOTE(qix-): in a way that allows us to really incorporate new backends
+    // NOTE(qix-): without a lot of work. This is a temporary measure to
+    // NOTE(qix-): work around a time-sensitive change that was necessary
+    // NOTE(qix-): without having to refactor a large portion of the codebase.
+    if ctx.project().preferred_key == AuthKey::SystemExecutable {
+        let path = ctx.project().worktree_path();
+        let remote = remote_name.to_string();
+        return std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(gitbutler_git::fetch(
+                    path,
+                    gitbutler_git::tokio::TokioExecutor,
+                    &remote,
+                    gitbutler_git::RefSpec::parse(refspec).unwrap(),
+                    handle_git_prompt_fetch,
+                    askpass,
+                ))
+        })
+        .join()
+        .unwrap()
+        .map_err(Into::into);
+    }
 
-        let auth_flows = credentials::help(self, remote_name)?;
-        for (mut remote, callbacks) in auth_flows {
-            for callback in callbacks {
-                let mut fetch_opts = git2::FetchOptions::new();
-                let mut cbs: git2::RemoteCallbacks = callback.into();
-                if self.project().omit_certificate_check.unwrap_or(false) {
-                    cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
-                }
-                fetch_opts.remote_callbacks(cbs);
-                fetch_opts.prune(git2::FetchPrune::On);
+    let auth_flows = credentials::help(ctx, remote_name)?;
+    for (mut remote, callbacks) in auth_flows {
+        for callback in callbacks {
+            let mut fetch_opts = git2::FetchOptions::new();
+            let mut cbs: git2::RemoteCallbacks = callback.into();
+            if ctx.project().omit_certificate_check.unwrap_or(false) {
+                cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
+            }
+            cbs.transfer_progress(|_| !should_interrupt.load(Ordering::Relaxed));
+            fetch_opts.remote_callbacks(cbs);
+            fetch_opts.prune(git2::FetchPrune::On);
 
-                match remote.fetch(&[&refspec], Some(&mut fetch_opts), None) {
-                    Ok(()) => {
-                        tracing::info!(project_id = %self.project().id, %refspec, "git fetched");
-                        return Ok(());
+            match remote.fetch(&[&refspec], Some(&mut fetch_opts), None) {
+                Ok(()) => {
+                    tracing::info!(project_id = %ctx.project().id, %refspec, "git fetched");
+                    return Ok(());
+                }
+                Err(_) if should_interrupt.load(Ordering::Relaxed) => {
+                    bail!("Fetching '{remote_name}' was cancelled");
+                }
+                Err(err) => match err.class() {
+                    git2::ErrorClass::Net | git2::ErrorClass::Http => {
+                        tracing::warn!(project_id = %ctx.project().id, ?err, "fetch failed due to network");
+                        continue;
                     }
-                    Err(err) => match err.class() {
-                        git2::ErrorClass::Net | git2::ErrorClass::Http => {
-                            tracing::warn!(project_id = %self.project().id, ?err, "fetch failed due to network");
+                    _ => match err.code() {
+                        git2::ErrorCode::Auth => {
+                            tracing::warn!(project_id = %ctx.project().id, ?err, "fetch failed due to auth");
                             continue;
                         }
-                        _ => match err.code() {
-                            git2::ErrorCode::Auth => {
-                                tracing::warn!(project_id = %self.project().id, ?err, "fetch failed due to auth");
-                                continue;
-                            }
-                            _ => {
-                                return Err(err.into());
-                            }
-                        },
+                        _ => {
+                            return Err(err.into());
+                        }
                     },
-                }
+                },
             }
         }
-
-        Err(anyhow!("authentication failed")).context(Code::ProjectGitAuth)
     }
+
+    Err(anyhow!("authentication failed")).context(Code::ProjectGitAuth)
 }
 
 async fn handle_git_prompt_push(
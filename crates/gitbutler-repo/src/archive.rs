@@ -0,0 +1,214 @@
+//! Export the files of a commit or of the worktree as an archive, without the `.git` directory, like
+//! `git archive` does.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::RepositoryExt;
+
+/// The kind of archive to export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+/// A file to put into an archive.
+struct ArchiveEntry {
+    path: String,
+    blob_id: git2::Oid,
+    mode: git2::FileMode,
+}
+
+/// Write the files of `treeish`, or of the worktree with its uncommitted changes if `None`, as an archive in
+/// `format` to `path`, and return how many files it contains.
+///
+/// Files and directories with the `export-ignore` attribute, as configured in the worktree, are left out.
+/// Submodules are left out as well, as their commits aren't part of the repository.
+pub fn export_archive(
+    repo: &git2::Repository,
+    treeish: Option<&str>,
+    format: ArchiveFormat,
+    path: &Path,
+) -> Result<usize> {
+    let (tree, mtime) = match treeish {
+        Some(treeish) => {
+            let object = repo
+                .revparse_single(treeish)
+                .with_context(|| format!("'{treeish}' isn't a commit or tree"))?;
+            let mtime = object
+                .peel_to_commit()
+                .map_or_else(|_| now(), |commit| commit.time().seconds());
+            (object.peel_to_tree()?, mtime)
+        }
+        None => (repo.create_wd_tree(0)?, now()),
+    };
+    let entries = archive_entries(repo, &tree)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create '{}'", parent.display()))?;
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    match format {
+        ArchiveFormat::Zip => write_zip(repo, &entries, mtime, file)?,
+        ArchiveFormat::TarGz => write_tar_gz(repo, &entries, mtime, file)?,
+    }
+    Ok(entries.len())
+}
+
+/// Return the files in `tree`, skipping those with the `export-ignore` attribute and submodules.
+fn archive_entries(repo: &git2::Repository, tree: &git2::Tree) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut error = None;
+    let walked = tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        let Some(name) = entry.name() else {
+            return git2::TreeWalkResult::Skip;
+        };
+        let path = format!("{root}{name}");
+        match is_export_ignored(repo, Path::new(&path)) {
+            Ok(true) => return git2::TreeWalkResult::Skip,
+            Ok(false) => {}
+            Err(err) => {
+                error = Some(err);
+                return git2::TreeWalkResult::Abort;
+            }
+        }
+        let mode = [
+            git2::FileMode::Blob,
+            git2::FileMode::BlobExecutable,
+            git2::FileMode::Link,
+        ]
+        .into_iter()
+        .find(|mode| i32::from(*mode) == entry.filemode());
+        if let Some(mode) = mode {
+            entries.push(ArchiveEntry {
+                path,
+                blob_id: entry.id(),
+                mode,
+            });
+        }
+        git2::TreeWalkResult::Ok
+    });
+    if let Some(err) = error {
+        return Err(err);
+    }
+    walked?;
+    Ok(entries)
+}
+
+fn is_export_ignored(repo: &git2::Repository, path: &Path) -> Result<bool> {
+    let value = repo.get_attr(path, "export-ignore", git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(git2::AttrValue::from_string(value) == git2::AttrValue::True)
+}
+
+fn write_zip(
+    repo: &git2::Repository,
+    entries: &[ArchiveEntry],
+    mtime: i64,
+    file: std::fs::File,
+) -> Result<()> {
+    use std::io::Write;
+
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .last_modified_time(zip_time(mtime));
+    for entry in entries {
+        let blob = repo.find_blob(entry.blob_id)?;
+        match entry.mode {
+            git2::FileMode::Link => {
+                zip.add_symlink(
+                    entry.path.as_str(),
+                    String::from_utf8_lossy(blob.content()),
+                    options,
+                )?;
+            }
+            mode => {
+                zip.start_file(
+                    entry.path.as_str(),
+                    options.unix_permissions(unix_mode(mode)),
+                )?;
+                zip.write_all(blob.content())?;
+            }
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn write_tar_gz(
+    repo: &git2::Repository,
+    entries: &[ArchiveEntry],
+    mtime: i64,
+    file: std::fs::File,
+) -> Result<()> {
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+    for entry in entries {
+        let blob = repo.find_blob(entry.blob_id)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(mtime.max(0) as u64);
+        header.set_mode(unix_mode(entry.mode));
+        match entry.mode {
+            git2::FileMode::Link => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                tar.append_link(
+                    &mut header,
+                    &entry.path,
+                    PathBuf::from(String::from_utf8_lossy(blob.content()).as_ref()),
+                )?;
+            }
+            _ => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_size(blob.content().len() as u64);
+                tar.append_data(&mut header, &entry.path, blob.content())?;
+            }
+        }
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn unix_mode(mode: git2::FileMode) -> u32 {
+    match mode {
+        git2::FileMode::BlobExecutable | git2::FileMode::Link => 0o755,
+        _ => 0o644,
+    }
+}
+
+/// Convert `seconds` since the Unix epoch to the time of a zip entry in UTC, which can't be before 1980.
+fn zip_time(seconds: i64) -> zip::DateTime {
+    // The civil date of a day since the epoch, see http://howardhinnant.github.io/date_algorithms.html.
+    let (days, secs) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(year).unwrap_or_default(),
+        month as u8,
+        day as u8,
+        (secs / 3600) as u8,
+        (secs % 3600 / 60) as u8,
+        (secs % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
@@ -0,0 +1,124 @@
+//! Clone repositories, optionally shallow or partial so very large repositories can be worked with without
+//! fetching all of their history or contents, and deepen shallow clones later.
+//!
+//! This runs the Git CLI, as neither `git2` nor `gix` support partial clones.
+use std::ffi::OsString;
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+/// How far along a clone, fetch or repack is, as reported by Git.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitProgress {
+    /// What Git is doing, like `Receiving objects`.
+    pub phase: String,
+    /// How much of the phase is done.
+    pub percent: u8,
+}
+
+/// Clone the repository at `url` into `path`, with only the last `depth` commits of each branch if set, and
+/// without the objects excluded by `filter`, like `blob:none`, if set. `on_progress` is called as Git reports
+/// progress.
+///
+/// Blobs left out by a filter are fetched from the remote on demand.
+pub fn clone_repository(
+    url: &str,
+    path: &Path,
+    depth: Option<NonZeroU32>,
+    filter: Option<&str>,
+    on_progress: impl FnMut(GitProgress),
+) -> Result<()> {
+    if path.exists() && path.read_dir()?.next().is_some() {
+        bail!("'{}' already exists and isn't empty", path.display());
+    }
+    let mut args: Vec<OsString> = vec!["clone".into(), "--progress".into()];
+    if let Some(depth) = depth {
+        args.push(format!("--depth={depth}").into());
+    }
+    if let Some(filter) = filter {
+        args.push(format!("--filter={filter}").into());
+    }
+    args.extend(["--".into(), url.into(), path.into()]);
+    run_git(None, &args, on_progress).with_context(|| format!("failed to clone '{url}'"))
+}
+
+/// Fetch `depth` more commits of the history of the shallow clone `repo`, or all of it if `None`.
+pub fn unshallow(
+    repo: &git2::Repository,
+    depth: Option<NonZeroU32>,
+    on_progress: impl FnMut(GitProgress),
+) -> Result<()> {
+    if !repo.is_shallow() {
+        bail!("The repository already has its whole history");
+    }
+    let deepen = match depth {
+        Some(depth) => format!("--deepen={depth}"),
+        None => "--unshallow".to_owned(),
+    };
+    let args: Vec<OsString> = vec!["fetch".into(), "--progress".into(), deepen.into()];
+    run_git(repo.workdir().or(Some(repo.path())), &args, on_progress)
+}
+
+/// Run Git with `args` in `dir`, without prompting in a terminal, and report the progress it writes.
+pub(crate) fn run_git(
+    dir: Option<&Path>,
+    args: &[OsString],
+    mut on_progress: impl FnMut(GitProgress),
+) -> Result<()> {
+    let mut cmd = Command::new(gix::path::env::exe_invocation());
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let mut child = cmd
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run git")?;
+
+    let stderr = child.stderr.take().expect("piped");
+    let mut output = Vec::new();
+    let mut line = Vec::new();
+    let mut last = None;
+    for byte in std::io::BufReader::new(stderr).bytes() {
+        let byte = byte?;
+        if byte != b'\r' && byte != b'\n' {
+            line.push(byte);
+            continue;
+        }
+        let text = String::from_utf8_lossy(&line).into_owned();
+        if let Some(progress) =
+            parse_progress(&text).filter(|progress| last.as_ref() != Some(progress))
+        {
+            on_progress(progress.clone());
+            last = Some(progress);
+        } else if byte == b'\n' && !text.trim().is_empty() {
+            output.push(text);
+        }
+        line.clear();
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{}", output.join("\n"));
+    }
+    Ok(())
+}
+
+/// Parse a progress line like `Receiving objects:  45% (450/1000)`, or `None` if `line` isn't one.
+fn parse_progress(line: &str) -> Option<GitProgress> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(':')?;
+    let (percent, _) = rest.split_once('%')?;
+    Some(GitProgress {
+        phase: phase.trim().to_owned(),
+        percent: percent.trim().parse().ok()?,
+    })
+}
@@ -1,4 +1,7 @@
-use crate::{remote::GitRemote, Config, RepositoryExt};
+use crate::{
+    content_type, remote::GitRemote, tags, tags::Tag, Config, ContentEncoding, ContentType,
+    FileTreeEntry, RepositoryExt, WorktreeFile,
+};
 use anyhow::{bail, Result};
 use base64::engine::Engine as _;
 use git2::Oid;
@@ -6,16 +9,18 @@ use gitbutler_project::Project;
 use infer::MatcherType;
 use itertools::Itertools;
 use serde::Serialize;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use tracing::warn;
 
+/// The size in bytes above which file contents aren't returned unless another limit is given.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
 #[derive(Default, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 // TODO: turn this whole struct into an enum, it's : everything is an option style tells us that.
 pub struct FileInfo {
     /// If `None`, this means the file was deleted or has no meaningful content.
-    /// If `Some` and `size` is `Some`, then the `content` is base64 encoded, and
-    /// `size` is its decoded size.
+    /// If `Some`, the `encoding` tells how it was obtained from the bytes of the file, which may be base64.
     pub content: Option<String>,
     /// The basename as derived from the relative filepath.
     pub file_name: String,
@@ -24,6 +29,12 @@ pub struct FileInfo {
     /// If `None`, it's considered a text file. Otherwise, it's a binary file with the given
     /// inferred mimetype.
     pub mime_type: Option<String>,
+    /// The kind of content, which is always set unless the file is deleted.
+    pub content_type: Option<ContentType>,
+    /// How `content` was obtained from the bytes of the file, which is set if there is `content`.
+    pub encoding: Option<ContentEncoding>,
+    /// If `true`, some bytes couldn't be decoded and were replaced, so `content` doesn't match the file exactly.
+    pub lossy: bool,
 }
 
 impl FileInfo {
@@ -31,22 +42,52 @@ impl FileInfo {
         Self::default()
     }
 
+    /// Create an instance from `content`, which is decoded as text in the encoding it seems to have, or
+    /// provided as base64 if it's an image.
     pub fn from_content(path_in_worktree: &Path, content: &[u8]) -> Self {
-        if Self::is_binary(content) {
-            FileInfo::image_or_empty(path_in_worktree, content)
-        } else {
-            FileInfo::utf8_text_or_binary(path_in_worktree, content)
+        match content_type::decode_text(content) {
+            Some(decoded) => FileInfo {
+                content: Some(decoded.text),
+                file_name: Self::file_name_str(path_in_worktree),
+                size: Some(content.len()),
+                mime_type: None,
+                content_type: Some(ContentType::Text),
+                encoding: Some(decoded.encoding),
+                lossy: decoded.lossy,
+            },
+            None => FileInfo::image_or_empty(path_in_worktree, content),
+        }
+    }
+
+    /// Create an instance with `content` as base64, whatever its type.
+    pub fn base64(path_in_worktree: &Path, content: &[u8]) -> Self {
+        FileInfo {
+            content: Some(base64::engine::general_purpose::STANDARD.encode(content)),
+            file_name: Self::file_name_str(path_in_worktree),
+            size: Some(content.len()),
+            mime_type: infer::get(content).map(|kind| kind.mime_type().to_owned()),
+            content_type: Some(ContentType::detect(path_in_worktree, content)),
+            encoding: Some(ContentEncoding::Base64),
+            lossy: false,
         }
     }
 
     /// Create a new instance for if content is text.
     /// Note that UTF8 is assumed, or else the file will be considered binary.
     pub fn utf8_text_or_binary(path_in_worktree: &Path, content: &[u8]) -> Self {
+        let text = std::str::from_utf8(content).map(ToOwned::to_owned).ok();
         FileInfo {
-            content: std::str::from_utf8(content).map(ToOwned::to_owned).ok(),
+            content_type: Some(if text.is_some() {
+                ContentType::Text
+            } else {
+                ContentType::Binary
+            }),
+            encoding: text.is_some().then_some(ContentEncoding::Utf8),
+            content: text,
             file_name: Self::file_name_str(path_in_worktree),
             size: Some(content.len()),
             mime_type: None,
+            lossy: false,
         }
     }
 
@@ -57,6 +98,33 @@ impl FileInfo {
             file_name: Self::file_name_str(path_in_worktree),
             size: Some(len as usize),
             mime_type: None,
+            content_type: Some(ContentType::Binary),
+            encoding: None,
+            lossy: false,
+        }
+    }
+
+    /// No content is provided as the file is larger than the given limit, but the type of content is
+    /// detected from the `prefix` of the content, which is `len` bytes long.
+    pub fn too_large(path_in_worktree: &Path, prefix: &[u8], len: u64) -> Self {
+        FileInfo {
+            content: None,
+            file_name: Self::file_name_str(path_in_worktree),
+            size: Some(len as usize),
+            mime_type: None,
+            content_type: Some(ContentType::detect(path_in_worktree, prefix)),
+            encoding: None,
+            lossy: false,
+        }
+    }
+
+    /// Like [`Self::from_content()`], but return [`Self::too_large()`] if `content` is larger than `max_size`.
+    pub fn from_content_limited(path_in_worktree: &Path, content: &[u8], max_size: u64) -> Self {
+        if content.len() as u64 > max_size {
+            let prefix = &content[..content.len().min(content_type::PREFIX_LEN)];
+            FileInfo::too_large(path_in_worktree, prefix, content.len() as u64)
+        } else {
+            FileInfo::from_content(path_in_worktree, content)
         }
     }
 
@@ -69,6 +137,9 @@ impl FileInfo {
             file_name: Self::file_name_str(path_in_worktree),
             size: Some(content.len()),
             mime_type: None,
+            content_type: Some(ContentType::detect(path_in_worktree, content)),
+            encoding: None,
+            lossy: false,
         };
 
         let kind = infer::get(content);
@@ -77,7 +148,8 @@ impl FileInfo {
         }) {
             let base64_content = base64::engine::general_purpose::STANDARD.encode(content);
             file_info.content = Some(base64_content);
-            file_info.mime_type = Some(mime_type.to_owned())
+            file_info.mime_type = Some(mime_type.to_owned());
+            file_info.encoding = Some(ContentEncoding::Base64);
         }
         file_info
     }
@@ -88,15 +160,42 @@ impl FileInfo {
             .to_string_lossy()
             .into_owned()
     }
+}
 
-    fn is_binary(content: &[u8]) -> bool {
-        let partial_content = &content[..content.len().min(8000)];
-        gix::filter::plumbing::eol::Stats::from_bytes(partial_content).is_binary()
-    }
+/// A file as it is in the worktree, the index and the tree of `HEAD`, as returned by
+/// [`RepoCommands::read_file_versions()`]. Each version is [`FileInfo::deleted()`] if the file doesn't exist there.
+#[derive(Default, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersions {
+    pub worktree: FileInfo,
+    pub index: FileInfo,
+    pub head: FileInfo,
+}
+
+/// Return the remote name and the branch name on that remote that the current branch is tracking, if any.
+fn head_upstream(repo: &git2::Repository) -> Option<(String, String)> {
+    let head = repo.head().ok()?;
+    let branch_name = head.shorthand()?;
+    let config = repo.config().ok()?;
+    let remote = config
+        .get_string(&format!("branch.{branch_name}.remote"))
+        .ok()?;
+    let merge = config
+        .get_string(&format!("branch.{branch_name}.merge"))
+        .ok()?;
+    let upstream = merge
+        .strip_prefix("refs/heads/")
+        .unwrap_or(&merge)
+        .to_owned();
+    Some((remote, upstream))
 }
 
 pub trait RepoCommands {
     fn add_remote(&self, name: &str, url: &str) -> Result<()>;
+    /// Remove the remote called `name` along with its remote-tracking branches.
+    fn remove_remote(&self, name: &str) -> Result<()>;
+    /// Change the fetch URL of the remote called `name` to `url`.
+    fn set_remote_url(&self, name: &str, url: &str) -> Result<()>;
     fn remotes(&self) -> Result<Vec<GitRemote>>;
     fn get_local_config(&self, key: &str) -> Result<Option<String>>;
     fn set_local_config(&self, key: &str, value: &str) -> Result<()>;
@@ -119,9 +218,85 @@ pub trait RepoCommands {
     ///
     /// Returns `FileInfo::default()` if file could not be found.
     fn read_file_from_workspace(&self, path: &Path) -> Result<FileInfo>;
+
+    /// Read `path` from the worktree only, so that new files and the latest edits are seen, and return
+    /// `FileInfo::deleted()` if it doesn't exist there.
+    ///
+    /// `path` must be relative to the worktree and can't leave it. The content of files larger than `max_size` isn't
+    /// returned, see [`FileInfo::too_large()`].
+    fn read_file_from_worktree(&self, path: &Path, max_size: u64) -> Result<FileInfo>;
+
+    /// Read the bytes of `path` from the tree of `commit_id`, or from the worktree if `None`, for callers that
+    /// need them exactly, like in [`FileInfo::base64()`]. Returns `None` if the file doesn't exist.
+    ///
+    /// `path` must be relative to the worktree and can't leave it. Fails if the file is larger than `max_size`.
+    fn read_file_bytes(
+        &self,
+        path: &Path,
+        commit_id: Option<Oid>,
+        max_size: u64,
+    ) -> Result<Option<Vec<u8>>>;
+
+    /// Read `path` from the worktree, the index and `HEAD^{tree}` at once, for showing them side by side.
+    ///
+    /// The same rules as for [`Self::read_file_from_worktree()`] apply.
+    fn read_file_versions(&self, path: &Path, max_size: u64) -> Result<FileVersions>;
+
+    /// List the entries directly within the worktree directory at `subpath`, or at the worktree root if `None`,
+    /// along with their kind, size and git status.
+    ///
+    /// Subdirectories aren't listed recursively, call this again with their path to load them.
+    fn file_tree(&self, subpath: Option<&Path>) -> Result<Vec<FileTreeEntry>>;
+
+    /// List the worktree-relative paths of all tracked files sorted by path, or only of those matching `glob`,
+    /// and no more than `limit` of them if set.
+    fn list_project_files(&self, glob: Option<&str>, limit: Option<usize>) -> Result<Vec<PathBuf>>;
+
+    /// List all tags that point to a commit, sorted by name.
+    fn tags(&self) -> Result<Vec<Tag>>;
+    /// Create a tag called `name` pointing to `target_id`, see [`tags::create_tag()`].
+    fn create_tag(
+        &self,
+        name: &str,
+        target_id: Oid,
+        message: Option<&str>,
+        sign: bool,
+    ) -> Result<Oid>;
+    fn delete_tag(&self, name: &str) -> Result<()>;
 }
 
 impl RepoCommands for Project {
+    fn tags(&self) -> Result<Vec<Tag>> {
+        let repo = &git2::Repository::open(&self.path)?;
+        tags::list_tags(repo)
+    }
+
+    fn create_tag(
+        &self,
+        name: &str,
+        target_id: Oid,
+        message: Option<&str>,
+        sign: bool,
+    ) -> Result<Oid> {
+        let repo = &git2::Repository::open(&self.path)?;
+        tags::create_tag(repo, name, target_id, message, sign)
+    }
+
+    fn delete_tag(&self, name: &str) -> Result<()> {
+        let repo = &git2::Repository::open(&self.path)?;
+        tags::delete_tag(repo, name)
+    }
+
+    fn file_tree(&self, subpath: Option<&Path>) -> Result<Vec<FileTreeEntry>> {
+        let repo = &git2::Repository::open(&self.path)?;
+        crate::file_tree::file_tree(repo, subpath)
+    }
+
+    fn list_project_files(&self, glob: Option<&str>, limit: Option<usize>) -> Result<Vec<PathBuf>> {
+        let repo = gix::open(&self.path)?;
+        crate::file_tree::list_files(&repo, glob, limit)
+    }
+
     fn get_local_config(&self, key: &str) -> Result<Option<String>> {
         let repo = &git2::Repository::open(&self.path)?;
         let config: Config = repo.into();
@@ -145,17 +320,43 @@ impl RepoCommands for Project {
 
     fn remotes(&self) -> anyhow::Result<Vec<GitRemote>> {
         let repo = &git2::Repository::open(&self.path)?;
+        let head_upstream = head_upstream(repo);
         let remotes = repo
             .remotes_as_string()?
             .iter()
             .map(|name| repo.find_remote(name))
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
-            .map(|remote| remote.into())
+            .map(|remote| {
+                let mut remote = GitRemote::from(remote);
+                remote.default_upstream = head_upstream
+                    .as_ref()
+                    .filter(|(remote_name, _)| remote.name.as_ref() == Some(remote_name))
+                    .map(|(_, branch)| branch.clone());
+                remote
+            })
             .collect_vec();
         Ok(remotes)
     }
 
+    fn remove_remote(&self, name: &str) -> Result<()> {
+        let repo = &git2::Repository::open(&self.path)?;
+        if repo.find_remote(name).is_err() {
+            bail!("Remote '{}' doesn't exist", name);
+        }
+        repo.remote_delete(name)?;
+        Ok(())
+    }
+
+    fn set_remote_url(&self, name: &str, url: &str) -> Result<()> {
+        let repo = &git2::Repository::open(&self.path)?;
+        if repo.find_remote(name).is_err() {
+            bail!("Remote '{}' doesn't exist", name);
+        }
+        repo.remote_set_url(name, url)?;
+        Ok(())
+    }
+
     fn add_remote(&self, name: &str, url: &str) -> Result<()> {
         let repo = &git2::Repository::open(&self.path)?;
 
@@ -253,4 +454,119 @@ impl RepoCommands for Project {
             Err(err) => return Err(err.into()),
         })
     }
+
+    fn read_file_from_worktree(&self, path: &Path, max_size: u64) -> Result<FileInfo> {
+        let relative_path = checked_relative_path(path)?;
+        let path_in_worktree = self.path.join(relative_path);
+        Ok(
+            match content_type::read_worktree_file(&path_in_worktree, max_size)? {
+                WorktreeFile::Content { content, .. } => {
+                    FileInfo::from_content(relative_path, &content)
+                }
+                WorktreeFile::TooLarge { prefix, len, .. } => {
+                    FileInfo::too_large(relative_path, &prefix, len)
+                }
+                WorktreeFile::Link { target } => {
+                    FileInfo::utf8_text_or_binary(relative_path, &gix::path::into_bstr(target))
+                }
+                WorktreeFile::Directory => bail!(
+                    "Path to read at '{}' is a directory",
+                    relative_path.display()
+                ),
+                WorktreeFile::Other { len } => FileInfo::binary(relative_path, len),
+                WorktreeFile::Missing => FileInfo::deleted(),
+            },
+        )
+    }
+
+    fn read_file_bytes(
+        &self,
+        path: &Path,
+        commit_id: Option<Oid>,
+        max_size: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let relative_path = checked_relative_path(path)?;
+        let content = match commit_id {
+            Some(commit_id) => {
+                let repo = git2::Repository::open(&self.path)?;
+                let tree = repo.find_commit(commit_id)?.tree()?;
+                match tree.get_path(relative_path) {
+                    Ok(entry) => Some(repo.find_blob(entry.id())?.content().to_owned()),
+                    Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            None => {
+                let path_in_worktree = self.path.join(relative_path);
+                match path_in_worktree.symlink_metadata() {
+                    Ok(md) if md.is_symlink() => Some(
+                        gix::path::into_bstr(std::fs::read_link(&path_in_worktree)?)
+                            .into_owned()
+                            .into(),
+                    ),
+                    Ok(md) if md.len() > max_size => bail!(
+                        "File at '{}' is larger than {max_size} bytes, it has {} bytes",
+                        relative_path.display(),
+                        md.len()
+                    ),
+                    Ok(_) => Some(std::fs::read(&path_in_worktree)?),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        };
+        if let Some(content) = content.as_ref().filter(|c| c.len() as u64 > max_size) {
+            bail!(
+                "File at '{}' is larger than {max_size} bytes, it has {} bytes",
+                relative_path.display(),
+                content.len()
+            );
+        }
+        Ok(content)
+    }
+
+    fn read_file_versions(&self, path: &Path, max_size: u64) -> Result<FileVersions> {
+        let worktree = self.read_file_from_worktree(path, max_size)?;
+        let relative_path = checked_relative_path(path)?;
+        let repo = git2::Repository::open(&self.path)?;
+        let index = match repo.index()?.get_path(relative_path, 0) {
+            Some(entry) => {
+                let blob = repo.find_blob(entry.id)?;
+                FileInfo::from_content_limited(relative_path, blob.content(), max_size)
+            }
+            None => FileInfo::deleted(),
+        };
+        let head = match repo.head().and_then(|head| head.peel_to_tree()) {
+            Ok(tree) => match tree.get_path(relative_path) {
+                Ok(entry) => {
+                    let blob = repo.find_blob(entry.id())?;
+                    FileInfo::from_content_limited(relative_path, blob.content(), max_size)
+                }
+                Err(err) if err.code() == git2::ErrorCode::NotFound => FileInfo::deleted(),
+                Err(err) => return Err(err.into()),
+            },
+            // There is no commit yet.
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => FileInfo::deleted(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(FileVersions {
+            worktree,
+            index,
+            head,
+        })
+    }
+}
+
+/// Return `path` if it's relative and stays within the worktree.
+fn checked_relative_path(path: &Path) -> Result<&Path> {
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        bail!(
+            "Path to read at '{}' must be relative to the worktree and can't leave it",
+            path.display()
+        );
+    }
+    Ok(path)
 }
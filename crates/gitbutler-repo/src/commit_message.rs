@@ -1,6 +1,19 @@
 #![deny(rust_2018_idioms)]
 
+use anyhow::{bail, Context, Result};
 use bstr::{BString, ByteSlice as _, ByteVec as _};
+use gitbutler_project::CoAuthor;
+use serde::Serialize;
+
+use crate::RepositoryExt;
+
+/// The trailer certifying that the author has the right to submit the change, as added by `git commit --signoff`.
+pub const SIGNED_OFF_BY: &str = "Signed-off-by";
+/// The trailer crediting someone else as author of the change, as understood by forges like GitHub.
+pub const CO_AUTHORED_BY: &str = "Co-authored-by";
+
+/// Subjects longer than this many characters are cut off by many tools that show them.
+pub const MAX_SUBJECT_CHARS: usize = 72;
 
 pub struct CommitMessage {
     pub title: BString,
@@ -52,3 +65,227 @@ impl CommitMessage {
         }
     }
 }
+
+/// Return `message` with a `Signed-off-by` trailer for the author of commits in `repo` if `sign_off` is `true`, and a
+/// `Co-authored-by` trailer for each of `co_authors`.
+///
+/// The author signs off rather than the committer, as GitButler may be configured to commit in its own name.
+pub fn with_trailers(
+    repo: &git2::Repository,
+    message: &str,
+    sign_off: bool,
+    co_authors: &[CoAuthor],
+) -> Result<String> {
+    let mut trailers = Vec::new();
+    if sign_off {
+        let (author, _committer) = repo.signatures()?;
+        trailers.push((
+            SIGNED_OFF_BY,
+            person(
+                &String::from_utf8_lossy(author.name_bytes()),
+                &String::from_utf8_lossy(author.email_bytes()),
+            ),
+        ));
+    }
+    trailers.extend(
+        co_authors
+            .iter()
+            .map(|co_author| (CO_AUTHORED_BY, person(&co_author.name, &co_author.email))),
+    );
+    Ok(append_trailers(message, &trailers))
+}
+
+/// Return `name` and `email` in the form used by trailers, like `Jane Doe <jane@example.com>`.
+fn person(name: &str, email: &str) -> String {
+    format!("{} <{}>", name.trim(), email.trim())
+}
+
+/// Append `trailers` as `(token, value)` to `message` the way `git interpret-trailers` does: to the trailers the
+/// message ends with, or in a paragraph of their own. Trailers the message has already are skipped.
+pub fn append_trailers(message: &str, trailers: &[(&str, String)]) -> String {
+    let trimmed = message.trim_end();
+    let lines: Vec<_> = trimmed.lines().collect();
+    let last_paragraph = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(&[][..], |blank| &lines[blank + 1..]);
+    let mut separated =
+        !last_paragraph.is_empty() && last_paragraph.iter().all(|line| is_trailer(line));
+
+    let mut out = trimmed.to_owned();
+    for (token, value) in trailers {
+        let trailer = format!("{token}: {value}");
+        if lines.iter().any(|line| line.trim() == trailer) {
+            continue;
+        }
+        out.push_str(if separated { "\n" } else { "\n\n" });
+        out.push_str(&trailer);
+        separated = true;
+    }
+    if out.len() == trimmed.len() {
+        return message.to_owned();
+    }
+    out.push('\n');
+    out
+}
+
+/// Return `true` if `line` looks like a trailer, like `Reviewed-by: Jane Doe <jane@example.com>`.
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(token, _)| {
+        !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// Return the content of the file configured as `commit.template` in the git config of `repo`, to start commit
+/// messages with, or `None` if there is none.
+///
+/// Like with git, a relative path is relative to the worktree.
+pub fn template(repo: &gix::Repository) -> Result<Option<String>> {
+    let config = repo.config_snapshot();
+    let Some(path) = config.trusted_path("commit.template").transpose()? else {
+        return Ok(None);
+    };
+    let path = match repo.work_dir() {
+        Some(worktree_dir) if path.is_relative() => worktree_dir.join(path),
+        _ => path.into_owned(),
+    };
+    let template = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read the commit template at '{}'", path.display()))?;
+    Ok(Some(template))
+}
+
+/// Whether a [`Violation`] keeps a message from being committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The message can be committed, but should be improved.
+    Warning,
+    /// The message can't be committed as it is.
+    Error,
+}
+
+/// What is wrong with a commit message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ViolationKind {
+    /// There is nothing but whitespace.
+    Empty,
+    /// The subject is longer than [`MAX_SUBJECT_CHARS`].
+    SubjectTooLong,
+    /// The subject isn't separated from the body by an empty line.
+    MissingBlankLine,
+    /// The subject isn't of the form `<type>[(<scope>)][!]: <description>`, as required by
+    /// [Conventional Commits](https://www.conventionalcommits.org).
+    NotConventional,
+}
+
+/// A problem with a commit message, as found by [`validate()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub severity: Severity,
+    /// The 1-based number of the line with the problem.
+    pub line: usize,
+    /// What is wrong, to show to the user.
+    pub message: String,
+}
+
+/// Return all problems with `message`, where the subject has to follow Conventional Commits if
+/// `conventional_commits` is `true`.
+pub fn validate(message: &str, conventional_commits: bool) -> Vec<Violation> {
+    let violation = |kind, severity, line, message: String| Violation {
+        kind,
+        severity,
+        line,
+        message,
+    };
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or_default();
+    if message.trim().is_empty() {
+        return vec![violation(
+            ViolationKind::Empty,
+            Severity::Error,
+            1,
+            "The commit message is empty".into(),
+        )];
+    }
+
+    let mut violations = Vec::new();
+    let subject_chars = subject.chars().count();
+    if subject_chars > MAX_SUBJECT_CHARS {
+        violations.push(violation(
+            ViolationKind::SubjectTooLong,
+            Severity::Warning,
+            1,
+            format!("The subject has {subject_chars} characters, more than {MAX_SUBJECT_CHARS}"),
+        ));
+    }
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        violations.push(violation(
+            ViolationKind::MissingBlankLine,
+            Severity::Warning,
+            2,
+            "The subject should be followed by an empty line".into(),
+        ));
+    }
+    if conventional_commits {
+        if let Err(problem) = check_conventional_subject(subject) {
+            violations.push(violation(
+                ViolationKind::NotConventional,
+                Severity::Error,
+                1,
+                format!("The subject should look like 'feat(scope): description', but {problem}"),
+            ));
+        }
+    }
+    violations
+}
+
+/// Fail if `message` has a [violation](validate()) with [`Severity::Error`], and log those that are warnings.
+pub fn ensure_valid(message: &str, conventional_commits: bool) -> Result<()> {
+    let (errors, warnings): (Vec<_>, Vec<_>) = validate(message, conventional_commits)
+        .into_iter()
+        .partition(|violation| violation.severity == Severity::Error);
+    for warning in &warnings {
+        tracing::warn!(kind = ?warning.kind, "{}", warning.message);
+    }
+    if !errors.is_empty() {
+        bail!(
+            "{}",
+            errors
+                .iter()
+                .map(|error| error.message.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Check that `subject` is `<type>[(<scope>)][!]: <description>`, or return what's wrong with it.
+fn check_conventional_subject(subject: &str) -> Result<(), &'static str> {
+    let Some((prefix, description)) = subject.split_once(':') else {
+        return Err("there is no ':' after the type");
+    };
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) => {
+            let Some(scope) = scope.strip_suffix(')') else {
+                return Err("the scope isn't closed with ')'");
+            };
+            if scope.trim().is_empty() || scope.contains(['(', ')']) {
+                return Err("the scope is empty or has parentheses");
+            }
+            kind
+        }
+        None => prefix,
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err("the type isn't a single word");
+    }
+    if !description.starts_with(' ') || description.trim().is_empty() {
+        return Err("the description doesn't follow after ': '");
+    }
+    Ok(())
+}
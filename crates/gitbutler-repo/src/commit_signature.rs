@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::{bail, Context, Result};
+use bstr::ByteSlice;
+use serde::Serialize;
+
+use crate::repository_ext::{into_command, prepare_with_shell};
+
+/// The kind of signature of a commit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureFormat {
+    Gpg,
+    Ssh,
+}
+
+/// The outcome of verifying the signature of a commit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureStatus {
+    /// The commit isn't signed at all.
+    Unsigned,
+    /// The signature matches the commit, and was made by a known key.
+    Valid,
+    /// The signature matches the commit, but its key isn't among the allowed signers, or none are configured with
+    /// `gpg.ssh.allowedSignersFile`, so who made it is unknown.
+    UnknownKey,
+    /// The signature doesn't match the commit, or couldn't be verified with the available keys.
+    Invalid,
+}
+
+/// Information about the signature of a commit, as returned by [`verify_commit_signature()`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureVerification {
+    pub status: SignatureStatus,
+    /// The kind of signature, `None` if the commit is unsigned.
+    pub format: Option<SignatureFormat>,
+    /// Who signed the commit, if known.
+    pub signer: Option<String>,
+    /// The output of the verification program, for display to the user.
+    pub details: String,
+}
+
+impl SignatureVerification {
+    fn unsigned() -> Self {
+        SignatureVerification {
+            status: SignatureStatus::Unsigned,
+            format: None,
+            signer: None,
+            details: String::new(),
+        }
+    }
+}
+
+/// Verify the signature of the commit with `commit_id` in `repo`, using `gpg` or `ssh-keygen` as configured
+/// with `gpg.program`, `gpg.ssh.program` and `gpg.ssh.allowedSignersFile`, just like `git verify-commit`.
+pub fn verify_commit_signature(
+    repo: &git2::Repository,
+    commit_id: git2::Oid,
+) -> Result<SignatureVerification> {
+    let (signature, signed_data) = match repo.extract_signature(&commit_id, None) {
+        Ok(extracted) => extracted,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            return Ok(SignatureVerification::unsigned())
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut signature_storage = tempfile::NamedTempFile::new()?;
+    signature_storage.write_all(&signature)?;
+
+    let repo = gix::open(repo.path())?;
+    let config = repo.config_snapshot();
+    if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+        let program = config
+            .trusted_program("gpg.ssh.program")
+            .filter(|program| !program.is_empty())
+            .map_or_else(
+                || Path::new("ssh-keygen").into(),
+                |program| Cow::Owned(program.into_owned().into()),
+            );
+        let allowed_signers = config
+            .trusted_path("gpg.ssh.allowedSignersFile")
+            .transpose()?;
+        verify_ssh(
+            program.as_ref(),
+            allowed_signers.as_deref(),
+            signature_storage.path(),
+            &signed_data,
+        )
+    } else {
+        let program = config
+            .trusted_program("gpg.program")
+            .filter(|program| !program.is_empty())
+            .map_or_else(
+                || Path::new("gpg").into(),
+                |program| Cow::Owned(program.into_owned().into()),
+            );
+        verify_gpg(program.as_ref(), signature_storage.path(), &signed_data)
+    }
+}
+
+fn verify_gpg(
+    program: &Path,
+    signature_path: &Path,
+    signed_data: &[u8],
+) -> Result<SignatureVerification> {
+    let output = run_with_stdin(
+        prepare_with_shell(program)
+            .args(["--status-fd=1", "--verify"])
+            .arg(signature_path)
+            .arg("-"),
+        signed_data,
+    )?;
+    let status_lines = output.stdout.to_str_lossy();
+    let signer = status_lines.lines().find_map(|line| {
+        // [GNUPG:] GOODSIG <long-keyid> <user-id>
+        let rest = line.strip_prefix("[GNUPG:] GOODSIG ")?;
+        rest.split_once(' ').map(|(_, user_id)| user_id.to_owned())
+    });
+    Ok(SignatureVerification {
+        status: if output.status.success() && signer.is_some() {
+            SignatureStatus::Valid
+        } else {
+            SignatureStatus::Invalid
+        },
+        format: Some(SignatureFormat::Gpg),
+        signer,
+        details: output.stderr.to_str_lossy().into_owned(),
+    })
+}
+
+fn verify_ssh(
+    program: &Path,
+    allowed_signers: Option<&Path>,
+    signature_path: &Path,
+    signed_data: &[u8],
+) -> Result<SignatureVerification> {
+    let Some(allowed_signers) = allowed_signers else {
+        return check_ssh_without_signer(program, signature_path, signed_data);
+    };
+
+    let principals = into_command(
+        prepare_with_shell(program)
+            .args(["-Y", "find-principals", "-f"])
+            .arg(allowed_signers)
+            .arg("-s")
+            .arg(signature_path),
+    )
+    .stdin(Stdio::null())
+    .output()?;
+    let Some(principal) = principals
+        .stdout
+        .to_str_lossy()
+        .lines()
+        .next()
+        .map(ToOwned::to_owned)
+        .filter(|_| principals.status.success())
+    else {
+        return check_ssh_without_signer(program, signature_path, signed_data);
+    };
+
+    let output = run_with_stdin(
+        prepare_with_shell(program)
+            .args(["-Y", "verify", "-n", "git", "-f"])
+            .arg(allowed_signers)
+            .arg("-I")
+            .arg(&principal)
+            .arg("-s")
+            .arg(signature_path),
+        signed_data,
+    )?;
+    Ok(SignatureVerification {
+        status: if output.status.success() {
+            SignatureStatus::Valid
+        } else {
+            SignatureStatus::Invalid
+        },
+        format: Some(SignatureFormat::Ssh),
+        signer: Some(principal),
+        details: both_outputs(&output),
+    })
+}
+
+/// Check that the SSH signature at `signature_path` matches `signed_data` when its key isn't known to belong to
+/// anyone, which doesn't make it [valid](SignatureStatus::Valid) as anyone could have made it.
+fn check_ssh_without_signer(
+    program: &Path,
+    signature_path: &Path,
+    signed_data: &[u8],
+) -> Result<SignatureVerification> {
+    let output = run_with_stdin(
+        prepare_with_shell(program)
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(signature_path),
+        signed_data,
+    )?;
+    Ok(SignatureVerification {
+        status: if output.status.success() {
+            SignatureStatus::UnknownKey
+        } else {
+            SignatureStatus::Invalid
+        },
+        format: Some(SignatureFormat::Ssh),
+        signer: None,
+        details: both_outputs(&output),
+    })
+}
+
+fn run_with_stdin(prepare: gix::command::Prepare, stdin: &[u8]) -> Result<std::process::Output> {
+    let mut cmd = into_command(prepare);
+    cmd.stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(Stdio::piped());
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            bail!("Could not find '{}'. Please make sure it is in your `PATH` or configure its full path in the Git configuration", cmd.get_program().to_string_lossy())
+        }
+        Err(err) => {
+            return Err(err).context(format!(
+                "Could not execute verification program using {:?}",
+                cmd
+            ))
+        }
+    };
+    child.stdin.take().expect("configured").write_all(stdin)?;
+    Ok(child.wait_with_output()?)
+}
+
+fn both_outputs(output: &std::process::Output) -> String {
+    format!(
+        "{} {}",
+        output.stdout.to_str_lossy(),
+        output.stderr.to_str_lossy()
+    )
+    .trim()
+    .to_owned()
+}
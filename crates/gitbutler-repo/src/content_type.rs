@@ -0,0 +1,229 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use infer::MatcherType;
+use serde::{Deserialize, Serialize};
+
+/// A coarse classification of file contents, to decide how they should be displayed or processed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentType {
+    /// Content that can be shown as text.
+    Text,
+    /// An image format that can be previewed.
+    Image,
+    /// A compressed or archived container of other files.
+    Archive,
+    /// Any other binary content.
+    Binary,
+}
+
+/// How the `content` of a [`FileInfo`](crate::FileInfo) was obtained from the bytes of the file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentEncoding {
+    /// The bytes were valid UTF-8 and are used as is.
+    Utf8,
+    /// The bytes started with a little-endian UTF-16 byte order mark and were decoded as such.
+    Utf16Le,
+    /// The bytes started with a big-endian UTF-16 byte order mark and were decoded as such.
+    Utf16Be,
+    /// The bytes looked like text but weren't valid UTF-8, so each byte was decoded as a Latin-1 character.
+    /// This never fails, but may show the wrong characters if the file is in another legacy encoding.
+    Latin1,
+    /// The bytes are encoded as base64, for binary content like images.
+    Base64,
+}
+
+/// Text decoded from the bytes of a file by [`decode_text()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: ContentEncoding,
+    /// `true` if some bytes couldn't be decoded and were replaced with `U+FFFD`.
+    pub lossy: bool,
+}
+
+/// Decode `content` as text, detecting its encoding, or return `None` if it's binary.
+///
+/// UTF-16 is detected by its byte order mark. Otherwise, content that looks like text is UTF-8 if valid, and
+/// Latin-1 if not.
+pub fn decode_text(content: &[u8]) -> Option<DecodedText> {
+    let utf16 = match content {
+        [0xFF, 0xFE, rest @ ..] => Some((ContentEncoding::Utf16Le, rest)),
+        [0xFE, 0xFF, rest @ ..] => Some((ContentEncoding::Utf16Be, rest)),
+        _ => None,
+    };
+    if let Some((encoding, rest)) = utf16 {
+        let units = rest.chunks_exact(2).map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if encoding == ContentEncoding::Utf16Le {
+                u16::from_le_bytes(pair)
+            } else {
+                u16::from_be_bytes(pair)
+            }
+        });
+        let mut lossy = rest.len() % 2 != 0;
+        let text = char::decode_utf16(units)
+            .map(|c| {
+                c.unwrap_or_else(|_| {
+                    lossy = true;
+                    char::REPLACEMENT_CHARACTER
+                })
+            })
+            .collect();
+        return Some(DecodedText {
+            text,
+            encoding,
+            lossy,
+        });
+    }
+    if is_binary(content) {
+        return None;
+    }
+    Some(match std::str::from_utf8(content) {
+        Ok(text) => DecodedText {
+            text: text.to_owned(),
+            encoding: ContentEncoding::Utf8,
+            lossy: false,
+        },
+        Err(_) => DecodedText {
+            text: content.iter().map(|byte| char::from(*byte)).collect(),
+            encoding: ContentEncoding::Latin1,
+            lossy: false,
+        },
+    })
+}
+
+/// File extensions of archives, used if the content itself doesn't give it away.
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar"];
+/// File extensions of images, used if the content itself doesn't give it away.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "tiff"];
+
+impl ContentType {
+    /// Classify `content` by its magic bytes, falling back to the extension of `path` for binary
+    /// content of an unknown format.
+    pub fn detect(path: &Path, content: &[u8]) -> Self {
+        match infer::get(content).map(|kind| kind.matcher_type()) {
+            Some(MatcherType::Image) => return ContentType::Image,
+            Some(MatcherType::Archive) => return ContentType::Archive,
+            Some(MatcherType::Text) => return ContentType::Text,
+            _ => {}
+        }
+        if !is_binary(content) {
+            return ContentType::Text;
+        }
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+        {
+            Some(ext) if ARCHIVE_EXTENSIONS.contains(&ext.as_str()) => ContentType::Archive,
+            Some(ext) if IMAGE_EXTENSIONS.contains(&ext.as_str()) => ContentType::Image,
+            _ => ContentType::Binary,
+        }
+    }
+
+    /// Return `true` if the content isn't [text](ContentType::Text).
+    pub fn is_binary(&self) -> bool {
+        *self != ContentType::Text
+    }
+}
+
+/// The number of bytes read from the start of files that are too large to be read entirely, enough to
+/// [detect](ContentType::detect()) their content type.
+pub(crate) const PREFIX_LEN: usize = 8000;
+
+/// A file in the worktree as [read](read_worktree_file()) for displaying or recording it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorktreeFile {
+    /// A regular file with all of its `content`, which is `executable` if any of its executable bits are set.
+    Content {
+        content: Vec<u8>,
+        content_type: ContentType,
+        executable: bool,
+    },
+    /// A regular file of `len` bytes, more than could be read, with the first 8000 bytes of its content.
+    TooLarge {
+        prefix: Vec<u8>,
+        len: u64,
+        content_type: ContentType,
+    },
+    /// A symbolic link to `target`, which is never followed.
+    Link { target: PathBuf },
+    /// A directory.
+    Directory,
+    /// Anything else, like a socket or a device, which has no content to read.
+    Other { len: u64 },
+    /// Nothing exists at the path.
+    Missing,
+}
+
+/// Read the file at `path` without following symbolic links, along with its content type, or only the start of its
+/// content if it's larger than `max_size`.
+///
+/// A file that disappears while it's read is [missing](WorktreeFile::Missing).
+pub fn read_worktree_file(path: &Path, max_size: u64) -> std::io::Result<WorktreeFile> {
+    let metadata = match path.symlink_metadata() {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(WorktreeFile::Missing),
+        Err(err) => return Err(err),
+    };
+    let read = || -> std::io::Result<WorktreeFile> {
+        Ok(if metadata.is_file() {
+            if metadata.len() > max_size {
+                let mut prefix = Vec::with_capacity(PREFIX_LEN);
+                std::fs::File::open(path)?
+                    .take(PREFIX_LEN as u64)
+                    .read_to_end(&mut prefix)?;
+                WorktreeFile::TooLarge {
+                    content_type: ContentType::detect(path, &prefix),
+                    prefix,
+                    len: metadata.len(),
+                }
+            } else {
+                let content = std::fs::read(path)?;
+                WorktreeFile::Content {
+                    content_type: ContentType::detect(path, &content),
+                    content,
+                    executable: is_executable(&metadata),
+                }
+            }
+        } else if metadata.is_symlink() {
+            WorktreeFile::Link {
+                target: std::fs::read_link(path)?,
+            }
+        } else if metadata.is_dir() {
+            WorktreeFile::Directory
+        } else {
+            WorktreeFile::Other {
+                len: metadata.len(),
+            }
+        })
+    };
+    match read() {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(WorktreeFile::Missing),
+        result => result,
+    }
+}
+
+/// Return `true` if the file described by `metadata` has any of its executable bits set, which can only be told on
+/// Unix.
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Return `true` if `content` looks binary, judging by its first 8000 bytes just like Git does.
+pub(crate) fn is_binary(content: &[u8]) -> bool {
+    let partial_content = &content[..content.len().min(PREFIX_LEN)];
+    gix::filter::plumbing::eol::Stats::from_bytes(partial_content).is_binary()
+}
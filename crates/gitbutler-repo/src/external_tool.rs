@@ -0,0 +1,228 @@
+//! Launch the merge and diff tools the user configured for Git, so conflicts can be resolved and changes reviewed
+//! in a familiar program.
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+/// What happened after an external tool exited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalToolOutcome {
+    /// The name of the tool as configured in `merge.tool` or `diff.tool`.
+    pub tool: String,
+    /// If `true`, the user saved the file in the tool.
+    ///
+    /// For merge tools, this means the conflict was resolved and the file was staged.
+    pub saved: bool,
+    /// The exit code of the tool, or `None` if it was terminated by a signal.
+    pub exit_code: Option<i32>,
+}
+
+/// Resolve the conflict of the file at the worktree-relative `path` with the tool configured in `merge.tool`,
+/// whose command is read from `mergetool.<tool>.cmd`, much like `git mergetool` does.
+///
+/// The three versions of the file are written to temporary files and passed to the tool as `$BASE`, `$LOCAL` and
+/// `$REMOTE`, with `$MERGED` being the file in the worktree. This blocks until the tool exits.
+/// The file is considered resolved and is staged if the tool changed it, or, if `mergetool.<tool>.trustExitCode`
+/// is set, if the tool exited successfully.
+///
+/// Tools without a command, like those Git knows by name such as `vimdiff`, are launched with `git mergetool`
+/// instead, and the file is resolved if it decided so.
+pub fn run_merge_tool(repo: &git2::Repository, path: &Path) -> Result<ExternalToolOutcome> {
+    let workdir = worktree_path(repo, path)?;
+    let config = repo.config()?;
+    let tool = config
+        .get_string("merge.tool")
+        .context("No merge tool is configured, please set `merge.tool` in the Git configuration")?;
+    let cmd = tool_command(&config, "mergetool", &tool);
+    let trust_exit_code = config
+        .get_bool(&format!("mergetool.{tool}.trustExitCode"))
+        .unwrap_or(false);
+
+    let mut index = repo.index()?;
+    let conflict = index
+        .conflict_get(path)
+        .with_context(|| format!("'{}' is not conflicted", path.display()))?;
+    let Some(cmd) = cmd else {
+        let status = run_git_tool(workdir, "mergetool", &tool, &[], path)?;
+        // Git stages the file itself once it's resolved.
+        index.read(true)?;
+        return Ok(ExternalToolOutcome {
+            tool,
+            saved: index.conflict_get(path).is_err(),
+            exit_code: status.code(),
+        });
+    };
+    let tempdir = tempfile::tempdir()?;
+    let write_version = |label: &str, entry: Option<git2::IndexEntry>| -> Result<PathBuf> {
+        let content = match entry {
+            Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+            None => Vec::new(),
+        };
+        write_temporary(tempdir.path(), label, path, &content)
+    };
+    let base = write_version("BASE", conflict.ancestor)?;
+    let local = write_version("LOCAL", conflict.our)?;
+    let remote = write_version("REMOTE", conflict.their)?;
+
+    let merged = workdir.join(path);
+    let before = std::fs::read(&merged).ok();
+    let status = run_tool(workdir, &cmd, &base, &local, &remote, &merged)?;
+    let saved = if trust_exit_code {
+        status.success()
+    } else {
+        std::fs::read(&merged).ok() != before
+    };
+    if saved {
+        index.add_path(path)?;
+        index.write()?;
+    }
+    Ok(ExternalToolOutcome {
+        tool,
+        saved,
+        exit_code: status.code(),
+    })
+}
+
+/// Show the uncommitted changes of the file at the worktree-relative `path` in the tool configured in `diff.tool`,
+/// or in `merge.tool` if unset, whose command is read from `difftool.<tool>.cmd`, much like `git difftool` does.
+///
+/// The version of the file in `HEAD` is written to a temporary file and passed to the tool as `$LOCAL`, while `$REMOTE`
+/// and `$MERGED` are the file in the worktree. This blocks until the tool exits.
+/// The file is considered saved if the tool changed it.
+///
+/// Tools without a command, like those Git knows by name, are launched with `git difftool` instead.
+pub fn run_diff_tool(repo: &git2::Repository, path: &Path) -> Result<ExternalToolOutcome> {
+    let workdir = worktree_path(repo, path)?;
+    let config = repo.config()?;
+    let tool = config
+        .get_string("diff.tool")
+        .or_else(|_| config.get_string("merge.tool"))
+        .context("No diff tool is configured, please set `diff.tool` in the Git configuration")?;
+    let Some(cmd) = tool_command(&config, "difftool", &tool) else {
+        let merged = workdir.join(path);
+        let before = std::fs::read(&merged).ok();
+        // An unborn `HEAD` has no files yet, so there is nothing but the index to compare with.
+        let revisions: &[&str] = if repo.head().is_ok() { &["HEAD"] } else { &[] };
+        let status = run_git_tool(workdir, "difftool", &tool, revisions, path)?;
+        return Ok(ExternalToolOutcome {
+            tool,
+            saved: std::fs::read(&merged).ok() != before,
+            exit_code: status.code(),
+        });
+    };
+
+    let head_content = match repo.head().and_then(|head| head.peel_to_tree()) {
+        Ok(tree) => match tree.get_path(path) {
+            Ok(entry) => repo.find_blob(entry.id())?.content().to_vec(),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        },
+        // An unborn `HEAD` has no files yet.
+        Err(err) if err.code() == git2::ErrorCode::UnbornBranch => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let tempdir = tempfile::tempdir()?;
+    let local = write_temporary(tempdir.path(), "LOCAL", path, &head_content)?;
+
+    let merged = workdir.join(path);
+    let before = std::fs::read(&merged).ok();
+    let status = run_tool(workdir, &cmd, &merged, &local, &merged, &merged)?;
+    Ok(ExternalToolOutcome {
+        tool,
+        saved: std::fs::read(&merged).ok() != before,
+        exit_code: status.code(),
+    })
+}
+
+/// Return the worktree of `repo`, and fail if `path` isn't a path within it.
+fn worktree_path<'repo>(repo: &'repo git2::Repository, path: &Path) -> Result<&'repo Path> {
+    let Some(workdir) = repo.workdir() else {
+        bail!("Cannot run external tools in a bare repository");
+    };
+    if !path.is_relative()
+        || path
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!(
+            "Refusing to open '{}' as it's not a path within the worktree",
+            path.display()
+        );
+    }
+    Ok(workdir)
+}
+
+/// Read the command of `tool` from `<section>.<tool>.cmd`, or return `None` if it has none.
+fn tool_command(config: &git2::Config, section: &str, tool: &str) -> Option<String> {
+    config
+        .get_string(&format!("{section}.{tool}.cmd"))
+        .ok()
+        .filter(|cmd| !cmd.trim().is_empty())
+}
+
+/// Write `content` to a file in `dir` that is named after `path` and `label`, keeping the extension
+/// so tools can pick the right syntax highlighting.
+fn write_temporary(dir: &Path, label: &str, path: &Path, content: &[u8]) -> Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}_{label}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{label}"),
+    };
+    let temporary = dir.join(name);
+    std::fs::write(&temporary, content)?;
+    Ok(temporary)
+}
+
+/// Run `cmd` through the shell in `workdir` with the given files set as variables, and wait for it to exit.
+fn run_tool(
+    workdir: &Path,
+    cmd: &str,
+    base: &Path,
+    local: &Path,
+    remote: &Path,
+    merged: &Path,
+) -> Result<std::process::ExitStatus> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .current_dir(workdir)
+        .env("BASE", base)
+        .env("LOCAL", local)
+        .env("REMOTE", remote)
+        .env("MERGED", merged)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Could not launch '{cmd}'"))
+}
+
+/// Run `git <subcommand>`, which is `mergetool` or `difftool`, with `tool` on the worktree-relative `path` in
+/// `workdir`, comparing with `revisions`, and wait for it to exit. That's for tools without a command of their own.
+fn run_git_tool(
+    workdir: &Path,
+    subcommand: &str,
+    tool: &str,
+    revisions: &[&str],
+    path: &Path,
+) -> Result<std::process::ExitStatus> {
+    Command::new(gix::path::env::exe_invocation())
+        .arg(subcommand)
+        .arg("--no-prompt")
+        .arg(format!("--tool={tool}"))
+        .args(revisions)
+        .arg("--")
+        .arg(path)
+        .current_dir(workdir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Could not launch 'git {subcommand}' with '{tool}'"))
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use bstr::ByteSlice;
+use gitbutler_diff::lfs::{self, LfsStatus};
+use serde::Serialize;
+
+/// The kind of a [`FileTreeEntry`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileTreeEntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// The git status of a file in the worktree, relative to `HEAD`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileTreeStatus {
+    /// The file is new and not yet known to git.
+    Untracked,
+    /// The file is new and was added to the index.
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Conflicted,
+}
+
+/// A single entry of a directory in the worktree, as returned by [`file_tree()`](crate::RepoCommands::file_tree()).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTreeEntry {
+    /// The basename of the entry.
+    pub name: String,
+    /// The path of the entry relative to the worktree root.
+    pub path: PathBuf,
+    pub kind: FileTreeEntryKind,
+    /// The size in bytes of files and symlinks, `None` for directories.
+    pub size: Option<u64>,
+    /// The git status of files and symlinks, `None` if they are unchanged or if this is a directory.
+    pub status: Option<FileTreeStatus>,
+    /// If `true`, this entry or anything below it has uncommitted changes.
+    pub has_changes: bool,
+    /// If `true`, the entry is ignored by git and changes to it won't be picked up.
+    pub ignored: bool,
+    /// What files stored with Git LFS hold in the worktree, `None` if they aren't stored with Git LFS
+    /// or if this isn't a file.
+    pub lfs: Option<LfsStatus>,
+}
+
+impl From<git2::Status> for FileTreeStatus {
+    fn from(status: git2::Status) -> Self {
+        if status.is_conflicted() {
+            FileTreeStatus::Conflicted
+        } else if status.is_index_renamed() || status.is_wt_renamed() {
+            FileTreeStatus::Renamed
+        } else if status.is_index_new() {
+            FileTreeStatus::Added
+        } else if status.is_wt_new() {
+            FileTreeStatus::Untracked
+        } else if status.is_index_deleted() || status.is_wt_deleted() {
+            FileTreeStatus::Deleted
+        } else {
+            FileTreeStatus::Modified
+        }
+    }
+}
+
+/// List the entries directly within `subpath` of the worktree of `repo`, or of the worktree root if `None`.
+///
+/// Directories are not descended into so huge repositories can be explored lazily, one directory at a time.
+/// Entries are sorted with directories first, then by name.
+pub(crate) fn file_tree(
+    repo: &git2::Repository,
+    subpath: Option<&Path>,
+) -> Result<Vec<FileTreeEntry>> {
+    let Some(workdir) = repo.workdir() else {
+        bail!("Cannot list files of a bare repository");
+    };
+    let subpath = subpath.unwrap_or_else(|| Path::new(""));
+    if !subpath.is_relative()
+        || subpath
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!(
+            "Refusing to list '{}' as it's not a path within the worktree",
+            subpath.display()
+        );
+    }
+
+    // Only what's below `subpath` is of interest, taken literally so names like `[id]` aren't globs, and untracked
+    // directories are reported as a whole instead of walking everything in them.
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .exclude_submodules(true)
+        .disable_pathspec_match(true);
+    if !subpath.as_os_str().is_empty() {
+        opts.pathspec(subpath);
+    }
+    let mut statuses = HashMap::<PathBuf, git2::Status>::new();
+    let mut untracked_dirs = Vec::new();
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let path = gix::path::from_bstr(entry.path_bytes().as_bstr()).into_owned();
+        if entry.path_bytes().ends_with(b"/") {
+            untracked_dirs.push(path);
+        } else {
+            statuses.insert(path, entry.status());
+        }
+    }
+    let status_of = |path: &Path| {
+        statuses.get(path).copied().or_else(|| {
+            untracked_dirs
+                .iter()
+                .any(|dir| path.starts_with(dir))
+                .then_some(git2::Status::WT_NEW)
+        })
+    };
+
+    let mut entries = Vec::new();
+    for dir_entry in std::fs::read_dir(workdir.join(subpath))? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        if subpath.as_os_str().is_empty() && name == ".git" {
+            continue;
+        }
+        let path = subpath.join(&name);
+        let md = dir_entry.path().symlink_metadata()?;
+        let kind = if md.is_symlink() {
+            FileTreeEntryKind::Symlink
+        } else if md.is_dir() {
+            FileTreeEntryKind::Directory
+        } else {
+            FileTreeEntryKind::File
+        };
+
+        let (size, status, has_changes) = match kind {
+            FileTreeEntryKind::Directory => {
+                let has_changes = statuses
+                    .keys()
+                    .chain(&untracked_dirs)
+                    .any(|changed| changed.starts_with(&path) || path.starts_with(changed));
+                (None, None, has_changes)
+            }
+            FileTreeEntryKind::File | FileTreeEntryKind::Symlink => {
+                let status = status_of(&path).map(FileTreeStatus::from);
+                (Some(md.len()), status, status.is_some())
+            }
+        };
+        let lfs = match kind {
+            FileTreeEntryKind::File => lfs::status(repo, &path)?,
+            FileTreeEntryKind::Directory | FileTreeEntryKind::Symlink => None,
+        };
+        entries.push(FileTreeEntry {
+            ignored: repo.is_path_ignored(&path)?,
+            name,
+            path,
+            kind,
+            size,
+            status,
+            has_changes,
+            lfs,
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        (a.kind != FileTreeEntryKind::Directory)
+            .cmp(&(b.kind != FileTreeEntryKind::Directory))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(entries)
+}
+
+/// List the paths of all files tracked in the index of `repo`, in the order of the index which sorts them by path.
+///
+/// Only paths matching `glob` are listed if it's set, with `*` not matching `/` so `**/` is needed to match
+/// in subdirectories, and no more than `limit` paths are returned if it's set.
+/// This reads the index in a single pass instead of walking the worktree, so it remains fast in huge repositories.
+pub(crate) fn list_files(
+    repo: &gix::Repository,
+    glob: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<PathBuf>> {
+    let index = repo.index_or_empty()?;
+    let mut paths = Vec::new();
+    let mut last = None;
+    for entry in index.entries() {
+        if limit.is_some_and(|limit| paths.len() >= limit) {
+            break;
+        }
+        let path = entry.path(&index);
+        // Conflicted files have an entry for each side, but are listed only once.
+        if last == Some(path) {
+            continue;
+        }
+        last = Some(path);
+        if let Some(glob) = glob {
+            if !gix::glob::wildmatch(
+                glob.into(),
+                path,
+                gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+            ) {
+                continue;
+            }
+        }
+        paths.push(gix::path::from_bstr(path).into_owned());
+    }
+    Ok(paths)
+}
@@ -0,0 +1,193 @@
+//! Report how large a repository is and whether it would benefit from being optimized, and optimize it.
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::clone::{run_git, GitProgress};
+
+/// How many of the largest blobs are reported.
+const LARGEST_BLOBS: usize = 10;
+
+/// Branches without commits for this long are considered stale.
+const STALE_AFTER_SECONDS: i64 = 90 * 24 * 60 * 60;
+
+/// The defaults of `gc.auto` and `gc.autoPackLimit`, beyond which Git would optimize the repository by itself.
+const DEFAULT_GC_AUTO: i64 = 6700;
+const DEFAULT_GC_AUTO_PACK_LIMIT: i64 = 50;
+
+/// The size and health of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoHealth {
+    /// The number of objects in the object database, some of which may be stored more than once.
+    pub object_count: usize,
+    /// The number of objects stored in their own file, and how many bytes these files take.
+    pub loose_objects: usize,
+    pub loose_size: u64,
+    /// The packs of objects, largest first.
+    pub packs: Vec<PackFile>,
+    /// The largest blobs, largest first.
+    pub largest_blobs: Vec<LargeBlob>,
+    /// The local branches without commits for 90 days, oldest first.
+    pub stale_branches: Vec<StaleBranch>,
+    /// Whether there are enough loose objects or packs for [`optimize()`] to make a difference.
+    pub gc_recommended: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackFile {
+    /// The name of the file in `objects/pack`.
+    pub name: String,
+    /// The size of the pack in bytes, without its index.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeBlob {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub id: git2::Oid,
+    /// The size of the blob in bytes, uncompressed.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleBranch {
+    /// The short name of the branch, like `feature`.
+    pub name: String,
+    /// When the last commit of the branch was made, in seconds since the Unix epoch.
+    pub last_commit_at: i64,
+}
+
+/// Report the size and health of `repo`.
+///
+/// This reads the header of every object, which takes a while for very large repositories.
+pub fn repo_health(repo: &git2::Repository) -> Result<RepoHealth> {
+    let objects_dir = repo.commondir().join("objects");
+    let (loose_objects, loose_size) = loose_objects(&objects_dir)?;
+    let packs = pack_files(&objects_dir.join("pack"))?;
+
+    let odb = repo.odb()?;
+    let mut object_count = 0;
+    let mut blobs = Vec::new();
+    let mut error = None;
+    odb.foreach(|id| {
+        object_count += 1;
+        match odb.read_header(*id) {
+            Ok((size, git2::ObjectType::Blob)) => blobs.push(LargeBlob {
+                id: *id,
+                size: size as u64,
+            }),
+            Ok(_) => {}
+            Err(err) => {
+                error = Some(err);
+                return false;
+            }
+        }
+        true
+    })
+    .or_else(|err| match err.code() {
+        git2::ErrorCode::User => Ok(()),
+        _ => Err(err),
+    })?;
+    if let Some(err) = error {
+        return Err(err.into());
+    }
+    blobs.sort_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+    blobs.dedup_by_key(|blob| blob.id);
+    blobs.truncate(LARGEST_BLOBS);
+
+    let config = repo.config()?;
+    let gc_auto = config.get_i64("gc.auto").unwrap_or(DEFAULT_GC_AUTO);
+    let gc_auto_pack_limit = config
+        .get_i64("gc.autoPackLimit")
+        .unwrap_or(DEFAULT_GC_AUTO_PACK_LIMIT);
+    let gc_recommended = (gc_auto > 0 && loose_objects as i64 > gc_auto)
+        || (gc_auto_pack_limit > 0 && packs.len() as i64 > gc_auto_pack_limit);
+
+    Ok(RepoHealth {
+        object_count,
+        loose_objects,
+        loose_size,
+        packs,
+        largest_blobs: blobs,
+        stale_branches: stale_branches(repo)?,
+        gc_recommended,
+    })
+}
+
+/// Pack the objects of `repo` and remove those that are unreachable, with `git gc`, calling `on_progress` as Git
+/// reports progress.
+pub fn optimize(repo: &git2::Repository, on_progress: impl FnMut(GitProgress)) -> Result<()> {
+    run_git(
+        Some(repo.commondir()),
+        &["gc".into(), "--progress".into()],
+        on_progress,
+    )
+    .context("failed to optimize the repository")
+}
+
+/// Return the number of loose objects in `objects_dir` and the bytes they take.
+fn loose_objects(objects_dir: &Path) -> Result<(usize, u64)> {
+    let (mut count, mut size) = (0, 0);
+    for dir in std::fs::read_dir(objects_dir)? {
+        let dir = dir?;
+        let name = dir.file_name();
+        let is_fanout = name.len() == 2
+            && name
+                .to_str()
+                .is_some_and(|name| name.chars().all(|c| c.is_ascii_hexdigit()));
+        if !is_fanout || !dir.file_type()?.is_dir() {
+            continue;
+        }
+        for object in std::fs::read_dir(dir.path())? {
+            count += 1;
+            size += object?.metadata()?.len();
+        }
+    }
+    Ok((count, size))
+}
+
+fn pack_files(pack_dir: &Path) -> Result<Vec<PackFile>> {
+    let mut packs = Vec::new();
+    if !pack_dir.exists() {
+        return Ok(packs);
+    }
+    for entry in std::fs::read_dir(pack_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".pack") {
+            packs.push(PackFile {
+                name,
+                size: entry.metadata()?.len(),
+            });
+        }
+    }
+    packs.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Ok(packs)
+}
+
+fn stale_branches(repo: &git2::Repository) -> Result<Vec<StaleBranch>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+    let mut stale = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let (Some(name), Ok(commit)) = (branch.name()?, branch.get().peel_to_commit()) else {
+            continue;
+        };
+        let last_commit_at = commit.time().seconds();
+        if now - last_commit_at > STALE_AFTER_SECONDS {
+            stale.push(StaleBranch {
+                name: name.to_owned(),
+                last_commit_at,
+            });
+        }
+    }
+    stale.sort_by_key(|branch| branch.last_commit_at);
+    Ok(stale)
+}
@@ -1,8 +1,11 @@
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
 
-use anyhow::Result;
-use git2_hooks;
-use git2_hooks::HookResult as H;
+use anyhow::{Context, Result};
+use bstr::ByteSlice;
 use gitbutler_command_context::CommandContext;
 use gitbutler_diff::GitHunk;
 use serde::Serialize;
@@ -41,24 +44,67 @@ pub enum MessageHookResult {
     Failure(ErrorData),
 }
 
-pub fn commit_msg(ctx: &CommandContext, mut message: String) -> Result<MessageHookResult> {
-    let original_message = message.clone();
-    match git2_hooks::hooks_commit_msg(ctx.repo(), Some(&["../.husky"]), &mut message)? {
-        H::Ok { hook: _ } => match message == original_message {
-            true => Ok(MessageHookResult::Success),
-            false => Ok(MessageHookResult::Message(MessageData { message })),
-        },
-        H::NoHookFound => Ok(MessageHookResult::NotConfigured),
-        H::RunNotSuccessful { stdout, stderr, .. } => {
-            let error = join_output(stdout, stderr);
-            Ok(MessageHookResult::Failure(ErrorData { error }))
+/// The output stream of a hook that a [`HookOutput`] line was written to.
+#[derive(Serialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum HookOutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A single line of output of a running hook, passed to the caller as soon as the hook produces it.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    /// The name of the hook, like `pre-commit`.
+    pub hook: &'static str,
+    pub stream: HookOutputStream,
+    pub line: String,
+}
+
+const HOOK_PRE_COMMIT: &str = "pre-commit";
+const HOOK_COMMIT_MSG: &str = "commit-msg";
+const HOOK_POST_COMMIT: &str = "post-commit";
+
+/// Run the `commit-msg` hook on `message`, calling `on_output` for each line the hook prints.
+pub fn commit_msg(
+    ctx: &CommandContext,
+    message: String,
+    mut on_output: impl FnMut(HookOutput),
+) -> Result<MessageHookResult> {
+    let repo = ctx.repo();
+    let message_path = repo.path().join("COMMIT_EDITMSG");
+    std::fs::write(&message_path, &message)?;
+
+    let outcome = run_hook(
+        repo,
+        HOOK_COMMIT_MSG,
+        &[message_path.as_os_str()],
+        &mut on_output,
+    )?;
+    Ok(match outcome {
+        HookOutcome::NotFound => MessageHookResult::NotConfigured,
+        HookOutcome::Success => {
+            let new_message = std::fs::read_to_string(&message_path)?;
+            if new_message == message {
+                MessageHookResult::Success
+            } else {
+                MessageHookResult::Message(MessageData {
+                    message: new_message,
+                })
+            }
         }
-    }
+        HookOutcome::Failure { stdout, stderr } => MessageHookResult::Failure(ErrorData {
+            error: join_output(stdout, stderr),
+        }),
+    })
 }
 
+/// Run the `pre-commit` hook with only `selected_hunks` staged, calling `on_output` for each line the hook prints.
 pub fn pre_commit(
     ctx: &CommandContext,
     selected_hunks: &[(PathBuf, Vec<GitHunk>)],
+    mut on_output: impl FnMut(HookOutput),
 ) -> Result<HookResult> {
     let repo = ctx.repo();
     let original_tree = repo.index()?.write_tree()?;
@@ -72,29 +118,141 @@ pub fn pre_commit(
     });
 
     staging::stage(ctx, selected_hunks)?;
-    Ok(
-        match git2_hooks::hooks_pre_commit(ctx.repo(), Some(&["../.husky"]))? {
-            H::Ok { hook: _ } => HookResult::Success,
-            H::NoHookFound => HookResult::NotConfigured,
-            H::RunNotSuccessful { stdout, stderr, .. } => {
-                let error = join_output(stdout, stderr);
-                HookResult::Failure(ErrorData { error })
-            }
-        },
-    )
+    Ok(run_hook(repo, HOOK_PRE_COMMIT, &[], &mut on_output)?.into())
+}
+
+/// Run the `post-commit` hook, calling `on_output` for each line the hook prints.
+pub fn post_commit(
+    ctx: &CommandContext,
+    mut on_output: impl FnMut(HookOutput),
+) -> Result<HookResult> {
+    Ok(run_hook(ctx.repo(), HOOK_POST_COMMIT, &[], &mut on_output)?.into())
+}
+
+enum HookOutcome {
+    NotFound,
+    Success,
+    Failure { stdout: String, stderr: String },
 }
 
-pub fn post_commit(ctx: &CommandContext) -> Result<HookResult> {
-    match git2_hooks::hooks_post_commit(ctx.repo(), Some(&["../.husky"]))? {
-        H::Ok { hook: _ } => Ok(HookResult::Success),
-        H::NoHookFound => Ok(HookResult::NotConfigured),
-        H::RunNotSuccessful { stdout, stderr, .. } => {
-            let error = join_output(stdout, stderr);
-            Ok(HookResult::Failure(ErrorData { error }))
+impl From<HookOutcome> for HookResult {
+    fn from(outcome: HookOutcome) -> Self {
+        match outcome {
+            HookOutcome::NotFound => HookResult::NotConfigured,
+            HookOutcome::Success => HookResult::Success,
+            HookOutcome::Failure { stdout, stderr } => HookResult::Failure(ErrorData {
+                error: join_output(stdout, stderr),
+            }),
         }
     }
 }
 
+/// Run the hook named `hook` with `args` from the worktree root, like Git would, and pass each line
+/// it prints to `on_output` while it's running.
+fn run_hook(
+    repo: &git2::Repository,
+    hook: &'static str,
+    args: &[&OsStr],
+    on_output: &mut dyn FnMut(HookOutput),
+) -> Result<HookOutcome> {
+    let Some(hook_path) = find_hook(repo, hook)? else {
+        return Ok(HookOutcome::NotFound);
+    };
+
+    // Let the shell execute the hook so scripts without shebang work, just like they do with Git.
+    // This is also the only way to run them on Windows.
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(r#""$0" "$@""#).arg(&hook_path);
+    cmd.args(args)
+        .current_dir(repo.workdir().unwrap_or_else(|| repo.path()))
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        .env("GIT_EDITOR", ":")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Could not run {hook} hook at {}", hook_path.display()))?;
+
+    let (tx, rx) = mpsc::channel();
+    let stdout_reader = forward_lines(
+        child.stdout.take().expect("piped"),
+        HookOutputStream::Stdout,
+        tx.clone(),
+    );
+    let stderr_reader = forward_lines(
+        child.stderr.take().expect("piped"),
+        HookOutputStream::Stderr,
+        tx,
+    );
+
+    let (mut stdout, mut stderr) = (String::new(), String::new());
+    for (stream, line) in rx {
+        let buf = match stream {
+            HookOutputStream::Stdout => &mut stdout,
+            HookOutputStream::Stderr => &mut stderr,
+        };
+        buf.push_str(&line);
+        buf.push('\n');
+        on_output(HookOutput { hook, stream, line });
+    }
+    stdout_reader.join().ok();
+    stderr_reader.join().ok();
+
+    Ok(if child.wait()?.success() {
+        HookOutcome::Success
+    } else {
+        HookOutcome::Failure { stdout, stderr }
+    })
+}
+
+/// Send each line read from `reader` to `tx`, until the stream is closed.
+fn forward_lines(
+    reader: impl Read + Send + 'static,
+    stream: HookOutputStream,
+    tx: mpsc::Sender<(HookOutputStream, String)>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).split(b'\n') {
+            let Ok(line) = line else { break };
+            let line = line
+                .trim_end_with(|c| c == '\r')
+                .to_str_lossy()
+                .into_owned();
+            if tx.send((stream, line)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Find the executable for `hook` in the directory configured with `core.hooksPath`, or in `.git/hooks`.
+/// Without `core.hooksPath`, `.husky` in the worktree is tried as well.
+fn find_hook(repo: &git2::Repository, hook: &str) -> Result<Option<PathBuf>> {
+    let worktree_dir = repo.workdir().unwrap_or_else(|| repo.path());
+    let candidates = match repo.config()?.get_path("core.hooksPath") {
+        Ok(hooks_dir) => vec![worktree_dir.join(hooks_dir).join(hook)],
+        Err(err) if err.code() == git2::ErrorCode::NotFound => vec![
+            repo.path().join("hooks").join(hook),
+            worktree_dir.join(".husky").join(hook),
+        ],
+        Err(err) => return Err(err.into()),
+    };
+    Ok(candidates.into_iter().find(|path| is_executable(path)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|md| md.is_file() && md.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 fn join_output(stdout: String, stderr: String) -> String {
     if stdout.is_empty() && stderr.is_ascii() {
         return "hook produced no output".to_owned();
@@ -0,0 +1,140 @@
+//! Pin the identity of the commits GitButler creates in a repository, like a work identity for the repositories
+//! of an employer, regardless of the global Git configuration.
+//!
+//! The identity is stored in the local configuration of the repository, next to the other GitButler settings,
+//! and only applies to commits created by GitButler, not to those made with Git directly.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// The keys of the local Git configuration the identity is stored at.
+pub const IDENTITY_NAME_KEY: &str = "gitbutler.identity.name";
+pub const IDENTITY_EMAIL_KEY: &str = "gitbutler.identity.email";
+
+/// Hosts anyone can have an account on with any email address, which thus can't tell which identity to use.
+const PUBLIC_HOSTS: &[&str] = &[
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "codeberg.org",
+    "sr.ht",
+];
+
+/// The author and committer of the commits GitButler creates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    /// Return a signature of this identity, at the current time, or the one in `overriding_variable_name`.
+    pub(crate) fn signature(
+        &self,
+        overriding_variable_name: &str,
+    ) -> Result<git2::Signature<'static>> {
+        gitbutler_oxidize::gix_to_git2_signature(gix::actor::SignatureRef {
+            name: self.name.as_str().into(),
+            email: self.email.as_str().into(),
+            time: crate::commit_time(overriding_variable_name),
+        })
+    }
+}
+
+/// Return the identity pinned for `repo`, if there is one.
+pub fn identity(repo: &git2::Repository) -> Result<Option<Identity>> {
+    let config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    let get = |key| match config.get_string(key) {
+        Ok(value) if !value.trim().is_empty() => Ok(Some(value)),
+        Ok(_) => Ok(None),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err),
+    };
+    Ok(match (get(IDENTITY_NAME_KEY)?, get(IDENTITY_EMAIL_KEY)?) {
+        (Some(name), Some(email)) => Some(Identity { name, email }),
+        _ => None,
+    })
+}
+
+/// Pin `identity` for `repo`, or remove the pinned identity if `None`, and return warnings about it, see
+/// [`identity_warnings()`].
+pub fn set_identity(repo: &git2::Repository, identity: Option<&Identity>) -> Result<Vec<String>> {
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    let Some(identity) = identity else {
+        for key in [IDENTITY_NAME_KEY, IDENTITY_EMAIL_KEY] {
+            if let Err(err) = config.remove(key) {
+                if err.code() != git2::ErrorCode::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+        return Ok(Vec::new());
+    };
+    let (name, email) = (identity.name.trim(), identity.email.trim());
+    if name.is_empty() {
+        bail!("The name of the identity can't be empty");
+    }
+    if email
+        .split_once('@')
+        .is_none_or(|(local, domain)| local.is_empty() || domain.is_empty())
+    {
+        bail!("'{email}' isn't an email address");
+    }
+    config.set_str(IDENTITY_NAME_KEY, name)?;
+    config.set_str(IDENTITY_EMAIL_KEY, email)?;
+    identity_warnings(
+        repo,
+        &Identity {
+            name: name.to_owned(),
+            email: email.to_owned(),
+        },
+    )
+}
+
+/// Return a warning for each remote of `repo` on a host of an organization, like `git.example.com`, whose
+/// domain the email of `identity` isn't at, as it's likely the wrong identity for the repository.
+///
+/// Remotes on public hosts like GitHub are ignored, as any email address can be used there.
+pub fn identity_warnings(repo: &git2::Repository, identity: &Identity) -> Result<Vec<String>> {
+    let email_domain = identity
+        .email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut warnings = Vec::new();
+    for name in repo.remotes()?.iter().flatten() {
+        let remote = repo.find_remote(name)?;
+        let Some(host) = remote
+            .url()
+            .and_then(|url| gix::url::parse(url.into()).ok())
+            .and_then(|url| url.host().map(str::to_ascii_lowercase))
+        else {
+            continue;
+        };
+        let Some(domain) = organization_domain(&host) else {
+            continue;
+        };
+        let matches = email_domain == domain || email_domain.ends_with(&format!(".{domain}"));
+        if !matches {
+            warnings.push(format!(
+                "The remote '{name}' is hosted at {host}, but '{}' isn't an address at {domain}",
+                identity.email
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Return the domain of the organization hosting at `host`, like `example.com` for `git.example.com`, or `None` if
+/// it's a public host, an IP address or an alias without a domain.
+fn organization_domain(host: &str) -> Option<String> {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return None;
+    }
+    let labels: Vec<_> = host.split('.').filter(|label| !label.is_empty()).collect();
+    if labels.len() < 2 {
+        return None;
+    }
+    let domain = labels[labels.len() - 2..].join(".");
+    (!PUBLIC_HOSTS.contains(&domain.as_str())).then_some(domain)
+}
@@ -0,0 +1,94 @@
+//! Start a new project in an empty directory: initialize a repository, write a `.gitignore` from one of the
+//! [templates](Template) bundled with the app, and create the initial commit, so the project can be added right away.
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::RepositoryExt;
+
+/// The message of the commit that starts the history of new projects.
+pub const INITIAL_COMMIT_MESSAGE: &str = "Initial commit";
+
+/// What a new project is started with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Template {
+    /// No files at all.
+    #[default]
+    Empty,
+    Rust,
+    Node,
+    Python,
+    Go,
+}
+
+impl Template {
+    /// All templates, in the order to offer them in.
+    pub const ALL: &[Template] = &[
+        Template::Empty,
+        Template::Rust,
+        Template::Node,
+        Template::Python,
+        Template::Go,
+    ];
+
+    /// Return the content of the `.gitignore` file of projects started with this template, if there is one.
+    pub fn gitignore(self) -> Option<&'static str> {
+        match self {
+            Template::Empty => None,
+            Template::Rust => Some(include_str!("../templates/gitignore/rust.gitignore")),
+            Template::Node => Some(include_str!("../templates/gitignore/node.gitignore")),
+            Template::Python => Some(include_str!("../templates/gitignore/python.gitignore")),
+            Template::Go => Some(include_str!("../templates/gitignore/go.gitignore")),
+        }
+    }
+}
+
+/// Initialize a repository in `path`, which must be missing or an empty directory, with the files of `template`
+/// committed as its first commit, and return the id of that commit.
+///
+/// If anything fails, what was created is removed again so the directory stays empty.
+pub fn init_repository(path: &Path, template: Template) -> Result<git2::Oid> {
+    if path.exists() && path.read_dir()?.next().is_some() {
+        bail!("'{}' isn't empty", path.display());
+    }
+    std::fs::create_dir_all(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let result = init_and_commit(path, template);
+    if result.is_err() {
+        for entry in path.read_dir().into_iter().flatten().flatten() {
+            let entry = entry.path();
+            let removed = if entry.is_dir() {
+                std::fs::remove_dir_all(&entry)
+            } else {
+                std::fs::remove_file(&entry)
+            };
+            if let Err(err) = removed {
+                tracing::warn!(?err, path = %entry.display(), "failed to clean up after failed init");
+            }
+        }
+    }
+    result
+}
+
+fn init_and_commit(path: &Path, template: Template) -> Result<git2::Oid> {
+    let repo = git2::Repository::init(path)
+        .with_context(|| format!("failed to initialize a repository in '{}'", path.display()))?;
+    let mut index = repo.index()?;
+    if let Some(gitignore) = template.gitignore() {
+        std::fs::write(path.join(".gitignore"), gitignore)?;
+        index.add_path(Path::new(".gitignore"))?;
+        index.write()?;
+    }
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let (author, committer) = repo.signatures()?;
+    Ok(repo.commit(
+        Some("HEAD"),
+        &author,
+        &committer,
+        INITIAL_COMMIT_MESSAGE,
+        &tree,
+        &[],
+    )?)
+}
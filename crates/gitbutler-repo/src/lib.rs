@@ -1,16 +1,32 @@
 pub mod rebase;
 
+pub mod archive;
+
+pub mod clone;
+
 mod commands;
-pub use commands::{FileInfo, RepoCommands};
+pub use commands::{FileInfo, FileVersions, RepoCommands, DEFAULT_MAX_FILE_SIZE};
 pub use remote::GitRemote;
 
+mod content_type;
+pub use content_type::{
+    decode_text, read_worktree_file, ContentEncoding, ContentType, DecodedText, WorktreeFile,
+};
+
+mod file_tree;
+pub use file_tree::{FileTreeEntry, FileTreeEntryKind, FileTreeStatus};
+
 mod repository_ext;
 pub use repository_ext::RepositoryExt;
 
 pub mod credentials;
 
 mod config;
+pub mod health;
 pub mod hooks;
+
+pub mod identity;
+pub mod init;
 mod remote;
 pub mod staging;
 
@@ -22,6 +38,18 @@ pub mod logging;
 
 pub mod commit_message;
 
+pub mod commit_signature;
+
+pub mod external_tool;
+
+pub mod merge;
+
+pub mod patches;
+
+pub mod tags;
+
+pub mod tracking;
+
 use gitbutler_oxidize::gix_to_git2_signature;
 pub const GITBUTLER_COMMIT_AUTHOR_NAME: &str = "GitButler";
 pub const GITBUTLER_COMMIT_AUTHOR_EMAIL: &str = "gitbutler@gitbutler.com";
@@ -45,6 +73,16 @@ pub fn signature(purpose: SignaturePurpose) -> anyhow::Result<git2::Signature<'s
     gix_to_git2_signature(signature)
 }
 
+/// Provide a signature with the GitButler author at `seconds` since the Unix epoch in UTC, no matter the current
+/// time or the time overridden by the environment.
+pub fn signature_at(seconds: i64) -> anyhow::Result<git2::Signature<'static>> {
+    gix_to_git2_signature(gix::actor::SignatureRef {
+        name: GITBUTLER_COMMIT_AUTHOR_NAME.into(),
+        email: GITBUTLER_COMMIT_AUTHOR_EMAIL.into(),
+        time: gix::date::Time::new(seconds, 0),
+    })
+}
+
 /// Return the time of a commit as `now` unless the `overriding_variable_name` contains a parseable date,
 /// which is used instead.
 fn commit_time(overriding_variable_name: &str) -> gix::date::Time {
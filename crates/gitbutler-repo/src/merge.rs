@@ -0,0 +1,265 @@
+//! Merge branches into the branch that is checked out, like `git merge`, for use outside of the GitButler workspace.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_fs::FileStamp;
+use serde::Serialize;
+
+use crate::{FileInfo, RepositoryExt as _};
+
+/// A file that was changed on both sides of a merge in ways that couldn't be combined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictEntry {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The blob of the file in the merge base, `None` if the file was added on both sides.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub ancestor: Option<git2::Oid>,
+    /// The blob of the file on the checked out branch, `None` if it was deleted there.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub ours: Option<git2::Oid>,
+    /// The blob of the file on the merged branch, `None` if it was deleted there.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub theirs: Option<git2::Oid>,
+}
+
+/// The outcome of [`merge_branch()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum MergeOutcome {
+    /// The branch is already contained in `HEAD`, nothing was done.
+    UpToDate,
+    /// `HEAD` was moved forward to the tip of the branch.
+    FastForward(#[serde(with = "gitbutler_serde::oid")] git2::Oid),
+    /// A merge commit was created.
+    Merged(#[serde(with = "gitbutler_serde::oid")] git2::Oid),
+    /// The merge stopped with conflicts in the worktree and the index. Resolve them and call
+    /// [`continue_merge()`], or [`abort_merge()`].
+    Conflicted(Vec<ConflictEntry>),
+}
+
+/// Merge the branch or revision named `branch` into the branch checked out in `repo`.
+///
+/// Fast-forwards if possible, otherwise creates a merge commit unless there are conflicts.
+pub fn merge_branch(repo: &git2::Repository, branch: &str) -> Result<MergeOutcome> {
+    if repo.state() != git2::RepositoryState::Clean {
+        bail!("Cannot merge while another operation is in progress");
+    }
+    let head = repo.head().context("Cannot merge into an unborn branch")?;
+    if head
+        .name()
+        .is_some_and(|name| name.starts_with("refs/heads/gitbutler/"))
+    {
+        bail!("Cannot merge into the GitButler workspace, integrate the branch using the workspace instead");
+    }
+
+    let theirs = match repo.resolve_reference_from_short_name(branch) {
+        Ok(reference) => repo.reference_to_annotated_commit(&reference)?,
+        Err(_) => {
+            let commit = repo
+                .revparse_single(branch)
+                .with_context(|| format!("Could not find branch '{branch}'"))?
+                .peel_to_commit()?;
+            repo.find_annotated_commit(commit.id())?
+        }
+    };
+
+    let (analysis, preference) = repo.merge_analysis(&[&theirs])?;
+    if analysis.is_up_to_date() {
+        return Ok(MergeOutcome::UpToDate);
+    }
+    if analysis.is_fast_forward() && !preference.is_no_fast_forward() {
+        let target = repo.find_commit(theirs.id())?;
+        repo.checkout_tree(
+            target.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )?;
+        head.resolve()?
+            .set_target(theirs.id(), &format!("merge {branch}: Fast-forward"))?;
+        return Ok(MergeOutcome::FastForward(theirs.id()));
+    }
+    if preference.is_fastforward_only() {
+        bail!("Cannot fast-forward to '{branch}', and merge.ff=only is configured");
+    }
+
+    repo.merge(
+        &[&theirs],
+        None,
+        Some(git2::build::CheckoutBuilder::new().safe()),
+    )?;
+    let conflicts = conflict_entries(repo)?;
+    if !conflicts.is_empty() {
+        return Ok(MergeOutcome::Conflicted(conflicts));
+    }
+    continue_merge(repo).map(MergeOutcome::Merged)
+}
+
+/// Return the conflicts of an ongoing merge in `repo`, as recorded in the index.
+pub fn conflict_entries(repo: &git2::Repository) -> Result<Vec<ConflictEntry>> {
+    let index = repo.index()?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let Some(path) = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .next()
+            .map(|entry| gix::path::from_bstr(entry.path.as_bstr()).into_owned())
+        else {
+            continue;
+        };
+        entries.push(ConflictEntry {
+            path,
+            ancestor: conflict.ancestor.map(|entry| entry.id),
+            ours: conflict.our.map(|entry| entry.id),
+            theirs: conflict.their.map(|entry| entry.id),
+        });
+    }
+    Ok(entries)
+}
+
+/// The contents of a conflicted file in the merge base and on both sides, for the user to pick from.
+///
+/// Sides on which the file doesn't exist are [deleted](FileInfo::deleted()).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictVersions {
+    pub base: FileInfo,
+    pub ours: FileInfo,
+    pub theirs: FileInfo,
+    /// The state of the file in the worktree as of reading these versions, to [resolve](resolve_conflict()) the
+    /// conflict with.
+    pub stamp: FileStamp,
+}
+
+/// Read the versions of the conflicted file at the worktree-relative `path` from the conflict entries in the index.
+pub fn conflict_versions(repo: &git2::Repository, path: &Path) -> Result<ConflictVersions> {
+    let Some(workdir) = repo.workdir() else {
+        bail!("Cannot resolve conflicts in a bare repository");
+    };
+    let conflict = repo
+        .index()?
+        .conflict_get(path)
+        .with_context(|| format!("'{}' is not conflicted", path.display()))?;
+    let stamp = FileStamp::of(workdir.join(path))?;
+    let read = |entry: Option<git2::IndexEntry>| -> Result<FileInfo> {
+        Ok(match entry {
+            Some(entry) => FileInfo::from_content(path, repo.find_blob(entry.id)?.content()),
+            None => FileInfo::deleted(),
+        })
+    };
+    Ok(ConflictVersions {
+        base: read(conflict.ancestor)?,
+        ours: read(conflict.our)?,
+        theirs: read(conflict.their)?,
+        stamp,
+    })
+}
+
+/// Write `resolved_content` to the conflicted file at the worktree-relative `path` and stage it,
+/// which marks the conflict as resolved.
+///
+/// Fails with [`gitbutler_fs::ChangedSinceRead`] if the file doesn't match the `stamp` of its
+/// [versions](ConflictVersions::stamp) anymore, as another program changed it since they were read.
+pub fn resolve_conflict(
+    repo: &git2::Repository,
+    path: &Path,
+    stamp: FileStamp,
+    resolved_content: &[u8],
+) -> Result<()> {
+    let Some(workdir) = repo.workdir() else {
+        bail!("Cannot resolve conflicts in a bare repository");
+    };
+    if !path.is_relative()
+        || path
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!(
+            "Refusing to write '{}' as it's not a path within the worktree",
+            path.display()
+        );
+    }
+    let worktree_path = workdir.join(path);
+    let mut index = repo.index()?;
+    if index.conflict_get(path).is_err() {
+        bail!("'{}' is not conflicted", path.display());
+    }
+    gitbutler_fs::write_unless_changed(&worktree_path, stamp, resolved_content)?;
+    index.add_path(path)?;
+    index.write()?;
+    Ok(())
+}
+
+/// Conclude the merge in progress by committing the resolved index, and return the id of the merge commit.
+///
+/// Fails if there are unresolved conflicts left.
+pub fn continue_merge(repo: &git2::Repository) -> Result<git2::Oid> {
+    if repo.state() != git2::RepositoryState::Merge {
+        bail!("There is no merge in progress");
+    }
+    let unresolved = conflict_entries(repo)?;
+    if !unresolved.is_empty() {
+        bail!(
+            "Cannot conclude the merge with unresolved conflicts in {}",
+            unresolved
+                .iter()
+                .map(|entry| entry.path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let mut merge_heads = Vec::new();
+    repo.mergehead_foreach(|id| {
+        merge_heads.push(*id);
+        true
+    })?;
+    let merge_heads = merge_heads
+        .into_iter()
+        .map(|id| repo.find_commit(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parents: Vec<_> = std::iter::once(&head).chain(&merge_heads).collect();
+    let message = repo
+        .message()
+        .unwrap_or_else(|_| "Merge".to_owned())
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let signature = repo.signature()?;
+    let commit_id = repo.commit_with_signature(
+        None,
+        &signature,
+        &signature,
+        message.trim_end(),
+        &tree,
+        &parents,
+        None,
+    )?;
+    repo.head()?
+        .resolve()?
+        .set_target(commit_id, "commit (merge)")?;
+    repo.cleanup_state()?;
+    Ok(commit_id)
+}
+
+/// Stop the merge in progress and restore the worktree and index to `HEAD`.
+pub fn abort_merge(repo: &git2::Repository) -> Result<()> {
+    if repo.state() != git2::RepositoryState::Merge {
+        bail!("There is no merge in progress");
+    }
+    let head = repo.head()?.peel_to_commit()?;
+    repo.reset(head.as_object(), git2::ResetType::Hard, None)?;
+    repo.cleanup_state()?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Exchange commits as patches in the format of `git format-patch`, for those collaborating through mailing lists
+//! or by pasting patches into reviews.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::RepositoryExt;
+
+/// The longest a subject can be when used in the name of a patch file, like `git format-patch` does.
+const MAX_FILE_NAME_SUBJECT_LEN: usize = 52;
+
+/// The patches that were applied, or would be if only checking.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchApplication {
+    /// The number of patches in the text.
+    pub patches: usize,
+    /// The paths the patches change, relative to the worktree, in the order they first appear.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Write one patch file per commit in `range` to `out_dir`, oldest first, and return their paths.
+///
+/// `range` is anything `git rev-parse` understands, like `main..feature`, with a single revision meaning
+/// the commits from it to `HEAD`. Merge commits are skipped, as `git format-patch` does.
+pub fn format_patch(repo: &git2::Repository, range: &str, out_dir: &Path) -> Result<Vec<PathBuf>> {
+    let spec = repo
+        .revparse(range)
+        .with_context(|| format!("'{range}' isn't a valid range"))?;
+    let (from, to) = if spec.mode().contains(git2::RevparseMode::RANGE) {
+        (
+            spec.from().map(git2::Object::id),
+            spec.to().map(git2::Object::id),
+        )
+    } else {
+        (
+            spec.from().map(git2::Object::id),
+            Some(repo.head()?.peel_to_commit()?.id()),
+        )
+    };
+    let (Some(from), Some(to)) = (from, to) else {
+        bail!("'{range}' isn't a valid range");
+    };
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    walk.push(to)?;
+    walk.hide(from)?;
+    let mut commits = Vec::new();
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() <= 1 {
+            commits.push(commit);
+        }
+    }
+
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create '{}'", out_dir.display()))?;
+    let mut paths = Vec::with_capacity(commits.len());
+    for (index, commit) in commits.iter().enumerate() {
+        let parent_tree = commit
+            .parents()
+            .next()
+            .map(|parent| parent.tree())
+            .transpose()?;
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        let email = git2::Email::from_diff(
+            &diff,
+            index + 1,
+            commits.len(),
+            &commit.id(),
+            commit.summary().unwrap_or_default(),
+            commit.body().unwrap_or_default(),
+            &commit.author(),
+            &mut git2::EmailCreateOptions::new(),
+        )?;
+        let path = out_dir.join(format!(
+            "{:04}-{}.patch",
+            index + 1,
+            file_name_subject(commit.summary().unwrap_or_default())
+        ));
+        std::fs::write(&path, email.as_slice())
+            .with_context(|| format!("failed to write '{}'", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Apply the patches in `patch_text`, as written by `git format-patch` or `git diff`, to the worktree of `repo` as
+/// uncommitted changes, or only check that they apply if `check_only` is set.
+///
+/// Patches are applied in order and either all of them are, or none is. The commit messages and authors in them
+/// aren't used.
+pub fn apply_patch(
+    repo: &git2::Repository,
+    patch_text: &str,
+    check_only: bool,
+) -> Result<PatchApplication> {
+    let diffs = split_patches(patch_text)
+        .into_iter()
+        .map(|patch| git2::Diff::from_buffer(patch.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .context("the text isn't a patch")?;
+    if diffs.iter().all(|diff| diff.deltas().next().is_none()) {
+        bail!("the text doesn't contain any patch");
+    }
+
+    let mut paths = Vec::new();
+    let mut tree = repo.create_wd_tree(0)?;
+    for (index, diff) in diffs.iter().enumerate() {
+        let mut result = repo
+            .apply_to_tree(&tree, diff, None)
+            .with_context(|| format!("patch {} of {} doesn't apply", index + 1, diffs.len()))?;
+        tree = repo.find_tree(result.write_tree_to(repo)?)?;
+        for delta in diff.deltas() {
+            for path in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+            {
+                if !paths.iter().any(|known| known == path) {
+                    paths.push(path.to_owned());
+                }
+            }
+        }
+    }
+
+    if !check_only {
+        for diff in &diffs {
+            repo.apply(diff, git2::ApplyLocation::WorkDir, None)?;
+        }
+    }
+    Ok(PatchApplication {
+        patches: diffs.len(),
+        paths,
+    })
+}
+
+/// Split `text` into the patches it contains, each starting with the `From <commit id>` line of its email, or
+/// return it as is if it isn't a series of emails.
+fn split_patches(text: &str) -> Vec<&str> {
+    let mut starts: Vec<_> = text
+        .match_indices("From ")
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            (start == 0 || text[..start].ends_with('\n'))
+                && text[start + "From ".len()..]
+                    .split_once(' ')
+                    .is_some_and(|(id, _)| id.len() == 40 && git2::Oid::from_str(id).is_ok())
+        })
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(text.len());
+    starts
+        .windows(2)
+        .map(|bounds| &text[bounds[0]..bounds[1]])
+        .filter(|patch| !patch.trim().is_empty())
+        .collect()
+}
+
+/// Turn `subject` into the part of a patch file name after its number, like `git format-patch` does.
+fn file_name_subject(subject: &str) -> String {
+    let mut name = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    name.truncate(MAX_FILE_NAME_SUBJECT_LEN);
+    name.trim_end_matches(['-', '.']).to_owned()
+}
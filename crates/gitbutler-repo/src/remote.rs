@@ -5,7 +5,12 @@ use serde::Serialize;
 #[serde(rename_all = "camelCase")]
 pub struct GitRemote {
     pub name: Option<String>,
+    /// The URL to fetch from.
     pub url: Option<String>,
+    /// The URL to push to, which is the fetch URL unless a dedicated push URL is configured.
+    pub push_url: Option<String>,
+    /// The upstream of the current branch, like `main`, if it is tracked on this remote.
+    pub default_upstream: Option<String>,
 }
 
 impl From<git2::Remote<'_>> for GitRemote {
@@ -13,6 +18,8 @@ impl From<git2::Remote<'_>> for GitRemote {
         GitRemote {
             name: value.name().map(|name| name.to_owned()),
             url: value.url().map(|url| url.to_owned()),
+            push_url: value.pushurl().or(value.url()).map(|url| url.to_owned()),
+            default_upstream: None,
         }
     }
 }
@@ -498,21 +498,30 @@ impl RepositoryExt for git2::Repository {
 
     fn signatures(&self) -> Result<(git2::Signature, git2::Signature)> {
         let repo = gix::open(self.path())?;
+        // A pinned identity takes precedence over the configured one.
+        let identity = crate::identity::identity(self)?;
 
-        let author = repo
-            .author()
-            .transpose()?
-            .map(gix_to_git2_signature)
-            .transpose()?
-            .context("No author is configured in Git")
-            .context(Code::AuthorMissing)?;
+        let author = match &identity {
+            Some(identity) => identity.signature("GIT_AUTHOR_DATE")?,
+            None => repo
+                .author()
+                .transpose()?
+                .map(gix_to_git2_signature)
+                .transpose()?
+                .context("No author is configured in Git")
+                .context(Code::AuthorMissing)?,
+        };
 
         let config: Config = self.into();
         let committer = if config.user_real_comitter()? {
-            repo.committer()
-                .transpose()?
-                .map(gix_to_git2_signature)
-                .unwrap_or_else(|| crate::signature(SignaturePurpose::Committer))
+            match &identity {
+                Some(identity) => identity.signature("GIT_COMMITTER_DATE"),
+                None => repo
+                    .committer()
+                    .transpose()?
+                    .map(gix_to_git2_signature)
+                    .unwrap_or_else(|| crate::signature(SignaturePurpose::Committer)),
+            }
         } else {
             crate::signature(SignaturePurpose::Committer)
         }?;
@@ -536,7 +545,7 @@ impl RepositoryExt for git2::Repository {
     }
 }
 
-fn prepare_with_shell(program: impl Into<OsString>) -> gix::command::Prepare {
+pub(crate) fn prepare_with_shell(program: impl Into<OsString>) -> gix::command::Prepare {
     let prepare = gix::command::prepare(program);
     if cfg!(windows) {
         prepare
@@ -551,9 +560,9 @@ fn prepare_with_shell(program: impl Into<OsString>) -> gix::command::Prepare {
     }
 }
 
-fn into_command(prepare: gix::command::Prepare) -> std::process::Command {
+pub(crate) fn into_command(prepare: gix::command::Prepare) -> std::process::Command {
     let cmd: std::process::Command = prepare.into();
-    tracing::debug!(?cmd, "command to produce commit signature");
+    tracing::debug!(?cmd, "command to produce or verify commit signature");
     cmd
 }
 
@@ -0,0 +1,126 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::RepositoryExt;
+
+/// A tag as shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// The short name of the tag, like `v1.0` for `refs/tags/v1.0`.
+    pub name: String,
+    /// The commit the tag points to, after peeling annotated tags.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub target_id: git2::Oid,
+    /// The tag object if this is an annotated tag, or `None` if it's a lightweight tag.
+    pub annotation: Option<TagAnnotation>,
+}
+
+/// The details stored in the object of an annotated tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagAnnotation {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub tag_id: git2::Oid,
+    /// The message, without the signature.
+    pub message: String,
+    pub tagger_name: Option<String>,
+    /// When the tag was created in seconds since the Unix epoch, if it has a tagger.
+    pub created_at: Option<i64>,
+    /// Whether the tag carries a signature, which isn't verified.
+    pub signed: bool,
+}
+
+/// The line that starts the signature of signed tags, appended to their message.
+const SIGNATURE_START: &str = "-----BEGIN ";
+
+/// List all tags of `repo` that point to a commit, sorted by name.
+pub fn list_tags(repo: &git2::Repository) -> Result<Vec<Tag>> {
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let reference = repo.find_reference(&format!("refs/tags/{name}"))?;
+        let Ok(target) = reference.peel_to_commit() else {
+            continue;
+        };
+        let annotation = match reference.peel_to_tag() {
+            Ok(tag) => {
+                let message = tag.message().unwrap_or_default();
+                let signature_start = message
+                    .find(&format!("\n{SIGNATURE_START}"))
+                    .map(|start| start + 1)
+                    .or_else(|| message.starts_with(SIGNATURE_START).then_some(0));
+                Some(TagAnnotation {
+                    tag_id: tag.id(),
+                    message: message[..signature_start.unwrap_or(message.len())].to_owned(),
+                    tagger_name: tag
+                        .tagger()
+                        .and_then(|tagger| tagger.name().map(ToOwned::to_owned)),
+                    created_at: tag.tagger().map(|tagger| tagger.when().seconds()),
+                    signed: signature_start.is_some(),
+                })
+            }
+            Err(_) => None,
+        };
+        tags.push(Tag {
+            name: name.to_owned(),
+            target_id: target.id(),
+            annotation,
+        });
+    }
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tags)
+}
+
+/// Create a tag called `name` that points to the commit `target_id`, and return the id of the tag object, or of
+/// the commit for lightweight tags.
+///
+/// The tag is annotated if it has a `message`, and signed with the configured signing key if `sign` is set, which
+/// requires a `message`. Fails if a tag with this name already exists.
+pub fn create_tag(
+    repo: &git2::Repository,
+    name: &str,
+    target_id: git2::Oid,
+    message: Option<&str>,
+    sign: bool,
+) -> Result<git2::Oid> {
+    let refname = format!("refs/tags/{name}");
+    if !git2::Reference::is_valid_name(&refname) {
+        bail!("'{name}' isn't a valid tag name");
+    }
+    if repo.find_reference(&refname).is_ok() {
+        bail!("A tag called '{name}' already exists");
+    }
+    let target = repo.find_object(target_id, Some(git2::ObjectType::Commit))?;
+    let Some(message) = message else {
+        if sign {
+            bail!("Only annotated tags can be signed, the tag needs a message");
+        }
+        return Ok(repo.tag_lightweight(name, &target, false)?);
+    };
+
+    let message = git2::message_prettify(message, None)?;
+    let (tagger, _) = repo.signatures()?;
+    let tag_id = repo.tag_annotation_create(name, &target, &tagger, &message)?;
+    let tag_id = if sign {
+        let odb = repo.odb()?;
+        let mut buffer = odb.read(tag_id)?.data().to_vec();
+        let signature = repo.sign_buffer(&buffer).context("Failed to sign tag")?;
+        buffer.extend_from_slice(&signature);
+        if !buffer.ends_with(b"\n") {
+            buffer.push(b'\n');
+        }
+        odb.write(git2::ObjectType::Tag, &buffer)?
+    } else {
+        tag_id
+    };
+    repo.reference(&refname, tag_id, false, &format!("tag: {name}"))?;
+    Ok(tag_id)
+}
+
+/// Delete the tag called `name`, failing if there is no such tag.
+pub fn delete_tag(repo: &git2::Repository, name: &str) -> Result<()> {
+    repo.find_reference(&format!("refs/tags/{name}"))
+        .with_context(|| format!("There is no tag called '{name}'"))?;
+    repo.tag_delete(name)?;
+    Ok(())
+}
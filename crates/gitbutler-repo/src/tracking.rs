@@ -0,0 +1,102 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use gitbutler_project::{FetchResult, Project};
+use serde::Serialize;
+
+/// The time after the last fetch after which the ahead/behind counts are considered outdated.
+pub const STALE_FETCH_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// How a local branch relates to its upstream branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchTracking {
+    /// The short name of the local branch, like `main`.
+    pub name: String,
+    /// The short name of the configured upstream branch, like `origin/main`, or `None` if there is none.
+    pub upstream: Option<String>,
+    /// The amount of commits on the local branch that aren't on its upstream, or `None` if the upstream is
+    /// configured but doesn't exist, for instance because it wasn't fetched yet or was deleted on the remote.
+    pub ahead: Option<usize>,
+    /// The amount of commits on the upstream that aren't on the local branch.
+    pub behind: Option<usize>,
+}
+
+/// The tracking information of all branches, as returned by [`tracking_status()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingStatus {
+    pub branches: Vec<BranchTracking>,
+    /// When the remotes were last fetched, in milliseconds since the Unix epoch.
+    pub last_fetched_ms: Option<u128>,
+    /// Whether the last fetch is older than [`STALE_FETCH_AFTER`] or never happened, so the upstream branches
+    /// might differ from what the counts are based on.
+    pub fetch_stale: bool,
+}
+
+/// Return the tracking information of all local branches of `project` along with how recent it is.
+pub fn tracking_status(project: &Project) -> Result<TrackingStatus> {
+    let repo = git2::Repository::open(&project.path)?;
+    let last_fetched = project
+        .project_data_last_fetch
+        .as_ref()
+        .map(FetchResult::timestamp)
+        .copied();
+    Ok(TrackingStatus {
+        branches: branch_tracking(&repo)?,
+        last_fetched_ms: last_fetched
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis()),
+        fetch_stale: last_fetched.is_none_or(|time| {
+            SystemTime::now()
+                .duration_since(time)
+                .is_ok_and(|elapsed| elapsed > STALE_FETCH_AFTER)
+        }),
+    })
+}
+
+/// Return how each local branch of `repo` relates to its upstream, sorted by name.
+pub fn branch_tracking(repo: &git2::Repository) -> Result<Vec<BranchTracking>> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(ToOwned::to_owned) else {
+            continue;
+        };
+        let Some(refname) = branch.get().name().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let upstream_refname = match repo.branch_upstream_name(&refname) {
+            Ok(upstream) => upstream.as_str().map(ToOwned::to_owned),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let Some(upstream_refname) = upstream_refname else {
+            branches.push(BranchTracking {
+                name,
+                upstream: None,
+                ahead: None,
+                behind: None,
+            });
+            continue;
+        };
+
+        let upstream = upstream_refname
+            .strip_prefix("refs/remotes/")
+            .or_else(|| upstream_refname.strip_prefix("refs/heads/"))
+            .unwrap_or(&upstream_refname)
+            .to_owned();
+        let counts = match (branch.get().target(), repo.refname_to_id(&upstream_refname)) {
+            (Some(local), Ok(upstream)) => Some(repo.graph_ahead_behind(local, upstream)?),
+            _ => None,
+        };
+        branches.push(BranchTracking {
+            name,
+            upstream: Some(upstream),
+            ahead: counts.map(|(ahead, _)| ahead),
+            behind: counts.map(|(_, behind)| behind),
+        });
+    }
+    branches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(branches)
+}
@@ -0,0 +1,57 @@
+use std::io::Read;
+
+use gitbutler_repo::archive::{export_archive, ArchiveFormat};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+#[test]
+fn commits_and_worktree_without_export_ignored_files() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap().to_owned();
+    std::fs::write(workdir.join(".gitattributes"), "secret.txt export-ignore\n")?;
+    std::fs::write(workdir.join("secret.txt"), "hidden")?;
+    std::fs::create_dir(workdir.join("dir"))?;
+    std::fs::write(workdir.join("dir").join("file"), "committed")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("dir").join("file"), "uncommitted")?;
+
+    let out = tempfile::tempdir()?;
+    let zip_path = out.path().join("head.zip");
+    assert_eq!(
+        export_archive(&repo, Some("HEAD"), ArchiveFormat::Zip, &zip_path)?,
+        2,
+        ".gitattributes and dir/file"
+    );
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&zip_path)?)?;
+    let mut names: Vec<_> = zip.file_names().map(ToOwned::to_owned).collect();
+    names.sort();
+    assert_eq!(names, [".gitattributes", "dir/file"]);
+    let mut content = String::new();
+    zip.by_name("dir/file")?.read_to_string(&mut content)?;
+    assert_eq!(content, "committed");
+
+    let tar_path = out.path().join("worktree.tar.gz");
+    export_archive(&repo, None, ArchiveFormat::TarGz, &tar_path)?;
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(
+        &tar_path,
+    )?));
+    let mut files = Vec::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        files.push((entry.path()?.to_string_lossy().into_owned(), content));
+    }
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            (
+                ".gitattributes".to_owned(),
+                "secret.txt export-ignore\n".to_owned()
+            ),
+            ("dir/file".to_owned(), "uncommitted".to_owned())
+        ],
+        "the worktree is exported with its changes"
+    );
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::num::NonZeroU32;
+
+use gitbutler_repo::clone::{clone_repository, unshallow};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+/// A repository with three commits to clone from.
+fn origin() -> (git2::Repository, tempfile::TempDir) {
+    let (repo, tmp) = test_repository();
+    for content in ["one", "two"] {
+        std::fs::write(repo.workdir().unwrap().join("file"), content).unwrap();
+        commit_all(&repo);
+    }
+    repo.config()
+        .unwrap()
+        .set_bool("uploadpack.allowFilter", true)
+        .unwrap();
+    (repo, tmp)
+}
+
+fn url(repo: &git2::Repository) -> String {
+    format!("file://{}", repo.workdir().unwrap().display())
+}
+
+fn commit_count(repo: &git2::Repository) -> anyhow::Result<usize> {
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    Ok(walk.count())
+}
+
+#[test]
+fn shallow_clone_and_unshallow() -> anyhow::Result<()> {
+    let (origin, _origin_tmp) = origin();
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("clone");
+
+    let mut progress = Vec::new();
+    clone_repository(&url(&origin), &path, NonZeroU32::new(1), None, |p| {
+        progress.push(p)
+    })?;
+    let repo = git2::Repository::open(&path)?;
+    assert!(repo.is_shallow());
+    assert_eq!(commit_count(&repo)?, 1);
+    assert!(progress.iter().all(|p| p.percent <= 100));
+
+    unshallow(&repo, NonZeroU32::new(1), |_| {})?;
+    assert_eq!(commit_count(&repo)?, 2, "deepened by one commit");
+    unshallow(&repo, None, |_| {})?;
+    let repo = git2::Repository::open(&path)?;
+    assert!(!repo.is_shallow());
+    assert_eq!(commit_count(&repo)?, 3);
+    assert!(
+        unshallow(&repo, None, |_| {}).is_err(),
+        "there is nothing left to fetch"
+    );
+    Ok(())
+}
+
+#[test]
+fn partial_clone_records_its_filter() -> anyhow::Result<()> {
+    let (origin, _origin_tmp) = origin();
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("clone");
+
+    clone_repository(&url(&origin), &path, None, Some("blob:none"), |_| {})?;
+    let repo = git2::Repository::open(&path)?;
+    assert_eq!(
+        repo.config()?
+            .get_string("remote.origin.partialclonefilter")?,
+        "blob:none"
+    );
+    assert_eq!(
+        std::fs::read_to_string(path.join("file"))?,
+        "two",
+        "the blobs of the checkout are fetched as needed"
+    );
+    Ok(())
+}
+
+#[test]
+fn clone_into_existing_directory_fails() -> anyhow::Result<()> {
+    let (origin, origin_tmp) = origin();
+    let err = clone_repository(&url(&origin), origin_tmp.path(), None, None, |_| {}).unwrap_err();
+    assert!(err.to_string().contains("already exists"));
+    Ok(())
+}
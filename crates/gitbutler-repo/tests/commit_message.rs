@@ -0,0 +1,133 @@
+use gitbutler_project::CoAuthor;
+use gitbutler_repo::commit_message::{
+    self, Severity, ViolationKind, CO_AUTHORED_BY, SIGNED_OFF_BY,
+};
+use gitbutler_repo::RepositoryExt;
+use gitbutler_testsupport::test_repository;
+
+fn kinds(message: &str, conventional_commits: bool) -> Vec<(ViolationKind, Severity)> {
+    commit_message::validate(message, conventional_commits)
+        .into_iter()
+        .map(|violation| (violation.kind, violation.severity))
+        .collect()
+}
+
+#[test]
+fn style_problems_are_warnings() {
+    assert!(kinds("Add the thing\n\nBecause.", false).is_empty());
+    assert_eq!(
+        kinds(" \n", false),
+        [(ViolationKind::Empty, Severity::Error)]
+    );
+    assert_eq!(
+        kinds(&format!("{}\nno blank line", "a".repeat(73)), false),
+        [
+            (ViolationKind::SubjectTooLong, Severity::Warning),
+            (ViolationKind::MissingBlankLine, Severity::Warning)
+        ]
+    );
+    assert!(commit_message::ensure_valid(&"a".repeat(100), false).is_ok());
+    assert!(commit_message::ensure_valid("", false).is_err());
+}
+
+#[test]
+fn conventional_subjects_are_enforced_if_enabled() {
+    for valid in [
+        "feat: add the thing",
+        "fix(parser): handle empty input",
+        "refactor!: drop the old API",
+        "build(deps)!: bump everything",
+    ] {
+        assert!(kinds(valid, true).is_empty(), "{valid}");
+    }
+    for invalid in [
+        "Add the thing",
+        "feat:missing space",
+        "feat(): empty scope",
+        "feat(scope: unclosed",
+        "two words: no",
+        "fix: ",
+    ] {
+        assert_eq!(
+            kinds(invalid, true),
+            [(ViolationKind::NotConventional, Severity::Error)],
+            "{invalid}"
+        );
+        assert!(commit_message::ensure_valid(invalid, true).is_err());
+        assert!(kinds(invalid, false).is_empty(), "only checked if enabled");
+    }
+}
+
+#[test]
+fn template_is_read_from_the_git_config() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let worktree_dir = repo.workdir().unwrap();
+    assert_eq!(commit_message::template(&gix::open(repo.path())?)?, None);
+
+    std::fs::write(worktree_dir.join(".gitmessage"), "feat: \n\n# Why?\n")?;
+    repo.config()?.set_str("commit.template", ".gitmessage")?;
+    assert_eq!(
+        commit_message::template(&gix::open(repo.path())?)?.as_deref(),
+        Some("feat: \n\n# Why?\n"),
+        "relative paths are relative to the worktree"
+    );
+
+    repo.config()?.set_str("commit.template", "missing")?;
+    assert!(commit_message::template(&gix::open(repo.path())?).is_err());
+    Ok(())
+}
+
+#[test]
+fn trailers_are_appended_like_git_does() {
+    let co_author = |name: &str| (CO_AUTHORED_BY, format!("{name} <{name}@example.com>"));
+    assert_eq!(
+        commit_message::append_trailers("Subject\n", &[co_author("jane")]),
+        "Subject\n\nCo-authored-by: jane <jane@example.com>\n"
+    );
+    assert_eq!(
+        commit_message::append_trailers(
+            "Subject\n\nBody: not a trailer paragraph\nbecause of this line\n",
+            &[co_author("jane")]
+        ),
+        "Subject\n\nBody: not a trailer paragraph\nbecause of this line\n\nCo-authored-by: jane <jane@example.com>\n"
+    );
+    assert_eq!(
+        commit_message::append_trailers(
+            "Subject\n\nReviewed-by: joe <joe@example.com>",
+            &[co_author("jane"), co_author("joe")]
+        ),
+        "Subject\n\nReviewed-by: joe <joe@example.com>\nCo-authored-by: jane <jane@example.com>\nCo-authored-by: joe <joe@example.com>\n",
+        "trailers the message ends with are extended"
+    );
+    assert_eq!(
+        commit_message::append_trailers(
+            "Subject\n\nCo-authored-by: jane <jane@example.com>\n",
+            &[co_author("jane")]
+        ),
+        "Subject\n\nCo-authored-by: jane <jane@example.com>\n",
+        "present trailers aren't repeated"
+    );
+}
+
+#[test]
+fn sign_off_is_by_the_author() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let co_authors = [CoAuthor {
+        name: "Jane".into(),
+        email: "jane@example.com".into(),
+    }];
+    let (author, _) = repo.signatures()?;
+    assert_eq!(
+        commit_message::with_trailers(&repo, "Subject", true, &co_authors)?,
+        format!(
+            "Subject\n\n{SIGNED_OFF_BY}: {} <{}>\n{CO_AUTHORED_BY}: Jane <jane@example.com>\n",
+            author.name().unwrap(),
+            author.email().unwrap()
+        )
+    );
+    assert_eq!(
+        commit_message::with_trailers(&repo, "Subject", false, &[])?,
+        "Subject"
+    );
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use gitbutler_repo::commit_signature::{verify_commit_signature, SignatureFormat, SignatureStatus};
+use gitbutler_testsupport::test_repository;
+
+#[test]
+fn unsigned_commit() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let head = repo.head()?.peel_to_commit()?.id();
+    let verification = verify_commit_signature(&repo, head)?;
+    assert_eq!(verification.status, SignatureStatus::Unsigned);
+    assert_eq!(verification.format, None);
+    assert_eq!(verification.signer, None);
+    Ok(())
+}
+
+#[test]
+fn ssh_signature_without_allowed_signers_is_of_an_unknown_key() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let keys = tempfile::tempdir()?;
+    let (key, _) = ssh_key(keys.path(), "me")?;
+
+    let commit_id = ssh_signed_commit(&repo, &key, Signing::Commit)?;
+    let verification = verify_commit_signature(&repo, commit_id)?;
+    assert_eq!(
+        verification.status,
+        SignatureStatus::UnknownKey,
+        "the signature matches, but anyone could have made it"
+    );
+    assert_eq!(verification.format, Some(SignatureFormat::Ssh));
+    assert_eq!(verification.signer, None);
+
+    let commit_id = ssh_signed_commit(&repo, &key, Signing::OtherData)?;
+    assert_eq!(
+        verify_commit_signature(&repo, commit_id)?.status,
+        SignatureStatus::Invalid
+    );
+    Ok(())
+}
+
+#[test]
+fn ssh_signature_of_an_allowed_signer_is_valid() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let keys = tempfile::tempdir()?;
+    let (key, public_key) = ssh_key(keys.path(), "me")?;
+    let (other_key, _) = ssh_key(keys.path(), "other")?;
+    let allowed_signers = keys.path().join("allowed_signers");
+    std::fs::write(&allowed_signers, format!("me@example.com {public_key}\n"))?;
+    repo.config()?.set_str(
+        "gpg.ssh.allowedSignersFile",
+        &allowed_signers.to_string_lossy(),
+    )?;
+
+    let commit_id = ssh_signed_commit(&repo, &key, Signing::Commit)?;
+    let verification = verify_commit_signature(&repo, commit_id)?;
+    assert_eq!(verification.status, SignatureStatus::Valid);
+    assert_eq!(verification.format, Some(SignatureFormat::Ssh));
+    assert_eq!(verification.signer.as_deref(), Some("me@example.com"));
+
+    let commit_id = ssh_signed_commit(&repo, &other_key, Signing::Commit)?;
+    let verification = verify_commit_signature(&repo, commit_id)?;
+    assert_eq!(
+        verification.status,
+        SignatureStatus::UnknownKey,
+        "the key isn't among the allowed signers"
+    );
+    assert_eq!(verification.signer, None);
+
+    let commit_id = ssh_signed_commit(&repo, &key, Signing::OtherData)?;
+    assert_eq!(
+        verify_commit_signature(&repo, commit_id)?.status,
+        SignatureStatus::Invalid
+    );
+    Ok(())
+}
+
+/// Generate an SSH key named `name` in `dir`, and return the path to its private key along with its public key.
+fn ssh_key(dir: &Path, name: &str) -> anyhow::Result<(PathBuf, String)> {
+    let key = dir.join(name);
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+        .arg(&key)
+        .status()?;
+    assert!(status.success(), "ssh-keygen failed to create a key");
+    let public_key = std::fs::read_to_string(dir.join(format!("{name}.pub")))?;
+    Ok((key, public_key.trim().to_owned()))
+}
+
+enum Signing {
+    Commit,
+    /// Sign something else than the commit, for a signature that doesn't match it.
+    OtherData,
+}
+
+/// Commit the tree of `HEAD` on top of it, with a signature made with the SSH key at `key`.
+fn ssh_signed_commit(
+    repo: &git2::Repository,
+    key: &Path,
+    signing: Signing,
+) -> anyhow::Result<git2::Oid> {
+    let head = repo.head()?.peel_to_commit()?;
+    let author = git2::Signature::now("gitbutler-test", "gitbutler-test@example.com")?;
+    let buffer = repo.commit_create_buffer(&author, &author, "signed", &head.tree()?, &[&head])?;
+    let buffer = buffer.as_str().expect("valid UTF-8");
+    let signed_data = match signing {
+        Signing::Commit => buffer,
+        Signing::OtherData => "something else",
+    };
+
+    let mut child = Command::new("ssh-keygen")
+        .args(["-q", "-Y", "sign", "-n", "git", "-f"])
+        .arg(key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("piped")
+        .write_all(signed_data.as_bytes())?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success(), "ssh-keygen failed to sign");
+    let signature = String::from_utf8(output.stdout)?;
+    Ok(repo.commit_signed(buffer, &signature, None)?)
+}
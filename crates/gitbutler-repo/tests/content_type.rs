@@ -0,0 +1,118 @@
+use gitbutler_repo::{
+    decode_text, read_worktree_file, ContentEncoding, ContentType, FileInfo, WorktreeFile,
+};
+use std::path::Path;
+
+#[test]
+fn detect_by_magic_bytes_then_extension() {
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    assert_eq!(
+        ContentType::detect(Path::new("no-ext"), png),
+        ContentType::Image
+    );
+    let zip = b"PK\x03\x04\0\0\0\0";
+    assert_eq!(
+        ContentType::detect(Path::new("file.bin"), zip),
+        ContentType::Archive
+    );
+    assert_eq!(
+        ContentType::detect(Path::new("file.rs"), b"fn main() {}\n"),
+        ContentType::Text
+    );
+    assert_eq!(
+        ContentType::detect(Path::new("file.tgz"), b"\0\x01\x02unknown"),
+        ContentType::Archive,
+        "extensions are used if the format is unknown"
+    );
+    assert_eq!(
+        ContentType::detect(Path::new("file.dat"), b"\0\x01\x02unknown"),
+        ContentType::Binary
+    );
+}
+
+#[test]
+fn file_info_carries_content_type() {
+    let info = FileInfo::from_content(Path::new("a.txt"), b"hello");
+    assert_eq!(info.content_type, Some(ContentType::Text));
+    let info = FileInfo::from_content(Path::new("a.dat"), b"\0\x01\x02unknown");
+    assert_eq!(info.content_type, Some(ContentType::Binary));
+    assert_eq!(FileInfo::deleted().content_type, None);
+}
+
+#[test]
+fn legacy_encodings_are_decoded_instead_of_dropped() {
+    let latin1 = b"caf\xe9\n";
+    let info = FileInfo::from_content(Path::new("latin1.txt"), latin1);
+    assert_eq!(info.content_type, Some(ContentType::Text));
+    assert_eq!(info.content.as_deref(), Some("café\n"));
+    assert_eq!(info.encoding, Some(ContentEncoding::Latin1));
+    assert!(!info.lossy);
+
+    let utf16le = b"\xff\xfeh\0i\0";
+    let info = FileInfo::from_content(Path::new("utf16.txt"), utf16le);
+    assert_eq!(info.content.as_deref(), Some("hi"));
+    assert_eq!(info.encoding, Some(ContentEncoding::Utf16Le));
+
+    let broken_utf16be = b"\xfe\xff\xd8\x00\0a";
+    let decoded = decode_text(broken_utf16be).expect("the byte order mark makes it text");
+    assert_eq!(decoded.text, "\u{fffd}a");
+    assert!(decoded.lossy, "the unpaired surrogate was replaced");
+
+    let info = FileInfo::from_content(Path::new("file.rs"), "fn main() {}\n".as_bytes());
+    assert_eq!(info.encoding, Some(ContentEncoding::Utf8));
+}
+
+#[test]
+fn base64_keeps_the_exact_bytes() {
+    let info = FileInfo::base64(Path::new("file.dat"), b"\0\x01caf\xe9");
+    assert_eq!(info.content.as_deref(), Some("AAFjYWbp"));
+    assert_eq!(info.encoding, Some(ContentEncoding::Base64));
+    assert_eq!(info.size, Some(6));
+}
+
+#[test]
+fn worktree_files_are_read_with_their_content_type() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("file.rs"), "fn main() {}\n")?;
+    assert_eq!(
+        read_worktree_file(&dir.path().join("file.rs"), 1024)?,
+        WorktreeFile::Content {
+            content: b"fn main() {}\n".to_vec(),
+            content_type: ContentType::Text,
+            executable: false,
+        }
+    );
+
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    std::fs::write(dir.path().join("image"), png)?;
+    assert_eq!(
+        read_worktree_file(&dir.path().join("image"), 4)?,
+        WorktreeFile::TooLarge {
+            prefix: png.to_vec(),
+            len: png.len() as u64,
+            content_type: ContentType::Image,
+        },
+        "the type of large files is detected from the start of their content"
+    );
+
+    assert_eq!(
+        read_worktree_file(dir.path(), 1024)?,
+        WorktreeFile::Directory
+    );
+    assert_eq!(
+        read_worktree_file(&dir.path().join("missing"), 1024)?,
+        WorktreeFile::Missing
+    );
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("file.rs", dir.path().join("link"))?;
+        assert_eq!(
+            read_worktree_file(&dir.path().join("link"), 1024)?,
+            WorktreeFile::Link {
+                target: "file.rs".into()
+            },
+            "links aren't followed"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use gitbutler_repo::external_tool::{run_diff_tool, run_merge_tool, ExternalToolOutcome};
+use gitbutler_repo::merge::{merge_branch, MergeOutcome};
+use gitbutler_testsupport::{commit_all, test_repository};
+use std::path::Path;
+
+fn switch_to(repo: &git2::Repository, branch: &str) -> anyhow::Result<()> {
+    repo.set_head(&format!("refs/heads/{branch}"))?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    Ok(())
+}
+
+/// Produce a conflict in `file` by merging a `feature` branch that changed it differently.
+fn conflict(repo: &git2::Repository) -> anyhow::Result<()> {
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("file.txt"), "base\n")?;
+    commit_all(repo);
+    repo.branch("feature", &repo.head()?.peel_to_commit()?, false)?;
+    switch_to(repo, "feature")?;
+    std::fs::write(workdir.join("file.txt"), "theirs\n")?;
+    commit_all(repo);
+    switch_to(repo, "master")?;
+    std::fs::write(workdir.join("file.txt"), "ours\n")?;
+    commit_all(repo);
+    assert!(matches!(
+        merge_branch(repo, "feature")?,
+        MergeOutcome::Conflicted(_)
+    ));
+    Ok(())
+}
+
+fn set_config(repo: &git2::Repository, key: &str, value: &str) -> anyhow::Result<()> {
+    repo.config()?
+        .open_level(git2::ConfigLevel::Local)?
+        .set_str(key, value)?;
+    Ok(())
+}
+
+#[test]
+fn merge_tool_that_saves_resolves_the_conflict() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    conflict(&repo)?;
+    set_config(&repo, "merge.tool", "take-theirs")?;
+    set_config(
+        &repo,
+        "mergetool.take-theirs.cmd",
+        r#"cat "$BASE" "$REMOTE" > "$MERGED""#,
+    )?;
+
+    let outcome = run_merge_tool(&repo, Path::new("file.txt"))?;
+    assert_eq!(
+        outcome,
+        ExternalToolOutcome {
+            tool: "take-theirs".into(),
+            saved: true,
+            exit_code: Some(0),
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.workdir().unwrap().join("file.txt"))?,
+        "base\ntheirs\n",
+        "the tool was given the versions of the file"
+    );
+    assert!(
+        !repo.index()?.has_conflicts(),
+        "the saved file was staged, resolving the conflict"
+    );
+    Ok(())
+}
+
+#[test]
+fn merge_tool_that_does_not_save_leaves_the_conflict() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    conflict(&repo)?;
+    set_config(&repo, "merge.tool", "noop")?;
+    set_config(&repo, "mergetool.noop.cmd", "true")?;
+
+    let outcome = run_merge_tool(&repo, Path::new("file.txt"))?;
+    assert!(!outcome.saved);
+    assert!(repo.index()?.has_conflicts());
+
+    set_config(&repo, "mergetool.noop.trustExitCode", "true")?;
+    let outcome = run_merge_tool(&repo, Path::new("file.txt"))?;
+    assert!(outcome.saved, "the exit code is trusted when configured");
+    assert!(!repo.index()?.has_conflicts());
+    Ok(())
+}
+
+#[test]
+fn merge_tool_must_be_configured() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    conflict(&repo)?;
+    let err = run_merge_tool(&repo, Path::new("file.txt")).unwrap_err();
+    assert!(err.to_string().contains("merge.tool"));
+
+    Ok(())
+}
+
+#[test]
+fn merge_tool_without_command_is_run_by_git() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    conflict(&repo)?;
+    set_config(&repo, "merge.tool", "unknown")?;
+    let outcome = run_merge_tool(&repo, Path::new("file.txt"))?;
+    assert!(!outcome.saved, "Git doesn't know the tool either");
+    assert_ne!(outcome.exit_code, Some(0));
+    assert!(repo.index()?.has_conflicts());
+
+    // Like a tool Git knows, but whose program is configured.
+    set_config(&repo, "merge.tool", "vimdiff")?;
+    set_config(&repo, "mergetool.vimdiff.path", "true")?;
+    set_config(&repo, "mergetool.vimdiff.trustExitCode", "true")?;
+    let outcome = run_merge_tool(&repo, Path::new("file.txt"))?;
+    assert_eq!(outcome.tool, "vimdiff");
+    assert!(outcome.saved, "Git resolved the file as the tool succeeded");
+    assert!(!repo.index()?.has_conflicts());
+    Ok(())
+}
+
+#[test]
+fn diff_tool_compares_head_with_worktree() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("file.txt"), "committed\n")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("file.txt"), "changed\n")?;
+    set_config(&repo, "diff.tool", "revert")?;
+    set_config(&repo, "difftool.revert.cmd", r#"cp "$LOCAL" "$REMOTE""#)?;
+
+    let outcome = run_diff_tool(&repo, Path::new("file.txt"))?;
+    assert!(outcome.saved, "the worktree file was changed");
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("file.txt"))?,
+        "committed\n"
+    );
+    assert!(run_diff_tool(&repo, Path::new("../outside")).is_err());
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use gitbutler_diff::lfs::LfsStatus;
+use gitbutler_project::Project;
+use gitbutler_repo::{FileTreeEntryKind, FileTreeStatus, RepoCommands};
+use gitbutler_testsupport::{commit_all, test_repository};
+use std::path::Path;
+
+#[test]
+fn lists_one_level_with_status() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::create_dir_all(workdir.join("dir/nested"))?;
+    std::fs::write(workdir.join("dir/nested/committed"), "content")?;
+    std::fs::write(workdir.join("unchanged"), "content")?;
+    std::fs::write(workdir.join(".gitignore"), "ignored\n")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("dir/nested/committed"), "changed")?;
+    std::fs::write(workdir.join("new"), "new content")?;
+    std::fs::write(workdir.join("ignored"), "")?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let entries = project.file_tree(None)?;
+    let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["dir", ".gitignore", "ignored", "new", "unchanged"]);
+
+    let dir = &entries[0];
+    assert_eq!(dir.kind, FileTreeEntryKind::Directory);
+    assert_eq!(dir.size, None);
+    assert!(dir.has_changes, "a nested file was modified");
+
+    assert!(entries[2].ignored);
+    assert_eq!(entries[3].status, Some(FileTreeStatus::Untracked));
+    assert_eq!(entries[3].size, Some(11));
+    assert_eq!(entries[4].status, None);
+    assert!(!entries[4].has_changes);
+
+    let nested = project.file_tree(Some(Path::new("dir/nested")))?;
+    assert_eq!(nested.len(), 1);
+    assert_eq!(nested[0].path, Path::new("dir/nested/committed"));
+    assert_eq!(nested[0].status, Some(FileTreeStatus::Modified));
+
+    assert!(project.file_tree(Some(Path::new("../outside"))).is_err());
+    Ok(())
+}
+
+#[test]
+fn only_the_listed_directory_is_scanned_literally() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::create_dir_all(workdir.join("[id]"))?;
+    std::fs::write(workdir.join("[id]/page"), "content")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("[id]/page"), "changed")?;
+    std::fs::create_dir_all(workdir.join("untracked/nested"))?;
+    std::fs::write(workdir.join("untracked/nested/file"), "new")?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let entries = project.file_tree(Some(Path::new("[id]")))?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].status,
+        Some(FileTreeStatus::Modified),
+        "the name is taken literally, not as a pattern that only matches 'i' or 'd'"
+    );
+
+    let entries = project.file_tree(None)?;
+    let untracked = entries.iter().find(|e| e.name == "untracked").unwrap();
+    assert!(untracked.has_changes);
+
+    let nested = project.file_tree(Some(Path::new("untracked/nested")))?;
+    assert_eq!(nested.len(), 1);
+    assert_eq!(
+        nested[0].status,
+        Some(FileTreeStatus::Untracked),
+        "files in untracked directories are untracked even though they aren't listed one by one"
+    );
+    Ok(())
+}
+
+#[test]
+fn lfs_files_are_recognized_and_left_out_of_diffs() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    let pointer = "version https://git-lfs.github.com/spec/v1\n\
+                   oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                   size 12345\n";
+    std::fs::write(workdir.join(".gitattributes"), "*.bin filter=lfs -text\n")?;
+    std::fs::write(workdir.join("pointer.bin"), pointer)?;
+    std::fs::write(workdir.join("downloaded.bin"), pointer)?;
+    std::fs::write(workdir.join("regular"), "content")?;
+    let head = commit_all(&repo);
+    std::fs::write(workdir.join("downloaded.bin"), "actual content")?;
+    std::fs::write(workdir.join("regular"), "changed")?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let entries = project.file_tree(None)?;
+    let lfs: Vec<_> = entries.iter().map(|e| (e.name.as_str(), e.lfs)).collect();
+    assert_eq!(
+        lfs,
+        [
+            (".gitattributes", None),
+            ("downloaded.bin", Some(LfsStatus::Downloaded)),
+            ("pointer.bin", Some(LfsStatus::Pointer)),
+            ("regular", None),
+        ]
+    );
+
+    let diff = gitbutler_diff::workdir(&repo, head)?;
+    let changed: Vec<_> = diff.keys().collect();
+    assert_eq!(
+        changed,
+        [Path::new("regular")],
+        "the downloaded content isn't what would be committed"
+    );
+    Ok(())
+}
+
+#[test]
+fn project_files_are_listed_from_the_index() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::create_dir_all(workdir.join("src/nested"))?;
+    std::fs::write(workdir.join("src/nested/lib.rs"), "")?;
+    std::fs::write(workdir.join("src/main.rs"), "")?;
+    std::fs::write(workdir.join("build.rs"), "")?;
+    std::fs::write(workdir.join("README.md"), "")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("untracked.rs"), "")?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let all = project.list_project_files(None, None)?;
+    assert_eq!(
+        all,
+        [
+            Path::new("README.md"),
+            Path::new("build.rs"),
+            Path::new("src/main.rs"),
+            Path::new("src/nested/lib.rs"),
+        ],
+        "untracked files aren't listed"
+    );
+
+    let top_level = project.list_project_files(Some("*.rs"), None)?;
+    assert_eq!(top_level, [Path::new("build.rs")]);
+
+    let nested = project.list_project_files(Some("src/**/*.rs"), Some(1))?;
+    assert_eq!(nested, [Path::new("src/main.rs")]);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use gitbutler_repo::health::{optimize, repo_health};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+#[test]
+fn report_and_optimize() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("small"), "small")?;
+    std::fs::write(workdir.join("large"), "large".repeat(1000))?;
+    let head_id = commit_all(&repo);
+
+    let head = repo.find_commit(head_id)?;
+    let long_ago = git2::Signature::new("test", "test@email.com", &git2::Time::new(0, 0))?;
+    let old_id = repo.commit(None, &long_ago, &long_ago, "old", &head.tree()?, &[&head])?;
+    repo.branch("old", &repo.find_commit(old_id)?, false)?;
+    repo.branch("recent", &head, false)?;
+
+    let health = repo_health(&repo)?;
+    assert!(health.loose_objects > 0);
+    assert!(health.object_count >= health.loose_objects);
+    assert!(health.packs.is_empty());
+    assert_eq!(health.largest_blobs[0].size, 5000);
+    assert_eq!(
+        health
+            .stale_branches
+            .iter()
+            .map(|branch| (branch.name.as_str(), branch.last_commit_at))
+            .collect::<Vec<_>>(),
+        [("old", 0)]
+    );
+    assert!(!health.gc_recommended);
+
+    repo.config()?.set_i64("gc.auto", 1)?;
+    assert!(repo_health(&repo)?.gc_recommended);
+
+    let mut phases = Vec::new();
+    optimize(&repo, |progress| phases.push(progress.phase))?;
+    let health = repo_health(&repo)?;
+    assert_eq!(
+        health.loose_objects, 0,
+        "all objects are reachable and packed"
+    );
+    assert_eq!(health.packs.len(), 1);
+    assert!(!health.gc_recommended);
+    assert!(phases.iter().any(|phase| phase.contains("objects")));
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use gitbutler_repo::identity::{identity, set_identity, Identity};
+use gitbutler_repo::RepositoryExt;
+use gitbutler_testsupport::test_repository;
+
+fn work_identity() -> Identity {
+    Identity {
+        name: "Jane Doe".into(),
+        email: "jane@example.com".into(),
+    }
+}
+
+#[test]
+fn pinned_identity_signs_commits() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    assert_eq!(identity(&repo)?, None);
+
+    let warnings = set_identity(&repo, Some(&work_identity()))?;
+    assert!(warnings.is_empty(), "there are no remotes to compare with");
+    assert_eq!(identity(&repo)?, Some(work_identity()));
+
+    let (author, _) = repo.signatures()?;
+    assert_eq!(author.name(), Some("Jane Doe"));
+    assert_eq!(author.email(), Some("jane@example.com"));
+
+    set_identity(&repo, None)?;
+    assert_eq!(identity(&repo)?, None);
+    let (author, _) = repo.signatures()?;
+    assert_ne!(author.email(), Some("jane@example.com"));
+    Ok(())
+}
+
+#[test]
+fn invalid_identities_are_rejected() {
+    let (repo, _tmp) = test_repository();
+    for (name, email) in [
+        ("", "jane@example.com"),
+        ("Jane", "jane"),
+        ("Jane", "@example.com"),
+    ] {
+        let identity = Identity {
+            name: name.into(),
+            email: email.into(),
+        };
+        assert!(set_identity(&repo, Some(&identity)).is_err(), "{email}");
+    }
+}
+
+#[test]
+fn warns_about_emails_foreign_to_the_host_of_a_remote() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    repo.remote("work", "git@git.example.com:team/app.git")?;
+    repo.remote("public", "https://github.com/team/app.git")?;
+
+    assert!(set_identity(&repo, Some(&work_identity()))?.is_empty());
+    let subdomain = Identity {
+        email: "jane@mail.example.com".into(),
+        ..work_identity()
+    };
+    assert!(set_identity(&repo, Some(&subdomain))?.is_empty());
+
+    let personal = Identity {
+        email: "jane@personal.org".into(),
+        ..work_identity()
+    };
+    let warnings = set_identity(&repo, Some(&personal))?;
+    assert_eq!(warnings.len(), 1, "only the work remote is checked");
+    assert!(warnings[0].contains("'work'"), "{}", warnings[0]);
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use gitbutler_repo::init::{init_repository, Template};
+
+#[test]
+fn only_empty_directories_are_initialized() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("file.txt"), "content")?;
+    assert!(init_repository(tmp.path(), Template::Rust).is_err());
+    assert!(
+        !tmp.path().join(".git").exists(),
+        "nothing is created in directories that aren't empty"
+    );
+    assert!(tmp.path().join("file.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn templates_other_than_empty_have_a_gitignore() {
+    for template in Template::ALL {
+        assert_eq!(
+            template
+                .gitignore()
+                .is_some_and(|content| !content.trim().is_empty()),
+            *template != Template::Empty,
+            "{template:?}"
+        );
+    }
+}
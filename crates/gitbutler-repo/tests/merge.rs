@@ -0,0 +1,131 @@
+use gitbutler_repo::merge::{
+    abort_merge, conflict_versions, continue_merge, merge_branch, resolve_conflict, MergeOutcome,
+};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+fn switch_to(repo: &git2::Repository, branch: &str) -> anyhow::Result<()> {
+    repo.set_head(&format!("refs/heads/{branch}"))?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    Ok(())
+}
+
+/// Create `feature` from `HEAD` with `file` set to `theirs`, and change it to `ours` on `master`.
+fn diverge(repo: &git2::Repository, file: &str, ours: &str, theirs: &str) -> anyhow::Result<()> {
+    let workdir = repo.workdir().unwrap();
+    repo.branch("feature", &repo.head()?.peel_to_commit()?, false)?;
+    switch_to(repo, "feature")?;
+    std::fs::write(workdir.join(file), theirs)?;
+    commit_all(repo);
+    switch_to(repo, "master")?;
+    std::fs::write(workdir.join(file), ours)?;
+    commit_all(repo);
+    Ok(())
+}
+
+#[test]
+fn fast_forward_and_up_to_date() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    repo.branch("feature", &repo.head()?.peel_to_commit()?, false)?;
+    switch_to(&repo, "feature")?;
+    std::fs::write(repo.workdir().unwrap().join("file"), "content")?;
+    let feature_head = commit_all(&repo);
+    switch_to(&repo, "master")?;
+
+    assert_eq!(
+        merge_branch(&repo, "feature")?,
+        MergeOutcome::FastForward(feature_head)
+    );
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), feature_head);
+    assert!(repo.workdir().unwrap().join("file").exists());
+
+    assert_eq!(merge_branch(&repo, "feature")?, MergeOutcome::UpToDate);
+    Ok(())
+}
+
+#[test]
+fn clean_merge_creates_merge_commit() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    repo.branch("feature", &repo.head()?.peel_to_commit()?, false)?;
+    switch_to(&repo, "feature")?;
+    std::fs::write(workdir.join("theirs"), "theirs")?;
+    commit_all(&repo);
+    switch_to(&repo, "master")?;
+    std::fs::write(workdir.join("ours"), "ours")?;
+    commit_all(&repo);
+
+    let MergeOutcome::Merged(merge_commit) = merge_branch(&repo, "feature")? else {
+        panic!("changes to different files merge cleanly");
+    };
+    let merge_commit = repo.find_commit(merge_commit)?;
+    assert_eq!(merge_commit.parent_count(), 2);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert!(workdir.join("ours").exists() && workdir.join("theirs").exists());
+    Ok(())
+}
+
+#[test]
+fn conflicts_are_reported_and_can_be_aborted_or_continued() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("file"), "base\n")?;
+    commit_all(&repo);
+    diverge(&repo, "file", "ours\n", "theirs\n")?;
+
+    let MergeOutcome::Conflicted(conflicts) = merge_branch(&repo, "feature")? else {
+        panic!("both sides changed the same line");
+    };
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, std::path::Path::new("file"));
+    assert!(conflicts[0].ancestor.is_some());
+    assert!(conflicts[0].ours.is_some() && conflicts[0].theirs.is_some());
+    assert!(
+        continue_merge(&repo).is_err(),
+        "conflicts must be resolved first"
+    );
+
+    abort_merge(&repo)?;
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(std::fs::read_to_string(workdir.join("file"))?, "ours\n");
+
+    merge_branch(&repo, "feature")?;
+    let versions = conflict_versions(&repo, std::path::Path::new("file"))?;
+    assert_eq!(versions.base.content.as_deref(), Some("base\n"));
+    assert_eq!(versions.ours.content.as_deref(), Some("ours\n"));
+    assert_eq!(versions.theirs.content.as_deref(), Some("theirs\n"));
+
+    std::fs::write(workdir.join("file"), "edited after reading the versions\n")?;
+    let err = resolve_conflict(
+        &repo,
+        std::path::Path::new("file"),
+        versions.stamp,
+        b"resolved\n",
+    )
+    .unwrap_err();
+    assert!(
+        err.downcast_ref::<gitbutler_fs::ChangedSinceRead>()
+            .is_some(),
+        "edits made since reading the versions aren't overwritten"
+    );
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("file"))?,
+        "edited after reading the versions\n"
+    );
+
+    let versions = conflict_versions(&repo, std::path::Path::new("file"))?;
+    resolve_conflict(
+        &repo,
+        std::path::Path::new("file"),
+        versions.stamp,
+        b"resolved\n",
+    )?;
+    assert_eq!(std::fs::read_to_string(workdir.join("file"))?, "resolved\n");
+    assert!(
+        conflict_versions(&repo, std::path::Path::new("file")).is_err(),
+        "the conflict is resolved"
+    );
+    let merge_commit = repo.find_commit(continue_merge(&repo)?)?;
+    assert_eq!(merge_commit.parent_count(), 2);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    Ok(())
+}
@@ -1,4 +1,19 @@
+mod archive;
+mod clone;
+mod commit_message;
+mod commit_signature;
+mod content_type;
 mod create_wd_tree;
 mod credentials;
+mod external_tool;
+mod file_tree;
+mod health;
+mod identity;
+mod init;
+mod merge;
 mod merge_base_octopussy;
+mod patches;
+mod read_file;
 mod rebase;
+mod tags;
+mod tracking;
@@ -0,0 +1,55 @@
+use gitbutler_repo::patches::{apply_patch, format_patch};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+#[test]
+fn format_and_apply_a_series() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap().to_owned();
+    let base = repo.head()?.peel_to_commit()?;
+    std::fs::write(workdir.join("file"), "one\n")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("file"), "one\ntwo\n")?;
+    commit_all(&repo);
+
+    let out_dir = tempfile::tempdir()?;
+    let paths = format_patch(&repo, &format!("{}..HEAD", base.id()), out_dir.path())?;
+    assert_eq!(
+        paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        ["0001-some-commit.patch", "0002-some-commit.patch"]
+    );
+    let series = paths
+        .iter()
+        .map(std::fs::read_to_string)
+        .collect::<Result<String, _>>()?;
+    assert!(series.contains("Subject: [PATCH 1/2] some commit"));
+
+    repo.reset(base.as_object(), git2::ResetType::Hard, None)?;
+    let checked = apply_patch(&repo, &series, true)?;
+    assert_eq!(checked.patches, 2);
+    assert_eq!(checked.paths, [std::path::PathBuf::from("file")]);
+    assert!(!workdir.join("file").exists(), "checking changes nothing");
+
+    apply_patch(&repo, &series, false)?;
+    assert_eq!(std::fs::read_to_string(workdir.join("file"))?, "one\ntwo\n");
+    assert!(
+        apply_patch(&repo, &series, false).is_err(),
+        "the series was applied already"
+    );
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("file"))?,
+        "one\ntwo\n",
+        "nothing is applied if any patch fails"
+    );
+    Ok(())
+}
+
+#[test]
+fn text_without_patches_is_rejected() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    assert!(apply_patch(&repo, "just some text\n", true).is_err());
+    assert!(format_patch(&repo, "no-such-branch..HEAD", repo.path()).is_err());
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use gitbutler_project::Project;
+use gitbutler_repo::{ContentType, RepoCommands};
+use gitbutler_testsupport::{commit_all, test_repository};
+use std::path::Path;
+
+#[test]
+fn worktree_and_versions() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("file"), "committed")?;
+    commit_all(&repo);
+    std::fs::write(workdir.join("file"), "staged")?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new("file"))?;
+    index.write()?;
+    std::fs::write(workdir.join("file"), "edited")?;
+    std::fs::write(workdir.join("new"), "new content")?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let new = project.read_file_from_worktree(Path::new("new"), 1024)?;
+    assert_eq!(new.content.as_deref(), Some("new content"));
+    assert_eq!(
+        project
+            .read_file_from_worktree(Path::new("missing"), 1024)?
+            .size,
+        None,
+        "missing files are considered deleted"
+    );
+
+    let versions = project.read_file_versions(Path::new("file"), 1024)?;
+    assert_eq!(versions.worktree.content.as_deref(), Some("edited"));
+    assert_eq!(versions.index.content.as_deref(), Some("staged"));
+    assert_eq!(versions.head.content.as_deref(), Some("committed"));
+
+    let versions = project.read_file_versions(Path::new("new"), 1024)?;
+    assert_eq!(
+        versions.index.size, None,
+        "untracked files aren't in the index"
+    );
+    assert_eq!(versions.head.size, None);
+    Ok(())
+}
+
+#[test]
+fn large_files_and_paths_outside_the_worktree() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join("large"), "text ".repeat(100))?;
+
+    let project = Project {
+        path: workdir.to_owned(),
+        ..Default::default()
+    };
+    let large = project.read_file_from_worktree(Path::new("large"), 100)?;
+    assert_eq!(
+        large.content, None,
+        "content above the limit isn't returned"
+    );
+    assert_eq!(large.size, Some(500));
+    assert_eq!(large.content_type, Some(ContentType::Text));
+
+    assert!(project
+        .read_file_from_worktree(Path::new("../outside"), 100)
+        .is_err());
+    assert!(project
+        .read_file_from_worktree(&workdir.join("large"), 100)
+        .is_err());
+    Ok(())
+}
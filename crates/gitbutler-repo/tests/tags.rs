@@ -0,0 +1,49 @@
+use gitbutler_repo::tags::{create_tag, delete_tag, list_tags};
+use gitbutler_testsupport::test_repository;
+
+#[test]
+fn create_list_and_delete() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    assert_eq!(create_tag(&repo, "light", head, None, false)?, head);
+    let tag_id = create_tag(&repo, "v1.0", head, Some("First release"), false)?;
+    assert_ne!(tag_id, head, "annotated tags get their own object");
+
+    let tags = list_tags(&repo)?;
+    assert_eq!(
+        tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(),
+        ["light", "v1.0"]
+    );
+    assert!(tags.iter().all(|tag| tag.target_id == head));
+    assert_eq!(tags[0].annotation, None);
+    let annotation = tags[1].annotation.as_ref().unwrap();
+    assert_eq!(annotation.tag_id, tag_id);
+    assert_eq!(annotation.message, "First release\n");
+    assert_eq!(annotation.tagger_name.as_deref(), Some("gitbutler-test"));
+    assert!(!annotation.signed);
+
+    delete_tag(&repo, "light")?;
+    assert_eq!(list_tags(&repo)?.len(), 1);
+    assert!(delete_tag(&repo, "light").is_err());
+    Ok(())
+}
+
+#[test]
+fn invalid_tags_are_rejected() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    create_tag(&repo, "v1.0", head, None, false)?;
+    assert!(
+        create_tag(&repo, "v1.0", head, None, false).is_err(),
+        "tags aren't overwritten"
+    );
+    assert!(create_tag(&repo, "not..valid", head, None, false).is_err());
+    assert!(
+        create_tag(&repo, "signed", head, None, true).is_err(),
+        "signing requires a message"
+    );
+    assert!(list_tags(&repo)?.iter().all(|tag| tag.name == "v1.0"));
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use gitbutler_repo::tracking::{branch_tracking, BranchTracking};
+use gitbutler_testsupport::test_repository;
+
+#[test]
+fn ahead_and_behind_upstream() -> anyhow::Result<()> {
+    let (repo, _tmp) = test_repository();
+    let initial = repo.head()?.peel_to_commit()?;
+    let signature = git2::Signature::now("test", "test@email.com")?;
+    let tree = initial.tree()?;
+    let local = repo.commit(None, &signature, &signature, "local", &tree, &[&initial])?;
+    let remote = repo.commit(None, &signature, &signature, "remote", &tree, &[&initial])?;
+    repo.reference("refs/heads/master", local, true, "local work")?;
+    repo.reference("refs/remotes/origin/master", remote, true, "fetched")?;
+    repo.reference("refs/heads/untracked", initial.id(), true, "branch")?;
+    repo.reference("refs/heads/gone", initial.id(), true, "branch")?;
+    let mut config = repo.config()?;
+    config.set_str("remote.origin.url", "https://example.com/repo.git")?;
+    config.set_str("remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*")?;
+    for branch in ["master", "gone"] {
+        config.set_str(&format!("branch.{branch}.remote"), "origin")?;
+        config.set_str(
+            &format!("branch.{branch}.merge"),
+            &format!("refs/heads/{branch}"),
+        )?;
+    }
+
+    assert_eq!(
+        branch_tracking(&repo)?,
+        [
+            BranchTracking {
+                name: "gone".into(),
+                upstream: Some("origin/gone".into()),
+                ahead: None,
+                behind: None,
+            },
+            BranchTracking {
+                name: "master".into(),
+                upstream: Some("origin/master".into()),
+                ahead: Some(1),
+                behind: Some(1),
+            },
+            BranchTracking {
+                name: "untracked".into(),
+                upstream: None,
+                ahead: None,
+                behind: None,
+            },
+        ]
+    );
+    Ok(())
+}
@@ -2,6 +2,9 @@
 //!
 //! These are stateless and global, while discouraging storing secrets
 //! in memory beyond their use.
+//!
+//! The only credential that isn't kept here is the private key generated by `gitbutler-keys`, as SSH needs it
+//! to be a file.
 
 use std::sync::Mutex;
 
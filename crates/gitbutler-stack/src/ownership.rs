@@ -89,6 +89,42 @@ impl BranchOwnershipClaims {
 
         taken
     }
+
+    /// Return the hunks of `claim` that overlap with the hunks of these claims, if any.
+    pub fn overlapping(&self, claim: &OwnershipClaim) -> Option<OwnershipClaim> {
+        let hunks: Vec<_> = claim
+            .hunks
+            .iter()
+            .filter(|hunk| {
+                self.claims
+                    .iter()
+                    .filter(|owned| owned.file_path == claim.file_path)
+                    .any(|owned| owned.hunks.iter().any(|other| other.overlaps(hunk)))
+            })
+            .cloned()
+            .collect();
+        (!hunks.is_empty()).then(|| OwnershipClaim {
+            file_path: claim.file_path.clone(),
+            hunks,
+        })
+    }
+
+    /// Remove the hunks overlapping with the hunks of `claim`, and return `true` if there were any.
+    pub fn remove_overlapping(&mut self, claim: &OwnershipClaim) -> bool {
+        let mut removed = false;
+        self.claims.retain_mut(|owned| {
+            if owned.file_path != claim.file_path || owned.is_full() {
+                return true;
+            }
+            let len = owned.hunks.len();
+            owned
+                .hunks
+                .retain(|hunk| !claim.hunks.iter().any(|other| other.overlaps(hunk)));
+            removed |= owned.hunks.len() != len;
+            !owned.hunks.is_empty()
+        });
+        removed
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +68,10 @@ pub struct Stack {
     #[serde(with = "gitbutler_serde::oid")]
     pub head: git2::Oid,
     pub ownership: BranchOwnershipClaims,
+    /// The hunks that were explicitly assigned to this stack. Changes to their lines are assigned to this stack too,
+    /// and other stacks can't claim them until their ownership is transferred.
+    #[serde(default)]
+    pub locked_ownership: BranchOwnershipClaims,
     // order is the number by which UI should sort branches
     pub order: usize,
     // is Some(timestamp), the branch is considered a default destination for new changes.
@@ -158,6 +162,7 @@ impl Stack {
             tree,
             head,
             ownership: BranchOwnershipClaims::default(),
+            locked_ownership: BranchOwnershipClaims::default(),
             order,
             selected_for_changes,
             allow_rebasing,
@@ -0,0 +1,192 @@
+//! Opt-in encryption of files at rest with AES-256-GCM, under a random key kept elsewhere, like in the keychain of
+//! the operating system, or a key derived from a passphrase.
+//!
+//! Whole files are encrypted into the [`ENCRYPTED_MAGIC`] followed by a random nonce and the ciphertext with its
+//! tag. Files that are appended to line by line are encrypted per line instead, as the [`ENCRYPTED_LINE_PREFIX`]
+//! followed by the same as base64, so they can still be appended to. Readers tell encrypted data apart from plain
+//! data by these prefixes, so files that were only partly encrypted, like when encrypting them was interrupted, can
+//! still be read.
+//!
+//! The [cipher](Cipher) of a directory is [unlocked](unlock()) once for the whole process, so everything reading or
+//! writing files in it can find it with [`cipher()`].
+use std::{
+    collections::HashMap,
+    fmt,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use anyhow::{anyhow, bail, Result};
+use base64::engine::{general_purpose::STANDARD, Engine as _};
+use ring::{
+    aead, pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// The start of encrypted files, which is followed by the nonce and the ciphertext.
+pub const ENCRYPTED_MAGIC: &[u8] = b"GITBUTLER-ENCRYPTED\0";
+/// The start of encrypted lines, which is followed by the nonce and the ciphertext as base64.
+pub const ENCRYPTED_LINE_PREFIX: &str = "enc:";
+/// The length of keys, in bytes.
+pub const KEY_LEN: usize = 32;
+/// The length of the salt to derive a key from a passphrase with, in bytes.
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// The iterations of PBKDF2-HMAC-SHA256 to derive a key from a passphrase.
+const KEY_ITERATIONS: u32 = 600_000;
+
+/// The ciphers of the directories that were [unlocked](unlock()), by their path.
+static CIPHERS: LazyLock<Mutex<HashMap<PathBuf, Arc<Cipher>>>> = LazyLock::new(Default::default);
+
+/// A key to encrypt and decrypt data with.
+pub struct Cipher {
+    key: aead::LessSafeKey,
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The key must never end up in logs.
+        f.write_str("Cipher(..)")
+    }
+}
+
+impl Cipher {
+    /// Create a cipher from `key`, like one that was [generated](Self::generate_key()).
+    pub fn from_key(key: &[u8]) -> Result<Self> {
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, key)
+            .map_err(|_| anyhow!("The encryption key is invalid"))?;
+        Ok(Cipher {
+            key: aead::LessSafeKey::new(key),
+        })
+    }
+
+    /// Create a cipher with a key derived from `passphrase` and `salt`, which is [random](random()) and kept with
+    /// the encrypted data.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(KEY_ITERATIONS).expect("not zero"),
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Self::from_key(&key)
+    }
+
+    /// Return a new random key.
+    pub fn generate_key() -> Result<[u8; KEY_LEN]> {
+        random()
+    }
+
+    /// Return `plaintext` encrypted, starting with the [`ENCRYPTED_MAGIC`].
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        Ok([ENCRYPTED_MAGIC, &self.seal(plaintext)?].concat())
+    }
+
+    /// Return the plaintext of `encrypted`, as [encrypted](Self::encrypt()) with the same key.
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        let Some(sealed) = encrypted.strip_prefix(ENCRYPTED_MAGIC) else {
+            bail!("The data isn't encrypted");
+        };
+        self.open(sealed)
+    }
+
+    /// Return `line`, which must not contain newlines, encrypted as a single line starting with the
+    /// [`ENCRYPTED_LINE_PREFIX`].
+    pub fn encrypt_line(&self, line: &str) -> Result<String> {
+        Ok(format!(
+            "{ENCRYPTED_LINE_PREFIX}{}",
+            STANDARD.encode(self.seal(line.as_bytes())?)
+        ))
+    }
+
+    /// Return the plaintext of `line`, as [encrypted](Self::encrypt_line()) with the same key.
+    pub fn decrypt_line(&self, line: &str) -> Result<String> {
+        let Some(sealed) = line.strip_prefix(ENCRYPTED_LINE_PREFIX) else {
+            bail!("The line isn't encrypted");
+        };
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|_| anyhow!("The encrypted line is damaged"))?;
+        String::from_utf8(self.open(&sealed)?)
+            .map_err(|_| anyhow!("The encrypted line isn't valid UTF-8"))
+    }
+
+    /// Return the nonce followed by the ciphertext of `plaintext` and its tag.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce: [u8; NONCE_LEN] = random()?;
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("Could not encrypt the data"))?;
+        Ok([&nonce[..], &sealed].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            bail!("The encrypted data is damaged");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = aead::Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("The encrypted data is damaged"))?;
+        let mut plaintext = ciphertext.to_vec();
+        let len = self
+            .key
+            .open_in_place(nonce, aead::Aad::empty(), &mut plaintext)
+            .map_err(|_| anyhow!("The key is wrong, or the encrypted data is damaged"))?
+            .len();
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+}
+
+/// Return `true` if `data` was [encrypted](Cipher::encrypt()).
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+/// Return `true` if `line` was [encrypted](Cipher::encrypt_line()).
+pub fn is_encrypted_line(line: &str) -> bool {
+    line.starts_with(ENCRYPTED_LINE_PREFIX)
+}
+
+/// Return `N` random bytes, like a salt.
+pub fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("Could not generate random numbers for the encryption"))?;
+    Ok(bytes)
+}
+
+/// Make `cipher` the one to encrypt and decrypt the files in `dir` with, for as long as the process runs or until
+/// it's [locked](lock()) again.
+pub fn unlock(dir: impl Into<PathBuf>, cipher: Cipher) {
+    CIPHERS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(dir.into(), Arc::new(cipher));
+}
+
+/// Forget the cipher of `dir`, so its encrypted files can't be read until it's [unlocked](unlock()) again.
+pub fn lock(dir: &Path) {
+    CIPHERS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(dir);
+}
+
+/// Return the cipher that `dir` was [unlocked](unlock()) with, or `None` if it wasn't.
+pub fn cipher(dir: &Path) -> Option<Arc<Cipher>> {
+    CIPHERS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(dir)
+        .cloned()
+}
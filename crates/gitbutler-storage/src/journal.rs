@@ -0,0 +1,181 @@
+//! A write-ahead journal that makes writes of [`Storage`](crate::Storage) recoverable if the application is
+//! killed, or the system loses power, before the written file reached the disk.
+//!
+//! Before a file is replaced, its new content is appended to a journal file of the same relative path in the
+//! [`JOURNAL_DIR`], along with its length and checksum, and synced to disk. Once the file was replaced, the journal
+//! is removed. A journal that is still present on startup thus holds a write that may not have completed, and is
+//! replayed by [`recover()`].
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+/// The directory, relative to the storage root, holding the journals of files that are written.
+pub const JOURNAL_DIR: &str = "journal";
+/// The directory, relative to the storage root, into which journals with corrupt records are moved.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// A record of a journal that couldn't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorruptRecord {
+    /// The path of the file the record was written for, relative to the storage root.
+    pub path: PathBuf,
+    /// The byte offset of the record in its journal.
+    pub offset: usize,
+    /// Why the record couldn't be read.
+    pub reason: String,
+    /// Where the journal was moved to, relative to the storage root.
+    pub quarantined_to: PathBuf,
+}
+
+/// What was done by [`recover()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    /// The paths of the files that were written from their journal, relative to the storage root.
+    pub replayed: Vec<PathBuf>,
+    /// The records that were found to be truncated or damaged.
+    pub corrupt: Vec<CorruptRecord>,
+}
+
+impl RecoveryReport {
+    /// Return `true` if there was nothing to recover.
+    pub fn is_empty(&self) -> bool {
+        self.replayed.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// Append a record with `content` to the journal of the file at `rela_path`, and sync it to disk.
+pub(crate) fn append(root: &Path, rela_path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let journal_path = root.join(JOURNAL_DIR).join(rela_path);
+    if let Some(parent) = journal_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut record = format!("{:08x} {}\n", crc32fast::hash(content), content.len()).into_bytes();
+    record.extend_from_slice(content);
+    record.push(b'\n');
+
+    let mut journal = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path)?;
+    journal.write_all(&record)?;
+    journal.sync_all()
+}
+
+/// Remove the journal of the file or directory at `rela_path` once it was written or deleted.
+pub(crate) fn clear(root: &Path, rela_path: &Path) -> std::io::Result<()> {
+    let journal_path = root.join(JOURNAL_DIR).join(rela_path);
+    let res = if journal_path.is_dir() {
+        fs::remove_dir_all(journal_path)
+    } else {
+        fs::remove_file(journal_path)
+    };
+    match res {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Finish the writes whose journals are still present below `root`, and remove the journals.
+///
+/// The last intact record of each journal is written to its file. Journals with truncated or damaged records
+/// are moved to the [`QUARANTINE_DIR`] so they can be inspected, while the records before the damaged one are
+/// still replayed.
+pub(crate) fn recover(root: &Path) -> anyhow::Result<RecoveryReport> {
+    let mut report = RecoveryReport::default();
+    let journal_dir = root.join(JOURNAL_DIR);
+    let listing = gitbutler_fs::list_files(&journal_dir, &[])?;
+    for skipped in &listing.skipped {
+        tracing::warn!(path = %skipped.path.display(), reason = %skipped.reason, "skipped unreadable journal");
+    }
+    for rela_path in listing.files {
+        let journal_path = journal_dir.join(&rela_path);
+        let journal = fs::read(&journal_path)?;
+        let (last_content, corruption) = parse(&journal);
+        if let Some(content) = last_content {
+            gitbutler_fs::create_dirs_then_write(root.join(&rela_path), content)?;
+            report.replayed.push(rela_path.clone());
+        }
+        match corruption {
+            Some((offset, reason)) => {
+                let quarantined_to = quarantine(root, &rela_path, &journal_path)?;
+                tracing::warn!(path = %rela_path.display(), offset, reason, "quarantined corrupt journal");
+                report.corrupt.push(CorruptRecord {
+                    path: rela_path,
+                    offset,
+                    reason,
+                    quarantined_to,
+                });
+            }
+            None => fs::remove_file(&journal_path)?,
+        }
+    }
+    Ok(report)
+}
+
+/// Return the content of the last intact record of `journal`, along with the offset of and the reason for
+/// the first corrupt record, if there is one.
+fn parse(journal: &[u8]) -> (Option<&[u8]>, Option<(usize, String)>) {
+    let mut last_content = None;
+    let mut offset = 0;
+    while offset < journal.len() {
+        match parse_record(&journal[offset..]) {
+            Ok((content, len)) => {
+                last_content = Some(content);
+                offset += len;
+            }
+            Err(reason) => return (last_content, Some((offset, reason))),
+        }
+    }
+    (last_content, None)
+}
+
+/// Return the content of the record at the start of `journal`, along with the length of the whole record.
+fn parse_record(journal: &[u8]) -> Result<(&[u8], usize), String> {
+    let header_end = journal
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or("the header is truncated")?;
+    let header =
+        std::str::from_utf8(&journal[..header_end]).map_err(|_| "the header is damaged")?;
+    let (checksum, len) = header.split_once(' ').ok_or("the header is damaged")?;
+    let checksum = u32::from_str_radix(checksum, 16).map_err(|_| "the checksum is damaged")?;
+    let len: usize = len.parse().map_err(|_| "the length is damaged")?;
+
+    let content_start = header_end + 1;
+    let content = journal
+        .get(content_start..content_start + len)
+        .ok_or("the content is truncated")?;
+    if journal.get(content_start + len) != Some(&b'\n') {
+        return Err("the record isn't terminated".into());
+    }
+    if crc32fast::hash(content) != checksum {
+        return Err("the checksum doesn't match the content".into());
+    }
+    Ok((content, content_start + len + 1))
+}
+
+/// Move the journal at `journal_path` of the file at `rela_path` into the quarantine, returning its new
+/// path relative to `root`.
+fn quarantine(root: &Path, rela_path: &Path, journal_path: &Path) -> std::io::Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let mut file_name = rela_path.file_name().unwrap_or_default().to_owned();
+    file_name.push(format!(".{seconds}"));
+    let quarantined_to = Path::new(QUARANTINE_DIR)
+        .join(rela_path)
+        .with_file_name(file_name);
+    let to = root.join(&quarantined_to);
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(journal_path, to)?;
+    Ok(quarantined_to)
+}
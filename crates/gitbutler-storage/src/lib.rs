@@ -1,2 +1,5 @@
+pub mod encryption;
+pub mod journal;
+pub mod migrations;
 mod storage;
 pub use storage::Storage;
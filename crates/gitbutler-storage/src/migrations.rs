@@ -0,0 +1,198 @@
+//! Versioning of the files written by [`Storage`], so their format can change without breaking existing installs.
+//!
+//! The version of the data is kept in the [`VERSION_FILE`] at the storage root, and is `0` if it doesn't exist.
+//! [`Storage::migrate()`] runs all [migrations](Migration) with a higher version in order, after backing up all
+//! files to the [`BACKUP_DIR`]. If one of them fails, all files are restored from the backup, so the data is never
+//! left half-migrated.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::{journal, Storage};
+
+/// The file, relative to the storage root, holding the schema version of the data.
+pub const VERSION_FILE: &str = "schema_version";
+/// The directory, relative to the storage root, holding the backups made before migrating.
+pub const BACKUP_DIR: &str = "backups";
+/// The amount of backups to keep, the oldest ones are removed after each successful migration.
+const KEEP_BACKUPS: usize = 3;
+
+/// A change to the format of the stored data.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// The schema version of the data after this migration ran, starting at `1`.
+    pub version: u32,
+    /// What the migration changes, for logs.
+    pub description: &'static str,
+    /// Rewrite the data in the storage to the format of `version`.
+    pub run: fn(&Storage) -> Result<()>,
+}
+
+/// What was done by [`Storage::migrate()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    /// The schema version before migrating.
+    pub from_version: u32,
+    /// The schema version after migrating, which is the same as `from_version` if nothing had to be migrated.
+    pub to_version: u32,
+    /// The directory of the backup made before migrating, relative to the storage root.
+    pub backup: Option<PathBuf>,
+}
+
+impl Storage {
+    /// Return the schema version of the stored data, which is `0` if it was never [migrated](Self::migrate()).
+    pub fn schema_version(&self) -> Result<u32> {
+        match self.read(VERSION_FILE)? {
+            Some(version) => version
+                .trim()
+                .parse()
+                .with_context(|| format!("The schema version '{}' is invalid", version.trim())),
+            None => Ok(0),
+        }
+    }
+
+    /// Bring the stored data to the version of the last of `migrations` by running those that didn't run yet,
+    /// ordered by version. This should be called once on startup, before anything reads the data.
+    ///
+    /// All files are backed up first, and restored if any migration fails. Fails without changing anything if
+    /// the data was written by a newer version of the application, or if two migrations share a version.
+    pub fn migrate(&self, migrations: &[Migration]) -> Result<MigrationReport> {
+        let mut migrations = migrations.to_vec();
+        migrations.sort_by_key(|migration| migration.version);
+        if let Some(pair) = migrations
+            .windows(2)
+            .find(|pair| pair[0].version == pair[1].version)
+        {
+            bail!(
+                "BUG: there are multiple migrations to version {}",
+                pair[0].version
+            );
+        }
+
+        let from_version = self.schema_version()?;
+        let latest = migrations.last().map_or(0, |migration| migration.version);
+        if from_version > latest {
+            bail!(
+                "The data has schema version {from_version}, but this version of the application only knows up to \
+                 version {latest}. Please update the application."
+            );
+        }
+        let pending: Vec<_> = migrations
+            .into_iter()
+            .filter(|migration| migration.version > from_version)
+            .collect();
+        if pending.is_empty() {
+            return Ok(MigrationReport {
+                from_version,
+                to_version: from_version,
+                backup: None,
+            });
+        }
+
+        let backup = self.backup(from_version)?;
+        for migration in &pending {
+            tracing::info!(
+                version = migration.version,
+                description = migration.description,
+                "migrating storage"
+            );
+            let result = (migration.run)(self)
+                .and_then(|()| Ok(self.write(VERSION_FILE, &migration.version.to_string())?));
+            if let Err(err) = result {
+                self.restore(&backup).with_context(|| {
+                    format!(
+                        "Failed to restore the backup at '{}' after a migration failed",
+                        backup.display()
+                    )
+                })?;
+                return Err(err.context(format!(
+                    "Migration to schema version {} failed, the data was restored from the backup at '{}'",
+                    migration.version,
+                    backup.display()
+                )));
+            }
+        }
+        self.remove_old_backups()?;
+        Ok(MigrationReport {
+            from_version,
+            to_version: latest,
+            backup: Some(backup),
+        })
+    }
+
+    /// The root-relative paths of all stored files, except for the ones the storage manages itself.
+    fn data_files(&self) -> Result<Vec<PathBuf>> {
+        let root = self.local_data_dir.as_path();
+        gitbutler_fs::list_files(
+            root,
+            &[
+                Path::new(BACKUP_DIR),
+                Path::new(journal::JOURNAL_DIR),
+                Path::new(journal::QUARANTINE_DIR),
+            ],
+        )?
+        .into_complete()
+    }
+
+    /// Copy all data files into a new directory in the [`BACKUP_DIR`], and return its root-relative path.
+    fn backup(&self, version: u32) -> Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let backup = Path::new(BACKUP_DIR).join(format!("{seconds}-v{version}"));
+        for rela_path in self.data_files()? {
+            let to = self.local_data_dir.join(&backup).join(&rela_path);
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(self.local_data_dir.join(&rela_path), to)?;
+        }
+        Ok(backup)
+    }
+
+    /// Replace all data files with the ones in the `backup` directory.
+    fn restore(&self, backup: &Path) -> Result<()> {
+        let backup_dir = self.local_data_dir.join(backup);
+        let backed_up = gitbutler_fs::list_files(&backup_dir, &[])?.into_complete()?;
+        for rela_path in self.data_files()? {
+            if !backed_up.contains(&rela_path) {
+                fs::remove_file(self.local_data_dir.join(rela_path))?;
+            }
+        }
+        for rela_path in backed_up {
+            let content = fs::read(backup_dir.join(&rela_path))?;
+            gitbutler_fs::create_dirs_then_write(self.local_data_dir.join(rela_path), content)?;
+        }
+        Ok(())
+    }
+
+    /// Remove all but the [`KEEP_BACKUPS`] newest backups.
+    fn remove_old_backups(&self) -> Result<()> {
+        let entries = match fs::read_dir(self.local_data_dir.join(BACKUP_DIR)) {
+            Ok(entries) => entries,
+            // There was nothing to back up.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut backups: Vec<_> = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        // Names start with the time of the backup.
+        backups.sort_by_key(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split_once('-'))
+                .and_then(|(seconds, _)| seconds.parse::<u64>().ok())
+        });
+        for old in backups.iter().rev().skip(KEEP_BACKUPS) {
+            fs::remove_dir_all(old)?;
+        }
+        Ok(())
+    }
+}
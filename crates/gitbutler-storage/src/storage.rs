@@ -1,31 +1,62 @@
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    encryption::{self, Cipher},
+    journal,
 };
 
 /// A facility to read, write and delete files.
 #[derive(Debug, Clone)]
 pub struct Storage {
     /// The directory into which all of or files will be written or read-from.
-    local_data_dir: PathBuf,
+    pub(crate) local_data_dir: PathBuf,
+    /// The cipher to encrypt what's written with, and to decrypt what's read.
+    cipher: Option<Arc<Cipher>>,
 }
 
 impl Storage {
     pub fn new(local_data_dir: impl Into<PathBuf>) -> Storage {
         Storage {
             local_data_dir: local_data_dir.into(),
+            cipher: None,
         }
     }
 
+    /// Encrypt everything that is written from now on with `cipher`, and decrypt what is read with it,
+    /// or keep writing plain files if it's `None`.
+    pub fn with_cipher(mut self, cipher: Option<Arc<Cipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
     /// Read the content of the file at `rela_path` which is a path relative to our root directory.
     /// Return `Ok(None)` if the file doesn't exist.
+    ///
+    /// Encrypted files are decrypted, which fails if there is no cipher to do so.
     // TODO(ST): make all these operations write bytes.
     pub fn read(&self, rela_path: impl AsRef<Path>) -> std::io::Result<Option<String>> {
-        match fs::read_to_string(self.local_data_dir.join(rela_path)) {
-            Ok(content) => Ok(Some(content)),
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(err) => Err(err),
-        }
+        let content = match fs::read(self.local_data_dir.join(rela_path)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let content = if encryption::is_encrypted(&content) {
+            let Some(cipher) = &self.cipher else {
+                return Err(std::io::Error::other(
+                    "The file is encrypted, but its history is locked",
+                ));
+            };
+            cipher.decrypt(&content).map_err(std::io::Error::other)?
+        } else {
+            content
+        };
+        String::from_utf8(content)
+            .map(Some)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
     }
 
     /// Write `content` to `rela_path` atomically, so it's either written completely, or not at all.
@@ -41,9 +72,37 @@ impl Storage {
     /// need them to be consistent *need* to synchronize by some mean.
     ///
     /// Generally, the filesystem is used for synchronization, not in-memory primitives.
-    pub fn write(&self, rela_path: impl AsRef<Path>, content: &str) -> std::io::Result<()> {
-        let file_path = self.local_data_dir.join(rela_path);
-        gitbutler_fs::create_dirs_then_write(file_path, content)
+    ///
+    /// ### On Durability
+    ///
+    /// The content is [journaled](journal) first, so the write can be finished by [`Self::recover()`]
+    /// if the file didn't make it to disk. With a [cipher](Self::with_cipher()), only the encrypted content
+    /// is journaled.
+    pub fn write(
+        &self,
+        rela_path: impl AsRef<Path>,
+        content: impl AsRef<[u8]>,
+    ) -> std::io::Result<()> {
+        let rela_path = rela_path.as_ref();
+        let encrypted;
+        let content = match &self.cipher {
+            Some(cipher) => {
+                encrypted = cipher
+                    .encrypt(content.as_ref())
+                    .map_err(std::io::Error::other)?;
+                encrypted.as_slice()
+            }
+            None => content.as_ref(),
+        };
+        journal::append(&self.local_data_dir, rela_path, content)?;
+        gitbutler_fs::create_dirs_then_write(self.local_data_dir.join(rela_path), content)?;
+        journal::clear(&self.local_data_dir, rela_path)
+    }
+
+    /// Finish all writes that were interrupted, typically as the application was killed, and
+    /// quarantine journals that are corrupt. This should be called once on startup.
+    pub fn recover(&self) -> anyhow::Result<journal::RecoveryReport> {
+        journal::recover(&self.local_data_dir)
     }
 
     /// Delete the file or directory at `rela_path`.
@@ -52,6 +111,9 @@ impl Storage {
     ///
     /// If a symlink is encountered.
     pub fn delete(&self, rela_path: impl AsRef<Path>) -> std::io::Result<()> {
+        let rela_path = rela_path.as_ref();
+        // Pending writes must not bring back what is deleted.
+        journal::clear(&self.local_data_dir, rela_path)?;
         let file_path = self.local_data_dir.join(rela_path);
         let md = match file_path.symlink_metadata() {
             Ok(md) => md,
@@ -0,0 +1,52 @@
+use std::{fs, sync::Arc};
+
+use gitbutler_storage::{
+    encryption::{self, Cipher},
+    Storage,
+};
+
+#[test]
+fn encrypted_files_need_the_cipher_to_be_read() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let cipher = Arc::new(Cipher::from_key(&Cipher::generate_key()?)?);
+    let storage = Storage::new(tmp.path()).with_cipher(Some(cipher.clone()));
+    storage.write("file.json", "secret")?;
+
+    let on_disk = fs::read(tmp.path().join("file.json"))?;
+    assert!(encryption::is_encrypted(&on_disk));
+    assert!(!on_disk.windows(6).any(|w| w == b"secret"));
+    assert_eq!(storage.read("file.json")?.as_deref(), Some("secret"));
+
+    let locked = Storage::new(tmp.path());
+    assert!(locked.read("file.json").is_err(), "there is no cipher");
+    let other = Storage::new(tmp.path())
+        .with_cipher(Some(Arc::new(Cipher::from_key(&Cipher::generate_key()?)?)));
+    assert!(other.read("file.json").is_err(), "the key is wrong");
+
+    locked.write("plain.json", "plain")?;
+    assert_eq!(
+        storage.read("plain.json")?.as_deref(),
+        Some("plain"),
+        "plain files can still be read"
+    );
+    Ok(())
+}
+
+#[test]
+fn lines_are_encrypted_one_by_one() -> anyhow::Result<()> {
+    let salt: [u8; encryption::SALT_LEN] = encryption::random()?;
+    let cipher = Cipher::from_passphrase("passphrase", &salt)?;
+    let line = cipher.encrypt_line("{\"path\":\"file\"}")?;
+    assert!(encryption::is_encrypted_line(&line));
+    assert!(!line.contains('\n'));
+    assert_eq!(cipher.decrypt_line(&line)?, "{\"path\":\"file\"}");
+    assert_ne!(
+        cipher.encrypt_line("{\"path\":\"file\"}")?,
+        line,
+        "every line gets its own nonce"
+    );
+
+    let wrong = Cipher::from_passphrase("wrong", &salt)?;
+    assert!(wrong.decrypt_line(&line).is_err());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::{fs, path::PathBuf};
+
+use gitbutler_storage::{journal, Storage};
+
+#[test]
+fn writes_leave_no_journal_behind() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write("dir/file.json", "content")?;
+    assert_eq!(storage.read("dir/file.json")?.as_deref(), Some("content"));
+    assert!(!tmp
+        .path()
+        .join(journal::JOURNAL_DIR)
+        .join("dir/file.json")
+        .exists());
+    assert!(storage.recover()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn interrupted_writes_are_replayed() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write("file.json", "old")?;
+    // A record that was journaled, but not written to the file.
+    let content = "new";
+    fs::create_dir_all(tmp.path().join(journal::JOURNAL_DIR))?;
+    fs::write(
+        tmp.path().join(journal::JOURNAL_DIR).join("file.json"),
+        format!("{:08x} {}\n{content}\n", crc32(content), content.len()),
+    )?;
+
+    let report = storage.recover()?;
+    assert_eq!(report.replayed, vec![PathBuf::from("file.json")]);
+    assert!(report.corrupt.is_empty());
+    assert_eq!(storage.read("file.json")?.as_deref(), Some("new"));
+    assert!(storage.recover()?.is_empty(), "the journal was removed");
+    Ok(())
+}
+
+#[test]
+fn corrupt_records_are_quarantined() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write("file.json", "old")?;
+    fs::create_dir_all(tmp.path().join(journal::JOURNAL_DIR))?;
+    // The app was killed while appending the record.
+    fs::write(
+        tmp.path().join(journal::JOURNAL_DIR).join("file.json"),
+        format!("{:08x} 10\nnew", crc32("new content")),
+    )?;
+
+    let report = storage.recover()?;
+    assert!(report.replayed.is_empty());
+    assert_eq!(report.corrupt.len(), 1);
+    let record = &report.corrupt[0];
+    assert_eq!(record.offset, 0);
+    assert_eq!(record.reason, "the content is truncated");
+    assert!(tmp.path().join(&record.quarantined_to).is_file());
+    assert!(record.quarantined_to.starts_with(journal::QUARANTINE_DIR));
+    assert_eq!(
+        storage.read("file.json")?.as_deref(),
+        Some("old"),
+        "the file is left as it was"
+    );
+    assert!(storage.recover()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn deleting_discards_pending_writes() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    fs::create_dir_all(tmp.path().join(journal::JOURNAL_DIR))?;
+    fs::write(
+        tmp.path().join(journal::JOURNAL_DIR).join("file.json"),
+        format!("{:08x} 3\nnew\n", crc32("new")),
+    )?;
+    storage.delete("file.json")?;
+    assert!(storage.recover()?.is_empty());
+    assert_eq!(storage.read("file.json")?, None);
+    Ok(())
+}
+
+fn crc32(content: &str) -> u32 {
+    crc32fast::hash(content.as_bytes())
+}
@@ -0,0 +1,77 @@
+use anyhow::bail;
+use gitbutler_storage::{
+    migrations::{Migration, VERSION_FILE},
+    Storage,
+};
+
+const RENAME: Migration = Migration {
+    version: 1,
+    description: "rename the file",
+    run: |storage| {
+        let content = storage.read("old.json")?.unwrap_or_default();
+        storage.write("new.json", &content)?;
+        storage.delete("old.json")?;
+        Ok(())
+    },
+};
+
+const FAIL: Migration = Migration {
+    version: 2,
+    description: "fail halfway",
+    run: |storage| {
+        storage.write("new.json", "half-migrated")?;
+        bail!("failed on purpose")
+    },
+};
+
+#[test]
+fn pending_migrations_run_once_in_order() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write("old.json", "content")?;
+    assert_eq!(storage.schema_version()?, 0);
+
+    let report = storage.migrate(&[RENAME])?;
+    assert_eq!((report.from_version, report.to_version), (0, 1));
+    let backup = report.backup.expect("a backup is made before migrating");
+    assert!(tmp.path().join(backup).join("old.json").is_file());
+    assert_eq!(storage.read("new.json")?.as_deref(), Some("content"));
+    assert_eq!(storage.read(VERSION_FILE)?.as_deref(), Some("1"));
+
+    let report = storage.migrate(&[RENAME])?;
+    assert_eq!((report.from_version, report.to_version), (1, 1));
+    assert_eq!(
+        report.backup, None,
+        "nothing is backed up without migrations to run"
+    );
+    Ok(())
+}
+
+#[test]
+fn failed_migrations_restore_the_backup() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write("old.json", "content")?;
+
+    let err = storage.migrate(&[FAIL, RENAME]).unwrap_err();
+    assert!(err.to_string().contains("schema version 2"), "{err:?}");
+    assert_eq!(storage.read("old.json")?.as_deref(), Some("content"));
+    assert_eq!(
+        storage.read("new.json")?,
+        None,
+        "files written by the migrations are removed"
+    );
+    assert_eq!(storage.schema_version()?, 0);
+    Ok(())
+}
+
+#[test]
+fn newer_data_and_duplicate_versions_are_refused() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let storage = Storage::new(tmp.path());
+    storage.write(VERSION_FILE, "5")?;
+    assert!(storage.migrate(&[RENAME]).is_err());
+    assert!(storage.migrate(&[RENAME, RENAME]).is_err());
+    assert_eq!(storage.schema_version()?, 5);
+    Ok(())
+}
@@ -8,11 +8,13 @@ use gitbutler_command_context::CommandContext;
 use gitbutler_error::error::Code;
 use gitbutler_id::id::Id;
 use gitbutler_oplog::{
+    deltas::{CHECKPOINTS_REF, PRUNED_CHECKPOINTS_REF},
     entry::{OperationKind, SnapshotDetails},
+    meta_ref::OPLOG_REF,
     OplogExt,
 };
 use gitbutler_project as projects;
-use gitbutler_project::{CodePushState, Project};
+use gitbutler_project::{CodePushState, Project, SUBPROJECT_REFS_PREFIX};
 use gitbutler_reference::Refname;
 use gitbutler_stack::{StackId, Target, VirtualBranchesHandle};
 use gitbutler_url::Url;
@@ -206,6 +208,14 @@ fn push_all_refs(
                 Refname::Remote(_) | Refname::Virtual(_) | Refname::Local(_)
             )
         })
+        // The oplog is pushed to the data remote on its own, and checkpoints stay local, also those of sub-projects.
+        .filter(|r| {
+            let name = r.to_string();
+            name != OPLOG_REF
+                && name != CHECKPOINTS_REF
+                && name != PRUNED_CHECKPOINTS_REF
+                && !name.starts_with(SUBPROJECT_REFS_PREFIX)
+        })
         .map(|r| format!("+{}:{}", r, r))
         .collect();
 
@@ -0,0 +1,335 @@
+//! Back up the oplog to a user-controlled remote, and restore it from there.
+//!
+//! This is an alternative to syncing with GitButler servers, and uses whichever remote the user
+//! configured in [`Project::history_backup_remote`](gitbutler_project::Project::history_backup_remote).
+//!
+//! When several machines, or users, back up the history of the same repository to the same remote, their oplogs
+//! diverge. [`merge_history()`] reconciles them by interleaving the snapshots only one of them has, ordered by
+//! logical timestamps, so all of them arrive at the same oplog no matter who merges first.
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_oplog::{wip, OplogExt};
+use gitbutler_project::Project;
+use gitbutler_reference::RemoteRefname;
+use gitbutler_repo_actions::RepoActionsExt;
+use gitbutler_stack::StackId;
+use serde::Serialize;
+
+/// The reference on the remote side that holds the oplog head, unless it's a [sub-project](history_backup_ref()).
+///
+/// It's outside of `refs/heads/` so it's no branch that shows up, or could be protected, on the remote.
+pub const HISTORY_BACKUP_REF: &str = "refs/gb/sessions";
+
+/// The name of the reference on the remote side that holds the oplog head of `project`, which is
+/// [`HISTORY_BACKUP_REF`] unless it's a sub-project, whose references are
+/// [namespaced](gitbutler_project::Project::namespaced_ref) like they are locally.
+pub fn history_backup_ref(project: &Project) -> String {
+    let namespaced = project.namespaced_ref("refs/gitbutler/sessions");
+    match namespaced.strip_prefix("refs/gitbutler/") {
+        Some(rest) => format!("refs/gb/{rest}"),
+        None => namespaced,
+    }
+}
+
+/// The local reference that remembers where the [backup reference](history_backup_ref()) of `project` was on
+/// `remote_name` when it was last fetched or pushed, which is where it has to be to be overwritten.
+fn tracking_ref(project: &Project, remote_name: &str) -> String {
+    project.namespaced_ref(&format!("refs/gitbutler/backup/{remote_name}/oplog"))
+}
+
+/// Push the current oplog head to the history backup remote of the project in `ctx`.
+///
+/// The backed-up oplog is only replaced if it's where it was when it was last fetched or pushed from here, so
+/// the history other machines backed up is [merged](merge_history()) first instead of being lost.
+/// Use `askpass` to control if credential prompts should be shown to the user, just like with other pushes.
+/// Returns `false` if no backup remote is configured or if there is no oplog yet.
+pub fn push_history(ctx: &CommandContext, askpass: Option<Option<StackId>>) -> Result<bool> {
+    let project = ctx.project();
+    let Some(remote_name) = project.history_backup_remote.as_deref() else {
+        return Ok(false);
+    };
+    let Some(oplog_head) = project.oplog_head()? else {
+        return Ok(false);
+    };
+
+    let repo = ctx.repo();
+    let tracking_ref = tracking_ref(project, remote_name);
+    let lease = match repo.refname_to_id(&tracking_ref) {
+        Ok(id) => Some(id),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    if lease == Some(oplog_head) {
+        return Ok(true);
+    }
+
+    ctx.push_ref(
+        remote_name,
+        oplog_head,
+        &history_backup_ref(project),
+        lease,
+        askpass,
+    )
+    .context("failed to push oplog to history backup remote")?;
+    repo.reference(&tracking_ref, oplog_head, true, "backed up history")?;
+    tracing::info!(project_id = %project.id, remote = remote_name, %oplog_head, "oplog backed up");
+    Ok(true)
+}
+
+/// Push the [WIP snapshots](gitbutler_oplog::wip) of all branches to the history backup remote of the project in
+/// `ctx`, where they are kept under the same refs, and return how many refs were pushed.
+///
+/// Returns `0` if no backup remote is configured.
+pub fn push_wip_snapshots(ctx: &CommandContext, askpass: Option<Option<StackId>>) -> Result<usize> {
+    let Some(remote_name) = ctx.project().history_backup_remote.as_deref() else {
+        return Ok(0);
+    };
+    let refs = wip::list_wip_refs(ctx.repo())?;
+    for (name, id) in &refs {
+        let short_name = name.strip_prefix("refs/").unwrap_or(name);
+        ctx.push(
+            *id,
+            &RemoteRefname::new(remote_name, short_name),
+            true,
+            Some(format!("+{id}:{name}")),
+            askpass,
+        )
+        .with_context(|| format!("failed to push {name} to history backup remote"))?;
+    }
+    Ok(refs.len())
+}
+
+/// Fetch the oplog from the history backup remote of the project in `ctx` and make it the local oplog.
+///
+/// This is meant to be used on fresh clones, and will refuse to replace an oplog that already exists
+/// unless the backed-up oplog contains it.
+/// Returns the id of the restored oplog head.
+pub fn restore_history(ctx: &CommandContext, askpass: Option<String>) -> Result<git2::Oid> {
+    let project = ctx.project();
+    let backup_head = fetch_history(ctx, askpass)?;
+
+    if let Some(local_head) = project.oplog_head()? {
+        let contains_local =
+            local_head == backup_head || ctx.repo().graph_descendant_of(backup_head, local_head)?;
+        if !contains_local {
+            anyhow::bail!(
+                "Refusing to replace the local oplog at {local_head} with unrelated backup at {backup_head}"
+            );
+        }
+    }
+
+    let mut guard = project.exclusive_worktree_access();
+    project.set_oplog_head(backup_head, guard.write_permission())?;
+    Ok(backup_head)
+}
+
+/// Fetch the oplog from the history backup remote of the project in `ctx`, and return its head.
+fn fetch_history(ctx: &CommandContext, askpass: Option<String>) -> Result<git2::Oid> {
+    let project = ctx.project();
+    let remote_name = project
+        .history_backup_remote
+        .as_deref()
+        .context("No history backup remote is configured for this project")?;
+
+    let local_ref = tracking_ref(project, remote_name);
+    ctx.fetch_refspec(
+        remote_name,
+        format!("+{}:{local_ref}", history_backup_ref(project)),
+        askpass,
+    )
+    .context("failed to fetch oplog from history backup remote")?;
+
+    Ok(ctx
+        .repo()
+        .find_reference(&local_ref)?
+        .peel_to_commit()
+        .context("backed up oplog doesn't point to a commit")?
+        .id())
+}
+
+/// How [`merge_history()`] reconciled the local oplog with the backed-up one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryMergeKind {
+    /// The local oplog already contained all backed-up snapshots.
+    UpToDate,
+    /// The local oplog was missing, or was contained in the backed-up one, which replaced it.
+    FastForward,
+    /// Both oplogs had snapshots the other one didn't have, which were interleaved.
+    Merged,
+}
+
+/// The result of [`merge_history()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMerge {
+    pub kind: HistoryMergeKind,
+    /// The oplog head after merging.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The amount of backed-up snapshots that weren't in the local oplog.
+    pub added_snapshots: usize,
+}
+
+/// A snapshot of one oplog, as ordered by [`merge_history()`].
+struct OrderedSnapshot {
+    id: git2::Oid,
+    /// The creation time of the snapshot, raised to that of the snapshot before it in its oplog if the clock went
+    /// backwards, so the order of each oplog is kept.
+    logical_time: i64,
+    /// What identifies a snapshot across oplogs, even once it was recreated on top of other snapshots.
+    identity: (git2::Oid, i64, String),
+}
+
+/// Fetch the oplog from the history backup remote of the project in `ctx`, and merge it with the local one, which
+/// is then pushed back with the next backup.
+///
+/// Snapshots that are in both oplogs since they diverged, as identified by their content, creation time and
+/// message, are kept once. All others are recreated on top of the last snapshot the oplogs share, ordered by
+/// logical timestamps and then by their content. Merging the same oplogs thus always leads to the same result,
+/// and snapshots the local oplog shares with the backed-up one keep their ids.
+pub fn merge_history(ctx: &CommandContext, askpass: Option<String>) -> Result<HistoryMerge> {
+    let project = ctx.project();
+    let repo = ctx.repo();
+    let backup_head = fetch_history(ctx, askpass)?;
+    let local_head = project.oplog_head()?;
+
+    let (kind, base) = match local_head {
+        None => (HistoryMergeKind::FastForward, None),
+        Some(local_head)
+            if local_head == backup_head
+                || repo.graph_descendant_of(local_head, backup_head)? =>
+        {
+            return Ok(HistoryMerge {
+                kind: HistoryMergeKind::UpToDate,
+                head: local_head,
+                added_snapshots: 0,
+            });
+        }
+        Some(local_head) if repo.graph_descendant_of(backup_head, local_head)? => {
+            (HistoryMergeKind::FastForward, Some(local_head))
+        }
+        Some(local_head) => (
+            HistoryMergeKind::Merged,
+            repo.merge_base(local_head, backup_head).ok(),
+        ),
+    };
+    let backed_up = ordered_snapshots(repo, backup_head, base)?;
+    let (head, added_snapshots) = match local_head {
+        Some(local_head) if kind == HistoryMergeKind::Merged => {
+            let local = ordered_snapshots(repo, local_head, base)?;
+            let local_identities: HashSet<_> = local
+                .iter()
+                .map(|snapshot| snapshot.identity.clone())
+                .collect();
+            let added_snapshots = backed_up
+                .iter()
+                .filter(|snapshot| !local_identities.contains(&snapshot.identity))
+                .count();
+            (
+                recreate(repo, base, interleave(local, backed_up))?,
+                added_snapshots,
+            )
+        }
+        _ => (backup_head, backed_up.len()),
+    };
+
+    let mut guard = project.exclusive_worktree_access();
+    project.set_oplog_head(head, guard.write_permission())?;
+    tracing::info!(project_id = %project.id, %head, added_snapshots, "merged history from backup remote");
+    Ok(HistoryMerge {
+        kind,
+        head,
+        added_snapshots,
+    })
+}
+
+/// Return the snapshots from `head` back to, but excluding, `base`, or all of them if `base` is `None`, oldest
+/// first.
+fn ordered_snapshots(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    base: Option<git2::Oid>,
+) -> Result<Vec<OrderedSnapshot>> {
+    let mut commits = Vec::new();
+    let mut next = Some(repo.find_commit(head)?);
+    while let Some(commit) = next.take() {
+        if Some(commit.id()) == base {
+            break;
+        }
+        next = commit.parents().next();
+        commits.push(commit);
+    }
+
+    let mut logical_time = i64::MIN;
+    Ok(commits
+        .into_iter()
+        .rev()
+        .map(|commit| {
+            let created_at = commit.time().seconds();
+            logical_time = logical_time.max(created_at);
+            OrderedSnapshot {
+                id: commit.id(),
+                logical_time,
+                identity: (
+                    commit.tree_id(),
+                    created_at,
+                    String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+                ),
+            }
+        })
+        .collect())
+}
+
+/// Merge the snapshots of two oplogs, each oldest first, into one sequence that keeps the order of both, and where
+/// snapshots that are in both appear once.
+fn interleave(a: Vec<OrderedSnapshot>, b: Vec<OrderedSnapshot>) -> Vec<OrderedSnapshot> {
+    let order = |snapshot: &OrderedSnapshot| (snapshot.logical_time, snapshot.identity.clone());
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let mut seen = HashSet::new();
+    let (mut a, mut b) = (a.into_iter().peekable(), b.into_iter().peekable());
+    loop {
+        let next = match (a.peek(), b.peek()) {
+            (Some(first), Some(second)) if order(first) <= order(second) => a.next(),
+            (Some(_), Some(_)) => b.next(),
+            (Some(_), None) => a.next(),
+            (None, Some(_)) => b.next(),
+            (None, None) => break,
+        };
+        let snapshot = next.expect("one was peeked");
+        if seen.insert(snapshot.identity.clone()) {
+            merged.push(snapshot);
+        }
+    }
+    merged
+}
+
+/// Commit `snapshots` again, oldest first, on top of `base`, keeping everything but their parent, and return the
+/// new head.
+///
+/// Snapshots whose parent stays the same are recreated with the same id.
+fn recreate(
+    repo: &git2::Repository,
+    base: Option<git2::Oid>,
+    snapshots: Vec<OrderedSnapshot>,
+) -> Result<git2::Oid> {
+    let mut parent = base.map(|base| repo.find_commit(base)).transpose()?;
+    for snapshot in snapshots {
+        let commit = repo.find_commit(snapshot.id)?;
+        let message = commit.message_raw().with_context(|| {
+            format!("the message of snapshot {} isn't valid UTF-8", snapshot.id)
+        })?;
+        let id = repo.commit(
+            None,
+            &commit.author(),
+            &commit.committer(),
+            message,
+            &commit.tree()?,
+            parent.iter().collect::<Vec<_>>().as_slice(),
+        )?;
+        parent = Some(repo.find_commit(id)?);
+    }
+    Ok(parent.context("there were no snapshots to merge")?.id())
+}
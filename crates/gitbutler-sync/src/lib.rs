@@ -1,2 +1,3 @@
 pub mod cloud;
+pub mod history_backup;
 pub mod stack_upload;
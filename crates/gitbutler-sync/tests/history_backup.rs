@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use but_settings::AppSettings;
+use gitbutler_command_context::CommandContext;
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails},
+    OplogExt,
+};
+use gitbutler_project::{self as projects, Project, UpdateRequest};
+use gitbutler_sync::history_backup::{
+    history_backup_ref, merge_history, push_history, restore_history, HistoryMergeKind,
+    HISTORY_BACKUP_REF,
+};
+use gitbutler_testsupport::{paths, TestProject};
+use tempfile::TempDir;
+
+/// A project that backs up its history to `origin`.
+struct Test {
+    repository: TestProject,
+    projects: projects::Controller,
+    project: Project,
+    _data_dir: TempDir,
+}
+
+impl Default for Test {
+    fn default() -> Self {
+        let repository = TestProject::default();
+        let (projects, project, data_dir) = backed_up_project(repository.path());
+        gitbutler_branch_actions::set_base_branch(
+            &ctx(&project),
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+        Test {
+            repository,
+            projects,
+            project,
+            _data_dir: data_dir,
+        }
+    }
+}
+
+impl Test {
+    /// A fresh clone of the remote, as a project that backs up its history to `origin` as well.
+    fn clone_remote(&self) -> (Project, TempDir, TempDir) {
+        let worktree = tempfile::tempdir().unwrap();
+        git2::Repository::clone(&self.remote_url(), worktree.path()).unwrap();
+        let (_, project, data_dir) = backed_up_project(worktree.path());
+        (project, worktree, data_dir)
+    }
+
+    fn remote(&self) -> git2::Repository {
+        git2::Repository::open_bare(self.remote_url()).unwrap()
+    }
+
+    fn remote_url(&self) -> String {
+        let remote = self
+            .repository
+            .local_repository
+            .find_remote("origin")
+            .unwrap();
+        remote.url().unwrap().to_owned()
+    }
+}
+
+fn backed_up_project(worktree: &Path) -> (projects::Controller, Project, TempDir) {
+    let data_dir = paths::data_dir();
+    let projects = projects::Controller::from_path(data_dir.path());
+    let project = projects.add(worktree).unwrap();
+    let project = projects
+        .update(&UpdateRequest {
+            id: project.id,
+            history_backup_remote: Some("origin".into()),
+            ..Default::default()
+        })
+        .unwrap();
+    (projects, project, data_dir)
+}
+
+fn ctx(project: &Project) -> CommandContext {
+    CommandContext::open(project, AppSettings::default()).unwrap()
+}
+
+fn snapshot(project: &Project) -> git2::Oid {
+    let mut guard = project.exclusive_worktree_access();
+    project
+        .create_snapshot(
+            SnapshotDetails::new(OperationKind::FileChanges),
+            guard.write_permission(),
+        )
+        .unwrap()
+}
+
+#[test]
+fn history_is_restored_from_the_backup_remote() -> anyhow::Result<()> {
+    let test = Test::default();
+    snapshot(&test.project);
+    let oplog_head = snapshot(&test.project);
+    assert!(push_history(&ctx(&test.project), None)?);
+    assert_eq!(
+        test.remote().refname_to_id(HISTORY_BACKUP_REF)?,
+        oplog_head,
+        "the oplog is kept outside of the branches of the remote"
+    );
+
+    let (clone, _worktree, _data_dir) = test.clone_remote();
+    assert_eq!(clone.oplog_head()?, None, "clones start without history");
+    assert_eq!(restore_history(&ctx(&clone), None)?, oplog_head);
+    assert_eq!(clone.oplog_head()?, Some(oplog_head));
+    Ok(())
+}
+
+#[test]
+fn restoring_keeps_an_unrelated_local_history() -> anyhow::Result<()> {
+    let test = Test::default();
+    snapshot(&test.project);
+    push_history(&ctx(&test.project), None)?;
+
+    let (clone, _worktree, _data_dir) = test.clone_remote();
+    gitbutler_branch_actions::set_base_branch(
+        &ctx(&clone),
+        &"refs/remotes/origin/master".parse()?,
+    )?;
+    let local_head = snapshot(&clone);
+    assert!(restore_history(&ctx(&clone), None).is_err());
+    assert_eq!(
+        clone.oplog_head()?,
+        Some(local_head),
+        "snapshots that aren't backed up aren't discarded"
+    );
+    Ok(())
+}
+
+#[test]
+fn backups_only_replace_history_that_was_merged() -> anyhow::Result<()> {
+    let test = Test::default();
+    snapshot(&test.project);
+    push_history(&ctx(&test.project), None)?;
+
+    let (clone, _worktree, _data_dir) = test.clone_remote();
+    gitbutler_branch_actions::set_base_branch(
+        &ctx(&clone),
+        &"refs/remotes/origin/master".parse()?,
+    )?;
+    snapshot(&clone);
+    assert!(
+        push_history(&ctx(&clone), None).is_err(),
+        "the history backed up by the other clone would be lost"
+    );
+
+    let merge = merge_history(&ctx(&clone), None)?;
+    assert_eq!(merge.kind, HistoryMergeKind::Merged);
+    assert!(push_history(&ctx(&clone), None)?);
+    assert_eq!(test.remote().refname_to_id(HISTORY_BACKUP_REF)?, merge.head);
+    Ok(())
+}
+
+#[test]
+fn backups_are_not_refused_by_protected_branches() -> anyhow::Result<()> {
+    let test = Test::default();
+    let project = test.projects.update(&UpdateRequest {
+        id: test.project.id,
+        protected_branches: Some(vec!["*".into(), "gb/*".into()]),
+        ..Default::default()
+    })?;
+    snapshot(&project);
+    assert!(push_history(&ctx(&project), None)?);
+    let oplog_head = snapshot(&project);
+    assert!(
+        push_history(&ctx(&project), None)?,
+        "each backup replaces the one before"
+    );
+    assert_eq!(test.remote().refname_to_id(HISTORY_BACKUP_REF)?, oplog_head);
+    Ok(())
+}
+
+#[test]
+fn sub_projects_back_up_to_their_own_reference() -> anyhow::Result<()> {
+    let test = Test::default();
+    std::fs::create_dir_all(test.repository.path().join("lib"))?;
+    let subproject = test
+        .projects
+        .add_subproject(test.project.id, Path::new("lib"))?;
+
+    assert_eq!(history_backup_ref(&test.project), HISTORY_BACKUP_REF);
+    assert_eq!(
+        history_backup_ref(&subproject),
+        format!("refs/gb/subprojects/{}/sessions", subproject.id)
+    );
+    Ok(())
+}
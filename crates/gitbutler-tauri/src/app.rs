@@ -25,6 +25,10 @@ impl App {
     pub fn users(&self) -> gitbutler_user::Controller {
         gitbutler_user::Controller::from_path(&self.app_data_dir)
     }
+
+    pub fn keys(&self) -> gitbutler_keys::Controller {
+        gitbutler_keys::Controller::from_path(&self.app_data_dir)
+    }
 }
 
 impl App {
@@ -122,9 +126,13 @@ impl App {
 
     pub fn delete_all_data(&self) -> Result<()> {
         let controller = self.projects();
-        for project in controller.list().context("failed to list projects")? {
+        let projects = controller.list().context("failed to list projects")?;
+        let deleted_projects = controller
+            .list_deleted()
+            .context("failed to list deleted projects")?;
+        for project in projects.into_iter().chain(deleted_projects) {
             controller
-                .delete(project.id)
+                .purge(project.id)
                 .map_err(|err| err.context("failed to delete project"))?;
         }
         Ok(())
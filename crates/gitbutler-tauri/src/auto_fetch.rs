@@ -0,0 +1,161 @@
+//! Fetch the remotes of open projects in the background, for projects that opted into it with
+//! [`Project::auto_fetch_interval_seconds`](gitbutler_project::Project::auto_fetch_interval_seconds).
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use but_settings::AppSettingsWithDiskSync;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::{self as projects, FetchResult, ProjectId};
+use gitbutler_repo::tracking::{self, TrackingStatus};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::WindowState;
+
+/// The event emitted after each fetch.
+pub const FETCH_COMPLETED_EVENT: &str = "fetch://completed";
+
+/// How often to check whether a project is due to be fetched.
+const TICK: Duration = Duration::from_secs(15);
+
+/// The longest the next fetch is delayed after repeated failures, like when the network is down.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// The payload of [`FETCH_COMPLETED_EVENT`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchCompleted {
+    project_id: ProjectId,
+    /// The error of the fetch, if it failed for any of the remotes.
+    error: Option<String>,
+    tracking: TrackingStatus,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    interval: Duration,
+    next_fetch: Instant,
+    failures: u32,
+}
+
+impl Schedule {
+    fn new(interval: Duration) -> Self {
+        Schedule {
+            interval,
+            next_fetch: Instant::now() + jittered(interval),
+            failures: 0,
+        }
+    }
+
+    /// Plan the next fetch after the previous one succeeded or failed, doubling the delay with each
+    /// consecutive failure.
+    fn reschedule(&mut self, succeeded: bool) {
+        self.failures = if succeeded { 0 } else { self.failures + 1 };
+        let delay = self
+            .interval
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(MAX_BACKOFF.max(self.interval));
+        self.next_fetch = Instant::now() + jittered(delay);
+    }
+}
+
+/// Return `delay` shifted by up to a tenth in either direction, so projects don't all fetch at once.
+fn jittered(delay: Duration) -> Duration {
+    let random = RandomState::new().hash_one(Instant::now());
+    let factor = 0.9 + (random % 1000) as f64 / 5000.0;
+    delay.mul_f64(factor)
+}
+
+/// Start fetching the open projects with auto-fetch enabled in the background, and emit
+/// [`FETCH_COMPLETED_EVENT`] after each fetch.
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut schedules: HashMap<ProjectId, Schedule> = HashMap::new();
+        let mut ticks = tokio::time::interval(TICK);
+        loop {
+            ticks.tick().await;
+            let open_projects = app_handle.state::<WindowState>().open_projects();
+            let projects = app_handle.state::<projects::Controller>();
+            let mut due = Vec::new();
+            for project_id in &open_projects {
+                let Some(interval) = projects
+                    .get(*project_id)
+                    .ok()
+                    .and_then(|project| project.auto_fetch_interval_seconds)
+                    .filter(|seconds| *seconds > 0)
+                    .map(Duration::from_secs)
+                else {
+                    schedules.remove(project_id);
+                    continue;
+                };
+                let schedule = schedules
+                    .entry(*project_id)
+                    .and_modify(|schedule| {
+                        if schedule.interval != interval {
+                            *schedule = Schedule::new(interval);
+                        }
+                    })
+                    .or_insert_with(|| Schedule::new(interval));
+                if schedule.next_fetch <= Instant::now() {
+                    due.push(*project_id);
+                }
+            }
+            schedules.retain(|project_id, _| open_projects.contains(project_id));
+
+            for project_id in due {
+                let app_handle = app_handle.clone();
+                let succeeded =
+                    tauri::async_runtime::spawn_blocking(move || fetch(&app_handle, project_id))
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|result| result)
+                        .unwrap_or_else(|err| {
+                            tracing::warn!(%project_id, ?err, "automatic fetch failed");
+                            false
+                        });
+                if let Some(schedule) = schedules.get_mut(&project_id) {
+                    schedule.reschedule(succeeded);
+                }
+            }
+        }
+    });
+}
+
+/// Fetch all remotes of the project, record when that happened, and emit the result. Returns `true` if
+/// all remotes were fetched.
+fn fetch(app_handle: &AppHandle, project_id: ProjectId) -> Result<bool> {
+    let projects = app_handle.state::<projects::Controller>();
+    let settings = app_handle.state::<AppSettingsWithDiskSync>();
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let result = gitbutler_branch_actions::fetch_from_remotes(
+        &ctx,
+        Some("auto_fetch".into()),
+        &AtomicBool::new(false),
+    )?;
+    let project = projects.update(&projects::UpdateRequest {
+        id: project_id,
+        project_data_last_fetched: Some(result.clone()),
+        ..Default::default()
+    })?;
+
+    let error = match result {
+        FetchResult::Fetched { .. } => None,
+        FetchResult::Error { error, .. } => Some(error),
+    };
+    let succeeded = error.is_none();
+    app_handle.emit(
+        FETCH_COMPLETED_EVENT,
+        FetchCompleted {
+            project_id,
+            error,
+            tracking: tracking::tracking_status(&project)?,
+        },
+    )?;
+    Ok(succeeded)
+}
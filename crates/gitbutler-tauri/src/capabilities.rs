@@ -0,0 +1,328 @@
+//! Check what the app relies on in its environment, for onboarding to warn about problems before they get in the
+//! way, like a missing `git` binary or a full disk.
+use std::path::Path;
+
+use serde::Serialize;
+
+/// How many directories the file watcher may watch at least, on Linux, before large worktrees can't be watched
+/// completely.
+const MIN_INOTIFY_WATCHES: u64 = 65_536;
+/// How much space should be left in the app data directory for snapshots and logs to be written.
+const LOW_DISK_SPACE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Below this amount of free space, writes in the app data directory are likely to fail.
+const CRITICAL_DISK_SPACE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// What the app relies on in its environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// The `git` binary, used to fetch and push.
+    Git,
+    /// An SSH agent, which holds the keys for remotes accessed over SSH.
+    SshAgent,
+    /// The keychain of the system, where credentials are stored.
+    Keychain,
+    /// How many directories may be watched for changes, which only applies to Linux.
+    InotifyLimits,
+    /// The free space in the app data directory.
+    DiskSpace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Ok,
+    /// Working, but some features may not, or not for long.
+    Warning,
+    /// Not working, which will cause errors.
+    Error,
+    /// Doesn't apply to this system.
+    NotApplicable,
+}
+
+/// What was found about a [`Capability`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityCheck {
+    pub capability: Capability,
+    pub status: CheckStatus,
+    /// What was found, to show to the user.
+    pub message: String,
+}
+
+impl CapabilityCheck {
+    fn new(capability: Capability, status: CheckStatus, message: impl Into<String>) -> Self {
+        CapabilityCheck {
+            capability,
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// The result of [`capability_report()`], with one check for each [`Capability`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityReport {
+    pub checks: Vec<CapabilityCheck>,
+}
+
+impl CapabilityReport {
+    /// Return the worst status among all checks.
+    pub fn status(&self) -> CheckStatus {
+        let checks = || self.checks.iter().map(|check| check.status);
+        if checks().any(|status| status == CheckStatus::Error) {
+            CheckStatus::Error
+        } else if checks().any(|status| status == CheckStatus::Warning) {
+            CheckStatus::Warning
+        } else {
+            CheckStatus::Ok
+        }
+    }
+}
+
+/// Check the environment of the app, whose data is stored in `app_data_dir`.
+///
+/// Note that checking the keychain stores and deletes a secret, for which the system may ask for permission.
+pub fn capability_report(app_data_dir: &Path) -> CapabilityReport {
+    CapabilityReport {
+        checks: vec![
+            check_git(),
+            check_ssh_agent(),
+            check_keychain(),
+            check_inotify_limits(),
+            check_disk_space(app_data_dir),
+        ],
+    }
+}
+
+fn check_git() -> CapabilityCheck {
+    let git = gix::path::env::exe_invocation();
+    match std::process::Command::new(git).arg("--version").output() {
+        Ok(output) if output.status.success() => CapabilityCheck::new(
+            Capability::Git,
+            CheckStatus::Ok,
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ),
+        Ok(output) => CapabilityCheck::new(
+            Capability::Git,
+            CheckStatus::Error,
+            format!(
+                "'{}' failed: {}",
+                git.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Err(err) => CapabilityCheck::new(
+            Capability::Git,
+            CheckStatus::Error,
+            format!("Git wasn't found, which is needed to fetch and push ({err})"),
+        ),
+    }
+}
+
+fn check_ssh_agent() -> CapabilityCheck {
+    #[cfg(windows)]
+    let agent = Some(std::path::PathBuf::from(r"\\.\pipe\openssh-ssh-agent"));
+    #[cfg(not(windows))]
+    let agent = std::env::var_os("SSH_AUTH_SOCK").map(std::path::PathBuf::from);
+
+    let missing = "Remotes accessed over SSH need keys configured in GitButler or an SSH agent";
+    match agent {
+        Some(agent) if agent.exists() => CapabilityCheck::new(
+            Capability::SshAgent,
+            CheckStatus::Ok,
+            format!("An SSH agent is listening at '{}'", agent.display()),
+        ),
+        Some(agent) => CapabilityCheck::new(
+            Capability::SshAgent,
+            CheckStatus::Warning,
+            format!(
+                "No SSH agent is listening at '{}'. {missing}",
+                agent.display()
+            ),
+        ),
+        None => CapabilityCheck::new(
+            Capability::SshAgent,
+            CheckStatus::Warning,
+            format!("No SSH agent is running. {missing}"),
+        ),
+    }
+}
+
+fn check_keychain() -> CapabilityCheck {
+    use gitbutler_secret::{secret, Sensitive};
+
+    const HANDLE: &str = "capability-check";
+    let namespace = secret::Namespace::BuildKind;
+    let probe = Sensitive("probe".to_owned());
+    let result = secret::persist(HANDLE, &probe, namespace)
+        .and_then(|()| secret::retrieve(HANDLE, namespace))
+        .and_then(|retrieved| {
+            secret::delete(HANDLE, namespace)?;
+            Ok(retrieved)
+        });
+    match result {
+        Ok(Some(retrieved)) if retrieved.0 == probe.0 => CapabilityCheck::new(
+            Capability::Keychain,
+            CheckStatus::Ok,
+            "Credentials can be stored in the keychain",
+        ),
+        Ok(_) => CapabilityCheck::new(
+            Capability::Keychain,
+            CheckStatus::Error,
+            "Credentials stored in the keychain couldn't be read back",
+        ),
+        Err(err) => CapabilityCheck::new(
+            Capability::Keychain,
+            CheckStatus::Error,
+            format!("Credentials can't be stored in the keychain: {err:#}"),
+        ),
+    }
+}
+
+fn check_inotify_limits() -> CapabilityCheck {
+    if !cfg!(target_os = "linux") {
+        return CapabilityCheck::new(
+            Capability::InotifyLimits,
+            CheckStatus::NotApplicable,
+            "Only Linux limits how many directories may be watched",
+        );
+    }
+    let path = "/proc/sys/fs/inotify/max_user_watches";
+    match std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|limit| Ok(limit.trim().parse::<u64>()?))
+    {
+        Ok(limit) if limit >= MIN_INOTIFY_WATCHES => CapabilityCheck::new(
+            Capability::InotifyLimits,
+            CheckStatus::Ok,
+            format!("Up to {limit} directories can be watched"),
+        ),
+        Ok(limit) => CapabilityCheck::new(
+            Capability::InotifyLimits,
+            CheckStatus::Warning,
+            format!(
+                "Only {limit} directories can be watched, so changes in large projects may go unnoticed. \
+                 Raise 'fs.inotify.max_user_watches' to at least {MIN_INOTIFY_WATCHES}"
+            ),
+        ),
+        Err(err) => CapabilityCheck::new(
+            Capability::InotifyLimits,
+            CheckStatus::Warning,
+            format!("Couldn't read '{path}': {err:#}"),
+        ),
+    }
+}
+
+fn check_disk_space(app_data_dir: &Path) -> CapabilityCheck {
+    let app_data_dir =
+        gix::path::realpath(app_data_dir).unwrap_or_else(|_| app_data_dir.to_owned());
+    let Some(available) = free_disk_space(&app_data_dir) else {
+        return CapabilityCheck::new(
+            Capability::DiskSpace,
+            CheckStatus::Warning,
+            format!(
+                "Couldn't find the disk of '{}' to check its free space",
+                app_data_dir.display()
+            ),
+        );
+    };
+    let message = format!(
+        "{} MiB are free in '{}'",
+        available / (1024 * 1024),
+        app_data_dir.display()
+    );
+    CapabilityCheck::new(Capability::DiskSpace, disk_space_status(available), message)
+}
+
+/// Return the free space of the disk that `path` is on, or `None` if it can't be found.
+pub(crate) fn free_disk_space(path: &Path) -> Option<u64> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mount_points: Vec<_> = disks
+        .iter()
+        .map(|disk| (disk.mount_point(), disk.available_space()))
+        .collect();
+    available_space(&mount_points, path)
+}
+
+/// Return how concerning it is to have `available` bytes left in the app data directory.
+pub(crate) fn disk_space_status(available: u64) -> CheckStatus {
+    if available < CRITICAL_DISK_SPACE_BYTES {
+        CheckStatus::Error
+    } else if available < LOW_DISK_SPACE_BYTES {
+        CheckStatus::Warning
+    } else {
+        CheckStatus::Ok
+    }
+}
+
+/// Return the available space of the disk among `disks`, as `(mount point, available bytes)`, that `path` is on.
+fn available_space(disks: &[(&Path, u64)], path: &Path) -> Option<u64> {
+    disks
+        .iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, available)| *available)
+}
+
+pub mod commands {
+    use tauri::{AppHandle, Manager};
+    use tracing::instrument;
+
+    use super::CapabilityReport;
+    use crate::error::Error;
+
+    /// Check what the app relies on in its environment, like `git`, an SSH agent, the keychain and free disk space.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle), err(Debug))]
+    pub fn capability_report(app_handle: AppHandle) -> Result<CapabilityReport, Error> {
+        let app_data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(anyhow::Error::from)?;
+        Ok(super::capability_report(&app_data_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn available_space_is_of_the_innermost_disk() {
+        let disks = [
+            (Path::new("/"), 1),
+            (Path::new("/home"), 2),
+            (Path::new("/home/me/external"), 3),
+        ];
+        assert_eq!(available_space(&disks, Path::new("/home/me/data")), Some(2));
+        assert_eq!(available_space(&disks, Path::new("/var/lib")), Some(1));
+        assert_eq!(
+            available_space(&disks, Path::new("/home/me/external/x")),
+            Some(3)
+        );
+        assert_eq!(available_space(&disks[1..], Path::new("/var")), None);
+    }
+
+    #[test]
+    fn report_status_is_the_worst_of_all_checks() {
+        let check = |status| CapabilityCheck::new(Capability::Git, status, "");
+        let report = |statuses: &[CheckStatus]| CapabilityReport {
+            checks: statuses.iter().copied().map(check).collect(),
+        };
+        assert_eq!(
+            report(&[CheckStatus::Ok, CheckStatus::NotApplicable]).status(),
+            CheckStatus::Ok
+        );
+        assert_eq!(
+            report(&[CheckStatus::Warning, CheckStatus::Ok]).status(),
+            CheckStatus::Warning
+        );
+        assert_eq!(
+            report(&[CheckStatus::Warning, CheckStatus::Error]).status(),
+            CheckStatus::Error
+        );
+    }
+}
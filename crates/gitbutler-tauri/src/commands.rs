@@ -59,9 +59,7 @@ pub fn git_index_size(
     settings: State<'_, AppSettingsWithDiskSync>,
     project_id: ProjectId,
 ) -> Result<usize, Error> {
-    Ok(app
-        .git_index_size(project_id, settings.get()?.clone())
-        .expect("git index size"))
+    Ok(app.git_index_size(project_id, settings.get()?.clone())?)
 }
 
 #[tauri::command(async)]
@@ -1,4 +1,7 @@
-use gitbutler_config::{api::ProjectCommands, git::GbConfig};
+use gitbutler_config::{
+    api::ProjectCommands,
+    git::{GbConfig, GlobalGitConfig},
+};
 use gitbutler_project as projects;
 use gitbutler_project::ProjectId;
 use tauri::State;
@@ -27,3 +30,15 @@ pub fn set_gb_config(
         .set_gb_config(config)
         .map_err(Into::into)
 }
+
+#[tauri::command(async)]
+#[instrument(err(Debug))]
+pub fn get_git_config() -> Result<GlobalGitConfig, Error> {
+    gitbutler_config::git::global_git_config().map_err(Into::into)
+}
+
+#[tauri::command(async)]
+#[instrument(err(Debug))]
+pub fn set_git_config(config: GlobalGitConfig) -> Result<(), Error> {
+    gitbutler_config::git::set_global_git_config(config).map_err(Into::into)
+}
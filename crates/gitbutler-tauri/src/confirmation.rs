@@ -0,0 +1,255 @@
+//! Require an explicit confirmation for [destructive commands](DESTRUCTIVE_COMMANDS), so a bug in the frontend can't
+//! destroy data without the user having been asked first.
+//!
+//! After the user confirmed, the frontend obtains a token for the [`Action`] with
+//! [`commands::request_confirmation()`] and passes it as `confirmationToken` argument to the command, which is
+//! rejected without it. Tokens can only be used once, for the action they were requested for, and expire shortly.
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{ipc::Invoke, ipc::InvokeBody, Manager, Runtime};
+
+use crate::error::Error;
+
+/// How long a token may be used after it was requested.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// The destructive actions that need to be confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    /// Push with force, which may drop commits from the remote branch.
+    ForcePush,
+    /// Discard or overwrite uncommitted changes, or put them aside.
+    Discard,
+    /// Permanently remove snapshots or the data of the app.
+    PurgeHistory,
+    /// Delete branches.
+    DeleteBranch,
+    /// Delete tags.
+    DeleteTag,
+    /// Remove a value from the global git configuration.
+    RemoveGlobalConfig,
+}
+
+/// The commands that need a confirmation token, along with the action they perform.
+const DESTRUCTIVE_COMMANDS: &[(&str, Action)] = &[
+    ("push_stack", Action::ForcePush),
+    ("push_base_branch", Action::ForcePush),
+    ("discard_changes", Action::Discard),
+    ("reset_files", Action::Discard),
+    ("unapply_without_saving_virtual_branch", Action::Discard),
+    ("restore_directory_at", Action::Discard),
+    ("suspend_workspace", Action::Discard),
+    ("cleanup_history", Action::PurgeHistory),
+    ("delete_all_data", Action::PurgeHistory),
+    ("delete_local_branch", Action::DeleteBranch),
+    ("delete_branches", Action::DeleteBranch),
+    ("delete_tag", Action::DeleteTag),
+    ("git_remove_global_config", Action::RemoveGlobalConfig),
+];
+
+/// The tokens that were requested but not used yet.
+#[derive(Default)]
+pub struct Confirmations(parking_lot::Mutex<HashMap<String, (Action, Instant)>>);
+
+impl Confirmations {
+    /// Return a new token that confirms `action` once.
+    pub fn request(&self, action: Action) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut tokens = self.0.lock();
+        tokens.retain(|_, (_, requested)| requested.elapsed() < TOKEN_LIFETIME);
+        tokens.insert(token.clone(), (action, Instant::now()));
+        token
+    }
+
+    /// Use up `token`, and fail if it doesn't confirm `action`.
+    pub fn redeem(&self, token: &str, action: Action) -> Result<()> {
+        match self.0.lock().remove(token) {
+            Some((confirmed, requested))
+                if confirmed == action && requested.elapsed() < TOKEN_LIFETIME =>
+            {
+                Ok(())
+            }
+            Some((confirmed, _)) if confirmed != action => {
+                bail!("The confirmation was given for {confirmed:?}, not {action:?}")
+            }
+            Some(_) => bail!("The confirmation expired, please confirm again"),
+            None => bail!("The confirmation is unknown or was used already"),
+        }
+    }
+}
+
+/// Wrap `handler`, as created by [`tauri::generate_handler!`], to reject the invocations of
+/// [destructive commands](DESTRUCTIVE_COMMANDS) without a valid confirmation token before they run.
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        if let Err(err) = check(&invoke) {
+            invoke.resolver.reject(Error::from(err));
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// Redeem the confirmation token of `invoke` if it's a destructive command.
+fn check<R: Runtime>(invoke: &Invoke<R>) -> Result<()> {
+    let command = invoke.message.command();
+    let args = match invoke.message.payload() {
+        InvokeBody::Json(args) => args,
+        _ => &Value::Null,
+    };
+    let Some(action) = action(command, args) else {
+        return Ok(());
+    };
+    let Some(token) = args.get("confirmationToken").and_then(Value::as_str) else {
+        bail!(
+            "'{}' needs to be confirmed first",
+            command.replace('_', " ")
+        );
+    };
+    invoke
+        .message
+        .webview_ref()
+        .state::<Confirmations>()
+        .redeem(token, action)
+}
+
+/// Return the action that `command` performs with `args`, if it's destructive.
+fn action(command: &str, args: &Value) -> Option<Action> {
+    let action = DESTRUCTIVE_COMMANDS
+        .iter()
+        .find_map(|(name, action)| (*name == command).then_some(*action))?;
+    if action == Action::ForcePush && args.get("withForce").and_then(Value::as_bool) != Some(true) {
+        return None;
+    }
+    Some(action)
+}
+
+pub mod commands {
+    use tauri::State;
+    use tracing::instrument;
+
+    use super::{Action, Confirmations};
+    use crate::error::Error;
+
+    /// Return a token that lets the commands performing `action` run once, to be requested after the user
+    /// confirmed it.
+    #[tauri::command(async)]
+    #[instrument(skip(confirmations), err(Debug))]
+    pub fn request_confirmation(
+        confirmations: State<'_, Confirmations>,
+        action: Action,
+    ) -> Result<String, Error> {
+        Ok(confirmations.request(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn tokens_confirm_their_action_once() {
+        let confirmations = Confirmations::default();
+        let token = confirmations.request(Action::Discard);
+        assert!(confirmations.redeem(&token, Action::DeleteBranch).is_err());
+
+        let token = confirmations.request(Action::Discard);
+        assert!(confirmations.redeem(&token, Action::Discard).is_ok());
+        assert!(confirmations.redeem(&token, Action::Discard).is_err());
+        assert!(confirmations.redeem("made-up", Action::Discard).is_err());
+    }
+
+    #[test]
+    fn only_forced_pushes_are_destructive() {
+        assert_eq!(
+            action("push_stack", &json!({"withForce": true})),
+            Some(Action::ForcePush)
+        );
+        assert_eq!(action("push_stack", &json!({"withForce": false})), None);
+        assert_eq!(action("push_base_branch", &json!({})), None);
+        assert_eq!(
+            action("delete_branches", &json!({})),
+            Some(Action::DeleteBranch)
+        );
+        assert_eq!(action("list_virtual_branches", &json!({})), None);
+    }
+
+    /// The registered commands whose names suggest that they destroy data, even though what they change can be
+    /// undone or is kept elsewhere.
+    const NOT_DESTRUCTIVE: &[&str] = &[
+        // Deleted projects can be brought back with `undo_delete_project`.
+        "delete_project",
+        "undo_delete_project",
+        // Only forgets the credentials of the user, who can sign in again.
+        "delete_user",
+        "remove_bookmark",
+        // Only the configuration of the remote is removed, local branches stay.
+        "remove_remote",
+        // The commits of the series stay in the stack.
+        "remove_series",
+        // The commits are undone into uncommitted changes.
+        "reset_virtual_branch",
+        // A snapshot is taken first, so it can be undone.
+        "restore_snapshot",
+        // Only ever fast-forwards the local oplog.
+        "restore_history_from_remote",
+    ];
+
+    /// Return the names of the commands registered with `generate_handler!` in `main.rs`.
+    fn registered_commands() -> Vec<&'static str> {
+        let (_, handlers) = include_str!("main.rs")
+            .split_once("generate_handler![")
+            .expect("commands are registered");
+        handlers
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !line.starts_with(']'))
+            .filter(|line| !line.starts_with("//") && !line.starts_with("#["))
+            .filter_map(|line| line.trim_end_matches(',').rsplit("::").next())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn destructive_commands_are_registered_and_complete() {
+        let registered = registered_commands();
+        assert!(registered.contains(&"request_confirmation"));
+        for (name, _) in DESTRUCTIVE_COMMANDS {
+            assert!(
+                registered.contains(name),
+                "'{name}' isn't a registered command"
+            );
+        }
+
+        let destructive_verbs = [
+            "delete_", "discard_", "remove_", "reset_", "restore_", "cleanup_", "purge_",
+            "suspend_",
+        ];
+        for name in registered {
+            let sounds_destructive = destructive_verbs
+                .iter()
+                .any(|verb| name.starts_with(verb) || name.contains(&format!("_{verb}")))
+                || name.contains("without_saving");
+            if sounds_destructive {
+                assert!(
+                    DESTRUCTIVE_COMMANDS
+                        .iter()
+                        .any(|(destructive, _)| *destructive == name)
+                        || NOT_DESTRUCTIVE.contains(&name),
+                    "'{name}' needs to be confirmed, or be listed as not destructive"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Write a report for each panic into the app data directory, so users can attach it when filing a bug even if
+//! the app went down with it.
+use std::{
+    backtrace::Backtrace,
+    fs,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The directory, relative to the app data directory, holding one directory per crash report.
+pub const CRASH_REPORTS_DIR: &str = "crash-reports";
+/// The name of the file with the report within its directory.
+const REPORT_FILE: &str = "report.json";
+/// The amount of lines of the latest log file to include in a report.
+const LOG_LINES: usize = 200;
+/// The amount of reports to keep, the oldest ones are removed when a new one is written.
+const KEEP_REPORTS: usize = 20;
+
+/// What is known about a panic, as written by the hook installed with [`install_panic_hook()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    /// The name of the directory of the report, which starts with the time of the crash.
+    pub id: String,
+    /// When the panic happened, in milliseconds since the Unix epoch.
+    pub created_at_ms: u128,
+    pub app_version: String,
+    /// The panic message.
+    pub message: String,
+    /// The source location of the panic, like `src/file.rs:10:5`.
+    pub location: Option<String>,
+    /// The name of the panicking thread, if it has one.
+    pub thread: Option<String>,
+    pub backtrace: String,
+    /// The last lines of the log file, oldest first.
+    pub log_lines: Vec<String>,
+    /// Whether the report was archived for submission with [`commands::submit_crash_report()`].
+    #[serde(default)]
+    pub submitted: bool,
+}
+
+/// Write a [`CrashReport`] into `app_data_dir` on each panic, including the tail of the newest log file in
+/// `logs_dir`, before calling the previously installed hook.
+pub fn install_panic_hook(app_data_dir: PathBuf, logs_dir: PathBuf, app_version: String) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_crash_report(&app_data_dir, &logs_dir, &app_version, info) {
+            Ok(dir) => tracing::error!(report = %dir.display(), "wrote crash report"),
+            Err(err) => tracing::error!(?err, "failed to write crash report"),
+        }
+        previous(info);
+    }));
+}
+
+/// Return all crash reports in `app_data_dir`, newest first.
+pub fn list_crash_reports(app_data_dir: &Path) -> Result<Vec<CrashReport>> {
+    let mut reports = Vec::new();
+    for id in report_ids(&app_data_dir.join(CRASH_REPORTS_DIR))? {
+        match read_crash_report(app_data_dir, &id) {
+            Ok(report) => reports.push(report),
+            Err(err) => tracing::warn!(?err, id, "ignoring unreadable crash report"),
+        }
+    }
+    reports.reverse();
+    Ok(reports)
+}
+
+fn read_crash_report(app_data_dir: &Path, id: &str) -> Result<CrashReport> {
+    let path = app_data_dir
+        .join(CRASH_REPORTS_DIR)
+        .join(id)
+        .join(REPORT_FILE);
+    let content = fs::read(&path)
+        .with_context(|| format!("failed to read crash report at '{}'", path.display()))?;
+    Ok(serde_json::from_slice(&content)?)
+}
+
+fn mark_submitted(app_data_dir: &Path, id: &str) -> Result<()> {
+    let mut report = read_crash_report(app_data_dir, id)?;
+    if report.submitted {
+        return Ok(());
+    }
+    report.submitted = true;
+    let path = app_data_dir
+        .join(CRASH_REPORTS_DIR)
+        .join(id)
+        .join(REPORT_FILE);
+    fs::write(path, serde_json::to_vec_pretty(&report)?)?;
+    Ok(())
+}
+
+fn write_crash_report(
+    app_data_dir: &Path,
+    logs_dir: &Path,
+    app_version: &str,
+    info: &PanicHookInfo<'_>,
+) -> Result<PathBuf> {
+    let created_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_owned()
+    };
+    let reports_dir = app_data_dir.join(CRASH_REPORTS_DIR);
+    let id = format!("{created_at_ms}-{}", std::process::id());
+    let report = CrashReport {
+        id: id.clone(),
+        created_at_ms,
+        app_version: app_version.to_owned(),
+        message,
+        location: info.location().map(ToString::to_string),
+        thread: std::thread::current().name().map(ToOwned::to_owned),
+        backtrace: Backtrace::force_capture().to_string(),
+        log_lines: crate::logs::tail(logs_dir, LOG_LINES, None).unwrap_or_default(),
+        submitted: false,
+    };
+    let dir = reports_dir.join(id);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(REPORT_FILE), serde_json::to_vec_pretty(&report)?)?;
+    for old in report_ids(&reports_dir)?.iter().rev().skip(KEEP_REPORTS) {
+        fs::remove_dir_all(reports_dir.join(old))?;
+    }
+    Ok(dir)
+}
+
+/// Return the names of the report directories in `reports_dir`, oldest first.
+fn report_ids(reports_dir: &Path) -> Result<Vec<String>> {
+    let entries = match fs::read_dir(reports_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut ids = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                ids.push(name.to_owned());
+            }
+        }
+    }
+    // Names start with the time of the crash.
+    ids.sort_by_key(|id| {
+        id.split_once('-')
+            .and_then(|(millis, _)| millis.parse::<u128>().ok())
+    });
+    Ok(ids)
+}
+
+pub mod commands {
+    use std::path::PathBuf;
+
+    use anyhow::Context;
+    use gitbutler_error::{error, error::Code};
+    use gitbutler_feedback::Archival;
+    use tauri::{AppHandle, Manager, State};
+    use tracing::instrument;
+
+    use super::{CrashReport, CRASH_REPORTS_DIR};
+    use crate::error::Error;
+
+    /// Return all crash reports, newest first.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle), err(Debug))]
+    pub fn list_crash_reports(app_handle: AppHandle) -> Result<Vec<CrashReport>, Error> {
+        let app_data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .context("missing app data dir")?;
+        Ok(super::list_crash_reports(&app_data_dir)?)
+    }
+
+    /// Archive the crash report with `id` for attaching it to a bug report, mark it as submitted, and return the
+    /// path to the archive.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle, archival), err(Debug))]
+    pub fn submit_crash_report(
+        app_handle: AppHandle,
+        archival: State<'_, Archival>,
+        id: String,
+    ) -> Result<PathBuf, Error> {
+        let app_data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .context("missing app data dir")?;
+        let reports_dir = app_data_dir.join(CRASH_REPORTS_DIR);
+        if !super::report_ids(&reports_dir)?.contains(&id) {
+            return Err(anyhow::anyhow!("unknown crash report '{id}'")
+                .context(error::Context::new_static(
+                    Code::Validation,
+                    "The crash report doesn't exist",
+                ))
+                .into());
+        }
+        let archive = archival.archive_dir(&reports_dir.join(&id))?;
+        super::mark_submitted(&app_data_dir, &id)?;
+        Ok(archive)
+    }
+}
@@ -0,0 +1,267 @@
+//! Open projects from links, which editors and terminals use to offer "Open in GitButler":
+//!
+//! * `gitbutler://project/<id>` activates the project with `id`.
+//! * `gitbutler://open?path=<worktree>` activates the project with the worktree at the absolute `path`. If it isn't
+//!   a project yet, the user is asked whether to add it, as anything can send links.
+//!
+//! The operating system starts the app with the link as argument, which is handed to the running instance if there
+//! is one, except on macOS where the running app receives it as [`tauri::RunEvent::Opened`]. Either way the window
+//! is focused and learns about the project to show with a `deep-link://open-project` event, or about the worktree
+//! to offer adding with a `deep-link://add-project` event. If the link started the app, the window calls
+//! [`commands::take_deep_link_project()`] instead.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_project::{Controller, ProjectId};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+use url::Url;
+
+/// The URL scheme of links handled by the app.
+pub const SCHEME: &str = "gitbutler";
+
+const OPEN_PROJECT_EVENT: &str = "deep-link://open-project";
+const ADD_PROJECT_EVENT: &str = "deep-link://add-project";
+
+/// What a link asks the app to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    /// Show the project with this id.
+    Project(ProjectId),
+    /// Show the project with the worktree at this absolute path, offering to add it if needed.
+    Open(PathBuf),
+}
+
+/// What the window is asked to do for a link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LinkTarget {
+    /// Show the project with `project_id`.
+    #[serde(rename_all = "camelCase")]
+    Project { project_id: ProjectId },
+    /// Ask the user whether to add the worktree at `path` as project, and show it if so.
+    AddProject { path: PathBuf },
+}
+
+impl DeepLink {
+    pub fn parse(url: &Url) -> Result<Self> {
+        if url.scheme() != SCHEME {
+            bail!("'{url}' isn't a {SCHEME}:// link");
+        }
+        match url.host_str() {
+            Some("project") => {
+                let id = url.path().trim_matches('/');
+                let id = id
+                    .parse()
+                    .with_context(|| format!("'{id}' isn't a valid project id"))?;
+                Ok(DeepLink::Project(id))
+            }
+            Some("open") => {
+                let Some((_, path)) = url.query_pairs().find(|(name, _)| name == "path") else {
+                    bail!("'{url}' doesn't say which path to open");
+                };
+                let path = PathBuf::from(path.into_owned());
+                if !path.is_absolute() {
+                    bail!("'{}' isn't an absolute path", path.display());
+                }
+                Ok(DeepLink::Open(path))
+            }
+            _ => bail!("'{url}' isn't a known link"),
+        }
+    }
+}
+
+/// Return the first link among the command-line `args` the app was started with.
+pub fn find_in_args<S: AsRef<str>>(args: &[S]) -> Option<Url> {
+    args.iter()
+        .filter_map(|arg| Url::parse(arg.as_ref()).ok())
+        .find(|url| url.scheme() == SCHEME)
+}
+
+/// What to do for the link the app was started with once the window is ready, as it came before the window could
+/// listen for it.
+#[derive(Default)]
+pub struct PendingProject(parking_lot::Mutex<Option<LinkTarget>>);
+
+/// Handle `url` for the app started with it, before its window is ready.
+pub fn open_on_start(app_handle: &AppHandle, url: &Url) {
+    match resolve(app_handle, url) {
+        Ok(target) => {
+            *app_handle.state::<PendingProject>().0.lock() = Some(target);
+        }
+        Err(err) => tracing::warn!(?err, %url, "failed to open link"),
+    }
+}
+
+/// Handle `url` in the running app, like when it was launched again with it or received it from the system.
+pub fn open(app_handle: &AppHandle, url: &Url) {
+    match resolve(app_handle, url) {
+        Ok(target) => show(app_handle, target),
+        Err(err) => tracing::warn!(?err, %url, "failed to open link"),
+    }
+}
+
+/// Bring the window of the app to the front and have it show the project with `project_id`.
+pub fn show_project(app_handle: &AppHandle, project_id: ProjectId) {
+    show(app_handle, LinkTarget::Project { project_id });
+}
+
+/// Bring the window of the app to the front and have it do what `target` asks for.
+fn show(app_handle: &AppHandle, target: LinkTarget) {
+    if app_handle.webview_windows().is_empty() {
+        // The window that is about to be created asks for the target once it's ready.
+        *app_handle.state::<PendingProject>().0.lock() = Some(target);
+        focus_window(app_handle);
+        return;
+    }
+    let Some(window) = focus_window(app_handle) else {
+        return;
+    };
+    let sent = match target {
+        LinkTarget::Project { project_id } => {
+            app_handle.emit_to(window.label(), OPEN_PROJECT_EVENT, project_id)
+        }
+        LinkTarget::AddProject { path } => {
+            app_handle.emit_to(window.label(), ADD_PROJECT_EVENT, path)
+        }
+    };
+    if let Err(err) = sent {
+        tracing::warn!(?err, "failed to send link to the window");
+    }
+}
+
+/// Bring the window of the app to the front, like when it was launched again, and return it. If all windows were
+/// closed while recording in the background, the main window is created again.
+pub fn focus_window(app_handle: &AppHandle) -> Option<WebviewWindow> {
+    let existing = app_handle
+        .get_webview_window("main")
+        .or_else(|| app_handle.webview_windows().into_values().next());
+    let window = match existing {
+        Some(window) => window,
+        None => match crate::window::create(app_handle, "main", "index.html".into()) {
+            Ok(window) => window,
+            Err(err) => {
+                tracing::error!(?err, "failed to create window");
+                return None;
+            }
+        },
+    };
+    if let Err(err) = window
+        .unminimize()
+        .and_then(|()| window.show())
+        .and_then(|()| window.set_focus())
+    {
+        tracing::warn!(?err, "failed to focus window");
+    }
+    Some(window)
+}
+
+/// Return what to do for `url`, which only ever shows existing projects. Worktrees that aren't projects yet are
+/// left to the user to add, as links can come from anywhere.
+fn resolve(app_handle: &AppHandle, url: &Url) -> Result<LinkTarget> {
+    let projects = app_handle.state::<Controller>();
+    match DeepLink::parse(url)? {
+        DeepLink::Project(id) => Ok(LinkTarget::Project {
+            project_id: projects.get(id)?.id,
+        }),
+        DeepLink::Open(path) => {
+            let path = realpath(&path);
+            let existing = projects.list()?.into_iter().find(|project| {
+                !project.is_subproject() && !project.is_deleted() && realpath(&project.path) == path
+            });
+            Ok(match existing {
+                Some(project) => LinkTarget::Project {
+                    project_id: project.id,
+                },
+                None => LinkTarget::AddProject { path },
+            })
+        }
+    }
+}
+
+fn realpath(path: &Path) -> PathBuf {
+    gix::path::realpath(path).unwrap_or_else(|_| path.to_owned())
+}
+
+pub mod commands {
+    use tauri::State;
+    use tracing::instrument;
+
+    use super::{LinkTarget, PendingProject};
+    use crate::error::Error;
+
+    /// Return what a link asked for before the window was ready, once.
+    #[tauri::command(async)]
+    #[instrument(skip(pending), err(Debug))]
+    pub fn take_deep_link_project(
+        pending: State<'_, PendingProject>,
+    ) -> Result<Option<LinkTarget>, Error> {
+        Ok(pending.0.lock().take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Result<DeepLink> {
+        DeepLink::parse(&Url::parse(url).expect("valid URL"))
+    }
+
+    #[test]
+    fn project_links() {
+        let id = ProjectId::generate();
+        assert_eq!(
+            parse(&format!("gitbutler://project/{id}")).unwrap(),
+            DeepLink::Project(id)
+        );
+        assert_eq!(
+            parse(&format!("gitbutler://project/{id}/")).unwrap(),
+            DeepLink::Project(id)
+        );
+        assert!(parse("gitbutler://project/not-an-id").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn open_links() {
+        assert_eq!(
+            parse("gitbutler://open?path=%2Fhome%2Fme%2Fmy%20repo").unwrap(),
+            DeepLink::Open("/home/me/my repo".into())
+        );
+        assert!(parse("gitbutler://open?path=relative").is_err());
+        assert!(parse("gitbutler://open").is_err());
+    }
+
+    #[test]
+    fn targets_are_tagged_for_the_window() {
+        let project_id = ProjectId::generate();
+        assert_eq!(
+            serde_json::to_value(LinkTarget::Project { project_id }).unwrap(),
+            serde_json::json!({ "type": "project", "projectId": project_id })
+        );
+        assert_eq!(
+            serde_json::to_value(LinkTarget::AddProject {
+                path: "/tmp/repo".into()
+            })
+            .unwrap(),
+            serde_json::json!({ "type": "addProject", "path": "/tmp/repo" })
+        );
+    }
+
+    #[test]
+    fn other_links() {
+        assert!(parse("https://project/x").is_err());
+        assert!(parse("gitbutler://settings").is_err());
+    }
+
+    #[test]
+    fn links_in_args() {
+        let args = ["/usr/bin/gitbutler", "--flag", "gitbutler://open?path=/tmp"];
+        assert_eq!(
+            find_in_args(&args).map(String::from),
+            Some("gitbutler://open?path=/tmp".to_owned())
+        );
+        assert_eq!(find_in_args(&["/usr/bin/gitbutler"]), None);
+    }
+}
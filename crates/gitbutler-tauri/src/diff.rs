@@ -1,14 +1,17 @@
 use crate::error::Error;
 use crate::from_json::HexHash;
+use crate::in_blocking_thread;
 use but_core::ui::{TreeChange, WorktreeChanges};
 use gitbutler_project::ProjectId;
+use gix::bstr::ByteSlice;
+use std::path::PathBuf;
 use tracing::instrument;
 
 /// Provide a unified diff for `change`, but fail if `change` is a [type-change](but_core::ModeFlags::TypeChange)
 /// or if it involves a change to a [submodule](gix::object::Kind::Commit).
 #[tauri::command(async)]
 #[instrument(skip(projects, change, settings), err(Debug))]
-pub fn tree_change_diffs(
+pub async fn tree_change_diffs(
     projects: tauri::State<'_, gitbutler_project::Controller>,
     settings: tauri::State<'_, but_settings::AppSettingsWithDiskSync>,
     project_id: ProjectId,
@@ -16,27 +19,31 @@ pub fn tree_change_diffs(
 ) -> anyhow::Result<but_core::UnifiedDiff, Error> {
     let change: but_core::TreeChange = change.into();
     let project = projects.get(project_id)?;
-    let repo = gix::open(project.path).map_err(anyhow::Error::from)?;
-    change
-        .unified_diff(&repo, settings.get()?.context_lines)
-        .map_err(Into::into)
+    let context_lines = settings.get()?.context_lines;
+    in_blocking_thread(move || {
+        let repo = gix::open(project.path)?;
+        change.unified_diff(&repo, context_lines)
+    })
+    .await
 }
 
 #[tauri::command(async)]
 #[instrument(skip(projects), err(Debug))]
-pub fn commit_changes(
+pub async fn commit_changes(
     projects: tauri::State<'_, gitbutler_project::Controller>,
     project_id: ProjectId,
     old_commit_id: Option<HexHash>,
     new_commit_id: HexHash,
 ) -> anyhow::Result<Vec<TreeChange>, Error> {
     let project = projects.get(project_id)?;
-    but_core::diff::ui::commit_changes_by_worktree_dir(
-        project.path,
-        old_commit_id.map(Into::into),
-        new_commit_id.into(),
-    )
-    .map_err(Into::into)
+    in_blocking_thread(move || {
+        but_core::diff::ui::commit_changes_by_worktree_dir(
+            project.path,
+            old_commit_id.map(Into::into),
+            new_commit_id.into(),
+        )
+    })
+    .await
 }
 
 /// This UI-version of [`but_core::diff::worktree_changes()`] simplifies the `git status` information for display in
@@ -50,12 +57,67 @@ pub fn commit_changes(
 /// All ignored status changes are also provided so they can be displayed separately.
 #[tauri::command(async)]
 #[instrument(skip(projects), err(Debug))]
-pub fn worktree_changes(
+pub async fn worktree_changes(
     projects: tauri::State<'_, gitbutler_project::Controller>,
     project_id: ProjectId,
 ) -> anyhow::Result<WorktreeChanges, Error> {
     let project = projects.get(project_id)?;
-    Ok(but_core::diff::ui::worktree_changes_by_worktree_dir(
-        project.path,
-    )?)
+    in_blocking_thread(move || but_core::diff::ui::worktree_changes_by_worktree_dir(project.path))
+        .await
+}
+
+/// The token budget of [`commit_context()`] if none is given.
+const DEFAULT_COMMIT_CONTEXT_TOKENS: usize = 4000;
+
+/// Summarize the uncommitted changes to `paths`, or to all files if empty, in a form that is suitable for having a
+/// language model draft a commit message, using at most `token_budget` tokens.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub async fn commit_context(
+    projects: tauri::State<'_, gitbutler_project::Controller>,
+    settings: tauri::State<'_, but_settings::AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    paths: Vec<PathBuf>,
+    token_budget: Option<usize>,
+) -> anyhow::Result<but_core::diff::CommitContext, Error> {
+    let project = projects.get(project_id)?;
+    let context_lines = settings.get()?.context_lines;
+    in_blocking_thread(move || {
+        let repo = gix::open(project.path)?;
+        let changes: Vec<_> = but_core::diff::worktree_changes(&repo)?
+            .changes
+            .into_iter()
+            .filter(|change| {
+                paths.is_empty()
+                    || paths.iter().any(|path| {
+                        gix::path::from_bstr(change.path.as_bstr()).as_ref() == path.as_path()
+                    })
+            })
+            .collect();
+        but_core::diff::commit_context(
+            &repo,
+            &changes,
+            context_lines,
+            token_budget.unwrap_or(DEFAULT_COMMIT_CONTEXT_TOKENS),
+        )
+    })
+    .await
+}
+
+/// Compare `old` with `new` in units of `granularity`, like words for highlighting the changes within lines,
+/// and return the segments that are equal, removed or added, in order.
+#[tauri::command(async)]
+#[instrument(skip(old, new), err(Debug))]
+pub fn compute_diff(
+    old: String,
+    new: String,
+    granularity: but_core::diff::Granularity,
+    algorithm: Option<but_core::diff::DiffAlgorithm>,
+) -> anyhow::Result<Vec<but_core::diff::DiffSegment>, Error> {
+    Ok(but_core::diff::compute_diff(
+        &old,
+        &new,
+        granularity,
+        algorithm.unwrap_or_default(),
+    ))
 }
@@ -1,3 +1,20 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use gitbutler_forge::{
+    forge::{Forge, ForgeName, NewPullRequest, RemoteForge},
+    pull_request::{CiStatus, PullRequest, PullRequestStatus},
+};
+use gitbutler_project::ProjectId;
+use gitbutler_secret::{secret, Sensitive};
+use gitbutler_stack::{StackId, VirtualBranchesHandle};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
 pub mod commands {
     use std::path::Path;
 
@@ -15,6 +32,8 @@ pub mod commands {
 
     use crate::error::Error;
 
+    use super::{BranchForgeStatus, CiStatus, NewPullRequest, PullRequest};
+
     #[tauri::command(async)]
     #[instrument(skip(projects), err(Debug))]
     pub fn get_available_review_templates(
@@ -53,4 +72,302 @@ pub mod commands {
             .content
             .context("PR template was not valid UTF-8")?)
     }
+
+    /// Return the status of the pull requests of all branches in the workspace that have one.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub async fn branch_forge_status(
+        projects: State<'_, Controller>,
+        users: State<'_, gitbutler_user::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<BranchForgeStatus>, Error> {
+        Ok(super::branch_forge_status(&projects, &users, project_id).await?)
+    }
+
+    /// List the open pull requests, or merge requests, of the repository that `origin` points to.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub async fn list_pull_requests(
+        projects: State<'_, Controller>,
+        users: State<'_, gitbutler_user::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<PullRequest>, Error> {
+        Ok(super::list_pull_requests(&projects, &users, project_id).await?)
+    }
+
+    /// Open a pull request, or merge request, to merge the branch `head` into `base` in the repository that
+    /// `origin` points to.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, users, body), err(Debug))]
+    pub async fn create_pull_request(
+        projects: State<'_, Controller>,
+        users: State<'_, gitbutler_user::Controller>,
+        project_id: ProjectId,
+        head: String,
+        base: String,
+        title: String,
+        body: String,
+    ) -> Result<PullRequest, Error> {
+        let pull_request = NewPullRequest {
+            head: &head,
+            base: &base,
+            title: &title,
+            body: &body,
+        };
+        Ok(super::create_pull_request(&projects, &users, project_id, &pull_request).await?)
+    }
+
+    /// Return the checks that ran on the commit that `reference` points to, which may be anything Git can
+    /// resolve to a commit, like a branch name or a commit id.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub async fn ci_status(
+        projects: State<'_, Controller>,
+        users: State<'_, gitbutler_user::Controller>,
+        project_id: ProjectId,
+        reference: String,
+    ) -> Result<CiStatus, Error> {
+        let project = projects.get_validated(project_id)?;
+        let sha = git2::Repository::open(&project.path)
+            .and_then(|repo| Ok(repo.revparse_single(&reference)?.peel_to_commit()?.id()))
+            .with_context(|| format!("'{reference}' doesn't point to a commit"))?;
+        Ok(super::ci_status(&projects, &users, project_id, sha).await?)
+    }
+}
+
+/// The status of the pull request of a branch in the workspace.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchForgeStatus {
+    pub stack_id: StackId,
+    pub branch_name: String,
+    pub pull_request: PullRequestStatus,
+}
+
+/// Query the forge for the pull requests of all branches in the workspace of `project_id`.
+///
+/// Branches whose status can't be obtained are skipped.
+pub async fn branch_forge_status(
+    projects: &gitbutler_project::Controller,
+    users: &gitbutler_user::Controller,
+    project_id: ProjectId,
+) -> anyhow::Result<Vec<BranchForgeStatus>> {
+    let project = projects.get_validated(project_id)?;
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let Ok(target) = vb_state.get_default_target() else {
+        return Ok(Vec::new());
+    };
+    let remote_url = {
+        let repo = git2::Repository::open(&project.path)?;
+        let remote = repo.find_remote(&target.push_remote_name())?;
+        remote.url().map(ToOwned::to_owned)
+    };
+    let Some(forge) = remote_url.as_deref().and_then(RemoteForge::from_remote_url) else {
+        return Ok(Vec::new());
+    };
+    let token = forge_token(users, &forge)?;
+
+    let client = reqwest::Client::new();
+    let mut statuses = Vec::new();
+    for stack in vb_state.list_stacks_in_workspace()? {
+        for branch in stack.branches() {
+            let Some(number) = branch.pr_number.filter(|_| !branch.archived) else {
+                continue;
+            };
+            match forge
+                .pull_request_status(
+                    &client,
+                    token.as_ref().map(|token| token.0.as_str()),
+                    number,
+                )
+                .await
+            {
+                Ok(pull_request) => statuses.push(BranchForgeStatus {
+                    stack_id: stack.id,
+                    branch_name: branch.name,
+                    pull_request,
+                }),
+                Err(err) => {
+                    tracing::warn!(
+                        ?err,
+                        branch = branch.name,
+                        number,
+                        "failed to query pull request status"
+                    )
+                }
+            }
+        }
+    }
+    Ok(statuses)
+}
+
+/// The handle of the GitLab access token in the global namespace of the keychain.
+pub const GITLAB_ACCESS_TOKEN_HANDLE: &str = "gitlab_access_token";
+
+/// The forge that the `origin` remote of `project_id` points to.
+fn origin_forge(
+    projects: &gitbutler_project::Controller,
+    project_id: ProjectId,
+) -> anyhow::Result<RemoteForge> {
+    let project = projects.get_validated(project_id)?;
+    let repo = git2::Repository::open(&project.path)?;
+    let remote = repo
+        .find_remote("origin")
+        .context("The project has no 'origin' remote")?;
+    remote
+        .url()
+        .and_then(RemoteForge::from_remote_url)
+        .context("The 'origin' remote isn't hosted on a supported forge")
+}
+
+/// The access token for `forge`, as stored in the keychain. The GitHub token belongs to the logged-in user.
+fn forge_token(
+    users: &gitbutler_user::Controller,
+    forge: &RemoteForge,
+) -> anyhow::Result<Option<Sensitive<String>>> {
+    match forge.name() {
+        ForgeName::GitLab => {
+            secret::retrieve(GITLAB_ACCESS_TOKEN_HANDLE, secret::Namespace::Global)
+        }
+        _ => Ok(users
+            .get_user()?
+            .and_then(|user| user.github_access_token().ok().flatten())),
+    }
+}
+
+pub async fn list_pull_requests(
+    projects: &gitbutler_project::Controller,
+    users: &gitbutler_user::Controller,
+    project_id: ProjectId,
+) -> anyhow::Result<Vec<PullRequest>> {
+    let forge = origin_forge(projects, project_id)?;
+    let token = forge_token(users, &forge)?;
+    forge
+        .list_pull_requests(
+            &reqwest::Client::new(),
+            token.as_ref().map(|token| token.0.as_str()),
+        )
+        .await
+}
+
+pub async fn create_pull_request(
+    projects: &gitbutler_project::Controller,
+    users: &gitbutler_user::Controller,
+    project_id: ProjectId,
+    pull_request: &NewPullRequest<'_>,
+) -> anyhow::Result<PullRequest> {
+    let forge = origin_forge(projects, project_id)?;
+    let token = forge_token(users, &forge)?.with_context(|| {
+        format!(
+            "Log in to {:?} or store an access token to create pull requests",
+            forge.name()
+        )
+    })?;
+    forge
+        .create_pull_request(&reqwest::Client::new(), &token.0, pull_request)
+        .await
+}
+
+/// Query the pull requests of the workspace of `project_id` without blocking, and send them to the frontend
+/// as `project://<id>/forge-status`.
+///
+/// This is done after each fetch, which the frontend already does periodically, so the
+/// branch views keep up with CI and reviews without additional polling.
+pub fn emit_branch_forge_status_in_background(app: &tauri::AppHandle, project_id: ProjectId) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let projects = app.state::<gitbutler_project::Controller>().inner().clone();
+        let users = app.state::<gitbutler_user::Controller>().inner().clone();
+        match branch_forge_status(&projects, &users, project_id).await {
+            Ok(statuses) if statuses.is_empty() => {}
+            Ok(statuses) => {
+                if let Err(err) =
+                    app.emit(&format!("project://{project_id}/forge-status"), statuses)
+                {
+                    tracing::warn!(?err, "failed to emit pull request status");
+                }
+            }
+            Err(err) => tracing::warn!(?err, "failed to query pull request status"),
+        }
+    });
+}
+
+/// How long the checks of a commit are reused before the forge is asked again.
+const CI_STATUS_TTL: Duration = Duration::from_secs(30);
+
+/// How long to wait after a push before querying the checks, to give the forge time to start them.
+const CI_STATUS_PUSH_DELAY: Duration = Duration::from_secs(10);
+
+static CI_STATUS_CACHE: LazyLock<Mutex<HashMap<(ProjectId, git2::Oid), (Instant, CiStatus)>>> =
+    LazyLock::new(Default::default);
+
+/// Return the checks that ran on the commit `sha`, as cached for [`CI_STATUS_TTL`].
+pub async fn ci_status(
+    projects: &gitbutler_project::Controller,
+    users: &gitbutler_user::Controller,
+    project_id: ProjectId,
+    sha: git2::Oid,
+) -> anyhow::Result<CiStatus> {
+    let key = (project_id, sha);
+    if let Some((at, status)) = CI_STATUS_CACHE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&key)
+    {
+        if at.elapsed() < CI_STATUS_TTL {
+            return Ok(status.clone());
+        }
+    }
+
+    let forge = origin_forge(projects, project_id)?;
+    let token = forge_token(users, &forge)?;
+    let status = forge
+        .ci_status(
+            &reqwest::Client::new(),
+            token.as_ref().map(|token| token.0.as_str()),
+            &sha.to_string(),
+        )
+        .await?;
+    CI_STATUS_CACHE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(key, (Instant::now(), status.clone()));
+    Ok(status)
+}
+
+/// The payload of the `project://<id>/ci-status` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitCiStatus {
+    sha: String,
+    status: CiStatus,
+}
+
+/// Query the checks of the commit `sha` again once the forge had time to start them after it was pushed, and
+/// send them to the frontend as `project://<id>/ci-status`.
+pub fn emit_ci_status_after_push(app: &tauri::AppHandle, project_id: ProjectId, sha: git2::Oid) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(CI_STATUS_PUSH_DELAY).await;
+        CI_STATUS_CACHE
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&(project_id, sha));
+        let projects = app.state::<gitbutler_project::Controller>().inner().clone();
+        let users = app.state::<gitbutler_user::Controller>().inner().clone();
+        match ci_status(&projects, &users, project_id, sha).await {
+            Ok(status) => {
+                if let Err(err) = app.emit(
+                    &format!("project://{project_id}/ci-status"),
+                    CommitCiStatus {
+                        sha: sha.to_string(),
+                        status,
+                    },
+                ) {
+                    tracing::warn!(?err, "failed to emit CI status");
+                }
+            }
+            Err(err) => tracing::warn!(?err, %sha, "failed to query CI status"),
+        }
+    });
 }
@@ -0,0 +1,33 @@
+use gitbutler_project as projects;
+use gitbutler_project::{AuthKey, ProjectId};
+use tauri::State;
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// Return the public key of the GitButler key pair in OpenSSH format, generating it on first use.
+///
+/// It's meant to be registered with forges to authenticate pushes and fetches.
+#[tauri::command(async)]
+#[instrument(skip(keys), err(Debug))]
+pub fn get_public_key(keys: State<'_, gitbutler_keys::Controller>) -> Result<String, Error> {
+    Ok(keys.get_or_create()?)
+}
+
+/// Make the project with `project_id` authenticate with the GitButler key pair, generating it if needed.
+#[tauri::command(async)]
+#[instrument(skip(keys, projects), err(Debug))]
+pub fn use_generated_key(
+    keys: State<'_, gitbutler_keys::Controller>,
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<projects::Project, Error> {
+    keys.get_or_create()?;
+    Ok(projects.update(&projects::UpdateRequest {
+        id: project_id,
+        preferred_key: Some(AuthKey::Local {
+            private_key_path: keys.private_key_path(),
+        }),
+        ..Default::default()
+    })?)
+}
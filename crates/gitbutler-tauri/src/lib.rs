@@ -23,28 +23,93 @@ pub use window::state::event::ChangeForFrontend;
 pub use window::state::WindowState;
 
 pub mod askpass;
+pub mod auto_fetch;
+pub mod capabilities;
 pub mod config;
+pub mod confirmation;
+pub mod crash;
+pub mod deep_link;
+pub mod diagnostics;
 pub mod error;
 pub mod forge;
 pub mod github;
+pub mod keys;
+pub mod local_api;
+pub mod migrations;
 pub mod modes;
+pub mod notifications;
 pub mod open;
+pub mod operations;
 pub mod projects;
+pub mod read_only;
 pub mod remotes;
 pub mod repo;
 pub mod secret;
+pub mod telemetry;
+pub mod traces;
+pub mod tray;
 pub mod undo;
+pub mod updater;
 pub mod users;
 pub mod virtual_branches;
+pub mod worktree_writes;
 
 pub mod settings;
+pub mod shutdown;
 pub mod stack;
+pub mod stream;
 pub mod zip;
 
 pub mod diff;
 pub mod env;
 pub mod workspace;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use but_settings::{AppSettings, Limiter};
+
+/// The maximum amount of commands to run in [`in_blocking_thread()`] at the same time, or `0` if unlimited.
+static COMMAND_THREADS_LIMIT: AtomicUsize = AtomicUsize::new(0);
+static COMMAND_THREADS: Limiter = Limiter::new();
+
+/// Apply the concurrency limits in `settings` that can't be read from the settings directly where they are used.
+///
+/// Call this on startup and whenever the settings change.
+pub fn apply_concurrency_settings(settings: &AppSettings) {
+    COMMAND_THREADS_LIMIT.store(
+        settings.concurrency.effective_command_threads(),
+        Ordering::Relaxed,
+    );
+}
+
+/// Run `f` on a thread dedicated to blocking operations, which is what most `git2` and `gix` based
+/// commands are.
+///
+/// This keeps the threads of the async runtime free, which the watchers and other async commands
+/// rely on to make progress, and allows long-running commands to run concurrently, up to the
+/// configured limit.
+pub(crate) async fn in_blocking_thread<T>(
+    f: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> Result<T, error::Error>
+where
+    T: Send + 'static,
+{
+    // Wait for a permit before spawning, so waiting commands don't take up blocking threads.
+    let limit = COMMAND_THREADS_LIMIT.load(Ordering::Relaxed);
+    let permit = if limit != 0 {
+        Some(COMMAND_THREADS.acquire(limit).await)
+    } else {
+        None
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    })
+    .await
+    .map_err(anyhow::Error::from)?
+    .map_err(Into::into)
+}
+
 /// Utility types that make it easier to transform data from the frontend to the backend.
 ///
 /// Note that these types *should not* be used to transfer anything to the frontend.
@@ -0,0 +1,526 @@
+//! A JSON-RPC 2.0 API on a localhost port, for editor integrations that can't go through the webview.
+//!
+//! Requests, responses and notifications are newline-delimited JSON. The first request of each connection
+//! has to be `authenticate` with the token that is written, along with the port, to [`DISCOVERY_FILE`] in the
+//! app data directory. A new token is created whenever the app starts.
+//!
+//! The other methods call the same handlers as the Tauri commands of the same name:
+//!
+//! * `listProjects`
+//! * `listSnapshots { projectId, limit, sha? }`
+//! * `activitySummary { projectId, since, until }`, whose `sessions` are the periods of activity.
+//! * `listSessions { projectId, since, until, branch? }`, the periods of activity, optionally only those on a branch.
+//! * `listDeltas { projectId, since, until, origin?, branch? }`, the changes to the worktree, optionally only those
+//!   made by a `human` or a `machine`, or on a branch.
+//! * `editorHeartbeat { projectId, filePath }`, to be sent periodically while a file is focused.
+//! * `fileAt { projectId, filePath, at }`, the content of a file at `at` seconds since the Unix epoch, as of the
+//!   latest snapshot or checkpoint before.
+//! * `subscribeEvents { filter }`, after which matching events of the [event bus](gitbutler_watcher::bus) are sent
+//!   as `event` notifications, and `unsubscribeEvents { subscriptionId }`.
+//!
+//! At most [`MAX_CONNECTIONS`] clients are served at once, and requests can't be longer than
+//! [`MAX_REQUEST_BYTES`]. Connections without subscriptions are closed after [`IDLE_TIMEOUT`] without requests.
+//! Subscriptions of clients that don't keep up with their notifications are ended.
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use gitbutler_oplog::{deltas, eol};
+use gitbutler_project::{machine_changes::ChangeOrigin, ProjectId};
+use gitbutler_repo::FileInfo;
+use gitbutler_watcher::bus;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+/// The file in the app data directory that tells clients which port to connect to, and which token to use.
+pub const DISCOVERY_FILE: &str = "local-api.json";
+
+/// The number of clients that are served at once. Further connections are closed right away.
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// The size of the longest request line that is accepted. Connections that send longer ones are closed.
+pub const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// How long a connection without event subscriptions may go without sending requests.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long writing a response or notification may take before the connection is closed.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of responses and notifications that are waiting to be written to a connection. A subscription whose
+/// notification doesn't fit anymore is ended, so publishing events never waits for a client.
+const PENDING_LINES: usize = 256;
+
+/// The content of [`DISCOVERY_FILE`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Discovery<'a> {
+    port: u16,
+    token: &'a str,
+}
+
+/// Listen on `port` of the loopback interface, or on any free port if it's `0`, and serve the API in the
+/// background. The port and a new token are written to [`DISCOVERY_FILE`] in `app_data_dir`.
+pub fn start(app: AppHandle, port: u16, app_data_dir: &Path) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .with_context(|| format!("failed to listen on port {port}"))?;
+    let port = listener.local_addr()?.port();
+    let token: Arc<str> = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+    .into();
+    write_discovery_file(&app_data_dir.join(DISCOVERY_FILE), port, &token)?;
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    std::thread::Builder::new()
+        .name("gitbutler-local-api".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        tracing::warn!(?err, "failed to accept local API connection");
+                        continue;
+                    }
+                };
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    tracing::warn!("too many local API connections, closing the new one");
+                    continue;
+                }
+                let app = app.clone();
+                let token = token.clone();
+                let thread_connections = connections.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("gitbutler-local-api-connection".into())
+                    .spawn(move || {
+                        if let Err(err) = serve_connection(app, stream, &token) {
+                            tracing::debug!(?err, "local API connection failed");
+                        }
+                        thread_connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                if let Err(err) = spawned {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    tracing::error!(?err, "failed to spawn local API connection thread");
+                }
+            }
+        })?;
+    tracing::info!(port, "serving local API");
+    Ok(())
+}
+
+/// Serve the requests that come in through `stream` until it's closed, while another thread writes the responses
+/// and notifications.
+fn serve_connection(app: AppHandle, stream: TcpStream, token: &str) -> Result<()> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let (outbox, pending) = mpsc::sync_channel::<Vec<u8>>(PENDING_LINES);
+    let mut writer = stream.try_clone()?;
+    let writer_thread = std::thread::Builder::new()
+        .name("gitbutler-local-api-writer".into())
+        .spawn(move || {
+            for line in pending {
+                if let Err(err) = writer.write_all(&line) {
+                    tracing::debug!(?err, "failed to write to local API connection");
+                    // Also stops reading requests that could not be answered anymore.
+                    writer.shutdown(Shutdown::Both).ok();
+                    break;
+                }
+            }
+        })?;
+
+    let mut connection = Connection {
+        app,
+        outbox,
+        authenticated: false,
+        subscriptions: Vec::new(),
+    };
+    let result = connection.serve(&stream, token);
+    connection.unsubscribe_all();
+    // Dropping the last sender lets the writer finish what's pending, after which the stream is closed.
+    drop(connection);
+    writer_thread.join().ok();
+    result
+}
+
+fn write_discovery_file(path: &Path, port: u16, token: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // The token grants the same access as the app itself.
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    serde_json::to_writer(&mut file, &Discovery { port, token })?;
+    Ok(())
+}
+
+/// A JSON-RPC request, see <https://www.jsonrpc.org/specification>.
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Requests without id are notifications, which don't get a response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i64 = -32700;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+    const SERVER_ERROR: i64 = -32000;
+    const UNAUTHENTICATED: i64 = -32001;
+
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<crate::error::Error> for RpcError {
+    fn from(err: crate::error::Error) -> Self {
+        let data = serde_json::to_value(&err).ok();
+        let message = data
+            .as_ref()
+            .and_then(|data| data.get("message"))
+            .and_then(Value::as_str)
+            .unwrap_or("request failed")
+            .to_owned();
+        RpcError {
+            code: Self::SERVER_ERROR,
+            message,
+            data,
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(err: anyhow::Error) -> Self {
+        crate::error::Error::from(err).into()
+    }
+}
+
+struct Connection {
+    app: AppHandle,
+    /// The lines to write to the client, shared with event subscriptions.
+    outbox: mpsc::SyncSender<Vec<u8>>,
+    authenticated: bool,
+    subscriptions: Vec<bus::SubscriptionId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthenticateParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSnapshotsParams {
+    project_id: ProjectId,
+    limit: usize,
+    sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActivitySummaryParams {
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListDeltasParams {
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+    #[serde(default)]
+    origin: Option<ChangeOrigin>,
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSessionsParams {
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EditorHeartbeatParams {
+    project_id: ProjectId,
+    file_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileAtParams {
+    project_id: ProjectId,
+    file_path: PathBuf,
+    at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscribeEventsParams {
+    #[serde(default)]
+    filter: bus::EventFilter,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UnsubscribeEventsParams {
+    subscription_id: bus::SubscriptionId,
+}
+
+impl Connection {
+    /// Answer the requests that come in through `stream` until it's closed.
+    fn serve(&mut self, stream: &TcpStream, token: &str) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        while self.read_request(&mut reader, &mut line)? {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let (id, result) = match serde_json::from_slice::<Request>(&line) {
+                Ok(request) => {
+                    let result = self.call(&request.method, request.params, token);
+                    match request.id {
+                        Some(id) => (id, result),
+                        None => continue,
+                    }
+                }
+                Err(err) => (
+                    Value::Null,
+                    Err(RpcError::new(RpcError::PARSE_ERROR, err.to_string())),
+                ),
+            };
+            let response = match result {
+                Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+            };
+            self.outbox
+                .send(to_line(&response)?)
+                .context("the connection was closed")?;
+        }
+        Ok(())
+    }
+
+    /// Read the next request from `reader` into `line`, without its newline, and return `false` once the client
+    /// closed the connection.
+    ///
+    /// Fails if the request is longer than [`MAX_REQUEST_BYTES`], or if the client has been idle for longer than
+    /// [`IDLE_TIMEOUT`] while it isn't subscribed to events.
+    fn read_request(&self, reader: &mut impl BufRead, line: &mut Vec<u8>) -> Result<bool> {
+        line.clear();
+        loop {
+            // Reads one byte past the limit to tell apart requests that are exactly as long as it.
+            let limit = (MAX_REQUEST_BYTES + 1 - line.len()) as u64;
+            match reader.by_ref().take(limit).read_until(b'\n', line) {
+                Ok(_) => {
+                    if line.last() == Some(&b'\n') {
+                        line.pop();
+                        return Ok(true);
+                    }
+                    if line.len() > MAX_REQUEST_BYTES {
+                        anyhow::bail!("request is longer than {MAX_REQUEST_BYTES} bytes");
+                    }
+                    // The end of the stream, possibly after a last request without newline.
+                    return Ok(!line.is_empty());
+                }
+                // What was read so far stays in `line`, so subscribers can stay connected without sending anything.
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) && !self.subscriptions.is_empty() => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn call(&mut self, method: &str, params: Value, token: &str) -> Result<Value, RpcError> {
+        if method == "authenticate" {
+            let params: AuthenticateParams = parse_params(params)?;
+            self.authenticated = params.token == token;
+            return if self.authenticated {
+                Ok(Value::Bool(true))
+            } else {
+                Err(RpcError::new(RpcError::UNAUTHENTICATED, "invalid token"))
+            };
+        }
+        if !self.authenticated {
+            return Err(RpcError::new(
+                RpcError::UNAUTHENTICATED,
+                "authenticate first",
+            ));
+        }
+
+        let app = &self.app;
+        match method {
+            "listProjects" => to_value(crate::projects::commands::list_projects(
+                app.state(),
+                app.state(),
+            )?),
+            "listSnapshots" => {
+                let params: ListSnapshotsParams = parse_params(params)?;
+                to_value(crate::undo::list_snapshots(
+                    app.state(),
+                    params.project_id,
+                    params.limit,
+                    params.sha,
+                )?)
+            }
+            "activitySummary" => {
+                let params: ActivitySummaryParams = parse_params(params)?;
+                to_value(crate::undo::activity_summary(
+                    app.state(),
+                    params.project_id,
+                    params.since,
+                    params.until,
+                )?)
+            }
+            "listSessions" => {
+                let params: ListSessionsParams = parse_params(params)?;
+                to_value(crate::undo::list_sessions(
+                    app.state(),
+                    params.project_id,
+                    params.since,
+                    params.until,
+                    params.branch,
+                )?)
+            }
+            "listDeltas" => {
+                let params: ListDeltasParams = parse_params(params)?;
+                to_value(crate::undo::list_deltas(
+                    app.state(),
+                    params.project_id,
+                    params.since,
+                    params.until,
+                    params.origin,
+                    params.branch,
+                )?)
+            }
+            "editorHeartbeat" => {
+                let params: EditorHeartbeatParams = parse_params(params)?;
+                to_value(crate::undo::editor_heartbeat(
+                    app.state(),
+                    params.project_id,
+                    params.file_path,
+                )?)
+            }
+            "fileAt" => {
+                let params: FileAtParams = parse_params(params)?;
+                to_value(file_at(app, params)?)
+            }
+            "subscribeEvents" => {
+                let params: SubscribeEventsParams = parse_params(params)?;
+                let outbox = self.outbox.clone();
+                let id = bus::event_bus().subscribe(params.filter, move |id, envelope| {
+                    let notification = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "method": "event",
+                        "params": { "subscriptionId": id, "envelope": envelope },
+                    });
+                    // Ends the subscription if the client falls behind or is gone, as this runs while publishing.
+                    let keep =
+                        to_line(&notification).is_ok_and(|line| outbox.try_send(line).is_ok());
+                    if !keep {
+                        tracing::debug!(id, "ending local API event subscription");
+                    }
+                    keep
+                });
+                self.subscriptions.push(id);
+                to_value(id)
+            }
+            "unsubscribeEvents" => {
+                let params: UnsubscribeEventsParams = parse_params(params)?;
+                self.subscriptions
+                    .retain(|id| *id != params.subscription_id);
+                to_value(bus::event_bus().unsubscribe(params.subscription_id))
+            }
+            _ => Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                format!("unknown method '{method}'"),
+            )),
+        }
+    }
+
+    fn unsubscribe_all(&mut self) {
+        for id in self.subscriptions.drain(..) {
+            bus::event_bus().unsubscribe(id);
+        }
+    }
+}
+
+/// Return the content of the file at `params.file_path` according to the latest snapshot or checkpoint recorded
+/// at or before `params.at`, or `None` if there is neither.
+fn file_at(app: &AppHandle, params: FileAtParams) -> Result<Option<FileInfo>> {
+    let project = app
+        .state::<gitbutler_project::Controller>()
+        .get(params.project_id)
+        .context("failed to get project")?;
+    let Some(blob_at) = deltas::blob_at(&project, &params.file_path, params.at)? else {
+        return Ok(None);
+    };
+    Ok(Some(match blob_at.blob_id {
+        Some(blob_id) => {
+            let repo = git2::Repository::open(&project.path)?;
+            let blob = repo.find_blob(blob_id)?;
+            FileInfo::from_content(
+                &params.file_path,
+                &eol::restore(blob.content(), blob_at.eol),
+            )
+        }
+        None => FileInfo::deleted(),
+    }))
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|err| RpcError::new(RpcError::INVALID_PARAMS, err.to_string()))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|err| RpcError::from(anyhow::Error::from(err)))
+}
+
+fn to_line(value: &Value) -> Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(line)
+}
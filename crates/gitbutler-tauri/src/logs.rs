@@ -1,9 +1,57 @@
-use std::{fs, net::Ipv4Addr, path::Path, time::Duration};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{BufRead, BufReader},
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
 
 use tauri::{AppHandle, Manager};
-use tracing::{instrument, metadata::LevelFilter, subscriber::set_global_default};
+use tracing::{
+    instrument, metadata::LevelFilter, subscriber::set_global_default, subscriber::Interest, Level,
+    Metadata,
+};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, Layer};
+use tracing_subscriber::{
+    fmt::format::FmtSpan,
+    layer::{Context, Filter, SubscriberExt},
+    Layer,
+};
+
+const LOG_PREFIX: &str = "GitButler";
+
+/// The level up to which events and spans are logged, which can be changed at runtime with [`set_level()`].
+static LEVEL: parking_lot::RwLock<LevelFilter> = parking_lot::RwLock::new(LevelFilter::INFO);
+
+/// A filter for the [`LEVEL`] as it is at the time of logging.
+#[derive(Debug, Clone, Copy)]
+struct RuntimeLevelFilter;
+
+impl<S> Filter<S> for RuntimeLevelFilter {
+    fn enabled(&self, meta: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        meta.level() <= &*LEVEL.read()
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        if meta.level() <= &*LEVEL.read() {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(*LEVEL.read())
+    }
+}
+
+/// Log everything up to `level` from now on, until the app restarts.
+pub fn set_level(level: LevelFilter) {
+    *LEVEL.write() = level;
+    // Callsites cache whether they are enabled, let them ask again.
+    tracing::callsite::rebuild_interest_cache();
+}
 
 pub fn init(app_handle: &AppHandle, performance_logging: bool) {
     let logs_dir = app_handle
@@ -12,7 +60,7 @@ pub fn init(app_handle: &AppHandle, performance_logging: bool) {
         .expect("failed to get logs dir");
     fs::create_dir_all(&logs_dir).expect("failed to create logs dir");
 
-    let log_prefix = "GitButler";
+    let log_prefix = LOG_PREFIX;
     let log_suffix = "log";
     let max_log_files = 14;
     remove_old_logs(&logs_dir).ok();
@@ -36,11 +84,12 @@ pub fn init(app_handle: &AppHandle, performance_logging: bool) {
         .with_target(false)
         .compact();
 
-    let log_level_filter = std::env::var("LOG_LEVEL")
+    *LEVEL.write() = std::env::var("LOG_LEVEL")
         .unwrap_or("info".to_string())
         .to_lowercase()
         .parse()
         .unwrap_or(LevelFilter::INFO);
+    let log_level_filter = RuntimeLevelFilter;
 
     let use_colors_in_logs = cfg!(not(feature = "windows"));
     let subscriber = tracing_subscriber::registry()
@@ -61,6 +110,10 @@ pub fn init(app_handle: &AppHandle, performance_logging: bool) {
                 .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
                 .with_writer(file_writer)
                 .with_filter(log_level_filter),
+        )
+        .with(
+            // subscriber that keeps the latest spans for `get_recent_traces`
+            crate::traces::RecentTracesLayer.with_filter(log_level_filter),
         );
     if performance_logging {
         set_global_default(
@@ -86,6 +139,67 @@ pub fn init(app_handle: &AppHandle, performance_logging: bool) {
     .expect("failed to set subscriber");
 }
 
+/// Return the last `lines` lines of the most recently written log file in `logs_dir`, oldest first.
+///
+/// With `level`, only lines of events up to that level are returned, along with the lines that continue them.
+pub fn tail(
+    logs_dir: &Path,
+    lines: usize,
+    level: Option<LevelFilter>,
+) -> anyhow::Result<Vec<String>> {
+    let Some(path) = newest_log_file(logs_dir)? else {
+        return Ok(Vec::new());
+    };
+    let mut tail = VecDeque::with_capacity(lines);
+    let mut include = true;
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if let Some(level_filter) = level {
+            if let Some(line_level) = line_level(&line) {
+                include = line_level <= level_filter;
+            }
+            if !include {
+                continue;
+            }
+        }
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        if lines > 0 {
+            tail.push_back(line);
+        }
+    }
+    Ok(tail.into())
+}
+
+fn newest_log_file(logs_dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(logs_dir)? {
+        let entry = entry?;
+        let is_log = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(LOG_PREFIX));
+        let metadata = entry.metadata()?;
+        if !is_log || !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified()?;
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+    Ok(newest.map(|(_, path)| path))
+}
+
+/// Return the level of the event logged on `line`, which follows the timestamp, or `None` if `line` continues
+/// the previous one.
+fn line_level(line: &str) -> Option<Level> {
+    let mut tokens = line.split_whitespace();
+    tokens.next()?;
+    tokens.next()?.parse().ok()
+}
+
 fn get_server_addr(app_handle: &AppHandle) -> (Ipv4Addr, u16) {
     let config = app_handle.config();
     let product_name = config.product_name.as_ref().expect("product name not set");
@@ -191,3 +305,48 @@ fn remove_old_logs(log_directory: &Path) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+pub mod commands {
+    use anyhow::Context;
+    use gitbutler_error::{error, error::Code};
+    use tauri::{AppHandle, Manager};
+    use tracing::{instrument, metadata::LevelFilter};
+
+    use crate::error::Error;
+
+    /// Return the last `lines` lines of the current log file, only including events up to `level_filter` if set.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle), err(Debug))]
+    pub fn get_logs(
+        app_handle: AppHandle,
+        lines: usize,
+        level_filter: Option<String>,
+    ) -> Result<Vec<String>, Error> {
+        let level = level_filter.as_deref().map(parse_level).transpose()?;
+        let logs_dir = app_handle
+            .path()
+            .app_log_dir()
+            .context("missing app log dir")?;
+        Ok(super::tail(&logs_dir, lines, level)?)
+    }
+
+    /// Log everything up to `level`, like `debug`, until the app restarts.
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn set_log_level(level: String) -> Result<(), Error> {
+        let level = parse_level(&level)?;
+        super::set_level(level);
+        tracing::info!(%level, "changed log level");
+        Ok(())
+    }
+
+    fn parse_level(level: &str) -> anyhow::Result<LevelFilter> {
+        level
+            .to_lowercase()
+            .parse()
+            .context(error::Context::new_static(
+                Code::Validation,
+                "The log level must be one of off, error, warn, info, debug or trace",
+            ))
+    }
+}
@@ -11,15 +11,18 @@
     clippy::too_many_lines
 )]
 
+use but_settings::app_settings::NotificationCategory;
 use but_settings::AppSettingsWithDiskSync;
 use gitbutler_tauri::settings::SettingsStore;
 use gitbutler_tauri::{
-    askpass, commands, config, diff, env, forge, github, logs, menu, modes, open, projects,
-    remotes, repo, secret, settings, stack, undo, users, virtual_branches, workspace, zip, App,
-    WindowState,
+    askpass, capabilities, commands, config, confirmation, crash, deep_link, diagnostics, diff,
+    env, forge, github, keys, logs, menu, modes, notifications, open, operations, projects,
+    read_only, remotes, repo, secret, settings, stack, stream, telemetry, traces, tray, undo,
+    updater, users, virtual_branches, workspace, zip, App, WindowState,
 };
 use tauri::Emitter;
 use tauri::{generate_context, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_plugin_log::{Target, TargetKind};
 use tauri_plugin_store::StoreExt;
 
@@ -100,16 +103,74 @@ fn main() {
                     std::fs::create_dir_all(&app_cache_dir).expect("failed to create cache dir");
                     let config_dir = config_dir.join("gitbutler");
                     std::fs::create_dir_all(&config_dir).expect("failed to create config dir");
+                    crash::install_panic_hook(
+                        app_data_dir.clone(),
+                        app_log_dir.clone(),
+                        app_handle.package_info().version.to_string(),
+                    );
 
                     tracing::info!(version = %app_handle.package_info().version,
                                    name = %app_handle.package_info().name, "starting app");
 
                     app_handle.manage(WindowState::new(app_handle.clone()));
+                    app_handle.manage(notifications::Notifier::default());
+
+                    match gitbutler_tauri::shutdown::record_start(&app_data_dir) {
+                        Ok(gitbutler_tauri::shutdown::PreviousShutdown::Unclean) => {
+                            tracing::warn!("the app didn't shut down cleanly the last time, changes right before may not have been recorded");
+                            app_handle.emit("unclean_shutdown", ()).ok();
+                        }
+                        Ok(_) => {}
+                        Err(err) => tracing::error!(?err, "failed to record the start of the app"),
+                    }
+
+                    // Finish writes of projects and users that were interrupted when the app was last killed.
+                    let storage = gitbutler_storage::Storage::new(&app_data_dir);
+                    match storage.recover() {
+                        Ok(report) if !report.is_empty() => {
+                            tracing::warn!(?report, "recovered interrupted writes");
+                            app_handle.emit("storage_recovered", report).ok();
+                            notifications::notify(
+                                app_handle,
+                                NotificationCategory::HistoryRepaired,
+                                None,
+                                "Interrupted writes were recovered",
+                                "Projects and users that were being saved when the app stopped are complete again.",
+                            );
+                        }
+                        Ok(_) => {}
+                        Err(err) => tracing::error!(?err, "failed to recover interrupted writes"),
+                    }
+                    match storage.migrate(gitbutler_tauri::migrations::MIGRATIONS) {
+                        Ok(report) if report.backup.is_some() => {
+                            tracing::info!(?report, "migrated storage");
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            // Going on would read and write data this version doesn't understand, so nothing is
+                            // started, and the app quits once the error was seen.
+                            tracing::error!(?err, "failed to migrate storage");
+                            let exiting = app_handle.clone();
+                            app_handle
+                                .dialog()
+                                .message(format!(
+                                    "The data of GitButler couldn't be migrated to this version.\n\n{err:#}"
+                                ))
+                                .title("GitButler can't start")
+                                .kind(MessageDialogKind::Error)
+                                .show(move |_| exiting.exit(1));
+                            return Ok(());
+                        }
+                    }
 
                     let mut app_settings = AppSettingsWithDiskSync::new(config_dir.clone())?;
+                    gitbutler_tauri::apply_concurrency_settings(&app_settings.get()?);
+                    gitbutler_tauri::telemetry::apply_telemetry_settings(&app_settings.get()?);
                     app_settings.watch_in_background({
                         let app_handle = app_handle.clone();
                         move |app_settings| {
+                            gitbutler_tauri::apply_concurrency_settings(&app_settings);
+                            gitbutler_tauri::telemetry::apply_telemetry_settings(&app_settings);
                             gitbutler_tauri::ChangeForFrontend::from(app_settings).send(&app_handle)
                         }
                     })?;
@@ -117,7 +178,20 @@ fn main() {
                         app_data_dir: app_data_dir.clone(),
                     };
                     app_handle.manage(app.users());
+                    app_handle.manage(app.keys());
                     app_handle.manage(app.projects());
+                    let grace_period = std::time::Duration::from_secs(
+                        app_settings.get()?.project_deletion_grace_period_seconds,
+                    );
+                    // Purging can wait until the window shows, as it removes whole directories of data.
+                    tauri::async_runtime::spawn_blocking({
+                        let projects = app.projects();
+                        move || {
+                            if let Err(err) = projects.purge_expired(grace_period) {
+                                tracing::error!(?err, "failed to purge deleted projects");
+                            }
+                        }
+                    });
                     let settings_store: SettingsStore = tauri_app.store("settings.json")?.into();
                     app_handle.manage(settings_store);
 
@@ -128,6 +202,29 @@ fn main() {
                     });
                     app_handle.manage(app);
 
+                    app_handle.manage(deep_link::PendingProject::default());
+                    app_handle.manage(updater::PendingUpdate::default());
+                    app_handle.manage(confirmation::Confirmations::default());
+                    app_handle.manage(stream::Streams::default());
+                    app_handle.manage(operations::Operations::default());
+                    if let Some(url) = deep_link::find_in_args(&std::env::args().collect::<Vec<_>>()) {
+                        deep_link::open_on_start(app_handle, &url);
+                    }
+
+                    gitbutler_tauri::auto_fetch::start(app_handle.clone());
+                    notifications::start(app_handle.clone(), app_data_dir.clone());
+
+                    let local_api = app_settings.get()?.local_api;
+                    if local_api.enabled {
+                        if let Err(err) = gitbutler_tauri::local_api::start(
+                            app_handle.clone(),
+                            local_api.port,
+                            &app_data_dir,
+                        ) {
+                            tracing::error!(?err, "failed to start the local API");
+                        }
+                    }
+
                     tauri_app.on_menu_event(move |_handle, event| {
                         menu::handle_event(&window.clone(), &event)
                     });
@@ -148,22 +245,56 @@ fn main() {
                             };
                         }
                     }
+                    tauri::async_runtime::spawn({
+                        let window_state = app_handle.state::<WindowState>().inner().clone();
+                        let app_settings = app_settings.clone();
+                        async move {
+                            let mut interval =
+                                tokio::time::interval(std::time::Duration::from_secs(60));
+                            loop {
+                                interval.tick().await;
+                                let Ok(timeout) = app_settings
+                                    .get()
+                                    .map(|settings| settings.watcher_idle_timeout_seconds)
+                                else {
+                                    continue;
+                                };
+                                if timeout > 0 {
+                                    window_state.stop_inactive_watchers(
+                                        std::time::Duration::from_secs(timeout),
+                                    );
+                                }
+                            }
+                        }
+                    });
                     app_handle.manage(app_settings);
 
+                    if let Err(err) = tray::create(app_handle) {
+                        tracing::error!(?err, "failed to create the tray icon");
+                    }
+
                     Ok(())
                 })
                 .plugin(tauri_plugin_http::init())
                 .plugin(tauri_plugin_shell::init())
                 .plugin(tauri_plugin_os::init())
                 .plugin(tauri_plugin_process::init())
-                .plugin(tauri_plugin_single_instance::init(|_, _, _| {}))
+                .plugin(tauri_plugin_single_instance::init(|app_handle, args, _cwd| {
+                    // The app was launched again, maybe with a link to open.
+                    match deep_link::find_in_args(&args) {
+                        Some(url) => deep_link::open(app_handle, &url),
+                        None => {
+                            deep_link::focus_window(app_handle);
+                        }
+                    }
+                }))
                 .plugin(tauri_plugin_updater::Builder::new().build())
                 .plugin(tauri_plugin_dialog::init())
                 .plugin(tauri_plugin_fs::init())
                 // .plugin(tauri_plugin_context_menu::init())
                 .plugin(tauri_plugin_store::Builder::default().build())
                 .plugin(log.build())
-                .invoke_handler(tauri::generate_handler![
+                .invoke_handler(confirmation::guard(read_only::guard(tauri::generate_handler![
                     commands::git_remote_branches,
                     commands::git_head,
                     commands::delete_all_data,
@@ -180,27 +311,82 @@ fn main() {
                     users::commands::delete_user,
                     users::commands::get_user,
                     projects::commands::add_project,
+                    projects::commands::init_project,
+                    projects::commands::project_templates,
+                    projects::commands::clone_project,
+                    projects::commands::add_subproject,
+                    projects::commands::list_subprojects,
+                    projects::commands::add_projects,
+                    projects::commands::discover_projects,
+                    projects::commands::pause_recording,
+                    projects::commands::resume_recording,
+                    projects::commands::set_watch_include_paths,
+                    projects::commands::replay_events,
+                    projects::commands::watcher_metrics,
+                    projects::commands::subscribe_events,
+                    projects::commands::unsubscribe_events,
                     projects::commands::get_project,
                     projects::commands::update_project,
                     projects::commands::delete_project,
+                    projects::commands::undo_delete_project,
+                    projects::commands::reorder_projects,
+                    projects::commands::set_project_group,
                     projects::commands::list_projects,
                     projects::commands::set_project_active,
+                    projects::commands::activate_project,
+                    projects::commands::subscribe_file,
+                    projects::commands::unsubscribe_file,
                     projects::commands::open_project_in_window,
                     repo::commands::git_get_local_config,
                     repo::commands::git_set_local_config,
                     repo::commands::check_signing_settings,
+                    repo::commands::list_tags,
+                    repo::commands::create_tag,
+                    repo::commands::delete_tag,
+                    repo::commands::branch_tracking_status,
                     repo::commands::git_clone_repository,
                     repo::commands::get_uncommited_files,
                     repo::commands::get_commit_file,
                     repo::commands::get_workspace_file,
+                    repo::commands::get_worktree_file,
+                    repo::commands::get_file_versions,
+                    repo::commands::get_file_base64,
+                    repo::commands::file_tree,
+                    repo::commands::list_project_files,
+                    repo::commands::verify_commit_signature,
+                    repo::commands::merge_branch,
+                    repo::commands::unshallow,
+                    repo::commands::repo_health,
+                    repo::commands::optimize_repository,
+                    repo::commands::export_archive,
+                    repo::commands::get_project_identity,
+                    repo::commands::set_project_identity,
+                    repo::commands::format_patch,
+                    repo::commands::apply_patch,
+                    repo::commands::continue_merge,
+                    repo::commands::abort_merge,
+                    repo::commands::get_conflict_versions,
+                    repo::commands::resolve_conflict,
+                    repo::commands::launch_merge_tool,
+                    repo::commands::launch_diff_tool,
                     repo::commands::pre_commit_hook,
                     repo::commands::post_commit_hook,
                     repo::commands::message_hook,
+                    repo::commands::commit_template,
+                    repo::commands::validate_commit_message,
                     virtual_branches::commands::list_virtual_branches,
                     virtual_branches::commands::create_virtual_branch,
                     virtual_branches::commands::delete_local_branch,
+                    virtual_branches::commands::stale_branches,
+                    virtual_branches::commands::delete_branches,
                     virtual_branches::commands::commit_virtual_branch,
+                    virtual_branches::commands::commit_session,
+                    virtual_branches::commands::suspend_workspace,
+                    virtual_branches::commands::resume_workspace,
+                    virtual_branches::commands::list_suspended_workspaces,
+                    virtual_branches::commands::suggest_change_groups,
                     virtual_branches::commands::get_base_branch_data,
+                    virtual_branches::commands::get_default_branch,
                     virtual_branches::commands::set_base_branch,
                     virtual_branches::commands::push_base_branch,
                     virtual_branches::commands::integrate_upstream_commits,
@@ -210,14 +396,24 @@ fn main() {
                     virtual_branches::commands::save_and_unapply_virtual_branch,
                     virtual_branches::commands::unapply_lines,
                     virtual_branches::commands::unapply_ownership,
+                    virtual_branches::commands::transfer_ownership,
                     virtual_branches::commands::reset_files,
+                    virtual_branches::commands::discard_changes,
                     virtual_branches::commands::create_virtual_branch_from_branch,
                     virtual_branches::commands::can_apply_remote_branch,
                     virtual_branches::commands::list_commit_files,
                     virtual_branches::commands::reset_virtual_branch,
                     virtual_branches::commands::amend_virtual_branch,
+                    virtual_branches::commands::amend_commit,
+                    virtual_branches::commands::reword_commit,
                     virtual_branches::commands::move_commit_file,
                     virtual_branches::commands::undo_commit,
+                    virtual_branches::commands::cherry_pick,
+                    virtual_branches::commands::revert_commit,
+                    virtual_branches::commands::start_rebase,
+                    virtual_branches::commands::resume_rebase,
+                    virtual_branches::commands::abort_rebase,
+                    virtual_branches::commands::pending_rebase,
                     virtual_branches::commands::insert_blank_commit,
                     virtual_branches::commands::reorder_stack,
                     virtual_branches::commands::update_commit_message,
@@ -231,6 +427,7 @@ fn main() {
                     virtual_branches::commands::upstream_integration_statuses,
                     virtual_branches::commands::integrate_upstream,
                     virtual_branches::commands::resolve_upstream_integration,
+                    virtual_branches::commands::update_from_base,
                     virtual_branches::commands::find_commit,
                     stack::create_series,
                     stack::remove_series,
@@ -238,15 +435,61 @@ fn main() {
                     stack::update_series_description,
                     stack::update_series_pr_number,
                     stack::push_stack,
+                    stack::check_push_stack,
                     stack::push_stack_to_review,
                     secret::secret_get_global,
                     secret::secret_set_global,
                     undo::list_snapshots,
                     undo::restore_snapshot,
+                    undo::list_operations,
+                    undo::undo_last_operation,
                     undo::snapshot_diff,
+                    undo::snapshot_playback,
+                    undo::activity_summary,
+                    undo::list_sessions,
+                    undo::list_deltas,
+                    undo::profile_reconstruction,
+                    undo::editor_heartbeat,
+                    undo::file_history,
+                    undo::list_deleted_files,
+                    undo::recover_deleted_file,
+                    undo::restore_directory_at,
+                    undo::history_bisect,
+                    undo::add_bookmark,
+                    undo::update_bookmark,
+                    undo::remove_bookmark,
+                    undo::list_bookmarks,
+                    undo::export_history,
+                    undo::share_session,
+                    undo::open_shared_session,
+                    undo::import_history,
+                    undo::verify_history,
+                    undo::repair_history,
+                    undo::repair_meta_ref,
+                    undo::scan_history_for_secrets,
+                    undo::data_usage,
+                    traces::commands::get_recent_traces,
+                    crash::commands::list_crash_reports,
+                    logs::commands::get_logs,
+                    logs::commands::set_log_level,
+                    diagnostics::commands::diagnostics,
+                    crash::commands::submit_crash_report,
+                    telemetry::commands::set_telemetry_enabled,
+                    telemetry::commands::show_pending_telemetry,
+                    telemetry::commands::take_pending_telemetry,
+                    undo::cleanup_history,
+                    undo::history_budget,
+                    undo::unlock_history,
+                    undo::encrypt_history,
+                    undo::decrypt_history,
                     undo::take_synced_snapshot,
+                    undo::backup_history_to_remote,
+                    undo::restore_history_from_remote,
+                    undo::merge_remote_history,
                     config::get_gb_config,
                     config::set_gb_config,
+                    config::get_git_config,
+                    config::set_git_config,
                     menu::menu_item_set_enabled,
                     menu::get_editor_link_scheme,
                     github::commands::init_device_oauth,
@@ -254,6 +497,8 @@ fn main() {
                     askpass::commands::submit_prompt_response,
                     remotes::list_remotes,
                     remotes::add_remote,
+                    remotes::remove_remote,
+                    remotes::set_remote_url,
                     modes::operating_mode,
                     modes::enter_edit_mode,
                     modes::save_edit_and_return_to_workspace,
@@ -262,10 +507,34 @@ fn main() {
                     open::open_url,
                     forge::commands::get_available_review_templates,
                     forge::commands::get_review_template_contents,
+                    forge::commands::branch_forge_status,
+                    forge::commands::list_pull_requests,
+                    forge::commands::create_pull_request,
+                    forge::commands::ci_status,
                     settings::get_app_settings,
                     settings::update_onboarding_complete,
+                    settings::onboarding_state,
+                    capabilities::commands::capability_report,
+                    confirmation::commands::request_confirmation,
+                    stream::commands::begin_stream,
+                    stream::commands::end_stream,
+                    operations::commands::begin_operation,
+                    operations::commands::cancel_operation,
+                    settings::complete_onboarding_step,
                     settings::update_telemetry,
                     settings::update_feature_flags,
+                    settings::update_concurrency,
+                    settings::update_project_deletion_grace_period,
+                    settings::update_watcher_idle_timeout,
+                    settings::update_background_mode,
+                    settings::set_update_channel,
+                    updater::commands::check_for_updates,
+                    updater::commands::download_update,
+                    updater::commands::install_update,
+                    settings::update_local_api,
+                    settings::update_notifications,
+                    keys::get_public_key,
+                    keys::use_generated_key,
                     workspace::stacks,
                     workspace::stack_branches,
                     workspace::hunk_dependencies_for_workspace_changes,
@@ -274,31 +543,42 @@ fn main() {
                     diff::worktree_changes,
                     diff::commit_changes,
                     diff::tree_change_diffs,
+                    diff::commit_context,
+                    diff::compute_diff,
+                    deep_link::commands::take_deep_link_project,
                     // `env_vars` is only supposed to be avaialble in debug mode, not in production.
                     #[cfg(debug_assertions)]
                     env::env_vars,
-                ])
+                ])))
                 .menu(menu::build)
                 .on_window_event(|window, event| match event {
                     #[cfg(target_os = "macos")]
                     tauri::WindowEvent::CloseRequested { .. } => {
                         let app_handle = window.app_handle();
-                        if app_handle.windows().len() == 1 {
+                        if app_handle.windows().len() == 1 && !tray::background_mode(app_handle) {
                             app_handle.cleanup_before_exit();
                             app_handle.exit(0);
                         }
                     }
                     tauri::WindowEvent::Destroyed => {
-                        window
-                            .app_handle()
-                            .state::<WindowState>()
-                            .remove(window.label());
+                        let app_handle = window.app_handle();
+                        let window_state = app_handle.state::<WindowState>();
+                        if tray::background_mode(app_handle) {
+                            window_state.move_to_background(window.label());
+                            tray::refresh(app_handle);
+                            return;
+                        }
+                        if app_handle.webview_windows().len() <= 1 {
+                            // The app is about to exit, so have the watcher of the last window flush what it noticed.
+                            gitbutler_watcher::begin_shutdown();
+                        }
+                        window_state.remove(window.label());
                     }
                     tauri::WindowEvent::Focused(focused) if *focused => {
                         window
                             .app_handle()
                             .state::<WindowState>()
-                            .flush(window.label())
+                            .activate(window.label())
                             .ok();
                     }
                     _ => {}
@@ -310,8 +590,31 @@ fn main() {
             builder
                 .build(tauri_context)
                 .expect("Failed to build tauri app")
-                .run(|app_handle, event| {
-                    let _ = (app_handle, event);
+                .run(|app_handle, event| match event {
+                    #[cfg(target_os = "macos")]
+                    tauri::RunEvent::Opened { urls } => {
+                        for url in &urls {
+                            deep_link::open(app_handle, url);
+                        }
+                    }
+                    #[cfg(target_os = "macos")]
+                    tauri::RunEvent::Reopen {
+                        has_visible_windows: false,
+                        ..
+                    } => {
+                        deep_link::focus_window(app_handle);
+                    }
+                    // Keep recording in the background once the last window was closed, unless asked to quit.
+                    tauri::RunEvent::ExitRequested {
+                        code: None, api, ..
+                    } if tray::background_mode(app_handle) => api.prevent_exit(),
+                    tauri::RunEvent::Exit => match app_handle.path().app_data_dir() {
+                        Ok(app_data_dir) => {
+                            gitbutler_tauri::shutdown::shutdown(app_handle, &app_data_dir)
+                        }
+                        Err(err) => tracing::error!(?err, "failed to shut down cleanly"),
+                    },
+                    _ => {}
                 });
         });
 }
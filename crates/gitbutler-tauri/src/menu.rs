@@ -37,7 +37,8 @@ pub fn menu_item_set_enabled(handle: AppHandle, id: &str, enabled: bool) -> Resu
     Ok(())
 }
 
-#[tauri::command()]
+#[tauri::command(async)]
+#[instrument]
 pub fn get_editor_link_scheme() -> &'static str {
     let vscodium_installed = check_if_installed("codium");
     if vscodium_installed {
@@ -0,0 +1,9 @@
+//! The [migrations](Migration) of the data in the app data directory, run once on startup.
+use gitbutler_storage::migrations::Migration;
+
+/// All migrations, which must never be changed or removed once released, only added to with a higher version.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "record the schema version of the existing data",
+    run: |_| Ok(()),
+}];
@@ -0,0 +1,280 @@
+//! Turn problems the user should know about, like a watcher that failed or a push that was rejected, into
+//! notifications for the frontend to show.
+//!
+//! Notifications of the same category and project are shown at most once per [`DEDUPLICATION_WINDOW`], and no more
+//! than [`MAX_PER_RATE_WINDOW`] are shown per [`RATE_WINDOW`] overall, so a problem that repeats doesn't flood the
+//! user. The next notification that is shown tells how many were held back before it. Categories that are muted in
+//! the [app settings](but_settings::app_settings::NotificationSettings) are dropped.
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use but_settings::{app_settings::NotificationCategory, AppSettingsWithDiskSync};
+use gitbutler_error::error::Code;
+use gitbutler_project::ProjectId;
+use gitbutler_watcher::bus::{self, Event, EventFilter, EventKind};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::capabilities::{self, CheckStatus};
+
+/// The event each shown [`Notification`] is emitted as.
+pub const NOTIFICATION_EVENT: &str = "notification://show";
+
+/// Notifications of the same category and project are shown at most once within this time.
+const DEDUPLICATION_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// The time within which at most [`MAX_PER_RATE_WINDOW`] notifications are shown.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_PER_RATE_WINDOW: usize = 5;
+/// How often to check the free space of the disk holding the app data.
+const DISK_SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// A notification as it is emitted to the frontend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Unique among the notifications of a run of the app.
+    pub id: u64,
+    pub category: NotificationCategory,
+    /// The project the notification is about, if it's about a single one.
+    pub project_id: Option<ProjectId>,
+    pub title: String,
+    pub body: String,
+    /// When the notification was shown, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// If `true`, a notification of the operating system should be shown as well.
+    pub os: bool,
+    /// How many notifications of the same category and project were held back since the previous one was shown.
+    pub suppressed: usize,
+}
+
+/// What a notification is deduplicated by.
+type Key = (NotificationCategory, Option<ProjectId>);
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Shown {
+    last_shown: Option<Instant>,
+    /// The amount of notifications held back since `last_shown`.
+    suppressed: usize,
+}
+
+/// Decides which notifications are shown.
+#[derive(Debug, Default)]
+struct RateLimiter {
+    shown: HashMap<Key, Shown>,
+    /// When the notifications within the last [`RATE_WINDOW`] were shown, oldest first.
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    /// Return the amount of notifications held back since the previous one with `key` if a notification with `key`
+    /// may be shown `now`, or `None` if it's held back.
+    fn admit(&mut self, key: Key, now: Instant) -> Option<usize> {
+        while self
+            .recent
+            .front()
+            .is_some_and(|shown| now.duration_since(*shown) >= RATE_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        let shown = self.shown.entry(key).or_default();
+        if shown
+            .last_shown
+            .is_some_and(|last_shown| now.duration_since(last_shown) < DEDUPLICATION_WINDOW)
+            || self.recent.len() >= MAX_PER_RATE_WINDOW
+        {
+            shown.suppressed += 1;
+            return None;
+        }
+        shown.last_shown = Some(now);
+        self.recent.push_back(now);
+        Some(std::mem::take(&mut shown.suppressed))
+    }
+}
+
+/// Emits notifications to all windows, to be managed by the app.
+#[derive(Debug, Default, Clone)]
+pub struct Notifier {
+    state: Arc<parking_lot::Mutex<(RateLimiter, u64)>>,
+}
+
+/// Show a notification of `category` about `project_id` to the user, unless the category is muted or it's rate
+/// limited, and return `true` if it was emitted.
+pub fn notify(
+    app_handle: &AppHandle,
+    category: NotificationCategory,
+    project_id: Option<ProjectId>,
+    title: impl Into<String>,
+    body: impl Into<String>,
+) -> bool {
+    let Some(notifier) = app_handle.try_state::<Notifier>() else {
+        return false;
+    };
+    // Settings are managed once the app is set up, and notifications before that use the defaults.
+    let settings = match app_handle.try_state::<AppSettingsWithDiskSync>() {
+        Some(settings) => match settings.get() {
+            Ok(settings) => settings.notifications.clone(),
+            Err(err) => {
+                tracing::warn!(?err, "failed to read the notification settings");
+                return false;
+            }
+        },
+        None => but_settings::AppSettings::default().notifications,
+    };
+    if !settings.is_enabled(category) {
+        return false;
+    }
+    let notification = {
+        let mut state = notifier.state.lock();
+        let (limiter, next_id) = &mut *state;
+        let Some(suppressed) = limiter.admit((category, project_id), Instant::now()) else {
+            return false;
+        };
+        *next_id += 1;
+        Notification {
+            id: *next_id,
+            category,
+            project_id,
+            title: title.into(),
+            body: body.into(),
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis() as u64),
+            os: settings.os_notifications,
+            suppressed,
+        }
+    };
+    if let Err(err) = app_handle.emit(NOTIFICATION_EVENT, &notification) {
+        tracing::warn!(?err, "failed to emit notification");
+        return false;
+    }
+    true
+}
+
+/// Notify that pushing `what` of `project_id` failed with `err`, unless it was refused before anything was sent to
+/// the remote, which the user is told about right away.
+pub fn notify_push_failed(
+    app_handle: &AppHandle,
+    project_id: ProjectId,
+    what: &str,
+    err: &anyhow::Error,
+) {
+    if err.downcast_ref::<Code>() == Some(&Code::PushRefused) {
+        return;
+    }
+    notify(
+        app_handle,
+        NotificationCategory::PushRejected,
+        Some(project_id),
+        format!("Pushing {what} failed"),
+        format!("{err:#}"),
+    );
+}
+
+/// Notify about errors of watchers as they are published, and about the disk holding `app_data_dir` when it's
+/// nearly full.
+pub fn start(app_handle: AppHandle, app_data_dir: PathBuf) {
+    bus::event_bus().subscribe(
+        EventFilter {
+            project_id: None,
+            kinds: Some(vec![EventKind::WatcherError]),
+        },
+        {
+            let app_handle = app_handle.clone();
+            move |_, envelope| {
+                if let Event::WatcherError {
+                    project_id,
+                    message,
+                } = &envelope.event
+                {
+                    notify(
+                        &app_handle,
+                        NotificationCategory::WatcherStopped,
+                        Some(*project_id),
+                        "Changes may not be recorded",
+                        format!("The watcher of the project failed: {message}"),
+                    );
+                }
+                true
+            }
+        },
+    );
+
+    tauri::async_runtime::spawn(async move {
+        let mut previous = CheckStatus::Ok;
+        let mut interval = tokio::time::interval(DISK_SPACE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(available) = capabilities::free_disk_space(&app_data_dir) else {
+                continue;
+            };
+            let status = capabilities::disk_space_status(available);
+            // Only tell when it gets worse, not every time it's checked while the disk stays full.
+            if severity(status) > severity(previous) {
+                notify(
+                    &app_handle,
+                    NotificationCategory::DiskSpaceLow,
+                    None,
+                    "The disk is nearly full",
+                    format!(
+                        "Only {} MiB are left for recording changes and snapshots.",
+                        available / (1024 * 1024)
+                    ),
+                );
+            }
+            previous = status;
+        }
+    });
+}
+
+fn severity(status: CheckStatus) -> u8 {
+    match status {
+        CheckStatus::Ok | CheckStatus::NotApplicable => 0,
+        CheckStatus::Warning => 1,
+        CheckStatus::Error => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_notifications_are_held_back() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let key = (NotificationCategory::PushRejected, None);
+        assert_eq!(limiter.admit(key, start), Some(0));
+        assert_eq!(limiter.admit(key, start + Duration::from_secs(1)), None);
+        assert_eq!(limiter.admit(key, start + Duration::from_secs(2)), None);
+        assert_eq!(
+            limiter.admit(key, start + DEDUPLICATION_WINDOW),
+            Some(2),
+            "the next one tells how many were held back"
+        );
+        assert_eq!(
+            limiter.admit((NotificationCategory::DiskSpaceLow, None), start),
+            Some(0),
+            "other categories aren't affected"
+        );
+    }
+
+    #[test]
+    fn notifications_are_rate_limited_overall() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let key = |project| (NotificationCategory::WatcherStopped, Some(project));
+        let projects: Vec<_> = (0..=MAX_PER_RATE_WINDOW)
+            .map(|_| ProjectId::generate())
+            .collect();
+        for project in &projects[..MAX_PER_RATE_WINDOW] {
+            assert_eq!(limiter.admit(key(*project), start), Some(0));
+        }
+        let last = projects[MAX_PER_RATE_WINDOW];
+        assert_eq!(limiter.admit(key(last), start), None);
+        assert_eq!(limiter.admit(key(last), start + RATE_WINDOW), Some(1));
+    }
+}
@@ -0,0 +1,149 @@
+//! Cancel long-running operations, like cloning, fetching, exporting the history or restoring files.
+//!
+//! The frontend asks for an id with [`commands::begin_operation()`] and passes it to the command that may take
+//! long, which then runs as the operation with this id. [`commands::cancel_operation()`] asks it to stop, which it
+//! does the next time it checks, like when Git reports progress or between files, and fails with
+//! [`Code::Cancelled`]. Each id is for a single operation.
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_error::error::Code;
+
+/// How long an id that was issued but never used is kept.
+const UNUSED_ID_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+struct Issued {
+    should_interrupt: Arc<AtomicBool>,
+    issued_at: Instant,
+}
+
+/// The operations that were issued an id and didn't finish yet, to be managed by the app.
+#[derive(Default)]
+pub struct Operations(parking_lot::Mutex<HashMap<String, Issued>>);
+
+impl Operations {
+    /// Return a new id for an operation that can be cancelled.
+    fn issue(&self) -> String {
+        let operation_id = uuid::Uuid::new_v4().to_string();
+        let mut operations = self.0.lock();
+        operations.retain(|_, issued| {
+            Arc::strong_count(&issued.should_interrupt) > 1
+                || issued.issued_at.elapsed() < UNUSED_ID_LIFETIME
+        });
+        operations.insert(
+            operation_id.clone(),
+            Issued {
+                should_interrupt: Arc::default(),
+                issued_at: Instant::now(),
+            },
+        );
+        operation_id
+    }
+
+    /// Ask the operation with `operation_id` to stop, and return `false` if there is no such operation.
+    fn cancel(&self, operation_id: &str) -> bool {
+        match self.0.lock().get(operation_id) {
+            Some(issued) => {
+                issued.should_interrupt.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Call `run` with the flag that tells if the operation with `operation_id` should stop, and forget the
+    /// operation once it returns. If it fails after it was cancelled, the error is marked as [`Code::Cancelled`].
+    ///
+    /// Operations without id can't be cancelled.
+    pub fn run<T>(
+        &self,
+        operation_id: Option<&str>,
+        run: impl FnOnce(&AtomicBool) -> Result<T>,
+    ) -> Result<T> {
+        let should_interrupt = match operation_id {
+            Some(operation_id) => self
+                .0
+                .lock()
+                .get(operation_id)
+                .map(|issued| issued.should_interrupt.clone())
+                .with_context(|| format!("Operation '{operation_id}' is unknown or finished"))?,
+            None => Arc::default(),
+        };
+        let result = run(&should_interrupt);
+        if let Some(operation_id) = operation_id {
+            self.0.lock().remove(operation_id);
+        }
+        result.map_err(|err| {
+            if should_interrupt.load(Ordering::Relaxed) {
+                err.context(Code::Cancelled)
+            } else {
+                err
+            }
+        })
+    }
+}
+
+pub mod commands {
+    use tauri::State;
+    use tracing::instrument;
+
+    use super::Operations;
+    use crate::error::Error;
+
+    /// Return the id to run a long operation with, so it can be [cancelled](cancel_operation()) while it runs.
+    #[tauri::command(async)]
+    #[instrument(skip(operations), err(Debug))]
+    pub fn begin_operation(operations: State<'_, Operations>) -> Result<String, Error> {
+        Ok(operations.issue())
+    }
+
+    /// Ask the operation with `operation_id` to stop, and return `false` if it isn't known or finished already.
+    #[tauri::command(async)]
+    #[instrument(skip(operations), err(Debug))]
+    pub fn cancel_operation(
+        operations: State<'_, Operations>,
+        operation_id: String,
+    ) -> Result<bool, Error> {
+        Ok(operations.cancel(&operation_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_operations_fail_as_cancelled() {
+        let operations = Operations::default();
+        let operation_id = operations.issue();
+        assert!(operations.cancel(&operation_id));
+        let err = operations
+            .run(Some(&operation_id), |should_interrupt| {
+                anyhow::ensure!(!should_interrupt.load(Ordering::Relaxed), "interrupted");
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Code>(), Some(&Code::Cancelled));
+        assert!(
+            !operations.cancel(&operation_id),
+            "the operation is forgotten once it's done"
+        );
+        assert!(operations.run(Some(&operation_id), |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn operations_fail_as_usual_unless_cancelled() {
+        let operations = Operations::default();
+        let err = operations
+            .run(None, |_| -> Result<()> { anyhow::bail!("failed") })
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Code>(), None);
+    }
+}
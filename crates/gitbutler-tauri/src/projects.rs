@@ -1,15 +1,55 @@
-use gitbutler_project::Project;
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails},
+    OplogExt,
+};
+use gitbutler_project::{Controller, Project, ProjectId, StorageLocation, UpdateRequest};
+
+/// Pause or resume recording changes to files of the project with `project_id` in snapshots. Before pausing,
+/// a snapshot is taken so nothing before the pause is lost.
+pub fn set_recording_paused(
+    projects: &Controller,
+    project_id: ProjectId,
+    paused: bool,
+) -> anyhow::Result<Project> {
+    let project = projects.get(project_id)?;
+    if paused && !project.recording_paused {
+        let mut guard = project.exclusive_worktree_access();
+        if let Err(err) = project.create_snapshot(
+            SnapshotDetails::new(OperationKind::FileChanges),
+            guard.write_permission(),
+        ) {
+            tracing::warn!(
+                ?err,
+                "failed to snapshot changes before pausing the recording"
+            );
+        }
+    }
+    projects.update(&UpdateRequest {
+        id: project_id,
+        recording_paused: Some(paused),
+        ..Default::default()
+    })
+}
 
 pub mod commands {
+    use std::num::NonZeroU32;
     use std::path;
+    use std::time::Duration;
 
     use anyhow::Context;
     use but_settings::AppSettingsWithDiskSync;
+    use gitbutler_oplog::{entry::OperationKind, journal, secrets::SecretScanner};
     use gitbutler_project::{self as projects, Controller, ProjectId};
-    use tauri::{State, Window};
+    use gitbutler_repo::{clone, init};
+    use gitbutler_watcher::bus;
+    use tauri::{AppHandle, Emitter, Manager, State, Window};
     use tracing::instrument;
 
-    use crate::{error::Error, projects::ProjectForFrontend, window, WindowState};
+    use crate::{
+        error::Error,
+        projects::{AddProjectOutcome, AddedProject, ProjectForFrontend},
+        window, WindowState,
+    };
 
     #[tauri::command(async)]
     #[instrument(skip(projects), err(Debug))]
@@ -17,16 +57,224 @@ pub mod commands {
         projects: State<'_, Controller>,
         project: projects::UpdateRequest,
     ) -> Result<projects::Project, Error> {
-        Ok(projects.update(&project)?)
+        if let Some(secret_patterns) = &project.secret_patterns {
+            SecretScanner::new(secret_patterns)?;
+        }
+        let updated = projects.update(&project)?;
+        // Only the names of the settings are recorded, as their values may be credentials.
+        let settings = serde_json::to_value(&project)
+            .ok()
+            .and_then(|value| value.as_object().cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(name, value)| name != "id" && !value.is_null() && value != &false)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Err(err) = journal::record(
+            &updated,
+            OperationKind::UpdateProjectSettings,
+            [("settings", settings)],
+            &Default::default(),
+            &Default::default(),
+            None,
+        ) {
+            tracing::warn!(?err, "failed to record settings change in journal");
+        }
+        Ok(updated)
     }
 
+    /// Add the repository at `path` as project, along with where it's stored so the user can be warned if
+    /// the file watcher can't rely on it.
     #[tauri::command(async)]
     #[instrument(skip(projects), err(Debug))]
     pub fn add_project(
         projects: State<'_, Controller>,
         path: &path::Path,
+    ) -> Result<AddedProject, Error> {
+        let project = projects.add(path)?;
+        Ok(AddedProject {
+            storage_location: projects::StorageLocation::detect(&project.path),
+            inner: project,
+        })
+    }
+
+    /// Start a new project in `path`, which must be missing or an empty directory: initialize a repository with a
+    /// `.gitignore` from `template` in its initial commit, and add it as project.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn init_project(
+        projects: State<'_, Controller>,
+        path: &path::Path,
+        template: Option<init::Template>,
+    ) -> Result<AddedProject, Error> {
+        init::init_repository(path, template.unwrap_or_default())?;
+        let project = projects.add(path)?;
+        Ok(AddedProject {
+            storage_location: projects::StorageLocation::detect(&project.path),
+            inner: project,
+        })
+    }
+
+    /// Return the templates new projects can be started with, see [`init_project()`].
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn project_templates() -> Result<Vec<init::Template>, Error> {
+        Ok(init::Template::ALL.to_vec())
+    }
+
+    /// Clone the repository at `url` into `path` and add it as project. Only the last `depth` commits are fetched
+    /// if set, and the objects excluded by `filter`, like `blob:none`, are only fetched when needed.
+    ///
+    /// Progress is sent as `clone://progress` events.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle), err(Debug))]
+    pub fn clone_project(
+        projects: State<'_, Controller>,
+        app_handle: AppHandle,
+        url: &str,
+        path: &path::Path,
+        depth: Option<NonZeroU32>,
+        filter: Option<String>,
+    ) -> Result<AddedProject, Error> {
+        clone::clone_repository(url, path, depth, filter.as_deref(), |progress| {
+            if let Err(err) = app_handle.emit("clone://progress", &progress) {
+                tracing::warn!(?err, "failed to send clone progress");
+            }
+        })?;
+        let project = projects.add(path)?;
+        Ok(AddedProject {
+            storage_location: projects::StorageLocation::detect(&project.path),
+            inner: project,
+        })
+    }
+
+    /// Add the worktree-relative `subdirectory` of the repository of the project with `parent_id` as a project of
+    /// its own, like an app in a monorepo. It shares the watcher of the repository while open.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn add_subproject(
+        projects: State<'_, Controller>,
+        parent_id: ProjectId,
+        subdirectory: path::PathBuf,
+    ) -> Result<projects::Project, Error> {
+        Ok(projects.add_subproject(parent_id, &subdirectory)?)
+    }
+
+    /// Return the sub-projects in the repository of the project with `project_id`.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_subprojects(
+        projects: State<'_, Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<projects::Project>, Error> {
+        Ok(projects.subprojects(project_id)?)
+    }
+
+    /// Add all repositories at `paths` as projects, returning one outcome per path in the same order.
+    ///
+    /// The watchers of the added projects are started in the background afterwards, so their changes are recorded
+    /// before they are opened, which takes them over.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle, app_settings), err(Debug))]
+    pub fn add_projects(
+        projects: State<'_, Controller>,
+        app_handle: AppHandle,
+        app_settings: State<'_, AppSettingsWithDiskSync>,
+        paths: Vec<path::PathBuf>,
+    ) -> Result<Vec<AddProjectOutcome>, Error> {
+        let results = projects.add_many(&paths);
+        let added: Vec<_> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok().cloned())
+            .collect();
+        let app_settings = app_settings.inner().clone();
+        // Each watcher registers all directories of its worktree, which would keep the frontend waiting.
+        std::thread::spawn(move || {
+            let window_state = app_handle.state::<WindowState>();
+            for project in added {
+                if let Err(err) = window_state.watch_in_background(&project, app_settings.clone()) {
+                    tracing::warn!(?err, project_id = %project.id, "failed to start watcher of added project");
+                }
+            }
+            crate::tray::refresh(&app_handle);
+        });
+        Ok(paths
+            .into_iter()
+            .zip(results)
+            .map(|(path, result)| match result {
+                Ok(project) => AddProjectOutcome {
+                    path,
+                    storage_location: Some(projects::StorageLocation::detect(&project.path)),
+                    project: Some(project),
+                    error: None,
+                },
+                Err(err) => AddProjectOutcome {
+                    path,
+                    project: None,
+                    storage_location: None,
+                    error: Some(err.into()),
+                },
+            })
+            .collect())
+    }
+
+    /// Find repositories in `root_path` and below, descending at most `max_depth` directories,
+    /// so they can be added with [`add_projects()`].
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn discover_projects(
+        projects: State<'_, Controller>,
+        root_path: &path::Path,
+        max_depth: usize,
+    ) -> Result<Vec<projects::DiscoveredProject>, Error> {
+        Ok(projects.discover(root_path, max_depth)?)
+    }
+
+    /// Stop recording changes to files of the project with `project_id` in snapshots, for instance
+    /// while a code generator runs. A snapshot is taken first so nothing before the pause is lost.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle), err(Debug))]
+    pub fn pause_recording(
+        projects: State<'_, Controller>,
+        app_handle: AppHandle,
+        project_id: ProjectId,
+    ) -> Result<projects::Project, Error> {
+        let project = super::set_recording_paused(&projects, project_id, true)?;
+        crate::tray::refresh(&app_handle);
+        Ok(project)
+    }
+
+    /// Record changes to files of the project with `project_id` in snapshots again.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle), err(Debug))]
+    pub fn resume_recording(
+        projects: State<'_, Controller>,
+        app_handle: AppHandle,
+        project_id: ProjectId,
     ) -> Result<projects::Project, Error> {
-        Ok(projects.add(path)?)
+        let project = super::set_recording_paused(&projects, project_id, false)?;
+        crate::tray::refresh(&app_handle);
+        Ok(project)
+    }
+
+    /// Only watch and record changes to files in the worktree-relative directories `include_paths` of the project
+    /// with `project_id`, or in all of the worktree if empty. Running watchers follow without being restarted.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, window_state), err(Debug))]
+    pub fn set_watch_include_paths(
+        projects: State<'_, Controller>,
+        window_state: State<'_, WindowState>,
+        project_id: ProjectId,
+        include_paths: Vec<path::PathBuf>,
+    ) -> Result<projects::Project, Error> {
+        let project = projects.update(&projects::UpdateRequest {
+            id: project_id,
+            watch_include_paths: Some(include_paths),
+            ..Default::default()
+        })?;
+        window_state.set_watch_include_paths(project_id, &project.watch_include_paths)?;
+        Ok(project)
     }
 
     #[tauri::command(async)]
@@ -43,6 +291,61 @@ pub mod commands {
         }
     }
 
+    /// Return the changes sent for the project with `project_id` after `since_seq`, for a reloaded window
+    /// to catch up. Pass `None` as `since_seq` to only learn the latest sequence number.
+    #[tauri::command(async)]
+    #[instrument(skip(window_state), err(Debug))]
+    pub fn replay_events(
+        window_state: State<'_, WindowState>,
+        project_id: ProjectId,
+        since_seq: Option<u64>,
+    ) -> Result<window::state::event::EventReplay, Error> {
+        Ok(window_state.replay_events(project_id, since_seq))
+    }
+
+    /// Have `window` receive `events` events with the id of the subscription and every event of the
+    /// [event bus](bus::event_bus()) that matches `filter`, until it's closed or the subscription is ended.
+    #[tauri::command(async)]
+    #[instrument(skip(window), err(Debug))]
+    pub fn subscribe_events(
+        window: Window,
+        filter: bus::EventFilter,
+    ) -> Result<bus::SubscriptionId, Error> {
+        let app = window.app_handle().clone();
+        let label = window.label().to_owned();
+        Ok(
+            bus::event_bus().subscribe(filter, move |subscription_id, envelope| {
+                if app.get_window(&label).is_none() {
+                    return false;
+                }
+                let payload = serde_json::json!({
+                    "subscriptionId": subscription_id,
+                    "envelope": envelope,
+                });
+                if let Err(err) = app.emit_to(label.as_str(), "events", payload) {
+                    tracing::warn!(?err, window = label, "failed to send event");
+                }
+                true
+            }),
+        )
+    }
+
+    /// End the subscription with `subscription_id`, returning `false` if it didn't exist.
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn unsubscribe_events(subscription_id: bus::SubscriptionId) -> Result<bool, Error> {
+        Ok(bus::event_bus().unsubscribe(subscription_id))
+    }
+
+    /// Return how many filesystem events the watchers of the open projects received and handled.
+    #[tauri::command(async)]
+    #[instrument(skip(window_state), err(Debug))]
+    pub fn watcher_metrics(
+        window_state: State<'_, WindowState>,
+    ) -> Result<Vec<gitbutler_watcher::WatcherMetrics>, Error> {
+        Ok(window_state.watcher_metrics())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, window_state), err(Debug))]
     pub fn list_projects(
@@ -74,11 +377,69 @@ pub mod commands {
         id: ProjectId,
     ) -> Result<(), Error> {
         let project = projects.get_validated(id).context("project not found")?;
-        Ok(window_state.set_project_to_window(
+        window_state.set_project_to_window(
             window.label(),
             &project,
             app_settings.inner().clone(),
-        )?)
+        )?;
+        // Histories whose key is in the keychain are unlocked right away, others once their passphrase is entered.
+        if let Err(err) = gitbutler_oplog::encryption::ensure_unlocked(&project) {
+            tracing::info!(?err, "history stays locked");
+        }
+        // Deltas that were being written when the app was last killed are finished or quarantined.
+        match gitbutler_oplog::deltas::recover(&project) {
+            Ok(report) if !report.is_empty() => {
+                tracing::warn!(?report, "recovered interrupted writes of deltas");
+                window.emit("storage_recovered", report).ok();
+            }
+            Ok(_) => {}
+            Err(err) => tracing::error!(?err, "failed to recover interrupted writes of deltas"),
+        }
+        crate::tray::refresh(window.app_handle());
+        Ok(())
+    }
+
+    /// Mark the project with `project_id` as active, restarting its watcher if it was stopped after being
+    /// inactive for too long.
+    #[tauri::command(async)]
+    #[instrument(skip(window_state), err(Debug))]
+    pub fn activate_project(
+        window_state: State<'_, WindowState>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        Ok(window_state.activate_project(project_id)?)
+    }
+
+    /// Have `window` receive `project://<id>/files/changed` events whenever the file at the worktree-relative
+    /// `file_path` changes on disk, typically because it's displayed in an editor.
+    #[tauri::command(async)]
+    #[instrument(skip(window_state, window), err(Debug))]
+    pub fn subscribe_file(
+        window_state: State<'_, WindowState>,
+        window: Window,
+        project_id: ProjectId,
+        file_path: path::PathBuf,
+    ) -> Result<(), Error> {
+        if !file_path.is_relative() {
+            return Err(anyhow::format_err!(
+                "Can only subscribe to worktree-relative paths, got '{}'",
+                file_path.display()
+            )
+            .into());
+        }
+        Ok(window_state.subscribe_file(window.label(), project_id, file_path)?)
+    }
+
+    /// Stop sending change events for `file_path` to `window`, as previously requested with [`subscribe_file()`].
+    #[tauri::command(async)]
+    #[instrument(skip(window_state, window), err(Debug))]
+    pub fn unsubscribe_file(
+        window_state: State<'_, WindowState>,
+        window: Window,
+        file_path: path::PathBuf,
+    ) -> Result<(), Error> {
+        window_state.unsubscribe_file(window.label(), &file_path);
+        Ok(())
     }
 
     /// Open the project with the given ID in a new Window, or focus an existing one.
@@ -86,8 +447,19 @@ pub mod commands {
     /// Note that this command is blocking the main thread just to prevent the chance for races
     /// without haveing to lock explicitly.
     #[tauri::command]
-    #[instrument(skip(handle), err(Debug))]
-    pub fn open_project_in_window(handle: tauri::AppHandle, id: ProjectId) -> Result<(), Error> {
+    #[instrument(skip(handle, window_state), err(Debug))]
+    pub fn open_project_in_window(
+        handle: tauri::AppHandle,
+        window_state: State<'_, WindowState>,
+        id: ProjectId,
+    ) -> Result<(), Error> {
+        if let Some(window) = window_state
+            .window_for_project(id)
+            .and_then(|label| handle.get_webview_window(&label))
+        {
+            window.set_focus().map_err(anyhow::Error::from)?;
+            return Ok(());
+        }
         let label = std::time::UNIX_EPOCH
             .elapsed()
             .or_else(|_| std::time::UNIX_EPOCH.duration_since(std::time::SystemTime::now()))
@@ -97,10 +469,57 @@ pub mod commands {
         Ok(())
     }
 
+    /// Delete the project with `id` and stop watching it, while keeping its data for the grace period
+    /// configured in the app settings so it can be restored with [`undo_delete_project()`].
+    ///
+    /// Projects whose grace period expired are purged along the way.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, window_state, app_settings), err(Debug))]
+    pub fn delete_project(
+        projects: State<'_, Controller>,
+        window_state: State<'_, WindowState>,
+        app_settings: State<'_, AppSettingsWithDiskSync>,
+        id: ProjectId,
+    ) -> Result<(), Error> {
+        projects.delete(id)?;
+        window_state.remove_project(id);
+        gitbutler_command_context::repository_pool::forget(id);
+
+        let grace_period =
+            Duration::from_secs(app_settings.get()?.project_deletion_grace_period_seconds);
+        projects.purge_expired(grace_period)?;
+        Ok(())
+    }
+
+    /// List the projects with `project_ids` first, in that order, followed by all other projects by title.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn reorder_projects(
+        projects: State<'_, Controller>,
+        project_ids: Vec<ProjectId>,
+    ) -> Result<(), Error> {
+        Ok(projects.reorder(&project_ids)?)
+    }
+
+    /// Put the project with `id` into `group`, or take it out of its group if `group` is unset or empty.
     #[tauri::command(async)]
     #[instrument(skip(projects), err(Debug))]
-    pub fn delete_project(projects: State<'_, Controller>, id: ProjectId) -> Result<(), Error> {
-        projects.delete(id).map_err(Into::into)
+    pub fn set_project_group(
+        projects: State<'_, Controller>,
+        id: ProjectId,
+        group: Option<String>,
+    ) -> Result<projects::Project, Error> {
+        Ok(projects.set_group(id, group)?)
+    }
+
+    /// Restore the project with `id` if it was deleted within the grace period.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn undo_delete_project(
+        projects: State<'_, Controller>,
+        id: ProjectId,
+    ) -> Result<projects::Project, Error> {
+        Ok(projects.undo_delete(id)?)
     }
 }
 
@@ -111,3 +530,25 @@ pub struct ProjectForFrontend {
     /// Tell if the project is known to be open in a Window in the frontend.
     pub is_open: bool,
 }
+
+/// The project added with [`commands::add_project()`].
+#[derive(serde::Serialize)]
+pub struct AddedProject {
+    #[serde(flatten)]
+    pub inner: Project,
+    /// Where the files of the project are stored. Unless it's [local](StorageLocation::Local), the user should
+    /// be warned that changes may be missed or seen where there are none, and that Git may fail to lock files.
+    pub storage_location: StorageLocation,
+}
+
+/// The result of adding a single path as part of [`commands::add_projects()`].
+#[derive(serde::Serialize)]
+pub struct AddProjectOutcome {
+    pub path: std::path::PathBuf,
+    /// The newly added project, if adding it succeeded.
+    pub project: Option<Project>,
+    /// Where the files of the newly added project are stored.
+    pub storage_location: Option<StorageLocation>,
+    /// Why the path couldn't be added as project.
+    pub error: Option<crate::error::Error>,
+}
@@ -0,0 +1,375 @@
+//! Enforce the [read-only mode](gitbutler_project::Project::read_only) of projects for all commands, so none that
+//! would change a read-only project can slip through, no matter which part of the frontend invokes it.
+use gitbutler_project::{Controller, ProjectId};
+use serde_json::Value;
+use tauri::{ipc::Invoke, ipc::InvokeBody, Manager, Runtime};
+
+use crate::error::Error;
+
+/// The commands that change the worktree, the branches, the configuration or the history of a project.
+///
+/// Commands that only record changes, like taking snapshots, or that only fetch, are still allowed. Each registered
+/// command is either listed here or known not to change the project, which is checked by the tests.
+const MUTATING_COMMANDS: &[&str] = &[
+    // Worktree and branches
+    "mark_resolved",
+    "create_tag",
+    "delete_tag",
+    "merge_branch",
+    "apply_patch",
+    "continue_merge",
+    "abort_merge",
+    "resolve_conflict",
+    "launch_merge_tool",
+    "create_virtual_branch",
+    "delete_local_branch",
+    "delete_branches",
+    "commit_virtual_branch",
+    "commit_session",
+    "suspend_workspace",
+    "resume_workspace",
+    "set_base_branch",
+    "push_base_branch",
+    "integrate_upstream_commits",
+    "update_virtual_branch",
+    "update_branch_order",
+    "unapply_without_saving_virtual_branch",
+    "save_and_unapply_virtual_branch",
+    "unapply_lines",
+    "unapply_ownership",
+    "transfer_ownership",
+    "reset_files",
+    "discard_changes",
+    "create_virtual_branch_from_branch",
+    "reset_virtual_branch",
+    "amend_virtual_branch",
+    "amend_commit",
+    "reword_commit",
+    "move_commit_file",
+    "undo_commit",
+    "cherry_pick",
+    "revert_commit",
+    "start_rebase",
+    "resume_rebase",
+    "abort_rebase",
+    "insert_blank_commit",
+    "reorder_stack",
+    "update_commit_message",
+    "squash_commits",
+    "move_commit",
+    "integrate_upstream",
+    "resolve_upstream_integration",
+    "update_from_base",
+    "create_series",
+    "remove_series",
+    "update_series_name",
+    "update_series_description",
+    "update_series_pr_number",
+    "push_stack",
+    "push_stack_to_review",
+    "create_commit_from_worktree_changes",
+    "amend_commit_from_worktree_changes",
+    "enter_edit_mode",
+    "save_edit_and_return_to_workspace",
+    "abort_edit_and_return_to_workspace",
+    // Configuration and maintenance
+    "git_set_local_config",
+    "set_project_identity",
+    "add_remote",
+    "remove_remote",
+    "set_remote_url",
+    "optimize_repository",
+    "unshallow",
+    "set_gb_config",
+    // Hooks can change the worktree, like formatters do.
+    "pre_commit_hook",
+    "post_commit_hook",
+    // Remotes
+    "create_pull_request",
+    // History
+    "restore_snapshot",
+    "recover_deleted_file",
+    "restore_directory_at",
+    "undo_last_operation",
+    "import_history",
+    "repair_history",
+    "repair_meta_ref",
+    "cleanup_history",
+    "restore_history_from_remote",
+    "merge_remote_history",
+];
+
+/// Wrap `handler`, as created by [`tauri::generate_handler!`], to reject the invocations of
+/// [mutating commands](MUTATING_COMMANDS) for read-only projects before they run.
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        if let Some(err) = refusal(&invoke) {
+            invoke.resolver.reject(Error::from(err));
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// Return why `invoke` is refused, if it's a mutating command for a read-only project.
+fn refusal<R: Runtime>(invoke: &Invoke<R>) -> Option<anyhow::Error> {
+    let command = invoke.message.command();
+    if !MUTATING_COMMANDS.contains(&command) {
+        return None;
+    }
+    let InvokeBody::Json(args) = invoke.message.payload() else {
+        return None;
+    };
+    let project_id = project_id(args)?;
+    let project = invoke
+        .message
+        .webview_ref()
+        .state::<Controller>()
+        .get(project_id)
+        .ok()?;
+    project.ensure_writable(&command.replace('_', " ")).err()
+}
+
+/// Return the id of the project that the arguments of a command refer to, by the names commands use for it.
+fn project_id(args: &Value) -> Option<ProjectId> {
+    ["projectId", "id"]
+        .into_iter()
+        .find_map(|name| serde_json::from_value(args.get(name)?.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The registered commands that don't change the worktree, the branches, the configuration or the history of
+    /// a project, so they are allowed for read-only projects.
+    const NOT_MUTATING: &[&str] = &[
+        // Global configuration, settings and the app itself
+        "git_set_global_config",
+        "git_remove_global_config",
+        "git_get_global_config",
+        "get_git_config",
+        "set_git_config",
+        "delete_all_data",
+        "get_logs_archive_path",
+        "get_project_archive_path",
+        "set_user",
+        "delete_user",
+        "get_user",
+        "secret_get_global",
+        "secret_set_global",
+        "get_recent_traces",
+        "list_crash_reports",
+        "get_logs",
+        "set_log_level",
+        "diagnostics",
+        "submit_crash_report",
+        "set_telemetry_enabled",
+        "show_pending_telemetry",
+        "take_pending_telemetry",
+        "menu_item_set_enabled",
+        "get_editor_link_scheme",
+        "init_device_oauth",
+        "check_auth_status",
+        "submit_prompt_response",
+        "open_url",
+        "get_app_settings",
+        "update_onboarding_complete",
+        "onboarding_state",
+        "capability_report",
+        "request_confirmation",
+        "begin_stream",
+        "end_stream",
+        "begin_operation",
+        "cancel_operation",
+        "complete_onboarding_step",
+        "update_telemetry",
+        "update_feature_flags",
+        "update_concurrency",
+        "update_project_deletion_grace_period",
+        "update_watcher_idle_timeout",
+        "update_background_mode",
+        "set_update_channel",
+        "check_for_updates",
+        "download_update",
+        "install_update",
+        "update_local_api",
+        "update_notifications",
+        "get_public_key",
+        "use_generated_key",
+        "take_deep_link_project",
+        "env_vars",
+        // Projects as GitButler knows them, which includes turning read-only mode off again
+        "add_project",
+        "init_project",
+        "project_templates",
+        "clone_project",
+        "git_clone_repository",
+        "add_subproject",
+        "list_subprojects",
+        "add_projects",
+        "discover_projects",
+        "get_project",
+        "update_project",
+        "delete_project",
+        "undo_delete_project",
+        "reorder_projects",
+        "set_project_group",
+        "list_projects",
+        "set_project_active",
+        "activate_project",
+        "open_project_in_window",
+        // Watching and recording
+        "pause_recording",
+        "resume_recording",
+        "set_watch_include_paths",
+        "replay_events",
+        "watcher_metrics",
+        "subscribe_events",
+        "unsubscribe_events",
+        "subscribe_file",
+        "unsubscribe_file",
+        "editor_heartbeat",
+        "take_synced_snapshot",
+        // Reading the repository
+        "git_remote_branches",
+        "git_head",
+        "git_index_size",
+        "git_get_local_config",
+        "git_test_push",
+        "git_test_fetch",
+        "check_signing_settings",
+        "list_tags",
+        "branch_tracking_status",
+        "get_uncommited_files",
+        "get_commit_file",
+        "get_workspace_file",
+        "get_worktree_file",
+        "get_file_versions",
+        "get_file_base64",
+        "file_tree",
+        "list_project_files",
+        "verify_commit_signature",
+        "repo_health",
+        "export_archive",
+        "get_project_identity",
+        "format_patch",
+        "get_conflict_versions",
+        "launch_diff_tool",
+        "message_hook",
+        "commit_template",
+        "validate_commit_message",
+        "get_gb_config",
+        "list_remotes",
+        "operating_mode",
+        "edit_initial_index_state",
+        "fetch_from_remotes",
+        // Branches and the workspace
+        "list_virtual_branches",
+        "stale_branches",
+        "list_suspended_workspaces",
+        "suggest_change_groups",
+        "get_base_branch_data",
+        "get_default_branch",
+        "can_apply_remote_branch",
+        "list_commit_files",
+        "pending_rebase",
+        "find_git_branches",
+        "list_branches",
+        "get_branch_listing_details",
+        "normalize_branch_name",
+        "upstream_integration_statuses",
+        "find_commit",
+        "check_push_stack",
+        "stacks",
+        "stack_branches",
+        "hunk_dependencies_for_workspace_changes",
+        "worktree_changes",
+        "commit_changes",
+        "tree_change_diffs",
+        "commit_context",
+        "compute_diff",
+        // Forges
+        "get_available_review_templates",
+        "get_review_template_contents",
+        "branch_forge_status",
+        "list_pull_requests",
+        "ci_status",
+        // History, whose annotations and backups don't change what was recorded
+        "list_snapshots",
+        "list_operations",
+        "snapshot_diff",
+        "snapshot_playback",
+        "activity_summary",
+        "list_sessions",
+        "list_deltas",
+        "profile_reconstruction",
+        "file_history",
+        "list_deleted_files",
+        "history_bisect",
+        "add_bookmark",
+        "update_bookmark",
+        "remove_bookmark",
+        "list_bookmarks",
+        "export_history",
+        "share_session",
+        "open_shared_session",
+        "verify_history",
+        "scan_history_for_secrets",
+        "data_usage",
+        "history_budget",
+        "unlock_history",
+        "encrypt_history",
+        "decrypt_history",
+        "backup_history_to_remote",
+    ];
+
+    /// Return the names of the commands registered with `generate_handler!` in `main.rs`.
+    fn registered_commands() -> Vec<&'static str> {
+        let (_, handlers) = include_str!("main.rs")
+            .split_once("generate_handler![")
+            .expect("commands are registered");
+        handlers
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !line.starts_with(']'))
+            .filter(|line| !line.starts_with("//") && !line.starts_with("#["))
+            .filter_map(|line| line.trim_end_matches(',').rsplit("::").next())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn every_registered_command_is_known_to_mutate_or_not() {
+        let registered = registered_commands();
+        for name in MUTATING_COMMANDS.iter().chain(NOT_MUTATING) {
+            assert!(
+                registered.contains(name),
+                "'{name}' isn't a registered command"
+            );
+        }
+        for name in registered {
+            assert!(
+                MUTATING_COMMANDS.contains(&name) != NOT_MUTATING.contains(&name),
+                "'{name}' needs to be listed as either mutating or not mutating, exactly once"
+            );
+        }
+    }
+
+    #[test]
+    fn the_project_is_found_by_the_names_commands_use() {
+        let id = ProjectId::generate();
+        assert_eq!(
+            project_id(&serde_json::json!({ "projectId": id })),
+            Some(id)
+        );
+        assert_eq!(project_id(&serde_json::json!({ "id": id })), Some(id));
+        assert_eq!(
+            project_id(&serde_json::json!({ "projectId": "nope" })),
+            None
+        );
+        assert_eq!(project_id(&serde_json::json!({})), None);
+    }
+}
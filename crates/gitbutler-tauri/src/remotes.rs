@@ -26,3 +26,26 @@ pub fn add_remote(
     let project = projects.get(project_id)?;
     Ok(project.add_remote(name, url)?)
 }
+
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn remove_remote(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    name: &str,
+) -> Result<(), Error> {
+    let project = projects.get(project_id)?;
+    Ok(project.remove_remote(name)?)
+}
+
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn set_remote_url(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    name: &str,
+    url: &str,
+) -> Result<(), Error> {
+    let project = projects.get(project_id)?;
+    Ok(project.set_remote_url(name, url)?)
+}
@@ -1,17 +1,33 @@
 pub mod commands {
-    use crate::error::{Error, UnmarkedError};
+    use crate::error::Error;
+    use crate::operations::Operations;
+    use crate::worktree_writes;
     use anyhow::Result;
     use but_settings::AppSettingsWithDiskSync;
     use gitbutler_branch_actions::{hooks, RemoteBranchFile};
     use gitbutler_command_context::CommandContext;
+    use gitbutler_fs::FileStamp;
     use gitbutler_project as projects;
     use gitbutler_project::ProjectId;
-    use gitbutler_repo::hooks::{HookResult, MessageHookResult};
-    use gitbutler_repo::{FileInfo, RepoCommands};
+    use gitbutler_repo::archive::{self, ArchiveFormat};
+    use gitbutler_repo::clone;
+    use gitbutler_repo::commit_message::{self, Violation};
+    use gitbutler_repo::commit_signature::{verify_commit_signature, SignatureVerification};
+    use gitbutler_repo::external_tool::{self, ExternalToolOutcome};
+    use gitbutler_repo::health::{self, RepoHealth};
+    use gitbutler_repo::hooks::{HookOutput, HookResult, MessageHookResult};
+    use gitbutler_repo::identity::{self, Identity};
+    use gitbutler_repo::merge::{self, ConflictVersions, MergeOutcome};
+    use gitbutler_repo::patches::{self, PatchApplication};
+    use gitbutler_repo::tags::Tag;
+    use gitbutler_repo::tracking::{self, TrackingStatus};
+    use gitbutler_repo::{
+        FileInfo, FileTreeEntry, FileVersions, RepoCommands, DEFAULT_MAX_FILE_SIZE,
+    };
     use gitbutler_stack::BranchOwnershipClaims;
-    use std::path::Path;
-    use std::sync::atomic::AtomicBool;
-    use tauri::State;
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use tauri::{AppHandle, Emitter, State, Window};
     use tracing::instrument;
 
     #[tauri::command(async)]
@@ -47,19 +63,23 @@ pub mod commands {
         project.check_signing_settings().map_err(Into::into)
     }
 
+    /// Clone the repository at `repository_url` into `target_dir`, which can be cancelled while it runs as the
+    /// operation with `operation_id`.
     #[tauri::command(async)]
-    #[instrument]
+    #[instrument(skip(operations))]
     pub fn git_clone_repository(
+        operations: State<'_, Operations>,
         repository_url: &str,
         target_dir: &Path,
-    ) -> Result<(), UnmarkedError> {
-        let should_interrupt = AtomicBool::new(false);
-
-        gix::prepare_clone(repository_url, target_dir)?
-            .fetch_then_checkout(gix::progress::Discard, &should_interrupt)
-            .map(|(checkout, _outcome)| checkout)?
-            .main_worktree(gix::progress::Discard, &should_interrupt)?;
-        Ok(())
+        operation_id: Option<String>,
+    ) -> Result<(), Error> {
+        Ok(operations.run(operation_id.as_deref(), |should_interrupt| {
+            gix::prepare_clone(repository_url, target_dir)?
+                .fetch_then_checkout(gix::progress::Discard, should_interrupt)
+                .map(|(checkout, _outcome)| checkout)?
+                .main_worktree(gix::progress::Discard, should_interrupt)?;
+            Ok(())
+        })?)
     }
 
     #[tauri::command(async)]
@@ -88,6 +108,344 @@ pub mod commands {
         Ok(project.read_file_from_commit(commit_oid, relative_path)?)
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn verify_commit_signature(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        commit_id: String,
+    ) -> Result<SignatureVerification, Error> {
+        let project = projects.get(project_id)?;
+        let commit_id = git2::Oid::from_str(&commit_id).map_err(anyhow::Error::from)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(verify_commit_signature(&repo, commit_id)?)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_tags(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Tag>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(project.tags()?)
+    }
+
+    /// Create a tag called `name` pointing to the commit `oid`, which is annotated if it has a `message` and
+    /// signed if `sign` is set. Returns the id of the tag object, or of the commit for lightweight tags.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn create_tag(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: &str,
+        oid: String,
+        message: Option<&str>,
+        sign: Option<bool>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let oid = git2::Oid::from_str(&oid).map_err(anyhow::Error::from)?;
+        let tag_id = project.create_tag(name, oid, message, sign.unwrap_or(false))?;
+        Ok(tag_id.to_string())
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn delete_tag(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: &str,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        Ok(project.delete_tag(name)?)
+    }
+
+    /// Return how far each local branch is ahead of and behind its upstream, and whether the last fetch is too
+    /// old for these counts to be trusted.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn branch_tracking_status(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<TrackingStatus, Error> {
+        let project = projects.get(project_id)?;
+        Ok(tracking::tracking_status(&project)?)
+    }
+
+    /// Merge `branch` into the branch that is checked out, which must not be the GitButler workspace.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn merge_branch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: String,
+    ) -> Result<MergeOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(merge::merge_branch(&repo, &branch)?)
+    }
+
+    /// Report the size of the repository of the project, its stale branches and whether it should be optimized.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn repo_health(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<RepoHealth, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(health::repo_health(&repo)?)
+    }
+
+    /// Optimize the repository of the project in the background, sending progress as
+    /// `project://<id>/optimize/progress` events and the outcome as a `project://<id>/optimize/finished` event,
+    /// with the error if it failed, to the calling window.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle, window), err(Debug))]
+    pub fn optimize_repository(
+        projects: State<'_, projects::Controller>,
+        app_handle: AppHandle,
+        window: Window,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        let label = window.label().to_owned();
+        std::thread::spawn(move || {
+            let progress_event = format!("project://{project_id}/optimize/progress");
+            let result = health::optimize(&repo, |progress| {
+                if let Err(err) = app_handle.emit_to(label.as_str(), &progress_event, &progress) {
+                    tracing::warn!(?err, "failed to send optimize progress");
+                }
+            });
+            let error = result.err().map(|err| format!("{err:#}"));
+            if let Err(err) = app_handle.emit_to(
+                label.as_str(),
+                &format!("project://{project_id}/optimize/finished"),
+                OptimizeFinished { error },
+            ) {
+                tracing::warn!(?err, "failed to send optimize outcome");
+            }
+        });
+        Ok(())
+    }
+
+    /// The payload of the event sent once [`optimize_repository()`] is done.
+    #[derive(Clone, serde::Serialize)]
+    struct OptimizeFinished {
+        error: Option<String>,
+    }
+
+    /// Fetch `depth` more commits of the history of a shallow clone, or all of it if `None`, sending progress as
+    /// `project://<id>/unshallow/progress` events to the calling window.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, app_handle, window), err(Debug))]
+    pub fn unshallow(
+        projects: State<'_, projects::Controller>,
+        app_handle: AppHandle,
+        window: Window,
+        project_id: ProjectId,
+        depth: Option<NonZeroU32>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        let event_name = format!("project://{project_id}/unshallow/progress");
+        Ok(clone::unshallow(&repo, depth, |progress| {
+            if let Err(err) = app_handle.emit_to(window.label(), &event_name, &progress) {
+                tracing::warn!(?err, "failed to send unshallow progress");
+            }
+        })?)
+    }
+
+    /// Write the files of `treeish`, or of the worktree if `None`, as an archive in `format` to `path`, and return
+    /// how many files it contains.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn export_archive(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        treeish: Option<String>,
+        format: ArchiveFormat,
+        path: PathBuf,
+    ) -> Result<usize, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(archive::export_archive(
+            &repo,
+            treeish.as_deref(),
+            format,
+            &path,
+        )?)
+    }
+
+    /// Return the identity pinned for the commits GitButler creates in the project, if there is one.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_project_identity(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Option<Identity>, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(identity::identity(&repo)?)
+    }
+
+    /// Pin `identity` as author and committer of the commits GitButler creates in the project, regardless of the
+    /// global Git configuration, or unpin it if `None`. Return warnings if the identity doesn't seem to fit the
+    /// hosts of the remotes.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn set_project_identity(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        identity: Option<Identity>,
+    ) -> Result<Vec<String>, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(identity::set_identity(&repo, identity.as_ref())?)
+    }
+
+    /// Write one patch file per commit in `range`, like `main..feature`, to `out_dir` and return their paths.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn format_patch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        range: &str,
+        out_dir: PathBuf,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(patches::format_patch(&repo, range, &out_dir)?)
+    }
+
+    /// Apply the patches in `patch_text` to the worktree as uncommitted changes, or only check that they apply
+    /// if `check_only` is set.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, patch_text), err(Debug))]
+    pub fn apply_patch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        patch_text: &str,
+        check_only: bool,
+    ) -> Result<PatchApplication, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        let _guard = project.exclusive_worktree_access();
+        Ok(patches::apply_patch(&repo, patch_text, check_only)?)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn continue_merge(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(merge::continue_merge(&repo)?.to_string())
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn abort_merge(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(merge::abort_merge(&repo)?)
+    }
+
+    /// Return the merge base, ours and theirs versions of the conflicted file at `file_path`.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_conflict_versions(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+    ) -> Result<ConflictVersions, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(merge::conflict_versions(&repo, &file_path)?)
+    }
+
+    /// Write `resolved_content` to the conflicted file at `file_path` and stage it, once it isn't being edited,
+    /// unless it changed since its versions were read with the `stamp` they were returned with.
+    #[tauri::command(async)]
+    #[instrument(skip(app, projects, resolved_content), err(Debug))]
+    pub fn resolve_conflict(
+        app: AppHandle,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+        stamp: FileStamp,
+        resolved_content: String,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(worktree_writes::coordinate(
+            &app,
+            &project,
+            &file_path,
+            || merge::resolve_conflict(&repo, &file_path, stamp, resolved_content.as_bytes()),
+        )?)
+    }
+
+    /// Resolve the conflicted file at `file_path` in the merge tool configured in Git, and stage it if it was saved.
+    ///
+    /// This only returns once the tool was closed.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn launch_merge_tool(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+    ) -> Result<ExternalToolOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(external_tool::run_merge_tool(&repo, &file_path)?)
+    }
+
+    /// Show the uncommitted changes of the file at `file_path` in the diff tool configured in Git.
+    ///
+    /// This only returns once the tool was closed.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn launch_diff_tool(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+    ) -> Result<ExternalToolOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(external_tool::run_diff_tool(&repo, &file_path)?)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects))]
+    pub fn file_tree(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        subpath: Option<PathBuf>,
+    ) -> Result<Vec<FileTreeEntry>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(project.file_tree(subpath.as_deref())?)
+    }
+
+    /// List the tracked files of the project, optionally only those matching `glob` and at most `limit` of them.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_project_files(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        glob: Option<String>,
+        limit: Option<usize>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(project.list_project_files(glob.as_deref(), limit)?)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects))]
     pub fn get_workspace_file(
@@ -99,41 +457,161 @@ pub mod commands {
         Ok(project.read_file_from_workspace(relative_path)?)
     }
 
+    /// Read `relative_path` from the worktree only, without falling back to the index or `HEAD`. The content of
+    /// files larger than `max_size` bytes isn't returned.
     #[tauri::command(async)]
-    #[instrument(skip(projects, settings))]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_worktree_file(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        relative_path: &Path,
+        max_size: Option<u64>,
+    ) -> Result<FileInfo, Error> {
+        let project = projects.get(project_id)?;
+        Ok(project
+            .read_file_from_worktree(relative_path, max_size.unwrap_or(DEFAULT_MAX_FILE_SIZE))?)
+    }
+
+    /// Read `relative_path` from the worktree, the index and `HEAD` at once.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_file_versions(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        relative_path: &Path,
+        max_size: Option<u64>,
+    ) -> Result<FileVersions, Error> {
+        let project = projects.get(project_id)?;
+        Ok(project.read_file_versions(relative_path, max_size.unwrap_or(DEFAULT_MAX_FILE_SIZE))?)
+    }
+
+    /// Read `relative_path` from the commit with `commit_id`, or from the worktree if unset, with its content
+    /// as base64 so the bytes are returned exactly, whatever their encoding.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_file_base64(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        relative_path: &Path,
+        commit_id: Option<String>,
+        max_size: Option<u64>,
+    ) -> Result<FileInfo, Error> {
+        let project = projects.get(project_id)?;
+        let commit_id = commit_id
+            .map(|id| git2::Oid::from_str(&id))
+            .transpose()
+            .map_err(anyhow::Error::from)?;
+        let content = project.read_file_bytes(
+            relative_path,
+            commit_id,
+            max_size.unwrap_or(DEFAULT_MAX_FILE_SIZE),
+        )?;
+        Ok(content.map_or_else(FileInfo::deleted, |content| {
+            FileInfo::base64(relative_path, &content)
+        }))
+    }
+
+    /// Run the `pre-commit` hook with only the changes in `ownership` staged.
+    ///
+    /// Hook output is sent line by line as `project://<project_id>/hooks/output` events to the calling window while
+    /// it's running.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, app_handle, window))]
     pub fn pre_commit_hook(
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
+        app_handle: AppHandle,
+        window: Window,
         project_id: ProjectId,
         ownership: BranchOwnershipClaims,
     ) -> Result<HookResult, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        Ok(hooks::pre_commit(&ctx, &ownership)?)
+        Ok(hooks::pre_commit(
+            &ctx,
+            &ownership,
+            emit_hook_output(&app_handle, &window, project_id),
+        )?)
     }
 
     #[tauri::command(async)]
-    #[instrument(skip(projects, settings))]
+    #[instrument(skip(projects, settings, app_handle, window))]
     pub fn post_commit_hook(
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
+        app_handle: AppHandle,
+        window: Window,
         project_id: ProjectId,
     ) -> Result<HookResult, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        Ok(gitbutler_repo::hooks::post_commit(&ctx)?)
+        Ok(gitbutler_repo::hooks::post_commit(
+            &ctx,
+            emit_hook_output(&app_handle, &window, project_id),
+        )?)
     }
 
     #[tauri::command(async)]
-    #[instrument(skip(projects, settings))]
+    #[instrument(skip(projects, settings, app_handle, window))]
     pub fn message_hook(
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
+        app_handle: AppHandle,
+        window: Window,
         project_id: ProjectId,
         message: String,
     ) -> Result<MessageHookResult, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        Ok(gitbutler_repo::hooks::commit_msg(&ctx, message)?)
+        Ok(gitbutler_repo::hooks::commit_msg(
+            &ctx,
+            message,
+            emit_hook_output(&app_handle, &window, project_id),
+        )?)
+    }
+
+    /// Return the content of the commit template configured as `commit.template` in git, to start commit messages
+    /// with, or `None` if there is none.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn commit_template(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Option<String>, Error> {
+        let project = projects.get(project_id)?;
+        let repo = gix::open(project.worktree_path()).map_err(anyhow::Error::from)?;
+        Ok(commit_message::template(&repo)?)
+    }
+
+    /// Return the problems with `message` as commit message of the project. Those that are errors keep it from
+    /// being committed, like not following Conventional Commits if the project requires it.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn validate_commit_message(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        message: &str,
+    ) -> Result<Vec<Violation>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(commit_message::validate(
+            message,
+            project.conventional_commits,
+        ))
+    }
+
+    /// Return a function to forward each line of hook output to `window`, which runs the hook.
+    fn emit_hook_output(
+        app_handle: &AppHandle,
+        window: &Window,
+        project_id: ProjectId,
+    ) -> impl FnMut(HookOutput) {
+        let app_handle = app_handle.clone();
+        let label = window.label().to_owned();
+        let event_name = format!("project://{project_id}/hooks/output");
+        move |output| {
+            if let Err(err) = app_handle.emit_to(label.as_str(), &event_name, &output) {
+                tracing::warn!(?err, "failed to send hook output");
+            }
+        }
     }
 }
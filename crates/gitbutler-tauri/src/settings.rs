@@ -1,10 +1,15 @@
 #![allow(deprecated)]
 use anyhow::Result;
+use but_settings::api::ConcurrencyUpdate;
 use but_settings::api::FeatureFlagsUpdate;
+use but_settings::api::LocalApiUpdate;
+use but_settings::api::NotificationsUpdate;
 use but_settings::api::TelemetryUpdate;
+use but_settings::app_settings::UpdateChannel;
 use but_settings::AppSettings;
 use but_settings::AppSettingsWithDiskSync;
 use but_settings::LegacySettings;
+use but_settings::{OnboardingState, OnboardingStep};
 use std::sync::Arc;
 use tauri::State;
 use tauri::Wry;
@@ -62,6 +67,23 @@ pub fn update_onboarding_complete(
         .map_err(|e| e.into())
 }
 
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn onboarding_state(
+    handle: State<'_, AppSettingsWithDiskSync>,
+) -> Result<OnboardingState, Error> {
+    Ok(handle.get()?.onboarding_state())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn complete_onboarding_step(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    step: OnboardingStep,
+) -> Result<(), Error> {
+    handle.complete_onboarding_step(step).map_err(|e| e.into())
+}
+
 #[tauri::command(async)]
 #[instrument(skip(handle), err(Debug))]
 pub fn update_telemetry(
@@ -79,3 +101,70 @@ pub fn update_feature_flags(
 ) -> Result<(), Error> {
     handle.update_feature_flags(update).map_err(|e| e.into())
 }
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_concurrency(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    update: ConcurrencyUpdate,
+) -> Result<(), Error> {
+    handle.update_concurrency(update).map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_local_api(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    update: LocalApiUpdate,
+) -> Result<(), Error> {
+    handle.update_local_api(update).map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_project_deletion_grace_period(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    seconds: u64,
+) -> Result<(), Error> {
+    handle
+        .update_project_deletion_grace_period(seconds)
+        .map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_watcher_idle_timeout(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    seconds: u64,
+) -> Result<(), Error> {
+    handle
+        .update_watcher_idle_timeout(seconds)
+        .map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_background_mode(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    enabled: bool,
+) -> Result<(), Error> {
+    handle.update_background_mode(enabled).map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn set_update_channel(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    channel: Option<UpdateChannel>,
+) -> Result<(), Error> {
+    handle.set_update_channel(channel).map_err(|e| e.into())
+}
+
+#[tauri::command(async)]
+#[instrument(skip(handle), err(Debug))]
+pub fn update_notifications(
+    handle: State<'_, AppSettingsWithDiskSync>,
+    update: NotificationsUpdate,
+) -> Result<(), Error> {
+    handle.update_notifications(update).map_err(|e| e.into())
+}
@@ -0,0 +1,59 @@
+//! Shut down cleanly when the app exits, so changes noticed right before aren't lost, and tell on the next start
+//! if the app didn't get to do that, like when it was killed or crashed.
+use std::{path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use tauri::{AppHandle, Manager};
+
+use crate::WindowState;
+
+/// The file in the app data directory that tells whether the app is running or was shut down cleanly.
+const MARKER_FILE: &str = "shutdown-state";
+const RUNNING: &str = "running";
+const CLEAN: &str = "clean";
+
+/// How long to wait for the watchers to hand over and handle the changes they noticed last.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How the app ended the last time it ran, as returned by [`record_start()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviousShutdown {
+    /// The app wasn't started before.
+    None,
+    Clean,
+    /// The app exited without shutting down, so changes it noticed last may not have been recorded.
+    Unclean,
+}
+
+/// Note in `app_data_dir` that the app is running, and return how it ended the last time.
+pub fn record_start(app_data_dir: &Path) -> Result<PreviousShutdown> {
+    let path = app_data_dir.join(MARKER_FILE);
+    let previous = match std::fs::read_to_string(&path) {
+        Ok(state) if state.trim() == CLEAN => PreviousShutdown::Clean,
+        Ok(_) => PreviousShutdown::Unclean,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => PreviousShutdown::None,
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read '{}'", path.display()))
+        }
+    };
+    std::fs::write(&path, RUNNING)
+        .with_context(|| format!("failed to write '{}'", path.display()))?;
+    Ok(previous)
+}
+
+/// Stop all watchers, wait for the changes they noticed to be recorded, and note in `app_data_dir` that the app
+/// was shut down cleanly, unless that took too long.
+pub fn shutdown(app_handle: &AppHandle, app_data_dir: &Path) {
+    gitbutler_watcher::begin_shutdown();
+    if let Some(window_state) = app_handle.try_state::<WindowState>() {
+        window_state.stop_all_watchers();
+    }
+    if !gitbutler_watcher::wait_until_flushed(FLUSH_TIMEOUT) {
+        tracing::warn!(timeout = ?FLUSH_TIMEOUT, "gave up waiting for the watchers to record pending changes");
+        return;
+    }
+    let path = app_data_dir.join(MARKER_FILE);
+    if let Err(err) = std::fs::write(&path, CLEAN) {
+        tracing::warn!(?err, path = %path.display(), "failed to note the clean shutdown");
+    }
+}
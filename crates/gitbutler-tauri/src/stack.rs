@@ -3,9 +3,10 @@ use gitbutler_branch_actions::stack::CreateSeriesRequest;
 use gitbutler_command_context::CommandContext;
 use gitbutler_project as projects;
 use gitbutler_project::ProjectId;
-use gitbutler_stack::StackId;
+use gitbutler_repo_actions::push_safety::PushRefusal;
+use gitbutler_stack::{StackId, VirtualBranchesHandle};
 use gitbutler_user::User;
-use tauri::State;
+use tauri::{AppHandle, State};
 use tracing::instrument;
 
 use crate::virtual_branches::commands::emit_vbranches;
@@ -105,22 +106,53 @@ pub fn update_series_pr_number(
 }
 
 #[tauri::command(async)]
-#[instrument(skip(projects, windows, settings), err(Debug))]
+#[instrument(skip(app, projects, windows, settings), err(Debug))]
 pub fn push_stack(
+    app: AppHandle,
     windows: State<'_, WindowState>,
     projects: State<'_, projects::Controller>,
     settings: State<'_, AppSettingsWithDiskSync>,
     project_id: ProjectId,
     branch_id: StackId,
     with_force: bool,
+    with_tags: Option<bool>,
 ) -> Result<(), Error> {
     let project = projects.get(project_id)?;
     let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-    gitbutler_branch_actions::stack::push_stack(&ctx, branch_id, with_force)?;
+    if let Err(err) = gitbutler_branch_actions::stack::push_stack(
+        &ctx,
+        branch_id,
+        with_force,
+        with_tags.unwrap_or(false),
+    ) {
+        crate::notifications::notify_push_failed(&app, project_id, "the branch", &err);
+        return Err(err.into());
+    }
     emit_vbranches(&windows, project_id, ctx.app_settings());
+    let head = VirtualBranchesHandle::new(project.gb_dir())
+        .get_stack(branch_id)?
+        .head();
+    crate::forge::emit_ci_status_after_push(&app, project_id, head);
     Ok(())
 }
 
+/// Explain why pushing the stack would be refused, for each of its series that it would be refused for.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn check_push_stack(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    branch_id: StackId,
+    with_force: bool,
+) -> Result<Vec<PushRefusal>, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    Ok(gitbutler_branch_actions::stack::check_push_stack(
+        &ctx, branch_id, with_force,
+    )?)
+}
+
 #[tauri::command(async)]
 #[instrument(skip(projects, settings, windows), err(Debug))]
 pub fn push_stack_to_review(
@@ -0,0 +1,330 @@
+//! Send responses that are too large for a single IPC message, like the whole history or big files, in chunks.
+//!
+//! The frontend listens to `stream://{streamId}` with an id of its choosing, and then requests the response with
+//! [`commands::begin_stream()`]. It returns how many [`StreamChunk`]s will be emitted, each with its sequence number
+//! so they can be put together in order and parsed as JSON even if they arrive before the response. The JSON is
+//! serialized into chunks as they are sent, once beforehand to count them, so it's never held in memory as a whole.
+//! [`commands::end_stream()`] stops sending the remaining chunks of a stream that isn't needed anymore.
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use anyhow::{bail, Result};
+use gitbutler_oplog::{
+    deltas::{self, Delta},
+    export,
+    export::HistoryExportFormat,
+};
+use gitbutler_project::{self as projects, machine_changes::ChangeOrigin, ProjectId};
+use gitbutler_repo::{FileInfo, RepoCommands};
+use serde::{Deserialize, Serialize};
+
+/// The most bytes of the response sent in a single chunk.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// The largest file that is read with [`StreamRequest::ReadWorkdirFile`] unless another limit is given, as its
+/// content is held in memory while it's sent, and parsed all at once by the frontend.
+const DEFAULT_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The commands that can be streamed, with the same arguments as the command of the same name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum StreamRequest {
+    #[serde(rename_all = "camelCase")]
+    ListDeltas {
+        project_id: ProjectId,
+        since: i64,
+        until: i64,
+        origin: Option<ChangeOrigin>,
+        branch: Option<String>,
+    },
+    /// Render the history instead of writing it to a file, for a [`FileInfo`] with its content as base64.
+    #[serde(rename_all = "camelCase")]
+    ExportHistory {
+        project_id: ProjectId,
+        format: HistoryExportFormat,
+        since: i64,
+        until: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReadWorkdirFile {
+        project_id: ProjectId,
+        relative_path: PathBuf,
+        max_size: Option<u64>,
+    },
+}
+
+/// What the frontend needs to know to receive a stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStart {
+    pub stream_id: String,
+    /// The amount of chunks that will be emitted.
+    pub chunks: usize,
+    /// The length of the whole response in bytes.
+    pub size: usize,
+}
+
+/// A part of the JSON of a response, to be concatenated with the others in the order of `seq`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamChunk {
+    pub seq: usize,
+    pub data: String,
+}
+
+/// The ids of the streams that are still being sent.
+#[derive(Default)]
+pub struct Streams(parking_lot::Mutex<HashSet<String>>);
+
+impl Streams {
+    fn begin(&self, stream_id: &str) -> Result<()> {
+        if !self.0.lock().insert(stream_id.to_owned()) {
+            bail!("Stream '{stream_id}' is in progress already");
+        }
+        Ok(())
+    }
+
+    fn is_active(&self, stream_id: &str) -> bool {
+        self.0.lock().contains(stream_id)
+    }
+
+    fn end(&self, stream_id: &str) {
+        self.0.lock().remove(stream_id);
+    }
+}
+
+/// The response to a [`StreamRequest`], to be serialized as it's sent.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum StreamResponse {
+    Deltas(Vec<Delta>),
+    File(FileInfo),
+}
+
+/// Run `request` and return its response.
+fn respond(projects: &projects::Controller, request: StreamRequest) -> Result<StreamResponse> {
+    Ok(match request {
+        StreamRequest::ListDeltas {
+            project_id,
+            since,
+            until,
+            origin,
+            branch,
+        } => {
+            let project = projects.get(project_id)?;
+            StreamResponse::Deltas(deltas::list_deltas(
+                &project,
+                since..until,
+                origin,
+                branch.as_deref(),
+            )?)
+        }
+        StreamRequest::ExportHistory {
+            project_id,
+            format,
+            since,
+            until,
+        } => {
+            let project = projects.get(project_id)?;
+            let name = match format {
+                HistoryExportFormat::JsonLines => "history.jsonl",
+                HistoryExportFormat::FastExport => "history.fast-export",
+            };
+            let content =
+                export::render_history(&project, format, since..until, &AtomicBool::new(false))?;
+            StreamResponse::File(FileInfo::base64(Path::new(name), &content))
+        }
+        StreamRequest::ReadWorkdirFile {
+            project_id,
+            relative_path,
+            max_size,
+        } => {
+            let project = projects.get(project_id)?;
+            StreamResponse::File(project.read_file_from_worktree(
+                &relative_path,
+                max_size.unwrap_or(DEFAULT_MAX_FILE_SIZE),
+            )?)
+        }
+    })
+}
+
+/// Serialize `value` as JSON and pass it to `on_chunk` in chunks of at most `size` bytes, or a little more to not
+/// split a multi-byte character. Stops as soon as `on_chunk` fails.
+fn write_chunks(
+    value: &impl Serialize,
+    size: usize,
+    on_chunk: impl FnMut(&str) -> io::Result<()>,
+) -> Result<()> {
+    let mut writer = ChunkWriter {
+        size,
+        buf: Vec::with_capacity(size),
+        on_chunk,
+    };
+    serde_json::to_writer(&mut writer, value)?;
+    let rest = writer.buf.len();
+    if rest > 0 {
+        writer.emit(rest)?;
+    }
+    Ok(())
+}
+
+/// Collects what is written into chunks for [`write_chunks()`].
+struct ChunkWriter<F> {
+    size: usize,
+    buf: Vec<u8>,
+    on_chunk: F,
+}
+
+impl<F: FnMut(&str) -> io::Result<()>> ChunkWriter<F> {
+    /// Pass the first `end` bytes to `on_chunk` and forget them.
+    fn emit(&mut self, end: usize) -> io::Result<()> {
+        let chunk = std::str::from_utf8(&self.buf[..end])
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        (self.on_chunk)(chunk)?;
+        self.buf.drain(..end);
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&str) -> io::Result<()>> io::Write for ChunkWriter<F> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        // A chunk ends before the first byte that isn't within a character, which may not be written yet.
+        while self.buf.len() > self.size {
+            let mut end = self.size;
+            while end < self.buf.len() && self.buf[end] & 0b1100_0000 == 0b1000_0000 {
+                end += 1;
+            }
+            if end == self.buf.len() {
+                break;
+            }
+            self.emit(end)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub mod commands {
+    use std::io;
+
+    use gitbutler_project as projects;
+    use tauri::{AppHandle, Emitter, Manager, State, Window};
+    use tracing::instrument;
+
+    use super::{
+        respond, write_chunks, StreamChunk, StreamRequest, StreamStart, Streams, CHUNK_SIZE,
+    };
+    use crate::error::Error;
+
+    /// Run `request` and emit its response in chunks as `stream://{stream_id}` events to the calling window.
+    #[tauri::command(async)]
+    #[instrument(skip(app, window, projects, streams), err(Debug))]
+    pub fn begin_stream(
+        app: AppHandle,
+        window: Window,
+        projects: State<'_, projects::Controller>,
+        streams: State<'_, Streams>,
+        stream_id: String,
+        request: StreamRequest,
+    ) -> Result<StreamStart, Error> {
+        let response = respond(&projects, request)?;
+        let mut start = StreamStart {
+            stream_id: stream_id.clone(),
+            chunks: 0,
+            size: 0,
+        };
+        write_chunks(&response, CHUNK_SIZE, |chunk| {
+            start.chunks += 1;
+            start.size += chunk.len();
+            Ok(())
+        })?;
+        streams.begin(&stream_id)?;
+        let label = window.label().to_owned();
+        std::thread::spawn(move || {
+            let event = format!("stream://{stream_id}");
+            let streams = app.state::<Streams>();
+            let mut seq = 0;
+            let sent = write_chunks(&response, CHUNK_SIZE, |data| {
+                if !streams.is_active(&stream_id) {
+                    return Err(io::Error::other("the stream was ended"));
+                }
+                let chunk = StreamChunk {
+                    seq,
+                    data: data.to_owned(),
+                };
+                app.emit_to(label.as_str(), &event, chunk)
+                    .map_err(io::Error::other)?;
+                seq += 1;
+                Ok(())
+            });
+            if let Err(err) = sent {
+                if streams.is_active(&stream_id) {
+                    tracing::warn!(?err, %stream_id, "failed to emit chunk");
+                }
+            }
+            streams.end(&stream_id);
+        });
+        Ok(start)
+    }
+
+    /// Stop sending the chunks of the stream with `stream_id`, if there are any left.
+    #[tauri::command(async)]
+    #[instrument(skip(streams), err(Debug))]
+    pub fn end_stream(streams: State<'_, Streams>, stream_id: String) -> Result<(), Error> {
+        streams.end(&stream_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(value: &str, size: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        write_chunks(&value, size, |chunk| {
+            chunks.push(chunk.to_owned());
+            Ok(())
+        })
+        .unwrap();
+        chunks
+    }
+
+    #[test]
+    fn chunks_keep_characters_whole() {
+        assert_eq!(chunks("", 4), [r#""""#]);
+        assert_eq!(chunks("abcdefghij", 4), [r#""abc"#, "defg", r#"hij""#]);
+        assert_eq!(chunks("aäb", 3), [r#""aä"#, r#"b""#], "'ä' takes two bytes");
+        assert_eq!(chunks("aäb", 2).concat(), r#""aäb""#);
+    }
+
+    #[test]
+    fn chunks_stop_when_they_cannot_be_sent() {
+        let mut sent = 0;
+        let result = write_chunks(&"abcdefghij", 4, |_| {
+            sent += 1;
+            Err(io::Error::other("ended"))
+        });
+        assert!(result.is_err());
+        assert_eq!(sent, 1);
+    }
+
+    #[test]
+    fn streams_can_be_in_progress_once() {
+        let streams = Streams::default();
+        assert!(streams.begin("id").is_ok());
+        assert!(streams.begin("id").is_err());
+        assert!(streams.is_active("id"));
+        streams.end("id");
+        assert!(!streams.is_active("id"));
+        assert!(streams.begin("id").is_ok());
+    }
+}
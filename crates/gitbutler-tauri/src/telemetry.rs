@@ -0,0 +1,143 @@
+//! Anonymous usage counters, collected only if the user opted in with
+//! [`app_usage_counters_enabled`](but_settings::app_settings::TelemetrySettings::app_usage_counters_enabled).
+//!
+//! Only the names of operations, how often they ran and how long they took, and the codes of errors are
+//! counted, never their arguments, paths or messages. The counters are batched in memory, and the frontend takes
+//! the pending batch from time to time to send it along with its other usage metrics. What would be sent next can
+//! be inspected with [`commands::show_pending_telemetry()`].
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use but_settings::AppSettings;
+use serde::Serialize;
+
+/// The upper bounds of the duration buckets, in milliseconds, with a last bucket for everything slower.
+const DURATION_BUCKETS_MS: [u64; 5] = [10, 100, 1_000, 10_000, 60_000];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PENDING: parking_lot::Mutex<Batch> = parking_lot::Mutex::new(Batch::new());
+
+/// The counters collected since the last batch was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Batch {
+    /// When collecting the batch started, in milliseconds since the Unix epoch, or `0` if nothing was counted yet.
+    pub since_ms: u128,
+    /// The operations that ran, by name.
+    pub operations: BTreeMap<String, OperationStats>,
+    /// How often errors with each code were shown.
+    pub error_codes: BTreeMap<String, u64>,
+}
+
+/// How often an operation ran, and how long it took.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStats {
+    pub count: u64,
+    /// How many runs took at most each of the [`DURATION_BUCKETS_MS`], with the last entry counting the slower
+    /// ones.
+    pub duration_histogram: [u64; DURATION_BUCKETS_MS.len() + 1],
+}
+
+impl Batch {
+    const fn new() -> Self {
+        Batch {
+            since_ms: 0,
+            operations: BTreeMap::new(),
+            error_codes: BTreeMap::new(),
+        }
+    }
+
+    fn start(&mut self) {
+        if self.since_ms == 0 {
+            self.since_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis());
+        }
+    }
+}
+
+/// Start or stop collecting counters as configured in `settings`, dropping the pending ones when stopping.
+pub fn apply_telemetry_settings(settings: &AppSettings) {
+    let enabled = settings.telemetry.app_usage_counters_enabled;
+    let was_enabled = ENABLED.swap(enabled, Ordering::Relaxed);
+    if was_enabled && !enabled {
+        *PENDING.lock() = Batch::new();
+    }
+}
+
+/// Count a run of the operation called `name` that took `duration`.
+pub(crate) fn record_operation(name: &str, duration: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let millis = duration.as_millis();
+    let bucket = DURATION_BUCKETS_MS
+        .iter()
+        .position(|bound| millis <= u128::from(*bound))
+        .unwrap_or(DURATION_BUCKETS_MS.len());
+    let mut pending = PENDING.lock();
+    pending.start();
+    let stats = pending
+        .operations
+        .entry(name.to_owned())
+        .or_insert_with(|| OperationStats {
+            count: 0,
+            duration_histogram: Default::default(),
+        });
+    stats.count += 1;
+    stats.duration_histogram[bucket] += 1;
+}
+
+/// Count an error with `code` that was returned to the frontend.
+pub(crate) fn record_error(code: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut pending = PENDING.lock();
+    pending.start();
+    *pending.error_codes.entry(code.to_owned()).or_default() += 1;
+}
+
+pub mod commands {
+    use but_settings::{api::TelemetryUpdate, AppSettingsWithDiskSync};
+    use tauri::State;
+    use tracing::instrument;
+
+    use super::{Batch, PENDING};
+    use crate::error::Error;
+
+    /// Opt in to or out of collecting anonymous usage counters. Opting out drops the ones not sent yet.
+    #[tauri::command(async)]
+    #[instrument(skip(settings), err(Debug))]
+    pub fn set_telemetry_enabled(
+        settings: State<'_, AppSettingsWithDiskSync>,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        settings.update_telemetry(TelemetryUpdate {
+            app_metrics_enabled: None,
+            app_error_reporting_enabled: None,
+            app_non_anon_metrics_enabled: None,
+            app_usage_counters_enabled: Some(enabled),
+        })?;
+        super::apply_telemetry_settings(&settings.get()?);
+        Ok(())
+    }
+
+    /// Return the counters that would be sent next, without taking them.
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn show_pending_telemetry() -> Result<Batch, Error> {
+        Ok(PENDING.lock().clone())
+    }
+
+    /// Return the pending counters and start a new batch, for the frontend to send them.
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn take_pending_telemetry() -> Result<Batch, Error> {
+        Ok(std::mem::replace(&mut *PENDING.lock(), Batch::new()))
+    }
+}
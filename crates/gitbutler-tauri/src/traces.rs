@@ -0,0 +1,154 @@
+//! Keep the most recently completed spans in memory, along with their fields and how long they took, so slow
+//! operations can be diagnosed without access to the log files.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// The amount of spans to keep, the oldest ones are dropped first.
+const CAPACITY: usize = 1000;
+/// Field values longer than this are truncated.
+const MAX_FIELD_LEN: usize = 200;
+
+static RECENT: parking_lot::Mutex<VecDeque<Trace>> = parking_lot::Mutex::new(VecDeque::new());
+
+/// A span that was closed, like the call of a command or a stage of handling a filesystem event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    /// The name of the span, typically that of the instrumented function.
+    pub name: String,
+    /// The module path of the span.
+    pub target: String,
+    /// The name of the span this one was entered in, if any.
+    pub parent: Option<String>,
+    /// The fields of the span like `project_id`, formatted for display.
+    pub fields: BTreeMap<String, String>,
+    /// How long the span was open, in milliseconds.
+    pub duration_ms: f64,
+    /// When the span was closed, in milliseconds since the Unix epoch.
+    pub closed_at_ms: u128,
+}
+
+/// Return the recently closed spans that took at least `min_duration_ms`, newest first.
+pub fn recent_traces(min_duration_ms: f64) -> Vec<Trace> {
+    RECENT
+        .lock()
+        .iter()
+        .rev()
+        .filter(|trace| trace.duration_ms >= min_duration_ms)
+        .cloned()
+        .collect()
+}
+
+/// A layer recording each closed span into the buffer returned by [`recent_traces()`].
+pub struct RecentTracesLayer;
+
+/// What is stored along with each open span.
+struct Timing {
+    opened: Instant,
+    fields: BTreeMap<String, String>,
+}
+
+impl<S> Layer<S> for RecentTracesLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        span.extensions_mut().insert(Timing {
+            opened: Instant::now(),
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(timing) = span.extensions_mut().get_mut::<Timing>() {
+            values.record(&mut FieldVisitor(&mut timing.fields));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        let duration = timing.opened.elapsed();
+        let parent = span.parent().map(|parent| parent.name().to_owned());
+        if parent.is_none() && span.metadata().target().starts_with("gitbutler_tauri") {
+            crate::telemetry::record_operation(span.name(), duration);
+        }
+        let trace = Trace {
+            name: span.name().to_owned(),
+            target: span.metadata().target().to_owned(),
+            parent,
+            fields: timing.fields,
+            duration_ms: duration.as_secs_f64() * 1000.0,
+            closed_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis()),
+        };
+        let mut recent = RECENT.lock();
+        if recent.len() == CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(trace);
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, mut value: String) {
+        if value.len() > MAX_FIELD_LEN {
+            let mut end = MAX_FIELD_LEN;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            value.truncate(end);
+            value.push('…');
+        }
+        self.0.insert(field.name().to_owned(), value);
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}"));
+    }
+}
+
+pub mod commands {
+    use tracing::instrument;
+
+    use super::Trace;
+    use crate::error::Error;
+
+    /// Return the recently completed operations that took at least `min_duration_ms`, newest first.
+    #[tauri::command(async)]
+    #[instrument(err(Debug))]
+    pub fn get_recent_traces(min_duration_ms: Option<f64>) -> Result<Vec<Trace>, Error> {
+        Ok(super::recent_traces(min_duration_ms.unwrap_or(0.0)))
+    }
+}
@@ -0,0 +1,145 @@
+//! The tray icon, which keeps the app reachable while it records changes to the projects of closed windows in the
+//! background, see [`background_mode()`].
+//!
+//! Its menu offers to show the window, to open any project, and to pause or resume recording each open project.
+use anyhow::{Context, Result};
+use but_settings::AppSettingsWithDiskSync;
+use gitbutler_project::{Controller, ProjectId};
+use tauri::{
+    menu::{
+        CheckMenuItemBuilder, Menu, MenuBuilder, MenuEvent, MenuItemBuilder, PredefinedMenuItem,
+        SubmenuBuilder,
+    },
+    tray::TrayIconBuilder,
+    AppHandle, Manager,
+};
+
+use crate::{deep_link, projects, WindowState};
+
+const TRAY_ID: &str = "main";
+const SHOW_ID: &str = "tray/show";
+const QUIT_ID: &str = "tray/quit";
+const OPEN_PREFIX: &str = "tray/open/";
+const RECORD_PREFIX: &str = "tray/record/";
+
+/// Return `true` if closing the last window should keep the app running in the tray, to keep recording changes.
+pub fn background_mode(app_handle: &AppHandle) -> bool {
+    app_handle
+        .try_state::<AppSettingsWithDiskSync>()
+        .and_then(|settings| settings.get().ok().map(|settings| settings.background_mode))
+        .unwrap_or(false)
+}
+
+/// Add the tray icon for the app.
+pub fn create(app_handle: &AppHandle) -> Result<()> {
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(app_handle.package_info().name.clone())
+        .menu(&build_menu(app_handle)?)
+        .on_menu_event(handle_event);
+    if let Some(icon) = app_handle.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app_handle)?;
+    Ok(())
+}
+
+/// Rebuild the menu of the tray icon, like after projects were opened, closed, paused or resumed.
+pub fn refresh(app_handle: &AppHandle) {
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(err) = build_menu(app_handle).and_then(|menu| Ok(tray.set_menu(Some(menu))?)) {
+        tracing::warn!(?err, "failed to update the tray menu");
+    }
+}
+
+fn build_menu(app_handle: &AppHandle) -> Result<Menu<tauri::Wry>> {
+    let all_projects = app_handle
+        .state::<Controller>()
+        .list()
+        .context("failed to list projects")?;
+
+    let mut open_menu = SubmenuBuilder::new(app_handle, "Open Project");
+    for project in all_projects
+        .iter()
+        .filter(|project| !project.is_deleted() && !project.is_subproject())
+    {
+        open_menu = open_menu.item(
+            &MenuItemBuilder::with_id(format!("{OPEN_PREFIX}{}", project.id), &project.title)
+                .build(app_handle)?,
+        );
+    }
+
+    let open_ids = app_handle.state::<WindowState>().open_projects();
+    let background_ids = app_handle.state::<WindowState>().background_projects();
+    let mut menu = MenuBuilder::new(app_handle)
+        .item(&MenuItemBuilder::with_id(SHOW_ID, "Show Window").build(app_handle)?)
+        .item(&open_menu.build()?);
+    let recording = all_projects
+        .iter()
+        .filter(|project| open_ids.contains(&project.id));
+    for (index, project) in recording.enumerate() {
+        if index == 0 {
+            menu = menu.item(&PredefinedMenuItem::separator(app_handle)?);
+        }
+        let title = if background_ids.contains(&project.id) {
+            format!("Record {} (in background)", project.title)
+        } else {
+            format!("Record {}", project.title)
+        };
+        menu = menu.item(
+            &CheckMenuItemBuilder::with_id(format!("{RECORD_PREFIX}{}", project.id), title)
+                .checked(!project.recording_paused)
+                .build(app_handle)?,
+        );
+    }
+    Ok(menu
+        .item(&PredefinedMenuItem::separator(app_handle)?)
+        .item(&MenuItemBuilder::with_id(QUIT_ID, "Quit").build(app_handle)?)
+        .build()?)
+}
+
+fn handle_event(app_handle: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    let result = if id == SHOW_ID {
+        deep_link::focus_window(app_handle);
+        Ok(())
+    } else if id == QUIT_ID {
+        app_handle.exit(0);
+        Ok(())
+    } else if let Some(project_id) = id.strip_prefix(OPEN_PREFIX) {
+        open_project(app_handle, project_id)
+    } else if let Some(project_id) = id.strip_prefix(RECORD_PREFIX) {
+        toggle_recording(app_handle, project_id)
+    } else {
+        return;
+    };
+    if let Err(err) = result {
+        tracing::error!(?err, id, "failed to handle tray menu item");
+    }
+    refresh(app_handle);
+}
+
+fn open_project(app_handle: &AppHandle, project_id: &str) -> Result<()> {
+    let project_id: ProjectId = project_id.parse()?;
+    if let Some(window) = app_handle
+        .state::<WindowState>()
+        .window_for_project(project_id)
+        .and_then(|label| app_handle.get_webview_window(&label))
+    {
+        window.unminimize()?;
+        window.set_focus()?;
+        return Ok(());
+    }
+    // Show the project in the main window, which takes it over from the background if needed.
+    deep_link::show_project(app_handle, project_id);
+    Ok(())
+}
+
+fn toggle_recording(app_handle: &AppHandle, project_id: &str) -> Result<()> {
+    let project_id: ProjectId = project_id.parse()?;
+    let projects = app_handle.state::<Controller>();
+    let paused = projects.get(project_id)?.recording_paused;
+    projects::set_recording_paused(&projects, project_id, !paused)?;
+    Ok(())
+}
@@ -1,18 +1,42 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use anyhow::Context;
-use but_settings::AppSettingsWithDiskSync;
+use but_settings::{app_settings::NotificationCategory, AppSettingsWithDiskSync};
 use gitbutler_command_context::CommandContext;
-use gitbutler_diff::FileDiff;
-use gitbutler_oplog::{entry::Snapshot, OplogExt};
+use gitbutler_diff::{FileDiff, FileMode};
+use gitbutler_oplog::{
+    activity::{ActivitySession, ActivitySummary},
+    bisect::{self, HistoryBisection, HistoryPredicate},
+    bookmarks::{self, Bookmark},
+    deltas::{self, Delta, ReconstructionProfile},
+    encryption,
+    entry::{OperationKind, Snapshot},
+    export::{self, HistoryExport, HistoryExportFormat},
+    file_history::FileHistoryEntry,
+    heartbeat,
+    import::{self, HistoryImport},
+    journal::{self, JournalEntry},
+    meta_ref::{self, MetaRefRepair},
+    reconstruct::{self, RestoreOutcome, RestoreProgress},
+    retention::{self, HistoryBudget},
+    secrets::{self, SecretFinding, SecretScanner},
+    share::{self, SessionShare, SharedSession},
+    tombstones::{self, Tombstone},
+    usage::{self, Cleanup, CleanupOptions, DataUsage},
+    verify::{self, HistoryVerification},
+    OplogExt,
+};
 use gitbutler_project as projects;
-use gitbutler_project::ProjectId;
+use gitbutler_project::{machine_changes::ChangeOrigin, ProjectId};
+use gitbutler_repo::FileInfo;
 use gitbutler_stack::StackId;
+use gitbutler_sync::history_backup::HistoryMerge;
 use gitbutler_user::User;
-use tauri::State;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
 use tracing::instrument;
 
-use crate::error::Error;
+use crate::{error::Error, operations::Operations};
 
 #[tauri::command(async)]
 #[instrument(skip(projects), err(Debug))]
@@ -40,13 +64,50 @@ pub fn restore_snapshot(
 ) -> Result<(), Error> {
     let project = projects.get(project_id).context("failed to get project")?;
     let mut guard = project.exclusive_worktree_access();
-    project.restore_snapshot(
+    let before = journal::RefState::capture(&project)?;
+    let result = project.restore_snapshot(
         sha.parse().map_err(anyhow::Error::from)?,
         guard.write_permission(),
+    );
+    journal::record(
+        &project,
+        OperationKind::RestoreFromSnapshot,
+        [("sha", sha)],
+        &before,
+        &journal::RefState::capture(&project)?,
+        result.as_ref().err(),
     )?;
+    result?;
     Ok(())
 }
 
+/// Return the latest `limit` operations performed on the project, or all that are kept if `None`, newest first.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn list_operations(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    limit: Option<usize>,
+) -> Result<Vec<JournalEntry>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(journal::latest_entries(&project, limit)?)
+}
+
+/// Undo the latest operation that moved branches, like a commit or a rebase, and return what was undone.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn undo_last_operation(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<JournalEntry, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let mut guard = project.exclusive_worktree_access();
+    Ok(journal::undo_last_operation(
+        &project,
+        guard.write_permission(),
+    )?)
+}
+
 #[tauri::command(async)]
 #[instrument(skip(projects), err(Debug))]
 pub fn snapshot_diff(
@@ -59,6 +120,544 @@ pub fn snapshot_diff(
     Ok(diff)
 }
 
+/// Summarize the changes to the worktree of the project recorded by snapshots created between
+/// `since` and `until`, both in seconds since the Unix epoch.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn activity_summary(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+) -> Result<ActivitySummary, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.activity_summary(since..until)?)
+}
+
+/// Return the activity sessions of the project between `since` and `until`, both in seconds since the Unix epoch,
+/// oldest first. If `branch` is set, only the sessions recorded on the branch with this short name are returned.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn list_sessions(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+    branch: Option<String>,
+) -> Result<Vec<ActivitySession>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.list_sessions(since..until, branch.as_deref())?)
+}
+
+/// Return the changes to the worktree of the project noticed between `since` and `until`, both in seconds since
+/// the Unix epoch, oldest first. If `origin` is set, only the changes made by a human or a machine are returned,
+/// and if `branch` is set, only those made on the branch with this short name.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn list_deltas(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+    origin: Option<ChangeOrigin>,
+    branch: Option<String>,
+) -> Result<Vec<Delta>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(deltas::list_deltas(
+        &project,
+        since..until,
+        origin,
+        branch.as_deref(),
+    )?)
+}
+
+/// Tell that the file at the worktree-relative `file_path` is focused in an editor, for the time spent reading
+/// it to count as activity. Returns `false` if the heartbeat was too close to the previous one to be recorded.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn editor_heartbeat(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    file_path: PathBuf,
+) -> Result<bool, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("the clock is before the Unix epoch")?
+        .as_secs();
+    Ok(heartbeat::record_heartbeat(
+        &project,
+        &file_path,
+        i64::try_from(now).context("the clock is too far in the future")?,
+    )?)
+}
+
+/// Return the commits that touched the file at the worktree-relative `file_path`, following renames, and the
+/// activity sessions in which it was edited, newest first.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn file_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    file_path: PathBuf,
+) -> Result<Vec<FileHistoryEntry>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(gitbutler_oplog::file_history::file_history(
+        &project, &file_path,
+    )?)
+}
+
+/// Return the files of the project deleted between `since` and `until`, both in seconds since the Unix epoch,
+/// with their last content, oldest first.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn list_deleted_files(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+) -> Result<Vec<Tombstone>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(tombstones::list_deleted_files(&project, since..until)?)
+}
+
+/// Reconstruct the latest content of the file at the worktree-relative `file_path` from the recorded history,
+/// and return how long each step took. Meant for diagnosing slow timelines.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn profile_reconstruction(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    file_path: PathBuf,
+) -> Result<ReconstructionProfile, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(deltas::profile_reconstruction(&project, &file_path)?)
+}
+
+/// Bring back the file at the worktree-relative `file_path` with the content it had when it was deleted last.
+#[tauri::command(async)]
+#[instrument(skip(app, projects), err(Debug))]
+pub fn recover_deleted_file(
+    app: AppHandle,
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    file_path: PathBuf,
+) -> Result<Tombstone, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(crate::worktree_writes::coordinate(
+        &app,
+        &project,
+        &file_path,
+        || tombstones::recover_deleted_file(&project, &file_path),
+    )?)
+}
+
+/// Restore all files within the worktree-relative `directory` to how they were at `at` seconds since the Unix
+/// epoch, sending progress as `project://<id>/restore/progress` events to the calling window, and return what was
+/// done. Files that changed while restoring are left as they are, and sent as write conflicts.
+///
+/// It can be cancelled while it runs as the operation with `operation_id`, and nothing is written if that happens
+/// before all files were reconstructed.
+#[tauri::command(async)]
+#[instrument(skip(app_handle, window, projects, operations), err(Debug))]
+pub fn restore_directory_at(
+    app_handle: AppHandle,
+    window: Window,
+    projects: State<'_, projects::Controller>,
+    operations: State<'_, Operations>,
+    project_id: ProjectId,
+    directory: PathBuf,
+    at: i64,
+    operation_id: Option<String>,
+) -> Result<RestoreOutcome, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let progress_event = format!("project://{project_id}/restore/progress");
+    let progress = |progress: RestoreProgress| {
+        // Sending every file would flood the frontend when restoring large directories.
+        let step = (progress.total / 100).max(1);
+        if progress.reconstructed % step == 0 || progress.reconstructed == progress.total {
+            if let Err(err) = app_handle.emit_to(window.label(), &progress_event, progress) {
+                tracing::warn!(?err, "failed to send restore progress");
+            }
+        }
+    };
+    let mut guard = project.exclusive_worktree_access();
+    let before = journal::RefState::capture(&project)?;
+    let result = operations.run(operation_id.as_deref(), |should_interrupt| {
+        reconstruct::restore_directory_at(
+            &project,
+            &directory,
+            at,
+            should_interrupt,
+            &progress,
+            guard.write_permission(),
+        )
+    });
+    journal::record(
+        &project,
+        OperationKind::RestoreFiles,
+        [
+            ("directory", directory.display().to_string()),
+            ("at", at.to_string()),
+        ],
+        &before,
+        &journal::RefState::capture(&project)?,
+        result.as_ref().err(),
+    )?;
+    let outcome = result?;
+    for path in &outcome.changed {
+        crate::worktree_writes::report_conflict(&app_handle, project_id, path);
+    }
+    Ok(outcome)
+}
+
+/// Return when the file at the worktree-relative `file_path` first stopped satisfying `predicate`, searching its
+/// recorded states across all sessions, or `None` if it still does. It can be cancelled while it runs as the
+/// operation with `operation_id`.
+#[tauri::command(async)]
+#[instrument(skip(projects, operations), err(Debug))]
+pub fn history_bisect(
+    projects: State<'_, projects::Controller>,
+    operations: State<'_, Operations>,
+    project_id: ProjectId,
+    file_path: PathBuf,
+    predicate: HistoryPredicate,
+    operation_id: Option<String>,
+) -> Result<Option<HistoryBisection>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(operations.run(operation_id.as_deref(), |should_interrupt| {
+        bisect::history_bisect(&project, &file_path, &predicate, should_interrupt)
+    })?)
+}
+
+/// Attach `note` and `emoji` to `timestamp` in seconds since the Unix epoch, or to a session by its start,
+/// and return the bookmark.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn add_bookmark(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    timestamp: i64,
+    note: String,
+    emoji: Option<String>,
+) -> Result<Bookmark, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(bookmarks::add_bookmark(
+        &project,
+        timestamp,
+        &note,
+        emoji.as_deref(),
+    )?)
+}
+
+/// Change the `note` and `emoji` of the bookmark `id`, and return it.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn update_bookmark(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    id: u64,
+    note: String,
+    emoji: Option<String>,
+) -> Result<Bookmark, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(bookmarks::update_bookmark(
+        &project,
+        id,
+        &note,
+        emoji.as_deref(),
+    )?)
+}
+
+/// Remove the bookmark `id`, and return it.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn remove_bookmark(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    id: u64,
+) -> Result<Bookmark, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(bookmarks::remove_bookmark(&project, id)?)
+}
+
+/// Return the bookmarks for times between `since` and `until`, both in seconds since the Unix epoch, oldest first.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn list_bookmarks(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    since: i64,
+    until: i64,
+) -> Result<Vec<Bookmark>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(bookmarks::list_bookmarks(&project, since..until)?)
+}
+
+/// Write the history recorded by snapshots created between `since` and `until`, both in seconds since
+/// the Unix epoch, to the file at `path`. It can be cancelled while it runs as the operation with `operation_id`.
+#[tauri::command(async)]
+#[instrument(skip(projects, operations), err(Debug))]
+pub fn export_history(
+    projects: State<'_, projects::Controller>,
+    operations: State<'_, Operations>,
+    project_id: ProjectId,
+    format: HistoryExportFormat,
+    since: i64,
+    until: i64,
+    path: PathBuf,
+    operation_id: Option<String>,
+) -> Result<HistoryExport, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(operations.run(operation_id.as_deref(), |should_interrupt| {
+        export::export_history(&project, format, since..until, &path, should_interrupt)
+    })?)
+}
+
+/// Write the session that started at `session_id`, in seconds since the Unix epoch, to the file at `path` for
+/// another user to replay it, encrypted with `passphrase` if it's set.
+#[tauri::command(async)]
+#[instrument(skip(projects, passphrase), err(Debug))]
+pub fn share_session(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    session_id: i64,
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<SessionShare, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(share::share_session(
+        &project,
+        session_id,
+        passphrase.as_deref(),
+        &path,
+    )?)
+}
+
+/// Read the session shared by another user from the file at `path`, to replay it.
+#[tauri::command(async)]
+#[instrument(skip(passphrase), err(Debug))]
+pub fn open_shared_session(
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> Result<SharedSession, Error> {
+    Ok(share::open_shared_session(&path, passphrase.as_deref())?)
+}
+
+/// Merge the snapshots recorded in the repository at `path`, like the copy of the project on another machine,
+/// into the history of the project.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn import_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    path: PathBuf,
+) -> Result<HistoryImport, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let mut guard = project.exclusive_worktree_access();
+    Ok(import::import_history(
+        &project,
+        &path,
+        guard.write_permission(),
+    )?)
+}
+
+/// Find secrets recorded in the snapshots of the project, using the built-in and the configured patterns.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn scan_history_for_secrets(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<Vec<SecretFinding>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let scanner = SecretScanner::new(&project.secret_patterns)?;
+    Ok(secrets::scan_history(&project, &scanner)?)
+}
+
+/// Check that all snapshots of the project can be restored, and that the latest one matches what is on disk.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn verify_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<HistoryVerification, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(verify::verify_history(&project)?)
+}
+
+/// Create a correction snapshot if the latest snapshot of the project can't be restored or doesn't match
+/// what is on disk, returning its id.
+#[tauri::command(async)]
+#[instrument(skip(app, projects, settings), err(Debug))]
+pub fn repair_history(
+    app: AppHandle,
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<Option<String>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    // The correction snapshot records the worktrees of the branches, which are updated by listing them.
+    gitbutler_branch_actions::list_virtual_branches(&ctx)?;
+    let verification = verify::verify_history(&project)?;
+    let mut guard = project.exclusive_worktree_access();
+    let snapshot_id = verify::repair_history(&project, &verification, guard.write_permission())?;
+    if snapshot_id.is_some() {
+        crate::notifications::notify(
+            &app,
+            NotificationCategory::HistoryRepaired,
+            Some(project_id),
+            "The history was repaired",
+            format!(
+                "A snapshot correcting {} divergences from what is on disk was recorded.",
+                verification.divergences.len()
+            ),
+        );
+    }
+    Ok(snapshot_id.map(|id| id.to_string()))
+}
+
+/// Point the reference that keeps the snapshots of the project from being garbage-collected to the latest one
+/// again, in case cleanup tooling removed it.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn repair_meta_ref(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<MetaRefRepair, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let mut guard = project.exclusive_worktree_access();
+    Ok(meta_ref::repair_meta_ref(
+        &project,
+        guard.write_permission(),
+    )?)
+}
+
+/// The space taken by the history and data of a single project.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectDataUsage {
+    pub project_id: ProjectId,
+    #[serde(flatten)]
+    pub usage: DataUsage,
+    /// The size of the data kept for the project in the app data directory.
+    pub app_data_bytes: u64,
+}
+
+/// The space taken by the application, as returned by [`data_usage()`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataUsageReport {
+    pub projects: Vec<ProjectDataUsage>,
+    /// The size of the log files, which are shared by all projects.
+    pub logs_bytes: u64,
+}
+
+/// Report how much space the history and data of the project with `project_id` take, or that of all projects
+/// if it's unset.
+#[tauri::command(async)]
+#[instrument(skip(app_handle, projects), err(Debug))]
+pub fn data_usage(
+    app_handle: AppHandle,
+    projects: State<'_, projects::Controller>,
+    project_id: Option<ProjectId>,
+) -> Result<DataUsageReport, Error> {
+    let list = match project_id {
+        Some(project_id) => vec![projects.get(project_id).context("failed to get project")?],
+        None => projects.list()?,
+    };
+    let mut usages = Vec::with_capacity(list.len());
+    for project in list {
+        usages.push(ProjectDataUsage {
+            project_id: project.id,
+            usage: usage::data_usage(&project)?,
+            app_data_bytes: usage::directory_size(&projects.project_metadata_dir(project.id))?,
+        });
+    }
+    let logs_bytes = match app_handle.path().app_log_dir() {
+        Ok(logs_dir) => usage::directory_size(&logs_dir)?,
+        Err(_) => 0,
+    };
+    Ok(DataUsageReport {
+        projects: usages,
+        logs_bytes,
+    })
+}
+
+/// Remove the snapshots of the project that `options` don't keep, along with its old deltas and checkpoints.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn cleanup_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    options: CleanupOptions,
+) -> Result<Cleanup, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let mut guard = project.exclusive_worktree_access();
+    Ok(usage::cleanup(&project, options, guard.write_permission())?)
+}
+
+/// Tell how much history of each file of the project with `project_id` is kept, and how much of it will be
+/// dropped by the history retention of the project.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn history_budget(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<HistoryBudget, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("the clock is before the Unix epoch")?
+        .as_secs();
+    Ok(retention::history_budget(
+        &project,
+        i64::try_from(now).context("the clock is too far in the future")?,
+    )?)
+}
+
+/// Unlock the encrypted history of the project with `project_id` with `passphrase`, or with its key in the
+/// keychain if unset, until the app quits.
+#[tauri::command(async)]
+#[instrument(skip(projects, passphrase), err(Debug))]
+pub fn unlock_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    passphrase: Option<String>,
+) -> Result<(), Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(encryption::unlock(&project, passphrase.as_deref())?)
+}
+
+/// Encrypt the history of the project with `project_id` at rest with a key derived from `passphrase`, or with a
+/// random key kept in the keychain if unset, and return the project with its new settings.
+#[tauri::command(async)]
+#[instrument(skip(projects, passphrase), err(Debug))]
+pub fn encrypt_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    passphrase: Option<String>,
+) -> Result<projects::Project, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(encryption::encrypt_history(
+        &projects,
+        &project,
+        passphrase.as_deref(),
+    )?)
+}
+
+/// Store the encrypted history of the project with `project_id` in plain files again, and return the project with
+/// its new settings.
+#[tauri::command(async)]
+#[instrument(skip(projects), err(Debug))]
+pub fn decrypt_history(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<projects::Project, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(encryption::decrypt_history(&projects, &project)?)
+}
+
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
 pub fn take_synced_snapshot(
@@ -73,3 +672,133 @@ pub fn take_synced_snapshot(
     let snapshot_oid = gitbutler_sync::cloud::take_synced_snapshot(&ctx, &user, stack_id)?;
     Ok(snapshot_oid.to_string())
 }
+
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn backup_history_to_remote(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<bool, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    Ok(gitbutler_sync::history_backup::push_history(
+        &ctx,
+        Some(None),
+    )?)
+}
+
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn restore_history_from_remote(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<String, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let oplog_head =
+        gitbutler_sync::history_backup::restore_history(&ctx, Some("restore-history".to_string()))?;
+    Ok(oplog_head.to_string())
+}
+
+/// Fetch the oplog from the history backup remote and merge it with the local one, interleaving the snapshots
+/// recorded on other machines since the oplogs diverged.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn merge_remote_history(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<HistoryMerge, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    Ok(gitbutler_sync::history_backup::merge_history(
+        &ctx,
+        Some("merge-history".to_string()),
+    )?)
+}
+
+/// The state of a file at one point of a playback, as sent by [`snapshot_playback()`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackFrame {
+    /// The worktree-relative path of the file being played back.
+    pub file_path: PathBuf,
+    /// The position of this frame, starting at 0.
+    pub index: usize,
+    /// The total amount of frames in the playback.
+    pub total: usize,
+    /// The snapshot the file had this content in.
+    pub snapshot_id: String,
+    /// The creation time of the snapshot in seconds since the Unix epoch.
+    pub created_at: i64,
+    pub file: FileInfo,
+    /// The mode of the file, which may be all that changed since the previous frame.
+    pub mode: Option<FileMode>,
+}
+
+/// The most frames per second a playback can be sent with.
+const MAX_PLAYBACK_FPS: u32 = 60;
+
+/// Play back how the file at the worktree-relative `file_path` changed across the most recent `limit` snapshots.
+///
+/// All frames are computed upfront, and then sent as `project://<project_id>/playback/frame` events to the calling
+/// window at a rate of `fps` in the background, oldest first. Returns the amount of frames that will be sent.
+#[tauri::command(async)]
+#[instrument(skip(projects, app_handle, window), err(Debug))]
+pub fn snapshot_playback(
+    projects: State<'_, projects::Controller>,
+    app_handle: AppHandle,
+    window: Window,
+    project_id: ProjectId,
+    file_path: PathBuf,
+    fps: u32,
+    limit: usize,
+) -> Result<usize, Error> {
+    if fps == 0 || fps > MAX_PLAYBACK_FPS {
+        return Err(anyhow::format_err!(
+            "Playback needs between 1 and {MAX_PLAYBACK_FPS} frames per second, got {fps}"
+        )
+        .into());
+    }
+    let project = projects.get(project_id).context("failed to get project")?;
+    let repo = git2::Repository::open(&project.path).context("failed to open repository")?;
+    let versions = project.file_versions(&file_path, limit)?;
+    let total = versions.len();
+    let frames = versions
+        .into_iter()
+        .enumerate()
+        .map(|(index, version)| -> anyhow::Result<_> {
+            let file = match version.blob_id {
+                Some(blob_id) => {
+                    FileInfo::from_content(&file_path, repo.find_blob(blob_id)?.content())
+                }
+                None => FileInfo::deleted(),
+            };
+            Ok(PlaybackFrame {
+                file_path: file_path.clone(),
+                index,
+                total,
+                snapshot_id: version.snapshot_id.to_string(),
+                created_at: version.created_at.seconds(),
+                file,
+                mode: version.mode,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let event_name = format!("project://{project_id}/playback/frame");
+    let frame_interval = Duration::from_secs(1) / fps;
+    let label = window.label().to_owned();
+    std::thread::spawn(move || {
+        for frame in frames {
+            if let Err(err) = app_handle.emit_to(label.as_str(), &event_name, &frame) {
+                tracing::warn!(?err, "failed to send playback frame");
+                return;
+            }
+            std::thread::sleep(frame_interval);
+        }
+    });
+    Ok(total)
+}
@@ -0,0 +1,203 @@
+//! Update the app from the channel chosen in the settings, or the one it was built for if none was chosen.
+//!
+//! [`commands::check_for_updates()`] remembers the update it found, which [`commands::download_update()`] then
+//! downloads while sending its progress to the window as `updater://progress` events. Downloads are only kept if
+//! their signature matches the public key the app was built with, and [`commands::install_update()`] installs them.
+use anyhow::{Context, Result};
+use but_settings::{app_settings::UpdateChannel, AppSettingsWithDiskSync};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use url::Url;
+
+const PROGRESS_EVENT: &str = "updater://progress";
+
+/// Information about an available update, to show to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    /// The release notes.
+    pub body: Option<String>,
+    /// When the update was released.
+    pub date: Option<String>,
+}
+
+impl From<&Update> for UpdateInfo {
+    fn from(update: &Update) -> Self {
+        UpdateInfo {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            body: update.body.clone(),
+            date: update.date.map(|date| date.to_string()),
+        }
+    }
+}
+
+/// The progress of a download, in the shape of `DownloadEvent` of the updater plugin for the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", content = "data", rename_all_fields = "camelCase")]
+pub enum DownloadEvent {
+    Started { content_length: Option<u64> },
+    Progress { chunk_length: usize },
+    Finished,
+}
+
+/// The update found by the last check, along with its verified download once there is one.
+#[derive(Default)]
+pub struct PendingUpdate(parking_lot::Mutex<Option<(Update, Option<Vec<u8>>)>>);
+
+/// Return the endpoint to look up updates of `channel` at, with placeholders filled in by the updater.
+fn endpoint(channel: UpdateChannel) -> Result<Url> {
+    let path = match channel {
+        UpdateChannel::Stable => "release",
+        UpdateChannel::Nightly => "nightly",
+    };
+    Url::parse(&format!(
+        "https://app.gitbutler.com/releases/{path}/{{{{target}}}}-{{{{arch}}}}/{{{{current_version}}}}"
+    ))
+    .context("invalid update endpoint")
+}
+
+/// Look up an update of the app in the channel chosen in the settings.
+///
+/// Note that switching to a channel with older versions doesn't downgrade the app, it waits for a newer one instead.
+pub async fn check(app_handle: &AppHandle) -> Result<Option<Update>> {
+    let channel = app_handle
+        .state::<AppSettingsWithDiskSync>()
+        .get()?
+        .update_channel;
+    let mut updater = app_handle.updater_builder();
+    if let Some(channel) = channel {
+        updater = updater.endpoints(vec![endpoint(channel)?])?;
+    }
+    Ok(updater.build()?.check().await?)
+}
+
+/// Download `update`, sending the progress to the window with `window_label`, and return it once its signature was
+/// verified.
+pub async fn download(
+    app_handle: &AppHandle,
+    window_label: &str,
+    update: &Update,
+) -> Result<Vec<u8>> {
+    let emit = |event: DownloadEvent| {
+        if let Err(err) = app_handle.emit_to(window_label, PROGRESS_EVENT, event) {
+            tracing::warn!(?err, "failed to send download progress");
+        }
+    };
+    let mut started = false;
+    let bytes = update
+        .download(
+            |chunk_length, content_length| {
+                if !started {
+                    started = true;
+                    emit(DownloadEvent::Started { content_length });
+                }
+                emit(DownloadEvent::Progress { chunk_length });
+            },
+            || emit(DownloadEvent::Finished),
+        )
+        .await
+        .with_context(|| format!("failed to download version {}", update.version))?;
+    Ok(bytes)
+}
+
+pub mod commands {
+    use tauri::{AppHandle, State, Window};
+    use tracing::instrument;
+
+    use anyhow::bail;
+
+    use super::{PendingUpdate, UpdateInfo};
+    use crate::error::Error;
+
+    /// Look up an update in the chosen channel, and remember it for downloading. Return `None` if the app is up to
+    /// date.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle, pending), err(Debug))]
+    pub async fn check_for_updates(
+        app_handle: AppHandle,
+        pending: State<'_, PendingUpdate>,
+    ) -> Result<Option<UpdateInfo>, Error> {
+        let update = super::check(&app_handle).await?;
+        let info = update.as_ref().map(UpdateInfo::from);
+        *pending.0.lock() = update.map(|update| (update, None));
+        Ok(info)
+    }
+
+    /// Download the update found by the last check, sending the progress to the calling window as
+    /// `updater://progress` events.
+    #[tauri::command(async)]
+    #[instrument(skip(app_handle, window, pending), err(Debug))]
+    pub async fn download_update(
+        app_handle: AppHandle,
+        window: Window,
+        pending: State<'_, PendingUpdate>,
+    ) -> Result<(), Error> {
+        let Some(update) = pending.0.lock().as_ref().map(|(update, _)| update.clone()) else {
+            return Err(anyhow::anyhow!("there is no update to download").into());
+        };
+        let bytes = super::download(&app_handle, window.label(), &update).await?;
+        if let Some((pending, downloaded)) = pending.0.lock().as_mut() {
+            if pending.version == update.version {
+                *downloaded = Some(bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Install the update downloaded last. Note that on Windows, the app exits to let the installer run.
+    #[tauri::command(async)]
+    #[instrument(skip(pending), err(Debug))]
+    pub fn install_update(pending: State<'_, PendingUpdate>) -> Result<(), Error> {
+        let install = || -> anyhow::Result<()> {
+            let guard = pending.0.lock();
+            let Some((update, Some(bytes))) = guard.as_ref() else {
+                bail!("the update needs to be downloaded before it can be installed");
+            };
+            update.install(bytes)?;
+            Ok(())
+        };
+        Ok(install()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_keep_their_placeholders() {
+        let url = endpoint(UpdateChannel::Stable).unwrap().to_string();
+        assert!(url.starts_with("https://app.gitbutler.com/releases/release/"));
+        assert!(
+            url.ends_with("%7B%7Btarget%7D%7D-%7B%7Barch%7D%7D/%7B%7Bcurrent_version%7D%7D"),
+            "the updater replaces the encoded placeholders: {url}"
+        );
+        assert!(endpoint(UpdateChannel::Nightly)
+            .unwrap()
+            .path()
+            .starts_with("/releases/nightly/"));
+    }
+
+    #[test]
+    fn download_events_match_the_frontend() {
+        let json = |event| serde_json::to_value(event).unwrap();
+        assert_eq!(
+            json(DownloadEvent::Started {
+                content_length: Some(3)
+            }),
+            serde_json::json!({"event": "Started", "data": {"contentLength": 3}})
+        );
+        assert_eq!(
+            json(DownloadEvent::Progress { chunk_length: 1 }),
+            serde_json::json!({"event": "Progress", "data": {"chunkLength": 1}})
+        );
+        assert_eq!(
+            json(DownloadEvent::Finished),
+            serde_json::json!({"event": "Finished"})
+        );
+    }
+}
@@ -4,24 +4,30 @@ pub mod commands {
     use but_workspace::StackEntry;
     use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
     use gitbutler_branch_actions::branch_upstream_integration::IntegrationStrategy;
+    use gitbutler_branch_actions::change_groups::ChangeGroup;
     use gitbutler_branch_actions::internal::StackListResult;
+    use gitbutler_branch_actions::suspend::{ResumedWorkspace, SuspendedWorkspace};
     use gitbutler_branch_actions::upstream_integration::{
-        BaseBranchResolution, BaseBranchResolutionApproach, Resolution, StackStatuses,
+        BaseBranchResolution, BaseBranchResolutionApproach, Resolution, ResolutionApproach,
+        StackStatuses, StackUpdate,
     };
     use gitbutler_branch_actions::{
-        BaseBranch, BranchListing, BranchListingDetails, BranchListingFilter, RemoteBranchData,
-        RemoteBranchFile, RemoteCommit, StackOrder, VirtualBranchHunkRangeMap, VirtualBranches,
+        BaseBranch, BranchListing, BranchListingDetails, BranchListingFilter, CherryPickOutcome,
+        PendingRebase, RebaseInstruction, RebaseStatus, RemoteBranchData, RemoteBranchFile,
+        RemoteCommit, StackOrder, StaleBranch, VirtualBranchHunkRangeMap, VirtualBranches,
     };
     use gitbutler_command_context::CommandContext;
     use gitbutler_project as projects;
-    use gitbutler_project::{FetchResult, ProjectId};
+    use gitbutler_project::{CoAuthor, FetchResult, ProjectId};
     use gitbutler_reference::{normalize_branch_name as normalize_name, Refname, RemoteRefname};
+    use gitbutler_repo::commit_message;
     use gitbutler_stack::{BranchOwnershipClaims, StackId};
     use std::path::PathBuf;
-    use tauri::State;
+    use std::sync::atomic::AtomicBool;
+    use tauri::{AppHandle, State};
     use tracing::instrument;
 
-    use crate::{error::Error, WindowState};
+    use crate::{error::Error, in_blocking_thread, operations::Operations, WindowState};
 
     #[tauri::command(async)]
     #[instrument(err(Debug))]
@@ -29,8 +35,11 @@ pub mod commands {
         Ok(normalize_name(name)?)
     }
 
+    /// Commit the changes owned by `branch` with `message`, signed off by the author if `sign_off` is `true`, and
+    /// crediting each of `co_authors` with a `Co-authored-by` trailer.
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
     pub fn commit_virtual_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
@@ -39,27 +48,115 @@ pub mod commands {
         branch: StackId,
         message: &str,
         ownership: Option<BranchOwnershipClaims>,
+        sign_off: Option<bool>,
+        co_authors: Option<Vec<CoAuthor>>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let message = commit_message::with_trailers(
+            ctx.repo(),
+            message,
+            sign_off.unwrap_or(false),
+            &co_authors.unwrap_or_default(),
+        )?;
+        let oid =
+            gitbutler_branch_actions::create_commit(&ctx, branch, &message, ownership.as_ref())?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(oid.to_string())
+    }
+
+    /// Commit the changes to all files that changed during the activity session that started at `session_id`,
+    /// in seconds since the Unix epoch.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn commit_session(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        session_id: i64,
+        message: &str,
     ) -> Result<String, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
         let oid =
-            gitbutler_branch_actions::create_commit(&ctx, branch, message, ownership.as_ref())?;
+            gitbutler_branch_actions::session_commit::commit_session(&ctx, session_id, message)?;
         emit_vbranches(&windows, project_id, ctx.app_settings());
         Ok(oid.to_string())
     }
 
+    /// Put all uncommitted changes aside as the suspended workspace `name`, leaving a clean worktree.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn suspend_workspace(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        name: &str,
+    ) -> Result<SuspendedWorkspace, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let suspended = gitbutler_branch_actions::suspend::suspend_workspace(&ctx, name)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(suspended)
+    }
+
+    /// Restore the uncommitted changes of the suspended workspace `name` into the clean worktree.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn resume_workspace(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        name: &str,
+    ) -> Result<ResumedWorkspace, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let resumed = gitbutler_branch_actions::suspend::resume_workspace(&ctx, name)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(resumed)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings), err(Debug))]
-    pub fn list_virtual_branches(
+    pub fn list_suspended_workspaces(
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
         project_id: ProjectId,
-    ) -> Result<VirtualBranches, Error> {
+    ) -> Result<Vec<SuspendedWorkspace>, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        Ok(gitbutler_branch_actions::suspend::list_suspended_workspaces(&ctx)?)
+    }
+
+    /// Suggest how to split the uncommitted changes into groups by the session they were written in, as told by
+    /// their deltas.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub fn suggest_change_groups(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<Vec<ChangeGroup>, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        gitbutler_branch_actions::list_virtual_branches(&ctx)
-            .map_err(Into::into)
-            .map(
+        Ok(gitbutler_branch_actions::change_groups::suggest(&ctx)?)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub async fn list_virtual_branches(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<VirtualBranches, Error> {
+        let project = projects.get(project_id)?;
+        let settings = settings.get()?.clone();
+        in_blocking_thread(move || {
+            let ctx = CommandContext::open(&project, settings)?;
+            gitbutler_branch_actions::list_virtual_branches(&ctx).map(
                 |StackListResult {
                      branches,
                      skipped_files,
@@ -70,6 +167,8 @@ pub mod commands {
                     dependency_errors,
                 },
             )
+        })
+        .await
     }
 
     #[tauri::command(async)]
@@ -105,6 +204,36 @@ pub mod commands {
         Ok(())
     }
 
+    /// List the local branches that are merged into the target branch or whose remote branch is gone.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub fn stale_branches(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<Vec<StaleBranch>, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        Ok(gitbutler_branch_actions::stale_branches(&ctx)?)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn delete_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        names: Vec<String>,
+        force: bool,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        gitbutler_branch_actions::delete_branches(&ctx, &names, force)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn create_virtual_branch_from_branch(
@@ -164,6 +293,18 @@ pub mod commands {
         }
     }
 
+    /// Return the default branch of the repository, as the `HEAD` of a remote points to it, to suggest as base branch.
+    #[tauri::command(async)]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_default_branch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Option<RemoteRefname>, Error> {
+        let project = projects.get(project_id)?;
+        let repo = git2::Repository::open(&project.path).map_err(anyhow::Error::from)?;
+        Ok(gitbutler_branch_actions::base::default_branch(&repo)?)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn set_base_branch(
@@ -190,8 +331,9 @@ pub mod commands {
     }
 
     #[tauri::command(async)]
-    #[instrument(skip(projects, settings, windows), err(Debug))]
+    #[instrument(skip(app, projects, settings, windows), err(Debug))]
     pub fn push_base_branch(
+        app: AppHandle,
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
@@ -200,7 +342,10 @@ pub mod commands {
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        gitbutler_branch_actions::push_base_branch(&ctx, with_force)?;
+        if let Err(err) = gitbutler_branch_actions::push_base_branch(&ctx, with_force) {
+            crate::notifications::notify_push_failed(&app, project_id, "the target branch", &err);
+            return Err(err.into());
+        }
         emit_vbranches(&windows, project_id, ctx.app_settings());
         Ok(())
     }
@@ -286,6 +431,23 @@ pub mod commands {
         Ok(())
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn transfer_ownership(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        stack_id: StackId,
+        ownership: BranchOwnershipClaims,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        gitbutler_branch_actions::transfer_ownership(&ctx, stack_id, &ownership)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn unapply_lines(
@@ -320,6 +482,24 @@ pub mod commands {
         Ok(())
     }
 
+    /// Discard the uncommitted changes to `paths`, and return the id of the snapshot that brings them back when
+    /// restored.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn discard_changes(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        paths: Vec<PathBuf>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let snapshot = gitbutler_branch_actions::discard_changes(&ctx, &paths)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(snapshot.to_string())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings), err(Debug))]
     pub fn can_apply_remote_branch(
@@ -386,6 +566,60 @@ pub mod commands {
         Ok(oid.to_string())
     }
 
+    /// Amend the head commit of the branch with the uncommitted changes to `paths` and, if set, `message`.
+    /// Commits pushed to a protected branch are only amended if `force` is set.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn amend_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        branch_id: StackId,
+        paths: Option<Vec<PathBuf>>,
+        message: Option<String>,
+        force: Option<bool>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let oid = gitbutler_branch_actions::amend_commit(
+            &ctx,
+            branch_id,
+            &paths.unwrap_or_default(),
+            message.as_deref(),
+            force.unwrap_or_default(),
+        )?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(oid.to_string())
+    }
+
+    /// Change the message of any commit in the workspace, rebasing the commits above it.
+    /// Commits pushed to a protected branch are only reworded if `force` is set.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn reword_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        commit_oid: String,
+        message: &str,
+        force: Option<bool>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        gitbutler_branch_actions::reword_commit(
+            &ctx,
+            commit_oid,
+            message,
+            force.unwrap_or_default(),
+        )?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     #[allow(clippy::too_many_arguments)]
@@ -432,6 +666,103 @@ pub mod commands {
         Ok(())
     }
 
+    /// Apply the changes of the commit with `commit_oid` to the worktree, leaving conflict markers in
+    /// files that can't be merged cleanly.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn cherry_pick(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        commit_oid: String,
+    ) -> Result<CherryPickOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let outcome = gitbutler_branch_actions::cherry_pick(&ctx, commit_oid)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(outcome)
+    }
+
+    /// Undo the changes of the commit with `commit_oid` in the worktree, leaving conflict markers in
+    /// files that can't be merged cleanly.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn revert_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        commit_oid: String,
+    ) -> Result<CherryPickOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let outcome = gitbutler_branch_actions::revert_commit(&ctx, commit_oid)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(outcome)
+    }
+
+    /// Rewrite the stack containing the commits in `instructions` and place its commits onto `onto`.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn start_rebase(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        onto: String,
+        instructions: Vec<RebaseInstruction>,
+    ) -> Result<RebaseStatus, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let onto = git2::Oid::from_str(&onto).map_err(|e| anyhow!(e))?;
+        let status = gitbutler_branch_actions::start_rebase(&ctx, onto, instructions)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(status)
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn resume_rebase(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        gitbutler_branch_actions::resume_rebase(&ctx)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(())
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub fn abort_rebase(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        gitbutler_branch_actions::abort_rebase(&ctx)?;
+        Ok(())
+    }
+
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub fn pending_rebase(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<Option<PendingRebase>, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        Ok(gitbutler_branch_actions::pending_rebase(&ctx)?)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn insert_blank_commit(
@@ -484,16 +815,19 @@ pub mod commands {
 
     #[tauri::command(async)]
     #[instrument(skip(projects, settings), err(Debug))]
-    pub fn list_branches(
+    pub async fn list_branches(
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
         project_id: ProjectId,
         filter: Option<BranchListingFilter>,
     ) -> Result<Vec<BranchListing>, Error> {
         let project = projects.get(project_id)?;
-        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
-        let branches = gitbutler_branch_actions::list_branches(&ctx, filter, None)?;
-        Ok(branches)
+        let settings = settings.get()?.clone();
+        in_blocking_thread(move || {
+            let ctx = CommandContext::open(&project, settings)?;
+            gitbutler_branch_actions::list_branches(&ctx, filter, None)
+        })
+        .await
     }
 
     #[tauri::command(async)]
@@ -540,41 +874,61 @@ pub mod commands {
         Ok(())
     }
 
+    /// Fetch all remotes of the project, which can be cancelled while it runs as the operation with
+    /// `operation_id`.
     #[tauri::command(async)]
-    #[instrument(skip(projects, settings, windows), err(Debug))]
+    #[instrument(skip(projects, settings, windows, operations), err(Debug))]
     pub fn fetch_from_remotes(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         settings: State<'_, AppSettingsWithDiskSync>,
+        operations: State<'_, Operations>,
         project_id: ProjectId,
         action: Option<String>,
+        operation_id: Option<String>,
     ) -> Result<BaseBranch, Error> {
         let project = projects.get(project_id)?;
         let ctx = CommandContext::open(&project, settings.get()?.clone())?;
 
-        let project_data_last_fetched = gitbutler_branch_actions::fetch_from_remotes(
-            &ctx,
-            Some(action.unwrap_or_else(|| "unknown".to_string())),
-        )?;
+        operations.run(operation_id.as_deref(), |should_interrupt| {
+            fetch_and_record(
+                &projects,
+                &ctx,
+                action.unwrap_or_else(|| "unknown".to_string()),
+                should_interrupt,
+            )
+        })?;
+
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        let base_branch = gitbutler_branch_actions::base::get_base_branch_data(&ctx)?;
+        Ok(base_branch)
+    }
+
+    /// Fetch all remotes of the project of `ctx`, and record when it was fetched last.
+    fn fetch_and_record(
+        projects: &projects::Controller,
+        ctx: &CommandContext,
+        action: String,
+        should_interrupt: &AtomicBool,
+    ) -> anyhow::Result<()> {
+        let project_data_last_fetched =
+            gitbutler_branch_actions::fetch_from_remotes(ctx, Some(action), should_interrupt)?;
 
         // Updates the project controller with the last fetched timestamp
         //
         // TODO: This cross dependency likely indicates that last_fetched is stored in the wrong place - value is coupled with virtual branches state
         projects
             .update(&projects::UpdateRequest {
-                id: project.id,
+                id: ctx.project().id,
                 project_data_last_fetched: Some(project_data_last_fetched.clone()),
                 ..Default::default()
             })
             .context("failed to update project with last fetched timestamp")?;
 
         if let FetchResult::Error { error, .. } = project_data_last_fetched {
-            return Err(anyhow!(error).into());
+            return Err(anyhow!(error));
         }
-
-        emit_vbranches(&windows, project_id, ctx.app_settings());
-        let base_branch = gitbutler_branch_actions::base::get_base_branch_data(&ctx)?;
-        Ok(base_branch)
+        Ok(())
     }
 
     #[tauri::command(async)]
@@ -671,6 +1025,32 @@ pub mod commands {
         Ok(())
     }
 
+    /// Fetch the remotes of the project, then update all applied stacks to the latest commit of the target branch
+    /// by rebasing or merging, as `strategy` says, and return how each stack was updated and if it conflicted.
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn update_from_base(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        strategy: ResolutionApproach,
+    ) -> Result<Vec<StackUpdate>, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+
+        fetch_and_record(
+            &projects,
+            &ctx,
+            "update-from-base".to_string(),
+            &AtomicBool::new(false),
+        )?;
+        let updates = gitbutler_branch_actions::update_from_base(&ctx, strategy)?;
+
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(updates)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings), err(Debug))]
     pub fn resolve_upstream_integration(
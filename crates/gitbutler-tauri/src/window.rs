@@ -1,20 +1,28 @@
 pub(crate) mod state {
-    use std::{collections::BTreeMap, sync::Arc};
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+        sync::{Arc, Weak},
+        time::{Duration, Instant},
+    };
 
     use anyhow::{Context, Result};
     use but_settings::AppSettingsWithDiskSync;
     use gitbutler_project as projects;
-    use gitbutler_project::ProjectId;
+    use gitbutler_project::{ProjectId, WatcherMode};
     use gitbutler_user as users;
-    use tauri::{AppHandle, Manager};
+    use tauri::{AppHandle, Emitter, EventTarget, Manager};
     use tracing::instrument;
 
     pub(crate) mod event {
+        use std::collections::{HashMap, VecDeque};
+
         use anyhow::{Context, Result};
         use but_settings::AppSettings;
         use gitbutler_project::ProjectId;
         use gitbutler_watcher::Change;
-        use tauri::Emitter;
+        use serde::Serialize;
+        use tauri::{Emitter, EventTarget};
 
         /// A change we want to inform the frontend about.
         #[derive(Debug, Clone, PartialEq, Eq)]
@@ -46,6 +54,26 @@ pub(crate) mod state {
                         payload: serde_json::json!({}),
                         project_id,
                     },
+                    Change::GitIndexChanged(project_id) => ChangeForFrontend {
+                        name: "git://index-changed".to_string(),
+                        payload: serde_json::json!({ "projectId": project_id }),
+                        project_id,
+                    },
+                    Change::GitHeadChanged {
+                        project_id,
+                        old,
+                        new,
+                        message,
+                    } => ChangeForFrontend {
+                        name: "git://head-changed".to_string(),
+                        payload: serde_json::json!({
+                            "projectId": project_id,
+                            "old": old,
+                            "new": new,
+                            "message": message,
+                        }),
+                        project_id,
+                    },
                     Change::VirtualBranches {
                         project_id,
                         virtual_branches,
@@ -67,6 +95,44 @@ pub(crate) mod state {
                         payload: serde_json::json!(&but_core::ui::WorktreeChanges::from(changes)),
                         project_id,
                     },
+                    Change::FilesRenamed {
+                        project_id,
+                        renames,
+                    } => ChangeForFrontend {
+                        name: format!("project://{}/files/renamed", project_id),
+                        payload: serde_json::json!(renames
+                            .iter()
+                            .map(|(from, to)| serde_json::json!({ "from": from, "to": to }))
+                            .collect::<Vec<_>>()),
+                        project_id,
+                    },
+                    Change::FilesChanged { project_id, paths } => ChangeForFrontend {
+                        name: format!("project://{}/files/changed", project_id),
+                        payload: serde_json::json!({ "paths": paths }),
+                        project_id,
+                    },
+                    Change::ActivityPulse { project_id, pulse } => ChangeForFrontend {
+                        name: "activity://pulse".to_string(),
+                        payload: serde_json::json!({
+                            "projectId": project_id,
+                            "editsPerMinute": pulse.edits_per_minute,
+                            "activeFiles": pulse.active_files,
+                        }),
+                        project_id,
+                    },
+                    Change::UpstreamConflicts {
+                        project_id,
+                        conflicts,
+                    } => ChangeForFrontend {
+                        name: format!("project://{}/upstream-conflicts", project_id),
+                        payload: serde_json::json!(conflicts),
+                        project_id,
+                    },
+                    Change::ProjectReady(project_id) => ChangeForFrontend {
+                        name: format!("project://{}/ready", project_id),
+                        payload: serde_json::json!({}),
+                        project_id,
+                    },
                 }
             }
         }
@@ -83,6 +149,7 @@ pub(crate) mod state {
         }
 
         impl ChangeForFrontend {
+            /// Send this change to all windows.
             pub fn send(&self, app_handle: &tauri::AppHandle) -> Result<()> {
                 app_handle
                     .emit(&self.name, Some(&self.payload))
@@ -90,17 +157,175 @@ pub(crate) mod state {
                 tracing::trace!(event_name = self.name);
                 Ok(())
             }
+
+            /// Send this change only to the windows with the given `labels`, typically the ones
+            /// which display the project the change belongs to.
+            pub fn send_to_windows(
+                &self,
+                app_handle: &tauri::AppHandle,
+                labels: &[String],
+            ) -> Result<()> {
+                app_handle
+                    .emit_filter(&self.name, Some(&self.payload), |target| match target {
+                        EventTarget::Window { label }
+                        | EventTarget::Webview { label }
+                        | EventTarget::WebviewWindow { label } => labels.contains(label),
+                        _ => false,
+                    })
+                    .context("emit event")?;
+                tracing::trace!(event_name = self.name, windows = labels.len());
+                Ok(())
+            }
+
+            /// The id of the project this change belongs to.
+            pub fn project_id(&self) -> ProjectId {
+                self.project_id
+            }
+        }
+
+        /// A change that was sent to the frontend, as kept for replay.
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct RecordedChange {
+            /// The sequence number of the change, unique and increasing across all projects.
+            pub seq: u64,
+            pub name: String,
+            pub payload: serde_json::Value,
+        }
+
+        /// The changes of a project that happened after a given sequence number.
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct EventReplay {
+            /// The sequence number of the latest change of any project, to be passed as `since_seq` next time.
+            pub latest_seq: u64,
+            /// All changes of the project after `since_seq`, oldest first.
+            pub events: Vec<RecordedChange>,
+            /// If `false`, some changes after `since_seq` were already dropped from the buffer
+            /// and the state has to be refetched.
+            pub complete: bool,
+        }
+
+        /// A ring buffer with the most recent changes of each project, to allow reloading or newly opened
+        /// windows to catch up without refetching everything.
+        #[derive(Debug, Default)]
+        pub struct ReplayBuffer {
+            latest_seq: u64,
+            by_project: HashMap<ProjectId, ProjectChanges>,
+        }
+
+        #[derive(Debug, Default)]
+        struct ProjectChanges {
+            changes: VecDeque<RecordedChange>,
+            /// The sequence number of the latest change that was dropped to make room.
+            last_dropped_seq: Option<u64>,
+        }
+
+        impl ReplayBuffer {
+            /// The amount of changes to keep per project.
+            pub const CAPACITY: usize = 128;
+
+            /// Record `change` and return its sequence number.
+            pub fn record(&mut self, change: &ChangeForFrontend) -> u64 {
+                self.latest_seq += 1;
+                let project = self.by_project.entry(change.project_id).or_default();
+                if project.changes.len() == Self::CAPACITY {
+                    project.last_dropped_seq = project.changes.pop_front().map(|c| c.seq);
+                }
+                project.changes.push_back(RecordedChange {
+                    seq: self.latest_seq,
+                    name: change.name.clone(),
+                    payload: change.payload.clone(),
+                });
+                self.latest_seq
+            }
+
+            /// Return all changes of `project_id` after `since_seq`, or only the latest sequence number
+            /// if `since_seq` is `None`.
+            pub fn since(&self, project_id: ProjectId, since_seq: Option<u64>) -> EventReplay {
+                let project = self.by_project.get(&project_id);
+                let (events, complete) = match (since_seq, project) {
+                    (Some(since_seq), Some(project)) => (
+                        project
+                            .changes
+                            .iter()
+                            .filter(|change| change.seq > since_seq)
+                            .cloned()
+                            .collect(),
+                        project
+                            .last_dropped_seq
+                            .is_none_or(|dropped| dropped <= since_seq),
+                    ),
+                    (None, _) | (_, None) => (Vec::new(), true),
+                };
+                EventReplay {
+                    latest_seq: self.latest_seq,
+                    events,
+                    complete,
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            fn change(project_id: ProjectId) -> ChangeForFrontend {
+                ChangeForFrontend::from(Change::GitFetch(project_id))
+            }
+
+            #[test]
+            fn replays_changes_of_project_after_seq() {
+                let mut buffer = ReplayBuffer::default();
+                let (a, b) = (ProjectId::generate(), ProjectId::generate());
+                assert_eq!(buffer.since(a, None).latest_seq, 0);
+
+                buffer.record(&change(a));
+                let seq = buffer.record(&change(b));
+                buffer.record(&change(a));
+
+                let replay = buffer.since(a, Some(seq));
+                assert_eq!(replay.latest_seq, 3);
+                assert_eq!(replay.events.len(), 1);
+                assert_eq!(replay.events[0].seq, 3);
+                assert!(replay.complete);
+                assert_eq!(buffer.since(a, Some(0)).events.len(), 2);
+            }
+
+            #[test]
+            fn incomplete_once_changes_were_dropped() {
+                let mut buffer = ReplayBuffer::default();
+                let project_id = ProjectId::generate();
+                for _ in 0..ReplayBuffer::CAPACITY + 1 {
+                    buffer.record(&change(project_id));
+                }
+                let replay = buffer.since(project_id, Some(0));
+                assert_eq!(replay.events.len(), ReplayBuffer::CAPACITY);
+                assert!(!replay.complete);
+                assert!(buffer.since(project_id, Some(1)).complete);
+            }
         }
     }
-    use event::ChangeForFrontend;
+    use event::{ChangeForFrontend, EventReplay, ReplayBuffer};
 
     struct State {
         /// The id of the project displayed by the window.
         project_id: ProjectId,
-        /// The watcher of the currently active project.
-        watcher: gitbutler_watcher::WatcherHandle,
+        /// The watcher of the currently active project, or `None` while it's stopped as the project was inactive.
+        watcher: Option<SharedWatcher>,
+        /// The worktree of the project, to restart the watcher in.
+        worktree_dir: PathBuf,
+        /// How the watcher notices changes, as configured for the project.
+        watcher_mode: WatcherMode,
+        /// The worktree-relative directories the watcher is limited to, as configured for the project.
+        watch_include_paths: Vec<PathBuf>,
+        app_settings: AppSettingsWithDiskSync,
+        /// When the project was last activated, or interacted with.
+        last_active: Instant,
         /// An active lock to signal that the entire project is locked for the Window this state belongs to.
         exclusive_access: gitbutler_project::access::LockFile,
+        /// Worktree-relative paths of the files the window wants to hear about when they change on disk.
+        subscribed_files: BTreeSet<PathBuf>,
     }
 
     impl Drop for State {
@@ -112,9 +337,44 @@ pub(crate) mod state {
         }
     }
 
+    /// The watcher of a worktree as used by one of the projects in it, which may share it with the others.
+    struct SharedWatcher {
+        project_id: ProjectId,
+        handle: Arc<gitbutler_watcher::WatcherHandle>,
+    }
+
+    impl std::ops::Deref for SharedWatcher {
+        type Target = gitbutler_watcher::WatcherHandle;
+
+        fn deref(&self) -> &Self::Target {
+            &self.handle
+        }
+    }
+
+    impl Drop for SharedWatcher {
+        fn drop(&mut self) {
+            // The watcher stops once the last project using it is done, until then it just forgets about this one.
+            if Arc::strong_count(&self.handle) > 1 {
+                self.handle.remove_project(self.project_id).ok();
+            }
+        }
+    }
+
     type WindowLabel = String;
     pub(super) type WindowLabelRef = str;
 
+    /// The labels of projects whose windows were closed while their changes keep being recorded in the background
+    /// start with this, followed by the project id.
+    const BACKGROUND_LABEL_PREFIX: &str = "background:";
+
+    fn background_label(project_id: ProjectId) -> WindowLabel {
+        format!("{BACKGROUND_LABEL_PREFIX}{project_id}")
+    }
+
+    fn is_background(label: &WindowLabelRef) -> bool {
+        label.starts_with(BACKGROUND_LABEL_PREFIX)
+    }
+
     /// State associated to windows
     /// Note that this type is managed in Tauri and thus needs to be `Send` and `Sync`.
     #[derive(Clone)]
@@ -123,23 +383,109 @@ pub(crate) mod state {
         app_handle: AppHandle,
         /// The state for every open application window.
         state: Arc<parking_lot::Mutex<BTreeMap<WindowLabel, State>>>,
+        /// The most recent changes sent to windows, for them to catch up after reloading.
+        replay: Arc<parking_lot::Mutex<ReplayBuffer>>,
+        /// The running watchers by worktree, for the projects of a repository to share one.
+        watchers:
+            Arc<parking_lot::Mutex<BTreeMap<PathBuf, Weak<gitbutler_watcher::WatcherHandle>>>>,
     }
 
-    fn handler_from_app(app: &AppHandle) -> Result<gitbutler_watcher::Handler> {
+    /// Create a handler which sends changes only to the windows that currently display the project
+    /// the change belongs to, as found in `state`.
+    fn handler_from_app(
+        app: &AppHandle,
+        state: Arc<parking_lot::Mutex<BTreeMap<WindowLabel, State>>>,
+        replay: Arc<parking_lot::Mutex<ReplayBuffer>>,
+    ) -> Result<gitbutler_watcher::Handler> {
         let projects = app.state::<projects::Controller>().inner().clone();
         let users = app.state::<users::Controller>().inner().clone();
 
         Ok(gitbutler_watcher::Handler::new(projects, users, {
             let app = app.clone();
-            move |change| ChangeForFrontend::from(change).send(&app)
+            move |change| {
+                if let gitbutler_watcher::Change::GitFetch(project_id) = &change {
+                    crate::forge::emit_branch_forge_status_in_background(&app, *project_id);
+                }
+                // Pulses are for indicators in every window, and too short-lived to be worth replaying.
+                if matches!(change, gitbutler_watcher::Change::ActivityPulse { .. }) {
+                    return ChangeForFrontend::from(change).send(&app);
+                }
+                // File changes are only of interest to the windows that subscribed to them, and each
+                // one only learns about its own files. They are also too frequent to be worth replaying.
+                if let gitbutler_watcher::Change::FilesChanged { project_id, paths } = change {
+                    let subscribers = subscribers_of_files(&state.lock(), project_id, &paths);
+                    for (label, paths) in subscribers {
+                        ChangeForFrontend::from(gitbutler_watcher::Change::FilesChanged {
+                            project_id,
+                            paths,
+                        })
+                        .send_to_windows(&app, &[label])?;
+                    }
+                    return Ok(());
+                }
+                let change = ChangeForFrontend::from(change);
+                replay.lock().record(&change);
+                let labels = windows_for_project(&state.lock(), change.project_id());
+                change.send_to_windows(&app, &labels)
+            }
         }))
     }
 
+    /// Return the watcher running in `worktree_dir` among `watchers` after adding the project with `project_id`
+    /// to it, or `None` if there is none.
+    fn share_watcher(
+        watchers: &BTreeMap<PathBuf, Weak<gitbutler_watcher::WatcherHandle>>,
+        project_id: ProjectId,
+        worktree_dir: &Path,
+        watch_include_paths: &[PathBuf],
+    ) -> Result<Option<SharedWatcher>> {
+        let Some(handle) = watchers.get(worktree_dir).and_then(Weak::upgrade) else {
+            return Ok(None);
+        };
+        handle.add_project(project_id, watch_include_paths.to_vec())?;
+        tracing::debug!(%project_id, owner = %handle.project_id(), "sharing watcher of worktree");
+        Ok(Some(SharedWatcher { project_id, handle }))
+    }
+
+    fn windows_for_project(
+        state_by_label: &BTreeMap<WindowLabel, State>,
+        project_id: ProjectId,
+    ) -> Vec<WindowLabel> {
+        state_by_label
+            .iter()
+            .filter(|(_, state)| state.project_id == project_id)
+            .map(|(label, _)| label.clone())
+            .collect()
+    }
+
+    /// Return the labels of windows displaying `project_id` along with those of `paths` they subscribed to,
+    /// skipping windows that aren't subscribed to any of them.
+    fn subscribers_of_files(
+        state_by_label: &BTreeMap<WindowLabel, State>,
+        project_id: ProjectId,
+        paths: &[PathBuf],
+    ) -> Vec<(WindowLabel, Vec<PathBuf>)> {
+        state_by_label
+            .iter()
+            .filter(|(_, state)| state.project_id == project_id)
+            .filter_map(|(label, state)| {
+                let paths: Vec<_> = paths
+                    .iter()
+                    .filter(|path| state.subscribed_files.contains(*path))
+                    .cloned()
+                    .collect();
+                (!paths.is_empty()).then(|| (label.clone(), paths))
+            })
+            .collect()
+    }
+
     impl WindowState {
         pub fn new(app_handle: AppHandle) -> Self {
             Self {
                 app_handle,
                 state: Default::default(),
+                replay: Default::default(),
+                watchers: Default::default(),
             }
         }
 
@@ -155,65 +501,426 @@ pub(crate) mod state {
             app_settings: AppSettingsWithDiskSync,
         ) -> Result<()> {
             let mut state_by_label = self.state.lock();
-            if let Some(state) = state_by_label.get(window) {
+            if !state_by_label
+                .get(window)
+                .is_some_and(|state| state.project_id == project.id)
+            {
+                // Take over the project from the background, along with its watcher and lock.
+                if let Some(state) = state_by_label.remove(&background_label(project.id)) {
+                    state_by_label.insert(window.to_owned(), state);
+                }
+            }
+            if let Some(state) = state_by_label.get_mut(window) {
                 if state.project_id == project.id {
+                    if state.watcher_mode != project.watcher_mode {
+                        // Restart the watcher in the newly configured mode.
+                        state.watcher = None;
+                        state.watcher_mode = project.watcher_mode;
+                    }
+                    if state.watch_include_paths != project.watch_include_paths {
+                        state.watch_include_paths = project.watch_include_paths.clone();
+                        if let Some(watcher) = &state.watcher {
+                            watcher
+                                .set_include_paths(project.id, state.watch_include_paths.clone())?;
+                        }
+                    }
+                    drop(state_by_label);
+                    self.resume_watcher(window)?;
                     return Ok(());
                 }
             }
             let exclusive_access = project.try_exclusive_access()?;
-            let handler = handler_from_app(&self.app_handle)?;
             let worktree_dir = project.path.clone();
             let project_id = project.id;
-            let watcher = gitbutler_watcher::watch_in_background(
-                handler,
-                worktree_dir,
+            let watcher_mode = project.watcher_mode;
+            let watch_include_paths = project.watch_include_paths.clone();
+            // Starting a watcher registers all directories of the worktree, so do it without holding on to the
+            // state of all windows, for several windows to start theirs at the same time and the others to stay
+            // responsive meanwhile.
+            drop(state_by_label);
+            let watcher = self.start_watcher(
                 project_id,
-                app_settings,
+                &worktree_dir,
+                watcher_mode,
+                &watch_include_paths,
+                &app_settings,
             )?;
+            let mut state_by_label = self.state.lock();
             state_by_label.insert(
                 window.to_owned(),
                 State {
                     project_id,
-                    watcher,
+                    watcher: Some(watcher),
+                    worktree_dir,
+                    watcher_mode,
+                    watch_include_paths,
+                    app_settings,
+                    last_active: Instant::now(),
                     exclusive_access,
+                    subscribed_files: Default::default(),
                 },
             );
             tracing::debug!("Maintaining {} Windows", state_by_label.len());
             Ok(())
         }
 
-        pub fn post(&self, action: gitbutler_watcher::Action) -> Result<()> {
+        fn start_watcher(
+            &self,
+            project_id: ProjectId,
+            worktree_dir: &Path,
+            watcher_mode: WatcherMode,
+            watch_include_paths: &[PathBuf],
+            app_settings: &AppSettingsWithDiskSync,
+        ) -> Result<SharedWatcher> {
+            {
+                let mut watchers = self.watchers.lock();
+                watchers.retain(|_, watcher| watcher.strong_count() > 0);
+                if let Some(watcher) =
+                    share_watcher(&watchers, project_id, worktree_dir, watch_include_paths)?
+                {
+                    return Ok(watcher);
+                }
+            }
+            // Start it without holding on to the watchers of other worktrees, so they can start at the same time.
+            let handler =
+                handler_from_app(&self.app_handle, self.state.clone(), self.replay.clone())?;
+            let handle = Arc::new(gitbutler_watcher::watch_in_background(
+                handler,
+                worktree_dir,
+                project_id,
+                watcher_mode,
+                watch_include_paths.to_vec(),
+                app_settings.clone(),
+            )?);
+            let mut watchers = self.watchers.lock();
+            // Another project of the worktree may have started one meanwhile, then this one stops again.
+            if let Some(watcher) =
+                share_watcher(&watchers, project_id, worktree_dir, watch_include_paths)?
+            {
+                return Ok(watcher);
+            }
+            watchers.insert(worktree_dir.to_owned(), Arc::downgrade(&handle));
+            Ok(SharedWatcher { project_id, handle })
+        }
+
+        /// Return the watcher of the project displayed by `window`, restarting it if it was stopped while the
+        /// project was inactive, or `None` if the window doesn't display a project.
+        ///
+        /// After a restart, the state of the project is recalculated and the window learns that all files it
+        /// subscribed to may have changed, to catch up with what happened while nobody was watching. Like in
+        /// [`Self::set_project_to_window()`], the watcher is started without holding on to the state of all windows.
+        fn resume_watcher(
+            &self,
+            window: &WindowLabelRef,
+        ) -> Result<Option<Arc<gitbutler_watcher::WatcherHandle>>> {
             let mut state_by_label = self.state.lock();
-            let state = state_by_label
-                .values_mut()
-                .find(|state| state.project_id == action.project_id());
-            if let Some(state) = state {
-                state.watcher.post(action).context("failed to post event")
-            } else {
-                Err(anyhow::anyhow!(
+            let Some(state) = state_by_label.get_mut(window) else {
+                return Ok(None);
+            };
+            state.last_active = Instant::now();
+            if let Some(watcher) = &state.watcher {
+                return Ok(Some(watcher.handle.clone()));
+            }
+            let project_id = state.project_id;
+            let worktree_dir = state.worktree_dir.clone();
+            let watcher_mode = state.watcher_mode;
+            let watch_include_paths = state.watch_include_paths.clone();
+            let app_settings = state.app_settings.clone();
+            drop(state_by_label);
+            let watcher = self.start_watcher(
+                project_id,
+                &worktree_dir,
+                watcher_mode,
+                &watch_include_paths,
+                &app_settings,
+            )?;
+
+            let mut state_by_label = self.state.lock();
+            let Some(state) = state_by_label
+                .get_mut(window)
+                .filter(|state| state.project_id == project_id)
+            else {
+                // The window was closed or switched projects meanwhile, so the watcher isn't needed anymore.
+                return Ok(None);
+            };
+            if let Some(resumed) = &state.watcher {
+                // Another caller resumed it meanwhile, and already caught up.
+                let handle = resumed.handle.clone();
+                let shared = Arc::ptr_eq(&handle, &watcher.handle);
+                drop(watcher);
+                if shared {
+                    // Dropping the second use of the same watcher removed the project from it.
+                    handle.add_project(project_id, state.watch_include_paths.clone())?;
+                }
+                return Ok(Some(handle));
+            }
+            let handle = watcher.handle.clone();
+            let subscribed_files: Vec<_> = state.subscribed_files.iter().cloned().collect();
+            state.watcher = Some(watcher);
+            drop(state_by_label);
+
+            tracing::debug!(%project_id, "resumed watcher of inactive project");
+            handle.post(gitbutler_watcher::Action::CalculateVirtualBranches(
+                project_id,
+            ))?;
+            if !subscribed_files.is_empty() {
+                ChangeForFrontend::from(gitbutler_watcher::Change::FilesChanged {
+                    project_id,
+                    paths: subscribed_files,
+                })
+                .send_to_windows(&self.app_handle, &[window.to_owned()])?;
+            }
+            Ok(Some(handle))
+        }
+
+        pub fn post(&self, action: gitbutler_watcher::Action) -> Result<()> {
+            let label = self
+                .state
+                .lock()
+                .iter()
+                .find(|(_, state)| state.project_id == action.project_id())
+                .map(|(label, _)| label.clone());
+            match label {
+                Some(label) => match self.resume_watcher(&label)? {
+                    Some(watcher) => watcher.post(action).context("failed to post event"),
+                    None => Err(anyhow::anyhow!(
+                        "the window of project {wanted} closed before the event could be posted",
+                        wanted = action.project_id(),
+                    )),
+                },
+                None => Err(anyhow::anyhow!(
                     "matching watcher to post event not found, wanted {wanted}",
                     wanted = action.project_id(),
-                ))
+                )),
             }
         }
 
-        /// Flush file-monitor watcher events once the windows regains focus for it to respond instantly
-        /// instead of according to the tick-rate.
-        pub fn flush(&self, window: &WindowLabelRef) -> Result<()> {
-            let state_by_label = self.state.lock();
-            if let Some(state) = state_by_label.get(window) {
-                state.watcher.flush()?;
+        /// Mark the project displayed by `window` as active, typically once the window regains focus.
+        ///
+        /// This restarts its watcher if it was stopped, or flushes file-monitor watcher events for it to
+        /// respond instantly instead of according to the tick-rate.
+        pub fn activate(&self, window: &WindowLabelRef) -> Result<()> {
+            let was_watching = self
+                .state
+                .lock()
+                .get(window)
+                .is_some_and(|state| state.watcher.is_some());
+            if let Some(watcher) = self.resume_watcher(window)? {
+                if was_watching {
+                    watcher.flush()?;
+                }
             }
+            Ok(())
+        }
 
+        /// Like [`Self::activate()`], but for all windows displaying the project with `project_id`.
+        pub fn activate_project(&self, project_id: ProjectId) -> Result<()> {
+            let labels = windows_for_project(&self.state.lock(), project_id);
+            for label in labels {
+                self.activate(&label)?;
+            }
             Ok(())
         }
 
+        /// Stop the watchers of projects that weren't active for longer than `timeout`, to save resources.
+        /// They are restarted once the project is [activated](Self::activate()).
+        pub fn stop_inactive_watchers(&self, timeout: Duration) {
+            let mut state_by_label = self.state.lock();
+            for (label, state) in state_by_label.iter_mut() {
+                // Projects in the background are kept recording, which is the point of them being there.
+                if is_background(label) {
+                    continue;
+                }
+                if state.watcher.is_some() && state.last_active.elapsed() > timeout {
+                    tracing::debug!(project_id = %state.project_id, "stopped watcher of inactive project");
+                    state.watcher = None;
+                }
+            }
+        }
+
+        /// Stop the watchers of all projects, like when the app exits. They are restarted once a project is
+        /// [activated](Self::activate()).
+        pub fn stop_all_watchers(&self) {
+            let mut state_by_label = self.state.lock();
+            for state in state_by_label.values_mut() {
+                state.watcher = None;
+            }
+        }
+
+        /// Limit the watchers of all windows displaying `project_id` to the worktree-relative directories in
+        /// `include_paths`, or have them watch all of the worktree if empty, without restarting them.
+        pub fn set_watch_include_paths(
+            &self,
+            project_id: ProjectId,
+            include_paths: &[PathBuf],
+        ) -> Result<()> {
+            let mut state_by_label = self.state.lock();
+            for state in state_by_label
+                .values_mut()
+                .filter(|state| state.project_id == project_id)
+            {
+                state.watch_include_paths = include_paths.to_vec();
+                if let Some(watcher) = &state.watcher {
+                    watcher.set_include_paths(project_id, include_paths.to_vec())?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Have `window` be informed whenever the file at the worktree-relative `path` of `project_id`
+        /// changes on disk, typically while it's displayed in an editor.
+        ///
+        /// Subscriptions are dropped once the window displays another project.
+        pub fn subscribe_file(
+            &self,
+            window: &WindowLabelRef,
+            project_id: ProjectId,
+            path: PathBuf,
+        ) -> Result<()> {
+            let mut state_by_label = self.state.lock();
+            let state = state_by_label
+                .get_mut(window)
+                .filter(|state| state.project_id == project_id)
+                .with_context(|| {
+                    format!("Window '{window}' doesn't display project {project_id}")
+                })?;
+            state.subscribed_files.insert(path);
+            Ok(())
+        }
+
+        /// Stop informing `window` about changes to the file at the worktree-relative `path`.
+        ///
+        /// It's not an error if there was no such subscription.
+        pub fn unsubscribe_file(&self, window: &WindowLabelRef, path: &Path) {
+            let mut state_by_label = self.state.lock();
+            if let Some(state) = state_by_label.get_mut(window) {
+                state.subscribed_files.remove(path);
+            }
+        }
+
         /// Remove the state associated with `window`, typically upon its destruction.
         pub fn remove(&self, window: &WindowLabelRef) {
             let mut state_by_label = self.state.lock();
             state_by_label.remove(window);
         }
 
+        /// Keep watching the project displayed by `window` in the background, typically upon its destruction, unless
+        /// another window displays it too.
+        ///
+        /// The project is taken over by the next window that [displays it](Self::set_project_to_window()).
+        pub fn move_to_background(&self, window: &WindowLabelRef) {
+            let mut state_by_label = self.state.lock();
+            let Some(mut state) = state_by_label.remove(window) else {
+                return;
+            };
+            if !windows_for_project(&state_by_label, state.project_id).is_empty() {
+                return;
+            }
+            tracing::debug!(project_id = %state.project_id, "recording project in the background");
+            state.subscribed_files.clear();
+            state_by_label.insert(background_label(state.project_id), state);
+        }
+
+        /// Start watching `project` in the background, as if its window was closed, to record its changes before it's
+        /// ever opened. It's taken over by the next window that [displays it](Self::set_project_to_window()).
+        pub fn watch_in_background(
+            &self,
+            project: &projects::Project,
+            app_settings: AppSettingsWithDiskSync,
+        ) -> Result<()> {
+            self.set_project_to_window(&background_label(project.id), project, app_settings)
+        }
+
+        /// Return the ids of the projects that are only watched in the background, as their windows were closed.
+        pub fn background_projects(&self) -> Vec<ProjectId> {
+            let state_by_label = self.state.lock();
+            state_by_label
+                .iter()
+                .filter(|(label, _)| is_background(label))
+                .map(|(_, state)| state.project_id)
+                .collect()
+        }
+
+        /// Stop watching the project with `project_id` and release its lock in all windows that display it,
+        /// typically because it was deleted.
+        pub fn remove_project(&self, project_id: ProjectId) {
+            let mut state_by_label = self.state.lock();
+            state_by_label.retain(|_, state| state.project_id != project_id);
+        }
+
+        /// Return the event throughput of the watchers of all projects that are displayed in a window, once for
+        /// each watcher shared by several projects.
+        pub fn watcher_metrics(&self) -> Vec<gitbutler_watcher::WatcherMetrics> {
+            let state_by_label = self.state.lock();
+            let mut watchers: Vec<_> = state_by_label
+                .values()
+                .filter_map(|state| state.watcher.as_ref())
+                .map(|watcher| &watcher.handle)
+                .collect();
+            watchers.sort_by_key(|handle| Arc::as_ptr(handle));
+            watchers.dedup_by(|a, b| Arc::ptr_eq(a, b));
+            watchers
+                .into_iter()
+                .map(|handle| handle.metrics())
+                .collect()
+        }
+
+        /// Return the event throughput of the watcher of the project with `project_id`, if it's watched.
+        pub fn project_watcher_metrics(
+            &self,
+            project_id: ProjectId,
+        ) -> Option<gitbutler_watcher::WatcherMetrics> {
+            let state_by_label = self.state.lock();
+            state_by_label
+                .values()
+                .filter(|state| state.project_id == project_id)
+                .find_map(|state| state.watcher.as_ref())
+                .map(|watcher| watcher.handle.metrics())
+        }
+
+        /// Return when the watcher of the project with `project_id` saw its file at the worktree-relative `path`
+        /// change last, if that was within the last minute and the project is watched.
+        pub fn last_change(&self, project_id: ProjectId, path: &Path) -> Option<Instant> {
+            let state_by_label = self.state.lock();
+            state_by_label
+                .values()
+                .filter(|state| state.project_id == project_id)
+                .filter_map(|state| state.watcher.as_ref())
+                .find_map(|watcher| watcher.handle.last_change(path))
+        }
+
+        /// Return the label of a window that displays the project with `project_id`, if there is one.
+        pub fn window_for_project(&self, project_id: ProjectId) -> Option<WindowLabel> {
+            let state_by_label = self.state.lock();
+            windows_for_project(&state_by_label, project_id)
+                .into_iter()
+                .find(|label| !is_background(label))
+        }
+
+        /// Send `event` with `payload` only to the windows that display the project with `project_id`, like
+        /// changes of the project are sent.
+        pub fn emit_to_project(
+            &self,
+            project_id: ProjectId,
+            event: &str,
+            payload: impl serde::Serialize + Clone,
+        ) -> Result<()> {
+            let labels = windows_for_project(&self.state.lock(), project_id);
+            self.app_handle
+                .emit_filter(event, payload, |target| match target {
+                    EventTarget::Window { label }
+                    | EventTarget::Webview { label }
+                    | EventTarget::WebviewWindow { label } => labels.contains(label),
+                    _ => false,
+                })
+                .context("emit event")
+        }
+
+        /// Return the changes sent for `project_id` after `since_seq`, see [`ReplayBuffer::since()`].
+        pub fn replay_events(&self, project_id: ProjectId, since_seq: Option<u64>) -> EventReplay {
+            self.replay.lock().since(project_id, since_seq)
+        }
+
         /// Return the list of project ids that are currently open.
         pub fn open_projects(&self) -> Vec<ProjectId> {
             let state_by_label = self.state.lock();
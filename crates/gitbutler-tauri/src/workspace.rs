@@ -1,5 +1,6 @@
 use crate::error::Error;
 use crate::from_json::HexHash;
+use crate::in_blocking_thread;
 use but_hunk_dependency::ui::{
     hunk_dependencies_for_workspace_changes_by_worktree_dir, HunkDependencies,
 };
@@ -7,7 +8,8 @@ use but_settings::AppSettingsWithDiskSync;
 use but_workspace::{commit_engine, StackEntry};
 use gitbutler_command_context::CommandContext;
 use gitbutler_project as projects;
-use gitbutler_project::ProjectId;
+use gitbutler_project::{CoAuthor, ProjectId};
+use gitbutler_repo::commit_message;
 use gitbutler_stack::StackId;
 use tauri::State;
 use tracing::instrument;
@@ -41,14 +43,15 @@ pub fn stack_branches(
 //       Right now this is only a port from the V2 UI, and that data structure was never used directly.
 #[tauri::command(async)]
 #[instrument(skip(projects), err(Debug))]
-pub fn hunk_dependencies_for_workspace_changes(
+pub async fn hunk_dependencies_for_workspace_changes(
     projects: State<'_, projects::Controller>,
     project_id: ProjectId,
 ) -> Result<HunkDependencies, Error> {
     let project = projects.get(project_id)?;
-    let dependencies =
-        hunk_dependencies_for_workspace_changes_by_worktree_dir(&project.path, &project.gb_dir())?;
-    Ok(dependencies)
+    in_blocking_thread(move || {
+        hunk_dependencies_for_workspace_changes_by_worktree_dir(&project.path, &project.gb_dir())
+    })
+    .await
 }
 
 /// Create a new commit with `message` on top of `parent_id` that contains all `changes`.
@@ -57,8 +60,11 @@ pub fn hunk_dependencies_for_workspace_changes(
 /// All `changes` are meant to be relative to the worktree.
 /// Note that submodules *must* be provided as diffspec without hunks, as attempting to generate
 /// hunks would fail.
+/// If `sign_off` is `true`, the author signs off the commit, and each of `co_authors` is credited with a
+/// `Co-authored-by` trailer.
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
+#[allow(clippy::too_many_arguments)]
 pub fn create_commit_from_worktree_changes(
     projects: State<'_, projects::Controller>,
     settings: State<'_, AppSettingsWithDiskSync>,
@@ -67,8 +73,17 @@ pub fn create_commit_from_worktree_changes(
     parent_id: Option<HexHash>,
     worktree_changes: Vec<commit_engine::ui::DiffSpec>,
     message: String,
+    sign_off: Option<bool>,
+    co_authors: Option<Vec<CoAuthor>>,
 ) -> Result<commit_engine::ui::CreateCommitOutcome, Error> {
     let project = projects.get(project_id)?;
+    let message = commit_message::with_trailers(
+        &git2::Repository::open(project.worktree_path()).map_err(anyhow::Error::from)?,
+        &message,
+        sign_off.unwrap_or(false),
+        &co_authors.unwrap_or_default(),
+    )?;
+    commit_message::ensure_valid(&message, project.conventional_commits)?;
     let repo = gix::open(project.worktree_path()).map_err(anyhow::Error::from)?;
     Ok(commit_engine::create_commit_and_update_refs_with_project(
         &repo,
@@ -0,0 +1,120 @@
+//! Write to worktree files without racing with editors that may save them at the same time, like when restoring
+//! files or resolving conflicts.
+//!
+//! Before writing, [`coordinate()`] waits with increasing delays until the watcher saw the file left alone for a
+//! moment, and then holds the exclusive worktree access for the write. Writes fail with [`ChangedSinceRead`] if the
+//! file still changed between reading and writing it, which the windows of the project learn about with a
+//! `project://<id>/write-conflict` event to offer reloading it.
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use gitbutler_fs::ChangedSinceRead;
+use gitbutler_project::{Project, ProjectId};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::WindowState;
+
+/// How long a file must not have changed for writing it to be unlikely to race with an editor.
+const QUIET_PERIOD: Duration = Duration::from_secs(1);
+/// How long to wait each time the file was found to be changing, before giving up.
+const RETRY_DELAYS: &[Duration] = &[
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(400),
+    Duration::from_millis(800),
+];
+
+/// The payload of the `project://<id>/write-conflict` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteConflict {
+    pub project_id: ProjectId,
+    /// The path of the file that wasn't written.
+    pub path: PathBuf,
+}
+
+/// Run `write`, which writes the file at the worktree-relative `path` of `project`, once it isn't changing anymore,
+/// and send a write conflict to the windows of the project if it fails as the file changed during the write.
+pub fn coordinate<T>(
+    app_handle: &AppHandle,
+    project: &Project,
+    path: &Path,
+    write: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    wait_until_quiet(app_handle, project.id, path)?;
+    let mut guard = project.exclusive_worktree_access();
+    let _permission = guard.write_permission();
+    let result = write();
+    if let Some(conflict) = result
+        .as_ref()
+        .err()
+        .and_then(|err| err.downcast_ref::<ChangedSinceRead>())
+    {
+        tracing::warn!(project_id = %project.id, path = %conflict.path.display(), "file changed while writing it");
+        report_conflict(app_handle, project.id, path);
+    }
+    result
+}
+
+/// Tell the windows of the project with `project_id` that the file at the worktree-relative `path` wasn't written as
+/// it changed since it was read.
+pub fn report_conflict(app_handle: &AppHandle, project_id: ProjectId, path: &Path) {
+    let payload = WriteConflict {
+        project_id,
+        path: path.to_owned(),
+    };
+    let sent = match app_handle.try_state::<WindowState>() {
+        Some(windows) => windows.emit_to_project(
+            project_id,
+            &format!("project://{project_id}/write-conflict"),
+            payload,
+        ),
+        None => Ok(()),
+    };
+    if let Err(err) = sent {
+        tracing::warn!(?err, "failed to send write conflict");
+    }
+}
+
+/// Wait until the watcher of the project with `project_id` didn't see the file at `path` change for a while.
+fn wait_until_quiet(app_handle: &AppHandle, project_id: ProjectId, path: &Path) -> Result<()> {
+    let Some(windows) = app_handle.try_state::<WindowState>() else {
+        return Ok(());
+    };
+    let is_changing = || is_changing(windows.last_change(project_id, path), Instant::now());
+    for delay in RETRY_DELAYS {
+        if !is_changing() {
+            return Ok(());
+        }
+        std::thread::sleep(*delay);
+    }
+    if is_changing() {
+        bail!(
+            "'{}' keeps being changed by another program, try again once it was saved",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn is_changing(last_change: Option<Instant>, now: Instant) -> bool {
+    last_change.is_some_and(|at| now.saturating_duration_since(at) < QUIET_PERIOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_changing_within_the_quiet_period() {
+        let now = Instant::now() + QUIET_PERIOD * 2;
+        assert!(!is_changing(None, now));
+        assert!(is_changing(Some(now), now));
+        assert!(is_changing(Some(now - QUIET_PERIOD / 2), now));
+        assert!(!is_changing(Some(now - QUIET_PERIOD), now));
+    }
+}
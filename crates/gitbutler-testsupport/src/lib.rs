@@ -15,6 +15,8 @@ pub use suite::*;
 
 pub mod testing_repository;
 
+pub mod timeline;
+
 pub mod paths {
     use tempfile::TempDir;
 
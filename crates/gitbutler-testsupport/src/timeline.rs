@@ -0,0 +1,181 @@
+//! Script what happens in the worktree of a throwaway project and when, and record it as if the watcher noticed it
+//! at these times, so sessions, debouncing and replaying deltas can be tested without waiting for time to pass.
+//!
+//! Only the clock is scripted. The watcher takes its time from a [`Clock`] too, see
+//! `gitbutler_watcher::Handler::with_clock()`, but there is no virtual filesystem: deltas, checkpoints and snapshots
+//! read the worktree through git, which only sees the real one, so edits are written to the worktree of the project.
+//!
+//! ```ignore
+//! let played = Timeline::starting_at(start)
+//!     .edit("file.txt", "first\n")
+//!     .snapshot()
+//!     .wait(Duration::from_secs(60))
+//!     .edit("file.txt", "second\n")
+//!     .play(project)?;
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use gitbutler_oplog::{
+    deltas::{self, Delta},
+    entry::{OperationKind, SnapshotDetails},
+    heartbeat, OplogExt,
+};
+use gitbutler_project::{machine_changes, Project};
+use gitbutler_time::clock::{Clock, ManualClock};
+
+enum Step {
+    /// Write `content` to the file at `path`, or delete it if `None`.
+    Write {
+        path: PathBuf,
+        content: Option<Vec<u8>>,
+    },
+    Wait(Duration),
+    Snapshot,
+    Heartbeat(PathBuf),
+}
+
+/// A script of edits, snapshots and heartbeats along a [`ManualClock`], with nothing happening in between.
+pub struct Timeline {
+    clock: ManualClock,
+    steps: Vec<Step>,
+}
+
+/// What [`Timeline::play()`] recorded, in the order it happened.
+#[derive(Debug, Default)]
+pub struct PlayedTimeline {
+    /// The deltas as recorded, one per edit.
+    pub deltas: Vec<Delta>,
+    /// When each snapshot was created, in seconds since the Unix epoch, along with its id.
+    pub snapshots: Vec<(i64, git2::Oid)>,
+    /// When heartbeats were recorded, in seconds since the Unix epoch, leaving out those that were too close to
+    /// the previous one.
+    pub heartbeats: Vec<i64>,
+}
+
+impl Timeline {
+    /// Start a timeline at `seconds` since the Unix epoch.
+    pub fn starting_at(seconds: i64) -> Self {
+        Timeline {
+            clock: ManualClock::at_seconds(seconds),
+            steps: Vec::new(),
+        }
+    }
+
+    /// The clock the timeline is played along, to share with what else should see the same time.
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Write `content` to the worktree-relative `path`, creating its directories as needed.
+    pub fn edit(mut self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) -> Self {
+        self.steps.push(Step::Write {
+            path: path.as_ref().to_owned(),
+            content: Some(content.into()),
+        });
+        self
+    }
+
+    /// Delete the file at the worktree-relative `path`.
+    pub fn delete(mut self, path: impl AsRef<Path>) -> Self {
+        self.steps.push(Step::Write {
+            path: path.as_ref().to_owned(),
+            content: None,
+        });
+        self
+    }
+
+    /// Let `duration` pass.
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Wait(duration));
+        self
+    }
+
+    /// Create a snapshot of the worktree as the watcher does after files changed.
+    pub fn snapshot(mut self) -> Self {
+        self.steps.push(Step::Snapshot);
+        self
+    }
+
+    /// Send a heartbeat as an editor does while the file at the worktree-relative `path` is focused.
+    pub fn heartbeat(mut self, path: impl AsRef<Path>) -> Self {
+        self.steps.push(Step::Heartbeat(path.as_ref().to_owned()));
+        self
+    }
+
+    /// Perform all steps in the worktree of `project`, recording a delta for each edit as the watcher does, and
+    /// return what was recorded. The clock is left at the end of the timeline.
+    ///
+    /// Deltas are recorded without branch, like those made while `HEAD` is detached.
+    pub fn play(&self, project: &Project) -> Result<PlayedTimeline> {
+        let mut played = PlayedTimeline::default();
+        for step in &self.steps {
+            let at = self.clock.now_seconds();
+            match step {
+                Step::Write { path, content } => {
+                    let worktree_path = project.path.join(path);
+                    let changed = match content {
+                        Some(content) => {
+                            if let Some(parent) = worktree_path.parent() {
+                                std::fs::create_dir_all(parent)?;
+                            }
+                            std::fs::write(&worktree_path, content)
+                        }
+                        None => std::fs::remove_file(&worktree_path),
+                    };
+                    changed.with_context(|| {
+                        format!("failed to change '{}'", worktree_path.display())
+                    })?;
+                    played
+                        .deltas
+                        .push(record_delta(project, at, &[path], None)?);
+                }
+                Step::Wait(duration) => self.clock.advance(*duration),
+                Step::Snapshot => {
+                    let mut guard = project.exclusive_worktree_access();
+                    let snapshot_id = project.create_snapshot_at(
+                        SnapshotDetails::new(OperationKind::FileChanges),
+                        at,
+                        guard.write_permission(),
+                    )?;
+                    played.snapshots.push((at, snapshot_id));
+                }
+                Step::Heartbeat(path) => {
+                    if heartbeat::record_heartbeat(project, path, at)? {
+                        played.heartbeats.push(at);
+                    }
+                }
+            }
+        }
+        Ok(played)
+    }
+}
+
+/// Record a delta for the worktree-relative `paths` of `project` at `at` seconds since the Unix epoch, as the
+/// watcher does after they changed while `branch` was checked out.
+pub fn record_delta(
+    project: &Project,
+    at: i64,
+    paths: &[impl AsRef<Path>],
+    branch: Option<&str>,
+) -> Result<Delta> {
+    let paths: Vec<_> = paths.iter().map(|path| path.as_ref().to_owned()).collect();
+    let classification =
+        machine_changes::classify(project.id, &project.change_classification_rules, &paths);
+    deltas::record_delta(
+        project,
+        Delta {
+            at,
+            paths,
+            classification,
+            branch: branch.map(ToOwned::to_owned),
+            checkpoint: None,
+            contents: Vec::new(),
+            bulk: false,
+            renames: Vec::new(),
+        },
+    )
+}
@@ -0,0 +1,66 @@
+//! Where the current time comes from, so what depends on it can be driven by a [`ManualClock`] in tests instead of
+//! waiting for time to pass.
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> SystemTime;
+
+    /// Return the current time in seconds since the Unix epoch, or `0` if it's before the epoch.
+    fn now_seconds(&self) -> i64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64)
+    }
+}
+
+/// The time of the system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when it's told to. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock(Arc<Mutex<SystemTime>>);
+
+impl ManualClock {
+    /// Create a clock at `seconds` since the Unix epoch.
+    ///
+    /// # Panics
+    /// Panics if `seconds` is negative.
+    pub fn at_seconds(seconds: i64) -> Self {
+        let clock = ManualClock(Arc::new(Mutex::new(UNIX_EPOCH)));
+        clock.set_seconds(seconds);
+        clock
+    }
+
+    /// Set the time to `seconds` since the Unix epoch.
+    ///
+    /// # Panics
+    /// Panics if `seconds` is negative.
+    pub fn set_seconds(&self, seconds: i64) {
+        let seconds = u64::try_from(seconds).expect("times before the Unix epoch aren't supported");
+        *self.0.lock().unwrap_or_else(|err| err.into_inner()) =
+            UNIX_EPOCH + Duration::from_secs(seconds);
+    }
+
+    /// Move the time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner()) += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
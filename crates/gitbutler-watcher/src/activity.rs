@@ -0,0 +1,64 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How much is going on in the worktree of a project, as sent with [`Change::ActivityPulse`](crate::Change::ActivityPulse).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ActivityPulse {
+    /// The amount of file changes within the last minute.
+    pub edits_per_minute: usize,
+    /// The amount of distinct files that changed within the last minute.
+    pub active_files: usize,
+}
+
+impl ActivityPulse {
+    /// Return `true` if nothing changed within the last minute.
+    pub fn is_idle(&self) -> bool {
+        self.edits_per_minute == 0
+    }
+}
+
+/// Remembers when worktree files changed to compute an [`ActivityPulse`] over a sliding window of a minute.
+#[derive(Debug, Default)]
+pub(crate) struct ActivityTracker {
+    edits: VecDeque<(Instant, PathBuf)>,
+}
+
+impl ActivityTracker {
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    /// Record that all `paths` changed at `now`.
+    pub fn record(&mut self, paths: &[PathBuf], now: Instant) {
+        self.edits
+            .extend(paths.iter().map(|path| (now, path.clone())));
+    }
+
+    /// Return when the file at the worktree-relative `path` changed last within the window.
+    pub fn last_change(&self, path: &Path) -> Option<Instant> {
+        self.edits
+            .iter()
+            .rev()
+            .find(|(_, changed)| changed == path)
+            .map(|(at, _)| *at)
+    }
+
+    /// Forget edits that are older than the window and summarize the remaining ones.
+    pub fn pulse(&mut self, now: Instant) -> ActivityPulse {
+        while self
+            .edits
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > Self::WINDOW)
+        {
+            self.edits.pop_front();
+        }
+        ActivityPulse {
+            edits_per_minute: self.edits.len(),
+            active_files: self
+                .edits
+                .iter()
+                .map(|(_, path)| path)
+                .collect::<HashSet<_>>()
+                .len(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use gitbutler_project::ProjectId;
+
+/// Decides when to back up the oplog of projects to their history backup remote, which happens once the oplog
+/// stopped changing for a while instead of with each new snapshot.
+#[derive(Debug, Default)]
+pub(crate) struct BackupTimer {
+    /// When the backup of each project is due, and when its oplog first changed since the last backup.
+    pending: HashMap<ProjectId, (Instant, Instant)>,
+}
+
+impl BackupTimer {
+    /// How long the oplog has to stay unchanged before it's backed up.
+    const QUIET_PERIOD: Duration = Duration::from_secs(60);
+    /// How long the backup is postponed at most while the oplog keeps changing.
+    const MAX_DELAY: Duration = Duration::from_secs(10 * 60);
+
+    /// Record that the oplog of `project_id` changed at `now`, which postpones its backup.
+    pub fn record(&mut self, project_id: ProjectId, now: Instant) {
+        let (due, first_change) = self.pending.entry(project_id).or_insert((now, now));
+        *due = (now + Self::QUIET_PERIOD).min(*first_change + Self::MAX_DELAY);
+    }
+
+    /// Return when the next backup is due, if there is one.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(due, _)| *due).min()
+    }
+
+    /// Return the projects whose backup is due at `now`, and forget about them.
+    pub fn take_due(&mut self, now: Instant) -> Vec<ProjectId> {
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, (due, _))| *due <= now)
+            .map(|(project_id, _)| *project_id)
+            .collect();
+        for project_id in &due {
+            self.pending.remove(project_id);
+        }
+        due
+    }
+}
+
+/// Wait until `due`, or forever if it's `None`.
+pub(crate) async fn sleep_until(due: Option<Instant>) {
+    match due {
+        Some(due) => tokio::time::sleep_until(due.into()).await,
+        None => std::future::pending().await,
+    }
+}
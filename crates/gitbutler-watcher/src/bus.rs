@@ -0,0 +1,229 @@
+//! A bus for typed events about what the watchers observed, for the frontend and backend consumers alike.
+//!
+//! Events are wrapped in an [`EventEnvelope`] with a schema version, which is increased whenever the serialized
+//! form of an event changes in a way that isn't backwards compatible.
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gitbutler_project::{machine_changes::Classification, ProjectId};
+use serde::{Deserialize, Serialize};
+
+/// The version of the serialized form of [`EventEnvelope`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+static BUS: LazyLock<EventBus> = LazyLock::new(EventBus::default);
+
+/// Return the bus all watchers publish their events to.
+pub fn event_bus() -> &'static EventBus {
+    &BUS
+}
+
+/// Something a watcher observed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum Event {
+    /// A watcher started for a project, and anything that changes from now on is recorded.
+    #[serde(rename_all = "camelCase")]
+    SessionStarted { project_id: ProjectId },
+    /// A watcher stopped after handling all changes it noticed, and nothing is recorded from now on.
+    #[serde(rename_all = "camelCase")]
+    SessionEnded {
+        project_id: ProjectId,
+        reason: SessionEndReason,
+    },
+    /// Files in the worktree changed on disk.
+    #[serde(rename_all = "camelCase")]
+    DeltaRecorded {
+        project_id: ProjectId,
+        /// Worktree-relative paths of the changed files.
+        paths: Vec<PathBuf>,
+        /// `true` if the changes were made by a machine, as per `classification`.
+        machine_generated: bool,
+        /// `true` if the changes follow a git operation outside of GitButler, like a checkout in a terminal.
+        git_operation: bool,
+        /// Whether the changes were made by a human or a machine, and why.
+        classification: Classification,
+    },
+    /// Git changed the repository.
+    #[serde(rename_all = "camelCase")]
+    GitOperation {
+        project_id: ProjectId,
+        operation: GitOperation,
+    },
+    /// An event couldn't be handled.
+    #[serde(rename_all = "camelCase")]
+    WatcherError {
+        project_id: ProjectId,
+        message: String,
+    },
+    /// The system limit of file watches was reached, and the worktree is polled for changes instead, see
+    /// [`WatchLimitExceeded`](crate::WatchLimitExceeded).
+    #[serde(rename_all = "camelCase")]
+    WatchLimitExceeded {
+        project_id: ProjectId,
+        /// The current limit, if it's known.
+        limit: Option<u64>,
+        /// A command raising the limit.
+        suggestion: String,
+    },
+}
+
+/// Why a watcher stopped, as part of [`Event::SessionEnded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionEndReason {
+    /// The app is exiting.
+    AppClosed,
+    /// The watcher isn't needed anymore, like when the project is closed or was inactive for a while.
+    Stopped,
+}
+
+/// What kind of change git made, as part of [`Event::GitOperation`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum GitOperation {
+    /// Refs were fetched from a remote.
+    Fetch,
+    /// `HEAD` now points to `head`, like after a checkout.
+    Head { head: String },
+    /// The reflog of `HEAD` changed, like after a commit or a rebase.
+    Activity,
+}
+
+/// The kind of an [`Event`], to [filter](EventFilter) by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    SessionStarted,
+    SessionEnded,
+    DeltaRecorded,
+    GitOperation,
+    WatcherError,
+    WatchLimitExceeded,
+}
+
+impl Event {
+    /// Return the kind of this event.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::SessionStarted { .. } => EventKind::SessionStarted,
+            Event::SessionEnded { .. } => EventKind::SessionEnded,
+            Event::DeltaRecorded { .. } => EventKind::DeltaRecorded,
+            Event::GitOperation { .. } => EventKind::GitOperation,
+            Event::WatcherError { .. } => EventKind::WatcherError,
+            Event::WatchLimitExceeded { .. } => EventKind::WatchLimitExceeded,
+        }
+    }
+
+    /// Return the id of the project this event belongs to.
+    pub fn project_id(&self) -> ProjectId {
+        match self {
+            Event::SessionStarted { project_id }
+            | Event::SessionEnded { project_id, .. }
+            | Event::DeltaRecorded { project_id, .. }
+            | Event::GitOperation { project_id, .. }
+            | Event::WatcherError { project_id, .. }
+            | Event::WatchLimitExceeded { project_id, .. } => *project_id,
+        }
+    }
+}
+
+/// An [`Event`] as it is delivered to subscribers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventEnvelope {
+    /// The [version of the schema](SCHEMA_VERSION).
+    pub version: u32,
+    /// A number that increases with every published event, so consumers can detect gaps.
+    pub seq: u64,
+    /// When the event was published, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub event: Event,
+}
+
+/// Which events a subscriber wants to receive. Fields that are `None` match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilter {
+    pub project_id: Option<ProjectId>,
+    pub kinds: Option<Vec<EventKind>>,
+}
+
+impl EventFilter {
+    /// Return `true` if `event` should be delivered.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.project_id.is_none_or(|id| id == event.project_id())
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&event.kind()))
+    }
+}
+
+/// Identifies a subscription, to [end it](EventBus::unsubscribe()).
+pub type SubscriptionId = u64;
+
+/// A callback that receives the id of its subscription along with the matching events, and returns `false`
+/// to end its subscription.
+type Subscriber = Box<dyn Fn(SubscriptionId, &EventEnvelope) -> bool + Send>;
+
+/// Delivers published [events](Event) to everyone who subscribed to them.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<(SubscriptionId, EventFilter, Subscriber)>>,
+    next_id: AtomicU64,
+    seq: AtomicU64,
+}
+
+impl EventBus {
+    /// Call `subscriber` with the subscription id and every published event that matches `filter`, until it
+    /// returns `false` or the subscription is [ended](Self::unsubscribe()).
+    ///
+    /// Note that it's called while publishing, so it should return quickly and must not publish itself.
+    pub fn subscribe(
+        &self,
+        filter: EventFilter,
+        subscriber: impl Fn(SubscriptionId, &EventEnvelope) -> bool + Send + 'static,
+    ) -> SubscriptionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().push((id, filter, Box::new(subscriber)));
+        id
+    }
+
+    /// End the subscription with `id`, returning `true` if it existed.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.lock();
+        let before = subscribers.len();
+        subscribers.retain(|(subscription_id, ..)| *subscription_id != id);
+        subscribers.len() != before
+    }
+
+    /// Deliver `event` to all subscribers whose filter matches it.
+    pub fn publish(&self, event: Event) {
+        self.publish_at(event, SystemTime::now());
+    }
+
+    /// Like [`publish()`](Self::publish()), but as if `event` happened at `at`, like when the clock is driven by
+    /// tests.
+    pub fn publish_at(&self, event: Event, at: SystemTime) {
+        let envelope = EventEnvelope {
+            version: SCHEMA_VERSION,
+            seq: self.seq.fetch_add(1, Ordering::Relaxed),
+            timestamp_ms: at.duration_since(UNIX_EPOCH).map_or(0, |time| {
+                u64::try_from(time.as_millis()).unwrap_or(u64::MAX)
+            }),
+            event,
+        };
+        self.lock().retain(|(id, filter, subscriber)| {
+            !filter.matches(&envelope.event) || subscriber(*id, &envelope)
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(SubscriptionId, EventFilter, Subscriber)>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
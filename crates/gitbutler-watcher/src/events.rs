@@ -1,9 +1,13 @@
 use std::{fmt::Display, path::PathBuf};
 
-use gitbutler_branch_actions::{RemoteBranchFile, VirtualBranches};
+use gitbutler_branch_actions::{
+    upstream_conflicts::UpstreamConflicts, RemoteBranchFile, VirtualBranches,
+};
 use gitbutler_operating_modes::OperatingMode;
 use gitbutler_project::ProjectId;
 
+use crate::{ActivityPulse, HeadState};
+
 /// An event for internal use, as merge between [super::file_monitor::Event] and [Action].
 #[derive(Debug)]
 pub(super) enum InternalEvent {
@@ -13,8 +17,14 @@ pub(super) enum InternalEvent {
     // From file monitor
     GitFilesChange(ProjectId, Vec<PathBuf>),
     ProjectFilesChange(ProjectId, Vec<PathBuf>),
+    /// Worktree-relative `(from, to)` paths of files that were moved within the worktree.
+    ProjectFilesRenamed(ProjectId, Vec<(PathBuf, PathBuf)>),
     // Triggered on change in the `.git/gitbutler` directory
     GitButlerOplogChange(ProjectId),
+    /// Triggered once the watcher starts, to record what changed while nobody was watching.
+    ReconcileOfflineChanges(ProjectId),
+    /// Triggered once the oplog stopped changing for a while, to back it up to the history backup remote.
+    BackupHistory(ProjectId),
 }
 
 /// This type captures all operations that can be fed into a watcher that runs in the background.
@@ -65,7 +75,21 @@ impl Display for InternalEvent {
                     comma_separated_paths(paths)
                 )
             }
+            InternalEvent::ProjectFilesRenamed(project_id, renames) => {
+                write!(
+                    f,
+                    "ProjectFilesRenamed({}, {})",
+                    project_id,
+                    comma_separated_paths(
+                        &renames.iter().map(|(_, to)| to.clone()).collect::<Vec<_>>()
+                    )
+                )
+            }
             InternalEvent::CalculateVirtualBranches(pid) => write!(f, "VirtualBranch({})", pid),
+            InternalEvent::ReconcileOfflineChanges(pid) => {
+                write!(f, "ReconcileOfflineChanges({})", pid)
+            }
+            InternalEvent::BackupHistory(pid) => write!(f, "BackupHistory({})", pid),
         }
     }
 }
@@ -97,6 +121,16 @@ pub enum Change {
         operating_mode: OperatingMode,
     },
     GitActivity(ProjectId),
+    /// The index changed, like when files were staged in a terminal, so what is known about staged changes is stale.
+    GitIndexChanged(ProjectId),
+    /// `HEAD` was changed by a git operation outside of GitButler, like a checkout, pull or rebase in a terminal.
+    GitHeadChanged {
+        project_id: ProjectId,
+        old: HeadState,
+        new: HeadState,
+        /// The message of the reflog entry of the operation, like `checkout: moving from main to feature`.
+        message: Option<String>,
+    },
     VirtualBranches {
         project_id: ProjectId,
         virtual_branches: VirtualBranches,
@@ -109,4 +143,32 @@ pub enum Change {
         project_id: ProjectId,
         changes: but_core::WorktreeChanges,
     },
+    /// Files were moved within the worktree, so anything referring to them by path can follow along.
+    FilesRenamed {
+        project_id: ProjectId,
+        /// Worktree-relative `(from, to)` paths.
+        renames: Vec<(PathBuf, PathBuf)>,
+    },
+    /// Files in the worktree changed on disk.
+    ///
+    /// Meant to be forwarded only to windows that subscribed to one of the `paths`, like an editor
+    /// which displays the file.
+    FilesChanged {
+        project_id: ProjectId,
+        /// Worktree-relative paths of the changed files.
+        paths: Vec<PathBuf>,
+    },
+    /// A periodic summary of how actively the worktree is changing, sent while there is activity
+    /// and once more when it stops.
+    ActivityPulse {
+        project_id: ProjectId,
+        pulse: ActivityPulse,
+    },
+    /// After a fetch, the changes in the workspace that would conflict with new upstream commits.
+    UpstreamConflicts {
+        project_id: ProjectId,
+        conflicts: UpstreamConflicts,
+    },
+    /// The watcher of the project caught up with what changed while nobody was watching, so its state is current.
+    ProjectReady(ProjectId),
 }
@@ -1,14 +1,20 @@
-use std::{collections::HashSet, path::Path, time::Duration};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
-use gitbutler_notify_debouncer::{new_debouncer, Debouncer, NoCache};
+use gitbutler_notify_debouncer::{
+    new_debouncer_opt, DebounceEventHandler, DebounceEventResult, Debouncer, NoCache,
+};
 use gitbutler_oplog::OPLOG_FILE_NAME;
-use gitbutler_project::ProjectId;
-use notify::{RecommendedWatcher, Watcher};
-use tokio::task;
+use gitbutler_project::{ProjectId, WatcherMode};
+use notify::{PollWatcher, RecommendedWatcher, Watcher};
 use tracing::Level;
 
-use crate::events::InternalEvent;
+use crate::{bus, events::InternalEvent, paths::PathNormalizer};
 
 /// We will collect notifications for up to this amount of time at a very
 /// maximum before releasing them. This duration will be hit if e.g. a build
@@ -17,7 +23,7 @@ use crate::events::InternalEvent;
 const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(60);
 
 // The internal rate at which the debouncer will update its state.
-const TICK_RATE: Duration = Duration::from_millis(250);
+pub(crate) const TICK_RATE: Duration = Duration::from_millis(250);
 
 // The number of TICK_RATE intervals required of "dead air" (i.e. no new events
 // arriving) before we will automatically flush pending events. This means that
@@ -25,6 +31,54 @@ const TICK_RATE: Duration = Duration::from_millis(250);
 // the pending events, even if DEBOUNCE_TIMEOUT hasn't expired yet
 const FLUSH_AFTER_EMPTY: u32 = 3;
 
+/// How often all files are scanned for changes in [`WatcherMode::Polling`]. Each scan reads the metadata of
+/// every file in the worktree, so it shouldn't be much shorter, and is longer for larger worktrees, see
+/// [`poll_interval()`].
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The poll interval grows by [`POLL_INTERVAL`] for each this many files in the worktree.
+const FILES_PER_POLL_INTERVAL: usize = 20_000;
+/// The longest time between scans, no matter how large the worktree is.
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The file with the limit of inotify watches per user on Linux.
+const MAX_USER_WATCHES_FILE: &str = "/proc/sys/fs/inotify/max_user_watches";
+
+/// The worktree couldn't be watched as the system limit of watches was reached, which on Linux is
+/// `fs.inotify.max_user_watches`, shared by all applications of the user.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{}", self.message())]
+pub struct WatchLimitExceeded {
+    /// The current limit, if it's known.
+    pub limit: Option<u64>,
+    /// A command raising the limit.
+    pub suggestion: String,
+}
+
+impl WatchLimitExceeded {
+    /// Create an instance with the limit as currently configured.
+    fn current() -> Self {
+        let limit = std::fs::read_to_string(MAX_USER_WATCHES_FILE)
+            .ok()
+            .and_then(|limit| limit.trim().parse::<u64>().ok());
+        let raised = limit.map_or(524_288, |limit| (limit * 2).max(524_288));
+        WatchLimitExceeded {
+            limit,
+            suggestion: format!("sudo sysctl fs.inotify.max_user_watches={raised}"),
+        }
+    }
+
+    fn message(&self) -> String {
+        let limit = match self.limit {
+            Some(limit) => format!("limit of {limit} file watches"),
+            None => "limit of file watches".to_owned(),
+        };
+        format!(
+            "The {limit} was reached, and changes are polled for instead. Raise it with '{}'",
+            self.suggestion
+        )
+    }
+}
+
 /// This error is required only because `anyhow::Error` isn't implementing `std::error::Error`, and [`spawn()`]
 /// needs to wrap it into a `backoff::Error` which also has to implement the `Error` trait.
 #[derive(Debug, thiserror::Error)]
@@ -34,88 +88,106 @@ struct RunError {
     source: anyhow::Error,
 }
 
+/// The worktree-relative directories to watch, shared with the event handler, with all of the worktree being
+/// watched if empty.
+type IncludePaths = Arc<RwLock<Vec<PathBuf>>>;
+
+/// The running watcher of a project, which stops when dropped.
+pub(crate) struct Monitor {
+    debouncer: AnyDebouncer,
+    worktree_path: PathBuf,
+    git_dir: PathBuf,
+    include_paths: IncludePaths,
+    /// The directories that are currently watched recursively.
+    watched: Vec<PathBuf>,
+}
+
+enum AnyDebouncer {
+    Native(Debouncer<RecommendedWatcher, NoCache>),
+    Polling(Debouncer<PollWatcher, NoCache>),
+}
+
+impl Monitor {
+    /// Emit all pending events on the next tick of the debouncer.
+    pub(crate) fn flush_nonblocking(&self) {
+        match &self.debouncer {
+            AnyDebouncer::Native(debouncer) => debouncer.flush_nonblocking(),
+            AnyDebouncer::Polling(debouncer) => debouncer.flush_nonblocking(),
+        }
+    }
+
+    /// Only watch the worktree-relative directories in `include_paths` from now on, or all of the worktree if
+    /// empty, without restarting the watcher.
+    pub(crate) fn set_include_paths(&mut self, include_paths: Vec<PathBuf>) -> Result<()> {
+        let roots = watch_roots(&self.worktree_path, &self.git_dir, &include_paths);
+        *self
+            .include_paths
+            .write()
+            .unwrap_or_else(|err| err.into_inner()) = include_paths;
+        let watcher: &mut dyn Watcher = match &mut self.debouncer {
+            AnyDebouncer::Native(debouncer) => debouncer.watcher(),
+            AnyDebouncer::Polling(debouncer) => debouncer.watcher(),
+        };
+        for root in self.watched.iter().filter(|root| !roots.contains(root)) {
+            if let Err(err) = watcher.unwatch(root) {
+                tracing::warn!(?err, root = %root.display(), "failed to stop watching directory");
+            }
+        }
+        for root in roots.iter().filter(|root| !self.watched.contains(root)) {
+            watcher
+                .watch(root, notify::RecursiveMode::Recursive)
+                .with_context(|| format!("failed to watch '{}'", root.display()))?;
+        }
+        self.watched = roots;
+        Ok(())
+    }
+}
+
 /// Listen to interesting filesystem events of files in `path` that are not `.gitignore`d,
 /// turn them into [`Events`](Event) which classifies it, and associates it with `project_id`.
 /// These are sent through the passed `out` channel, to indicate either **Git** repository changes
 /// or **ProjectWorktree** changes
 ///
-/// ### Why is this not an iterator?
+/// ### Threads
+///
+/// Events are classified on the thread of the debouncer, which calls the handler once the events
+/// settled, so each watched project only needs the threads of its watcher and debouncer.
+/// The state that is kept between invocations, like the location of the git directory, is owned by the handler.
 ///
-/// The internal `notify_rx` could be an iterator, which performs all transformations and returns them as item.
-/// However, due to closures being continuously created each time events come in, nested closures need to own
-/// their resources which means they are `Clone` or `Copy`. This isn't the case for `git::Repository`.
-/// Even though `gix::Repository` is `Clone`, an efficient implementation of `is_path_ignored()` requires more state
-/// that ideally is kept between invocations. For that reason, the current channel-based 'worker' architecture
-/// is chosen to allow all this state to live on the stack.
+/// With [`WatcherMode::Polling`], the files are scanned periodically instead, which also produces coarser events.
 ///
-/// Additionally, a channel plays better with how events are handled downstream.
-pub fn spawn(
+/// If `include_paths` isn't empty, only the worktree-relative directories in it are watched, along with the git
+/// repository, which keeps the cost low in large monorepos. It can be changed later with
+/// [`Monitor::set_include_paths()`].
+pub(crate) fn spawn(
     project_id: ProjectId,
     worktree_path: &std::path::Path,
+    mode: WatcherMode,
+    include_paths: Vec<PathBuf>,
     out: tokio::sync::mpsc::UnboundedSender<InternalEvent>,
-) -> Result<Debouncer<RecommendedWatcher, NoCache>> {
-    let (notify_tx, notify_rx) = std::sync::mpsc::channel();
-    let mut debouncer = new_debouncer(
-        DEBOUNCE_TIMEOUT,
-        Some(TICK_RATE),
-        Some(FLUSH_AFTER_EMPTY),
-        notify_tx,
-    )
-    .context("failed to create debouncer")?;
-
-    let policy = backoff::ExponentialBackoffBuilder::new()
-        .with_max_elapsed_time(Some(std::time::Duration::from_secs(30)))
-        .build();
-
-    let git_dir = gix::open_opts(worktree_path, gix::open::Options::isolated())
-        .context(format!(
-            "failed to open project repository to obtain git-dir: {}",
-            worktree_path.display()
-        ))?
-        .path()
-        .to_owned();
-    let extra_git_dir_to_watch = {
-        let mut enclosing_worktree_dir = git_dir.clone();
-        enclosing_worktree_dir.pop();
-        if enclosing_worktree_dir != worktree_path {
-            Some(git_dir.as_path())
-        } else {
-            None
-        }
-    };
+) -> Result<Monitor> {
+    let repo = gix::open_opts(worktree_path, gix::open::Options::isolated()).context(format!(
+        "failed to open project repository to obtain git-dir: {}",
+        worktree_path.display()
+    ))?;
+    let git_dir = repo.path().to_owned();
+    let normalizer = PathNormalizer::new(&repo, worktree_path);
+    let file_count = repo
+        .index_or_empty()
+        .map(|index| index.entries().len())
+        .unwrap_or_default();
+    drop(repo);
+    let roots = watch_roots(worktree_path, &git_dir, &include_paths);
+    let include_paths: IncludePaths = Arc::new(RwLock::new(include_paths));
 
-    // Start the watcher, but retry if there are transient errors.
-    backoff::retry(policy, || {
-        debouncer
-            .watcher()
-            .watch(worktree_path, notify::RecursiveMode::Recursive)
-            .and_then(|()| {
-                if let Some(git_dir) = extra_git_dir_to_watch {
-                    debouncer
-                        .watcher()
-                        .watch(git_dir, notify::RecursiveMode::Recursive)
-                } else {
-                    Ok(())
-                }
-            })
-            .map_err(|err| match err.kind {
-                notify::ErrorKind::PathNotFound => backoff::Error::permanent(RunError::from(
-                    anyhow!("{} not found", worktree_path.display()),
-                )),
-                notify::ErrorKind::Io(_) | notify::ErrorKind::InvalidConfig(_) => {
-                    backoff::Error::permanent(RunError::from(anyhow::Error::from(err)))
-                }
-                _ => backoff::Error::transient(RunError::from(anyhow::Error::from(err))),
-            })
-    })
-    .context("failed to start watcher")?;
-
-    let worktree_path = worktree_path.to_owned();
-    task::spawn_blocking(move || {
-        let _runtime = tracing::span!(Level::INFO, "file monitor", %project_id ).entered();
-        tracing::debug!(%project_id, "file watcher started");
-
-        'outer: for result in notify_rx {
+    let handle_events = |mode: WatcherMode| {
+        let git_dir = git_dir.clone();
+        let worktree_path = worktree_path.to_owned();
+        let include_paths = include_paths.clone();
+        let normalizer = normalizer.clone();
+        let out = out.clone();
+        move |result: DebounceEventResult| {
+            let _runtime = tracing::span!(Level::INFO, "file monitor", %project_id).entered();
             let stats = tracing::span!(
                 Level::INFO,
                 "handle debounced events",
@@ -135,21 +207,39 @@ pub fn spawn(
                 }
                 Ok(events) => {
                     let num_events = events.len();
+                    let mut renames: Vec<_> = events
+                        .iter()
+                        .filter_map(|event| worktree_rename(&event.event, &git_dir, &normalizer))
+                        .collect();
                     let mut classified_file_paths: Vec<_> = events
                         .into_iter()
-                        .filter(|event| is_interesting_kind(event.kind))
+                        .filter(|event| is_interesting_kind(event.kind, mode))
                         .flat_map(|event| event.event.paths)
                         .map(|file| {
+                            let file = normalizer.normalize(file);
                             let kind = classify_file(&git_dir, &file);
                             (file, kind)
                         })
                         .collect();
+                    {
+                        let include_paths =
+                            include_paths.read().unwrap_or_else(|err| err.into_inner());
+                        for (file_path, kind) in classified_file_paths.iter_mut() {
+                            if *kind == FileKind::Project
+                                && !is_included(&worktree_path, &include_paths, file_path)
+                            {
+                                *kind = FileKind::ProjectIgnored;
+                            }
+                        }
+                    }
+                    let mut index_casing = None;
                     if classified_file_paths
                         .iter()
                         .any(|(_, kind)| *kind == FileKind::Project)
                     {
                         if let Ok(repo) = gix::open(&worktree_path) {
                             if let Ok(index) = repo.index_or_empty() {
+                                index_casing = normalizer.index_casing(&index);
                                 if let Ok(mut excludes) = repo.excludes(
                                     &index,
                                     None,
@@ -172,6 +262,11 @@ pub fn spawn(
                             }
                         }
                     }
+                    renames.retain(|(_, to)| {
+                        !classified_file_paths.iter().any(|(file_path, kind)| {
+                            *kind == FileKind::ProjectIgnored && file_path == to
+                        })
+                    });
                     let mut oplog_changed = false;
                     let (mut stripped_git_paths, mut worktree_relative_paths) =
                         (HashSet::new(), HashSet::new());
@@ -189,11 +284,24 @@ pub fn spawn(
                                     if relative_file_path.as_os_str().is_empty() {
                                         continue;
                                     }
+                                    // Files with names reserved for devices can't be read on Windows, like
+                                    // `nul` written by tools expecting a Unix shell.
+                                    if cfg!(windows)
+                                        && gitbutler_fs::paths::reserved_component(
+                                            relative_file_path,
+                                        )
+                                        .is_some()
+                                    {
+                                        ignored += 1;
+                                        continue;
+                                    }
                                     if let Ok(stripped) = relative_file_path.strip_prefix(".git") {
                                         stripped_git_paths.insert(stripped.to_owned());
                                     } else {
-                                        worktree_relative_paths
-                                            .insert(relative_file_path.to_owned());
+                                        worktree_relative_paths.insert(match &index_casing {
+                                            Some(casing) => casing.apply(relative_file_path),
+                                            None => relative_file_path.to_owned(),
+                                        });
                                     };
                                 }
                                 Err(err) => {
@@ -214,8 +322,8 @@ pub fn spawn(
                         stats.record("git_dedup", paths_dedup.len());
                         let event = InternalEvent::GitFilesChange(project_id, paths_dedup);
                         if out.send(event).is_err() {
-                            tracing::info!("channel closed - stopping file watcher");
-                            break 'outer;
+                            tracing::info!("channel closed - ignoring file watcher events");
+                            return;
                         }
                     }
                     if !worktree_relative_paths.is_empty() {
@@ -223,29 +331,196 @@ pub fn spawn(
                         stats.record("project_dedup", paths_dedup.len());
                         let event = InternalEvent::ProjectFilesChange(project_id, paths_dedup);
                         if out.send(event).is_err() {
-                            tracing::info!("channel closed - stopping file watcher");
-                            break 'outer;
+                            tracing::info!("channel closed - ignoring file watcher events");
+                            return;
+                        }
+                    }
+                    let renames: Vec<_> = renames
+                        .into_iter()
+                        .filter_map(|(from, to)| {
+                            Some((
+                                from.strip_prefix(&worktree_path).ok()?.to_owned(),
+                                to.strip_prefix(&worktree_path).ok()?.to_owned(),
+                            ))
+                        })
+                        .collect();
+                    if !renames.is_empty() {
+                        let event = InternalEvent::ProjectFilesRenamed(project_id, renames);
+                        if out.send(event).is_err() {
+                            tracing::info!("channel closed - ignoring file watcher events");
+                            return;
                         }
                     }
                     if oplog_changed {
                         let event = InternalEvent::GitButlerOplogChange(project_id);
                         if out.send(event).is_err() {
-                            tracing::info!("channel closed - stopping file watcher");
-                            break 'outer;
+                            tracing::info!("channel closed - ignoring file watcher events");
+                            return;
                         }
                     }
                 }
             }
         }
-    });
+    };
+    let start_polling = || -> Result<AnyDebouncer> {
+        let interval = poll_interval(file_count);
+        let debouncer = start_debouncer::<PollWatcher>(
+            handle_events(WatcherMode::Polling),
+            notify::Config::default().with_poll_interval(interval),
+            &roots,
+        )?;
+        tracing::debug!(%project_id, ?interval, "polling file watcher started");
+        Ok(AnyDebouncer::Polling(debouncer))
+    };
+    let debouncer = match mode {
+        WatcherMode::Native => match start_debouncer::<RecommendedWatcher>(
+            handle_events(WatcherMode::Native),
+            notify::Config::default(),
+            &roots,
+        ) {
+            Ok(debouncer) => {
+                tracing::debug!(%project_id, "file watcher started");
+                AnyDebouncer::Native(debouncer)
+            }
+            // Polling is slower, but recording changes still works with it.
+            Err(err) => {
+                let Some(exceeded) = err.downcast_ref::<WatchLimitExceeded>() else {
+                    return Err(err);
+                };
+                tracing::warn!(%project_id, %exceeded, "falling back to polling");
+                bus::event_bus().publish(bus::Event::WatchLimitExceeded {
+                    project_id,
+                    limit: exceeded.limit,
+                    suggestion: exceeded.suggestion.clone(),
+                });
+                start_polling()?
+            }
+        },
+        WatcherMode::Polling => start_polling()?,
+    };
+    Ok(Monitor {
+        debouncer,
+        worktree_path: worktree_path.to_owned(),
+        git_dir,
+        include_paths,
+        watched: roots,
+    })
+}
+
+/// Return the directories to watch recursively: the worktree, or only the existing directories in
+/// `include_paths` relative to it if there are any, and the git directory unless it's already within them.
+fn watch_roots(worktree_path: &Path, git_dir: &Path, include_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots: Vec<_> = if include_paths.is_empty() {
+        vec![worktree_path.to_owned()]
+    } else {
+        include_paths
+            .iter()
+            .map(|path| worktree_path.join(path))
+            .filter(|path| path.is_dir())
+            .collect()
+    };
+    if !roots.iter().any(|root| git_dir.starts_with(root)) {
+        roots.push(git_dir.to_owned());
+    }
+    roots
+}
+
+/// Return `true` if the worktree file at `file_path` is in one of the worktree-relative `include_paths`, or if
+/// there are none.
+fn is_included(worktree_path: &Path, include_paths: &[PathBuf], file_path: &Path) -> bool {
+    include_paths.is_empty()
+        || file_path
+            .strip_prefix(worktree_path)
+            .is_ok_and(|relative| include_paths.iter().any(|path| relative.starts_with(path)))
+}
+
+/// Create a debouncer with a watcher of type `W` configured with `config`, and watch all `roots` with it.
+fn start_debouncer<W: Watcher>(
+    handle_events: impl DebounceEventHandler,
+    config: notify::Config,
+    roots: &[PathBuf],
+) -> Result<Debouncer<W, NoCache>> {
+    let mut debouncer = new_debouncer_opt::<_, W, _>(
+        DEBOUNCE_TIMEOUT,
+        Some(TICK_RATE),
+        Some(FLUSH_AFTER_EMPTY),
+        handle_events,
+        NoCache,
+        config,
+    )
+    .context("failed to create debouncer")?;
+
+    let policy = backoff::ExponentialBackoffBuilder::new()
+        .with_max_elapsed_time(Some(std::time::Duration::from_secs(30)))
+        .build();
+
+    // Start the watcher, but retry if there are transient errors.
+    backoff::retry(policy, || {
+        roots
+            .iter()
+            .try_for_each(|root| {
+                debouncer
+                    .watcher()
+                    .watch(root, notify::RecursiveMode::Recursive)
+                    .map_err(|err| (root, err))
+            })
+            .map_err(|(root, err)| {
+                if is_watch_limit(&err) {
+                    return backoff::Error::permanent(RunError::from(anyhow::Error::from(
+                        WatchLimitExceeded::current(),
+                    )));
+                }
+                match err.kind {
+                    notify::ErrorKind::PathNotFound => backoff::Error::permanent(RunError::from(
+                        anyhow!("{} not found", root.display()),
+                    )),
+                    notify::ErrorKind::Io(_) | notify::ErrorKind::InvalidConfig(_) => {
+                        backoff::Error::permanent(RunError::from(anyhow::Error::from(err)))
+                    }
+                    _ => backoff::Error::transient(RunError::from(anyhow::Error::from(err))),
+                }
+            })
+    })
+    .map_err(|err| match err {
+        backoff::Error::Permanent(err) | backoff::Error::Transient { err, .. } => err.source,
+    })
+    .context("failed to start watcher")?;
+
     Ok(debouncer)
 }
 
+/// Return `true` if `err` is about the system limit of watches being reached, which inotify reports as `ENOSPC`.
+fn is_watch_limit(err: &notify::Error) -> bool {
+    match &err.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        notify::ErrorKind::Io(err) => cfg!(target_os = "linux") && err.raw_os_error() == Some(28),
+        _ => false,
+    }
+}
+
+/// Return how often to scan a worktree with `file_count` files for changes, so scanning large worktrees doesn't
+/// keep the machine busy.
+fn poll_interval(file_count: usize) -> Duration {
+    let steps = u32::try_from(file_count / FILES_PER_POLL_INTERVAL + 1).unwrap_or(u32::MAX);
+    POLL_INTERVAL.saturating_mul(steps).min(MAX_POLL_INTERVAL)
+}
+
 #[cfg(target_family = "unix")]
-fn is_interesting_kind(kind: notify::EventKind) -> bool {
+fn is_interesting_kind(kind: notify::EventKind, mode: WatcherMode) -> bool {
+    // Scans can't tell what kind of change it was, so all are interesting.
+    if mode == WatcherMode::Polling {
+        return matches!(
+            kind,
+            notify::EventKind::Create(_)
+                | notify::EventKind::Modify(_)
+                | notify::EventKind::Remove(_)
+        );
+    }
     matches!(
         kind,
         notify::EventKind::Create(notify::event::CreateKind::File)
+            // Files created in a new directory before it is watched don't produce events of their own.
+            | notify::EventKind::Create(notify::event::CreateKind::Folder)
             | notify::EventKind::Modify(notify::event::ModifyKind::Data(_))
             | notify::EventKind::Modify(notify::event::ModifyKind::Name(_))
             | notify::EventKind::Remove(notify::event::RemoveKind::File)
@@ -253,13 +528,36 @@ fn is_interesting_kind(kind: notify::EventKind) -> bool {
 }
 
 #[cfg(target_os = "windows")]
-fn is_interesting_kind(kind: notify::EventKind) -> bool {
+fn is_interesting_kind(kind: notify::EventKind, _mode: WatcherMode) -> bool {
     matches!(
         kind,
         notify::EventKind::Create(_) | notify::EventKind::Modify(_) | notify::EventKind::Remove(_)
     )
 }
 
+/// Return the `(from, to)` paths if `event` is a rename within the worktree, as correlated by the debouncer
+/// using the rename cookie or file id.
+fn worktree_rename(
+    event: &notify::Event,
+    git_dir: &Path,
+    normalizer: &PathNormalizer,
+) -> Option<(PathBuf, PathBuf)> {
+    use notify::event::{ModifyKind, RenameMode};
+    if event.kind != notify::EventKind::Modify(ModifyKind::Name(RenameMode::Both)) {
+        return None;
+    }
+    let [from, to] = event.paths.as_slice() else {
+        return None;
+    };
+    let (from, to) = (
+        normalizer.normalize(from.clone()),
+        normalizer.normalize(to.clone()),
+    );
+    (classify_file(git_dir, &from) == FileKind::Project
+        && classify_file(git_dir, &to) == FileKind::Project)
+        .then_some((from, to))
+}
+
 /// A classification for a changed file.
 #[derive(Eq, PartialEq)]
 enum FileKind {
@@ -282,6 +580,8 @@ fn classify_file(git_dir: &Path, file_path: &Path) -> FileKind {
             || check_file_path == Path::new("HEAD")
             || check_file_path == Path::new("GB_FLUSH")
             || check_file_path == Path::new("index")
+            || check_file_path == Path::new("packed-refs")
+            || check_file_path.starts_with("refs/heads")
         {
             FileKind::Git
         } else if check_file_path == Path::new("gitbutler").join(OPLOG_FILE_NAME) {
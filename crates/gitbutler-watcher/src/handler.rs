@@ -8,15 +8,20 @@ use gitbutler_diff::DiffByPathMap;
 use gitbutler_error::error::Marker;
 use gitbutler_operating_modes::{in_open_workspace_mode, operating_mode};
 use gitbutler_oplog::{
-    entry::{OperationKind, SnapshotDetails},
-    OplogExt,
+    deltas,
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    wip, OplogExt,
 };
-use gitbutler_project::{self as projects, Project, ProjectId};
-use gitbutler_sync::cloud::{push_oplog, push_repo};
+use gitbutler_project::{self as projects, machine_changes, Project, ProjectId};
+use gitbutler_sync::{
+    cloud::{push_oplog, push_repo},
+    history_backup::{push_history, push_wip_snapshots},
+};
+use gitbutler_time::clock::{Clock, SystemClock};
 use gitbutler_user as users;
 use tracing::instrument;
 
-use super::{events, Change};
+use super::{bus, events, head, index, Change};
 
 /// A type that contains enough state to make decisions based on changes in the filesystem, which themselves
 /// may trigger [Changes](Change)
@@ -34,6 +39,8 @@ pub struct Handler {
     /// A function to send events - decoupled from app-handle for testing purposes.
     #[allow(clippy::type_complexity)]
     send_event: Arc<dyn Fn(Change) -> Result<()> + Send + Sync + 'static>,
+    /// When changes are noticed, which is the time of the system unless tests drive it.
+    clock: Arc<dyn Clock>,
 }
 
 impl Handler {
@@ -48,9 +55,19 @@ impl Handler {
             projects,
             users,
             send_event: Arc::new(send_event),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Tell the time at which changes are noticed with `clock` instead of the time of the system. It's the time
+    /// of the deltas that are recorded, and of the events published to the [bus](bus::event_bus()), including
+    /// those starting and ending sessions. Snapshots keep the time of their commits, which the environment can
+    /// override.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Handle the events that come in from the filesystem, or the public API.
     #[instrument(skip(self, app_settings), fields(event = %event), err(Debug))]
     pub(super) fn handle(
@@ -60,10 +77,24 @@ impl Handler {
     ) -> Result<()> {
         match event {
             events::InternalEvent::ProjectFilesChange(project_id, paths) => {
+                let _ = self.emit_app_event(Change::FilesChanged {
+                    project_id,
+                    paths: paths.clone(),
+                });
                 let ctx = self.open_command_context(project_id, app_settings.get()?.clone())?;
                 self.project_files_change(paths, &ctx)
             }
 
+            events::InternalEvent::ProjectFilesRenamed(project_id, renames) => {
+                let _ = self.emit_app_event(Change::FilesRenamed {
+                    project_id,
+                    renames: renames.clone(),
+                });
+                let ctx = self.open_command_context(project_id, app_settings.get()?.clone())?;
+                self.project_files_renamed(renames, &ctx);
+                Ok(())
+            }
+
             events::InternalEvent::GitFilesChange(project_id, paths) => {
                 let ctx = self.open_command_context(project_id, app_settings.get()?.clone())?;
                 self.git_files_change(paths, &ctx)
@@ -83,10 +114,32 @@ impl Handler {
                 self.calculate_virtual_branches(&ctx, None)
                     .context("failed to handle virtual branch event")
             }
+            events::InternalEvent::ReconcileOfflineChanges(project_id) => {
+                let ctx = self.open_command_context(project_id, app_settings.get()?.clone())?;
+                // Start from the current state so only changes made from now on count as git operations.
+                head::update(project_id, head::HeadState::of(&ctx));
+                let result = self
+                    .reconcile_offline_changes(&ctx)
+                    .context("failed to record offline changes");
+                // The state is as current as it gets even if the changes couldn't be recorded.
+                self.emit_app_event(Change::ProjectReady(project_id))?;
+                result
+            }
+            events::InternalEvent::BackupHistory(project_id) => {
+                let ctx = self.open_command_context(project_id, app_settings.get()?.clone())?;
+                push_history(&ctx, None)
+                    .map(|_| ())
+                    .context("failed to back up oplog")
+            }
         }
     }
 
-    fn emit_app_event(&self, event: Change) -> Result<()> {
+    /// Publish `event` to the [bus](bus::event_bus()) as happening now, as per the clock.
+    pub(super) fn publish(&self, event: bus::Event) {
+        bus::event_bus().publish_at(event, self.clock.now());
+    }
+
+    pub(super) fn emit_app_event(&self, event: Change) -> Result<()> {
         (self.send_event)(event).context("failed to send event")
     }
 
@@ -102,14 +155,14 @@ impl Handler {
         CommandContext::open(&project, app_settings).context("Failed to create a command context")
     }
 
-    #[instrument(skip(self, ctx, worktree_changes))]
+    #[instrument(skip(self, ctx, worktree_changes), fields(project_id = %ctx.project().id))]
     fn calculate_virtual_branches(
         &self,
         ctx: &CommandContext,
         worktree_changes: Option<DiffByPathMap>,
     ) -> Result<()> {
-        // Skip if we're not on the open workspace mode
-        if !in_open_workspace_mode(ctx) {
+        // Skip if we're not on the open workspace mode, or if the workspace belongs to the project of the repository
+        if !in_open_workspace_mode(ctx) || ctx.project().is_subproject() {
             return Ok(());
         }
 
@@ -143,15 +196,99 @@ impl Handler {
         }
     }
 
-    #[instrument(skip(self, paths, ctx), fields(paths = paths.len()))]
+    /// Record the `renames` of files as a delta of their own, so the history of each file can be followed across
+    /// the move. Their contents are recorded like for any other change, along with the change event of their
+    /// paths.
+    #[instrument(skip(self, renames, ctx), fields(project_id = %ctx.project().id, renames = renames.len()))]
+    fn project_files_renamed(&self, renames: Vec<(PathBuf, PathBuf)>, ctx: &CommandContext) {
+        let project = ctx.project();
+        if project.recording_paused {
+            return;
+        }
+        let paths: Vec<_> = renames
+            .iter()
+            .flat_map(|(from, to)| [from.clone(), to.clone()])
+            .collect();
+        let delta = deltas::Delta {
+            at: self.clock.now_seconds(),
+            classification: machine_changes::classify(
+                project.id,
+                &project.change_classification_rules,
+                &paths,
+            ),
+            paths,
+            branch: head::current_branch(project.id, ctx),
+            checkpoint: None,
+            contents: Vec::new(),
+            bulk: false,
+            renames: renames
+                .into_iter()
+                .map(|(from, to)| deltas::DeltaRename { from, to })
+                .collect(),
+        };
+        if let Err(err) = deltas::record_delta(project, delta) {
+            tracing::warn!(?err, "failed to record renames");
+        }
+    }
+
+    #[instrument(
+        skip(self, paths, ctx),
+        fields(project_id = %ctx.project().id, paths = paths.len(), machine_generated, git_operation)
+    )]
     fn project_files_change(&self, paths: Vec<PathBuf>, ctx: &CommandContext) -> Result<()> {
+        // Changes written by our own operations, like cherry-picks, already have a snapshot of their own,
+        // and so do those of git operations like a checkout. Those of other tools like formatters aren't worth one.
+        let project = ctx.project();
+        let classification =
+            machine_changes::classify(project.id, &project.change_classification_rules, &paths);
+        let recording_paused = project.recording_paused;
+        if !recording_paused {
+            let at = self.clock.now_seconds();
+            let delta = deltas::Delta {
+                at,
+                paths: paths.clone(),
+                classification,
+                branch: head::current_branch(project.id, ctx),
+                checkpoint: None,
+                contents: Vec::new(),
+                bulk: false,
+                renames: Vec::new(),
+            };
+            if let Err(err) = deltas::record_delta(project, delta) {
+                tracing::warn!(?err, "failed to record delta");
+            }
+            match wip::write_wip_snapshot(project, at) {
+                Ok(Some(_)) => {
+                    if let Err(err) = push_wip_snapshots(ctx, None) {
+                        tracing::warn!(?err, "failed to back up WIP snapshots");
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => tracing::warn!(?err, "failed to write WIP snapshot"),
+            }
+        }
+        let machine_generated = classification.is_machine_generated();
+        let git_operation =
+            classification.reason == Some(machine_changes::ClassificationReason::GitOperation);
+        tracing::Span::current().record("machine_generated", machine_generated);
+        tracing::Span::current().record("git_operation", git_operation);
+        self.publish(bus::Event::DeltaRecorded {
+            project_id: ctx.project().id,
+            paths: paths.clone(),
+            machine_generated,
+            git_operation,
+            classification,
+        });
         let worktree_changes = self.emit_uncommited_files(ctx).ok();
 
         if ctx.app_settings().feature_flags.v3 {
             // This is part of the v3 APIs set and in the future this fully replaces the list virtual branches flow
             let _ = self.emit_worktree_changes(ctx.gix_repository()?, ctx.project().id);
         } else if in_open_workspace_mode(ctx) {
-            self.maybe_create_snapshot(ctx.project()).ok();
+            if !machine_generated && !recording_paused {
+                self.maybe_create_snapshot(ctx.project(), worktree_changes.as_ref())
+                    .ok();
+            }
             self.calculate_virtual_branches(ctx, worktree_changes)?;
         }
 
@@ -168,6 +305,7 @@ impl Handler {
     }
 
     /// Try to emit uncommited files. Swollow errors if they arrise.
+    #[instrument(skip(self, ctx), fields(project_id = %ctx.project().id))]
     fn emit_uncommited_files(&self, ctx: &CommandContext) -> Result<DiffByPathMap> {
         let files = gitbutler_branch_actions::get_uncommited_files_reusable(ctx)?;
 
@@ -182,41 +320,105 @@ impl Handler {
         Ok(files)
     }
 
-    fn maybe_create_snapshot(&self, project: &Project) -> anyhow::Result<()> {
+    /// Tell the frontend which changes would conflict with upstream commits that were just fetched.
+    #[instrument(skip(self, ctx), fields(project_id = %ctx.project().id))]
+    fn emit_upstream_conflicts(&self, ctx: &CommandContext) -> Result<()> {
+        if !in_open_workspace_mode(ctx) || ctx.project().is_subproject() {
+            return Ok(());
+        }
+        let Some(conflicts) = gitbutler_branch_actions::upstream_conflicts::predict(ctx)? else {
+            return Ok(());
+        };
+        self.emit_app_event(Change::UpstreamConflicts {
+            project_id: ctx.project().id,
+            conflicts,
+        })
+    }
+
+    #[instrument(skip(self, project, worktree_changes), fields(project_id = %project.id))]
+    fn maybe_create_snapshot(
+        &self,
+        project: &Project,
+        worktree_changes: Option<&DiffByPathMap>,
+    ) -> anyhow::Result<()> {
         if project
             .should_auto_snapshot(std::time::Duration::from_secs(300))
             .unwrap_or_default()
         {
+            let mut details = SnapshotDetails::new(OperationKind::FileChanges);
+            details.body = worktree_changes.and_then(|changes| edited_symbols(project, changes));
             let mut guard = project.exclusive_worktree_access();
-            project.create_snapshot(
-                SnapshotDetails::new(OperationKind::FileChanges),
-                guard.write_permission(),
-            )?;
+            project.create_snapshot(details, guard.write_permission())?;
         }
         Ok(())
     }
 
+    /// Record a snapshot of changes that were made while the app wasn't watching, so the history has no gaps.
+    #[instrument(skip(self, ctx), fields(project_id = %ctx.project().id))]
+    fn reconcile_offline_changes(&self, ctx: &CommandContext) -> Result<()> {
+        if ctx.app_settings().feature_flags.v3
+            || !in_open_workspace_mode(ctx)
+            || ctx.project().recording_paused
+        {
+            return Ok(());
+        }
+        // Brings the worktrees of the branches up to date, which the snapshot records.
+        self.calculate_virtual_branches(ctx, None)?;
+        let mut guard = ctx.project().exclusive_worktree_access();
+        if let Some(snapshot_id) = gitbutler_oplog::verify::record_offline_changes(
+            ctx.project(),
+            guard.write_permission(),
+        )? {
+            tracing::info!(project_id = %ctx.project().id, %snapshot_id, "recorded offline changes");
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, paths, ctx), fields(project_id = %ctx.project().id, paths = paths.len()))]
     pub fn git_files_change(&self, paths: Vec<PathBuf>, ctx: &CommandContext) -> Result<()> {
+        let mut head_may_have_moved = false;
         for path in paths {
+            if path.starts_with("refs/heads") {
+                head_may_have_moved = true;
+                continue;
+            }
             let Some(file_name) = path.to_str() else {
                 continue;
             };
             match file_name {
                 "FETCH_HEAD" => {
+                    self.publish_git_operation(ctx, bus::GitOperation::Fetch);
                     self.emit_app_event(Change::GitFetch(ctx.project().id))?;
+                    if let Err(err) = self.emit_upstream_conflicts(ctx) {
+                        tracing::warn!(?err, "failed to predict conflicts with upstream");
+                    }
                 }
                 "logs/HEAD" => {
+                    head_may_have_moved = true;
+                    self.publish_git_operation(ctx, bus::GitOperation::Activity);
                     self.emit_app_event(Change::GitActivity(ctx.project().id))?;
                 }
                 "index" => {
+                    if !index::settled_change(ctx.project().id, &ctx.repo().path().join("index")) {
+                        continue;
+                    }
+                    self.emit_app_event(Change::GitIndexChanged(ctx.project().id))?;
                     if ctx.app_settings().feature_flags.v3 {
                         let repo = gix::open(ctx.project().path.clone())?;
                         let _ = self.emit_worktree_changes(repo, ctx.project().id);
                     }
                 }
+                "packed-refs" => head_may_have_moved = true,
                 "HEAD" => {
+                    head_may_have_moved = true;
                     let head_ref = ctx.repo().head().context("failed to get head")?;
                     if let Some(head) = head_ref.name() {
+                        self.publish_git_operation(
+                            ctx,
+                            bus::GitOperation::Head {
+                                head: head.to_string(),
+                            },
+                        );
                         self.emit_app_event(Change::GitHead {
                             project_id: ctx.project().id,
                             head: head.to_string(),
@@ -227,11 +429,61 @@ impl Handler {
                 _ => {}
             }
         }
+        if head_may_have_moved {
+            self.detect_git_operation(ctx)?;
+        }
         Ok(())
     }
 
+    /// If `HEAD` was changed by a git operation outside of GitButler, tell the frontend and record a snapshot
+    /// that ends the current session, with the changes to the worktree that follow attributed to the operation.
+    #[instrument(skip(self, ctx), fields(project_id = %ctx.project().id))]
+    fn detect_git_operation(&self, ctx: &CommandContext) -> Result<()> {
+        let project = ctx.project();
+        let new = head::HeadState::of(ctx);
+        let Some(old) = head::update(project.id, new.clone()) else {
+            return Ok(());
+        };
+        machine_changes::record_git_operation(project.id);
+        let message = head::latest_reflog_message(ctx);
+        tracing::info!(?old, ?new, message, "HEAD was changed outside of GitButler");
+        if !project.recording_paused {
+            let mut details = SnapshotDetails::new(OperationKind::ExternalGitOperation);
+            details.body = message.clone();
+            details.trailers = [("old_head", &old), ("new_head", &new)]
+                .into_iter()
+                .filter_map(|(key, head)| {
+                    Some(Trailer {
+                        key: key.to_owned(),
+                        value: head.name.clone().or_else(|| head.commit_id.clone())?,
+                    })
+                })
+                .collect();
+            let mut guard = project.exclusive_worktree_access();
+            if let Err(err) = project.create_snapshot(details, guard.write_permission()) {
+                tracing::warn!(?err, "failed to snapshot the result of a git operation");
+            }
+        }
+        self.emit_app_event(Change::GitHeadChanged {
+            project_id: project.id,
+            old,
+            new,
+            message,
+        })
+    }
+
+    fn publish_git_operation(&self, ctx: &CommandContext, operation: bus::GitOperation) {
+        self.publish(bus::Event::GitOperation {
+            project_id: ctx.project().id,
+            operation,
+        });
+    }
+
     /// Invoked whenever there's a new oplog entry.
-    /// If synchronizing with GitButler's servers is enabled it will push Oplog refs
+    /// If synchronizing with GitButler's servers is enabled it will push Oplog refs.
+    /// The history backup remote is pushed to once the oplog stopped changing, with
+    /// [`BackupHistory`](events::InternalEvent::BackupHistory).
+    #[instrument(skip(self, ctx), fields(project_id = %ctx.project().id))]
     fn gitbutler_oplog_change(&self, ctx: &CommandContext) -> Result<()> {
         if let Some(user) = self.users.get_user()? {
             if ctx.project().oplog_sync_enabled() {
@@ -244,3 +496,38 @@ impl Handler {
         Ok(())
     }
 }
+
+/// Describe which functions and types were edited in `changes`, like "Edited parse_config() and Watcher::run()",
+/// or `None` if no symbols could be determined.
+fn edited_symbols(project: &Project, changes: &DiffByPathMap) -> Option<String> {
+    const MAX_SYMBOLS: usize = 5;
+    let mut paths: Vec<_> = changes
+        .values()
+        .filter(|diff| {
+            !diff.binary && !diff.skipped && but_symbols::Language::from_path(&diff.path).is_some()
+        })
+        .collect();
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut symbols = Vec::new();
+    for diff in paths {
+        let Ok(source) = std::fs::read(project.path.join(&diff.path)) else {
+            continue;
+        };
+        let lines = diff
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.new_lines > 0)
+            .map(|hunk| {
+                let start = hunk.new_start.saturating_sub(1) as usize;
+                start..start + hunk.new_lines as usize
+            });
+        match but_symbols::symbols_at_lines(&diff.path, &source, lines) {
+            Ok(found) => symbols.extend(found),
+            Err(err) => {
+                tracing::debug!(path = %diff.path.display(), ?err, "could not determine edited symbols")
+            }
+        }
+    }
+    but_symbols::describe(&symbols, MAX_SYMBOLS).map(|edited| format!("Edited {edited}"))
+}
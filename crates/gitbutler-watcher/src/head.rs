@@ -0,0 +1,84 @@
+//! Notice when `HEAD` is changed by git operations outside of GitButler, like a checkout, pull or rebase in a
+//! terminal.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::ProjectId;
+use serde::Serialize;
+
+/// The last known state of `HEAD` of each watched project.
+static LAST_KNOWN: Mutex<BTreeMap<ProjectId, HeadState>> = Mutex::new(BTreeMap::new());
+
+/// The state of `HEAD` before or after it changed, as part of [`Change::GitHeadChanged`](crate::Change::GitHeadChanged).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadState {
+    /// The full name of the branch `HEAD` points to, or `None` if it's detached.
+    pub name: Option<String>,
+    /// The commit `HEAD` points to, or `None` if the branch is unborn.
+    pub commit_id: Option<String>,
+}
+
+impl HeadState {
+    /// Read the current state of `HEAD` of the repository in `ctx`.
+    pub(crate) fn of(ctx: &CommandContext) -> Self {
+        let repo = ctx.repo();
+        HeadState {
+            name: repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|head| head.symbolic_target().map(ToOwned::to_owned)),
+            commit_id: repo
+                .head()
+                .ok()
+                .and_then(|head| head.target())
+                .map(|id| id.to_string()),
+        }
+    }
+
+    /// Return `true` if `HEAD` points to one of the branches GitButler maintains, like its workspace branch,
+    /// which GitButler updates with each of its operations.
+    fn is_gitbutler_branch(&self) -> bool {
+        self.name
+            .as_deref()
+            .is_some_and(|name| name.starts_with("refs/heads/gitbutler/"))
+    }
+
+    /// Return the short name of the branch `HEAD` points to, like `main`, or `None` if it's detached.
+    pub(crate) fn branch(&self) -> Option<&str> {
+        let name = self.name.as_deref()?;
+        Some(name.strip_prefix("refs/heads/").unwrap_or(name))
+    }
+}
+
+/// Return the branch of the project with `project_id` that changes are made on, as known from the start of the
+/// session or the last change of `HEAD`, or as read from the repository in `ctx` if neither was seen yet.
+pub(crate) fn current_branch(project_id: ProjectId, ctx: &CommandContext) -> Option<String> {
+    let known = LAST_KNOWN
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&project_id)
+        .cloned();
+    known
+        .unwrap_or_else(|| HeadState::of(ctx))
+        .branch()
+        .map(ToOwned::to_owned)
+}
+
+/// Remember `head` as the current state of `HEAD` of the project with `project_id`, and return the previous state
+/// if it was known and `HEAD` was changed by a git operation outside of GitButler since.
+pub(crate) fn update(project_id: ProjectId, head: HeadState) -> Option<HeadState> {
+    let previous = LAST_KNOWN
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(project_id, head.clone())?;
+    (previous != head && !head.is_gitbutler_branch()).then_some(previous)
+}
+
+/// Return the message of the latest entry in the reflog of `HEAD`, like `checkout: moving from main to feature`.
+pub(crate) fn latest_reflog_message(ctx: &CommandContext) -> Option<String> {
+    let reflog = ctx.repo().reflog("HEAD").ok()?;
+    let entry = reflog.get(0)?;
+    entry.message().map(ToOwned::to_owned)
+}
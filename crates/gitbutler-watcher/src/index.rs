@@ -0,0 +1,40 @@
+//! Notice when the index is changed by git operations outside of GitButler, like `git add` in a terminal, so
+//! what's shown as staged can be refreshed.
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use gitbutler_fs::FileStamp;
+use gitbutler_project::ProjectId;
+
+/// The state of the index of each watched project when its change was last reported.
+static LAST_KNOWN: Mutex<BTreeMap<ProjectId, FileStamp>> = Mutex::new(BTreeMap::new());
+
+/// How long the index must stay unchanged before its change is reported, as operations like a rebase write it
+/// several times in a row.
+const QUIET_PERIOD: Duration = Duration::from_millis(100);
+/// The most quiet periods to wait for, so an index that is written continuously is still reported.
+const MAX_WAITS: usize = 10;
+
+/// Wait for the index at `index_path` of the project with `project_id` to settle, and return `true` if it's
+/// different from when its change was last reported, remembering it as reported.
+pub(crate) fn settled_change(project_id: ProjectId, index_path: &Path) -> bool {
+    let mut stamp = FileStamp::of(index_path).ok();
+    for _ in 0..MAX_WAITS {
+        std::thread::sleep(QUIET_PERIOD);
+        let current = FileStamp::of(index_path).ok();
+        if current == stamp {
+            break;
+        }
+        stamp = current;
+    }
+    let Some(stamp) = stamp else {
+        return false;
+    };
+    LAST_KNOWN
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(project_id, stamp)
+        != Some(stamp)
+}
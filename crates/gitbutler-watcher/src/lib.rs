@@ -3,22 +3,36 @@
 #![allow(clippy::doc_markdown, clippy::missing_errors_doc)]
 
 mod events;
-use std::path::Path;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use but_settings::AppSettingsWithDiskSync;
 use events::InternalEvent;
 pub use events::{Action, Change};
-use gitbutler_project::ProjectId;
+use gitbutler_project::{ProjectId, WatcherMode};
 pub use handler::Handler;
-use tokio::{
-    sync::mpsc::{unbounded_channel, UnboundedSender},
-    task,
-};
+use serde::Serialize;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use tokio_util::sync::CancellationToken;
 
+mod activity;
+pub use activity::ActivityPulse;
+mod backup_timer;
+pub mod bus;
 mod file_monitor;
+pub use file_monitor::WatchLimitExceeded;
 mod handler;
+mod head;
+pub use head::HeadState;
+mod index;
+mod paths;
+mod pool;
+mod routes;
+use routes::{RouteChange, Routes};
 
 /// An abstraction over a link to the spawned watcher, which runs in the background.
 pub struct WatcherHandle {
@@ -27,8 +41,59 @@ pub struct WatcherHandle {
     /// The id of the project we are watching.
     project_id: ProjectId,
     signal_flush: UnboundedSender<()>,
+    /// A way to change the projects sharing the watcher, and the directories they cover.
+    routes: UnboundedSender<RouteChange>,
     /// A way to tell the background process to stop handling events.
     cancellation_token: CancellationToken,
+    throughput: Arc<Mutex<Throughput>>,
+    /// The recent edits in the worktree, shared with the background process that records them.
+    activity: Arc<Mutex<activity::ActivityTracker>>,
+}
+
+/// How many events a watcher received and handled, as returned by [`WatcherHandle::metrics()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherMetrics {
+    pub project_id: ProjectId,
+    pub events_received: u64,
+    pub events_handled: u64,
+    /// The amount of events waiting to be handled.
+    pub events_queued: usize,
+    /// The amount of events handled within the last minute.
+    pub events_per_minute: usize,
+    /// The average time it took to handle an event, in milliseconds.
+    pub average_handling_ms: u64,
+}
+
+/// The counters behind [`WatcherMetrics`].
+#[derive(Debug, Default)]
+struct Throughput {
+    received: u64,
+    handled: u64,
+    handling_time: Duration,
+    /// When events were handled within the last minute.
+    recently_handled: VecDeque<Instant>,
+}
+
+impl Throughput {
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    fn record_handled(&mut self, started: Instant, now: Instant) {
+        self.handled += 1;
+        self.handling_time += now.duration_since(started);
+        self.recently_handled.push_back(now);
+        self.forget_before(now);
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        while self
+            .recently_handled
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > Self::WINDOW)
+        {
+            self.recently_handled.pop_front();
+        }
+    }
 }
 
 impl Drop for WatcherHandle {
@@ -55,6 +120,121 @@ impl WatcherHandle {
         self.signal_flush.send(())?;
         Ok(())
     }
+
+    /// Only watch the worktree-relative directories in `include_paths` for the project with `project_id` from now
+    /// on, or all of the worktree if empty, without restarting the watcher.
+    pub fn set_include_paths(
+        &self,
+        project_id: ProjectId,
+        include_paths: Vec<PathBuf>,
+    ) -> Result<()> {
+        self.routes
+            .send(RouteChange::Set(project_id, include_paths))
+            .context("failed to change the watched paths")?;
+        Ok(())
+    }
+
+    /// Have the watcher also report changes for the project with `project_id` in the same worktree, like a
+    /// sub-project of a monorepo, to those of its files in the worktree-relative directories in `include_paths`.
+    ///
+    /// This way, the projects of a repository share a single watcher instead of each watching it.
+    pub fn add_project(&self, project_id: ProjectId, include_paths: Vec<PathBuf>) -> Result<()> {
+        self.set_include_paths(project_id, include_paths)
+    }
+
+    /// Stop reporting changes for the project with `project_id`, which was [added](Self::add_project()) before,
+    /// or is the one the watcher was started for.
+    pub fn remove_project(&self, project_id: ProjectId) -> Result<()> {
+        self.routes
+            .send(RouteChange::Remove(project_id))
+            .context("failed to remove project from watcher")?;
+        Ok(())
+    }
+
+    /// Return when the file at the worktree-relative `path` was changed last, if that was within the last minute,
+    /// to avoid writing it while an editor does too.
+    ///
+    /// Changes that look machine-generated, like build outputs, aren't considered.
+    pub fn last_change(&self, path: &Path) -> Option<Instant> {
+        self.activity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .last_change(path)
+    }
+
+    /// Return how many events were received and handled since the watcher was started.
+    pub fn metrics(&self) -> WatcherMetrics {
+        let mut throughput = self
+            .throughput
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        throughput.forget_before(Instant::now());
+        WatcherMetrics {
+            project_id: self.project_id,
+            events_received: throughput.received,
+            events_handled: throughput.handled,
+            events_queued: WORKERS.queued(self.project_id),
+            events_per_minute: throughput.recently_handled.len(),
+            average_handling_ms: throughput
+                .handling_time
+                .as_millis()
+                .checked_div(u128::from(throughput.handled))
+                .unwrap_or_default() as u64,
+        }
+    }
+}
+
+/// How often to send an [`ActivityPulse`] while the worktree is changing.
+const ACTIVITY_PULSE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long after starting to catch up with what changed while nobody was watching, so the window it starts
+/// for can load first.
+const CATCH_UP_DELAY: Duration = Duration::from_secs(2);
+
+/// Handles the events of all watchers, limiting how many are handled at the same time.
+static WORKERS: LazyLock<pool::WorkerPool> = LazyLock::new(Default::default);
+
+/// Set once the app is exiting, see [`begin_shutdown()`].
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// The amount of watchers whose processing loop still runs, including those that were stopped but still hand over
+/// the events they noticed last.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a processing loop as [running](RUNNING) until dropped.
+struct RunningGuard;
+
+impl RunningGuard {
+    fn new() -> Self {
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        RunningGuard
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Note that the app is about to exit, so watchers stopped from now on end their session with
+/// [`SessionEndReason::AppClosed`](bus::SessionEndReason::AppClosed).
+pub fn begin_shutdown() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+}
+
+/// Wait until all watchers stopped after handing over the events they noticed last, and all events were handled,
+/// for at most `timeout`. Return `true` if nothing is pending anymore.
+///
+/// This is meant to be called after dropping the handles of all watchers when the app exits, so no changes are lost.
+pub fn wait_until_flushed(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while RUNNING.load(Ordering::SeqCst) > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    WORKERS.wait_until_idle(deadline.saturating_duration_since(Instant::now()))
 }
 
 /// Run our file watcher processing loop in the background and let `handler` deal with them.
@@ -64,59 +244,178 @@ impl WatcherHandle {
 /// ### How it works
 ///
 /// The watcher is a processing loop that relies on filesystem events. These are aggregated so
-/// every ~100ms, the changed paths sorted by 'worktree' and 'git-repository' will be processed,
-/// each of these events is handled in its own thread, while being able to spawn additional processing
-/// tasks as well.
+/// every ~100ms, the changed paths sorted by 'worktree' and 'git-repository' will be processed.
+/// The events of all watchers are handled by a shared pool of worker threads with a queue per project,
+/// so the events of one project are handled in order, and busy projects can't starve the others.
 ///
 /// This also means that when there are continuous changes to the filesystem, these events might pile
-/// up if they take longer to process than the 100ms window between them, causing high-CPU and possibly
-/// high-memory. However, the likelihood for this is much lower than it was before the architecture
-/// was changed to what it is now, which should be much less wasteful.
+/// up in the queue of the project if they take longer to process than the 100ms window between them.
+///
+/// With `watcher_mode` set to [`WatcherMode::Polling`], the files are scanned periodically instead of relying
+/// on filesystem events, for filesystems that don't deliver them reliably. This is also done if the system limit
+/// of watches is reached, after publishing [`bus::Event::WatchLimitExceeded`].
+///
+/// If `include_paths` isn't empty, only changes in these worktree-relative directories are watched and recorded,
+/// see [`WatcherHandle::set_include_paths()`].
 pub fn watch_in_background(
     handler: handler::Handler,
     worktree_path: impl AsRef<Path>,
     project_id: ProjectId,
+    watcher_mode: WatcherMode,
+    include_paths: Vec<PathBuf>,
     app_settings: AppSettingsWithDiskSync,
 ) -> Result<WatcherHandle, anyhow::Error> {
     let (events_out, mut events_in) = unbounded_channel();
     let (flush_tx, mut flush_rx) = unbounded_channel();
+    let (routes_tx, mut routes_rx) = unbounded_channel();
+    let mut routes = Routes::new(project_id, include_paths.clone());
 
-    let debounce = file_monitor::spawn(project_id, worktree_path.as_ref(), events_out.clone())?;
+    let mut debounce = file_monitor::spawn(
+        project_id,
+        worktree_path.as_ref(),
+        watcher_mode,
+        include_paths,
+        events_out.clone(),
+    )?;
 
     let cancellation_token = CancellationToken::new();
+    let throughput = Arc::new(Mutex::new(Throughput::default()));
+    let activity = Arc::new(Mutex::new(activity::ActivityTracker::default()));
     let handle = WatcherHandle {
         tx: events_out,
         project_id,
         signal_flush: flush_tx,
+        routes: routes_tx,
         cancellation_token: cancellation_token.clone(),
+        throughput: throughput.clone(),
+        activity: activity.clone(),
     };
+    handler.publish(bus::Event::SessionStarted { project_id });
+    tokio::spawn({
+        let tx = handle.tx.clone();
+        async move {
+            // Leave the workers to what the window needs to show first, as catching up takes a while.
+            tokio::time::sleep(CATCH_UP_DELAY).await;
+            if tx
+                .send(InternalEvent::ReconcileOfflineChanges(project_id))
+                .is_err()
+            {
+                tracing::debug!(%project_id, "watcher stopped before catching up with offline changes");
+            }
+        }
+    });
+    let pulse_handler = handler.clone();
+    let session_handler = handler.clone();
     let handle_event =
         move |event: InternalEvent, app_settings: AppSettingsWithDiskSync| -> Result<()> {
             let handler = handler.clone();
-            // NOTE: Traditional parallelization (blocking) is required as `tokio::spawn()` on
-            //       the `handler.handle()` future isn't `Send` as it keeps non-Send things
-            //       across await points. Further, there is a fair share of `sync` IO happening
-            //       as well, so nothing can really be done here.
-            task::spawn_blocking(move || {
-                handler.handle(event, app_settings).ok();
+            let limit = app_settings
+                .get()
+                .map(|settings| settings.concurrency.effective_watcher_workers())
+                .unwrap_or(1);
+            let throughput = throughput.clone();
+            throughput
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .received += 1;
+            // NOTE: Blocking threads are required as the `handler.handle()` future isn't `Send`,
+            //       it keeps non-Send things across await points. Further, there is a fair share
+            //       of `sync` IO happening as well.
+            WORKERS.submit(project_id, limit, move || {
+                let started = Instant::now();
+                if let Err(err) = handler.handle(event, app_settings) {
+                    handler.publish(bus::Event::WatcherError {
+                        project_id,
+                        message: format!("{err:#}"),
+                    });
+                }
+                throughput
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .record_handled(started, Instant::now());
             });
             Ok(())
         };
 
+    let running = RunningGuard::new();
     tokio::spawn(async move {
+        let _running = running;
+        let mut was_idle = true;
+        let mut pulse_interval = tokio::time::interval(ACTIVITY_PULSE_INTERVAL);
+        let mut backups = backup_timer::BackupTimer::default();
         loop {
             tokio::select! {
-                Some(event) = events_in.recv() => handle_event(event, app_settings.clone())?,
+                Some(event) = events_in.recv() => {
+                    if let InternalEvent::ProjectFilesChange(_, paths) = &event {
+                        // The rules of the project aren't known here, but the built-in heuristics suffice.
+                        if !gitbutler_project::machine_changes::classify(project_id, &[], paths)
+                            .is_machine_generated()
+                        {
+                            activity
+                                .lock()
+                                .unwrap_or_else(|err| err.into_inner())
+                                .record(paths, Instant::now());
+                        }
+                    }
+                    if let InternalEvent::GitButlerOplogChange(project_id) = &event {
+                        backups.record(*project_id, Instant::now());
+                    }
+                    for event in routes.route(event) {
+                        handle_event(event, app_settings.clone())?
+                    }
+                }
+                () = backup_timer::sleep_until(backups.next_due()) => {
+                    for project_id in backups.take_due(Instant::now()) {
+                        handle_event(InternalEvent::BackupHistory(project_id), app_settings.clone())?
+                    }
+                }
+                _ = pulse_interval.tick() => {
+                    let pulse = activity
+                        .lock()
+                        .unwrap_or_else(|err| err.into_inner())
+                        .pulse(Instant::now());
+                    if !(pulse.is_idle() && was_idle) {
+                        pulse_handler
+                            .emit_app_event(Change::ActivityPulse { project_id, pulse })
+                            .ok();
+                    }
+                    was_idle = pulse.is_idle();
+                }
                 Some(_signal_flush) = flush_rx.recv() => {
                     debounce.flush_nonblocking();
                 }
+                Some(change) = routes_rx.recv() => {
+                    if let Some(added) = routes.apply(change) {
+                        handle_event(InternalEvent::ReconcileOfflineChanges(added), app_settings.clone())?
+                    }
+                    if let Err(err) = debounce.set_include_paths(routes.include_paths()) {
+                        tracing::warn!(%project_id, ?err, "failed to change the watched paths");
+                    }
+                }
                 () = cancellation_token.cancelled() => {
-                    tracing::debug!(%project_id, "stopped watcher");
-                    break;
+                    // Hand over what was noticed but not yet emitted, which the debouncer does on its next tick.
+                    debounce.flush_nonblocking();
+                    tokio::time::sleep(file_monitor::TICK_RATE * 2).await;
+                    drop(debounce);
+                    let mut result = Ok(());
+                    while let Ok(event) = events_in.try_recv() {
+                        for event in routes.route(event) {
+                            if let Err(err) = handle_event(event, app_settings.clone()) {
+                                result = Err(err);
+                            }
+                        }
+                    }
+                    let reason = if SHUTTING_DOWN.load(Ordering::SeqCst) {
+                        bus::SessionEndReason::AppClosed
+                    } else {
+                        bus::SessionEndReason::Stopped
+                    };
+                    session_handler.publish(bus::Event::SessionEnded { project_id, reason });
+                    tracing::debug!(%project_id, ?reason, "stopped watcher");
+                    return result;
                 }
             }
         }
-        Ok::<_, anyhow::Error>(())
     });
 
     Ok(handle)
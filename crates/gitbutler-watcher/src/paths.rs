@@ -0,0 +1,124 @@
+//! Bring the paths reported by the filesystem watcher into the form Git uses for them.
+//!
+//! Depending on the platform, events may be reported for the path with symlinks resolved, like `/private/var`
+//! for `/var` on macOS, in a different case than the one the repository was opened with on case-insensitive
+//! filesystems, or decomposed into NFD where Git uses precomposed NFC, as configured with `core.precomposeUnicode`.
+//! On Windows, they may also be verbatim, like `\\?\C:\repo`, or have a drive letter in another case.
+//! Without normalization, such paths wouldn't be recognized as being inside the worktree, wouldn't be found in
+//! the index, or would be reported twice for the same file.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone)]
+pub(crate) struct PathNormalizer {
+    /// Pairs of directories as they may be reported, and as they should be used, longest first.
+    roots: Vec<(PathBuf, PathBuf)>,
+    /// Whether the filesystem ignores case, as configured with `core.ignoreCase`.
+    ignore_case: bool,
+    /// Whether paths should be turned into NFC, as configured with `core.precomposeUnicode`.
+    precompose_unicode: bool,
+}
+
+impl PathNormalizer {
+    /// Create an instance for the paths of `repo`, whose worktree is at `worktree_path`.
+    pub(crate) fn new(repo: &gix::Repository, worktree_path: &Path) -> Self {
+        let config = repo.config_snapshot();
+        let ignore_case = config.boolean("core.ignoreCase").unwrap_or(false);
+        let precompose_unicode = config.boolean("core.precomposeUnicode").unwrap_or(false);
+        let mut roots = Vec::new();
+        for root in [worktree_path, repo.path()] {
+            roots.push((root.to_owned(), root.to_owned()));
+            let normalized = gitbutler_fs::paths::normalize(root);
+            if normalized != root {
+                roots.push((normalized, root.to_owned()));
+            }
+            if let Ok(canonical) = gix::path::realpath(root) {
+                let canonical = gitbutler_fs::paths::normalize(&canonical);
+                if !roots.iter().any(|(reported, _)| *reported == canonical) {
+                    roots.push((canonical, root.to_owned()));
+                }
+            }
+        }
+        roots.sort_by_key(|(reported, _)| std::cmp::Reverse(reported.components().count()));
+        PathNormalizer {
+            roots,
+            ignore_case,
+            precompose_unicode,
+        }
+    }
+
+    /// Return the absolute `path` of an event with the worktree or git directory prefix as the repository was
+    /// opened with, and precomposed if configured.
+    pub(crate) fn normalize(&self, path: PathBuf) -> PathBuf {
+        let path = gitbutler_fs::paths::normalize(&path);
+        let path = if self.precompose_unicode {
+            gix::utils::str::precompose_path(path.into()).into_owned()
+        } else {
+            path
+        };
+        for (reported, used) in &self.roots {
+            if let Some(rest) = self.strip_prefix(&path, reported) {
+                return if rest.as_os_str().is_empty() {
+                    used.clone()
+                } else {
+                    used.join(rest)
+                };
+            }
+        }
+        path
+    }
+
+    /// Return the spelling of the paths in `index`, or `None` if the filesystem is case-sensitive so paths are
+    /// always spelled like in the index.
+    pub(crate) fn index_casing(&self, index: &gix::index::State) -> Option<IndexCasing> {
+        self.ignore_case.then(|| {
+            IndexCasing(
+                index
+                    .entries()
+                    .iter()
+                    .map(|entry| {
+                        let path = gix::path::from_bstr(
+                            gix::path::from_unix_separators_on_windows(entry.path(index)),
+                        )
+                        .into_owned();
+                        (path.to_string_lossy().to_lowercase(), path)
+                    })
+                    .collect(),
+            )
+        })
+    }
+
+    fn strip_prefix<'a>(&self, path: &'a Path, prefix: &Path) -> Option<&'a Path> {
+        if let Ok(rest) = path.strip_prefix(prefix) {
+            return Some(rest);
+        }
+        if !self.ignore_case {
+            return None;
+        }
+        let mut components = path.components();
+        for expected in prefix.components() {
+            let actual = components.next()?;
+            if actual.as_os_str().to_string_lossy().to_lowercase()
+                != expected.as_os_str().to_string_lossy().to_lowercase()
+            {
+                return None;
+            }
+        }
+        Some(components.as_path())
+    }
+}
+
+/// The worktree-relative paths of the index by their lowercase spelling.
+pub(crate) struct IndexCasing(HashMap<String, PathBuf>);
+
+impl IndexCasing {
+    /// Return `relative_path` spelled as in the index if it's tracked under a different case.
+    pub(crate) fn apply(&self, relative_path: &Path) -> PathBuf {
+        self.0
+            .get(&relative_path.to_string_lossy().to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| relative_path.to_owned())
+    }
+}
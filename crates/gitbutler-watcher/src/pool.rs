@@ -0,0 +1,264 @@
+//! A worker pool shared by all watchers, so the amount of threads handling events doesn't grow with the
+//! amount of watched projects.
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use gitbutler_project::ProjectId;
+
+/// Idle workers exit after this time, so nothing lingers once the app calms down.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Handles jobs on at most `limit` threads, with a queue per project.
+///
+/// The jobs of a project run one after another in the order they were submitted, and projects take turns,
+/// so a project that is very busy, maybe due to a running build, can't delay the others.
+#[derive(Default)]
+pub(crate) struct WorkerPool {
+    state: Mutex<State>,
+    work_available: Condvar,
+    /// Notified whenever a job finished.
+    job_done: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// The jobs that wait to be run, per project.
+    queues: HashMap<ProjectId, VecDeque<Job>>,
+    /// The projects with queued jobs that currently don't run one, in the order they are served.
+    ready: VecDeque<ProjectId>,
+    /// The projects a job currently runs for.
+    busy: HashSet<ProjectId>,
+    /// The amount of worker threads.
+    workers: usize,
+    /// The amount of worker threads waiting for jobs.
+    idle: usize,
+    /// The maximum amount of worker threads, as passed with the latest job.
+    limit: usize,
+}
+
+impl WorkerPool {
+    /// Queue `job` to run after all previously submitted jobs of `project_id`, on at most `limit` threads.
+    /// A `limit` of `0` is treated as `1`.
+    pub fn submit(
+        &'static self,
+        project_id: ProjectId,
+        limit: usize,
+        job: impl FnOnce() + Send + 'static,
+    ) {
+        let mut state = self.lock();
+        state.limit = limit.max(1);
+        state
+            .queues
+            .entry(project_id)
+            .or_default()
+            .push_back(Box::new(job));
+        if !state.busy.contains(&project_id) && !state.ready.contains(&project_id) {
+            state.ready.push_back(project_id);
+        }
+        if state.idle == 0 && state.workers < state.limit {
+            state.workers += 1;
+            let spawned = std::thread::Builder::new()
+                .name("gitbutler-watcher-worker".into())
+                .spawn(move || self.work());
+            if let Err(err) = spawned {
+                state.workers -= 1;
+                tracing::error!(?err, "failed to spawn watcher worker");
+            }
+        }
+        drop(state);
+        self.work_available.notify_one();
+    }
+
+    /// Return the amount of jobs of `project_id` that wait to be run.
+    pub fn queued(&self, project_id: ProjectId) -> usize {
+        self.lock().queues.get(&project_id).map_or(0, VecDeque::len)
+    }
+
+    /// Wait until no jobs are queued or running anymore, for at most `timeout`, and return `true` if so.
+    pub fn wait_until_idle(&self, timeout: Duration) -> bool {
+        let (_state, result) = self
+            .job_done
+            .wait_timeout_while(self.lock(), timeout, |state| {
+                !state.queues.is_empty() || !state.busy.is_empty()
+            })
+            .unwrap_or_else(|err| err.into_inner());
+        !result.timed_out()
+    }
+
+    fn work(&self) {
+        let mut state = self.lock();
+        loop {
+            if state.workers > state.limit {
+                break;
+            }
+            let Some(project_id) = state.ready.pop_front() else {
+                state.idle += 1;
+                let (new_state, timeout) = self
+                    .work_available
+                    .wait_timeout(state, IDLE_TIMEOUT)
+                    .unwrap_or_else(|err| err.into_inner());
+                state = new_state;
+                state.idle -= 1;
+                if timeout.timed_out() && state.ready.is_empty() {
+                    break;
+                }
+                continue;
+            };
+            let Some(job) = state
+                .queues
+                .get_mut(&project_id)
+                .and_then(VecDeque::pop_front)
+            else {
+                continue;
+            };
+            state.busy.insert(project_id);
+            drop(state);
+
+            if std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).is_err() {
+                tracing::error!(%project_id, "watcher job panicked");
+            }
+
+            state = self.lock();
+            state.busy.remove(&project_id);
+            if state
+                .queues
+                .get(&project_id)
+                .is_some_and(|queue| !queue.is_empty())
+            {
+                // Let other projects go first.
+                state.ready.push_back(project_id);
+            } else {
+                state.queues.remove(&project_id);
+            }
+            self.job_done.notify_all();
+        }
+        state.workers -= 1;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    use super::*;
+
+    /// How long tests wait for jobs that should finish right away.
+    const GENEROUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn pool() -> &'static WorkerPool {
+        Box::leak(Box::default())
+    }
+
+    /// Return a job that counts how many of its kind run at the same time into `running`, and the maximum into
+    /// `max_running`.
+    fn counting_job(
+        running: &Arc<AtomicUsize>,
+        max_running: &Arc<AtomicUsize>,
+    ) -> impl FnOnce() + Send + 'static {
+        let (running, max_running) = (running.clone(), max_running.clone());
+        move || {
+            let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now_running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn waiting_until_idle_waits_for_all_jobs() {
+        let pool = pool();
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let done = done.clone();
+            pool.submit(ProjectId::generate(), 2, move || {
+                std::thread::sleep(Duration::from_millis(10));
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        assert!(pool.wait_until_idle(GENEROUS_TIMEOUT));
+        assert_eq!(done.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn waiting_until_idle_times_out_while_jobs_run() {
+        let pool = pool();
+        let (release, released) = mpsc::channel::<()>();
+        pool.submit(ProjectId::generate(), 1, move || {
+            released.recv().ok();
+        });
+        assert!(!pool.wait_until_idle(Duration::from_millis(50)));
+
+        release.send(()).unwrap();
+        assert!(pool.wait_until_idle(GENEROUS_TIMEOUT));
+    }
+
+    #[test]
+    fn a_panicking_job_leaves_the_other_jobs_running() {
+        let pool = pool();
+        let project_id = ProjectId::generate();
+        let (ran_tx, ran) = mpsc::channel();
+        pool.submit(project_id, 1, || panic!("the job failed"));
+        for n in 0..3 {
+            let ran_tx = ran_tx.clone();
+            pool.submit(project_id, 1, move || ran_tx.send(n).unwrap());
+        }
+        assert!(pool.wait_until_idle(GENEROUS_TIMEOUT));
+        assert_eq!(
+            ran.try_iter().collect::<Vec<_>>(),
+            [0, 1, 2],
+            "the jobs of the project after the panicking one still run, in order"
+        );
+        assert_eq!(pool.lock().workers, 1, "the worker survived the panic");
+    }
+
+    #[test]
+    fn jobs_run_on_at_most_limit_threads() {
+        let pool = pool();
+        let (running, max_running) = Default::default();
+        for _ in 0..12 {
+            pool.submit(
+                ProjectId::generate(),
+                3,
+                counting_job(&running, &max_running),
+            );
+        }
+        assert!(pool.wait_until_idle(GENEROUS_TIMEOUT));
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+        assert!(pool.lock().workers <= 3);
+    }
+
+    #[test]
+    fn jobs_of_a_project_are_queued_and_run_one_at_a_time() {
+        let pool = pool();
+        let project_id = ProjectId::generate();
+        let (started_tx, started) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        pool.submit(project_id, 4, move || {
+            started_tx.send(()).unwrap();
+            released.recv().ok();
+        });
+        started.recv_timeout(GENEROUS_TIMEOUT).unwrap();
+
+        let (running, max_running) = Default::default();
+        for _ in 0..3 {
+            pool.submit(project_id, 4, counting_job(&running, &max_running));
+        }
+        assert_eq!(
+            pool.queued(project_id),
+            3,
+            "the jobs wait for the running one"
+        );
+        release.send(()).unwrap();
+        assert!(pool.wait_until_idle(GENEROUS_TIMEOUT));
+        assert_eq!(pool.queued(project_id), 0);
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+    }
+}
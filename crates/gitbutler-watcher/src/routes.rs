@@ -0,0 +1,110 @@
+//! Share one watcher between the projects of a repository, like the sub-projects of a monorepo, and tell which
+//! of them a change is for by the directories they cover.
+use std::path::{Path, PathBuf};
+
+use gitbutler_project::ProjectId;
+
+use crate::events::InternalEvent;
+
+/// A change to the projects sharing a watcher, as sent through [`WatcherHandle`](crate::WatcherHandle).
+#[derive(Debug)]
+pub(crate) enum RouteChange {
+    /// Have the project cover the worktree-relative directories, adding it if it's new.
+    Set(ProjectId, Vec<PathBuf>),
+    Remove(ProjectId),
+}
+
+/// The projects sharing a watcher, with the worktree-relative directories each of them covers, where none
+/// means all of the worktree.
+#[derive(Debug)]
+pub(crate) struct Routes(Vec<(ProjectId, Vec<PathBuf>)>);
+
+impl Routes {
+    pub(crate) fn new(project_id: ProjectId, include_paths: Vec<PathBuf>) -> Self {
+        Routes(vec![(project_id, include_paths)])
+    }
+
+    /// Apply `change`, and return the id of the project if it was added.
+    pub(crate) fn apply(&mut self, change: RouteChange) -> Option<ProjectId> {
+        match change {
+            RouteChange::Set(project_id, include_paths) => {
+                match self.0.iter_mut().find(|(id, _)| *id == project_id) {
+                    Some((_, paths)) => {
+                        *paths = include_paths;
+                        None
+                    }
+                    None => {
+                        self.0.push((project_id, include_paths));
+                        Some(project_id)
+                    }
+                }
+            }
+            RouteChange::Remove(project_id) => {
+                self.0.retain(|(id, _)| *id != project_id);
+                None
+            }
+        }
+    }
+
+    /// Return the directories to watch so all projects are covered, with none meaning all of the worktree.
+    pub(crate) fn include_paths(&self) -> Vec<PathBuf> {
+        if self.0.iter().any(|(_, paths)| paths.is_empty()) {
+            return Vec::new();
+        }
+        let mut include_paths: Vec<_> = self
+            .0
+            .iter()
+            .flat_map(|(_, paths)| paths.iter().cloned())
+            .collect();
+        include_paths.sort();
+        include_paths.dedup();
+        include_paths
+    }
+
+    /// Turn `event` of the file monitor into one event for each project it concerns. Changes to the git
+    /// repository concern all of them, and changes to files only those covering them.
+    ///
+    /// Events that aren't about files are returned as they are.
+    pub(crate) fn route(&self, event: InternalEvent) -> Vec<InternalEvent> {
+        match event {
+            InternalEvent::GitFilesChange(_, paths) => self
+                .0
+                .iter()
+                .map(|(project_id, _)| InternalEvent::GitFilesChange(*project_id, paths.clone()))
+                .collect(),
+            InternalEvent::ProjectFilesChange(_, paths) => self
+                .0
+                .iter()
+                .filter_map(|(project_id, include_paths)| {
+                    let paths: Vec<_> = paths
+                        .iter()
+                        .filter(|path| covers(include_paths, path))
+                        .cloned()
+                        .collect();
+                    (!paths.is_empty())
+                        .then(|| InternalEvent::ProjectFilesChange(*project_id, paths))
+                })
+                .collect(),
+            InternalEvent::ProjectFilesRenamed(_, renames) => self
+                .0
+                .iter()
+                .filter_map(|(project_id, include_paths)| {
+                    let renames: Vec<_> = renames
+                        .iter()
+                        .filter(|(from, to)| {
+                            covers(include_paths, from) && covers(include_paths, to)
+                        })
+                        .cloned()
+                        .collect();
+                    (!renames.is_empty())
+                        .then(|| InternalEvent::ProjectFilesRenamed(*project_id, renames))
+                })
+                .collect(),
+            event => vec![event],
+        }
+    }
+}
+
+fn covers(include_paths: &[PathBuf], path: &Path) -> bool {
+    include_paths.is_empty() || include_paths.iter().any(|dir| path.starts_with(dir))
+}